@@ -0,0 +1,34 @@
+//! Writes the JSON Schema of every DTO, for external tools that read or write game data
+
+use std::path::Path;
+use std::process::ExitCode;
+use std::{env, fs};
+
+// export_schema [output_dir] writes `<name>.schema.json` files into the directory, or prints all
+// schemas as one JSON object without one
+fn main() -> ExitCode {
+    let schemas = dto::schemas();
+    let Some(dir) = env::args().nth(1) else {
+        let all: serde_json::Map<_, _> = schemas
+            .into_iter()
+            .map(|(name, schema)| (name.to_string(), schema))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&all).unwrap());
+        return ExitCode::SUCCESS;
+    };
+
+    if let Err(e) = fs::create_dir_all(&dir) {
+        eprintln!("Failed to create {}: {}", dir, e);
+        return ExitCode::FAILURE;
+    }
+    for (name, schema) in schemas {
+        let path = Path::new(&dir).join(format!("{}.schema.json", name));
+        let content = serde_json::to_string_pretty(&schema).unwrap();
+        if let Err(e) = fs::write(&path, content) {
+            eprintln!("Failed to write {}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+        println!("Wrote {}", path.display());
+    }
+    ExitCode::SUCCESS
+}
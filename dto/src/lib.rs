@@ -0,0 +1,507 @@
+//! Data transfer objects, the shape of the domain in Lua, save files and external tools
+
+pub mod schema;
+
+use schema::{dto_enum, dto_struct, JsonSchema};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+dto_struct! {
+    /// A tile position on one of the maps
+    pub struct LocationDto {
+        pub x: i32,
+        pub y: i32,
+        /// Level the tile is on, 0 is the ground level
+        pub z: i32,
+        /// Name of the map the tile is on, "main" unless there are several
+        pub map: String,
+    }
+}
+
+dto_struct! {
+    /// A person living on the map
+    pub struct PersonDto {
+        pub id: u32,
+        pub name: String,
+        pub location: LocationDto,
+    }
+}
+
+dto_struct! {
+    /// A goal of a mod and how far the player got
+    pub struct GoalDto {
+        pub id: String,
+        pub name: String,
+        pub progress: f64,
+        /// Progress the goal is complete at
+        pub target: f64,
+        pub completed: bool,
+    }
+}
+
+dto_struct! {
+    /// A business competing for the map, or the neutral side
+    pub struct FactionDto {
+        pub id: String,
+        /// "player", "ai" or "neutral"
+        pub kind: String,
+        pub name: String,
+        /// Color of what it owns as "#rrggbb"
+        pub color: String,
+    }
+}
+
+dto_struct! {
+    /// A job queued for a person
+    pub struct TaskDto {
+        pub id: u32,
+        pub person_id: u32,
+        /// "go_to", "work_at", "haul" or "patrol"
+        pub kind: String,
+        pub priority: i32,
+        /// Whether a task of higher priority may take over
+        pub interruptible: bool,
+        pub started: bool,
+        /// Where the task ends: the tile to go to, to work at or to carry the item to. The
+        /// waypoint a patrol walks to next.
+        pub location: LocationDto,
+        /// Item a haul task carries
+        pub item: Option<String>,
+        /// Where a haul task picks up its item
+        pub from: Option<LocationDto>,
+        /// Steps a work task takes
+        pub steps: Option<u32>,
+        /// Waypoints a patrol task walks in a loop
+        pub route: Option<Vec<LocationDto>>,
+        /// Steps worked so far
+        pub worked: u32,
+        /// Whether a haul task picked up its item
+        pub carrying: bool,
+    }
+}
+
+dto_struct! {
+    /// Carries persons and items along the roads
+    pub struct VehicleDto {
+        pub id: u32,
+        pub location: LocationDto,
+        /// Passengers and items it carries at most, both together
+        pub capacity: u32,
+        /// Ids of the persons aboard
+        pub passengers: Vec<u32>,
+        pub cargo: Vec<String>,
+        /// End of the route it drives, if it drives one
+        pub destination: Option<LocationDto>,
+    }
+}
+
+dto_struct! {
+    /// Links two distant tiles both ways, persons walking somewhere take it when that's shorter
+    pub struct PortalDto {
+        pub id: u32,
+        pub from: LocationDto,
+        pub to: LocationDto,
+    }
+}
+
+dto_struct! {
+    /// Persons moving and acting together
+    pub struct GroupDto {
+        pub id: u32,
+        pub name: String,
+        /// "household" or "crew"
+        pub kind: String,
+        /// Ids of the members in the order they joined
+        pub members: Vec<u32>,
+        /// Items the members share, missing for groups without a shared inventory
+        pub inventory: Option<Vec<String>>,
+    }
+}
+
+dto_struct! {
+    /// A region of a map only its owner may edit the tiles of
+    pub struct MapLockDto {
+        pub id: u32,
+        /// "script", "editor" or the name given when locking
+        pub owner: String,
+        pub min: LocationDto,
+        pub max: LocationDto,
+    }
+}
+
+dto_struct! {
+    /// Square of tiles on one level of a map, unloaded to disk with its persons and loaded back
+    pub struct ChunkDto {
+        pub map: String,
+        pub z: i32,
+        /// Column of the chunk, it covers the tiles from x times the chunk size on
+        pub x: i32,
+        /// Row of the chunk, it covers the tiles from y times the chunk size on
+        pub y: i32,
+    }
+}
+
+dto_struct! {
+    /// One person's move within a batch of moves
+    pub struct PersonMoveDto {
+        pub person_id: u32,
+        pub from_location: LocationDto,
+        pub to_location: LocationDto,
+    }
+}
+
+/// Value of an entity's metadata entry, written as plain JSON boolean, number or string
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MetaValueDto {
+    Boolean(bool),
+    Number(f64),
+    Text(String),
+}
+
+impl JsonSchema for MetaValueDto {
+    fn schema() -> Value {
+        json!({
+            "description": "Value of an entity's metadata entry",
+            "anyOf": [bool::schema(), f64::schema(), String::schema()],
+        })
+    }
+}
+
+dto_enum! {
+    /// Something that happened in the world, the event store keeps them all in order
+    pub enum EventDto {
+        /// A person was created
+        PersonCreated {
+            person_id: u32,
+            name: String,
+            location: LocationDto,
+        },
+        /// A person moved to another tile
+        PersonMoved {
+            person_id: u32,
+            from_location: LocationDto,
+            to_location: LocationDto,
+        },
+        /// Many persons moved at once, in the order of the moves
+        PersonsMoved {
+            moves: Vec<PersonMoveDto>,
+        },
+        /// An entity like "person:3" was tagged
+        TagAdded {
+            entity: String,
+            tag: String,
+        },
+        /// A tag was removed from an entity
+        TagRemoved {
+            entity: String,
+            tag: String,
+        },
+        /// A metadata entry of an entity was set
+        MetadataSet {
+            entity: String,
+            key: String,
+            value: MetaValueDto,
+        },
+        /// A metadata entry of an entity was removed
+        MetadataRemoved {
+            entity: String,
+            key: String,
+        },
+        /// The scenario ended as "won", "lost" or "ended"
+        ScenarioEnded {
+            outcome: String,
+            reason: String,
+            tick: u64,
+        },
+        /// The progress of a goal changed
+        GoalProgressed {
+            goal: String,
+            progress: f64,
+            target: f64,
+        },
+        /// A goal reached its target
+        GoalCompleted {
+            goal: String,
+        },
+        /// Tiles came into sight of a viewer like "player"
+        TilesRevealed {
+            viewer: String,
+            tiles: Vec<LocationDto>,
+        },
+        /// Tiles went out of sight of a viewer, they stay explored
+        TilesHidden {
+            viewer: String,
+            tiles: Vec<LocationDto>,
+        },
+        /// A faction was founded
+        FactionCreated {
+            faction: String,
+            kind: String,
+        },
+        /// A zone like "zone:1" was marked out between two corners
+        ZoneCreated {
+            zone: String,
+            min: LocationDto,
+            max: LocationDto,
+        },
+        /// An entity changed hands, a missing faction is nobody
+        OwnershipTransferred {
+            entity: String,
+            from: Option<String>,
+            to: Option<String>,
+        },
+        /// A person started on a task of kind "go_to", "work_at" or "haul"
+        TaskStarted {
+            task: u32,
+            person_id: u32,
+            kind: String,
+        },
+        /// A person finished a task
+        TaskCompleted {
+            task: u32,
+            person_id: u32,
+        },
+        /// A task was dropped before it was done
+        TaskAbandoned {
+            task: u32,
+            person_id: u32,
+            reason: String,
+        },
+        /// A vehicle was put on the map
+        VehicleCreated {
+            vehicle: u32,
+            location: LocationDto,
+        },
+        /// A vehicle drove onto the next tile of its route, its passengers move along
+        VehicleMoved {
+            vehicle: u32,
+            from_location: LocationDto,
+            to_location: LocationDto,
+        },
+        /// A vehicle reached the end of its route
+        VehicleArrived {
+            vehicle: u32,
+            location: LocationDto,
+        },
+        /// The road to the destination of a vehicle is gone, it stopped where it was
+        VehicleStranded {
+            vehicle: u32,
+            location: LocationDto,
+        },
+        /// A person boarded a vehicle or an item was loaded into it
+        VehicleLoaded {
+            vehicle: u32,
+            person_id: Option<u32>,
+            item: Option<String>,
+        },
+        /// A passenger got off a vehicle or an item was unloaded from it
+        VehicleUnloaded {
+            vehicle: u32,
+            person_id: Option<u32>,
+            item: Option<String>,
+        },
+        /// Two tiles were linked by a portal
+        PortalCreated {
+            portal: u32,
+            from_location: LocationDto,
+            to_location: LocationDto,
+        },
+        /// A portal was taken down
+        PortalRemoved {
+            portal: u32,
+        },
+        /// A person stepped through a portal, the move itself comes as PersonsMoved
+        PortalTraversed {
+            portal: u32,
+            person_id: u32,
+            from_location: LocationDto,
+            to_location: LocationDto,
+        },
+        /// A map was added to the world
+        MapCreated {
+            map: String,
+        },
+        /// Another map became the active one
+        MapSwitched {
+            from: String,
+            to: String,
+        },
+        /// Another level of the active map became the active one
+        LevelSwitched {
+            map: String,
+            from: i32,
+            to: i32,
+        },
+        /// A household or crew was founded
+        GroupCreated {
+            group: u32,
+            kind: String,
+            name: String,
+            shared_inventory: bool,
+        },
+        /// A person joined a group
+        GroupJoined {
+            group: u32,
+            person_id: u32,
+        },
+        /// A person left a group
+        GroupLeft {
+            group: u32,
+            person_id: u32,
+        },
+        /// A group was dissolved, its members are on their own again
+        GroupDisbanded {
+            group: u32,
+        },
+        /// An item was put into the shared inventory of a group
+        GroupItemStored {
+            group: u32,
+            item: String,
+        },
+        /// An item was taken out of the shared inventory of a group
+        GroupItemTaken {
+            group: u32,
+            item: String,
+        },
+        /// A tile was put on a layer of a map, replacing the one there
+        TilePlaced {
+            location: LocationDto,
+            layer: String,
+            tile: u32,
+        },
+        /// A cell of a layer of a map became empty
+        TileRemoved {
+            location: LocationDto,
+            layer: String,
+        },
+        /// Only the owner may edit the tiles between the corners from now on
+        RegionLocked {
+            lock: u32,
+            owner: String,
+            min: LocationDto,
+            max: LocationDto,
+        },
+        /// Anyone may edit the tiles of the region of a lock again
+        RegionUnlocked {
+            lock: u32,
+        },
+        /// A chunk was written to disk with the persons in it, they are gone until it's loaded
+        ChunkUnloaded {
+            chunk: ChunkDto,
+            persons: Vec<u32>,
+        },
+        /// A chunk was read back from disk with the persons it was unloaded with
+        ChunkLoaded {
+            chunk: ChunkDto,
+            persons: Vec<PersonDto>,
+        },
+    }
+}
+
+dto_struct! {
+    /// Summary of a finished scenario, shown by the frontends and written by the headless runner
+    pub struct ScenarioResultDto {
+        /// "won", "lost" or "ended"
+        pub outcome: String,
+        pub reason: String,
+        /// Tick the scenario ended at
+        pub ticks: u64,
+        /// Simulated seconds until the scenario ended
+        pub duration: f64,
+        /// Domain events published until the end
+        pub events: u64,
+        pub persons: u64,
+        pub occupied_locations: u64,
+        /// Latest value of every metric
+        pub metrics: BTreeMap<String, f64>,
+    }
+}
+
+/// JSON Schema documents of all DTOs by name
+pub fn schemas() -> Vec<(&'static str, Value)> {
+    let document = |name: &str, schema: Value| {
+        let mut document = json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": name,
+        });
+        if let (Some(document), Value::Object(schema)) = (document.as_object_mut(), schema) {
+            document.extend(schema);
+        }
+        document
+    };
+    vec![
+        ("location", document("LocationDto", LocationDto::schema())),
+        ("person", document("PersonDto", PersonDto::schema())),
+        ("goal", document("GoalDto", GoalDto::schema())),
+        ("faction", document("FactionDto", FactionDto::schema())),
+        ("task", document("TaskDto", TaskDto::schema())),
+        ("vehicle", document("VehicleDto", VehicleDto::schema())),
+        ("portal", document("PortalDto", PortalDto::schema())),
+        ("group", document("GroupDto", GroupDto::schema())),
+        ("map_lock", document("MapLockDto", MapLockDto::schema())),
+        ("chunk", document("ChunkDto", ChunkDto::schema())),
+        ("event", document("EventDto", EventDto::schema())),
+        (
+            "scenario_result",
+            document("ScenarioResultDto", ScenarioResultDto::schema()),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_are_tagged_by_type() {
+        let event = EventDto::PersonMoved {
+            person_id: 1,
+            from_location: LocationDto {
+                x: 0,
+                y: 0,
+                z: 0,
+                map: "main".to_string(),
+            },
+            to_location: LocationDto {
+                x: 2,
+                y: 3,
+                z: 0,
+                map: "main".to_string(),
+            },
+        };
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["type"], "PersonMoved");
+        assert_eq!(value["to_location"]["y"], 3);
+        assert_eq!(serde_json::from_value::<EventDto>(value).unwrap(), event);
+
+        let event = EventDto::MetadataSet {
+            entity: "person:1".to_string(),
+            key: "gold".to_string(),
+            value: MetaValueDto::Number(12.0),
+        };
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["value"], 12.0);
+        assert_eq!(serde_json::from_value::<EventDto>(value).unwrap(), event);
+    }
+
+    #[test]
+    fn test_schemas_follow_the_fields() {
+        let schema = PersonDto::schema();
+        assert_eq!(schema["description"], "A person living on the map");
+        assert_eq!(schema["required"], json!(["id", "name", "location"]));
+        assert_eq!(
+            schema["properties"]["location"]["properties"]["x"]["type"],
+            "integer"
+        );
+
+        let schema = EventDto::schema();
+        let created = &schema["oneOf"][0];
+        assert_eq!(created["properties"]["type"]["const"], "PersonCreated");
+        assert_eq!(
+            created["required"],
+            json!(["type", "person_id", "name", "location"])
+        );
+    }
+}
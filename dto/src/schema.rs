@@ -0,0 +1,149 @@
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// JSON Schema of a type, so external tools can check the data they exchange with the game
+pub trait JsonSchema {
+    fn schema() -> Value;
+}
+
+impl JsonSchema for u32 {
+    fn schema() -> Value {
+        json!({ "type": "integer", "minimum": 0 })
+    }
+}
+
+impl JsonSchema for u64 {
+    fn schema() -> Value {
+        json!({ "type": "integer", "minimum": 0 })
+    }
+}
+
+impl JsonSchema for i32 {
+    fn schema() -> Value {
+        json!({ "type": "integer" })
+    }
+}
+
+impl JsonSchema for f64 {
+    fn schema() -> Value {
+        json!({ "type": "number" })
+    }
+}
+
+impl JsonSchema for bool {
+    fn schema() -> Value {
+        json!({ "type": "boolean" })
+    }
+}
+
+impl JsonSchema for String {
+    fn schema() -> Value {
+        json!({ "type": "string" })
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Vec<T> {
+    fn schema() -> Value {
+        json!({ "type": "array", "items": T::schema() })
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Option<T> {
+    fn schema() -> Value {
+        json!({ "anyOf": [T::schema(), { "type": "null" }] })
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for BTreeMap<String, T> {
+    fn schema() -> Value {
+        json!({ "type": "object", "additionalProperties": T::schema() })
+    }
+}
+
+// Doc comment lines of a type as one description
+pub(crate) fn description(lines: &[&str]) -> String {
+    lines
+        .iter()
+        .map(|line| line.trim())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A struct with its schema, both from the same field list so they can't drift apart
+macro_rules! dto_struct {
+    (
+        $(#[doc = $doc:literal])*
+        pub struct $name:ident {
+            $($(#[doc = $field_doc:literal])* pub $field:ident: $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[doc = $doc])*
+        #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+        pub struct $name {
+            $($(#[doc = $field_doc])* pub $field: $ty),*
+        }
+
+        impl $crate::schema::JsonSchema for $name {
+            fn schema() -> serde_json::Value {
+                let mut properties = serde_json::Map::new();
+                $(properties.insert(
+                    stringify!($field).to_string(),
+                    <$ty as $crate::schema::JsonSchema>::schema(),
+                );)*
+                serde_json::json!({
+                    "type": "object",
+                    "description": $crate::schema::description(&[$($doc),*]),
+                    "properties": properties,
+                    "required": [$(stringify!($field)),*],
+                    "additionalProperties": false,
+                })
+            }
+        }
+    };
+}
+
+/// An enum of struct variants tagged by a `type` field, with its schema
+macro_rules! dto_enum {
+    (
+        $(#[doc = $doc:literal])*
+        pub enum $name:ident {
+            $($(#[doc = $variant_doc:literal])* $variant:ident { $($field:ident: $ty:ty),* $(,)? }),* $(,)?
+        }
+    ) => {
+        $(#[doc = $doc])*
+        #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+        #[serde(tag = "type")]
+        pub enum $name {
+            $($(#[doc = $variant_doc])* $variant { $($field: $ty),* }),*
+        }
+
+        impl $crate::schema::JsonSchema for $name {
+            fn schema() -> serde_json::Value {
+                let variants = vec![$({
+                    let mut properties = serde_json::Map::new();
+                    properties.insert(
+                        "type".to_string(),
+                        serde_json::json!({ "const": stringify!($variant) }),
+                    );
+                    $(properties.insert(
+                        stringify!($field).to_string(),
+                        <$ty as $crate::schema::JsonSchema>::schema(),
+                    );)*
+                    serde_json::json!({
+                        "type": "object",
+                        "description": $crate::schema::description(&[$($variant_doc),*]),
+                        "properties": properties,
+                        "required": ["type", $(stringify!($field)),*],
+                        "additionalProperties": false,
+                    })
+                }),*];
+                serde_json::json!({
+                    "description": $crate::schema::description(&[$($doc),*]),
+                    "oneOf": variants,
+                })
+            }
+        }
+    };
+}
+
+pub(crate) use {dto_enum, dto_struct};
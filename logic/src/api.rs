@@ -1,67 +1,269 @@
+mod core_api_builder;
+mod debug_api;
 mod event_api;
+mod faction_api;
+mod fog_api;
+mod goals_api;
+mod group_api;
 mod location_api;
+mod map_api;
+mod meta_api;
+mod metrics_api;
 mod person_api;
+mod portal_api;
+mod query_api;
+mod scenario_api;
+mod stream_api;
+mod tags_api;
+mod task_api;
+mod vehicle_api;
+mod world_api;
 
+use crate::domain::service::faction_service::FactionService;
+use crate::domain::service::fog_service::FogService;
+use crate::domain::service::goal_service::GoalService;
+use crate::domain::service::group_service::GroupService;
+use crate::domain::service::map_service::MapService;
 use crate::domain::service::person_service::PersonService;
-use crate::infrastructure::event_store::{create_event_store, EventStore};
-use crate::infrastructure::projection::{LocationOccupancyProjection, ProjectionManager};
-use crate::repo::VecRepository;
+use crate::domain::service::portal_service::PortalService;
+use crate::domain::service::scenario_service::ScenarioService;
+use crate::domain::service::stream_service::StreamService;
+use crate::domain::service::tag_service::TagService;
+use crate::domain::service::task_service::TaskService;
+use crate::domain::service::vehicle_service::VehicleService;
+use crate::domain::service::world_service::WorldService;
+use crate::infrastructure::event_store::{EventSender, EventStore};
+use crate::infrastructure::projection::{
+    GroupMembershipProjection, LocationOccupancyProjection, ProjectionManager, StatsProjection,
+};
+use crate::read_model::ReadModels;
+use crate::repo::PersonRepository;
+use crate::snapshot::{Snapshots, WorldSnapshot};
 use std::sync::{Arc, Mutex};
 
+pub use crate::domain::entity::faction::{Faction, FactionKind};
+pub use crate::domain::entity::goal::Goal;
+pub use crate::domain::entity::group::{Group, GroupKind};
+pub use crate::domain::entity::map_lock::MapLock;
 pub use crate::domain::entity::person::Person;
-use crate::domain::entity::person::PersonId;
+pub use crate::domain::entity::portal::Portal;
+pub use crate::domain::entity::task::Task;
+pub use crate::domain::entity::vehicle::Vehicle;
+pub use crate::domain::event::faction_event::FactionEvent;
+pub use crate::domain::event::fog_event::FogEvent;
+pub use crate::domain::event::goal_event::GoalEvent;
+pub use crate::domain::event::group_event::GroupEvent;
+pub use crate::domain::event::map_event::MapEvent;
+pub use crate::domain::event::person_event::{PersonEvent, PersonMove};
+pub use crate::domain::event::portal_event::PortalEvent;
+pub use crate::domain::event::scenario_event::{ScenarioEvent, ScenarioOutcome};
+pub use crate::domain::event::stream_event::StreamEvent;
+pub use crate::domain::event::tag_event::TagEvent;
+pub use crate::domain::event::task_event::TaskEvent;
+pub use crate::domain::event::vehicle_event::{Load, VehicleEvent};
+pub use crate::domain::event::world_event::WorldEvent;
+pub use crate::domain::event::DomainEvent;
+pub use crate::domain::service::faction_service::{NEUTRAL_FACTION, PLAYER_FACTION};
+pub use crate::domain::service::fog_service::PLAYER_VIEWER;
+pub use crate::domain::service::map_service::{
+    TileChange, DEFAULT_LAYER, SCRIPT_EDITOR, UI_EDITOR,
+};
+pub use crate::domain::value_object::chunk::{Chunk, CHUNK_SIZE};
+pub use crate::domain::value_object::entity_ref::EntityRef;
+pub use crate::domain::value_object::location::Location;
+pub use crate::domain::value_object::map_id::{MapId, MAIN_MAP};
+pub use crate::domain::value_object::meta_value::MetaValue;
+pub use crate::domain::value_object::region::Region;
+pub use crate::domain::value_object::visibility::Visibility;
+pub use crate::infrastructure::codec::{convert, Codec, EventCodec};
+pub use crate::infrastructure::event_log::{previous_archive, recover, FsyncPolicy, Recovery};
+pub use crate::infrastructure::event_store::{EventMemory, RetentionPolicy};
+pub use crate::infrastructure::projection::{Projection, Projections, RebuildProgress};
+pub use crate::infrastructure::time_series::{Metrics, TimeSeries};
+pub use core_api_builder::CoreApiBuilder;
+pub use debug_api::Discrepancy;
+pub use faction_api::Factions;
+pub use fog_api::Fog;
+pub use map_api::MapEdits;
+pub use portal_api::Portals;
+pub use stream_api::Chunks;
+pub use vehicle_api::Vehicles;
+pub use world_api::World;
 
 /// Main API facade for the logic module
 pub struct CoreApi {
     person: PersonApi,
     location: LocationApi,
     event: EventApi,
+    tags: TagsApi,
+    meta: MetaApi,
+    query: QueryApi,
+    metrics: MetricsApi,
+    scenario: ScenarioApi,
+    goals: GoalsApi,
+    fog: FogApi,
+    faction: FactionApi,
+    task: TaskApi,
+    vehicle: VehicleApi,
+    portal: PortalApi,
+    group: GroupApi,
+    map: MapApi,
+    stream: StreamApi,
+    world: WorldApi,
+    debug: DebugApi,
+    persons: Arc<Mutex<PersonService<PersonRepository>>>,
+    world_service: Arc<Mutex<WorldService>>,
+    snapshots: Snapshots,
+    projections: ProjectionManager,
+    read_models: ReadModels,
+    // Counts the published events, for waiting on the projections
+    event_sender: EventSender,
 }
 /// API for person-related operations
 pub struct PersonApi {
-    service: Arc<Mutex<PersonService<VecRepository<PersonId, Person>>>>,
+    service: Arc<Mutex<PersonService<PersonRepository>>>,
+    world: Arc<Mutex<WorldService>>,
 }
 
 /// API for location-related queries
 pub struct LocationApi {
     projection: Arc<Mutex<LocationOccupancyProjection>>,
+    world: Arc<Mutex<WorldService>>,
 }
 
 /// API for event-related operations
 pub struct EventApi {
     store: Arc<Mutex<EventStore>>,
 }
-impl CoreApi {
-    /// Create a new instance of the logic API
-    pub fn new() -> Self {
-        // Create the event store
-        let (event_store, event_sender) = create_event_store();
 
-        // Create the person repository
-        let repo = VecRepository::<PersonId, Person>::new();
+/// API for tagging entities
+pub struct TagsApi {
+    service: Arc<Mutex<TagService>>,
+    persons: Arc<Mutex<PersonService<PersonRepository>>>,
+    factions: Arc<Mutex<FactionService>>,
+}
 
-        // Create the person service
-        let person_service = Arc::new(Mutex::new(PersonService::new(repo, event_sender)));
+/// API for selecting persons by location and tags
+pub struct QueryApi {
+    persons: Arc<Mutex<PersonService<PersonRepository>>>,
+    tags: Arc<Mutex<TagService>>,
+    locations: Arc<Mutex<LocationOccupancyProjection>>,
+    world: Arc<Mutex<WorldService>>,
+}
 
-        // Create the projection manager
-        let projection_manager = ProjectionManager::new(event_store.clone());
+/// API for metrics sampled over time
+pub struct MetricsApi {
+    metrics: Metrics,
+    stats: Arc<Mutex<StatsProjection>>,
+    locations: Arc<Mutex<LocationOccupancyProjection>>,
+}
 
-        // Register the location occupancy projection
-        let location_projection =
-            projection_manager.register_projection(LocationOccupancyProjection::new());
+/// API for ending the running scenario
+pub struct ScenarioApi {
+    service: Arc<Mutex<ScenarioService>>,
+    store: Arc<Mutex<EventStore>>,
+    persons: Arc<Mutex<PersonService<PersonRepository>>>,
+    locations: Arc<Mutex<LocationOccupancyProjection>>,
+    metrics: Metrics,
+}
 
-        // Give the projections a moment to initialize
-        std::thread::sleep(std::time::Duration::from_millis(50));
+/// API for the goals of the mods
+pub struct GoalsApi {
+    service: Arc<Mutex<GoalService>>,
+}
 
-        CoreApi {
-            person: PersonApi {
-                service: person_service,
-            },
-            location: LocationApi {
-                projection: location_projection,
-            },
-            event: EventApi { store: event_store },
-        }
+/// API for what viewers explored of the map
+pub struct FogApi {
+    service: Arc<Mutex<FogService>>,
+    world: Arc<Mutex<WorldService>>,
+}
+
+/// API for the factions and what they own
+pub struct FactionApi {
+    service: Arc<Mutex<FactionService>>,
+    persons: Arc<Mutex<PersonService<PersonRepository>>>,
+    world: Arc<Mutex<WorldService>>,
+}
+
+/// API for the tasks queued for persons
+pub struct TaskApi {
+    service: Arc<Mutex<TaskService>>,
+    persons: Arc<Mutex<PersonService<PersonRepository>>>,
+    portals: Arc<Mutex<PortalService>>,
+    world: Arc<Mutex<WorldService>>,
+}
+
+/// API for the vehicles and the roads they drive on
+pub struct VehicleApi {
+    service: Arc<Mutex<VehicleService>>,
+    persons: Arc<Mutex<PersonService<PersonRepository>>>,
+    world: Arc<Mutex<WorldService>>,
+}
+
+/// API for the portals linking distant tiles
+pub struct PortalApi {
+    service: Arc<Mutex<PortalService>>,
+    world: Arc<Mutex<WorldService>>,
+}
+
+/// API for checking that runs, replays and saves agree
+pub struct DebugApi {
+    persons: Arc<Mutex<PersonService<PersonRepository>>>,
+    tags: Arc<Mutex<TagService>>,
+    factions: Arc<Mutex<FactionService>>,
+    tasks: Arc<Mutex<TaskService>>,
+    vehicles: Arc<Mutex<VehicleService>>,
+    portals: Arc<Mutex<PortalService>>,
+    groups: Arc<Mutex<GroupService>>,
+    map: Arc<Mutex<MapService>>,
+    stream: Arc<Mutex<StreamService>>,
+    locations: Arc<Mutex<LocationOccupancyProjection>>,
+}
+
+/// API for the households and crews
+pub struct GroupApi {
+    service: Arc<Mutex<GroupService>>,
+    membership: Arc<Mutex<GroupMembershipProjection>>,
+    persons: Arc<Mutex<PersonService<PersonRepository>>>,
+    tasks: Arc<Mutex<TaskService>>,
+    world: Arc<Mutex<WorldService>>,
+}
+
+/// API for the tiles of the maps
+pub struct MapApi {
+    service: Arc<Mutex<MapService>>,
+    world: Arc<Mutex<WorldService>>,
+}
+
+/// API for unloading chunks of the world to disk and loading them back
+pub struct StreamApi {
+    service: Arc<Mutex<StreamService>>,
+    persons: Arc<Mutex<PersonService<PersonRepository>>>,
+    world: Arc<Mutex<WorldService>>,
+}
+
+/// API for the maps of the world
+pub struct WorldApi {
+    service: Arc<Mutex<WorldService>>,
+}
+
+/// API for key-value metadata of entities
+pub struct MetaApi {
+    service: Arc<Mutex<TagService>>,
+    persons: Arc<Mutex<PersonService<PersonRepository>>>,
+    factions: Arc<Mutex<FactionService>>,
+}
+impl CoreApi {
+    /// Create a new instance of the logic API with the default configuration
+    pub fn new() -> Self {
+        CoreApiBuilder::new()
+            .build()
+            .expect("the default configuration has no archive to open")
+    }
+
+    /// Configure the logic API before creating it, see `CoreApiBuilder`
+    pub fn builder() -> CoreApiBuilder {
+        CoreApiBuilder::new()
     }
 
     /// Access person-related operations
@@ -78,4 +280,150 @@ impl CoreApi {
     pub fn event(&self) -> &EventApi {
         &self.event
     }
+
+    /// Access entity tags
+    pub fn tags(&self) -> &TagsApi {
+        &self.tags
+    }
+
+    /// Access entity metadata
+    pub fn meta(&self) -> &MetaApi {
+        &self.meta
+    }
+
+    /// Access queries over persons
+    pub fn query(&self) -> &QueryApi {
+        &self.query
+    }
+
+    /// Access metrics sampled over time
+    pub fn metrics(&self) -> &MetricsApi {
+        &self.metrics
+    }
+
+    /// Access the end of the running scenario
+    pub fn scenario(&self) -> &ScenarioApi {
+        &self.scenario
+    }
+
+    /// Access the goals of the mods
+    pub fn goals(&self) -> &GoalsApi {
+        &self.goals
+    }
+
+    /// Access the fog of war
+    pub fn fog(&self) -> &FogApi {
+        &self.fog
+    }
+
+    /// Access the factions and what they own
+    pub fn faction(&self) -> &FactionApi {
+        &self.faction
+    }
+
+    /// Access the tasks queued for persons
+    pub fn task(&self) -> &TaskApi {
+        &self.task
+    }
+
+    /// Access the vehicles and roads
+    pub fn vehicle(&self) -> &VehicleApi {
+        &self.vehicle
+    }
+
+    /// Access the portals
+    pub fn portal(&self) -> &PortalApi {
+        &self.portal
+    }
+
+    /// Access the households and crews
+    pub fn group(&self) -> &GroupApi {
+        &self.group
+    }
+
+    /// Access the tiles of the maps
+    pub fn map(&self) -> &MapApi {
+        &self.map
+    }
+
+    /// Access the chunks unloaded to disk
+    pub fn stream(&self) -> &StreamApi {
+        &self.stream
+    }
+
+    /// Access the maps of the world
+    pub fn world(&self) -> &WorldApi {
+        &self.world
+    }
+
+    /// Access the state hashes for desync detection
+    pub fn debug(&self) -> &DebugApi {
+        &self.debug
+    }
+
+    /// Wait until the projections applied all published events, false on timeout. The
+    /// projections update on their own threads, runs without a frontend outpace them otherwise.
+    pub fn wait_for_projections(&self, timeout: std::time::Duration) -> bool {
+        self.projections
+            .wait_until_caught_up(self.event_sender.published(), timeout)
+    }
+
+    /// Handle to the readiness of the projections, they rebuild from the stored events on their
+    /// own threads after the API is created
+    pub fn projections(&self) -> Projections {
+        self.projections.projections()
+    }
+
+    /// Handle to the queries the projections declare, for the frontends exposing them to the
+    /// scripts
+    pub fn read_models(&self) -> ReadModels {
+        self.read_models.clone()
+    }
+
+    /// Sample the stats into the metrics if the sample interval passed, called once per tick
+    /// before the scripts record their own metrics
+    pub fn sample_metrics(&self, tick: u64) {
+        self.metrics.metrics.set_tick(tick);
+        let occupied = self
+            .metrics
+            .locations
+            .lock()
+            .unwrap()
+            .get_total_occupied_location_count();
+        self.metrics
+            .stats
+            .lock()
+            .unwrap()
+            .sample(&self.metrics.metrics, tick, occupied);
+    }
+
+    /// The world as of the last `refresh_snapshot`, cheap to clone and free of locks
+    pub fn snapshot(&self) -> Arc<WorldSnapshot> {
+        self.snapshots.latest()
+    }
+
+    /// Handle to the latest snapshot for frontends reading it from another thread
+    pub fn snapshots(&self) -> Snapshots {
+        self.snapshots.clone()
+    }
+
+    /// Take a new snapshot of the world, done once per tick. The events it covers beyond the
+    /// retention are moved out of memory afterwards.
+    pub fn refresh_snapshot(&self, tick: u64) {
+        let persons = self
+            .persons
+            .lock()
+            .unwrap()
+            .get_all_persons()
+            .unwrap_or_default();
+        let (map, z) = {
+            let world = self.world_service.lock().unwrap();
+            (world.active().clone(), world.level())
+        };
+        self.snapshots
+            .publish(WorldSnapshot::new(tick, persons, &map, z));
+        if let Err(e) = self.event.trim() {
+            eprintln!("Failed to archive events: {:?}", e);
+        }
+    }
 }
@@ -0,0 +1,385 @@
+use crate::domain::service::faction_service::FactionService;
+use crate::domain::service::fog_service::FogService;
+use crate::domain::service::goal_service::GoalService;
+use crate::domain::service::group_service::GroupService;
+use crate::domain::service::map_service::MapService;
+use crate::domain::service::person_service::PersonService;
+use crate::domain::service::portal_service::PortalService;
+use crate::domain::service::scenario_service::ScenarioService;
+use crate::domain::service::stream_service::StreamService;
+use crate::domain::service::tag_service::TagService;
+use crate::domain::service::task_service::TaskService;
+use crate::domain::service::vehicle_service::VehicleService;
+use crate::domain::service::world_service::WorldService;
+use crate::infrastructure::chunk_store::ChunkStore;
+use crate::infrastructure::event_store::{create_event_store, RetentionPolicy};
+use crate::infrastructure::projection::{
+    GroupMembershipProjection, LocationOccupancyProjection, Projection, ProjectionManager,
+    StatsProjection,
+};
+use crate::infrastructure::time_series::Metrics;
+use crate::read_model::{Queryable, ReadModel, ReadModels};
+use crate::repo::{PersonRepository, RepositoryBackend};
+use crate::snapshot::Snapshots;
+use crate::{
+    CoreApi, DebugApi, EventApi, FactionApi, FogApi, GoalsApi, GroupApi, LocationApi, MapApi,
+    MetaApi, MetricsApi, PersonApi, PortalApi, QueryApi, ScenarioApi, StreamApi, TagsApi, TaskApi,
+    VehicleApi, WorldApi,
+};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Registers a projection of the caller with the manager of the API being built, and its read
+// model if it has one
+type Registration = Box<dyn FnOnce(&ProjectionManager, &ReadModels) -> Result<(), String> + Send>;
+
+/// Configuration of a `CoreApi`, for deployments tuning the event log and tests needing an API
+/// that is ready as soon as it's built:
+///
+/// ```no_run
+/// # use logic::CoreApi;
+/// # use std::time::Duration;
+/// let core = CoreApi::builder()
+///     .sample_interval(10)
+///     .ready_within(Duration::from_secs(1))
+///     .build()
+///     .unwrap();
+/// ```
+///
+/// The simulation clock isn't configured here, the logic advances by the ticks its caller
+/// passes in. Real time or turns are a setting of `lua_engine::clock::Clock`.
+#[derive(Default)]
+pub struct CoreApiBuilder {
+    repository: RepositoryBackend,
+    retention: Option<RetentionPolicy>,
+    sample_interval: Option<u64>,
+    projections: Vec<Registration>,
+    ready_within: Option<Duration>,
+    chunk_dir: Option<PathBuf>,
+}
+
+impl CoreApiBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Where the persons are kept, `RepositoryBackend::Vec` by default
+    pub fn repository(mut self, backend: RepositoryBackend) -> Self {
+        self.repository = backend;
+        self
+    }
+
+    /// Keep only the latest events in memory, archived or dropped as the policy says
+    pub fn retention(mut self, retention: RetentionPolicy) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+
+    /// Ticks between the samples of the stats into the metrics, every tick by default
+    pub fn sample_interval(mut self, ticks: u64) -> Self {
+        self.sample_interval = Some(ticks);
+        self
+    }
+
+    /// Register another projection, rebuilt and kept up to date on its own thread like the
+    /// built-in ones. Share its state through a handle it holds to read it.
+    pub fn projection<P: Projection>(mut self, projection: P) -> Self {
+        self.projections.push(Box::new(move |manager, _| {
+            manager.register_projection(projection);
+            Ok(())
+        }));
+        self
+    }
+
+    /// Register another projection along with the queries it declares, which the scripts reach
+    /// as `api.<module>.<query>` without any Lua glue
+    pub fn read_model<P: Queryable>(mut self, projection: P) -> Self {
+        self.projections.push(Box::new(move |manager, read_models| {
+            let projection = manager.register_projection(projection);
+            read_models.register(ReadModel::of(projection))
+        }));
+        self
+    }
+
+    /// Wait in `build` until every projection rebuilt and caught up with the events, so the
+    /// queries answer right away. Without it they catch up in the background.
+    pub fn ready_within(mut self, timeout: Duration) -> Self {
+        self.ready_within = Some(timeout);
+        self
+    }
+
+    /// Directory the unloaded chunks are written to, one of its own in the temp dir by default
+    pub fn chunk_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.chunk_dir = Some(dir.into());
+        self
+    }
+
+    /// Create the API, fails when the archive of the retention can't be opened, two read models
+    /// have the same module or the projections aren't ready in time
+    pub fn build(self) -> Result<CoreApi, String> {
+        // Create the event store, archiving the older events as configured
+        let (event_store, event_sender) = create_event_store();
+        if let Some(retention) = self.retention {
+            let recovery = event_store
+                .lock()
+                .unwrap()
+                .set_retention(retention)
+                .map_err(|e| format!("Failed to open the event archive: {}", e))?;
+            if let Some(recovery) = recovery {
+                println!("Event archive of the last run: {}", recovery);
+            }
+        }
+
+        // Create the person repository
+        let repo = PersonRepository::new(self.repository);
+
+        // Create the world service first, the other APIs place what they're given on its active
+        // map
+        let world_service = Arc::new(Mutex::new(WorldService::new(event_sender.clone())));
+
+        // Create the person service
+        let person_service = Arc::new(Mutex::new(PersonService::new(repo, event_sender.clone())));
+
+        // Create the tag service, shared by tags and metadata
+        let tag_service = Arc::new(Mutex::new(TagService::new(event_sender.clone())));
+
+        // Create the scenario service
+        let scenario_service = Arc::new(Mutex::new(ScenarioService::new(event_sender.clone())));
+
+        // Create the goal service
+        let goal_service = Arc::new(Mutex::new(GoalService::new(event_sender.clone())));
+
+        // Create the fog service
+        let fog_service = Arc::new(Mutex::new(FogService::new(event_sender.clone())));
+
+        // Create the faction service, owning zones and tracking who owns which entity
+        let faction_service = Arc::new(Mutex::new(FactionService::new(event_sender.clone())));
+
+        // Create the task service, the persons work on their tasks once per tick
+        let task_service = Arc::new(Mutex::new(TaskService::new(event_sender.clone())));
+
+        // Create the vehicle service, the vehicles drive once per tick
+        let vehicle_service = Arc::new(Mutex::new(VehicleService::new(event_sender.clone())));
+
+        // Create the portal service, the persons working on tasks take the portals
+        let portal_service = Arc::new(Mutex::new(PortalService::new(event_sender.clone())));
+
+        // Create the group service, the households and crews
+        let group_service = Arc::new(Mutex::new(GroupService::new(event_sender.clone())));
+
+        // Create the map service, the frontends apply the edits of the tiles to their maps
+        let map_service = Arc::new(Mutex::new(MapService::new(event_sender.clone())));
+
+        // Create the stream service, writing the unloaded chunks with their persons to disk
+        let chunk_store = match self.chunk_dir {
+            Some(dir) => ChunkStore::new(dir),
+            None => ChunkStore::temporary(),
+        };
+        let stream_service = Arc::new(Mutex::new(StreamService::new(
+            chunk_store,
+            event_sender.clone(),
+        )));
+
+        // Create the projection manager
+        let projection_manager = ProjectionManager::new(event_store.clone());
+
+        // Register the location occupancy projection, the scripts also query it as
+        // `api.occupancy`
+        let location_projection =
+            projection_manager.register_projection(LocationOccupancyProjection::new());
+        let read_models = ReadModels::default();
+        read_models.register(ReadModel::of(location_projection.clone()))?;
+
+        // Register the stats projection, sampled into the metrics every few ticks
+        let stats_projection = projection_manager.register_projection(StatsProjection::new());
+        if let Some(ticks) = self.sample_interval {
+            stats_projection.lock().unwrap().set_interval(ticks);
+        }
+
+        // Register the group membership projection
+        let membership_projection =
+            projection_manager.register_projection(GroupMembershipProjection::new());
+
+        // The projections of the caller, after the ones the APIs read
+        for register in self.projections {
+            register(&projection_manager, &read_models)?;
+        }
+
+        // Metrics of the stats projection and the scripts
+        let metrics = Metrics::default();
+
+        // Reads most of the world, for the state hashes
+        let debug = DebugApi {
+            persons: person_service.clone(),
+            tags: tag_service.clone(),
+            factions: faction_service.clone(),
+            tasks: task_service.clone(),
+            vehicles: vehicle_service.clone(),
+            portals: portal_service.clone(),
+            groups: group_service.clone(),
+            map: map_service.clone(),
+            stream: stream_service.clone(),
+            locations: location_projection.clone(),
+        };
+
+        let core = CoreApi {
+            person: PersonApi {
+                service: person_service.clone(),
+                world: world_service.clone(),
+            },
+            location: LocationApi {
+                projection: location_projection.clone(),
+                world: world_service.clone(),
+            },
+            event: EventApi {
+                store: event_store.clone(),
+            },
+            tags: TagsApi {
+                service: tag_service.clone(),
+                persons: person_service.clone(),
+                factions: faction_service.clone(),
+            },
+            meta: MetaApi {
+                service: tag_service.clone(),
+                persons: person_service.clone(),
+                factions: faction_service.clone(),
+            },
+            query: QueryApi {
+                persons: person_service.clone(),
+                tags: tag_service,
+                locations: location_projection.clone(),
+                world: world_service.clone(),
+            },
+            metrics: MetricsApi {
+                metrics: metrics.clone(),
+                stats: stats_projection,
+                locations: location_projection.clone(),
+            },
+            scenario: ScenarioApi {
+                service: scenario_service,
+                store: event_store,
+                persons: person_service.clone(),
+                locations: location_projection,
+                metrics,
+            },
+            goals: GoalsApi {
+                service: goal_service,
+            },
+            fog: FogApi {
+                service: fog_service,
+                world: world_service.clone(),
+            },
+            faction: FactionApi {
+                service: faction_service,
+                persons: person_service.clone(),
+                world: world_service.clone(),
+            },
+            task: TaskApi {
+                service: task_service.clone(),
+                persons: person_service.clone(),
+                portals: portal_service.clone(),
+                world: world_service.clone(),
+            },
+            vehicle: VehicleApi {
+                service: vehicle_service,
+                persons: person_service.clone(),
+                world: world_service.clone(),
+            },
+            portal: PortalApi {
+                service: portal_service,
+                world: world_service.clone(),
+            },
+            group: GroupApi {
+                service: group_service,
+                membership: membership_projection,
+                persons: person_service.clone(),
+                tasks: task_service,
+                world: world_service.clone(),
+            },
+            map: MapApi {
+                service: map_service,
+                world: world_service.clone(),
+            },
+            stream: StreamApi {
+                service: stream_service,
+                persons: person_service.clone(),
+                world: world_service.clone(),
+            },
+            world: WorldApi {
+                service: world_service.clone(),
+            },
+            debug,
+            persons: person_service,
+            world_service,
+            snapshots: Snapshots::default(),
+            projections: projection_manager,
+            read_models,
+            event_sender,
+        };
+
+        if let Some(timeout) = self.ready_within {
+            let ready =
+                core.projections().wait_until_ready(timeout) && core.wait_for_projections(timeout);
+            if !ready {
+                return Err(format!(
+                    "The projections weren't ready within {:?}",
+                    timeout
+                ));
+            }
+        }
+        Ok(core)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::event::DomainEvent;
+
+    // Counts the events it sees into a handle the test keeps
+    struct EventCounter(Arc<Mutex<usize>>);
+
+    impl Projection for EventCounter {
+        fn apply(&mut self, _event: &DomainEvent) {
+            *self.0.lock().unwrap() += 1;
+        }
+
+        fn name(&self) -> &str {
+            "EventCounter"
+        }
+    }
+
+    #[test]
+    fn test_built_api_is_ready_with_the_projections_given() {
+        let count = Arc::new(Mutex::new(0));
+        let core = CoreApi::builder()
+            .repository(RepositoryBackend::Map)
+            .projection(EventCounter(count.clone()))
+            .sample_interval(5)
+            .ready_within(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let names: Vec<String> = core
+            .projections()
+            .progress()
+            .into_iter()
+            .map(|progress| progress.name)
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "LocationOccupancyProjection",
+                "StatsProjection",
+                "GroupMembershipProjection",
+                "EventCounter"
+            ]
+        );
+        assert!(core.projections().ready());
+        assert_eq!(core.metrics().sample_interval(), 5);
+
+        core.person().create("Ann".to_string(), 0, 0).unwrap();
+        assert!(core.wait_for_projections(Duration::from_secs(5)));
+        assert_eq!(*count.lock().unwrap(), 1);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::domain::entity::person::PersonId;
+use crate::domain::value_object::entity_ref::EntityRef;
+use crate::domain::value_object::location::Location;
+use crate::state_hash::{hash_value, to_hex, StateHash};
+use crate::DebugApi;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A location where the occupancy projection and the persons disagree on who is there
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discrepancy {
+    pub location: Location,
+    /// Ids of the persons the projection has there, sorted
+    pub projected: Vec<u32>,
+    /// Ids of the persons whose location it is, sorted
+    pub actual: Vec<u32>,
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "({}, {}) on level {} of {}: projected {:?}, actual {:?}",
+            self.location.x,
+            self.location.y,
+            self.location.z,
+            self.location.map,
+            self.projected,
+            self.actual
+        )
+    }
+}
+
+impl DebugApi {
+    /// Hash of the world state as 16 hex digits. Runs, replays and saves that agree at the same
+    /// tick have the same hash, without comparing the whole state.
+    pub fn state_hash(&self) -> String {
+        to_hex(self.hash().combined())
+    }
+
+    /// Hashes of the parts of the world state as 16 hex digits by part: persons, tags,
+    /// factions, tasks, vehicles, portals, groups, map, chunks and locations. Tells which part
+    /// differs once the state hashes do. The persons of unloaded chunks count as the chunk.
+    pub fn state_hashes(&self) -> Vec<(String, String)> {
+        self.hash()
+            .parts
+            .into_iter()
+            .map(|(name, hash)| (name.to_string(), to_hex(hash)))
+            .collect()
+    }
+
+    /// Recompute who is where from the persons and compare it with the location occupancy
+    /// projection. Empty when they agree, otherwise the locations they disagree on, ordered by
+    /// map, level, row and column. Events the projection didn't get to yet count as
+    /// disagreement, wait for the projections first.
+    pub fn verify(&self) -> Vec<Discrepancy> {
+        let mut actual: HashMap<Location, Vec<u32>> = HashMap::new();
+        for person in self
+            .persons
+            .lock()
+            .unwrap()
+            .get_all_persons()
+            .unwrap_or_default()
+        {
+            actual.entry(person.location).or_default().push(person.id.0);
+        }
+        let mut discrepancies = Vec::new();
+        for (location, people) in self.locations.lock().unwrap().get_all_occupied() {
+            let projected: Vec<u32> = people.iter().map(|id| id.0).collect();
+            let mut actual = actual.remove(&location).unwrap_or_default();
+            actual.sort();
+            if projected != actual {
+                discrepancies.push(Discrepancy {
+                    location,
+                    projected,
+                    actual,
+                });
+            }
+        }
+        // The locations of persons the projection has nobody at
+        discrepancies.extend(actual.into_iter().map(|(location, mut actual)| {
+            actual.sort();
+            Discrepancy {
+                location,
+                projected: Vec::new(),
+                actual,
+            }
+        }));
+        discrepancies.sort_by(|a, b| {
+            let key = |d: &Discrepancy| {
+                let l = &d.location;
+                (l.map.clone(), l.z, l.y, l.x)
+            };
+            key(a).cmp(&key(b))
+        });
+        discrepancies
+    }
+
+    // Hash of the world state by part, the persons in the order of their ids and the rest in
+    // the order of their keys so it doesn't matter how they are stored
+    pub fn hash(&self) -> StateHash {
+        let mut persons = self
+            .persons
+            .lock()
+            .unwrap()
+            .get_all_persons()
+            .unwrap_or_default();
+        persons.sort_by_key(|person| person.id);
+        let ids: Vec<PersonId> = persons.iter().map(|person| person.id).collect();
+
+        let tags = {
+            let tags = self.tags.lock().unwrap();
+            let entities: Vec<EntityRef> = tags.entities();
+            let tagged: Vec<_> = entities
+                .into_iter()
+                .map(|entity| (entity, tags.tags_of(entity), tags.all_meta(entity)))
+                .collect();
+            hash_value(&tagged)
+        };
+        let factions = {
+            let factions = self.factions.lock().unwrap();
+            let mut all = factions.all();
+            all.sort_by(|a, b| a.id.cmp(&b.id));
+            let owned: Vec<_> = all
+                .into_iter()
+                .map(|faction| {
+                    let owned = factions.owned_by(&faction.id);
+                    (faction, owned)
+                })
+                .collect();
+            let mut zones = factions.zones();
+            zones.sort_by_key(|zone| zone.id);
+            hash_value(&(owned, zones))
+        };
+        let tasks = {
+            let tasks = self.tasks.lock().unwrap();
+            let queues: Vec<_> = ids.iter().map(|id| tasks.queue_of(*id)).collect();
+            hash_value(&queues)
+        };
+        let vehicles = hash_value(&self.vehicles.lock().unwrap().all());
+        let portals = hash_value(&self.portals.lock().unwrap().all());
+        let groups = hash_value(&self.groups.lock().unwrap().all());
+        let map = {
+            let map = self.map.lock().unwrap();
+            hash_value(&(map.edits(), map.locks()))
+        };
+        let chunks = hash_value(&self.stream.lock().unwrap().unloaded());
+        let locations = hash_value(&self.locations.lock().unwrap().get_all_occupied());
+
+        StateHash {
+            parts: vec![
+                ("persons", hash_value(&persons)),
+                ("tags", tags),
+                ("factions", factions),
+                ("tasks", tasks),
+                ("vehicles", vehicles),
+                ("portals", portals),
+                ("groups", groups),
+                ("map", map),
+                ("chunks", chunks),
+                ("locations", locations),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::event::person_event::PersonEvent;
+    use crate::domain::event::DomainEvent;
+    use crate::infrastructure::projection::Projection;
+    use crate::CoreApi;
+    use std::time::Duration;
+
+    #[test]
+    fn test_verify_finds_where_the_projection_is_wrong() {
+        let core = CoreApi::new();
+        let ann = core.person().create("Ann".to_string(), 1, 2).unwrap().id;
+        let bob = core.person().create("Bob".to_string(), 1, 2).unwrap().id;
+        assert!(core.wait_for_projections(Duration::from_secs(5)));
+        assert_eq!(core.debug().verify(), Vec::new());
+
+        // An event the repository never saw, as a buggy handler would leave it
+        core.debug()
+            .locations
+            .lock()
+            .unwrap()
+            .apply(&DomainEvent::Person(PersonEvent::PersonMoved {
+                person_id: ann,
+                from_location: Location::new(1, 2),
+                to_location: Location::new(5, 5),
+            }));
+        let discrepancies = core.debug().verify();
+        assert_eq!(
+            discrepancies,
+            vec![
+                Discrepancy {
+                    location: Location::new(1, 2),
+                    projected: vec![bob.0],
+                    actual: vec![ann.0, bob.0],
+                },
+                Discrepancy {
+                    location: Location::new(5, 5),
+                    projected: vec![ann.0],
+                    actual: Vec::new(),
+                },
+            ]
+        );
+        assert_eq!(
+            discrepancies[1].to_string(),
+            "(5, 5) on level 0 of main: projected [0], actual []"
+        );
+    }
+}
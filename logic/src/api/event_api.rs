@@ -1,8 +1,52 @@
+use crate::domain::event::DomainEvent;
+use crate::domain::value_object::region::Region;
+use crate::infrastructure::event_log::Recovery;
+use crate::infrastructure::event_store::{EventMemory, RetentionPolicy};
 use crate::EventApi;
+use std::io;
+use std::sync::mpsc::Receiver;
 
 impl EventApi {
     /// Get the total number of events in the event store
     pub fn count(&self) -> usize {
         self.store.lock().unwrap().event_count()
     }
+
+    /// How many events are held in memory and how much of it they take, the archived and
+    /// dropped ones counted as well
+    pub fn memory(&self) -> EventMemory {
+        self.store.lock().unwrap().memory()
+    }
+
+    // Change how many events are kept in memory, set per deployment rather than by the scripts
+    // so kept out of the docs. Tells what was recovered of the archive of the last run.
+    pub fn set_retention(&self, retention: RetentionPolicy) -> io::Result<Option<Recovery>> {
+        self.store.lock().unwrap().set_retention(retention)
+    }
+
+    // Move the events beyond the retention out of memory, done after every snapshot
+    pub fn trim(&self) -> io::Result<usize> {
+        self.store.lock().unwrap().trim()
+    }
+
+    // Visit the stored events in the order they were published, the archived ones read back from
+    // disk, stops at the first error. Holds the store meanwhile, not part of the Lua API so kept
+    // out of the docs
+    pub fn try_for_each<E: From<io::Error>>(
+        &self,
+        visit: impl FnMut(&DomainEvent) -> Result<(), E>,
+    ) -> Result<(), E> {
+        self.store.lock().unwrap().try_for_each(visit)
+    }
+
+    // Receive all events published from now on, not part of the Lua API so kept out of the docs
+    pub fn subscribe(&self) -> Receiver<DomainEvent> {
+        self.store.lock().unwrap().subscribe()
+    }
+
+    // Receive the events published from now on within a region, for frontends showing only a
+    // part of the world, see `EventStore::subscribe_region`
+    pub fn subscribe_region(&self, region: Region) -> Receiver<DomainEvent> {
+        self.store.lock().unwrap().subscribe_region(region)
+    }
 }
@@ -0,0 +1,124 @@
+use crate::api::tags_api::resolve_entity;
+use crate::domain::entity::faction::Faction;
+use crate::domain::service::faction_service::FactionService;
+use crate::domain::service::world_service::WorldService;
+use crate::domain::value_object::entity_ref::EntityRef;
+use crate::FactionApi;
+use std::sync::{Arc, Mutex};
+
+// Corners of a zone as (x1, y1, x2, y2) and the color of its owner
+type OwnedZone = ((i32, i32, i32, i32), [u8; 3]);
+
+impl FactionApi {
+    /// Found a faction of kind "player", "ai" or "neutral" drawn in a color like "#ff8800"
+    pub fn create(&self, id: &str, name: &str, kind: &str, color: &str) -> Result<Faction, String> {
+        let kind = kind.parse()?;
+        let color = parse_color(color)?;
+        self.service.lock().unwrap().create(id, name, kind, color)
+    }
+
+    /// Get a faction by its id
+    pub fn get(&self, id: &str) -> Option<Faction> {
+        self.service.lock().unwrap().get(id)
+    }
+
+    /// Get all factions in the order they were founded, "player" and "neutral" first
+    pub fn all(&self) -> Vec<Faction> {
+        self.service.lock().unwrap().all()
+    }
+
+    /// Mark out a zone between two corners, returns its reference like "zone:1"
+    pub fn create_zone(&self, x1: i32, y1: i32, x2: i32, y2: i32) -> String {
+        let region = self.world.lock().unwrap().region(x1, y1, x2, y2);
+        let zone = self.service.lock().unwrap().create_zone(region);
+        EntityRef::Zone(zone.id).to_string()
+    }
+
+    /// Get the zones containing a tile
+    pub fn zones_at(&self, x: i32, y: i32) -> Vec<String> {
+        let location = self.world.lock().unwrap().location(x, y);
+        self.service
+            .lock()
+            .unwrap()
+            .zones_at(&location)
+            .iter()
+            .map(|zone| EntityRef::Zone(zone.id).to_string())
+            .collect()
+    }
+
+    /// Hand an entity like "zone:1" to a faction, returns false if the faction owned it already
+    pub fn transfer(&self, entity: &str, faction: &str) -> Result<bool, String> {
+        let entity = resolve_entity(&self.persons, &self.service, entity)?;
+        self.service.lock().unwrap().transfer(entity, Some(faction))
+    }
+
+    /// Take an entity away from its faction, returns false if nobody owned it
+    pub fn release(&self, entity: &str) -> Result<bool, String> {
+        let entity = resolve_entity(&self.persons, &self.service, entity)?;
+        self.service.lock().unwrap().transfer(entity, None)
+    }
+
+    /// Get the faction owning an entity, nil if nobody does
+    pub fn owner(&self, entity: &str) -> Result<Option<String>, String> {
+        let entity = resolve_entity(&self.persons, &self.service, entity)?;
+        Ok(self.service.lock().unwrap().owner(entity))
+    }
+
+    /// Get the entities a faction owns
+    pub fn owned(&self, faction: &str) -> Result<Vec<String>, String> {
+        let service = self.service.lock().unwrap();
+        if service.get(faction).is_none() {
+            return Err(format!("There is no faction {}", faction));
+        }
+        Ok(service
+            .owned_by(faction)
+            .iter()
+            .map(EntityRef::to_string)
+            .collect())
+    }
+
+    // Handle to the factions for the frontends drawing them, not part of the Lua API
+    pub fn shared(&self) -> Factions {
+        Factions {
+            service: self.service.clone(),
+            world: self.world.clone(),
+        }
+    }
+}
+
+// Colors are written "#rrggbb"
+fn parse_color(color: &str) -> Result<[u8; 3], String> {
+    let invalid = || format!("'{}' is not a color like #ff8800", color);
+    let hex = color.strip_prefix('#').ok_or_else(invalid)?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return Err(invalid());
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// The factions as the frontends draw them
+#[derive(Clone)]
+pub struct Factions {
+    service: Arc<Mutex<FactionService>>,
+    world: Arc<Mutex<WorldService>>,
+}
+
+impl Factions {
+    // Every owned zone on the active level, ordered by id
+    pub fn owned_zones(&self) -> Vec<OwnedZone> {
+        let world = self.world.lock().unwrap();
+        let service = self.service.lock().unwrap();
+        service
+            .zones()
+            .iter()
+            .filter(|zone| world.shows(zone.region.corners().0))
+            .filter_map(|zone| {
+                let owner = service.owner(EntityRef::Zone(zone.id))?;
+                let color = service.get(&owner)?.color;
+                let (min, max) = zone.region.corners();
+                Some(((min.x, min.y, max.x, max.y), color))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,80 @@
+use crate::domain::service::fog_service::FogService;
+use crate::domain::service::world_service::WorldService;
+use crate::domain::value_object::visibility::Visibility;
+use crate::FogApi;
+use std::sync::{Arc, Mutex};
+
+impl FogApi {
+    /// Reveal the tiles between two corners to a viewer, returns how many weren't visible before
+    pub fn reveal(
+        &self,
+        x1: i32,
+        y1: i32,
+        x2: i32,
+        y2: i32,
+        viewer: &str,
+    ) -> Result<usize, String> {
+        let region = self.world.lock().unwrap().region(x1, y1, x2, y2);
+        self.service.lock().unwrap().reveal(viewer, &region)
+    }
+
+    /// Take the tiles between two corners out of a viewer's sight, they stay explored
+    pub fn hide(&self, x1: i32, y1: i32, x2: i32, y2: i32, viewer: &str) -> Result<usize, String> {
+        let region = self.world.lock().unwrap().region(x1, y1, x2, y2);
+        self.service.lock().unwrap().hide(viewer, &region)
+    }
+
+    /// Get what a viewer knows about a tile: "unexplored", "explored" or "visible"
+    pub fn visibility(&self, x: i32, y: i32, viewer: &str) -> String {
+        let location = self.world.lock().unwrap().location(x, y);
+        let service = self.service.lock().unwrap();
+        service.visibility(viewer, &location).to_string()
+    }
+
+    /// Get the number of tiles a viewer explored
+    pub fn explored(&self, viewer: &str) -> usize {
+        self.service.lock().unwrap().explored_count(viewer)
+    }
+
+    // Handle to the fog for the frontends drawing it, not part of the Lua API
+    pub fn shared(&self) -> Fog {
+        Fog {
+            service: self.service.clone(),
+            world: self.world.clone(),
+        }
+    }
+}
+
+/// The fog as the frontends draw it
+#[derive(Clone)]
+pub struct Fog {
+    service: Arc<Mutex<FogService>>,
+    world: Arc<Mutex<WorldService>>,
+}
+
+impl Fog {
+    // Whether the viewer has fog at all, viewers that explored nothing yet see everything
+    pub fn has_viewer(&self, viewer: &str) -> bool {
+        self.service.lock().unwrap().has_viewer(viewer)
+    }
+
+    // Visibility of every tile between two corners of the active map, row by row
+    pub fn visibility_in(
+        &self,
+        viewer: &str,
+        x1: i32,
+        y1: i32,
+        x2: i32,
+        y2: i32,
+    ) -> Vec<(i32, i32, Visibility)> {
+        let region = self.world.lock().unwrap().region(x1, y1, x2, y2);
+        let service = self.service.lock().unwrap();
+        region
+            .locations()
+            .map(|location| {
+                let visibility = service.visibility(viewer, &location);
+                (location.x, location.y, visibility)
+            })
+            .collect()
+    }
+}
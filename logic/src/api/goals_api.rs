@@ -0,0 +1,24 @@
+use crate::domain::entity::goal::Goal;
+use crate::GoalsApi;
+
+impl GoalsApi {
+    /// Define a goal with the progress it's complete at, fails if the id is taken
+    pub fn define(&self, id: &str, name: &str, target: f64) -> Result<Goal, String> {
+        self.service.lock().unwrap().define(id, name, target)
+    }
+
+    /// Set the progress of a goal, it completes once the progress reaches its target
+    pub fn progress(&self, id: &str, progress: f64) -> Result<Goal, String> {
+        self.service.lock().unwrap().set_progress(id, progress)
+    }
+
+    /// Get a goal by its id
+    pub fn get(&self, id: &str) -> Option<Goal> {
+        self.service.lock().unwrap().get(id)
+    }
+
+    /// Get all goals in the order they were defined
+    pub fn all(&self) -> Vec<Goal> {
+        self.service.lock().unwrap().all()
+    }
+}
@@ -0,0 +1,112 @@
+use crate::domain::entity::group::{Group, GroupId, GroupKind};
+use crate::domain::entity::person::{Person, PersonId};
+use crate::domain::entity::task::{Task, TaskKind};
+use crate::GroupApi;
+
+impl GroupApi {
+    /// Found a group of kind "household" or "crew", with a shared inventory if asked for
+    pub fn create(&self, name: &str, kind: &str, shared_inventory: bool) -> Result<Group, String> {
+        let kind: GroupKind = kind.parse()?;
+        Ok(self
+            .service
+            .lock()
+            .unwrap()
+            .create(name, kind, shared_inventory))
+    }
+
+    /// Add a person to a group, a person belongs to one household and one crew at most
+    pub fn join(&self, id: u32, person_id: u32) -> Result<Group, String> {
+        let person_id = PersonId(person_id);
+        self.persons
+            .lock()
+            .unwrap()
+            .get_person(person_id)
+            .map_err(|e| format!("Failed to get person: {:?}", e))?;
+        self.service.lock().unwrap().join(GroupId(id), person_id)
+    }
+
+    /// Take a person out of a group
+    pub fn leave(&self, id: u32, person_id: u32) -> Result<Group, String> {
+        self.service
+            .lock()
+            .unwrap()
+            .leave(GroupId(id), PersonId(person_id))
+    }
+
+    /// Dissolve a group with its shared inventory, returns false if there is no such group
+    pub fn disband(&self, id: u32) -> bool {
+        self.service.lock().unwrap().disband(GroupId(id))
+    }
+
+    /// Get a group by its id
+    pub fn get(&self, id: u32) -> Option<Group> {
+        self.service.lock().unwrap().get(GroupId(id))
+    }
+
+    /// Get all groups ordered by id
+    pub fn all(&self) -> Vec<Group> {
+        self.service.lock().unwrap().all()
+    }
+
+    /// Get the ids of the groups a person belongs to, as far as the membership projection got
+    pub fn of(&self, person_id: u32) -> Vec<u32> {
+        self.membership
+            .lock()
+            .unwrap()
+            .get_groups_of(PersonId(person_id))
+            .into_iter()
+            .map(|group| group.0)
+            .collect()
+    }
+
+    /// Move all members of a group to a tile at once, published as a single event
+    pub fn move_to(&self, id: u32, x: i32, y: i32) -> Result<Vec<Person>, String> {
+        let members = self.members(id)?;
+        let location = self.world.lock().unwrap().location(x, y);
+        self.persons
+            .lock()
+            .unwrap()
+            .move_persons(
+                members
+                    .into_iter()
+                    .map(|member| (member, location.clone()))
+                    .collect(),
+            )
+            .map_err(|e| format!("Failed to move persons: {:?}", e))
+    }
+
+    /// Queue a walk to a tile for every member of a group, like `task.go_to` for each
+    pub fn go_to(&self, id: u32, x: i32, y: i32) -> Result<Vec<Task>, String> {
+        let members = self.members(id)?;
+        let target = self.world.lock().unwrap().location(x, y);
+        let mut tasks = self.tasks.lock().unwrap();
+        Ok(members
+            .into_iter()
+            .map(|member| {
+                let kind = TaskKind::GoTo {
+                    target: target.clone(),
+                };
+                tasks.queue(member, kind, 0, true)
+            })
+            .collect())
+    }
+
+    /// Put an item into the shared inventory of a group
+    pub fn store(&self, id: u32, item: &str) -> Result<Group, String> {
+        self.service.lock().unwrap().store(GroupId(id), item)
+    }
+
+    /// Take an item out of the shared inventory of a group
+    pub fn take(&self, id: u32, item: &str) -> Result<Group, String> {
+        self.service.lock().unwrap().take(GroupId(id), item)
+    }
+
+    fn members(&self, id: u32) -> Result<Vec<PersonId>, String> {
+        self.service
+            .lock()
+            .unwrap()
+            .get(GroupId(id))
+            .map(|group| group.members)
+            .ok_or_else(|| format!("There is no group {}", id))
+    }
+}
@@ -1,10 +1,9 @@
-use crate::domain::value_object::location::Location;
 use crate::LocationApi;
 
 impl LocationApi {
     /// Get all people at a specific location
     pub fn get_people_at(&self, x: i32, y: i32) -> Vec<u32> {
-        let location = Location { x, y };
+        let location = self.world.lock().unwrap().location(x, y);
         self.projection
             .lock()
             .unwrap()
@@ -16,10 +15,14 @@ impl LocationApi {
 
     /// Get all occupied locations
     pub fn get_occupied(&self) -> Vec<(i32, i32)> {
+        let (map, z) = {
+            let world = self.world.lock().unwrap();
+            (world.active().clone(), world.level())
+        };
         self.projection
             .lock()
             .unwrap()
-            .get_occupied_locations()
+            .get_occupied_locations(&map, z)
             .into_iter()
             .map(|loc| (loc.x, loc.y))
             .collect()
@@ -27,18 +30,26 @@ impl LocationApi {
 
     /// Get the most crowded location
     pub fn most_crowded(&self) -> Option<(i32, i32, usize)> {
+        let (map, z) = {
+            let world = self.world.lock().unwrap();
+            (world.active().clone(), world.level())
+        };
         self.projection
             .lock()
             .unwrap()
-            .get_most_crowded_location()
+            .get_most_crowded_location(&map, z)
             .map(|(loc, count)| (loc.x, loc.y, count))
     }
 
     /// Get the number of occupied locations
     pub fn occupied_count(&self) -> usize {
+        let (map, z) = {
+            let world = self.world.lock().unwrap();
+            (world.active().clone(), world.level())
+        };
         self.projection
             .lock()
             .unwrap()
-            .get_occupied_location_count()
+            .get_occupied_location_count(&map, z)
     }
 }
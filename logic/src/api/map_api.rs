@@ -0,0 +1,127 @@
+use crate::domain::entity::map_lock::{MapLock, MapLockId};
+use crate::domain::service::map_service::{
+    MapService, TileChange, DEFAULT_LAYER, SCRIPT_EDITOR, UI_EDITOR,
+};
+use crate::domain::value_object::location::Location;
+use crate::domain::value_object::map_id::MapId;
+use crate::MapApi;
+use std::sync::{Arc, Mutex};
+
+impl MapApi {
+    /// Put a tile at x, y of the active map and level, on the "ground" layer for nil. The
+    /// frontends draw it once they take the edit, like the ones made in their editors. Fails
+    /// with the reason when another owner locked the tile.
+    pub fn place(&self, x: i32, y: i32, tile: u32, layer: Option<&str>) -> Result<(), String> {
+        let location = self.world.lock().unwrap().location(x, y);
+        self.service.lock().unwrap().place(
+            SCRIPT_EDITOR,
+            layer.unwrap_or(DEFAULT_LAYER),
+            location,
+            tile,
+        )
+    }
+
+    /// Clear the tile at x, y of the active map and level, on the "ground" layer for nil. Fails
+    /// with the reason when another owner locked the tile.
+    pub fn remove(&self, x: i32, y: i32, layer: Option<&str>) -> Result<(), String> {
+        let location = self.world.lock().unwrap().location(x, y);
+        self.service
+            .lock()
+            .unwrap()
+            .remove(SCRIPT_EDITOR, layer.unwrap_or(DEFAULT_LAYER), location)
+    }
+
+    /// Keep the tiles between two corners of the active map and level for an owner, "script"
+    /// for nil. Edits of anyone else fail there until it's unlocked, like the ones of the editor
+    /// ("editor") for a lock of the scripts.
+    pub fn lock(
+        &self,
+        x1: i32,
+        y1: i32,
+        x2: i32,
+        y2: i32,
+        owner: Option<&str>,
+    ) -> Result<MapLock, String> {
+        let region = self.world.lock().unwrap().region(x1, y1, x2, y2);
+        self.service
+            .lock()
+            .unwrap()
+            .lock(owner.unwrap_or(SCRIPT_EDITOR), region)
+    }
+
+    /// Let anyone edit the region of a lock again, returns false if there is no such lock
+    pub fn unlock(&self, id: u32) -> bool {
+        self.service.lock().unwrap().unlock(MapLockId(id))
+    }
+
+    /// Get the lock of a tile of the active map and level
+    pub fn lock_at(&self, x: i32, y: i32) -> Option<MapLock> {
+        let location = self.world.lock().unwrap().location(x, y);
+        self.service.lock().unwrap().lock_at(&location).cloned()
+    }
+
+    /// Get all locks ordered by id
+    pub fn locks(&self) -> Vec<MapLock> {
+        self.service.lock().unwrap().locks()
+    }
+
+    // Handle to the edits for the frontends making and drawing them, not part of the Lua API
+    pub fn shared(&self) -> MapEdits {
+        MapEdits {
+            service: self.service.clone(),
+            editor: UI_EDITOR.to_string(),
+        }
+    }
+}
+
+/// The edits of the tiles as the frontends make and draw them. Edits of their editors go
+/// through here too, so they are published and replayed like the ones of the scripts, and
+/// rejected with the reason where another owner locked the tiles.
+#[derive(Clone)]
+pub struct MapEdits {
+    service: Arc<Mutex<MapService>>,
+    editor: String,
+}
+
+impl MapEdits {
+    // The same edits made by someone else, like a remote peer
+    pub fn as_editor(&self, editor: &str) -> MapEdits {
+        MapEdits {
+            service: self.service.clone(),
+            editor: editor.to_string(),
+        }
+    }
+
+    // Put a tile on cells of a layer of a map the frontend shows, which may not be the active
+    // one. None of them change if one is locked.
+    pub fn place_at(
+        &self,
+        map: &str,
+        z: i32,
+        layer: &str,
+        cells: &[(i32, i32)],
+        tile: u32,
+    ) -> Result<(), String> {
+        let locations = cells.iter().map(|&(x, y)| cell(map, z, x, y)).collect();
+        self.service
+            .lock()
+            .unwrap()
+            .place_all(&self.editor, layer, locations, tile)
+    }
+
+    pub fn remove_at(&self, map: &str, z: i32, layer: &str, x: i32, y: i32) -> Result<(), String> {
+        self.service
+            .lock()
+            .unwrap()
+            .remove(&self.editor, layer, cell(map, z, x, y))
+    }
+
+    // The cells edited since the last call with what they hold now
+    pub fn take_changes(&self) -> Vec<TileChange> {
+        self.service.lock().unwrap().take_changes()
+    }
+}
+
+fn cell(map: &str, z: i32, x: i32, y: i32) -> Location {
+    Location::on(&MapId::new(map), x, y).at_level(z)
+}
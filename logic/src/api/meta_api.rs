@@ -0,0 +1,31 @@
+use crate::api::tags_api::resolve_entity;
+use crate::domain::value_object::meta_value::MetaValue;
+use crate::MetaApi;
+use std::collections::BTreeMap;
+
+impl MetaApi {
+    /// Set a metadata entry of an entity like "person:3" to a boolean, number or string
+    pub fn set(&self, entity: &str, key: &str, value: MetaValue) -> Result<(), String> {
+        let entity = resolve_entity(&self.persons, &self.factions, entity)?;
+        self.service.lock().unwrap().set_meta(entity, key, value);
+        Ok(())
+    }
+
+    /// Get a metadata entry of an entity, nil if it isn't set
+    pub fn get(&self, entity: &str, key: &str) -> Result<Option<MetaValue>, String> {
+        let entity = resolve_entity(&self.persons, &self.factions, entity)?;
+        Ok(self.service.lock().unwrap().get_meta(entity, key))
+    }
+
+    /// Remove a metadata entry of an entity, returns false if it wasn't set
+    pub fn remove(&self, entity: &str, key: &str) -> Result<bool, String> {
+        let entity = resolve_entity(&self.persons, &self.factions, entity)?;
+        Ok(self.service.lock().unwrap().remove_meta(entity, key))
+    }
+
+    /// Get all metadata entries of an entity
+    pub fn all(&self, entity: &str) -> Result<BTreeMap<String, MetaValue>, String> {
+        let entity = resolve_entity(&self.persons, &self.factions, entity)?;
+        Ok(self.service.lock().unwrap().all_meta(entity))
+    }
+}
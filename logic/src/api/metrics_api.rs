@@ -0,0 +1,43 @@
+use crate::infrastructure::time_series::Metrics;
+use crate::MetricsApi;
+
+impl MetricsApi {
+    /// Record a value of a metric at the current tick, mods can add their own metrics
+    pub fn record(&self, name: &str, value: f64) -> Result<(), String> {
+        if !value.is_finite() {
+            return Err(format!("Metric {} can't be {}", name, value));
+        }
+        self.metrics.record(name, value);
+        Ok(())
+    }
+
+    /// Get the samples of a metric as { tick, value } pairs, older ones averaged over many ticks
+    pub fn series(&self, name: &str) -> Vec<(f64, f64)> {
+        self.metrics.samples(name)
+    }
+
+    /// Get the newest value of a metric
+    pub fn latest(&self, name: &str) -> Option<f64> {
+        self.metrics.latest(name)
+    }
+
+    /// Get the names of all metrics
+    pub fn names(&self) -> Vec<String> {
+        self.metrics.names()
+    }
+
+    /// Set the ticks between two samples of the world stats: persons, moves, occupied and event_rate
+    pub fn set_sample_interval(&self, ticks: u64) {
+        self.stats.lock().unwrap().set_interval(ticks);
+    }
+
+    /// Get the ticks between two samples of the world stats
+    pub fn sample_interval(&self) -> u64 {
+        self.stats.lock().unwrap().interval()
+    }
+
+    // Handle to the metrics for the frontends plotting them, not part of the Lua API
+    pub fn shared(&self) -> Metrics {
+        self.metrics.clone()
+    }
+}
@@ -1,11 +1,10 @@
 use crate::domain::entity::person::{Person, PersonId};
-use crate::domain::value_object::location::Location;
 use crate::PersonApi;
 
 impl PersonApi {
     /// Create a new person at the specified location
     pub fn create(&self, name: String, x: i32, y: i32) -> Result<Person, String> {
-        let location = Location { x, y };
+        let location = self.world.lock().unwrap().location(x, y);
         self.service
             .lock()
             .unwrap()
@@ -15,7 +14,7 @@ impl PersonApi {
 
     /// Move a person to a new location
     pub fn move_to(&self, person_id: u32, x: i32, y: i32) -> Result<Person, String> {
-        let location = Location { x, y };
+        let location = self.world.lock().unwrap().location(x, y);
         self.service
             .lock()
             .unwrap()
@@ -23,6 +22,22 @@ impl PersonApi {
             .map_err(|e| format!("Failed to move person: {:?}", e))
     }
 
+    /// Move many persons at once as a list of { id, x, y }, published as a single event
+    pub fn move_all(&self, moves: Vec<(u32, i32, i32)>) -> Result<Vec<Person>, String> {
+        let moves = {
+            let world = self.world.lock().unwrap();
+            moves
+                .into_iter()
+                .map(|(person_id, x, y)| (PersonId(person_id), world.location(x, y)))
+                .collect()
+        };
+        self.service
+            .lock()
+            .unwrap()
+            .move_persons(moves)
+            .map_err(|e| format!("Failed to move persons: {:?}", e))
+    }
+
     /// Get a person by ID
     pub fn get(&self, person_id: u32) -> Result<Person, String> {
         self.service
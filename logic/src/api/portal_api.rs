@@ -0,0 +1,80 @@
+use crate::domain::entity::portal::{Portal, PortalId};
+use crate::domain::service::portal_service::PortalService;
+use crate::domain::service::world_service::WorldService;
+use crate::PortalApi;
+use std::sync::{Arc, Mutex};
+
+impl PortalApi {
+    /// Link the tile at x1, y1 with the one at x2, y2 on another map and level, the same ones
+    /// for nil. Persons walking somewhere take it both ways, stairs and lifts are portals too.
+    pub fn create(
+        &self,
+        x1: i32,
+        y1: i32,
+        x2: i32,
+        y2: i32,
+        map: Option<&str>,
+        z: Option<i32>,
+    ) -> Result<Portal, String> {
+        let (from, to) = {
+            let world = self.world.lock().unwrap();
+            (world.location(x1, y1), world.location_on(map, z, x2, y2)?)
+        };
+        self.service.lock().unwrap().create(from, to)
+    }
+
+    /// Take a portal down, returns false if there is no such portal
+    pub fn remove(&self, id: u32) -> bool {
+        self.service.lock().unwrap().remove(PortalId(id))
+    }
+
+    /// Get a portal by its id
+    pub fn get(&self, id: u32) -> Option<Portal> {
+        self.service.lock().unwrap().get(PortalId(id))
+    }
+
+    /// Get all portals ordered by id
+    pub fn all(&self) -> Vec<Portal> {
+        self.service.lock().unwrap().all()
+    }
+
+    /// Get the portal with an end at a tile
+    pub fn at(&self, x: i32, y: i32) -> Option<Portal> {
+        let location = self.world.lock().unwrap().location(x, y);
+        self.service.lock().unwrap().at(&location)
+    }
+
+    // Handle to the portals for the frontends drawing them, not part of the Lua API
+    pub fn shared(&self) -> Portals {
+        Portals {
+            service: self.service.clone(),
+            world: self.world.clone(),
+        }
+    }
+}
+
+/// The portals as the frontends draw them
+#[derive(Clone)]
+pub struct Portals {
+    service: Arc<Mutex<PortalService>>,
+    world: Arc<Mutex<WorldService>>,
+}
+
+impl Portals {
+    // The ends of the portals on the active level with the id of their portal, ordered by id
+    pub fn ends(&self) -> Vec<(u32, (i32, i32))> {
+        let world = self.world.lock().unwrap();
+        self.service
+            .lock()
+            .unwrap()
+            .all()
+            .iter()
+            .flat_map(|portal| {
+                [&portal.from, &portal.to]
+                    .into_iter()
+                    .filter(|end| world.shows(end))
+                    .map(|end| (portal.id.0, (end.x, end.y)))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,67 @@
+use crate::domain::entity::person::{Person, PersonId};
+use crate::domain::value_object::entity_ref::EntityRef;
+use crate::query::PersonQuery;
+use crate::QueryApi;
+use std::collections::BTreeSet;
+
+impl QueryApi {
+    // Run a query, persons ordered by id. The candidates come from the tag index or the location
+    // projection and are checked against the repository, so persons the projection hasn't
+    // caught up with yet are left out rather than returned at their old location.
+    pub fn persons(&self, query: &PersonQuery) -> Result<Vec<Person>, String> {
+        let mut candidates: Option<BTreeSet<PersonId>> = None;
+        if !query.tags.is_empty() {
+            let tags = self.tags.lock().unwrap();
+            for tag in &query.tags {
+                let tagged: BTreeSet<_> = tags
+                    .find_tagged(tag)
+                    .into_iter()
+                    .filter_map(|entity| match entity {
+                        EntityRef::Person(person_id) => Some(person_id),
+                        EntityRef::Zone(_) => None,
+                    })
+                    .collect();
+                candidates = Some(match candidates {
+                    Some(candidates) => candidates.intersection(&tagged).copied().collect(),
+                    None => tagged,
+                });
+            }
+        }
+        // The rectangle is on the active level of the active map
+        let mut query = query.clone();
+        if let Some(region) = &mut query.region {
+            let world = self.world.lock().unwrap();
+            *region = region.on_map(world.active()).at_level(world.level());
+        }
+        if candidates.is_none()
+            && let Some(region) = &query.region
+        {
+            let projection = self.locations.lock().unwrap();
+            candidates = Some(
+                projection
+                    .get_people_in_region(region)
+                    .into_iter()
+                    .collect(),
+            );
+        }
+
+        let persons = self.persons.lock().unwrap();
+        let limit = query.limit.unwrap_or(usize::MAX);
+        let matches = match candidates {
+            Some(candidates) => candidates
+                .into_iter()
+                .filter_map(|person_id| persons.get_person(person_id).ok())
+                .filter(|person| query.contains(person))
+                .take(limit)
+                .collect(),
+            None => {
+                let mut all = persons
+                    .get_all_persons()
+                    .map_err(|e| format!("Failed to query persons: {:?}", e))?;
+                all.sort_by_key(|person| person.id);
+                all.into_iter().take(limit).collect()
+            }
+        };
+        Ok(matches)
+    }
+}
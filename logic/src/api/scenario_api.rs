@@ -0,0 +1,50 @@
+use crate::domain::event::scenario_event::ScenarioOutcome;
+use crate::ScenarioApi;
+use dto::ScenarioResultDto;
+
+impl ScenarioApi {
+    // End the scenario and summarize it, None if it had ended already. The end conditions are
+    // Lua functions, so this is driven by the engine rather than called from Lua directly.
+    pub fn end(
+        &self,
+        outcome: ScenarioOutcome,
+        reason: &str,
+        tick: u64,
+        duration: f64,
+    ) -> Option<ScenarioResultDto> {
+        if !self.service.lock().unwrap().end(outcome, reason, tick) {
+            return None;
+        }
+        let persons = self
+            .persons
+            .lock()
+            .unwrap()
+            .get_all_persons()
+            .map_or(0, |persons| persons.len());
+        let metrics = self
+            .metrics
+            .names()
+            .into_iter()
+            .filter_map(|name| Some((name.clone(), self.metrics.latest(&name)?)))
+            .collect();
+        Some(ScenarioResultDto {
+            outcome: outcome.to_string(),
+            reason: reason.to_string(),
+            ticks: tick,
+            duration,
+            events: self.store.lock().unwrap().event_count() as u64,
+            persons: persons as u64,
+            occupied_locations: self
+                .locations
+                .lock()
+                .unwrap()
+                .get_total_occupied_location_count() as u64,
+            metrics,
+        })
+    }
+
+    // Check if the scenario has ended
+    pub fn has_ended(&self) -> bool {
+        self.service.lock().unwrap().has_ended()
+    }
+}
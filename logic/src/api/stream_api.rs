@@ -0,0 +1,109 @@
+use crate::domain::service::person_service::PersonService;
+use crate::domain::service::stream_service::StreamService;
+use crate::domain::value_object::chunk::Chunk;
+use crate::domain::value_object::map_id::MapId;
+use crate::repo::PersonRepository;
+use crate::StreamApi;
+use std::sync::{Arc, Mutex};
+
+type Persons = Arc<Mutex<PersonService<PersonRepository>>>;
+
+impl StreamApi {
+    /// Write the chunk holding x, y of the active map and level to disk along with the persons
+    /// in it, returns how many there were. They are gone until the chunk is loaded again.
+    pub fn unload(&self, x: i32, y: i32) -> Result<usize, String> {
+        let chunk = Chunk::of(&self.world.lock().unwrap().location(x, y));
+        unload(&self.service, &self.persons, chunk)
+    }
+
+    /// Read the chunk holding x, y of the active map and level back, returns how many persons
+    /// came back with it
+    pub fn load(&self, x: i32, y: i32) -> Result<usize, String> {
+        let chunk = Chunk::of(&self.world.lock().unwrap().location(x, y));
+        load(&self.service, &self.persons, chunk)
+    }
+
+    /// Whether the chunk holding x, y of the active map and level is in memory
+    pub fn is_loaded(&self, x: i32, y: i32) -> bool {
+        let chunk = Chunk::of(&self.world.lock().unwrap().location(x, y));
+        !self.service.lock().unwrap().is_unloaded(&chunk)
+    }
+
+    /// Get the unloaded chunks of all maps ordered by map, level, row and column
+    pub fn unloaded(&self) -> Vec<Chunk> {
+        self.service.lock().unwrap().unloaded()
+    }
+
+    // Handle to the chunks for the frontends unloading what their cameras left, not part of the
+    // Lua API
+    pub fn shared(&self) -> Chunks {
+        Chunks {
+            service: self.service.clone(),
+            persons: self.persons.clone(),
+        }
+    }
+}
+
+/// The chunks as the frontends stream them, unloading the ones far from their cameras and
+/// loading them back as they come close. The tiles stay with the frontends, which keep them
+/// wherever they like meanwhile.
+#[derive(Clone)]
+pub struct Chunks {
+    service: Arc<Mutex<StreamService>>,
+    persons: Persons,
+}
+
+impl Chunks {
+    // Returns how many persons were written with the chunk
+    pub fn unload(&self, chunk: &Chunk) -> Result<usize, String> {
+        unload(&self.service, &self.persons, chunk.clone())
+    }
+
+    // Returns how many persons came back with the chunk
+    pub fn load(&self, chunk: &Chunk) -> Result<usize, String> {
+        load(&self.service, &self.persons, chunk.clone())
+    }
+
+    pub fn is_unloaded(&self, chunk: &Chunk) -> bool {
+        self.service.lock().unwrap().is_unloaded(chunk)
+    }
+
+    // Unloaded chunks of a level of a map the frontend shows, which may not be the active one
+    pub fn unloaded_on(&self, map: &str, z: i32) -> Vec<Chunk> {
+        self.service
+            .lock()
+            .unwrap()
+            .unloaded_on(&MapId::new(map), z)
+    }
+}
+
+// The persons go back to the repository if the chunk can't be written or is unloaded already
+fn unload(
+    service: &Mutex<StreamService>,
+    persons: &Persons,
+    chunk: Chunk,
+) -> Result<usize, String> {
+    let mut service = service.lock().unwrap();
+    let mut persons = persons.lock().unwrap();
+    let taken = persons
+        .take_persons_in(&chunk.region())
+        .map_err(|e| format!("Failed to take persons: {:?}", e))?;
+    if let Err(e) = service.unload(chunk, &taken) {
+        persons
+            .restore_persons(taken)
+            .map_err(|e| format!("Failed to restore persons: {:?}", e))?;
+        return Err(e);
+    }
+    Ok(taken.len())
+}
+
+fn load(service: &Mutex<StreamService>, persons: &Persons, chunk: Chunk) -> Result<usize, String> {
+    let loaded = service.lock().unwrap().load(chunk)?;
+    let count = loaded.len();
+    persons
+        .lock()
+        .unwrap()
+        .restore_persons(loaded)
+        .map_err(|e| format!("Failed to restore persons: {:?}", e))?;
+    Ok(count)
+}
@@ -0,0 +1,66 @@
+use crate::domain::service::faction_service::FactionService;
+use crate::domain::service::person_service::PersonService;
+use crate::domain::value_object::entity_ref::EntityRef;
+use crate::repo::PersonRepository;
+use crate::TagsApi;
+use std::sync::Mutex;
+
+impl TagsApi {
+    /// Tag an entity like "person:3", returns false if it had the tag already
+    pub fn add(&self, entity: &str, tag: &str) -> Result<bool, String> {
+        let entity = resolve_entity(&self.persons, &self.factions, entity)?;
+        Ok(self.service.lock().unwrap().add_tag(entity, tag))
+    }
+
+    /// Remove a tag from an entity, returns false if it didn't have the tag
+    pub fn remove(&self, entity: &str, tag: &str) -> Result<bool, String> {
+        let entity = resolve_entity(&self.persons, &self.factions, entity)?;
+        Ok(self.service.lock().unwrap().remove_tag(entity, tag))
+    }
+
+    /// Check if an entity has a tag
+    pub fn has(&self, entity: &str, tag: &str) -> Result<bool, String> {
+        let entity = resolve_entity(&self.persons, &self.factions, entity)?;
+        Ok(self.service.lock().unwrap().has_tag(entity, tag))
+    }
+
+    /// Get the tags of an entity in alphabetical order
+    pub fn of(&self, entity: &str) -> Result<Vec<String>, String> {
+        let entity = resolve_entity(&self.persons, &self.factions, entity)?;
+        Ok(self.service.lock().unwrap().tags_of(entity))
+    }
+
+    /// Find all entities with a tag
+    pub fn find(&self, tag: &str) -> Vec<String> {
+        self.service
+            .lock()
+            .unwrap()
+            .find_tagged(tag)
+            .iter()
+            .map(EntityRef::to_string)
+            .collect()
+    }
+}
+
+// Parse an entity reference, refusing entities that don't exist so typos don't go unnoticed
+pub(crate) fn resolve_entity(
+    persons: &Mutex<PersonService<PersonRepository>>,
+    factions: &Mutex<FactionService>,
+    entity: &str,
+) -> Result<EntityRef, String> {
+    let entity: EntityRef = entity.parse()?;
+    match entity {
+        EntityRef::Person(person_id) => persons
+            .lock()
+            .unwrap()
+            .get_person(person_id)
+            .map(|_| entity)
+            .map_err(|_| format!("There is no {}", entity)),
+        EntityRef::Zone(zone_id) => factions
+            .lock()
+            .unwrap()
+            .zone(zone_id)
+            .map(|_| entity)
+            .ok_or_else(|| format!("There is no {}", entity)),
+    }
+}
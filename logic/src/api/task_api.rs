@@ -0,0 +1,124 @@
+use crate::domain::entity::person::PersonId;
+use crate::domain::entity::task::{Task, TaskId, TaskKind};
+use crate::TaskApi;
+use std::collections::HashMap;
+
+impl TaskApi {
+    /// Queue a walk to a tile, at priority 0 and interruptible until prioritized
+    pub fn go_to(&self, person_id: u32, x: i32, y: i32) -> Result<Task, String> {
+        let kind = TaskKind::GoTo {
+            target: self.world.lock().unwrap().location(x, y),
+        };
+        self.queue(person_id, kind)
+    }
+
+    /// Queue work at a tile taking a number of steps once the person got there
+    pub fn work_at(&self, person_id: u32, x: i32, y: i32, steps: u32) -> Result<Task, String> {
+        let kind = TaskKind::WorkAt {
+            location: self.world.lock().unwrap().location(x, y),
+            steps,
+        };
+        self.queue(person_id, kind)
+    }
+
+    /// Queue carrying an item from the tile at x1, y1 to the one at x2, y2
+    pub fn haul(
+        &self,
+        person_id: u32,
+        item: &str,
+        x1: i32,
+        y1: i32,
+        x2: i32,
+        y2: i32,
+    ) -> Result<Task, String> {
+        let world = self.world.lock().unwrap();
+        let kind = TaskKind::Haul {
+            item: item.to_string(),
+            from: world.location(x1, y1),
+            to: world.location(x2, y2),
+        };
+        drop(world);
+        self.queue(person_id, kind)
+    }
+
+    /// Queue walking a route of tiles in a loop, from the last waypoint back to the first, until
+    /// the task is cancelled
+    pub fn patrol(&self, person_id: u32, waypoints: &[(i32, i32)]) -> Result<Task, String> {
+        if waypoints.is_empty() {
+            return Err("A patrol route needs at least one waypoint".to_string());
+        }
+        let world = self.world.lock().unwrap();
+        let kind = TaskKind::Patrol {
+            route: waypoints
+                .iter()
+                .map(|(x, y)| world.location(*x, *y))
+                .collect(),
+        };
+        drop(world);
+        self.queue(person_id, kind)
+    }
+
+    /// Set the priority of a task, higher ones run first, and whether they may interrupt it
+    pub fn prioritize(&self, id: u32, priority: i32, interruptible: bool) -> Result<Task, String> {
+        self.service
+            .lock()
+            .unwrap()
+            .prioritize(TaskId(id), priority, interruptible)
+            .ok_or_else(|| format!("There is no task {}", id))
+    }
+
+    /// Get a queued task by its id, nil once it's done or abandoned
+    pub fn get(&self, id: u32) -> Option<Task> {
+        self.service.lock().unwrap().get(TaskId(id))
+    }
+
+    /// Get the tasks of a person in the order they will run, the running one first
+    pub fn queue_of(&self, person_id: u32) -> Vec<Task> {
+        self.service.lock().unwrap().queue_of(PersonId(person_id))
+    }
+
+    /// Abandon a task, returns false if there is no such task
+    pub fn cancel(&self, id: u32) -> bool {
+        self.service.lock().unwrap().cancel(TaskId(id), "cancelled")
+    }
+
+    // New tasks are of priority 0, anything more urgent may interrupt them
+    fn queue(&self, person_id: u32, kind: TaskKind) -> Result<Task, String> {
+        let person_id = PersonId(person_id);
+        self.persons
+            .lock()
+            .unwrap()
+            .get_person(person_id)
+            .map_err(|e| format!("Failed to get person: {:?}", e))?;
+        Ok(self.service.lock().unwrap().queue(person_id, kind, 0, true))
+    }
+
+    // Work a step on the running task of every person, done by the engine once per simulated
+    // tick, not part of the Lua API. Persons take the portals where that's shorter, the moves of
+    // the step are published as one PersonsMoved.
+    pub fn step(&self) {
+        let locations: HashMap<_, _> = self
+            .persons
+            .lock()
+            .unwrap()
+            .get_all_persons()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|person| (person.id, person.location))
+            .collect();
+        let portals = self.portals.lock().unwrap();
+        let moves = self
+            .service
+            .lock()
+            .unwrap()
+            .step(&locations, |from, to| portals.next_tile(from, to));
+        if moves.is_empty() {
+            return;
+        }
+        // The persons were all there a moment ago
+        let _ = self.persons.lock().unwrap().move_persons(moves.clone());
+        for (person_id, to) in &moves {
+            portals.traverse(*person_id, &locations[person_id], to);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use crate::domain::entity::person::PersonId;
+use crate::domain::entity::vehicle::{Vehicle, VehicleId};
+use crate::domain::service::vehicle_service::VehicleService;
+use crate::domain::service::world_service::WorldService;
+use crate::domain::value_object::location::Location;
+use crate::domain::value_object::map_id::MapId;
+use crate::VehicleApi;
+use std::sync::{Arc, Mutex};
+
+impl VehicleApi {
+    /// Put a vehicle carrying up to `capacity` passengers and items on a tile
+    pub fn create(&self, x: i32, y: i32, capacity: u32) -> Vehicle {
+        let location = self.world.lock().unwrap().location(x, y);
+        self.service
+            .lock()
+            .unwrap()
+            .create(location, capacity as usize)
+    }
+
+    /// Get a vehicle by its id
+    pub fn get(&self, id: u32) -> Option<Vehicle> {
+        self.service.lock().unwrap().get(VehicleId(id))
+    }
+
+    /// Get all vehicles ordered by id
+    pub fn all(&self) -> Vec<Vehicle> {
+        self.service.lock().unwrap().all()
+    }
+
+    /// Make a tile a road that vehicles cross at the cost, 1 being a plain road
+    pub fn set_road(&self, x: i32, y: i32, cost: f64) -> Result<(), String> {
+        let location = self.world.lock().unwrap().location(x, y);
+        self.service.lock().unwrap().set_road(location, cost)
+    }
+
+    /// Make a tile no road anymore, returns false if it wasn't one
+    pub fn clear_road(&self, x: i32, y: i32) -> bool {
+        let location = self.world.lock().unwrap().location(x, y);
+        self.service.lock().unwrap().clear_road(&location)
+    }
+
+    /// Drive a vehicle to a road tile along the cheapest roads, turns cost extra
+    pub fn drive_to(&self, id: u32, x: i32, y: i32) -> Result<Vehicle, String> {
+        let location = self.world.lock().unwrap().location(x, y);
+        self.service
+            .lock()
+            .unwrap()
+            .drive_to(VehicleId(id), location)
+    }
+
+    /// Take a person standing on the tile of a vehicle aboard, it moves along with the vehicle
+    pub fn board(&self, id: u32, person_id: u32) -> Result<Vehicle, String> {
+        let person_id = PersonId(person_id);
+        let location = self
+            .persons
+            .lock()
+            .unwrap()
+            .get_person(person_id)
+            .map_err(|e| format!("Failed to get person: {:?}", e))?
+            .location;
+        self.service
+            .lock()
+            .unwrap()
+            .board(VehicleId(id), person_id, &location)
+    }
+
+    /// Let a passenger off where the vehicle is
+    pub fn alight(&self, id: u32, person_id: u32) -> Result<Vehicle, String> {
+        self.service
+            .lock()
+            .unwrap()
+            .alight(VehicleId(id), PersonId(person_id))
+    }
+
+    /// Load an item into a vehicle
+    pub fn load(&self, id: u32, item: &str) -> Result<Vehicle, String> {
+        self.service.lock().unwrap().load(VehicleId(id), item)
+    }
+
+    /// Unload an item from a vehicle
+    pub fn unload(&self, id: u32, item: &str) -> Result<Vehicle, String> {
+        self.service.lock().unwrap().unload(VehicleId(id), item)
+    }
+
+    // Drive the vehicles for `dt` seconds, done by the engine once per simulated tick, not part
+    // of the Lua API. The passengers' moves of the step are published as one PersonsMoved.
+    pub fn step(&self, dt: f64) {
+        let moves = self.service.lock().unwrap().step(dt);
+        if !moves.is_empty() {
+            // Passengers can't leave, they are still there
+            let _ = self.persons.lock().unwrap().move_persons(moves);
+        }
+    }
+
+    // Handle to the vehicles and roads for the frontends, not part of the Lua API
+    pub fn shared(&self) -> Vehicles {
+        Vehicles {
+            service: self.service.clone(),
+            world: self.world.clone(),
+        }
+    }
+}
+
+/// The vehicles as the frontends draw them, with the roads they take from the map
+#[derive(Clone)]
+pub struct Vehicles {
+    service: Arc<Mutex<VehicleService>>,
+    world: Arc<Mutex<WorldService>>,
+}
+
+impl Vehicles {
+    // Where every vehicle on the active level is in tiles, between two tiles while driving, with
+    // how much it carries
+    pub fn positions(&self) -> Vec<((f32, f32), usize)> {
+        let world = self.world.lock().unwrap();
+        let service = self.service.lock().unwrap();
+        service
+            .all()
+            .iter()
+            .filter(|vehicle| world.shows(&vehicle.location))
+            .map(|vehicle| {
+                let mut position = (vehicle.location.x as f32, vehicle.location.y as f32);
+                if let Some(next) = vehicle.route.front()
+                    && let Some(cost) = service.roads().cost(next)
+                {
+                    let part = (vehicle.progress / cost).min(1.0) as f32;
+                    position.0 += (next.x - vehicle.location.x) as f32 * part;
+                    position.1 += (next.y - vehicle.location.y) as f32 * part;
+                }
+                (position, vehicle.load())
+            })
+            .collect()
+    }
+
+    // Roads are set on the map and level they were taken from, which needn't be the active ones
+    // by now
+    pub fn set_road(&self, map: &str, z: i32, x: i32, y: i32, cost: f64) -> Result<(), String> {
+        let location = Location::on(&MapId::new(map), x, y).at_level(z);
+        self.service.lock().unwrap().set_road(location, cost)
+    }
+
+    pub fn clear_road(&self, map: &str, z: i32, x: i32, y: i32) -> bool {
+        let location = Location::on(&MapId::new(map), x, y).at_level(z);
+        self.service.lock().unwrap().clear_road(&location)
+    }
+}
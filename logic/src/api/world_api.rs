@@ -0,0 +1,69 @@
+use crate::domain::service::world_service::WorldService;
+use crate::WorldApi;
+use std::sync::{Arc, Mutex};
+
+impl WorldApi {
+    /// Add a map, like another space station, empty until persons are moved onto it
+    pub fn create(&self, name: &str) -> Result<String, String> {
+        let map = self.service.lock().unwrap().create(name)?;
+        Ok(map.to_string())
+    }
+
+    /// Make a map the active one, coordinates given to the API are on its ground level from then
+    /// on. Returns false if it was active already.
+    pub fn switch(&self, name: &str) -> Result<bool, String> {
+        self.service.lock().unwrap().switch(name)
+    }
+
+    /// Get the name of the active map
+    pub fn current(&self) -> String {
+        self.service.lock().unwrap().active().to_string()
+    }
+
+    /// Make a level of the active map the active one, 0 being the ground level. Returns false if
+    /// it was active already.
+    pub fn switch_level(&self, z: i32) -> bool {
+        self.service.lock().unwrap().switch_level(z)
+    }
+
+    /// Get the active level of the active map
+    pub fn level(&self) -> i32 {
+        self.service.lock().unwrap().level()
+    }
+
+    /// Get the names of all maps in the order they were created, "main" first
+    pub fn all(&self) -> Vec<String> {
+        let service = self.service.lock().unwrap();
+        service.all().iter().map(|map| map.to_string()).collect()
+    }
+
+    // Handle to the active map for the frontends showing it, not part of the Lua API
+    pub fn shared(&self) -> World {
+        World {
+            service: self.service.clone(),
+        }
+    }
+}
+
+/// Which map and level the frontends show
+#[derive(Clone)]
+pub struct World {
+    service: Arc<Mutex<WorldService>>,
+}
+
+impl World {
+    // Name of the active map
+    pub fn active(&self) -> String {
+        self.service.lock().unwrap().active().to_string()
+    }
+
+    // Active level of the active map
+    pub fn level(&self) -> i32 {
+        self.service.lock().unwrap().level()
+    }
+
+    // Players go up and down the levels of a station from the frontends
+    pub fn switch_level(&self, z: i32) -> bool {
+        self.service.lock().unwrap().switch_level(z)
+    }
+}
@@ -0,0 +1,89 @@
+use crate::domain::event::DomainEvent;
+use std::fmt;
+
+/// First place where two event streams of the same scenario differ
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// Index of the first differing event
+    pub index: usize,
+    /// Event of the first stream at `index`, None when the stream ended there
+    pub left: Option<DomainEvent>,
+    /// Event of the second stream at `index`, None when the stream ended there
+    pub right: Option<DomainEvent>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let describe = |event: &Option<DomainEvent>| match event {
+            Some(event) => format!("{:?}", event),
+            None => "end of stream".to_string(),
+        };
+        write!(
+            f,
+            "Event streams diverge at event {}:\n  first:  {}\n  second: {}",
+            self.index,
+            describe(&self.left),
+            describe(&self.right)
+        )
+    }
+}
+
+/// Compare two event streams, None when they are identical
+pub fn first_divergence(left: &[DomainEvent], right: &[DomainEvent]) -> Option<Divergence> {
+    let index = left
+        .iter()
+        .zip(right)
+        .position(|(left, right)| left != right)
+        .unwrap_or(left.len().min(right.len()));
+    if index == left.len() && index == right.len() {
+        return None;
+    }
+    Some(Divergence {
+        index,
+        left: left.get(index).cloned(),
+        right: right.get(index).cloned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entity::person::PersonId;
+    use crate::domain::event::person_event::PersonEvent;
+    use crate::domain::value_object::location::Location;
+
+    fn created(id: u32, x: i32) -> DomainEvent {
+        DomainEvent::Person(PersonEvent::PersonCreated {
+            person_id: PersonId(id),
+            name: "Alice".to_string(),
+            location: Location::new(x, 0),
+        })
+    }
+
+    #[test]
+    fn test_identical_streams_do_not_diverge() {
+        let events = vec![created(1, 0), created(2, 1)];
+        assert_eq!(first_divergence(&events, &events.clone()), None);
+    }
+
+    #[test]
+    fn test_first_differing_event_is_reported() {
+        let left = vec![created(1, 0), created(2, 1), created(3, 2)];
+        let right = vec![created(1, 0), created(2, 5), created(3, 2)];
+
+        let divergence = first_divergence(&left, &right).unwrap();
+        assert_eq!(divergence.index, 1);
+        assert_eq!(divergence.left, Some(created(2, 1)));
+        assert_eq!(divergence.right, Some(created(2, 5)));
+    }
+
+    #[test]
+    fn test_shorter_stream_diverges_where_it_ends() {
+        let left = vec![created(1, 0), created(2, 1)];
+        let right = vec![created(1, 0)];
+
+        let divergence = first_divergence(&left, &right).unwrap();
+        assert_eq!(divergence.index, 1);
+        assert_eq!(divergence.right, None);
+    }
+}
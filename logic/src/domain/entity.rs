@@ -1 +1,9 @@
+pub(crate) mod faction;
+pub(crate) mod goal;
+pub(crate) mod group;
+pub(crate) mod map_lock;
 pub(crate) mod person;
+pub(crate) mod portal;
+pub(crate) mod task;
+pub(crate) mod vehicle;
+pub(crate) mod zone;
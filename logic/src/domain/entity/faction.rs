@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Who makes the decisions of a faction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FactionKind {
+    Player,
+    Ai,
+    /// Owns what nobody competes for, like settlers in the wild
+    Neutral,
+}
+
+impl fmt::Display for FactionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FactionKind::Player => write!(f, "player"),
+            FactionKind::Ai => write!(f, "ai"),
+            FactionKind::Neutral => write!(f, "neutral"),
+        }
+    }
+}
+
+impl FromStr for FactionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "player" => Ok(FactionKind::Player),
+            "ai" => Ok(FactionKind::Ai),
+            "neutral" => Ok(FactionKind::Neutral),
+            _ => Err(format!(
+                "'{}' is no faction kind, expected player, ai or neutral",
+                s
+            )),
+        }
+    }
+}
+
+/// A side owning persons and zones, the player's business or one of its competitors
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Faction {
+    pub id: String,
+    pub name: String,
+    pub kind: FactionKind,
+    /// Red, green and blue the frontends draw what it owns in
+    pub color: [u8; 3],
+}
@@ -0,0 +1,9 @@
+/// Something the player works towards, complete once its progress reaches the target
+#[derive(Debug, Clone, PartialEq)]
+pub struct Goal {
+    pub id: String,
+    pub name: String,
+    pub progress: f64,
+    pub target: f64,
+    pub completed: bool,
+}
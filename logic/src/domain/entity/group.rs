@@ -0,0 +1,52 @@
+use crate::domain::entity::person::PersonId;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct GroupId(pub u32);
+
+/// What keeps a group together, a person belongs to one group of each kind at most
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GroupKind {
+    /// Persons living together, like a family
+    Household,
+    /// Persons working together
+    Crew,
+}
+
+impl fmt::Display for GroupKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GroupKind::Household => write!(f, "household"),
+            GroupKind::Crew => write!(f, "crew"),
+        }
+    }
+}
+
+impl FromStr for GroupKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "household" => Ok(GroupKind::Household),
+            "crew" => Ok(GroupKind::Crew),
+            _ => Err(format!(
+                "'{}' is no group kind, expected household or crew",
+                s
+            )),
+        }
+    }
+}
+
+/// Persons moving and acting together, a household or a crew
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Group {
+    pub id: GroupId,
+    pub name: String,
+    pub kind: GroupKind,
+    /// Members in the order they joined
+    pub members: Vec<PersonId>,
+    /// Items the members share, None for groups without a shared inventory
+    pub inventory: Option<Vec<String>>,
+}
@@ -0,0 +1,14 @@
+use crate::domain::value_object::region::Region;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct MapLockId(pub u32);
+
+/// A region of a map only its owner may edit the tiles of, like a building site a quest keeps
+/// for itself. Scripts edit as "script", the editors of the frontends as "editor".
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MapLock {
+    pub id: MapLockId,
+    pub region: Region,
+    pub owner: String,
+}
@@ -1,7 +1,8 @@
 use crate::domain::value_object::location::Location;
 use crate::repo::NumericId;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct PersonId(pub u32);
 impl NumericId for PersonId {
     fn value(&self) -> u32 {
@@ -12,7 +13,7 @@ impl NumericId for PersonId {
         PersonId(value)
     }
 }
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Person {
     pub id: PersonId,
     pub name: String,
@@ -0,0 +1,27 @@
+use crate::domain::value_object::location::Location;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PortalId(pub u32);
+
+/// Links two distant tiles, like the docks of two space stations. It works both ways, stepping
+/// through it takes a person from one end to the other at once.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Portal {
+    pub id: PortalId,
+    pub from: Location,
+    pub to: Location,
+}
+
+impl Portal {
+    // The end opposite of a tile, None if the tile is neither end
+    pub fn other_end(&self, location: &Location) -> Option<Location> {
+        if *location == self.from {
+            Some(self.to.clone())
+        } else if *location == self.to {
+            Some(self.from.clone())
+        } else {
+            None
+        }
+    }
+}
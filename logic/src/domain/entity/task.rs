@@ -0,0 +1,53 @@
+use crate::domain::entity::person::PersonId;
+use crate::domain::value_object::location::Location;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TaskId(pub u32);
+
+/// What a task has its person do, each step moves the person a tile or does a bit of work
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum TaskKind {
+    /// Walk to a tile
+    GoTo { target: Location },
+    /// Walk to a tile and work there for a number of steps
+    WorkAt { location: Location, steps: u32 },
+    /// Walk to where an item lies, pick it up and carry it to another tile
+    Haul {
+        item: String,
+        from: Location,
+        to: Location,
+    },
+    /// Walk from waypoint to waypoint and on from the last to the first, until cancelled
+    Patrol { route: Vec<Location> },
+}
+
+impl TaskKind {
+    // Name of the kind in Lua and the DTOs
+    pub fn name(&self) -> &'static str {
+        match self {
+            TaskKind::GoTo { .. } => "go_to",
+            TaskKind::WorkAt { .. } => "work_at",
+            TaskKind::Haul { .. } => "haul",
+            TaskKind::Patrol { .. } => "patrol",
+        }
+    }
+}
+
+/// A job queued for a person, the one of the highest priority runs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Task {
+    pub id: TaskId,
+    pub person_id: PersonId,
+    pub kind: TaskKind,
+    pub priority: i32,
+    /// Whether a task of higher priority may take over, the task resumes once that is done
+    pub interruptible: bool,
+    pub started: bool,
+    /// Steps worked at the location of a work task
+    pub worked: u32,
+    /// Whether a haul task picked up its item
+    pub carrying: bool,
+    /// Index of the waypoint a patrol task walks to
+    pub waypoint: usize,
+}
@@ -0,0 +1,31 @@
+use crate::domain::entity::person::PersonId;
+use crate::domain::value_object::location::Location;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct VehicleId(pub u32);
+
+/// Carries persons and items along the roads, up to its capacity of both together
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Vehicle {
+    pub id: VehicleId,
+    pub location: Location,
+    pub capacity: usize,
+    pub passengers: Vec<PersonId>,
+    pub cargo: Vec<String>,
+    /// Tiles left to drive, the next one first
+    pub route: VecDeque<Location>,
+    /// How far it got towards the next tile, in the cost of that tile
+    pub progress: f64,
+}
+
+impl Vehicle {
+    pub fn load(&self) -> usize {
+        self.passengers.len() + self.cargo.len()
+    }
+
+    pub fn destination(&self) -> Option<&Location> {
+        self.route.back()
+    }
+}
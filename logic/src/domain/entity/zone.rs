@@ -0,0 +1,12 @@
+use crate::domain::value_object::region::Region;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ZoneId(pub u32);
+
+/// Part of the map factions can own, zones may overlap
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Zone {
+    pub id: ZoneId,
+    pub region: Region,
+}
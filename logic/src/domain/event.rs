@@ -1,9 +1,46 @@
+use crate::domain::event::faction_event::FactionEvent;
+use crate::domain::event::fog_event::FogEvent;
+use crate::domain::event::goal_event::GoalEvent;
+use crate::domain::event::group_event::GroupEvent;
+use crate::domain::event::map_event::MapEvent;
 use crate::domain::event::person_event::PersonEvent;
+use crate::domain::event::portal_event::PortalEvent;
+use crate::domain::event::scenario_event::ScenarioEvent;
+use crate::domain::event::stream_event::StreamEvent;
+use crate::domain::event::tag_event::TagEvent;
+use crate::domain::event::task_event::TaskEvent;
+use crate::domain::event::vehicle_event::VehicleEvent;
+use crate::domain::event::world_event::WorldEvent;
+use serde::{Deserialize, Serialize};
 
+pub(crate) mod faction_event;
+pub(crate) mod fog_event;
+pub(crate) mod goal_event;
+pub(crate) mod group_event;
+pub(crate) mod map_event;
 pub(crate) mod person_event;
+pub(crate) mod portal_event;
+pub(crate) mod scenario_event;
+pub(crate) mod stream_event;
+pub(crate) mod tag_event;
+pub(crate) mod task_event;
+pub(crate) mod vehicle_event;
+pub(crate) mod world_event;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum DomainEvent {
     Person(PersonEvent),
+    Tag(TagEvent),
+    Scenario(ScenarioEvent),
+    Goal(GoalEvent),
+    Fog(FogEvent),
+    Faction(FactionEvent),
+    Task(TaskEvent),
+    Vehicle(VehicleEvent),
+    Portal(PortalEvent),
+    World(WorldEvent),
+    Group(GroupEvent),
+    Map(MapEvent),
+    Stream(StreamEvent),
     // Other event types can be added here
 }
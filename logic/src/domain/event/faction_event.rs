@@ -0,0 +1,24 @@
+use crate::domain::entity::faction::FactionKind;
+use crate::domain::entity::zone::ZoneId;
+use crate::domain::value_object::entity_ref::EntityRef;
+use crate::domain::value_object::region::Region;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FactionEvent {
+    FactionCreated {
+        faction: String,
+        kind: FactionKind,
+    },
+    /// A zone was marked out, owned by nobody until it's transferred
+    ZoneCreated {
+        zone: ZoneId,
+        region: Region,
+    },
+    /// An entity changed hands, `None` being nobody
+    OwnershipTransferred {
+        entity: EntityRef,
+        from: Option<String>,
+        to: Option<String>,
+    },
+}
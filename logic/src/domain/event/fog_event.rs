@@ -0,0 +1,16 @@
+use crate::domain::value_object::location::Location;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FogEvent {
+    /// Tiles came into sight of a viewer, explored or not before
+    TilesRevealed {
+        viewer: String,
+        tiles: Vec<Location>,
+    },
+    /// Visible tiles went out of sight, they stay explored
+    TilesHidden {
+        viewer: String,
+        tiles: Vec<Location>,
+    },
+}
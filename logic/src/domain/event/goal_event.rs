@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GoalEvent {
+    GoalProgressed {
+        goal: String,
+        progress: f64,
+        target: f64,
+    },
+    GoalCompleted {
+        goal: String,
+    },
+}
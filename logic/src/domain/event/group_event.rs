@@ -0,0 +1,34 @@
+use crate::domain::entity::group::{GroupId, GroupKind};
+use crate::domain::entity::person::PersonId;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GroupEvent {
+    GroupCreated {
+        group: GroupId,
+        kind: GroupKind,
+        name: String,
+        shared_inventory: bool,
+    },
+    GroupJoined {
+        group: GroupId,
+        person_id: PersonId,
+    },
+    GroupLeft {
+        group: GroupId,
+        person_id: PersonId,
+    },
+    /// The group is gone, its members are on their own again
+    GroupDisbanded {
+        group: GroupId,
+    },
+    /// An item was put into the shared inventory of a group
+    GroupItemStored {
+        group: GroupId,
+        item: String,
+    },
+    GroupItemTaken {
+        group: GroupId,
+        item: String,
+    },
+}
@@ -0,0 +1,25 @@
+use crate::domain::entity::map_lock::MapLockId;
+use crate::domain::value_object::location::Location;
+use crate::domain::value_object::region::Region;
+use serde::{Deserialize, Serialize};
+
+/// Edits of the tiles of the maps, the frontends draw the maps with them applied
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MapEvent {
+    /// A tile was put on a layer, replacing the one there
+    TilePlaced {
+        location: Location,
+        layer: String,
+        tile: u32,
+    },
+    /// A cell of a layer became empty
+    TileRemoved { location: Location, layer: String },
+    /// Only the owner may edit the tiles of the region from now on
+    RegionLocked {
+        lock: MapLockId,
+        region: Region,
+        owner: String,
+    },
+    /// Anyone may edit the tiles of the region of the lock again
+    RegionUnlocked { lock: MapLockId },
+}
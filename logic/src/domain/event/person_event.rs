@@ -1,7 +1,8 @@
 use crate::domain::entity::person::PersonId;
 use crate::domain::value_object::location::Location;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum PersonEvent {
     PersonCreated {
         person_id: PersonId,
@@ -13,4 +14,14 @@ pub enum PersonEvent {
         from_location: Location,
         to_location: Location,
     },
+    /// Many persons moved at once, one event instead of one per person for mass movement
+    PersonsMoved { moves: Vec<PersonMove> },
+}
+
+/// A single move within `PersonsMoved`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PersonMove {
+    pub person_id: PersonId,
+    pub from_location: Location,
+    pub to_location: Location,
 }
@@ -0,0 +1,23 @@
+use crate::domain::entity::person::PersonId;
+use crate::domain::entity::portal::PortalId;
+use crate::domain::value_object::location::Location;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PortalEvent {
+    PortalCreated {
+        portal: PortalId,
+        from_location: Location,
+        to_location: Location,
+    },
+    PortalRemoved {
+        portal: PortalId,
+    },
+    /// A person stepped through a portal, the move itself is published as PersonsMoved
+    PortalTraversed {
+        portal: PortalId,
+        person_id: PersonId,
+        from_location: Location,
+        to_location: Location,
+    },
+}
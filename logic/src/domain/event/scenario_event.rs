@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// How a scenario ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScenarioOutcome {
+    Won,
+    Lost,
+    /// Ended without winner, e.g. when its time ran out
+    Ended,
+}
+
+impl fmt::Display for ScenarioOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioOutcome::Won => write!(f, "won"),
+            ScenarioOutcome::Lost => write!(f, "lost"),
+            ScenarioOutcome::Ended => write!(f, "ended"),
+        }
+    }
+}
+
+impl FromStr for ScenarioOutcome {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "won" => Ok(ScenarioOutcome::Won),
+            "lost" => Ok(ScenarioOutcome::Lost),
+            "ended" => Ok(ScenarioOutcome::Ended),
+            _ => Err(format!(
+                "'{}' is no scenario outcome, use won, lost or ended",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ScenarioEvent {
+    ScenarioEnded {
+        outcome: ScenarioOutcome,
+        reason: String,
+        tick: u64,
+    },
+}
@@ -0,0 +1,16 @@
+use crate::domain::entity::person::{Person, PersonId};
+use crate::domain::value_object::chunk::Chunk;
+use serde::{Deserialize, Serialize};
+
+/// Chunks of the world written to disk to save memory and read back once they're needed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StreamEvent {
+    /// The chunk was written to disk along with the persons in it, which are gone until it's
+    /// loaded again
+    ChunkUnloaded {
+        chunk: Chunk,
+        persons: Vec<PersonId>,
+    },
+    /// The chunk was read back from disk, the persons are where they were unloaded
+    ChunkLoaded { chunk: Chunk, persons: Vec<Person> },
+}
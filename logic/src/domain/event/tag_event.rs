@@ -0,0 +1,24 @@
+use crate::domain::value_object::entity_ref::EntityRef;
+use crate::domain::value_object::meta_value::MetaValue;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TagEvent {
+    TagAdded {
+        entity: EntityRef,
+        tag: String,
+    },
+    TagRemoved {
+        entity: EntityRef,
+        tag: String,
+    },
+    MetadataSet {
+        entity: EntityRef,
+        key: String,
+        value: MetaValue,
+    },
+    MetadataRemoved {
+        entity: EntityRef,
+        key: String,
+    },
+}
@@ -0,0 +1,23 @@
+use crate::domain::entity::person::PersonId;
+use crate::domain::entity::task::TaskId;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TaskEvent {
+    /// A task ran for the first time, resuming after an interruption doesn't start it again
+    TaskStarted {
+        task: TaskId,
+        person_id: PersonId,
+        kind: String,
+    },
+    TaskCompleted {
+        task: TaskId,
+        person_id: PersonId,
+    },
+    /// A task was dropped before it was done
+    TaskAbandoned {
+        task: TaskId,
+        person_id: PersonId,
+        reason: String,
+    },
+}
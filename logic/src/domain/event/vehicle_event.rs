@@ -0,0 +1,42 @@
+use crate::domain::entity::person::PersonId;
+use crate::domain::entity::vehicle::VehicleId;
+use crate::domain::value_object::location::Location;
+use serde::{Deserialize, Serialize};
+
+/// What a vehicle carries, a passenger or an item
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Load {
+    Passenger(PersonId),
+    Item(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum VehicleEvent {
+    VehicleCreated {
+        vehicle: VehicleId,
+        location: Location,
+    },
+    /// A vehicle drove onto the next tile of its route, its passengers move along
+    VehicleMoved {
+        vehicle: VehicleId,
+        from_location: Location,
+        to_location: Location,
+    },
+    VehicleArrived {
+        vehicle: VehicleId,
+        location: Location,
+    },
+    /// The road to the destination of a vehicle is gone, it stopped where it was
+    VehicleStranded {
+        vehicle: VehicleId,
+        location: Location,
+    },
+    VehicleLoaded {
+        vehicle: VehicleId,
+        load: Load,
+    },
+    VehicleUnloaded {
+        vehicle: VehicleId,
+        load: Load,
+    },
+}
@@ -0,0 +1,20 @@
+use crate::domain::value_object::map_id::MapId;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WorldEvent {
+    MapCreated {
+        map: MapId,
+    },
+    /// Coordinates given to the API are on the new map from now on
+    MapSwitched {
+        from: MapId,
+        to: MapId,
+    },
+    /// Coordinates given to the API are on the new level of the active map from now on
+    LevelSwitched {
+        map: MapId,
+        from: i32,
+        to: i32,
+    },
+}
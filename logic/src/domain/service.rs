@@ -1 +1,13 @@
+pub(crate) mod faction_service;
+pub(crate) mod fog_service;
+pub(crate) mod goal_service;
+pub(crate) mod group_service;
+pub(crate) mod map_service;
 pub(crate) mod person_service;
+pub(crate) mod portal_service;
+pub(crate) mod scenario_service;
+pub(crate) mod stream_service;
+pub(crate) mod tag_service;
+pub(crate) mod task_service;
+pub(crate) mod vehicle_service;
+pub(crate) mod world_service;
@@ -0,0 +1,205 @@
+use crate::domain::entity::faction::{Faction, FactionKind};
+use crate::domain::entity::zone::{Zone, ZoneId};
+use crate::domain::event::faction_event::FactionEvent;
+use crate::domain::event::DomainEvent;
+use crate::domain::value_object::entity_ref::EntityRef;
+use crate::domain::value_object::location::Location;
+use crate::domain::value_object::region::Region;
+use crate::infrastructure::event_store::{publish_event, EventSender};
+use std::collections::{BTreeMap, HashMap};
+
+/// Faction of the player, also the viewer of the fog the frontends show
+pub const PLAYER_FACTION: &str = "player";
+/// Faction owning what no business competes for
+pub const NEUTRAL_FACTION: &str = "neutral";
+
+/// Factions in the order they were founded, the zones and who owns which entity
+pub struct FactionService {
+    factions: Vec<Faction>,
+    zones: BTreeMap<ZoneId, Zone>,
+    owners: HashMap<EntityRef, String>,
+    next_zone_id: u32,
+    event_sender: EventSender,
+}
+
+impl FactionService {
+    // Starts with the player and the neutral faction, which exist in every game
+    pub fn new(event_sender: impl Into<EventSender>) -> Self {
+        FactionService {
+            factions: vec![
+                Faction {
+                    id: PLAYER_FACTION.to_string(),
+                    name: "Player".to_string(),
+                    kind: FactionKind::Player,
+                    color: [64, 128, 255],
+                },
+                Faction {
+                    id: NEUTRAL_FACTION.to_string(),
+                    name: "Neutral".to_string(),
+                    kind: FactionKind::Neutral,
+                    color: [160, 160, 160],
+                },
+            ],
+            zones: BTreeMap::new(),
+            owners: HashMap::new(),
+            next_zone_id: 1,
+            event_sender: event_sender.into(),
+        }
+    }
+
+    // Found a faction, fails if there is one with the same id
+    pub fn create(
+        &mut self,
+        id: &str,
+        name: &str,
+        kind: FactionKind,
+        color: [u8; 3],
+    ) -> Result<Faction, String> {
+        if self.get(id).is_some() {
+            return Err(format!("Faction {} already exists", id));
+        }
+        let faction = Faction {
+            id: id.to_string(),
+            name: name.to_string(),
+            kind,
+            color,
+        };
+        self.factions.push(faction.clone());
+        self.publish(FactionEvent::FactionCreated {
+            faction: faction.id.clone(),
+            kind,
+        });
+        Ok(faction)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Faction> {
+        self.factions
+            .iter()
+            .find(|faction| faction.id == id)
+            .cloned()
+    }
+
+    pub fn all(&self) -> Vec<Faction> {
+        self.factions.clone()
+    }
+
+    // Mark out a zone nobody owns yet
+    pub fn create_zone(&mut self, region: Region) -> Zone {
+        let zone = Zone {
+            id: ZoneId(self.next_zone_id),
+            region,
+        };
+        self.next_zone_id += 1;
+        self.zones.insert(zone.id, zone.clone());
+        self.publish(FactionEvent::ZoneCreated {
+            zone: zone.id,
+            region: zone.region.clone(),
+        });
+        zone
+    }
+
+    pub fn zone(&self, id: ZoneId) -> Option<Zone> {
+        self.zones.get(&id).cloned()
+    }
+
+    // Zones ordered by id
+    pub fn zones(&self) -> Vec<Zone> {
+        self.zones.values().cloned().collect()
+    }
+
+    // Zones containing the tile, ordered by id
+    pub fn zones_at(&self, location: &Location) -> Vec<Zone> {
+        self.zones
+            .values()
+            .filter(|zone| zone.region.contains(location))
+            .cloned()
+            .collect()
+    }
+
+    // Hand an entity to a faction, or to nobody with None, emitting OwnershipTransferred only if
+    // the owner changed. The entity has to exist, that's up to the caller.
+    pub fn transfer(&mut self, entity: EntityRef, to: Option<&str>) -> Result<bool, String> {
+        if let Some(faction) = to
+            && self.get(faction).is_none()
+        {
+            return Err(format!("There is no faction {}", faction));
+        }
+        let from = self.owners.get(&entity).cloned();
+        if from.as_deref() == to {
+            return Ok(false);
+        }
+        match to {
+            Some(faction) => self.owners.insert(entity, faction.to_string()),
+            None => self.owners.remove(&entity),
+        };
+        self.publish(FactionEvent::OwnershipTransferred {
+            entity,
+            from,
+            to: to.map(str::to_string),
+        });
+        Ok(true)
+    }
+
+    pub fn owner(&self, entity: EntityRef) -> Option<String> {
+        self.owners.get(&entity).cloned()
+    }
+
+    // Entities a faction owns, ordered by kind and id
+    pub fn owned_by(&self, faction: &str) -> Vec<EntityRef> {
+        let mut entities: Vec<_> = self
+            .owners
+            .iter()
+            .filter(|(_, owner)| *owner == faction)
+            .map(|(entity, _)| *entity)
+            .collect();
+        entities.sort();
+        entities
+    }
+
+    fn publish(&self, event: FactionEvent) {
+        publish_event(&self.event_sender, DomainEvent::Faction(event));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entity::person::PersonId;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_ownership_changes_hands_between_factions() {
+        let (sender, receiver) = mpsc::channel();
+        let mut service = FactionService::new(sender);
+        service
+            .create("rival", "Rival Corp", FactionKind::Ai, [255, 0, 0])
+            .unwrap();
+        assert!(service
+            .create("rival", "Again", FactionKind::Ai, [0, 0, 0])
+            .is_err());
+        let zone = service.create_zone(Region::new(0, 0, 3, 3));
+        assert_eq!(service.zones_at(&Location::new(2, 1)), vec![zone.clone()]);
+
+        let alice = EntityRef::Person(PersonId(0));
+        let zone_ref = EntityRef::Zone(zone.id);
+        assert!(service.transfer(alice, Some(PLAYER_FACTION)).unwrap());
+        assert!(!service.transfer(alice, Some(PLAYER_FACTION)).unwrap());
+        assert!(service.transfer(zone_ref, Some("rival")).unwrap());
+        assert!(service.transfer(alice, Some("nobody")).is_err());
+        assert!(service.transfer(alice, Some("rival")).unwrap());
+        assert_eq!(service.owned_by("rival"), vec![alice, zone_ref]);
+        assert!(service.transfer(zone_ref, None).unwrap());
+        assert_eq!(service.owner(zone_ref), None);
+
+        let events: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(events.len(), 6);
+        assert_eq!(
+            events[4],
+            DomainEvent::Faction(FactionEvent::OwnershipTransferred {
+                entity: alice,
+                from: Some(PLAYER_FACTION.to_string()),
+                to: Some("rival".to_string()),
+            })
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use crate::domain::event::fog_event::FogEvent;
+use crate::domain::event::DomainEvent;
+use crate::domain::value_object::location::Location;
+use crate::domain::value_object::region::Region;
+use crate::domain::value_object::visibility::Visibility;
+use crate::infrastructure::event_store::{publish_event, EventSender};
+use std::collections::HashMap;
+
+/// Viewer the frontends show the fog of, factions can have their own
+pub const PLAYER_VIEWER: &str = "player";
+
+// Most tiles revealed or hidden at once, every tile may end up in the event
+const MAX_REGION_AREA: u64 = 256 * 256;
+
+/// Explored and visible tiles of every viewer, tiles a viewer never saw aren't stored
+pub struct FogService {
+    // Explored tiles of a viewer with whether they are visible right now
+    viewers: HashMap<String, HashMap<Location, bool>>,
+    event_sender: EventSender,
+}
+
+impl FogService {
+    pub fn new(event_sender: impl Into<EventSender>) -> Self {
+        FogService {
+            viewers: HashMap::new(),
+            event_sender: event_sender.into(),
+        }
+    }
+
+    // Make the tiles of the region visible to the viewer, returns how many weren't before
+    pub fn reveal(&mut self, viewer: &str, region: &Region) -> Result<usize, String> {
+        check_area(region)?;
+        let tiles = self.viewers.entry(viewer.to_string()).or_default();
+        let revealed: Vec<Location> = region
+            .locations()
+            .filter(|location| !tiles.insert(location.clone(), true).unwrap_or(false))
+            .collect();
+        let count = revealed.len();
+        if count > 0 {
+            let event = FogEvent::TilesRevealed {
+                viewer: viewer.to_string(),
+                tiles: revealed,
+            };
+            publish_event(&self.event_sender, DomainEvent::Fog(event));
+        }
+        Ok(count)
+    }
+
+    // Take the visible tiles of the region out of the viewer's sight, returns how many there were
+    pub fn hide(&mut self, viewer: &str, region: &Region) -> Result<usize, String> {
+        check_area(region)?;
+        let Some(tiles) = self.viewers.get_mut(viewer) else {
+            return Ok(0);
+        };
+        let hidden: Vec<Location> = region
+            .locations()
+            .filter(|location| {
+                tiles
+                    .get_mut(location)
+                    .is_some_and(|visible| std::mem::replace(visible, false))
+            })
+            .collect();
+        let count = hidden.len();
+        if count > 0 {
+            let event = FogEvent::TilesHidden {
+                viewer: viewer.to_string(),
+                tiles: hidden,
+            };
+            publish_event(&self.event_sender, DomainEvent::Fog(event));
+        }
+        Ok(count)
+    }
+
+    pub fn visibility(&self, viewer: &str, location: &Location) -> Visibility {
+        match self
+            .viewers
+            .get(viewer)
+            .and_then(|tiles| tiles.get(location))
+        {
+            Some(true) => Visibility::Visible,
+            Some(false) => Visibility::Explored,
+            None => Visibility::Unexplored,
+        }
+    }
+
+    // Whether the viewer explored anything yet, viewers without fog see everything
+    pub fn has_viewer(&self, viewer: &str) -> bool {
+        self.viewers.contains_key(viewer)
+    }
+
+    pub fn explored_count(&self, viewer: &str) -> usize {
+        self.viewers.get(viewer).map_or(0, |tiles| tiles.len())
+    }
+}
+
+fn check_area(region: &Region) -> Result<(), String> {
+    if region.area() > MAX_REGION_AREA {
+        return Err(format!(
+            "Region of {} tiles is too big, at most {} tiles at once",
+            region.area(),
+            MAX_REGION_AREA
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_revealed_tiles_stay_explored_when_hidden() {
+        let (sender, receiver) = mpsc::channel();
+        let mut service = FogService::new(sender);
+        let tile = Location::new(1, 1);
+        assert!(!service.has_viewer(PLAYER_VIEWER));
+        assert_eq!(
+            service.visibility(PLAYER_VIEWER, &tile),
+            Visibility::Unexplored
+        );
+
+        assert_eq!(
+            service.reveal(PLAYER_VIEWER, &Region::new(0, 0, 1, 1)),
+            Ok(4)
+        );
+        assert_eq!(
+            service.reveal(PLAYER_VIEWER, &Region::new(1, 1, 2, 1)),
+            Ok(1)
+        );
+        assert_eq!(
+            service.visibility(PLAYER_VIEWER, &tile),
+            Visibility::Visible
+        );
+        assert_eq!(service.hide(PLAYER_VIEWER, &Region::new(1, 0, 5, 5)), Ok(3));
+        assert_eq!(
+            service.visibility(PLAYER_VIEWER, &tile),
+            Visibility::Explored
+        );
+        assert_eq!(service.explored_count(PLAYER_VIEWER), 5);
+        assert_eq!(service.visibility("pirates", &tile), Visibility::Unexplored);
+        assert!(service
+            .reveal(PLAYER_VIEWER, &Region::new(0, 0, 1000, 1000))
+            .is_err());
+
+        let events: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(events.len(), 3);
+        assert_eq!(
+            events[1],
+            DomainEvent::Fog(FogEvent::TilesRevealed {
+                viewer: PLAYER_VIEWER.to_string(),
+                tiles: vec![Location::new(2, 1)],
+            })
+        );
+    }
+}
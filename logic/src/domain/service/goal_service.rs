@@ -0,0 +1,122 @@
+use crate::domain::entity::goal::Goal;
+use crate::domain::event::goal_event::GoalEvent;
+use crate::domain::event::DomainEvent;
+use crate::infrastructure::event_store::{publish_event, EventSender};
+
+/// Goals of the mods in the order they were defined, with their progress
+pub struct GoalService {
+    goals: Vec<Goal>,
+    event_sender: EventSender,
+}
+
+impl GoalService {
+    pub fn new(event_sender: impl Into<EventSender>) -> Self {
+        GoalService {
+            goals: Vec::new(),
+            event_sender: event_sender.into(),
+        }
+    }
+
+    // Define a goal without progress, fails if there is one with the same id
+    pub fn define(&mut self, id: &str, name: &str, target: f64) -> Result<Goal, String> {
+        if !target.is_finite() {
+            return Err(format!("Goal {} can't have {} as target", id, target));
+        }
+        if self.get(id).is_some() {
+            return Err(format!("Goal {} is already defined", id));
+        }
+        let goal = Goal {
+            id: id.to_string(),
+            name: name.to_string(),
+            progress: 0.0,
+            target,
+            completed: false,
+        };
+        self.goals.push(goal.clone());
+        Ok(goal)
+    }
+
+    // Set the progress of a goal, emitting GoalProgressed when it changed and GoalCompleted when
+    // it reached the target. Completed goals keep their progress.
+    pub fn set_progress(&mut self, id: &str, progress: f64) -> Result<Goal, String> {
+        if !progress.is_finite() {
+            return Err(format!("Goal {} can't progress to {}", id, progress));
+        }
+        let goal = self
+            .goals
+            .iter_mut()
+            .find(|goal| goal.id == id)
+            .ok_or_else(|| format!("Goal {} is not defined", id))?;
+        if goal.completed || goal.progress == progress {
+            return Ok(goal.clone());
+        }
+
+        goal.progress = progress;
+        let mut events = vec![GoalEvent::GoalProgressed {
+            goal: goal.id.clone(),
+            progress,
+            target: goal.target,
+        }];
+        if progress >= goal.target {
+            goal.completed = true;
+            events.push(GoalEvent::GoalCompleted {
+                goal: goal.id.clone(),
+            });
+        }
+        let goal = goal.clone();
+        for event in events {
+            publish_event(&self.event_sender, DomainEvent::Goal(event));
+        }
+        Ok(goal)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Goal> {
+        self.goals.iter().find(|goal| goal.id == id).cloned()
+    }
+
+    pub fn all(&self) -> Vec<Goal> {
+        self.goals.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_goals_complete_once_at_their_target() {
+        let (sender, receiver) = mpsc::channel();
+        let mut service = GoalService::new(sender);
+        service.define("town", "Grow a town", 10.0).unwrap();
+        assert!(service.define("town", "Again", 5.0).is_err());
+        assert!(service.set_progress("village", 1.0).is_err());
+
+        service.set_progress("town", 4.0).unwrap();
+        service.set_progress("town", 4.0).unwrap();
+        let goal = service.set_progress("town", 12.0).unwrap();
+        assert!(goal.completed);
+        let goal = service.set_progress("town", 3.0).unwrap();
+        assert_eq!(goal.progress, 12.0);
+
+        let events: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(
+            events,
+            vec![
+                DomainEvent::Goal(GoalEvent::GoalProgressed {
+                    goal: "town".to_string(),
+                    progress: 4.0,
+                    target: 10.0,
+                }),
+                DomainEvent::Goal(GoalEvent::GoalProgressed {
+                    goal: "town".to_string(),
+                    progress: 12.0,
+                    target: 10.0,
+                }),
+                DomainEvent::Goal(GoalEvent::GoalCompleted {
+                    goal: "town".to_string(),
+                }),
+            ]
+        );
+    }
+}
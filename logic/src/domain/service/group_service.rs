@@ -0,0 +1,194 @@
+use crate::domain::entity::group::{Group, GroupId, GroupKind};
+use crate::domain::entity::person::PersonId;
+use crate::domain::event::group_event::GroupEvent;
+use crate::domain::event::DomainEvent;
+use crate::infrastructure::event_store::{publish_event, EventSender};
+use std::collections::BTreeMap;
+
+/// The households and crews, and who belongs to which
+pub struct GroupService {
+    groups: BTreeMap<GroupId, Group>,
+    next_id: u32,
+    event_sender: EventSender,
+}
+
+impl GroupService {
+    pub fn new(event_sender: impl Into<EventSender>) -> Self {
+        GroupService {
+            groups: BTreeMap::new(),
+            next_id: 1,
+            event_sender: event_sender.into(),
+        }
+    }
+
+    // A group starts out without members
+    pub fn create(&mut self, name: &str, kind: GroupKind, shared_inventory: bool) -> Group {
+        let group = Group {
+            id: GroupId(self.next_id),
+            name: name.to_string(),
+            kind,
+            members: Vec::new(),
+            inventory: shared_inventory.then(Vec::new),
+        };
+        self.next_id += 1;
+        self.groups.insert(group.id, group.clone());
+        self.publish(GroupEvent::GroupCreated {
+            group: group.id,
+            kind,
+            name: group.name.clone(),
+            shared_inventory,
+        });
+        group
+    }
+
+    pub fn get(&self, id: GroupId) -> Option<Group> {
+        self.groups.get(&id).cloned()
+    }
+
+    // Groups ordered by id
+    pub fn all(&self) -> Vec<Group> {
+        self.groups.values().cloned().collect()
+    }
+
+    // Group of the kind the person belongs to, if any
+    pub fn group_of(&self, person_id: PersonId, kind: GroupKind) -> Option<GroupId> {
+        self.groups
+            .values()
+            .find(|group| group.kind == kind && group.members.contains(&person_id))
+            .map(|group| group.id)
+    }
+
+    // Add a person to a group, unless the person is in another group of its kind
+    pub fn join(&mut self, id: GroupId, person_id: PersonId) -> Result<Group, String> {
+        let kind = self.groups.get(&id).ok_or_else(|| no_group(id))?.kind;
+        match self.group_of(person_id, kind) {
+            Some(other) if other == id => {
+                return Err(format!(
+                    "Person {} is in group {} already",
+                    person_id.0, id.0
+                ))
+            }
+            Some(other) => {
+                return Err(format!(
+                    "Person {} is in the {} {} already",
+                    person_id.0, kind, other.0
+                ))
+            }
+            None => {}
+        }
+        self.groups.get_mut(&id).unwrap().members.push(person_id);
+        self.publish(GroupEvent::GroupJoined {
+            group: id,
+            person_id,
+        });
+        Ok(self.groups[&id].clone())
+    }
+
+    pub fn leave(&mut self, id: GroupId, person_id: PersonId) -> Result<Group, String> {
+        let group = self.groups.get_mut(&id).ok_or_else(|| no_group(id))?;
+        let index = group
+            .members
+            .iter()
+            .position(|member| *member == person_id)
+            .ok_or_else(|| format!("Person {} isn't in group {}", person_id.0, id.0))?;
+        group.members.remove(index);
+        self.publish(GroupEvent::GroupLeft {
+            group: id,
+            person_id,
+        });
+        Ok(self.groups[&id].clone())
+    }
+
+    // Dissolve a group along with its inventory, returns false if there is no such group
+    pub fn disband(&mut self, id: GroupId) -> bool {
+        if self.groups.remove(&id).is_none() {
+            return false;
+        }
+        self.publish(GroupEvent::GroupDisbanded { group: id });
+        true
+    }
+
+    pub fn store(&mut self, id: GroupId, item: &str) -> Result<Group, String> {
+        self.inventory(id)?.push(item.to_string());
+        self.publish(GroupEvent::GroupItemStored {
+            group: id,
+            item: item.to_string(),
+        });
+        Ok(self.groups[&id].clone())
+    }
+
+    pub fn take(&mut self, id: GroupId, item: &str) -> Result<Group, String> {
+        let inventory = self.inventory(id)?;
+        let index = inventory
+            .iter()
+            .position(|stored| stored == item)
+            .ok_or_else(|| format!("Group {} has no {}", id.0, item))?;
+        inventory.remove(index);
+        self.publish(GroupEvent::GroupItemTaken {
+            group: id,
+            item: item.to_string(),
+        });
+        Ok(self.groups[&id].clone())
+    }
+
+    // The shared inventory of the group, unless it has none
+    fn inventory(&mut self, id: GroupId) -> Result<&mut Vec<String>, String> {
+        self.groups
+            .get_mut(&id)
+            .ok_or_else(|| no_group(id))?
+            .inventory
+            .as_mut()
+            .ok_or_else(|| format!("Group {} shares no inventory", id.0))
+    }
+
+    fn publish(&self, event: GroupEvent) {
+        publish_event(&self.event_sender, DomainEvent::Group(event));
+    }
+}
+
+fn no_group(id: GroupId) -> String {
+    format!("There is no group {}", id.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_persons_belong_to_one_group_of_each_kind() {
+        let (sender, receiver) = mpsc::channel();
+        let mut service = GroupService::new(sender);
+        let smiths = service.create("Smiths", GroupKind::Household, true);
+        let jones = service.create("Joneses", GroupKind::Household, false);
+        let miners = service.create("Miners", GroupKind::Crew, false);
+        let ann = PersonId(0);
+
+        service.join(smiths.id, ann).unwrap();
+        assert!(service.join(smiths.id, ann).is_err());
+        assert!(service.join(jones.id, ann).is_err());
+        service.join(miners.id, ann).unwrap();
+        assert_eq!(service.group_of(ann, GroupKind::Crew), Some(miners.id));
+
+        service.store(smiths.id, "bread").unwrap();
+        assert!(service.store(jones.id, "bread").is_err());
+        assert!(service.take(smiths.id, "gold").is_err());
+        let smiths = service.take(smiths.id, "bread").unwrap();
+        assert_eq!(smiths.inventory, Some(Vec::new()));
+
+        service.leave(smiths.id, ann).unwrap();
+        assert!(service.leave(smiths.id, ann).is_err());
+        service.join(jones.id, ann).unwrap();
+        assert!(service.disband(miners.id));
+        assert!(!service.disband(miners.id));
+        assert_eq!(service.group_of(ann, GroupKind::Crew), None);
+
+        let events: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(
+            events.last(),
+            Some(&DomainEvent::Group(GroupEvent::GroupDisbanded {
+                group: miners.id
+            }))
+        );
+    }
+}
@@ -0,0 +1,290 @@
+use crate::domain::entity::map_lock::{MapLock, MapLockId};
+use crate::domain::event::map_event::MapEvent;
+use crate::domain::event::DomainEvent;
+use crate::domain::value_object::location::Location;
+use crate::domain::value_object::region::Region;
+use crate::infrastructure::event_store::{publish_event, EventSender};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Layer edits go to when none is given, the bottom one of the frontends
+pub const DEFAULT_LAYER: &str = "ground";
+
+/// Who the edits of the scripts are made by
+pub const SCRIPT_EDITOR: &str = "script";
+
+/// Who the edits of the editors of the frontends are made by, remote peers use their own names
+pub const UI_EDITOR: &str = "editor";
+
+/// What a cell of a layer holds after the edits, None once its tile was removed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TileChange {
+    pub location: Location,
+    pub layer: String,
+    pub tile: Option<u32>,
+}
+
+/// The edits of the tiles, made from the scripts and the frontends alike. The maps themselves
+/// are the frontends', which apply the edits as they take them.
+///
+/// Edits are applied in the order they reach the service, the order of their events, so the last
+/// edit of a cell wins. Edits of a locked region by anyone but the owner of the lock are rejected
+/// with the reason instead, leaving every cell of the edit as it was.
+pub struct MapService {
+    // Tile of every cell edited so far
+    edits: HashMap<(String, Location), Option<u32>>,
+    // Cells edited since the changes were taken last
+    changed: HashSet<(String, Location)>,
+    locks: BTreeMap<MapLockId, MapLock>,
+    next_lock: u32,
+    event_sender: EventSender,
+}
+
+impl MapService {
+    pub fn new(event_sender: impl Into<EventSender>) -> Self {
+        MapService {
+            edits: HashMap::new(),
+            changed: HashSet::new(),
+            locks: BTreeMap::new(),
+            next_lock: 1,
+            event_sender: event_sender.into(),
+        }
+    }
+
+    pub fn place(
+        &mut self,
+        editor: &str,
+        layer: &str,
+        location: Location,
+        tile: u32,
+    ) -> Result<(), String> {
+        self.place_all(editor, layer, vec![location], tile)
+    }
+
+    // Put the tile on every cell given, or on none of them if a cell is locked
+    pub fn place_all(
+        &mut self,
+        editor: &str,
+        layer: &str,
+        locations: Vec<Location>,
+        tile: u32,
+    ) -> Result<(), String> {
+        for location in &locations {
+            self.check(editor, location)?;
+        }
+        for location in locations {
+            self.edit(layer, &location, Some(tile));
+            self.publish(MapEvent::TilePlaced {
+                location,
+                layer: layer.to_string(),
+                tile,
+            });
+        }
+        Ok(())
+    }
+
+    // Empty a cell, the service can't tell whether the map had a tile there
+    pub fn remove(&mut self, editor: &str, layer: &str, location: Location) -> Result<(), String> {
+        self.check(editor, &location)?;
+        self.edit(layer, &location, None);
+        self.publish(MapEvent::TileRemoved {
+            location,
+            layer: layer.to_string(),
+        });
+        Ok(())
+    }
+
+    // Whether the editor may edit a cell, the reason if not
+    pub fn check(&self, editor: &str, location: &Location) -> Result<(), String> {
+        match self.lock_at(location) {
+            Some(lock) if lock.owner != editor => Err(format!(
+                "Tile {},{} is locked by {} (lock {})",
+                location.x, location.y, lock.owner, lock.id.0
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // Keep a region for its owner, unless another owner locked a part of it already
+    pub fn lock(&mut self, owner: &str, region: Region) -> Result<MapLock, String> {
+        if let Some(other) = self
+            .locks
+            .values()
+            .find(|lock| lock.owner != owner && lock.region.overlaps(&region))
+        {
+            return Err(format!(
+                "The region overlaps lock {} of {}",
+                other.id.0, other.owner
+            ));
+        }
+        let lock = MapLock {
+            id: MapLockId(self.next_lock),
+            region,
+            owner: owner.to_string(),
+        };
+        self.next_lock += 1;
+        self.locks.insert(lock.id, lock.clone());
+        self.publish(MapEvent::RegionLocked {
+            lock: lock.id,
+            region: lock.region.clone(),
+            owner: lock.owner.clone(),
+        });
+        Ok(lock)
+    }
+
+    // Returns false if there is no such lock
+    pub fn unlock(&mut self, id: MapLockId) -> bool {
+        if self.locks.remove(&id).is_none() {
+            return false;
+        }
+        self.publish(MapEvent::RegionUnlocked { lock: id });
+        true
+    }
+
+    pub fn lock_at(&self, location: &Location) -> Option<&MapLock> {
+        self.locks
+            .values()
+            .find(|lock| lock.region.contains(location))
+    }
+
+    // Locks ordered by id
+    pub fn locks(&self) -> Vec<MapLock> {
+        self.locks.values().cloned().collect()
+    }
+
+    // The cells edited since the last call with what they hold now, ordered by map, level, row,
+    // column and layer
+    pub fn take_changes(&mut self) -> Vec<TileChange> {
+        let changed: Vec<_> = self.changed.drain().collect();
+        sorted(
+            changed
+                .into_iter()
+                .map(|key| {
+                    let tile = self.edits[&key];
+                    (key, tile)
+                })
+                .collect(),
+        )
+    }
+
+    // Every cell edited so far, ordered like `take_changes`
+    pub fn edits(&self) -> Vec<TileChange> {
+        sorted(
+            self.edits
+                .iter()
+                .map(|(key, tile)| (key.clone(), *tile))
+                .collect(),
+        )
+    }
+
+    fn edit(&mut self, layer: &str, location: &Location, tile: Option<u32>) {
+        let key = (layer.to_string(), location.clone());
+        self.edits.insert(key.clone(), tile);
+        self.changed.insert(key);
+    }
+
+    fn publish(&self, event: MapEvent) {
+        publish_event(&self.event_sender, DomainEvent::Map(event));
+    }
+}
+
+fn sorted(cells: Vec<((String, Location), Option<u32>)>) -> Vec<TileChange> {
+    let mut changes: Vec<_> = cells
+        .into_iter()
+        .map(|((layer, location), tile)| TileChange {
+            location,
+            layer,
+            tile,
+        })
+        .collect();
+    changes.sort_by(|a, b| {
+        let key = |change: &TileChange| {
+            let location = &change.location;
+            (location.map.to_string(), location.z, location.y, location.x)
+        };
+        key(a).cmp(&key(b)).then_with(|| a.layer.cmp(&b.layer))
+    });
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_changes_hold_the_latest_edit_of_each_cell() {
+        let (sender, receiver) = mpsc::channel();
+        let mut service = MapService::new(sender);
+        service
+            .place(UI_EDITOR, DEFAULT_LAYER, Location::new(3, 1), 7)
+            .unwrap();
+        service
+            .place(SCRIPT_EDITOR, "objects", Location::new(3, 1), 9)
+            .unwrap();
+        service
+            .place(UI_EDITOR, DEFAULT_LAYER, Location::new(0, 2), 7)
+            .unwrap();
+        service
+            .remove(SCRIPT_EDITOR, DEFAULT_LAYER, Location::new(0, 2))
+            .unwrap();
+
+        let changes = service.take_changes();
+        assert_eq!(
+            changes,
+            vec![
+                TileChange {
+                    location: Location::new(3, 1),
+                    layer: DEFAULT_LAYER.to_string(),
+                    tile: Some(7),
+                },
+                TileChange {
+                    location: Location::new(3, 1),
+                    layer: "objects".to_string(),
+                    tile: Some(9),
+                },
+                TileChange {
+                    location: Location::new(0, 2),
+                    layer: DEFAULT_LAYER.to_string(),
+                    tile: None,
+                },
+            ]
+        );
+        assert!(service.take_changes().is_empty());
+        assert_eq!(service.edits(), changes);
+        assert_eq!(receiver.try_iter().count(), 4);
+    }
+
+    #[test]
+    fn test_locked_regions_reject_the_edits_of_others() {
+        let (sender, receiver) = mpsc::channel();
+        let mut service = MapService::new(sender);
+        let lock = service.lock("quest", Region::new(0, 0, 4, 4)).unwrap();
+        assert!(service.lock(UI_EDITOR, Region::new(4, 4, 8, 8)).is_err());
+
+        let inside = vec![Location::new(5, 5), Location::new(4, 4)];
+        assert_eq!(
+            service.place_all(UI_EDITOR, DEFAULT_LAYER, inside.clone(), 7),
+            Err("Tile 4,4 is locked by quest (lock 1)".to_string())
+        );
+        assert!(service.take_changes().is_empty());
+        service
+            .place_all("quest", DEFAULT_LAYER, inside, 7)
+            .unwrap();
+        assert!(service
+            .remove(SCRIPT_EDITOR, DEFAULT_LAYER, Location::new(4, 4))
+            .is_err());
+
+        assert!(service.unlock(lock.id));
+        assert!(!service.unlock(lock.id));
+        service
+            .remove(SCRIPT_EDITOR, DEFAULT_LAYER, Location::new(4, 4))
+            .unwrap();
+        let events: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(events.len(), 5);
+        assert_eq!(
+            events[3],
+            DomainEvent::Map(MapEvent::RegionUnlocked { lock: lock.id })
+        );
+    }
+}
@@ -1,22 +1,22 @@
 use crate::domain::entity::person::Person;
 use crate::domain::entity::person::PersonId;
-use crate::domain::event::person_event::PersonEvent;
+use crate::domain::event::person_event::{PersonEvent, PersonMove};
 use crate::domain::event::DomainEvent;
 use crate::domain::value_object::location::Location;
-use crate::infrastructure::event_store::publish_event;
+use crate::domain::value_object::region::Region;
+use crate::infrastructure::event_store::{publish_event, EventSender};
 use crate::repo::Repository;
-use std::sync::mpsc::Sender;
 
 pub struct PersonService<R: Repository<PersonId, Person>> {
     repository: R,
-    event_sender: Sender<DomainEvent>,
+    event_sender: EventSender,
 }
 
 impl<R: Repository<PersonId, Person>> PersonService<R> {
-    pub fn new(repository: R, event_sender: Sender<DomainEvent>) -> Self {
+    pub fn new(repository: R, event_sender: impl Into<EventSender>) -> Self {
         PersonService {
             repository,
-            event_sender,
+            event_sender: event_sender.into(),
         }
     }
 
@@ -73,6 +73,64 @@ impl<R: Repository<PersonId, Person>> PersonService<R> {
         Ok(updated_person)
     }
 
+    // Move many persons and emit a single PersonsMoved event. Nobody moves if any of the persons
+    // doesn't exist. A person may move several times, each move starting where the last ended.
+    pub fn move_persons(
+        &mut self,
+        moves: Vec<(PersonId, Location)>,
+    ) -> Result<Vec<Person>, R::Error> {
+        for (person_id, _) in &moves {
+            self.repository.get(*person_id)?;
+        }
+
+        let mut moved = Vec::with_capacity(moves.len());
+        let mut updated_persons = Vec::with_capacity(moves.len());
+        for (person_id, new_location) in moves {
+            let current_person = self.repository.get(person_id)?;
+            let updated_person = Person {
+                location: new_location.clone(),
+                ..current_person
+            };
+            self.repository.update(person_id, updated_person.clone())?;
+            moved.push(PersonMove {
+                person_id,
+                from_location: current_person.location,
+                to_location: new_location,
+            });
+            updated_persons.push(updated_person);
+        }
+
+        if !moved.is_empty() {
+            let event = PersonEvent::PersonsMoved { moves: moved };
+            publish_event(&self.event_sender, DomainEvent::Person(event));
+        }
+
+        Ok(updated_persons)
+    }
+
+    // Take the persons in the region out of the repository without an event, for writing them
+    // to disk along with their chunk
+    pub fn take_persons_in(&mut self, region: &Region) -> Result<Vec<Person>, R::Error> {
+        let persons: Vec<Person> = self
+            .repository
+            .get_all()?
+            .into_iter()
+            .filter(|person| region.contains(&person.location))
+            .collect();
+        for person in &persons {
+            self.repository.remove(person.id)?;
+        }
+        Ok(persons)
+    }
+
+    // Put taken persons back under their ids
+    pub fn restore_persons(&mut self, persons: Vec<Person>) -> Result<(), R::Error> {
+        for person in persons {
+            self.repository.restore(person.id, person)?;
+        }
+        Ok(())
+    }
+
     // Get a person by ID
     pub fn get_person(&self, person_id: PersonId) -> Result<Person, R::Error> {
         self.repository.get(person_id)
@@ -98,7 +156,7 @@ mod tests {
         let mut service = PersonService::new(repo, sender);
 
         // Create a person
-        let location = Location { x: 10, y: 20 };
+        let location = Location::new(10, 20);
         let person = service
             .create_person("Alice".to_string(), location.clone())
             .unwrap();
@@ -131,7 +189,7 @@ mod tests {
         let mut repo = VecRepository::<PersonId, Person>::new();
 
         // Add a person directly to the repository
-        let initial_location = Location { x: 10, y: 20 };
+        let initial_location = Location::new(10, 20);
         let person = Person {
             id: PersonId(0),
             name: "Bob".to_string(),
@@ -142,7 +200,7 @@ mod tests {
         let mut service = PersonService::new(repo, sender);
 
         // Move the person
-        let new_location = Location { x: 30, y: 40 };
+        let new_location = Location::new(30, 40);
         let updated_person = service
             .move_person(PersonId(0), new_location.clone())
             .unwrap();
@@ -175,7 +233,7 @@ mod tests {
         let mut repo = VecRepository::<PersonId, Person>::new();
 
         // Add a person directly to the repository
-        let location = Location { x: 10, y: 20 };
+        let location = Location::new(10, 20);
         let person = Person {
             id: PersonId(0),
             name: "Charlie".to_string(),
@@ -205,12 +263,12 @@ mod tests {
         let person1 = Person {
             id: PersonId(0),
             name: "Dave".to_string(),
-            location: Location { x: 10, y: 20 },
+            location: Location::new(10, 20),
         };
         let person2 = Person {
             id: PersonId(1),
             name: "Eve".to_string(),
-            location: Location { x: 30, y: 40 },
+            location: Location::new(30, 40),
         };
 
         repo.add(person1.clone()).unwrap();
@@ -238,7 +296,7 @@ mod tests {
         let mut service = PersonService::new(repo, sender);
 
         // Try to move a nonexistent person
-        let result = service.move_person(PersonId(99), Location { x: 50, y: 60 });
+        let result = service.move_person(PersonId(99), Location::new(50, 60));
 
         // Verify the operation failed
         assert!(result.is_err());
@@ -256,11 +314,11 @@ mod tests {
 
         // Create multiple persons
         let person1 = service
-            .create_person("Frank".to_string(), Location { x: 10, y: 20 })
+            .create_person("Frank".to_string(), Location::new(10, 20))
             .unwrap();
 
         let person2 = service
-            .create_person("Grace".to_string(), Location { x: 30, y: 40 })
+            .create_person("Grace".to_string(), Location::new(30, 40))
             .unwrap();
 
         // Verify the persons were created with sequential IDs
@@ -306,23 +364,23 @@ mod tests {
 
         // Create a person
         let person = service
-            .create_person("Hannah".to_string(), Location { x: 10, y: 20 })
+            .create_person("Hannah".to_string(), Location::new(10, 20))
             .unwrap();
 
         // Move the person
         let updated_person = service
-            .move_person(person.id, Location { x: 30, y: 40 })
+            .move_person(person.id, Location::new(30, 40))
             .unwrap();
 
         // Move the person again
         let final_person = service
-            .move_person(updated_person.id, Location { x: 50, y: 60 })
+            .move_person(updated_person.id, Location::new(50, 60))
             .unwrap();
 
         // Verify the final state
         assert_eq!(final_person.id, PersonId(0));
         assert_eq!(final_person.name, "Hannah");
-        assert_eq!(final_person.location, Location { x: 50, y: 60 });
+        assert_eq!(final_person.location, Location::new(50, 60));
 
         // Verify all events were sent
         let event1 = receiver.recv().unwrap();
@@ -351,8 +409,8 @@ mod tests {
         }) = event3
         {
             assert_eq!(person_id, PersonId(0));
-            assert_eq!(from_location, Location { x: 30, y: 40 });
-            assert_eq!(to_location, Location { x: 50, y: 60 });
+            assert_eq!(from_location, Location::new(30, 40));
+            assert_eq!(to_location, Location::new(50, 60));
         } else {
             panic!("Expected PersonMoved event");
         }
@@ -369,8 +427,8 @@ mod tests {
         let mut service = PersonService::new(repo, sender);
 
         // Create two persons with the same name but different locations
-        let location1 = Location { x: 10, y: 20 };
-        let location2 = Location { x: 30, y: 40 };
+        let location1 = Location::new(10, 20);
+        let location2 = Location::new(30, 40);
 
         let person1 = service
             .create_person("Duplicate".to_string(), location1.clone())
@@ -408,7 +466,7 @@ mod tests {
         let mut repo = VecRepository::<PersonId, Person>::new();
 
         // Add a person directly to the repository
-        let location = Location { x: 10, y: 20 };
+        let location = Location::new(10, 20);
         let person = Person {
             id: PersonId(0),
             name: "Stationary".to_string(),
@@ -467,7 +525,7 @@ mod tests {
         let person = Person {
             id: PersonId(0),
             name: "Temporary".to_string(),
-            location: Location { x: 10, y: 20 },
+            location: Location::new(10, 20),
         };
         repo.add(person).unwrap();
 
@@ -478,7 +536,7 @@ mod tests {
 
         // Create a new person
         let new_person = service
-            .create_person("Replacement".to_string(), Location { x: 30, y: 40 })
+            .create_person("Replacement".to_string(), Location::new(30, 40))
             .unwrap();
 
         // Verify the new person has ID 1 (not reusing the removed ID 0)
@@ -505,9 +563,88 @@ mod tests {
         let mut service = PersonService::new(repo, sender);
 
         // Create a person - this should not panic even though the channel is closed
-        let result = service.create_person("Undelivered".to_string(), Location { x: 10, y: 20 });
+        let result = service.create_person("Undelivered".to_string(), Location::new(10, 20));
 
         // The operation should still succeed even though the event couldn't be sent
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_move_persons_in_one_event() {
+        // Setup
+        let (sender, receiver) = mpsc::channel();
+        let repo = VecRepository::<PersonId, Person>::new();
+        let mut service = PersonService::new(repo, sender);
+        service
+            .create_person("Alice".to_string(), Location::new(0, 0))
+            .unwrap();
+        service
+            .create_person("Bob".to_string(), Location::new(5, 5))
+            .unwrap();
+        receiver.try_iter().count();
+
+        // Move both, Alice twice
+        let moved = service
+            .move_persons(vec![
+                (PersonId(0), Location::new(1, 0)),
+                (PersonId(1), Location::new(6, 5)),
+                (PersonId(0), Location::new(2, 0)),
+            ])
+            .unwrap();
+        assert_eq!(moved.len(), 3);
+        assert_eq!(
+            service.get_person(PersonId(0)).unwrap().location,
+            Location::new(2, 0)
+        );
+
+        // Verify a single event with every move was sent
+        let events: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(events.len(), 1);
+        if let DomainEvent::Person(PersonEvent::PersonsMoved { moves }) = &events[0] {
+            assert_eq!(moves.len(), 3);
+            assert_eq!(moves[2].from_location, Location::new(1, 0));
+            assert_eq!(moves[2].to_location, Location::new(2, 0));
+        } else {
+            panic!("Expected PersonsMoved event");
+        }
+
+        // Nobody moves when one of the persons doesn't exist
+        let result = service.move_persons(vec![
+            (PersonId(1), Location::new(9, 9)),
+            (PersonId(7), Location::new(9, 9)),
+        ]);
+        assert!(result.is_err());
+        assert_eq!(
+            service.get_person(PersonId(1)).unwrap().location,
+            Location::new(6, 5)
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_taken_persons_are_restored_under_their_ids() {
+        // Setup
+        let (sender, receiver) = mpsc::channel();
+        let repo = VecRepository::<PersonId, Person>::new();
+        let mut service = PersonService::new(repo, sender);
+        service
+            .create_person("Alice".to_string(), Location::new(0, 0))
+            .unwrap();
+        service
+            .create_person("Bob".to_string(), Location::new(50, 0))
+            .unwrap();
+        receiver.try_iter().count();
+
+        // Only Alice is in the region
+        let taken = service.take_persons_in(&Region::new(0, 0, 9, 9)).unwrap();
+        assert_eq!(taken.len(), 1);
+        assert!(service.get_person(PersonId(0)).is_err());
+        assert_eq!(service.get_all_persons().unwrap().len(), 1);
+
+        // She comes back as she was, nobody is told either time
+        service.restore_persons(taken.clone()).unwrap();
+        assert_eq!(service.get_person(PersonId(0)).unwrap(), taken[0]);
+        assert!(service.restore_persons(taken).is_err());
+        assert!(receiver.try_recv().is_err());
+    }
 }
@@ -0,0 +1,201 @@
+use crate::domain::entity::person::PersonId;
+use crate::domain::entity::portal::{Portal, PortalId};
+use crate::domain::event::portal_event::PortalEvent;
+use crate::domain::event::DomainEvent;
+use crate::domain::value_object::location::Location;
+use crate::infrastructure::event_store::{publish_event, EventSender};
+use std::collections::BTreeMap;
+
+/// Steps it takes to go through a portal, walking to a neighboring tile takes one
+pub const PORTAL_COST: u32 = 1;
+
+/// The portals linking distant tiles, persons walking somewhere take them when that's shorter
+pub struct PortalService {
+    portals: BTreeMap<PortalId, Portal>,
+    next_id: u32,
+    event_sender: EventSender,
+}
+
+impl PortalService {
+    pub fn new(event_sender: impl Into<EventSender>) -> Self {
+        PortalService {
+            portals: BTreeMap::new(),
+            next_id: 1,
+            event_sender: event_sender.into(),
+        }
+    }
+
+    // Link two tiles, each tile can be the end of one portal only
+    pub fn create(&mut self, from: Location, to: Location) -> Result<Portal, String> {
+        if from == to {
+            return Err("A portal has to link two different tiles".to_string());
+        }
+        for end in [&from, &to] {
+            if let Some(portal) = self.at(end) {
+                return Err(format!(
+                    "There is portal {} at {}, {} already",
+                    portal.id.0, end.x, end.y
+                ));
+            }
+        }
+        let portal = Portal {
+            id: PortalId(self.next_id),
+            from,
+            to,
+        };
+        self.next_id += 1;
+        self.portals.insert(portal.id, portal.clone());
+        self.publish(PortalEvent::PortalCreated {
+            portal: portal.id,
+            from_location: portal.from.clone(),
+            to_location: portal.to.clone(),
+        });
+        Ok(portal)
+    }
+
+    // Returns false if there is no such portal
+    pub fn remove(&mut self, id: PortalId) -> bool {
+        if self.portals.remove(&id).is_none() {
+            return false;
+        }
+        self.publish(PortalEvent::PortalRemoved { portal: id });
+        true
+    }
+
+    pub fn get(&self, id: PortalId) -> Option<Portal> {
+        self.portals.get(&id).cloned()
+    }
+
+    // Portals ordered by id
+    pub fn all(&self) -> Vec<Portal> {
+        self.portals.values().cloned().collect()
+    }
+
+    // The portal with an end at the tile
+    pub fn at(&self, location: &Location) -> Option<Portal> {
+        self.portals
+            .values()
+            .find(|portal| portal.other_end(location).is_some())
+            .cloned()
+    }
+
+    // Tile to go to next on the shortest way between two tiles, the other end of a portal when
+    // the way leads through it. Walking is preferred when it's just as short.
+    pub fn next_tile(&self, from: &Location, to: &Location) -> Location {
+        if from == to || self.portals.is_empty() {
+            return from.step_towards(to);
+        }
+        // The start, the target and the ends of the portals, any of them can be walked to from
+        // any other
+        let mut tiles = vec![from.clone(), to.clone()];
+        for portal in self.portals.values() {
+            tiles.push(portal.from.clone());
+            tiles.push(portal.to.clone());
+        }
+        let mut distances = vec![u32::MAX; tiles.len()];
+        // The tile to go to first on the way to each tile, None while that way hasn't left the
+        // start tile yet
+        let mut first: Vec<Option<Location>> = vec![None; tiles.len()];
+        let mut done = vec![false; tiles.len()];
+        distances[0] = 0;
+        while let Some(current) = (0..tiles.len())
+            .filter(|&i| !done[i] && distances[i] != u32::MAX)
+            .min_by_key(|&i| distances[i])
+        {
+            done[current] = true;
+            if current == 1 {
+                break;
+            }
+            for next in (0..tiles.len()).filter(|&i| !done[i]) {
+                // Ends of a portal are 2 + 2 * index and 3 + 2 * index, linked to each other
+                let through = current >= 2 && next >= 2 && current / 2 == next / 2;
+                let cost = if through {
+                    PORTAL_COST
+                } else {
+                    tiles[current].distance(&tiles[next])
+                };
+                // Tiles on different maps are out of reach but through portals
+                let distance = distances[current].saturating_add(cost);
+                if distance >= distances[next] {
+                    continue;
+                }
+                distances[next] = distance;
+                first[next] = match &first[current] {
+                    Some(tile) => Some(tile.clone()),
+                    None if cost == 0 => None,
+                    None if through => Some(tiles[next].clone()),
+                    None => Some(from.step_towards(&tiles[next])),
+                };
+            }
+        }
+        first[1].clone().unwrap_or_else(|| from.step_towards(to))
+    }
+
+    // Emit PortalTraversed if a person went from one end of a portal to the other, returns
+    // whether it did
+    pub fn traverse(&self, person_id: PersonId, from: &Location, to: &Location) -> bool {
+        let Some(portal) = self
+            .at(from)
+            .filter(|portal| portal.other_end(from).as_ref() == Some(to))
+        else {
+            return false;
+        };
+        self.publish(PortalEvent::PortalTraversed {
+            portal: portal.id,
+            person_id,
+            from_location: from.clone(),
+            to_location: to.clone(),
+        });
+        true
+    }
+
+    fn publish(&self, event: PortalEvent) {
+        publish_event(&self.event_sender, DomainEvent::Portal(event));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_the_way_leads_through_portals_when_shorter() {
+        let (sender, receiver) = mpsc::channel();
+        let mut service = PortalService::new(sender);
+        let at = |x, y| Location::new(x, y);
+        let station = service.create(at(2, 0), at(100, 0)).unwrap();
+        assert!(service.create(at(100, 0), at(5, 5)).is_err());
+        assert!(service.create(at(3, 3), at(3, 3)).is_err());
+
+        // Walk to the near end, step through and walk on from the far one
+        assert_eq!(service.next_tile(&at(0, 0), &at(103, 2)), at(1, 0));
+        assert_eq!(service.next_tile(&at(2, 0), &at(103, 2)), at(100, 0));
+        assert_eq!(service.next_tile(&at(100, 0), &at(103, 2)), at(101, 1));
+        // Nearby the portal is no shortcut
+        assert_eq!(service.next_tile(&at(2, 0), &at(4, 0)), at(3, 0));
+
+        // Portals lead on to further portals
+        service.create(at(101, 5), at(-50, -50)).unwrap();
+        assert_eq!(service.next_tile(&at(2, 0), &at(-52, -50)), at(100, 0));
+        assert_eq!(service.next_tile(&at(100, 0), &at(-52, -50)), at(101, 1));
+
+        assert!(service.traverse(PersonId(0), &at(100, 0), &at(2, 0)));
+        assert!(!service.traverse(PersonId(0), &at(1, 0), &at(2, 0)));
+        // Without the station the way leads round through the other portal
+        assert!(service.remove(station.id));
+        assert_eq!(service.next_tile(&at(2, 0), &at(103, 2)), at(1, -1));
+
+        let events: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            events[2],
+            DomainEvent::Portal(PortalEvent::PortalTraversed {
+                portal: station.id,
+                person_id: PersonId(0),
+                from_location: at(100, 0),
+                to_location: at(2, 0),
+            })
+        );
+    }
+}
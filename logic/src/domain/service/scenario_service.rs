@@ -0,0 +1,64 @@
+use crate::domain::event::scenario_event::{ScenarioEvent, ScenarioOutcome};
+use crate::domain::event::DomainEvent;
+use crate::infrastructure::event_store::{publish_event, EventSender};
+
+/// Tracks whether the running scenario has ended, it ends only once
+pub struct ScenarioService {
+    ended: bool,
+    event_sender: EventSender,
+}
+
+impl ScenarioService {
+    pub fn new(event_sender: impl Into<EventSender>) -> Self {
+        ScenarioService {
+            ended: false,
+            event_sender: event_sender.into(),
+        }
+    }
+
+    // End the scenario and emit a ScenarioEnded event, false if it had ended already
+    pub fn end(&mut self, outcome: ScenarioOutcome, reason: &str, tick: u64) -> bool {
+        if self.ended {
+            return false;
+        }
+        self.ended = true;
+        let event = ScenarioEvent::ScenarioEnded {
+            outcome,
+            reason: reason.to_string(),
+            tick,
+        };
+        publish_event(&self.event_sender, DomainEvent::Scenario(event));
+        true
+    }
+
+    pub fn has_ended(&self) -> bool {
+        self.ended
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_scenario_ends_once() {
+        let (sender, receiver) = mpsc::channel();
+        let mut service = ScenarioService::new(sender);
+
+        assert!(!service.has_ended());
+        assert!(service.end(ScenarioOutcome::Won, "All fed", 120));
+        assert!(!service.end(ScenarioOutcome::Lost, "Too late", 121));
+        assert!(service.has_ended());
+
+        let events: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(
+            events,
+            vec![DomainEvent::Scenario(ScenarioEvent::ScenarioEnded {
+                outcome: ScenarioOutcome::Won,
+                reason: "All fed".to_string(),
+                tick: 120,
+            })]
+        );
+    }
+}
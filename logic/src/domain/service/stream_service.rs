@@ -0,0 +1,138 @@
+use crate::domain::entity::person::Person;
+use crate::domain::event::stream_event::StreamEvent;
+use crate::domain::event::DomainEvent;
+use crate::domain::value_object::chunk::Chunk;
+use crate::domain::value_object::map_id::MapId;
+use crate::infrastructure::chunk_store::ChunkStore;
+use crate::infrastructure::event_store::{publish_event, EventSender};
+use std::collections::BTreeSet;
+
+/// The chunks written to disk with their persons. The persons of an unloaded chunk are out of
+/// the repository, so the APIs and the tick don't see them until the chunk is loaded again,
+/// their tasks are abandoned like those of anyone gone.
+pub struct StreamService {
+    unloaded: BTreeSet<Chunk>,
+    store: ChunkStore,
+    event_sender: EventSender,
+}
+
+impl StreamService {
+    pub(crate) fn new(store: ChunkStore, event_sender: impl Into<EventSender>) -> Self {
+        StreamService {
+            unloaded: BTreeSet::new(),
+            store,
+            event_sender: event_sender.into(),
+        }
+    }
+
+    pub fn is_unloaded(&self, chunk: &Chunk) -> bool {
+        self.unloaded.contains(chunk)
+    }
+
+    // Unloaded chunks ordered by map, level, row and column
+    pub fn unloaded(&self) -> Vec<Chunk> {
+        self.unloaded.iter().cloned().collect()
+    }
+
+    // Unloaded chunks of a level of a map
+    pub fn unloaded_on(&self, map: &MapId, z: i32) -> Vec<Chunk> {
+        self.unloaded
+            .iter()
+            .filter(|chunk| chunk.map == *map && chunk.z == z)
+            .cloned()
+            .collect()
+    }
+
+    // Write the persons taken out of the chunk to disk
+    pub fn unload(&mut self, chunk: Chunk, persons: &[Person]) -> Result<(), String> {
+        if self.unloaded.contains(&chunk) {
+            return Err(format!("{} is unloaded already", describe(&chunk)));
+        }
+        self.store
+            .write(&chunk, persons)
+            .map_err(|e| format!("Failed to write {}: {}", describe(&chunk), e))?;
+        self.unloaded.insert(chunk.clone());
+        self.publish(StreamEvent::ChunkUnloaded {
+            chunk,
+            persons: persons.iter().map(|person| person.id).collect(),
+        });
+        Ok(())
+    }
+
+    // Read the persons of the chunk back from disk, to be put back into the repository
+    pub fn load(&mut self, chunk: Chunk) -> Result<Vec<Person>, String> {
+        if !self.unloaded.contains(&chunk) {
+            return Err(format!("{} isn't unloaded", describe(&chunk)));
+        }
+        let persons = self
+            .store
+            .take(&chunk)
+            .map_err(|e| format!("Failed to read {}: {}", describe(&chunk), e))?;
+        self.unloaded.remove(&chunk);
+        self.publish(StreamEvent::ChunkLoaded {
+            chunk,
+            persons: persons.clone(),
+        });
+        Ok(persons)
+    }
+
+    fn publish(&self, event: StreamEvent) {
+        publish_event(&self.event_sender, DomainEvent::Stream(event));
+    }
+}
+
+fn describe(chunk: &Chunk) -> String {
+    format!(
+        "Chunk {},{} of level {} of {}",
+        chunk.x, chunk.y, chunk.z, chunk.map
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entity::person::PersonId;
+    use crate::domain::value_object::location::Location;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_unloaded_chunks_are_read_back_once() {
+        let (sender, receiver) = mpsc::channel();
+        let dir = std::env::temp_dir().join(format!("chunks_{}", std::process::id()));
+        let mut service = StreamService::new(ChunkStore::new(&dir), sender);
+        let ann = Person {
+            id: PersonId(3),
+            name: "Ann".to_string(),
+            location: Location::new(40, 2),
+        };
+        let chunk = Chunk::of(&ann.location);
+
+        service
+            .unload(chunk.clone(), std::slice::from_ref(&ann))
+            .unwrap();
+        assert!(service.unload(chunk.clone(), &[]).is_err());
+        assert_eq!(service.unloaded_on(&MapId::main(), 0), vec![chunk.clone()]);
+        assert!(service.unloaded_on(&MapId::main(), 1).is_empty());
+
+        assert_eq!(service.load(chunk.clone()).unwrap(), vec![ann.clone()]);
+        assert!(service.load(chunk.clone()).is_err());
+        assert!(!service.is_unloaded(&chunk));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir(dir).unwrap();
+
+        let events: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(
+            events,
+            vec![
+                DomainEvent::Stream(StreamEvent::ChunkUnloaded {
+                    chunk: chunk.clone(),
+                    persons: vec![ann.id]
+                }),
+                DomainEvent::Stream(StreamEvent::ChunkLoaded {
+                    chunk,
+                    persons: vec![ann]
+                })
+            ]
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use crate::domain::event::tag_event::TagEvent;
+use crate::domain::event::DomainEvent;
+use crate::domain::value_object::entity_ref::EntityRef;
+use crate::domain::value_object::meta_value::MetaValue;
+use crate::infrastructure::event_store::{publish_event, EventSender};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+#[derive(Default)]
+struct EntityTags {
+    tags: BTreeSet<String>,
+    meta: BTreeMap<String, MetaValue>,
+}
+
+/// Tags and metadata mods attach to entities, without the entities knowing about them
+pub struct TagService {
+    entities: HashMap<EntityRef, EntityTags>,
+    by_tag: HashMap<String, BTreeSet<EntityRef>>,
+    event_sender: EventSender,
+}
+
+impl TagService {
+    pub fn new(event_sender: impl Into<EventSender>) -> Self {
+        TagService {
+            entities: HashMap::new(),
+            by_tag: HashMap::new(),
+            event_sender: event_sender.into(),
+        }
+    }
+
+    // Tag an entity, emitting TagAdded only if it wasn't tagged already
+    pub fn add_tag(&mut self, entity: EntityRef, tag: &str) -> bool {
+        let added = self
+            .entities
+            .entry(entity)
+            .or_default()
+            .tags
+            .insert(tag.to_string());
+        if added {
+            self.by_tag
+                .entry(tag.to_string())
+                .or_default()
+                .insert(entity);
+            self.publish(TagEvent::TagAdded {
+                entity,
+                tag: tag.to_string(),
+            });
+        }
+        added
+    }
+
+    // Remove a tag, emitting TagRemoved only if the entity had it
+    pub fn remove_tag(&mut self, entity: EntityRef, tag: &str) -> bool {
+        let removed = self
+            .entities
+            .get_mut(&entity)
+            .is_some_and(|entry| entry.tags.remove(tag));
+        if removed {
+            if let Some(entities) = self.by_tag.get_mut(tag) {
+                entities.remove(&entity);
+                if entities.is_empty() {
+                    self.by_tag.remove(tag);
+                }
+            }
+            self.publish(TagEvent::TagRemoved {
+                entity,
+                tag: tag.to_string(),
+            });
+        }
+        removed
+    }
+
+    pub fn has_tag(&self, entity: EntityRef, tag: &str) -> bool {
+        self.entities
+            .get(&entity)
+            .is_some_and(|entry| entry.tags.contains(tag))
+    }
+
+    // Tags of an entity in alphabetical order
+    pub fn tags_of(&self, entity: EntityRef) -> Vec<String> {
+        self.entities
+            .get(&entity)
+            .map(|entry| entry.tags.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    // Entities with a tag, ordered by kind and id
+    pub fn find_tagged(&self, tag: &str) -> Vec<EntityRef> {
+        self.by_tag
+            .get(tag)
+            .map(|entities| entities.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    // Set a metadata entry, emitting MetadataSet only if the value changed
+    pub fn set_meta(&mut self, entity: EntityRef, key: &str, value: MetaValue) {
+        let meta = &mut self.entities.entry(entity).or_default().meta;
+        if meta.get(key) == Some(&value) {
+            return;
+        }
+        meta.insert(key.to_string(), value.clone());
+        self.publish(TagEvent::MetadataSet {
+            entity,
+            key: key.to_string(),
+            value,
+        });
+    }
+
+    pub fn get_meta(&self, entity: EntityRef, key: &str) -> Option<MetaValue> {
+        self.entities
+            .get(&entity)
+            .and_then(|entry| entry.meta.get(key).cloned())
+    }
+
+    // Remove a metadata entry, emitting MetadataRemoved only if it existed
+    pub fn remove_meta(&mut self, entity: EntityRef, key: &str) -> bool {
+        let removed = self
+            .entities
+            .get_mut(&entity)
+            .is_some_and(|entry| entry.meta.remove(key).is_some());
+        if removed {
+            self.publish(TagEvent::MetadataRemoved {
+                entity,
+                key: key.to_string(),
+            });
+        }
+        removed
+    }
+
+    pub fn all_meta(&self, entity: EntityRef) -> BTreeMap<String, MetaValue> {
+        self.entities
+            .get(&entity)
+            .map(|entry| entry.meta.clone())
+            .unwrap_or_default()
+    }
+
+    // Entities with tags or metadata, ordered by kind and id
+    pub fn entities(&self) -> Vec<EntityRef> {
+        let mut entities: Vec<_> = self
+            .entities
+            .iter()
+            .filter(|(_, entry)| !entry.tags.is_empty() || !entry.meta.is_empty())
+            .map(|(entity, _)| *entity)
+            .collect();
+        entities.sort();
+        entities
+    }
+
+    fn publish(&self, event: TagEvent) {
+        publish_event(&self.event_sender, DomainEvent::Tag(event));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entity::person::PersonId;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_tags_are_indexed_and_published_once() {
+        let (sender, receiver) = mpsc::channel();
+        let mut service = TagService::new(sender);
+        let alice = EntityRef::Person(PersonId(0));
+        let bob = EntityRef::Person(PersonId(1));
+
+        assert!(service.add_tag(bob, "vip"));
+        assert!(service.add_tag(alice, "vip"));
+        assert!(!service.add_tag(alice, "vip"));
+        service.add_tag(alice, "guard");
+
+        assert!(service.has_tag(alice, "vip"));
+        assert_eq!(service.tags_of(alice), vec!["guard", "vip"]);
+        assert_eq!(service.find_tagged("vip"), vec![alice, bob]);
+
+        assert!(service.remove_tag(bob, "vip"));
+        assert!(!service.remove_tag(bob, "vip"));
+        assert_eq!(service.find_tagged("vip"), vec![alice]);
+        assert!(service.find_tagged("unknown").is_empty());
+
+        let events: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            events[3],
+            DomainEvent::Tag(TagEvent::TagRemoved {
+                entity: bob,
+                tag: "vip".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_metadata_changes_are_published() {
+        let (sender, receiver) = mpsc::channel();
+        let mut service = TagService::new(sender);
+        let alice = EntityRef::Person(PersonId(0));
+
+        service.set_meta(alice, "mood", MetaValue::Text("happy".to_string()));
+        service.set_meta(alice, "mood", MetaValue::Text("happy".to_string()));
+        service.set_meta(alice, "gold", MetaValue::Number(12.0));
+        assert_eq!(
+            service.get_meta(alice, "gold"),
+            Some(MetaValue::Number(12.0))
+        );
+        assert_eq!(service.all_meta(alice).len(), 2);
+
+        assert!(service.remove_meta(alice, "mood"));
+        assert!(!service.remove_meta(alice, "mood"));
+        assert_eq!(service.get_meta(alice, "mood"), None);
+
+        let events: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(events.len(), 3);
+        assert_eq!(
+            events[2],
+            DomainEvent::Tag(TagEvent::MetadataRemoved {
+                entity: alice,
+                key: "mood".to_string()
+            })
+        );
+    }
+}
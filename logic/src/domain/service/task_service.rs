@@ -0,0 +1,364 @@
+use crate::domain::entity::person::PersonId;
+use crate::domain::entity::task::{Task, TaskId, TaskKind};
+use crate::domain::event::task_event::TaskEvent;
+use crate::domain::event::DomainEvent;
+use crate::domain::value_object::location::Location;
+use crate::infrastructure::event_store::{publish_event, EventSender};
+use std::collections::{BTreeMap, HashMap};
+
+/// The tasks queued for every person. On each step the person works on the task that runs: a
+/// started task nothing may interrupt, otherwise the one of the highest priority, the earliest
+/// queued one among equals.
+pub struct TaskService {
+    queues: BTreeMap<PersonId, Vec<Task>>,
+    next_id: u32,
+    event_sender: EventSender,
+}
+
+impl TaskService {
+    pub fn new(event_sender: impl Into<EventSender>) -> Self {
+        TaskService {
+            queues: BTreeMap::new(),
+            next_id: 1,
+            event_sender: event_sender.into(),
+        }
+    }
+
+    // Queue a task, it starts on the first step it's the one running. The person has to exist,
+    // that's up to the caller.
+    pub fn queue(
+        &mut self,
+        person_id: PersonId,
+        kind: TaskKind,
+        priority: i32,
+        interruptible: bool,
+    ) -> Task {
+        let task = Task {
+            id: TaskId(self.next_id),
+            person_id,
+            kind,
+            priority,
+            interruptible,
+            started: false,
+            worked: 0,
+            carrying: false,
+            waypoint: 0,
+        };
+        self.next_id += 1;
+        self.queues.entry(person_id).or_default().push(task.clone());
+        task
+    }
+
+    pub fn get(&self, id: TaskId) -> Option<Task> {
+        self.queues
+            .values()
+            .flatten()
+            .find(|task| task.id == id)
+            .cloned()
+    }
+
+    // Tasks of a person in the order they will run, the running one first
+    pub fn queue_of(&self, person_id: PersonId) -> Vec<Task> {
+        let mut tasks = self.queues.get(&person_id).cloned().unwrap_or_default();
+        tasks.sort_by_key(|task| (!task.started || task.interruptible, -task.priority, task.id));
+        tasks
+    }
+
+    // Change the priority of a task and whether it may be interrupted, which decides from the
+    // next step on what runs
+    pub fn prioritize(&mut self, id: TaskId, priority: i32, interruptible: bool) -> Option<Task> {
+        let task = self
+            .queues
+            .values_mut()
+            .flatten()
+            .find(|task| task.id == id)?;
+        task.priority = priority;
+        task.interruptible = interruptible;
+        Some(task.clone())
+    }
+
+    // Drop a task, emitting TaskAbandoned. Returns false if there is no such task.
+    pub fn cancel(&mut self, id: TaskId, reason: &str) -> bool {
+        for tasks in self.queues.values_mut() {
+            if let Some(index) = tasks.iter().position(|task| task.id == id) {
+                let task = tasks.remove(index);
+                self.publish(TaskEvent::TaskAbandoned {
+                    task: task.id,
+                    person_id: task.person_id,
+                    reason: reason.to_string(),
+                });
+                return true;
+            }
+        }
+        false
+    }
+
+    // Work a step on the running task of every person found at `locations`, the tasks of
+    // persons that are gone are abandoned. `next_tile` tells where to go next on the way from one
+    // tile to another. Returns where persons move to, the caller moves them.
+    pub fn step(
+        &mut self,
+        locations: &HashMap<PersonId, Location>,
+        next_tile: impl Fn(&Location, &Location) -> Location,
+    ) -> Vec<(PersonId, Location)> {
+        let mut moves = Vec::new();
+        let mut events = Vec::new();
+        for (person_id, tasks) in &mut self.queues {
+            let Some(location) = locations.get(person_id) else {
+                events.extend(tasks.drain(..).map(|task| TaskEvent::TaskAbandoned {
+                    task: task.id,
+                    person_id: task.person_id,
+                    reason: "the person is gone".to_string(),
+                }));
+                continue;
+            };
+            let Some(index) = running(tasks) else {
+                continue;
+            };
+            let task = &mut tasks[index];
+            if !task.started {
+                task.started = true;
+                events.push(TaskEvent::TaskStarted {
+                    task: task.id,
+                    person_id: *person_id,
+                    kind: task.kind.name().to_string(),
+                });
+            }
+            let (next, done) = work(task, location, &next_tile);
+            if let Some(next) = next {
+                moves.push((*person_id, next));
+            }
+            if done {
+                let task = tasks.remove(index);
+                events.push(TaskEvent::TaskCompleted {
+                    task: task.id,
+                    person_id: task.person_id,
+                });
+            }
+        }
+        self.queues.retain(|_, tasks| !tasks.is_empty());
+        for event in events {
+            self.publish(event);
+        }
+        moves
+    }
+
+    fn publish(&self, event: TaskEvent) {
+        publish_event(&self.event_sender, DomainEvent::Task(event));
+    }
+}
+
+// Index of the task that runs, a started one nothing may interrupt keeps running
+fn running(tasks: &[Task]) -> Option<usize> {
+    if let Some(index) = tasks
+        .iter()
+        .position(|task| task.started && !task.interruptible)
+    {
+        return Some(index);
+    }
+    tasks
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, task)| (-task.priority, task.id))
+        .map(|(index, _)| index)
+}
+
+// A step of the task for a person at `location`: the tile to move to, if any, and whether the
+// task is done. Arriving completes walking, picking up an item or working takes a step, patrols
+// are never done.
+fn work(
+    task: &mut Task,
+    location: &Location,
+    next_tile: &impl Fn(&Location, &Location) -> Location,
+) -> (Option<Location>, bool) {
+    match &task.kind {
+        TaskKind::GoTo { target } => walk(location, target, next_tile),
+        TaskKind::WorkAt {
+            location: at,
+            steps,
+        } => {
+            if location != at {
+                return (Some(next_tile(location, at)), false);
+            }
+            task.worked += 1;
+            (None, task.worked >= *steps)
+        }
+        TaskKind::Haul { from, to, .. } => {
+            if task.carrying {
+                return walk(location, to, next_tile);
+            }
+            if location != from {
+                return (Some(next_tile(location, from)), false);
+            }
+            task.carrying = true;
+            (None, false)
+        }
+        TaskKind::Patrol { route } => {
+            if route.is_empty() {
+                return (None, true);
+            }
+            if *location == route[task.waypoint] {
+                task.waypoint = (task.waypoint + 1) % route.len();
+            }
+            // A single waypoint is guarded by standing on it
+            let target = &route[task.waypoint];
+            if location == target {
+                return (None, false);
+            }
+            (Some(next_tile(location, target)), false)
+        }
+    }
+}
+
+fn walk(
+    location: &Location,
+    target: &Location,
+    next_tile: &impl Fn(&Location, &Location) -> Location,
+) -> (Option<Location>, bool) {
+    if location == target {
+        return (None, true);
+    }
+    let next = next_tile(location, target);
+    let arrived = next == *target;
+    (Some(next), arrived)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_higher_priority_interrupts_unless_forbidden() {
+        let (sender, receiver) = mpsc::channel();
+        let mut service = TaskService::new(sender);
+        let ann = PersonId(0);
+        let mut locations = HashMap::from([(ann, Location::new(0, 0))]);
+        let mut step = |service: &mut TaskService| {
+            for (person_id, location) in service.step(&locations, Location::step_towards) {
+                locations.insert(person_id, location);
+            }
+            locations[&ann].clone()
+        };
+        let at = |x, y| Location::new(x, y);
+
+        let walk = service.queue(ann, TaskKind::GoTo { target: at(3, 0) }, 0, true);
+        assert_eq!(step(&mut service), at(1, 0));
+
+        // Work of a higher priority interrupts the walk, which resumes afterwards
+        let work = TaskKind::WorkAt {
+            location: at(1, 0),
+            steps: 1,
+        };
+        let work = service.queue(ann, work, 5, true);
+        assert_eq!(step(&mut service), at(1, 0));
+        assert_eq!(step(&mut service), at(2, 0));
+
+        // Nothing interrupts the haul, not even the most urgent task
+        let haul = TaskKind::Haul {
+            item: "crate".to_string(),
+            from: at(2, 1),
+            to: at(2, 0),
+        };
+        let haul = service.queue(ann, haul, 5, false);
+        assert_eq!(step(&mut service), at(2, 1));
+        let urgent = service.queue(ann, TaskKind::GoTo { target: at(0, 0) }, 10, true);
+        let queued: Vec<_> = service.queue_of(ann).iter().map(|task| task.id).collect();
+        assert_eq!(queued, vec![haul.id, urgent.id, walk.id]);
+        assert_eq!(step(&mut service), at(2, 1));
+        assert!(service.get(haul.id).unwrap().carrying);
+        assert_eq!(step(&mut service), at(2, 0));
+        assert_eq!(service.get(haul.id), None);
+
+        assert!(service.cancel(walk.id, "not needed"));
+        assert!(!service.cancel(walk.id, "not needed"));
+        assert_eq!(step(&mut service), at(1, 0));
+
+        let events: Vec<_> = receiver.try_iter().collect();
+        let expected: Vec<_> = vec![
+            TaskEvent::TaskStarted {
+                task: walk.id,
+                person_id: ann,
+                kind: "go_to".to_string(),
+            },
+            TaskEvent::TaskStarted {
+                task: work.id,
+                person_id: ann,
+                kind: "work_at".to_string(),
+            },
+            TaskEvent::TaskCompleted {
+                task: work.id,
+                person_id: ann,
+            },
+            TaskEvent::TaskStarted {
+                task: haul.id,
+                person_id: ann,
+                kind: "haul".to_string(),
+            },
+            TaskEvent::TaskCompleted {
+                task: haul.id,
+                person_id: ann,
+            },
+            TaskEvent::TaskAbandoned {
+                task: walk.id,
+                person_id: ann,
+                reason: "not needed".to_string(),
+            },
+            TaskEvent::TaskStarted {
+                task: urgent.id,
+                person_id: ann,
+                kind: "go_to".to_string(),
+            },
+        ]
+        .into_iter()
+        .map(DomainEvent::Task)
+        .collect();
+        assert_eq!(events, expected);
+
+        // Tasks of persons that are gone are abandoned
+        service.queue(ann, TaskKind::GoTo { target: at(5, 5) }, 0, true);
+        service.step(&HashMap::new(), Location::step_towards);
+        assert!(service.queue_of(ann).is_empty());
+        assert_eq!(receiver.try_iter().count(), 2);
+    }
+
+    #[test]
+    fn test_patrols_loop_until_cancelled() {
+        let (sender, _receiver) = mpsc::channel();
+        let mut service = TaskService::new(sender);
+        let ann = PersonId(0);
+        let at = |x, y| Location::new(x, y);
+        let mut locations = HashMap::from([(ann, at(0, 0))]);
+        let mut walk = |service: &mut TaskService, steps| {
+            (0..steps)
+                .map(|_| {
+                    for (person_id, location) in service.step(&locations, Location::step_towards) {
+                        locations.insert(person_id, location);
+                    }
+                    locations[&ann].clone()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let route = vec![at(1, 0), at(1, 1)];
+        let patrol = service.queue(ann, TaskKind::Patrol { route }, 0, true);
+        assert_eq!(
+            walk(&mut service, 5),
+            vec![at(1, 0), at(1, 1), at(1, 0), at(1, 1), at(1, 0)]
+        );
+        assert_eq!(service.get(patrol.id).unwrap().waypoint, 0);
+
+        // A single waypoint is guarded, an empty route is done right away
+        assert!(service.cancel(patrol.id, "relieved"));
+        let guard = TaskKind::Patrol {
+            route: vec![at(1, 0)],
+        };
+        let guard = service.queue(ann, guard, 0, true);
+        assert_eq!(walk(&mut service, 2), vec![at(1, 0), at(1, 0)]);
+        assert!(service.get(guard.id).is_some());
+        assert!(service.cancel(guard.id, "relieved"));
+        let empty = service.queue(ann, TaskKind::Patrol { route: vec![] }, 0, true);
+        walk(&mut service, 1);
+        assert_eq!(service.get(empty.id), None);
+    }
+}
@@ -0,0 +1,308 @@
+use crate::domain::entity::person::PersonId;
+use crate::domain::entity::vehicle::{Vehicle, VehicleId};
+use crate::domain::event::vehicle_event::{Load, VehicleEvent};
+use crate::domain::event::DomainEvent;
+use crate::domain::value_object::location::Location;
+use crate::domain::value_object::road_network::RoadNetwork;
+use crate::infrastructure::event_store::{publish_event, EventSender};
+use std::collections::BTreeMap;
+
+/// Road tiles of cost 1 a vehicle drives across per second
+pub const VEHICLE_SPEED: f64 = 4.0;
+
+/// The vehicles with the roads they drive on
+pub struct VehicleService {
+    vehicles: BTreeMap<VehicleId, Vehicle>,
+    roads: RoadNetwork,
+    next_id: u32,
+    event_sender: EventSender,
+}
+
+impl VehicleService {
+    pub fn new(event_sender: impl Into<EventSender>) -> Self {
+        VehicleService {
+            vehicles: BTreeMap::new(),
+            roads: RoadNetwork::default(),
+            next_id: 1,
+            event_sender: event_sender.into(),
+        }
+    }
+
+    // Make a tile a road crossed at the cost, fails unless the cost is positive
+    pub fn set_road(&mut self, location: Location, cost: f64) -> Result<(), String> {
+        if !cost.is_finite() || cost <= 0.0 {
+            return Err(format!("A road can't cost {}", cost));
+        }
+        self.roads.set(location, cost);
+        Ok(())
+    }
+
+    pub fn clear_road(&mut self, location: &Location) -> bool {
+        self.roads.remove(location)
+    }
+
+    pub fn roads(&self) -> &RoadNetwork {
+        &self.roads
+    }
+
+    // A vehicle may stand anywhere, it needs a road to drive off though
+    pub fn create(&mut self, location: Location, capacity: usize) -> Vehicle {
+        let vehicle = Vehicle {
+            id: VehicleId(self.next_id),
+            location,
+            capacity,
+            passengers: Vec::new(),
+            cargo: Vec::new(),
+            route: Default::default(),
+            progress: 0.0,
+        };
+        self.next_id += 1;
+        self.vehicles.insert(vehicle.id, vehicle.clone());
+        self.publish(VehicleEvent::VehicleCreated {
+            vehicle: vehicle.id,
+            location: vehicle.location.clone(),
+        });
+        vehicle
+    }
+
+    pub fn get(&self, id: VehicleId) -> Option<Vehicle> {
+        self.vehicles.get(&id).cloned()
+    }
+
+    // Vehicles ordered by id
+    pub fn all(&self) -> Vec<Vehicle> {
+        self.vehicles.values().cloned().collect()
+    }
+
+    // Vehicle the person rides in, if any
+    pub fn carrying(&self, person_id: PersonId) -> Option<VehicleId> {
+        self.vehicles
+            .values()
+            .find(|vehicle| vehicle.passengers.contains(&person_id))
+            .map(|vehicle| vehicle.id)
+    }
+
+    // Set off along the cheapest road to the target, replacing the route driven so far. Being
+    // there already, the vehicle arrives at once.
+    pub fn drive_to(&mut self, id: VehicleId, target: Location) -> Result<Vehicle, String> {
+        let vehicle = self.vehicles.get(&id).ok_or_else(|| no_vehicle(id))?;
+        let route = self
+            .roads
+            .route(&vehicle.location, &target)
+            .ok_or_else(|| {
+                format!(
+                    "No road leads from ({}, {}) to ({}, {})",
+                    vehicle.location.x, vehicle.location.y, target.x, target.y
+                )
+            })?;
+        let vehicle = self.vehicles.get_mut(&id).unwrap();
+        vehicle.route = route.into();
+        vehicle.progress = 0.0;
+        let vehicle = vehicle.clone();
+        if vehicle.route.is_empty() {
+            self.publish(VehicleEvent::VehicleArrived {
+                vehicle: id,
+                location: vehicle.location.clone(),
+            });
+        }
+        Ok(vehicle)
+    }
+
+    // Take a person standing at the vehicle aboard, the caller tells where the person is
+    pub fn board(
+        &mut self,
+        id: VehicleId,
+        person_id: PersonId,
+        location: &Location,
+    ) -> Result<Vehicle, String> {
+        if let Some(other) = self.carrying(person_id) {
+            return Err(format!(
+                "Person {} rides vehicle {} already",
+                person_id.0, other.0
+            ));
+        }
+        let vehicle = self.loadable(id)?;
+        if vehicle.location != *location {
+            return Err(format!("Person {} isn't at vehicle {}", person_id.0, id.0));
+        }
+        vehicle.passengers.push(person_id);
+        self.loaded(id, Load::Passenger(person_id))
+    }
+
+    // Let a passenger off where the vehicle is
+    pub fn alight(&mut self, id: VehicleId, person_id: PersonId) -> Result<Vehicle, String> {
+        let vehicle = self.vehicles.get_mut(&id).ok_or_else(|| no_vehicle(id))?;
+        let index = vehicle
+            .passengers
+            .iter()
+            .position(|passenger| *passenger == person_id)
+            .ok_or_else(|| format!("Person {} isn't aboard vehicle {}", person_id.0, id.0))?;
+        vehicle.passengers.remove(index);
+        self.unloaded(id, Load::Passenger(person_id))
+    }
+
+    pub fn load(&mut self, id: VehicleId, item: &str) -> Result<Vehicle, String> {
+        self.loadable(id)?.cargo.push(item.to_string());
+        self.loaded(id, Load::Item(item.to_string()))
+    }
+
+    pub fn unload(&mut self, id: VehicleId, item: &str) -> Result<Vehicle, String> {
+        let vehicle = self.vehicles.get_mut(&id).ok_or_else(|| no_vehicle(id))?;
+        let index = vehicle
+            .cargo
+            .iter()
+            .position(|cargo| cargo == item)
+            .ok_or_else(|| format!("Vehicle {} carries no {}", id.0, item))?;
+        vehicle.cargo.remove(index);
+        self.unloaded(id, Load::Item(item.to_string()))
+    }
+
+    // Drive every vehicle on its route for `dt` seconds. A vehicle whose next tile is no road
+    // anymore looks for another way, stranding if there is none. Returns where the passengers
+    // move to, the caller moves them.
+    pub fn step(&mut self, dt: f64) -> Vec<(PersonId, Location)> {
+        let mut moves = Vec::new();
+        let mut events = Vec::new();
+        for vehicle in self.vehicles.values_mut() {
+            if vehicle.route.is_empty() {
+                continue;
+            }
+            vehicle.progress += dt * VEHICLE_SPEED;
+            while let Some(next) = vehicle.route.front() {
+                let Some(cost) = self.roads.cost(next) else {
+                    let destination = vehicle.destination().unwrap().clone();
+                    match self.roads.route(&vehicle.location, &destination) {
+                        Some(route) if !route.is_empty() => vehicle.route = route.into(),
+                        _ => {
+                            vehicle.route.clear();
+                            vehicle.progress = 0.0;
+                            events.push(VehicleEvent::VehicleStranded {
+                                vehicle: vehicle.id,
+                                location: vehicle.location.clone(),
+                            });
+                        }
+                    }
+                    continue;
+                };
+                if vehicle.progress < cost {
+                    break;
+                }
+                vehicle.progress -= cost;
+                let next = vehicle.route.pop_front().unwrap();
+                let from_location = std::mem::replace(&mut vehicle.location, next.clone());
+                moves.extend(
+                    vehicle
+                        .passengers
+                        .iter()
+                        .map(|passenger| (*passenger, next.clone())),
+                );
+                events.push(VehicleEvent::VehicleMoved {
+                    vehicle: vehicle.id,
+                    from_location,
+                    to_location: next.clone(),
+                });
+                if vehicle.route.is_empty() {
+                    vehicle.progress = 0.0;
+                    events.push(VehicleEvent::VehicleArrived {
+                        vehicle: vehicle.id,
+                        location: next,
+                    });
+                }
+            }
+        }
+        for event in events {
+            self.publish(event);
+        }
+        moves
+    }
+
+    // The vehicle, unless it's full
+    fn loadable(&mut self, id: VehicleId) -> Result<&mut Vehicle, String> {
+        let vehicle = self.vehicles.get_mut(&id).ok_or_else(|| no_vehicle(id))?;
+        if vehicle.load() >= vehicle.capacity {
+            return Err(format!("Vehicle {} is full", id.0));
+        }
+        Ok(vehicle)
+    }
+
+    fn loaded(&self, id: VehicleId, load: Load) -> Result<Vehicle, String> {
+        self.publish(VehicleEvent::VehicleLoaded { vehicle: id, load });
+        Ok(self.vehicles[&id].clone())
+    }
+
+    fn unloaded(&self, id: VehicleId, load: Load) -> Result<Vehicle, String> {
+        self.publish(VehicleEvent::VehicleUnloaded { vehicle: id, load });
+        Ok(self.vehicles[&id].clone())
+    }
+
+    fn publish(&self, event: VehicleEvent) {
+        publish_event(&self.event_sender, DomainEvent::Vehicle(event));
+    }
+}
+
+fn no_vehicle(id: VehicleId) -> String {
+    format!("There is no vehicle {}", id.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_vehicles_take_the_cheapest_road() {
+        let (sender, receiver) = mpsc::channel();
+        let mut service = VehicleService::new(sender);
+        let at = |x, y| Location::new(x, y);
+        // A straight road and a cheaper one, which its two turns make dearer
+        for x in 0..=4 {
+            service.set_road(at(x, 0), 1.0).unwrap();
+        }
+        for (x, y) in [(0, 1), (1, 1), (2, 1), (3, 1), (4, 1)] {
+            service.set_road(at(x, y), 0.8).unwrap();
+        }
+        assert!(service.set_road(at(9, 9), 0.0).is_err());
+        assert_eq!(
+            service.roads().route(&at(0, 0), &at(4, 0)),
+            Some(vec![at(1, 0), at(2, 0), at(3, 0), at(4, 0)])
+        );
+
+        let bus = service.create(at(0, 0), 1);
+        let ann = PersonId(0);
+        assert!(service.board(bus.id, ann, &at(5, 5)).is_err());
+        service.board(bus.id, ann, &at(0, 0)).unwrap();
+        assert!(service.load(bus.id, "mail").is_err());
+        service.drive_to(bus.id, at(4, 0)).unwrap();
+        assert!(service.drive_to(bus.id, at(7, 7)).is_err());
+
+        // Half a second covers two tiles of cost 1
+        assert_eq!(service.step(0.5), vec![(ann, at(1, 0)), (ann, at(2, 0))]);
+        // Without its road the bus detours over the cheap one
+        service.clear_road(&at(3, 0));
+        service.step(1.0);
+        let bus = service.get(bus.id).unwrap();
+        assert_eq!(bus.location, at(4, 0));
+        assert!(bus.route.is_empty());
+
+        // Cut off from everything it strands
+        service.drive_to(bus.id, at(0, 0)).unwrap();
+        service.clear_road(&at(4, 1));
+        service.step(0.1);
+        service.alight(bus.id, ann).unwrap();
+
+        let events: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(
+            events.last(),
+            Some(&DomainEvent::Vehicle(VehicleEvent::VehicleUnloaded {
+                vehicle: bus.id,
+                load: Load::Passenger(ann),
+            }))
+        );
+        assert!(
+            events.contains(&DomainEvent::Vehicle(VehicleEvent::VehicleStranded {
+                vehicle: bus.id,
+                location: at(4, 0),
+            }))
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use crate::domain::event::world_event::WorldEvent;
+use crate::domain::event::DomainEvent;
+use crate::domain::value_object::location::Location;
+use crate::domain::value_object::map_id::MapId;
+use crate::domain::value_object::region::Region;
+use crate::infrastructure::event_store::{publish_event, EventSender};
+
+/// The maps of the world and the one that's active with its active level. Coordinates coming in
+/// through the API are on the active level of the active map, so scripts written for a single
+/// map keep working on whichever is active.
+pub struct WorldService {
+    maps: Vec<MapId>,
+    active: MapId,
+    level: i32,
+    event_sender: EventSender,
+}
+
+impl WorldService {
+    // Starts with the main map, which exists in every game
+    pub fn new(event_sender: impl Into<EventSender>) -> Self {
+        WorldService {
+            maps: vec![MapId::main()],
+            active: MapId::main(),
+            level: 0,
+            event_sender: event_sender.into(),
+        }
+    }
+
+    // Add a map, fails if there is one of the same name
+    pub fn create(&mut self, name: &str) -> Result<MapId, String> {
+        if name.is_empty() {
+            return Err("A map needs a name".to_string());
+        }
+        if self.get(name).is_some() {
+            return Err(format!("Map {} already exists", name));
+        }
+        let map = MapId::new(name);
+        self.maps.push(map.clone());
+        self.publish(WorldEvent::MapCreated { map: map.clone() });
+        Ok(map)
+    }
+
+    pub fn get(&self, name: &str) -> Option<MapId> {
+        self.maps.iter().find(|map| map.as_str() == name).cloned()
+    }
+
+    // Maps in the order they were created, the main map first
+    pub fn all(&self) -> Vec<MapId> {
+        self.maps.clone()
+    }
+
+    pub fn active(&self) -> &MapId {
+        &self.active
+    }
+
+    // Make another map the active one on its ground level, returns false if it was already
+    pub fn switch(&mut self, name: &str) -> Result<bool, String> {
+        let map = self
+            .get(name)
+            .ok_or_else(|| format!("There is no map {}", name))?;
+        if map == self.active {
+            return Ok(false);
+        }
+        let from = std::mem::replace(&mut self.active, map.clone());
+        self.level = 0;
+        self.publish(WorldEvent::MapSwitched { from, to: map });
+        Ok(true)
+    }
+
+    pub fn level(&self) -> i32 {
+        self.level
+    }
+
+    // Make another level of the active map the active one, returns false if it was already.
+    // Levels need no creating, every map has as many as it's given tiles on.
+    pub fn switch_level(&mut self, z: i32) -> bool {
+        if z == self.level {
+            return false;
+        }
+        let from = std::mem::replace(&mut self.level, z);
+        self.publish(WorldEvent::LevelSwitched {
+            map: self.active.clone(),
+            from,
+            to: z,
+        });
+        true
+    }
+
+    // Whether a tile is on the active level of the active map, the one the frontends show
+    pub fn shows(&self, location: &Location) -> bool {
+        location.map == self.active && location.z == self.level
+    }
+
+    // A tile of the active level
+    pub fn location(&self, x: i32, y: i32) -> Location {
+        Location::on(&self.active, x, y).at_level(self.level)
+    }
+
+    // The tiles between two corners of the active level
+    pub fn region(&self, x1: i32, y1: i32, x2: i32, y2: i32) -> Region {
+        Region::on(&self.active, x1, y1, x2, y2).at_level(self.level)
+    }
+
+    // A tile of a map given by name and a level, the active ones for None
+    pub fn location_on(
+        &self,
+        map: Option<&str>,
+        z: Option<i32>,
+        x: i32,
+        y: i32,
+    ) -> Result<Location, String> {
+        let location = match map {
+            Some(name) => self
+                .get(name)
+                .map(|map| Location::on(&map, x, y))
+                .ok_or_else(|| format!("There is no map {}", name))?,
+            None => self.location(x, y),
+        };
+        Ok(location.at_level(z.unwrap_or(self.level)))
+    }
+
+    fn publish(&self, event: WorldEvent) {
+        publish_event(&self.event_sender, DomainEvent::World(event));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_coordinates_are_on_the_active_map() {
+        let (sender, receiver) = mpsc::channel();
+        let mut service = WorldService::new(sender);
+        let station = service.create("station_2").unwrap();
+        assert!(service.create("station_2").is_err());
+        assert!(service.switch("nowhere").is_err());
+        assert_eq!(service.location(1, 2), Location::new(1, 2));
+
+        assert!(service.switch("station_2").unwrap());
+        assert!(!service.switch("station_2").unwrap());
+        assert_eq!(service.location(1, 2), Location::on(&station, 1, 2));
+        assert!(!service.region(0, 0, 5, 5).contains(&Location::new(1, 2)));
+        assert_eq!(
+            service.location_on(Some("main"), None, 1, 2),
+            Ok(Location::new(1, 2))
+        );
+        assert_eq!(service.all(), vec![MapId::main(), station.clone()]);
+
+        // Levels are kept apart just like maps
+        assert!(service.switch_level(2));
+        assert!(!service.switch_level(2));
+        let upstairs = service.location(1, 2);
+        assert_eq!(upstairs, Location::on(&station, 1, 2).at_level(2));
+        assert!(service.shows(&upstairs));
+        assert!(!service.region(0, 0, 5, 5).contains(&upstairs.at_level(0)));
+        assert_eq!(
+            service.location_on(None, Some(0), 1, 2),
+            Ok(Location::on(&station, 1, 2))
+        );
+
+        let events: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(
+            events,
+            vec![
+                DomainEvent::World(WorldEvent::MapCreated {
+                    map: station.clone()
+                }),
+                DomainEvent::World(WorldEvent::MapSwitched {
+                    from: MapId::main(),
+                    to: station.clone(),
+                }),
+                DomainEvent::World(WorldEvent::LevelSwitched {
+                    map: station,
+                    from: 0,
+                    to: 2,
+                }),
+            ]
+        );
+    }
+}
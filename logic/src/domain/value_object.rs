@@ -1 +1,8 @@
+pub(crate) mod chunk;
+pub(crate) mod entity_ref;
 pub(crate) mod location;
+pub(crate) mod map_id;
+pub(crate) mod meta_value;
+pub(crate) mod region;
+pub(crate) mod road_network;
+pub(crate) mod visibility;
@@ -0,0 +1,59 @@
+use crate::domain::value_object::location::Location;
+use crate::domain::value_object::map_id::MapId;
+use crate::domain::value_object::region::Region;
+use serde::{Deserialize, Serialize};
+
+/// Tiles along each side of a chunk, the unit the world is unloaded and loaded back in
+pub const CHUNK_SIZE: i32 = 32;
+
+/// Square of `CHUNK_SIZE`² tiles on one level of a map, chunk (0, 0) covers tiles
+/// 0..CHUNK_SIZE on both axes. Ordered by map, level, row and column.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Chunk {
+    pub map: MapId,
+    pub z: i32,
+    pub y: i32,
+    pub x: i32,
+}
+
+impl Chunk {
+    pub fn new(map: &MapId, z: i32, x: i32, y: i32) -> Self {
+        Chunk {
+            map: map.clone(),
+            z,
+            y,
+            x,
+        }
+    }
+
+    // Chunk holding the tile
+    pub fn of(location: &Location) -> Self {
+        Chunk::new(
+            &location.map,
+            location.z,
+            location.x.div_euclid(CHUNK_SIZE),
+            location.y.div_euclid(CHUNK_SIZE),
+        )
+    }
+
+    // The tiles of the chunk
+    pub fn region(&self) -> Region {
+        let (x, y) = (self.x * CHUNK_SIZE, self.y * CHUNK_SIZE);
+        Region::on(&self.map, x, y, x + CHUNK_SIZE - 1, y + CHUNK_SIZE - 1).at_level(self.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_cover_the_tiles_around_the_origin() {
+        let chunk = Chunk::of(&Location::new(-1, CHUNK_SIZE).at_level(2));
+        assert_eq!(chunk, Chunk::new(&MapId::main(), 2, -1, 1));
+        let region = chunk.region();
+        assert!(region.contains(&Location::new(-CHUNK_SIZE, CHUNK_SIZE).at_level(2)));
+        assert!(!region.contains(&Location::new(0, CHUNK_SIZE).at_level(2)));
+        assert_eq!(region.area(), (CHUNK_SIZE * CHUNK_SIZE) as u64);
+    }
+}
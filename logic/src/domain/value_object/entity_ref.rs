@@ -0,0 +1,53 @@
+use crate::domain::entity::person::PersonId;
+use crate::domain::entity::zone::ZoneId;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Reference to any entity of the world, written `person:3` or `zone:1` in scripts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum EntityRef {
+    Person(PersonId),
+    Zone(ZoneId),
+}
+
+impl fmt::Display for EntityRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntityRef::Person(id) => write!(f, "person:{}", id.0),
+            EntityRef::Zone(id) => write!(f, "zone:{}", id.0),
+        }
+    }
+}
+
+impl FromStr for EntityRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{}' is not an entity reference like person:3", s);
+        let (kind, id) = s.split_once(':').ok_or_else(invalid)?;
+        let id: u32 = id.parse().map_err(|_| invalid())?;
+        match kind {
+            "person" => Ok(EntityRef::Person(PersonId(id))),
+            "zone" => Ok(EntityRef::Zone(ZoneId(id))),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entity_refs_round_trip_through_text() {
+        let entity: EntityRef = "person:3".parse().unwrap();
+        assert_eq!(entity, EntityRef::Person(PersonId(3)));
+        assert_eq!(entity.to_string(), "person:3");
+        let zone: EntityRef = "zone:1".parse().unwrap();
+        assert_eq!(zone.to_string(), "zone:1");
+        assert!("building:3".parse::<EntityRef>().is_err());
+        assert!("person:x".parse::<EntityRef>().is_err());
+        assert!("3".parse::<EntityRef>().is_err());
+    }
+}
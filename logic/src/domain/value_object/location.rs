@@ -1,5 +1,66 @@
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+use crate::domain::value_object::map_id::MapId;
+use serde::{Deserialize, Serialize};
+
+/// A tile on one of the levels of a map of the world
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Location {
     pub x: i32,
     pub y: i32,
+    /// Level the tile is on, 0 is the ground level
+    pub z: i32,
+    pub map: MapId,
+}
+
+impl Location {
+    // A tile on the ground level of the main map
+    pub fn new(x: i32, y: i32) -> Self {
+        Location::on(&MapId::main(), x, y)
+    }
+
+    // A tile on the ground level of a map
+    pub fn on(map: &MapId, x: i32, y: i32) -> Self {
+        Location {
+            x,
+            y,
+            z: 0,
+            map: map.clone(),
+        }
+    }
+
+    // The same tile on another level
+    pub fn at_level(&self, z: i32) -> Location {
+        Location { z, ..self.clone() }
+    }
+
+    // Another tile on the same level
+    pub fn offset(&self, dx: i32, dy: i32) -> Location {
+        Location {
+            x: self.x + dx,
+            y: self.y + dy,
+            ..self.clone()
+        }
+    }
+
+    // Whether both tiles are on the same level of the same map, only then one can walk to the
+    // other. Stairs and lifts between levels are portals.
+    pub fn same_level(&self, other: &Location) -> bool {
+        self.z == other.z && self.map == other.map
+    }
+
+    // Neighboring tile closest to the target, diagonals included. There is no walking over to
+    // another map or level, towards a tile there the location stays where it is.
+    pub fn step_towards(&self, target: &Location) -> Location {
+        if !self.same_level(target) {
+            return self.clone();
+        }
+        self.offset((target.x - self.x).signum(), (target.y - self.y).signum())
+    }
+
+    // Steps it takes to walk to the target, diagonals included, u32::MAX on another map or level
+    pub fn distance(&self, target: &Location) -> u32 {
+        if !self.same_level(target) {
+            return u32::MAX;
+        }
+        self.x.abs_diff(target.x).max(self.y.abs_diff(target.y))
+    }
 }
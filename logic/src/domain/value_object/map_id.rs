@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, LazyLock};
+
+/// Name of the map every game starts on
+pub const MAIN_MAP: &str = "main";
+
+/// Names a map of the world, like "main" or "station_2". Cheap to clone, so every location can
+/// carry the map it's on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct MapId(Arc<str>);
+
+impl MapId {
+    pub fn new(name: &str) -> Self {
+        MapId(Arc::from(name))
+    }
+
+    // Shared by all locations on the main map instead of each allocating its own name
+    pub fn main() -> Self {
+        static MAIN: LazyLock<MapId> = LazyLock::new(|| MapId::new(MAIN_MAP));
+        MAIN.clone()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for MapId {
+    fn default() -> Self {
+        MapId::main()
+    }
+}
+
+impl fmt::Display for MapId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+/// Value of a metadata entry mods attach to entities
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MetaValue {
+    Boolean(bool),
+    Number(f64),
+    Text(String),
+}
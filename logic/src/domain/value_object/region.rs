@@ -0,0 +1,83 @@
+use crate::domain::value_object::location::Location;
+use crate::domain::value_object::map_id::MapId;
+use serde::{Deserialize, Serialize};
+
+/// Rectangle of tiles between two corners on one level, both inclusive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Region {
+    min: Location,
+    max: Location,
+}
+
+impl Region {
+    /// A region of the main map, the corners may be given in any order
+    pub fn new(x1: i32, y1: i32, x2: i32, y2: i32) -> Self {
+        Region::on(&MapId::main(), x1, y1, x2, y2)
+    }
+
+    /// A region of the ground level of another map
+    pub fn on(map: &MapId, x1: i32, y1: i32, x2: i32, y2: i32) -> Self {
+        Region {
+            min: Location::on(map, x1.min(x2), y1.min(y2)),
+            max: Location::on(map, x1.max(x2), y1.max(y2)),
+        }
+    }
+
+    /// The same rectangle on another map, on the same level
+    pub fn on_map(&self, map: &MapId) -> Self {
+        Region {
+            min: Location::on(map, self.min.x, self.min.y).at_level(self.min.z),
+            max: Location::on(map, self.max.x, self.max.y).at_level(self.max.z),
+        }
+    }
+
+    /// The same rectangle on another level
+    pub fn at_level(&self, z: i32) -> Self {
+        Region {
+            min: self.min.at_level(z),
+            max: self.max.at_level(z),
+        }
+    }
+
+    pub fn map(&self) -> &MapId {
+        &self.min.map
+    }
+
+    pub fn level(&self) -> i32 {
+        self.min.z
+    }
+
+    pub fn contains(&self, location: &Location) -> bool {
+        location.same_level(&self.min)
+            && (self.min.x..=self.max.x).contains(&location.x)
+            && (self.min.y..=self.max.y).contains(&location.y)
+    }
+
+    /// Whether the regions share at least one tile
+    pub fn overlaps(&self, other: &Region) -> bool {
+        self.min.same_level(&other.min)
+            && self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+    }
+
+    /// The corners with the smallest and the largest coordinates
+    pub fn corners(&self) -> (&Location, &Location) {
+        (&self.min, &self.max)
+    }
+
+    /// Number of tiles in the region
+    pub fn area(&self) -> u64 {
+        (self.max.x as i64 - self.min.x as i64 + 1) as u64
+            * (self.max.y as i64 - self.min.y as i64 + 1) as u64
+    }
+
+    /// Every tile of the region, row by row
+    pub fn locations(&self) -> impl Iterator<Item = Location> + '_ {
+        (self.min.y..=self.max.y).flat_map(move |y| {
+            (self.min.x..=self.max.x)
+                .map(move |x| Location::on(&self.min.map, x, y).at_level(self.min.z))
+        })
+    }
+}
@@ -0,0 +1,109 @@
+use crate::domain::value_object::location::Location;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Added to the cost of a route for every turn, so vehicles keep to straight roads
+pub const TURN_COST: f64 = 0.5;
+
+// A tile reached heading in a direction, turns cost depending on where a vehicle came from
+type Node = (Location, (i32, i32));
+
+/// Tiles vehicles can drive on with what crossing each costs. Vehicles only drive between
+/// neighbors sharing an edge, never diagonally.
+#[derive(Debug, Clone, Default)]
+pub struct RoadNetwork {
+    costs: HashMap<Location, f64>,
+}
+
+impl RoadNetwork {
+    pub fn set(&mut self, location: Location, cost: f64) {
+        self.costs.insert(location, cost);
+    }
+
+    pub fn remove(&mut self, location: &Location) -> bool {
+        self.costs.remove(location).is_some()
+    }
+
+    pub fn cost(&self, location: &Location) -> Option<f64> {
+        self.costs.get(location).copied()
+    }
+
+    // Cheapest tiles to drive over from one road tile to another, the start left out. None if
+    // either isn't a road or no road connects them.
+    pub fn route(&self, from: &Location, to: &Location) -> Option<Vec<Location>> {
+        if !self.costs.contains_key(from) || !self.costs.contains_key(to) {
+            return None;
+        }
+        let start: Node = (from.clone(), (0, 0));
+        let mut best = HashMap::from([(start.clone(), 0.0)]);
+        let mut previous: HashMap<Node, Node> = HashMap::new();
+        let mut open = BinaryHeap::from([Open {
+            cost: 0.0,
+            node: start,
+        }]);
+        while let Some(Open { cost, node }) = open.pop() {
+            if best.get(&node).is_some_and(|&known| cost > known) {
+                continue;
+            }
+            if node.0 == *to {
+                let mut route = Vec::new();
+                let mut current = node;
+                while let Some(before) = previous.get(&current) {
+                    route.push(current.0);
+                    current = before.clone();
+                }
+                route.reverse();
+                return Some(route);
+            }
+            let (location, heading) = &node;
+            for direction in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let next = location.offset(direction.0, direction.1);
+                let Some(tile_cost) = self.cost(&next) else {
+                    continue;
+                };
+                let turn = if *heading != (0, 0) && *heading != direction {
+                    TURN_COST
+                } else {
+                    0.0
+                };
+                let next_cost = cost + tile_cost + turn;
+                let next: Node = (next, direction);
+                if best.get(&next).is_none_or(|&known| next_cost < known) {
+                    best.insert(next.clone(), next_cost);
+                    previous.insert(next.clone(), node.clone());
+                    open.push(Open {
+                        cost: next_cost,
+                        node: next,
+                    });
+                }
+            }
+        }
+        None
+    }
+}
+
+// Entry of the open list, the cheapest pops first
+struct Open {
+    cost: f64,
+    node: Node,
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost.total_cmp(&other.cost) == Ordering::Equal
+    }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
@@ -0,0 +1,21 @@
+use std::fmt;
+
+/// What a viewer knows about a tile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    /// Never seen
+    Unexplored,
+    /// Seen before, but not in sight right now
+    Explored,
+    Visible,
+}
+
+impl fmt::Display for Visibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Visibility::Unexplored => write!(f, "unexplored"),
+            Visibility::Explored => write!(f, "explored"),
+            Visibility::Visible => write!(f, "visible"),
+        }
+    }
+}
@@ -0,0 +1,473 @@
+use crate::domain::entity::faction::Faction;
+use crate::domain::entity::goal::Goal;
+use crate::domain::entity::group::Group;
+use crate::domain::entity::map_lock::MapLock;
+use crate::domain::entity::person::Person;
+use crate::domain::entity::portal::Portal;
+use crate::domain::entity::task::{Task, TaskKind};
+use crate::domain::entity::vehicle::Vehicle;
+use crate::domain::event::faction_event::FactionEvent;
+use crate::domain::event::fog_event::FogEvent;
+use crate::domain::event::goal_event::GoalEvent;
+use crate::domain::event::group_event::GroupEvent;
+use crate::domain::event::map_event::MapEvent;
+use crate::domain::event::person_event::{PersonEvent, PersonMove};
+use crate::domain::event::portal_event::PortalEvent;
+use crate::domain::event::scenario_event::ScenarioEvent;
+use crate::domain::event::stream_event::StreamEvent;
+use crate::domain::event::tag_event::TagEvent;
+use crate::domain::event::task_event::TaskEvent;
+use crate::domain::event::vehicle_event::{Load, VehicleEvent};
+use crate::domain::event::world_event::WorldEvent;
+use crate::domain::event::DomainEvent;
+use crate::domain::value_object::chunk::Chunk;
+use crate::domain::value_object::entity_ref::EntityRef;
+use crate::domain::value_object::location::Location;
+use crate::domain::value_object::meta_value::MetaValue;
+use dto::{
+    ChunkDto, EventDto, FactionDto, GoalDto, GroupDto, LocationDto, MapLockDto, MetaValueDto,
+    PersonDto, PersonMoveDto, PortalDto, TaskDto, VehicleDto,
+};
+
+impl From<&Location> for LocationDto {
+    fn from(location: &Location) -> Self {
+        LocationDto {
+            x: location.x,
+            y: location.y,
+            z: location.z,
+            map: location.map.to_string(),
+        }
+    }
+}
+
+impl From<&Person> for PersonDto {
+    fn from(person: &Person) -> Self {
+        PersonDto {
+            id: person.id.0,
+            name: person.name.clone(),
+            location: (&person.location).into(),
+        }
+    }
+}
+
+impl From<&Goal> for GoalDto {
+    fn from(goal: &Goal) -> Self {
+        GoalDto {
+            id: goal.id.clone(),
+            name: goal.name.clone(),
+            progress: goal.progress,
+            target: goal.target,
+            completed: goal.completed,
+        }
+    }
+}
+
+impl From<&Faction> for FactionDto {
+    fn from(faction: &Faction) -> Self {
+        let [r, g, b] = faction.color;
+        FactionDto {
+            id: faction.id.clone(),
+            kind: faction.kind.to_string(),
+            name: faction.name.clone(),
+            color: format!("#{:02x}{:02x}{:02x}", r, g, b),
+        }
+    }
+}
+
+impl From<&Task> for TaskDto {
+    fn from(task: &Task) -> Self {
+        let (location, item, from, steps) = match &task.kind {
+            TaskKind::GoTo { target } => (target, None, None, None),
+            TaskKind::WorkAt { location, steps } => (location, None, None, Some(*steps)),
+            TaskKind::Haul { item, from, to } => (to, Some(item.clone()), Some(from.into()), None),
+            TaskKind::Patrol { route } => (&route[task.waypoint], None, None, None),
+        };
+        let route = match &task.kind {
+            TaskKind::Patrol { route } => Some(route.iter().map(LocationDto::from).collect()),
+            _ => None,
+        };
+        TaskDto {
+            id: task.id.0,
+            person_id: task.person_id.0,
+            kind: task.kind.name().to_string(),
+            priority: task.priority,
+            interruptible: task.interruptible,
+            started: task.started,
+            location: location.into(),
+            item,
+            from,
+            steps,
+            route,
+            worked: task.worked,
+            carrying: task.carrying,
+        }
+    }
+}
+
+impl From<&Vehicle> for VehicleDto {
+    fn from(vehicle: &Vehicle) -> Self {
+        VehicleDto {
+            id: vehicle.id.0,
+            location: (&vehicle.location).into(),
+            capacity: vehicle.capacity as u32,
+            passengers: vehicle.passengers.iter().map(|person| person.0).collect(),
+            cargo: vehicle.cargo.clone(),
+            destination: vehicle.destination().map(LocationDto::from),
+        }
+    }
+}
+
+impl From<&Portal> for PortalDto {
+    fn from(portal: &Portal) -> Self {
+        PortalDto {
+            id: portal.id.0,
+            from: (&portal.from).into(),
+            to: (&portal.to).into(),
+        }
+    }
+}
+
+impl From<&MapLock> for MapLockDto {
+    fn from(lock: &MapLock) -> Self {
+        let (min, max) = lock.region.corners();
+        MapLockDto {
+            id: lock.id.0,
+            owner: lock.owner.clone(),
+            min: min.into(),
+            max: max.into(),
+        }
+    }
+}
+
+impl From<&Chunk> for ChunkDto {
+    fn from(chunk: &Chunk) -> Self {
+        ChunkDto {
+            map: chunk.map.to_string(),
+            z: chunk.z,
+            x: chunk.x,
+            y: chunk.y,
+        }
+    }
+}
+
+impl From<&Group> for GroupDto {
+    fn from(group: &Group) -> Self {
+        GroupDto {
+            id: group.id.0,
+            name: group.name.clone(),
+            kind: group.kind.to_string(),
+            members: group.members.iter().map(|person| person.0).collect(),
+            inventory: group.inventory.clone(),
+        }
+    }
+}
+
+// A passenger as its `person_id`, an item as its name
+fn load_fields(load: &Load) -> (Option<u32>, Option<String>) {
+    match load {
+        Load::Passenger(person_id) => (Some(person_id.0), None),
+        Load::Item(item) => (None, Some(item.clone())),
+    }
+}
+
+impl From<&PersonMove> for PersonMoveDto {
+    fn from(person_move: &PersonMove) -> Self {
+        PersonMoveDto {
+            person_id: person_move.person_id.0,
+            from_location: (&person_move.from_location).into(),
+            to_location: (&person_move.to_location).into(),
+        }
+    }
+}
+
+impl From<&MetaValue> for MetaValueDto {
+    fn from(value: &MetaValue) -> Self {
+        match value {
+            MetaValue::Boolean(b) => MetaValueDto::Boolean(*b),
+            MetaValue::Number(n) => MetaValueDto::Number(*n),
+            MetaValue::Text(s) => MetaValueDto::Text(s.clone()),
+        }
+    }
+}
+
+impl From<&DomainEvent> for EventDto {
+    fn from(event: &DomainEvent) -> Self {
+        match event {
+            DomainEvent::Person(PersonEvent::PersonCreated {
+                person_id,
+                name,
+                location,
+            }) => EventDto::PersonCreated {
+                person_id: person_id.0,
+                name: name.clone(),
+                location: location.into(),
+            },
+            DomainEvent::Person(PersonEvent::PersonMoved {
+                person_id,
+                from_location,
+                to_location,
+            }) => EventDto::PersonMoved {
+                person_id: person_id.0,
+                from_location: from_location.into(),
+                to_location: to_location.into(),
+            },
+            DomainEvent::Person(PersonEvent::PersonsMoved { moves }) => EventDto::PersonsMoved {
+                moves: moves.iter().map(PersonMoveDto::from).collect(),
+            },
+            DomainEvent::Tag(TagEvent::TagAdded { entity, tag }) => EventDto::TagAdded {
+                entity: entity.to_string(),
+                tag: tag.clone(),
+            },
+            DomainEvent::Tag(TagEvent::TagRemoved { entity, tag }) => EventDto::TagRemoved {
+                entity: entity.to_string(),
+                tag: tag.clone(),
+            },
+            DomainEvent::Tag(TagEvent::MetadataSet { entity, key, value }) => {
+                EventDto::MetadataSet {
+                    entity: entity.to_string(),
+                    key: key.clone(),
+                    value: value.into(),
+                }
+            }
+            DomainEvent::Tag(TagEvent::MetadataRemoved { entity, key }) => {
+                EventDto::MetadataRemoved {
+                    entity: entity.to_string(),
+                    key: key.clone(),
+                }
+            }
+            DomainEvent::Scenario(ScenarioEvent::ScenarioEnded {
+                outcome,
+                reason,
+                tick,
+            }) => EventDto::ScenarioEnded {
+                outcome: outcome.to_string(),
+                reason: reason.clone(),
+                tick: *tick,
+            },
+            DomainEvent::Goal(GoalEvent::GoalProgressed {
+                goal,
+                progress,
+                target,
+            }) => EventDto::GoalProgressed {
+                goal: goal.clone(),
+                progress: *progress,
+                target: *target,
+            },
+            DomainEvent::Goal(GoalEvent::GoalCompleted { goal }) => {
+                EventDto::GoalCompleted { goal: goal.clone() }
+            }
+            DomainEvent::Fog(FogEvent::TilesRevealed { viewer, tiles }) => {
+                EventDto::TilesRevealed {
+                    viewer: viewer.clone(),
+                    tiles: tiles.iter().map(LocationDto::from).collect(),
+                }
+            }
+            DomainEvent::Fog(FogEvent::TilesHidden { viewer, tiles }) => EventDto::TilesHidden {
+                viewer: viewer.clone(),
+                tiles: tiles.iter().map(LocationDto::from).collect(),
+            },
+            DomainEvent::Faction(FactionEvent::FactionCreated { faction, kind }) => {
+                EventDto::FactionCreated {
+                    faction: faction.clone(),
+                    kind: kind.to_string(),
+                }
+            }
+            DomainEvent::Faction(FactionEvent::ZoneCreated { zone, region }) => {
+                let (min, max) = region.corners();
+                EventDto::ZoneCreated {
+                    zone: EntityRef::Zone(*zone).to_string(),
+                    min: min.into(),
+                    max: max.into(),
+                }
+            }
+            DomainEvent::Faction(FactionEvent::OwnershipTransferred { entity, from, to }) => {
+                EventDto::OwnershipTransferred {
+                    entity: entity.to_string(),
+                    from: from.clone(),
+                    to: to.clone(),
+                }
+            }
+            DomainEvent::Task(TaskEvent::TaskStarted {
+                task,
+                person_id,
+                kind,
+            }) => EventDto::TaskStarted {
+                task: task.0,
+                person_id: person_id.0,
+                kind: kind.clone(),
+            },
+            DomainEvent::Task(TaskEvent::TaskCompleted { task, person_id }) => {
+                EventDto::TaskCompleted {
+                    task: task.0,
+                    person_id: person_id.0,
+                }
+            }
+            DomainEvent::Task(TaskEvent::TaskAbandoned {
+                task,
+                person_id,
+                reason,
+            }) => EventDto::TaskAbandoned {
+                task: task.0,
+                person_id: person_id.0,
+                reason: reason.clone(),
+            },
+            DomainEvent::Vehicle(VehicleEvent::VehicleCreated { vehicle, location }) => {
+                EventDto::VehicleCreated {
+                    vehicle: vehicle.0,
+                    location: location.into(),
+                }
+            }
+            DomainEvent::Vehicle(VehicleEvent::VehicleMoved {
+                vehicle,
+                from_location,
+                to_location,
+            }) => EventDto::VehicleMoved {
+                vehicle: vehicle.0,
+                from_location: from_location.into(),
+                to_location: to_location.into(),
+            },
+            DomainEvent::Vehicle(VehicleEvent::VehicleArrived { vehicle, location }) => {
+                EventDto::VehicleArrived {
+                    vehicle: vehicle.0,
+                    location: location.into(),
+                }
+            }
+            DomainEvent::Vehicle(VehicleEvent::VehicleStranded { vehicle, location }) => {
+                EventDto::VehicleStranded {
+                    vehicle: vehicle.0,
+                    location: location.into(),
+                }
+            }
+            DomainEvent::Vehicle(VehicleEvent::VehicleLoaded { vehicle, load }) => {
+                let (person_id, item) = load_fields(load);
+                EventDto::VehicleLoaded {
+                    vehicle: vehicle.0,
+                    person_id,
+                    item,
+                }
+            }
+            DomainEvent::Vehicle(VehicleEvent::VehicleUnloaded { vehicle, load }) => {
+                let (person_id, item) = load_fields(load);
+                EventDto::VehicleUnloaded {
+                    vehicle: vehicle.0,
+                    person_id,
+                    item,
+                }
+            }
+            DomainEvent::Portal(PortalEvent::PortalCreated {
+                portal,
+                from_location,
+                to_location,
+            }) => EventDto::PortalCreated {
+                portal: portal.0,
+                from_location: from_location.into(),
+                to_location: to_location.into(),
+            },
+            DomainEvent::Portal(PortalEvent::PortalRemoved { portal }) => {
+                EventDto::PortalRemoved { portal: portal.0 }
+            }
+            DomainEvent::Portal(PortalEvent::PortalTraversed {
+                portal,
+                person_id,
+                from_location,
+                to_location,
+            }) => EventDto::PortalTraversed {
+                portal: portal.0,
+                person_id: person_id.0,
+                from_location: from_location.into(),
+                to_location: to_location.into(),
+            },
+            DomainEvent::World(WorldEvent::MapCreated { map }) => EventDto::MapCreated {
+                map: map.to_string(),
+            },
+            DomainEvent::World(WorldEvent::MapSwitched { from, to }) => EventDto::MapSwitched {
+                from: from.to_string(),
+                to: to.to_string(),
+            },
+            DomainEvent::World(WorldEvent::LevelSwitched { map, from, to }) => {
+                EventDto::LevelSwitched {
+                    map: map.to_string(),
+                    from: *from,
+                    to: *to,
+                }
+            }
+            DomainEvent::Group(GroupEvent::GroupCreated {
+                group,
+                kind,
+                name,
+                shared_inventory,
+            }) => EventDto::GroupCreated {
+                group: group.0,
+                kind: kind.to_string(),
+                name: name.clone(),
+                shared_inventory: *shared_inventory,
+            },
+            DomainEvent::Group(GroupEvent::GroupJoined { group, person_id }) => {
+                EventDto::GroupJoined {
+                    group: group.0,
+                    person_id: person_id.0,
+                }
+            }
+            DomainEvent::Group(GroupEvent::GroupLeft { group, person_id }) => EventDto::GroupLeft {
+                group: group.0,
+                person_id: person_id.0,
+            },
+            DomainEvent::Group(GroupEvent::GroupDisbanded { group }) => {
+                EventDto::GroupDisbanded { group: group.0 }
+            }
+            DomainEvent::Group(GroupEvent::GroupItemStored { group, item }) => {
+                EventDto::GroupItemStored {
+                    group: group.0,
+                    item: item.clone(),
+                }
+            }
+            DomainEvent::Group(GroupEvent::GroupItemTaken { group, item }) => {
+                EventDto::GroupItemTaken {
+                    group: group.0,
+                    item: item.clone(),
+                }
+            }
+            DomainEvent::Map(MapEvent::TilePlaced {
+                location,
+                layer,
+                tile,
+            }) => EventDto::TilePlaced {
+                location: location.into(),
+                layer: layer.clone(),
+                tile: *tile,
+            },
+            DomainEvent::Map(MapEvent::TileRemoved { location, layer }) => EventDto::TileRemoved {
+                location: location.into(),
+                layer: layer.clone(),
+            },
+            DomainEvent::Map(MapEvent::RegionLocked {
+                lock,
+                region,
+                owner,
+            }) => {
+                let (min, max) = region.corners();
+                EventDto::RegionLocked {
+                    lock: lock.0,
+                    owner: owner.clone(),
+                    min: min.into(),
+                    max: max.into(),
+                }
+            }
+            DomainEvent::Map(MapEvent::RegionUnlocked { lock }) => {
+                EventDto::RegionUnlocked { lock: lock.0 }
+            }
+            DomainEvent::Stream(StreamEvent::ChunkUnloaded { chunk, persons }) => {
+                EventDto::ChunkUnloaded {
+                    chunk: chunk.into(),
+                    persons: persons.iter().map(|person| person.0).collect(),
+                }
+            }
+            DomainEvent::Stream(StreamEvent::ChunkLoaded { chunk, persons }) => {
+                EventDto::ChunkLoaded {
+                    chunk: chunk.into(),
+                    persons: persons.iter().map(PersonDto::from).collect(),
+                }
+            }
+        }
+    }
+}
@@ -1,2 +1,6 @@
+pub(crate) mod chunk_store;
+pub(crate) mod codec;
+pub(crate) mod event_log;
 pub(crate) mod event_store;
 pub(crate) mod projection;
+pub(crate) mod time_series;
@@ -0,0 +1,100 @@
+use crate::domain::entity::person::Person;
+use crate::domain::value_object::chunk::Chunk;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Directory the unloaded chunks are written to, one JSON file per chunk holding the persons in
+/// it. Files are deleted once their chunk is loaded again.
+pub(crate) struct ChunkStore {
+    dir: PathBuf,
+    // Whether the directory is removed with the store, for the temporary ones
+    owned: bool,
+}
+
+impl ChunkStore {
+    // The directory is created with the first chunk written to it
+    pub(crate) fn new(dir: impl Into<PathBuf>) -> Self {
+        ChunkStore {
+            dir: dir.into(),
+            owned: false,
+        }
+    }
+
+    // A directory of its own in the temp dir, so several APIs of one process don't share chunks.
+    // It's removed along with the store.
+    pub(crate) fn temporary() -> Self {
+        static STORES: AtomicUsize = AtomicUsize::new(0);
+        let store = STORES.fetch_add(1, Ordering::Relaxed);
+        ChunkStore {
+            dir: std::env::temp_dir().join(format!("sb5s_chunks_{}_{}", std::process::id(), store)),
+            owned: true,
+        }
+    }
+
+    pub(crate) fn write(&self, chunk: &Chunk, persons: &[Person]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let json = serde_json::to_vec(persons).map_err(io::Error::other)?;
+        fs::write(self.path(chunk), json)
+    }
+
+    // Read the persons of a chunk back, deleting its file
+    pub(crate) fn take(&self, chunk: &Chunk) -> io::Result<Vec<Person>> {
+        let path = self.path(chunk);
+        let persons = serde_json::from_slice(&fs::read(&path)?).map_err(io::Error::other)?;
+        fs::remove_file(path)?;
+        Ok(persons)
+    }
+
+    // The map name is hex encoded, maps may be named anything including `../`
+    fn path(&self, chunk: &Chunk) -> PathBuf {
+        let map: String = chunk
+            .map
+            .as_str()
+            .bytes()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        self.dir
+            .join(format!("{}_{}_{}_{}.json", map, chunk.z, chunk.x, chunk.y))
+    }
+}
+
+impl Drop for ChunkStore {
+    fn drop(&mut self) {
+        if self.owned {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::value_object::map_id::MapId;
+
+    #[test]
+    fn test_temporary_store_is_removed_with_its_chunks() {
+        let store = ChunkStore::temporary();
+        let chunk = Chunk::new(&MapId::main(), 0, 1, 2);
+        store.write(&chunk, &[]).unwrap();
+        let dir = store.dir.clone();
+        assert!(dir.exists());
+
+        drop(store);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_chunks_stay_in_the_directory_whatever_the_map_is_named() {
+        let store = ChunkStore::temporary();
+        let chunk = Chunk::new(&MapId::new("../../escaped/x"), 0, 1, 2);
+        store.write(&chunk, &[]).unwrap();
+        let path = store.path(&chunk);
+        assert_eq!(path.parent(), Some(store.dir.as_path()));
+        assert!(path.exists());
+
+        assert!(store.take(&chunk).unwrap().is_empty());
+        assert!(!path.exists());
+    }
+}
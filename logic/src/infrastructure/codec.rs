@@ -0,0 +1,157 @@
+use crate::domain::event::DomainEvent;
+use crate::infrastructure::event_log::{read_event, EventLog, FsyncPolicy};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+/// Encoding of the events written to disk
+pub trait EventCodec {
+    /// Append one event
+    fn write(&self, out: &mut dyn Write, event: &DomainEvent) -> io::Result<()>;
+
+    /// Read the next event, None at the end of the input
+    fn read(&self, input: &mut dyn BufRead) -> io::Result<Option<DomainEvent>>;
+}
+
+/// One JSON object per line, easy to read and to grep while debugging
+pub struct JsonCodec;
+
+impl EventCodec for JsonCodec {
+    fn write(&self, out: &mut dyn Write, event: &DomainEvent) -> io::Result<()> {
+        serde_json::to_writer(&mut *out, event)?;
+        out.write_all(b"\n")
+    }
+
+    fn read(&self, input: &mut dyn BufRead) -> io::Result<Option<DomainEvent>> {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&line)?))
+    }
+}
+
+/// Compact bincode records, a fraction of the size of JSON and quicker to read back
+pub struct BinaryCodec;
+
+impl EventCodec for BinaryCodec {
+    fn write(&self, out: &mut dyn Write, event: &DomainEvent) -> io::Result<()> {
+        bincode::serialize_into(out, event).map_err(io::Error::other)
+    }
+
+    fn read(&self, input: &mut dyn BufRead) -> io::Result<Option<DomainEvent>> {
+        if input.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+        bincode::deserialize_from(input)
+            .map(Some)
+            .map_err(io::Error::other)
+    }
+}
+
+/// The codecs the event log can be written with, chosen in the config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Codec {
+    #[default]
+    Json,
+    Binary,
+}
+
+impl Codec {
+    pub const ALL: [Codec; 2] = [Codec::Json, Codec::Binary];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|codec| codec.name() == name)
+    }
+
+    /// The codec of a file by its extension, None if it has neither codec's
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        Self::ALL
+            .into_iter()
+            .find(|codec| codec.extension() == extension)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Codec::Json => "json",
+            Codec::Binary => "binary",
+        }
+    }
+
+    /// Extension of the files written with the codec
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Codec::Json => "jsonl",
+            Codec::Binary => "bin",
+        }
+    }
+
+    pub fn codec(&self) -> &'static dyn EventCodec {
+        match self {
+            Codec::Json => &JsonCodec,
+            Codec::Binary => &BinaryCodec,
+        }
+    }
+}
+
+// Events converted with a single write
+const CONVERT_BATCH: usize = 1024;
+
+/// Rewrite the event log at `from` with another codec to `to`, returns the number of events
+pub fn convert(from: &Path, from_codec: Codec, to: &Path, to_codec: Codec) -> io::Result<usize> {
+    let mut input = BufReader::new(File::open(from)?);
+    let mut out = EventLog::create(to, to_codec, FsyncPolicy::Never)?;
+    let mut batch = Vec::with_capacity(CONVERT_BATCH);
+    let mut converted = 0;
+    while let Some(event) = read_event(&mut input, from_codec)? {
+        batch.push(event);
+        if batch.len() == CONVERT_BATCH {
+            out.append(&batch)?;
+            converted += batch.len();
+            batch.clear();
+        }
+    }
+    out.append(&batch)?;
+    Ok(converted + batch.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entity::person::PersonId;
+    use crate::domain::entity::task::TaskId;
+    use crate::domain::event::person_event::PersonEvent;
+    use crate::domain::event::task_event::TaskEvent;
+    use crate::domain::value_object::location::Location;
+
+    #[test]
+    fn test_events_survive_every_codec() {
+        let events = vec![
+            DomainEvent::Person(PersonEvent::PersonCreated {
+                person_id: PersonId(1),
+                name: "Ann".to_string(),
+                location: Location::new(3, -4),
+            }),
+            DomainEvent::Task(TaskEvent::TaskStarted {
+                task: TaskId(7),
+                person_id: PersonId(1),
+                kind: "haul".to_string(),
+            }),
+        ];
+        for codec in Codec::ALL {
+            let mut bytes = Vec::new();
+            for event in &events {
+                codec.codec().write(&mut bytes, event).unwrap();
+            }
+            let mut input = bytes.as_slice();
+            let mut read = Vec::new();
+            while let Some(event) = codec.codec().read(&mut input).unwrap() {
+                read.push(event);
+            }
+            assert_eq!(read, events, "{} codec", codec.name());
+        }
+        assert_eq!(Codec::from_name("binary"), Some(Codec::Binary));
+        assert_eq!(Codec::from_path(Path::new("run.jsonl")), Some(Codec::Json));
+    }
+}
@@ -0,0 +1,393 @@
+//! The append-only file the event store archives to
+
+use crate::domain::event::DomainEvent;
+use crate::infrastructure::codec::Codec;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+// Length and CRC-32 in front of every binary record, both little endian
+const HEADER_BYTES: usize = 8;
+
+// Larger records are taken for garbage rather than allocated for
+const MAX_RECORD_BYTES: usize = 64 * 1024 * 1024;
+
+/// How often the event log is flushed to the disk itself rather than left to the OS, trading
+/// the speed of the writes for the events surviving a power loss
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    /// Leave it to the OS, a crash of the game loses nothing but a power loss may
+    Never,
+    /// After every batch of events written
+    #[default]
+    EveryWrite,
+    /// Once at least this many events were written since the last time
+    EveryEvents(usize),
+}
+
+impl FsyncPolicy {
+    /// "never", "write" or a number of events
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "never" => Some(FsyncPolicy::Never),
+            "write" => Some(FsyncPolicy::EveryWrite),
+            _ => name
+                .parse()
+                .ok()
+                .filter(|events| *events > 0)
+                .map(FsyncPolicy::EveryEvents),
+        }
+    }
+}
+
+/// What `recover` found in an event log
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Recovery {
+    /// Whole records kept
+    pub recovered: usize,
+    /// Records from the first broken one to the end, cut off
+    pub lost: usize,
+    /// Bytes cut off the end of the file
+    pub truncated_bytes: u64,
+}
+
+impl fmt::Display for Recovery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} events recovered", self.recovered)?;
+        if self.lost > 0 {
+            write!(
+                f,
+                ", {} lost ({} bytes cut off)",
+                self.lost, self.truncated_bytes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// An event log open for appending. Every record carries the CRC-32 of the encoded event, so a
+/// torn or damaged record is told apart. Binary records are the length and the CRC-32 followed
+/// by the event, JSON ones stay a line per event with the CRC-32 in hex and a space before the
+/// JSON, so the log can still be read and grepped as text.
+pub(crate) struct EventLog {
+    file: File,
+    codec: Codec,
+    pub(crate) fsync: FsyncPolicy,
+    // Events written since the last sync
+    unsynced: usize,
+}
+
+impl EventLog {
+    /// Start an empty log at the path, replacing what was there
+    pub(crate) fn create(path: &Path, codec: Codec, fsync: FsyncPolicy) -> io::Result<Self> {
+        Ok(Self {
+            file: File::create(path)?,
+            codec,
+            fsync,
+            unsynced: 0,
+        })
+    }
+
+    /// Append the events with a single write, synced as the policy says
+    pub(crate) fn append(&mut self, events: &[DomainEvent]) -> io::Result<()> {
+        let mut records = Vec::new();
+        let mut payload = Vec::new();
+        for event in events {
+            payload.clear();
+            self.codec.codec().write(&mut payload, event)?;
+            match self.codec {
+                Codec::Json => {
+                    let json = payload.strip_suffix(b"\n").unwrap_or(&payload);
+                    write!(records, "{:08x} ", crc32fast::hash(json))?;
+                    records.extend_from_slice(&payload);
+                }
+                Codec::Binary => {
+                    let length = u32::try_from(payload.len()).map_err(|_| {
+                        io::Error::new(io::ErrorKind::InvalidInput, "Event too large")
+                    })?;
+                    records.extend_from_slice(&length.to_le_bytes());
+                    records.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+                    records.extend_from_slice(&payload);
+                }
+            }
+        }
+        self.file.write_all(&records)?;
+
+        self.unsynced += events.len();
+        let due = match self.fsync {
+            FsyncPolicy::Never => false,
+            FsyncPolicy::EveryWrite => true,
+            FsyncPolicy::EveryEvents(events) => self.unsynced >= events,
+        };
+        if due {
+            self.file.sync_data()?;
+            self.unsynced = 0;
+        }
+        Ok(())
+    }
+}
+
+// The payload of the next record and the bytes the record takes, None at the end of the
+// input. A torn record fails with `UnexpectedEof`, one failing its checksum with `InvalidData`.
+fn read_record(input: &mut dyn BufRead, codec: Codec) -> io::Result<Option<(Vec<u8>, usize)>> {
+    match codec {
+        Codec::Json => read_line_record(input),
+        Codec::Binary => read_framed_record(input),
+    }
+}
+
+fn read_framed_record(input: &mut dyn BufRead) -> io::Result<Option<(Vec<u8>, usize)>> {
+    if input.fill_buf()?.is_empty() {
+        return Ok(None);
+    }
+    let mut header = [0; HEADER_BYTES];
+    input.read_exact(&mut header)?;
+    let (length, crc) = parse_header(&header);
+    if length > MAX_RECORD_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Event record of {} bytes", length),
+        ));
+    }
+    let mut payload = vec![0; length];
+    input.read_exact(&mut payload)?;
+    check(&payload, crc)?;
+    Ok(Some((payload, HEADER_BYTES + length)))
+}
+
+// A line of the CRC-32 in hex, a space and the JSON of the event
+fn read_line_record(input: &mut dyn BufRead) -> io::Result<Option<(Vec<u8>, usize)>> {
+    let mut line = Vec::new();
+    input
+        .take(MAX_RECORD_BYTES as u64 + 1)
+        .read_until(b'\n', &mut line)?;
+    if line.is_empty() {
+        return Ok(None);
+    }
+    if line.len() > MAX_RECORD_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Event record of more than {} bytes", MAX_RECORD_BYTES),
+        ));
+    }
+    let Some(record) = line.strip_suffix(b"\n") else {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Event record ends before its line does",
+        ));
+    };
+    let space = record.iter().position(|byte| *byte == b' ');
+    let crc = space
+        .and_then(|space| std::str::from_utf8(&record[..space]).ok())
+        .and_then(|crc| u32::from_str_radix(crc, 16).ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Event record starts without a checksum",
+            )
+        })?;
+    let payload = record[space.unwrap_or_default() + 1..].to_vec();
+    check(&payload, crc)?;
+    Ok(Some((payload, line.len())))
+}
+
+fn check(payload: &[u8], crc: u32) -> io::Result<()> {
+    if crc32fast::hash(payload) != crc {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Event record fails its checksum",
+        ));
+    }
+    Ok(())
+}
+
+fn parse_header(header: &[u8; HEADER_BYTES]) -> (usize, u32) {
+    let length = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+    let crc = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    (length as usize, crc)
+}
+
+// A record passing its checksum may still not be an event of the codec, like one written
+// with the other codec
+fn decode(payload: &[u8], codec: Codec) -> io::Result<DomainEvent> {
+    match codec.codec().read(&mut &payload[..]) {
+        Ok(Some(event)) => Ok(event),
+        Ok(None) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Event record holds no event",
+        )),
+        Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+    }
+}
+
+/// Read the next event of a log, None at the end of it
+pub(crate) fn read_event(input: &mut dyn BufRead, codec: Codec) -> io::Result<Option<DomainEvent>> {
+    read_record(input, codec)?
+        .map(|(payload, _)| decode(&payload, codec))
+        .transpose()
+}
+
+/// Check every record of the log at the path and cut the file off at the first broken one, a
+/// record torn by a crash or damaged on disk, so appending to it goes on after the last whole
+/// event
+pub fn recover(path: &Path, codec: Codec) -> io::Result<Recovery> {
+    let mut input = BufReader::new(File::open(path)?);
+    let mut recovery = Recovery::default();
+    let mut valid_end = 0;
+    loop {
+        match read_record(&mut input, codec).and_then(|record| {
+            record
+                .map(|(payload, length)| decode(&payload, codec).map(|_| length))
+                .transpose()
+        }) {
+            Ok(Some(length)) => {
+                recovery.recovered += 1;
+                valid_end += length as u64;
+            }
+            Ok(None) => return Ok(recovery),
+            Err(e)
+                if e.kind() == io::ErrorKind::UnexpectedEof
+                    || e.kind() == io::ErrorKind::InvalidData =>
+            {
+                break;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    // Count what's cut off by the lines or the lengths of the records, as far as they can
+    // still be read
+    let mut file = input.into_inner();
+    let size = file.metadata()?.len();
+    file.seek(SeekFrom::Start(valid_end))?;
+    match codec {
+        Codec::Json => recovery.lost = BufReader::new(&mut file).split(b'\n').count(),
+        Codec::Binary => {
+            let mut at = valid_end;
+            while at < size {
+                recovery.lost += 1;
+                let mut header = [0; HEADER_BYTES];
+                file.seek(SeekFrom::Start(at))?;
+                if file.read_exact(&mut header).is_err() {
+                    break;
+                }
+                at += (HEADER_BYTES + parse_header(&header).0) as u64;
+            }
+        }
+    }
+
+    let file = OpenOptions::new().write(true).open(path)?;
+    file.set_len(valid_end)?;
+    file.sync_all()?;
+    recovery.truncated_bytes = size - valid_end;
+    Ok(recovery)
+}
+
+/// Where the archive of the last run is kept once a new one starts at its path, like
+/// `events.previous.jsonl` for `events.jsonl`
+pub fn previous_archive(path: &Path) -> PathBuf {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => path.with_extension(format!("previous.{}", extension)),
+        None => path.with_extension("previous"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entity::person::PersonId;
+    use crate::domain::event::person_event::PersonEvent;
+    use crate::domain::value_object::location::Location;
+    use std::fs;
+
+    fn moved(x: i32) -> DomainEvent {
+        DomainEvent::Person(PersonEvent::PersonMoved {
+            person_id: PersonId(1),
+            from_location: Location::new(x, 0),
+            to_location: Location::new(x + 1, 0),
+        })
+    }
+
+    fn read_all(path: &Path, codec: Codec) -> Vec<DomainEvent> {
+        let mut input = BufReader::new(File::open(path).unwrap());
+        let mut events = Vec::new();
+        while let Some(event) = read_event(&mut input, codec).unwrap() {
+            events.push(event);
+        }
+        events
+    }
+
+    #[test]
+    fn test_torn_and_damaged_records_are_cut_off() {
+        for codec in Codec::ALL {
+            let path = std::env::temp_dir().join(format!(
+                "event_log_{}.{}",
+                std::process::id(),
+                codec.extension()
+            ));
+            let mut log = EventLog::create(&path, codec, FsyncPolicy::EveryEvents(2)).unwrap();
+            log.append(&(0..3).map(moved).collect::<Vec<_>>()).unwrap();
+            log.append(&[moved(3)]).unwrap();
+            drop(log);
+            let whole = fs::read(&path).unwrap();
+            if codec == Codec::Json {
+                // Still a line of text per event, the checksum in front of the JSON
+                let text = String::from_utf8(whole.clone()).unwrap();
+                for line in text.lines() {
+                    let (crc, json) = line.split_once(' ').unwrap();
+                    assert_eq!(crc.len(), 8);
+                    serde_json::from_str::<serde_json::Value>(json).unwrap();
+                }
+                assert_eq!(text.lines().count(), 4);
+            }
+
+            let other = path.with_extension("converted");
+            let to = if codec == Codec::Json {
+                Codec::Binary
+            } else {
+                Codec::Json
+            };
+            assert_eq!(
+                crate::infrastructure::codec::convert(&path, codec, &other, to).unwrap(),
+                4
+            );
+            assert_eq!(read_all(&other, to), (0..4).map(moved).collect::<Vec<_>>());
+            fs::remove_file(&other).unwrap();
+
+            // A crash in the middle of the last record
+            fs::write(&path, &whole[..whole.len() - 3]).unwrap();
+            let recovery = recover(&path, codec).unwrap();
+            assert_eq!((recovery.recovered, recovery.lost), (3, 1), "{:?}", codec);
+            assert_eq!(
+                read_all(&path, codec),
+                (0..3).map(moved).collect::<Vec<_>>()
+            );
+            assert_eq!(recover(&path, codec).unwrap().lost, 0);
+
+            // A flipped byte in the second record loses it and all after it
+            let mut damaged = whole.clone();
+            let second = match codec {
+                Codec::Json => whole.iter().position(|byte| *byte == b'\n').unwrap() + 1,
+                Codec::Binary => HEADER_BYTES + parse_header(&whole[..8].try_into().unwrap()).0,
+            };
+            damaged[second + 12] ^= 0xff;
+            fs::write(&path, &damaged).unwrap();
+            let recovery = recover(&path, codec).unwrap();
+            assert_eq!((recovery.recovered, recovery.lost), (1, 3), "{:?}", codec);
+            assert_eq!(recovery.truncated_bytes, (whole.len() - second) as u64);
+            assert_eq!(read_all(&path, codec), vec![moved(0)]);
+            fs::remove_file(&path).unwrap();
+        }
+
+        assert_eq!(
+            previous_archive(Path::new("events.jsonl")),
+            PathBuf::from("events.previous.jsonl")
+        );
+        assert_eq!(
+            FsyncPolicy::from_name("100"),
+            Some(FsyncPolicy::EveryEvents(100))
+        );
+        assert_eq!(FsyncPolicy::from_name("0"), None);
+    }
+}
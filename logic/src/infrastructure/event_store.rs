@@ -1,86 +1,482 @@
+use crate::domain::event::faction_event::FactionEvent;
+use crate::domain::event::fog_event::FogEvent;
+use crate::domain::event::map_event::MapEvent;
+use crate::domain::event::person_event::PersonEvent;
+use crate::domain::event::portal_event::PortalEvent;
+use crate::domain::event::stream_event::StreamEvent;
+use crate::domain::event::vehicle_event::VehicleEvent;
 use crate::domain::event::DomainEvent;
+use crate::domain::value_object::region::Region;
+use crate::infrastructure::codec::Codec;
+use crate::infrastructure::event_log::{
+    previous_archive, read_event, recover, EventLog, FsyncPolicy, Recovery,
+};
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 
+/// How many events the store keeps in memory, set per deployment for very long runs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Events kept in memory once a snapshot covers the older ones, all of them without a limit
+    pub keep_last: Option<usize>,
+    /// File the older events are appended to, read back on demand. Without one they are gone
+    /// for good.
+    pub archive: Option<PathBuf>,
+    /// Encoding of the archive. A JSON archive has a line per event like `.jsonl` files, with
+    /// the CRC-32 of the event in hex and a space in front of the JSON.
+    pub codec: Codec,
+    /// How often the archive is synced to the disk
+    pub fsync: FsyncPolicy,
+}
+
+/// Where the events of the store are kept
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventMemory {
+    /// Events held in memory
+    pub in_memory: usize,
+    /// Rough size of those in bytes, not counting the names and lists the events own
+    pub bytes: usize,
+    /// Events moved to the archive file
+    pub archived: usize,
+    /// Events dropped from memory without an archive to go to
+    pub dropped: usize,
+}
+
 /// Stores all domain events and allows subscribers to receive them
 pub(crate) struct EventStore {
+    // The latest events, the older ones are archived or dropped following the retention
     events: Vec<DomainEvent>,
-    receiver: Receiver<DomainEvent>,
-    subscribers: Vec<Sender<DomainEvent>>,
+    subscribers: Vec<Subscriber>,
+    retention: RetentionPolicy,
+    // Open while the retention has an archive
+    archive: Option<EventLog>,
+    archived: usize,
+    dropped: usize,
+}
+
+struct Subscriber {
+    sender: Sender<DomainEvent>,
+    // Only events within the region are sent, all of them without one
+    region: Option<Region>,
 }
 
 impl EventStore {
-    /// Create a new event store with a receiver for incoming events
-    pub fn new(receiver: Receiver<DomainEvent>) -> Self {
+    /// Create a new, empty event store
+    pub fn new() -> Self {
         EventStore {
             events: Vec::new(),
-            receiver,
             subscribers: Vec::new(),
+            retention: RetentionPolicy::default(),
+            archive: None,
+            archived: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Change how many events are kept in memory, applied with the next `trim`. A new archive
+    /// file or codec starts out empty, the events archived before count as dropped. An archive
+    /// left at the path by an earlier run is recovered and moved to `previous_archive`, the
+    /// returned recovery tells how many of its events survived.
+    pub fn set_retention(&mut self, retention: RetentionPolicy) -> io::Result<Option<Recovery>> {
+        let mut recovery = None;
+        if retention.archive != self.retention.archive || retention.codec != self.retention.codec {
+            self.archive = None;
+            if let Some(path) = &retention.archive {
+                if path.exists() {
+                    recovery = Some(recover(path, retention.codec)?);
+                    fs::rename(path, previous_archive(path))?;
+                }
+                self.archive = Some(EventLog::create(path, retention.codec, retention.fsync)?);
+            }
+            self.dropped += std::mem::take(&mut self.archived);
+        }
+        if let Some(archive) = &mut self.archive {
+            archive.fsync = retention.fsync;
+        }
+        self.retention = retention;
+        Ok(recovery)
+    }
+
+    /// Move the oldest events beyond the retention out of memory, into the archive if there is
+    /// one. Returns how many were moved, they stay in memory if the archive can't be written.
+    pub fn trim(&mut self) -> io::Result<usize> {
+        let Some(keep) = self.retention.keep_last else {
+            return Ok(0);
+        };
+        let excess = self.events.len().saturating_sub(keep);
+        if excess == 0 {
+            return Ok(0);
+        }
+        match &mut self.archive {
+            Some(archive) => {
+                archive.append(&self.events[..excess])?;
+                self.archived += excess;
+            }
+            None => self.dropped += excess,
+        }
+        self.events.drain(..excess);
+        Ok(excess)
+    }
+
+    /// How many events are in memory, archived or dropped
+    pub fn memory(&self) -> EventMemory {
+        EventMemory {
+            in_memory: self.events.len(),
+            bytes: self.events.capacity() * std::mem::size_of::<DomainEvent>(),
+            archived: self.archived,
+            dropped: self.dropped,
         }
     }
 
     /// Add a new subscriber that will receive future events
     pub fn subscribe(&mut self) -> Receiver<DomainEvent> {
+        self.add_subscriber(None)
+    }
+
+    /// Add a subscriber that only receives future events within the region: persons created
+    /// there and moves starting or ending there. Batches are cut down to those moves. Events
+    /// not tied to a place are always received.
+    pub fn subscribe_region(&mut self, region: Region) -> Receiver<DomainEvent> {
+        self.add_subscriber(Some(region))
+    }
+
+    fn add_subscriber(&mut self, region: Option<Region>) -> Receiver<DomainEvent> {
         let (sender, receiver) = mpsc::channel();
-        self.subscribers.push(sender);
+        self.subscribers.push(Subscriber { sender, region });
         receiver
     }
 
-    /// Get all historical events for rebuilding projections
+    /// Get the historical events held in memory for rebuilding projections
     pub fn get_all_events(&self) -> Vec<DomainEvent> {
         self.events.clone()
     }
 
-    /// Get the total number of stored events
+    /// Visit the stored events in order, the archived ones read back from the archive first.
+    /// Stops at the first error. Dropped events are skipped, `memory` tells how many there are.
+    pub fn try_for_each<E: From<io::Error>>(
+        &self,
+        mut visit: impl FnMut(&DomainEvent) -> Result<(), E>,
+    ) -> Result<(), E> {
+        if self.archived > 0
+            && let Some(path) = &self.retention.archive
+        {
+            let mut input = BufReader::new(File::open(path)?);
+            while let Some(event) = read_event(&mut input, self.retention.codec)? {
+                visit(&event)?;
+            }
+        }
+        self.events.iter().try_for_each(visit)
+    }
+
+    /// Get the total number of events ever stored, the ones out of memory included
     pub fn event_count(&self) -> usize {
-        self.events.len()
+        self.archived + self.dropped + self.events.len()
     }
 
-    /// Start processing events in a background thread
-    pub fn start_processing(mut self) -> thread::JoinHandle<()> {
-        thread::spawn(move || {
-            println!("Event store started processing events");
+    /// Store the event and notify all subscribers
+    fn append(&mut self, event: DomainEvent) {
+        self.events.push(event.clone());
+        self.subscribers
+            .retain(|subscriber| match &subscriber.region {
+                None => subscriber.sender.send(event.clone()).is_ok(),
+                Some(region) => match event_within(&event, region) {
+                    Some(event) => subscriber.sender.send(event).is_ok(),
+                    // Dropped subscribers are noticed with the next event they get
+                    None => true,
+                },
+            });
+    }
+}
 
-            while let Ok(event) = self.receiver.recv() {
-                println!("Event received: {:?}", event);
+// The part of an event happening within the region, if any
+fn event_within(event: &DomainEvent, region: &Region) -> Option<DomainEvent> {
+    match event {
+        DomainEvent::Person(PersonEvent::PersonCreated { location, .. }) => {
+            region.contains(location).then(|| event.clone())
+        }
+        DomainEvent::Person(PersonEvent::PersonMoved {
+            from_location,
+            to_location,
+            ..
+        }) => {
+            (region.contains(from_location) || region.contains(to_location)).then(|| event.clone())
+        }
+        DomainEvent::Person(PersonEvent::PersonsMoved { moves }) => {
+            let moves: Vec<_> = moves
+                .iter()
+                .filter(|person_move| {
+                    region.contains(&person_move.from_location)
+                        || region.contains(&person_move.to_location)
+                })
+                .cloned()
+                .collect();
+            (!moves.is_empty()).then_some(DomainEvent::Person(PersonEvent::PersonsMoved { moves }))
+        }
+        DomainEvent::Fog(FogEvent::TilesRevealed { viewer, tiles }) => {
+            let tiles: Vec<_> = tiles
+                .iter()
+                .filter(|tile| region.contains(tile))
+                .cloned()
+                .collect();
+            (!tiles.is_empty()).then(|| {
+                DomainEvent::Fog(FogEvent::TilesRevealed {
+                    viewer: viewer.clone(),
+                    tiles,
+                })
+            })
+        }
+        DomainEvent::Fog(FogEvent::TilesHidden { viewer, tiles }) => {
+            let tiles: Vec<_> = tiles
+                .iter()
+                .filter(|tile| region.contains(tile))
+                .cloned()
+                .collect();
+            (!tiles.is_empty()).then(|| {
+                DomainEvent::Fog(FogEvent::TilesHidden {
+                    viewer: viewer.clone(),
+                    tiles,
+                })
+            })
+        }
+        DomainEvent::Vehicle(VehicleEvent::VehicleCreated { location, .. })
+        | DomainEvent::Vehicle(VehicleEvent::VehicleArrived { location, .. })
+        | DomainEvent::Vehicle(VehicleEvent::VehicleStranded { location, .. }) => {
+            region.contains(location).then(|| event.clone())
+        }
+        DomainEvent::Vehicle(VehicleEvent::VehicleMoved {
+            from_location,
+            to_location,
+            ..
+        }) => {
+            (region.contains(from_location) || region.contains(to_location)).then(|| event.clone())
+        }
+        DomainEvent::Portal(PortalEvent::PortalCreated {
+            from_location,
+            to_location,
+            ..
+        })
+        | DomainEvent::Portal(PortalEvent::PortalTraversed {
+            from_location,
+            to_location,
+            ..
+        }) => {
+            (region.contains(from_location) || region.contains(to_location)).then(|| event.clone())
+        }
+        DomainEvent::Map(MapEvent::TilePlaced { location, .. })
+        | DomainEvent::Map(MapEvent::TileRemoved { location, .. }) => {
+            region.contains(location).then(|| event.clone())
+        }
+        DomainEvent::Map(MapEvent::RegionLocked { region: locked, .. }) => {
+            locked.overlaps(region).then(|| event.clone())
+        }
+        DomainEvent::Stream(StreamEvent::ChunkUnloaded { chunk, .. })
+        | DomainEvent::Stream(StreamEvent::ChunkLoaded { chunk, .. }) => {
+            chunk.region().overlaps(region).then(|| event.clone())
+        }
+        DomainEvent::Faction(FactionEvent::ZoneCreated { region: zone, .. }) => {
+            zone.overlaps(region).then(|| event.clone())
+        }
+        DomainEvent::Tag(_)
+        | DomainEvent::Scenario(_)
+        | DomainEvent::Goal(_)
+        | DomainEvent::Faction(_)
+        | DomainEvent::Task(_)
+        | DomainEvent::Vehicle(_)
+        | DomainEvent::Portal(_)
+        | DomainEvent::World(_)
+        | DomainEvent::Group(_)
+        | DomainEvent::Map(_) => Some(event.clone()),
+    }
+}
 
-                // Store the event
-                self.events.push(event.clone());
+/// Sends domain events to the event store, counting them so waiting for the projections also
+/// covers events still on their way to the store
+#[derive(Clone)]
+pub struct EventSender {
+    sender: Sender<DomainEvent>,
+    published: Arc<AtomicUsize>,
+}
 
-                // Notify all subscribers
-                self.subscribers
-                    .retain(|sender| sender.send(event.clone()).is_ok());
-            }
+impl EventSender {
+    // Number of events sent so far by this sender and its clones
+    pub fn published(&self) -> usize {
+        self.published.load(Ordering::Acquire)
+    }
+}
 
-            println!("Event store stopped processing events");
-        })
+impl From<Sender<DomainEvent>> for EventSender {
+    fn from(sender: Sender<DomainEvent>) -> Self {
+        EventSender {
+            sender,
+            published: Arc::new(AtomicUsize::new(0)),
+        }
     }
 }
+
 /// Create a new event store and return a sender for publishing events to it
-pub fn create_event_store() -> (Arc<Mutex<EventStore>>, Sender<DomainEvent>) {
+pub fn create_event_store() -> (Arc<Mutex<EventStore>>, EventSender) {
     let (sender, receiver) = mpsc::channel();
-    let event_store = EventStore::new(receiver);
-
-    let event_store_arc = Arc::new(Mutex::new(event_store));
-
-    let event_store_for_thread = event_store_arc.clone();
+    let event_store = Arc::new(Mutex::new(EventStore::new()));
 
+    // The store stays shared with the thread, so subscribers added later still get the events
+    let event_store_for_thread = event_store.clone();
     thread::spawn(move || {
-        let event_store = {
-            let mut guard = event_store_for_thread.lock().unwrap();
-            std::mem::replace(&mut *guard, EventStore::new(mpsc::channel().1))
-        };
+        println!("Event store started processing events");
+
+        while let Ok(event) = receiver.recv() {
+            println!("Event received: {:?}", event);
+            event_store_for_thread.lock().unwrap().append(event);
+        }
 
-        event_store.start_processing().join().unwrap();
+        println!("Event store stopped processing events");
     });
 
-    (event_store_arc, sender)
+    (event_store, EventSender::from(sender))
 }
 
-/// Helper function to publish an event to a channel
-pub fn publish_event<T>(sender: &Sender<T>, event: T) {
-    if let Err(e) = sender.send(event) {
-        eprintln!("Failed to publish event: {:?}", e);
+/// Helper function to publish an event to the event store
+pub fn publish_event(sender: &EventSender, event: DomainEvent) {
+    match sender.sender.send(event) {
+        Ok(()) => {
+            sender.published.fetch_add(1, Ordering::AcqRel);
+        }
+        Err(e) => eprintln!("Failed to publish event: {:?}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entity::person::PersonId;
+    use crate::domain::event::person_event::{PersonEvent, PersonMove};
+    use crate::domain::value_object::location::Location;
+    use std::time::Duration;
+
+    #[test]
+    fn test_late_subscribers_receive_events() {
+        let (store, sender) = create_event_store();
+        // Give the processing thread time to start before subscribing
+        thread::sleep(Duration::from_millis(20));
+        let receiver = store.lock().unwrap().subscribe();
+
+        let event = DomainEvent::Person(PersonEvent::PersonCreated {
+            person_id: PersonId(1),
+            name: "Alice".to_string(),
+            location: Location::new(1, 2),
+        });
+        publish_event(&sender, event.clone());
+
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(1)).unwrap(),
+            event
+        );
+        assert_eq!(store.lock().unwrap().event_count(), 1);
+    }
+
+    #[test]
+    fn test_region_subscribers_only_receive_events_there() {
+        let mut store = EventStore::new();
+        let receiver = store.subscribe_region(Region::new(0, 0, 9, 9));
+        let moved = |id: u32, from_x: i32, to_x: i32| PersonMove {
+            person_id: PersonId(id),
+            from_location: Location::new(from_x, 0),
+            to_location: Location::new(to_x, 0),
+        };
+
+        store.append(DomainEvent::Person(PersonEvent::PersonCreated {
+            person_id: PersonId(1),
+            name: "Far".to_string(),
+            location: Location::new(50, 0),
+        }));
+        store.append(DomainEvent::Person(PersonEvent::PersonMoved {
+            person_id: PersonId(1),
+            from_location: Location::new(50, 0),
+            to_location: Location::new(9, 0),
+        }));
+        store.append(DomainEvent::Person(PersonEvent::PersonsMoved {
+            moves: vec![moved(1, 9, 10), moved(2, 60, 61)],
+        }));
+        store.append(DomainEvent::Person(PersonEvent::PersonsMoved {
+            moves: vec![moved(2, 61, 62)],
+        }));
+
+        let received: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(received.len(), 2);
+        assert!(matches!(
+            received[0],
+            DomainEvent::Person(PersonEvent::PersonMoved { .. })
+        ));
+        assert_eq!(
+            received[1],
+            DomainEvent::Person(PersonEvent::PersonsMoved {
+                moves: vec![moved(1, 9, 10)]
+            })
+        );
+        assert_eq!(store.event_count(), 4);
+    }
+
+    #[test]
+    fn test_events_beyond_the_retention_are_archived() {
+        let path = std::env::temp_dir().join(format!("events_{}.bin", std::process::id()));
+        let mut store = EventStore::new();
+        store
+            .set_retention(RetentionPolicy {
+                keep_last: Some(2),
+                archive: Some(path.clone()),
+                codec: Codec::Binary,
+                fsync: FsyncPolicy::Never,
+            })
+            .unwrap();
+        let moved = |x: i32| {
+            DomainEvent::Person(PersonEvent::PersonMoved {
+                person_id: PersonId(1),
+                from_location: Location::new(x, 0),
+                to_location: Location::new(x + 1, 0),
+            })
+        };
+        for x in 0..5 {
+            store.append(moved(x));
+        }
+
+        assert_eq!(store.trim().unwrap(), 3);
+        assert_eq!(store.trim().unwrap(), 0);
+        let memory = store.memory();
+        assert_eq!(
+            (memory.in_memory, memory.archived, memory.dropped),
+            (2, 3, 0)
+        );
+        assert_eq!(store.event_count(), 5);
+        let mut visited = Vec::new();
+        store
+            .try_for_each(|event| {
+                visited.push(event.clone());
+                Ok::<_, io::Error>(())
+            })
+            .unwrap();
+        assert_eq!(visited, (0..5).map(moved).collect::<Vec<_>>());
+
+        // Without an archive the older events are dropped
+        store.set_retention(RetentionPolicy::default()).unwrap();
+        assert_eq!(store.memory().dropped, 3);
+        assert_eq!(store.event_count(), 5);
+
+        // The next run keeps the archive of this one aside
+        let recovery = EventStore::new()
+            .set_retention(RetentionPolicy {
+                keep_last: Some(2),
+                archive: Some(path.clone()),
+                codec: Codec::Binary,
+                fsync: FsyncPolicy::Never,
+            })
+            .unwrap();
+        assert_eq!(recovery.map(|recovery| recovery.recovered), Some(3));
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(previous_archive(&path)).unwrap();
     }
 }
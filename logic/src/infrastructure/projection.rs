@@ -1,12 +1,20 @@
+pub(crate) mod group_membership;
 pub(crate) mod location_occupancy;
+pub(crate) mod stats;
 
+use crate::domain::event::stream_event::StreamEvent;
 use crate::domain::event::DomainEvent;
+use crate::domain::value_object::region::Region;
 use crate::infrastructure::event_store::EventStore;
+pub use group_membership::GroupMembershipProjection;
 pub use location_occupancy::LocationOccupancyProjection;
-use std::sync::Mutex;
+pub use stats::StatsProjection;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 // Projection trait and manager
-pub(crate) trait Projection: Send + 'static {
+pub trait Projection: Send + 'static {
     /** Apply a single event to update the projection state */
     fn apply(&mut self, event: &DomainEvent);
 
@@ -16,18 +24,124 @@ pub(crate) trait Projection: Send + 'static {
     /** Optional method called after all historical events have been applied */
     fn after_rebuild(&mut self) {}
 
+    /** Optional method called once a region was unloaded to disk, after its event was applied.
+     * Its entities are gone until it's loaded again, drop what is kept about it to save memory */
+    fn region_unloaded(&mut self, _region: &Region) {}
+
+    /** Optional method called once an unloaded region was loaded back, after its event, which
+     * brings back the persons, was applied */
+    fn region_loaded(&mut self, _region: &Region) {}
+
     /** Name of the projection for logging/debugging */
     fn name(&self) -> &str;
 }
 
+/// How far a projection got rebuilding from the stored events
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RebuildProgress {
+    pub name: String,
+    /// Events applied so far, the live ones after the rebuild included
+    pub applied: usize,
+    /// Events stored when the projection was registered
+    pub total: usize,
+    /// Whether the rebuild finished and the projection follows the live events
+    pub ready: bool,
+}
+
+// Rebuild state of one projection, updated by its thread
+struct ProjectionStatus {
+    name: String,
+    total: usize,
+    // Number of events the projection has applied so far
+    applied: AtomicUsize,
+    ready: AtomicBool,
+}
+
+/// Readiness of the projections, for frontends showing the rebuild progress while they load
+#[derive(Clone, Default)]
+pub struct Projections {
+    statuses: Arc<Mutex<Vec<Arc<ProjectionStatus>>>>,
+}
+
+impl Projections {
+    /// Whether every projection finished rebuilding from the stored events
+    pub fn ready(&self) -> bool {
+        self.statuses
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|status| status.ready.load(Ordering::Acquire))
+    }
+
+    /// Rebuild progress of every projection in the order they were registered
+    pub fn progress(&self) -> Vec<RebuildProgress> {
+        self.statuses
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|status| RebuildProgress {
+                name: status.name.clone(),
+                applied: status.applied.load(Ordering::Acquire),
+                total: status.total,
+                ready: status.ready.load(Ordering::Acquire),
+            })
+            .collect()
+    }
+
+    /// Wait until every projection finished rebuilding, false on timeout
+    pub fn wait_until_ready(&self, timeout: Duration) -> bool {
+        let started = Instant::now();
+        while !self.ready() {
+            if started.elapsed() >= timeout {
+                return false;
+            }
+            std::thread::sleep(Duration::from_micros(100));
+        }
+        true
+    }
+}
+
 /** Projection manager that handles creating and rebuilding projections */
 pub struct ProjectionManager {
     event_store: std::sync::Arc<Mutex<EventStore>>,
+    projections: Projections,
 }
 
 impl ProjectionManager {
     pub fn new(event_store: std::sync::Arc<Mutex<EventStore>>) -> Self {
-        ProjectionManager { event_store }
+        ProjectionManager {
+            event_store,
+            projections: Projections::default(),
+        }
+    }
+
+    /// Handle to the readiness of the projections registered so far and later
+    pub fn projections(&self) -> Projections {
+        self.projections.clone()
+    }
+
+    // Wait until the store holds the first `published` events and every projection applied
+    // them, false on timeout
+    pub fn wait_until_caught_up(&self, published: usize, timeout: Duration) -> bool {
+        let started = Instant::now();
+        loop {
+            let stored = self.event_store.lock().unwrap().event_count();
+            let caught_up = stored >= published
+                && self
+                    .projections
+                    .statuses
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .all(|status| status.applied.load(Ordering::Acquire) >= stored);
+            if caught_up {
+                return true;
+            }
+            if started.elapsed() >= timeout {
+                return false;
+            }
+            std::thread::sleep(Duration::from_micros(100));
+        }
     }
 
     // Register a new projection, rebuild it from history, and start processing live events
@@ -47,6 +161,18 @@ impl ProjectionManager {
             store.get_all_events()
         };
 
+        let status = Arc::new(ProjectionStatus {
+            name: projection_arc.lock().unwrap().name().to_string(),
+            total: historical_events.len(),
+            applied: AtomicUsize::new(0),
+            ready: AtomicBool::new(false),
+        });
+        self.projections
+            .statuses
+            .lock()
+            .unwrap()
+            .push(status.clone());
+
         // Start a thread to rebuild from history and then process live events
         std::thread::spawn(move || {
             let mut projection = projection_clone.lock().unwrap();
@@ -60,13 +186,15 @@ impl ProjectionManager {
                 historical_events.len()
             );
 
-            // Apply all historical events
+            // Apply all historical events, counted one by one for the progress shown meanwhile
             for event in &historical_events {
-                projection.apply(event);
+                dispatch(&mut *projection, event);
+                status.applied.fetch_add(1, Ordering::Release);
             }
 
             println!("Finished rebuilding projection: {}", projection.name());
             projection.after_rebuild();
+            status.ready.store(true, Ordering::Release);
 
             // Release the lock before starting to process live events
             drop(projection);
@@ -79,7 +207,8 @@ impl ProjectionManager {
             // Process live events
             while let Ok(event) = receiver.recv() {
                 let mut projection = projection_clone.lock().unwrap();
-                projection.apply(&event);
+                dispatch(&mut *projection, &event);
+                status.applied.fetch_add(1, Ordering::Release);
             }
 
             println!(
@@ -91,3 +220,84 @@ impl ProjectionManager {
         projection_arc
     }
 }
+
+// Apply an event, telling the projection about the regions it unloads and loads
+fn dispatch<P: Projection>(projection: &mut P, event: &DomainEvent) {
+    projection.apply(event);
+    match event {
+        DomainEvent::Stream(StreamEvent::ChunkUnloaded { chunk, .. }) => {
+            projection.region_unloaded(&chunk.region())
+        }
+        DomainEvent::Stream(StreamEvent::ChunkLoaded { chunk, .. }) => {
+            projection.region_loaded(&chunk.region())
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entity::person::PersonId;
+    use crate::domain::event::person_event::PersonEvent;
+    use crate::domain::value_object::location::Location;
+    use crate::infrastructure::event_store::{create_event_store, publish_event};
+
+    // Counts the events, its rebuild waits until the test lets it start
+    struct Gated {
+        gate: std::sync::mpsc::Receiver<()>,
+        count: usize,
+    }
+
+    impl Projection for Gated {
+        fn apply(&mut self, _event: &DomainEvent) {
+            self.count += 1;
+        }
+
+        fn initialize(&mut self) {
+            self.gate.recv().unwrap();
+        }
+
+        fn name(&self) -> &str {
+            "Gated"
+        }
+    }
+
+    #[test]
+    fn test_rebuild_progress_is_reported_until_ready() {
+        let (store, sender) = create_event_store();
+        for id in 0..100 {
+            publish_event(
+                &sender,
+                DomainEvent::Person(PersonEvent::PersonCreated {
+                    person_id: PersonId(id),
+                    name: format!("Person {}", id),
+                    location: Location::new(0, 0),
+                }),
+            );
+        }
+        while store.lock().unwrap().event_count() < 100 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let manager = ProjectionManager::new(store);
+        let projections = manager.projections();
+        let (open, gate) = std::sync::mpsc::channel();
+        let gated = manager.register_projection(Gated { gate, count: 0 });
+        assert!(!projections.ready());
+        assert_eq!(
+            projections.progress(),
+            vec![RebuildProgress {
+                name: "Gated".to_string(),
+                applied: 0,
+                total: 100,
+                ready: false,
+            }]
+        );
+
+        open.send(()).unwrap();
+        assert!(projections.wait_until_ready(Duration::from_secs(5)));
+        let progress = projections.progress();
+        assert_eq!((progress[0].applied, progress[0].ready), (100, true));
+        assert_eq!(gated.lock().unwrap().count, 100);
+    }
+}
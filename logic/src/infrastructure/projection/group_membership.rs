@@ -0,0 +1,98 @@
+use crate::domain::entity::group::GroupId;
+use crate::domain::entity::person::PersonId;
+use crate::domain::event::group_event::GroupEvent;
+use crate::domain::event::DomainEvent;
+use crate::infrastructure::projection::Projection;
+use std::collections::BTreeMap;
+
+/// Projection that tracks the members of each household and crew
+pub struct GroupMembershipProjection {
+    members: BTreeMap<GroupId, Vec<PersonId>>,
+}
+
+impl GroupMembershipProjection {
+    /// Creates a new projection without groups
+    pub fn new() -> Self {
+        GroupMembershipProjection {
+            members: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the groups a person belongs to, ordered by id
+    pub fn get_groups_of(&self, person_id: PersonId) -> Vec<GroupId> {
+        self.members
+            .iter()
+            .filter(|(_, members)| members.contains(&person_id))
+            .map(|(group, _)| *group)
+            .collect()
+    }
+}
+
+impl Projection for GroupMembershipProjection {
+    fn apply(&mut self, event: &DomainEvent) {
+        let DomainEvent::Group(event) = event else {
+            return;
+        };
+        match event {
+            GroupEvent::GroupCreated { group, .. } => {
+                self.members.insert(*group, Vec::new());
+            }
+            GroupEvent::GroupJoined { group, person_id } => {
+                self.members.entry(*group).or_default().push(*person_id);
+            }
+            GroupEvent::GroupLeft { group, person_id } => {
+                if let Some(members) = self.members.get_mut(group) {
+                    members.retain(|member| member != person_id);
+                }
+            }
+            GroupEvent::GroupDisbanded { group } => {
+                self.members.remove(group);
+            }
+            GroupEvent::GroupItemStored { .. } | GroupEvent::GroupItemTaken { .. } => {}
+        }
+    }
+
+    fn name(&self) -> &str {
+        "GroupMembershipProjection"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entity::group::GroupKind;
+
+    #[test]
+    fn test_membership_follows_the_group_events() {
+        let mut projection = GroupMembershipProjection::new();
+        let (smiths, miners) = (GroupId(1), GroupId(2));
+        let ann = PersonId(0);
+        for (group, kind) in [(smiths, GroupKind::Household), (miners, GroupKind::Crew)] {
+            projection.apply(&DomainEvent::Group(GroupEvent::GroupCreated {
+                group,
+                kind,
+                name: "".to_string(),
+                shared_inventory: false,
+            }));
+            projection.apply(&DomainEvent::Group(GroupEvent::GroupJoined {
+                group,
+                person_id: ann,
+            }));
+        }
+        projection.apply(&DomainEvent::Group(GroupEvent::GroupJoined {
+            group: miners,
+            person_id: PersonId(1),
+        }));
+        assert_eq!(projection.get_groups_of(ann), vec![smiths, miners]);
+
+        projection.apply(&DomainEvent::Group(GroupEvent::GroupLeft {
+            group: miners,
+            person_id: ann,
+        }));
+        projection.apply(&DomainEvent::Group(GroupEvent::GroupDisbanded {
+            group: smiths,
+        }));
+        assert_eq!(projection.get_groups_of(ann), Vec::new());
+        assert_eq!(projection.get_groups_of(PersonId(1)), vec![miners]);
+    }
+}
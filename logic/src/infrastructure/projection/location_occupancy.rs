@@ -1,13 +1,18 @@
 use crate::domain::entity::person::PersonId;
 use crate::domain::event::person_event::PersonEvent;
+use crate::domain::event::stream_event::StreamEvent;
 use crate::domain::event::DomainEvent;
 use crate::domain::value_object::location::Location;
+use crate::domain::value_object::map_id::MapId;
+use crate::domain::value_object::region::Region;
 use crate::infrastructure::projection::Projection;
+use crate::read_model::{QueryList, Queryable};
+use serde_json::json;
 use std::collections::HashMap;
 
-/// Projection that tracks which people are at each location
+/// Projection that tracks which people are at each location, kept apart per level of each map
 pub struct LocationOccupancyProjection {
-    occupancy: HashMap<Location, Vec<PersonId>>,
+    occupancy: HashMap<(MapId, i32), HashMap<Location, Vec<PersonId>>>,
 }
 
 impl LocationOccupancyProjection {
@@ -20,40 +25,87 @@ impl LocationOccupancyProjection {
 
     fn add_person_to_location(&mut self, person_id: PersonId, location: Location) {
         self.occupancy
+            .entry((location.map.clone(), location.z))
+            .or_default()
             .entry(location)
             .or_insert_with(Vec::new)
             .push(person_id);
     }
 
     fn remove_person_from_location(&mut self, person_id: PersonId, location: &Location) {
-        if let Some(people) = self.occupancy.get_mut(location) {
+        let Some(map) = self.occupancy.get_mut(&(location.map.clone(), location.z)) else {
+            return;
+        };
+        if let Some(people) = map.get_mut(location) {
             people.retain(|&id| id != person_id);
 
             if people.is_empty() {
-                self.occupancy.remove(location);
+                map.remove(location);
             }
         }
     }
 
     /// Returns all people currently at the specified location
     pub fn get_people_at_location(&self, location: &Location) -> Vec<PersonId> {
-        self.occupancy.get(location).cloned().unwrap_or_default()
+        self.occupancy
+            .get(&(location.map.clone(), location.z))
+            .and_then(|map| map.get(location))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns all people inside the region
+    pub fn get_people_in_region(&self, region: &Region) -> Vec<PersonId> {
+        let Some(map) = self.occupancy.get(&(region.map().clone(), region.level())) else {
+            return Vec::new();
+        };
+        map.iter()
+            .filter(|(location, _)| region.contains(location))
+            .flat_map(|(_, people)| people.iter().copied())
+            .collect()
+    }
+
+    /// Returns all locations on a level of the map that currently have at least one person
+    pub fn get_occupied_locations(&self, map: &MapId, z: i32) -> Vec<Location> {
+        self.occupancy
+            .get(&(map.clone(), z))
+            .map(|map| map.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns every occupied location with its people, ordered by map, level, row and column
+    pub fn get_all_occupied(&self) -> Vec<(Location, Vec<PersonId>)> {
+        let mut occupied: Vec<_> = self
+            .occupancy
+            .values()
+            .flat_map(|map| map.iter())
+            .map(|(location, people)| {
+                let mut people = people.clone();
+                people.sort();
+                (location.clone(), people)
+            })
+            .collect();
+        occupied.sort_by(|(a, _), (b, _)| (&a.map, a.z, a.y, a.x).cmp(&(&b.map, b.z, b.y, b.x)));
+        occupied
     }
 
-    /// Returns all locations that currently have at least one person
-    pub fn get_occupied_locations(&self) -> Vec<Location> {
-        self.occupancy.keys().cloned().collect()
+    /// Returns the number of locations on a level of the map that have at least one person
+    pub fn get_occupied_location_count(&self, map: &MapId, z: i32) -> usize {
+        self.occupancy
+            .get(&(map.clone(), z))
+            .map_or(0, HashMap::len)
     }
 
-    /// Returns the total number of locations that have at least one person
-    pub fn get_occupied_location_count(&self) -> usize {
-        self.occupancy.len()
+    /// Returns the number of locations of all maps and levels that have at least one person
+    pub fn get_total_occupied_location_count(&self) -> usize {
+        self.occupancy.values().map(HashMap::len).sum()
     }
 
-    /// Returns the location with the most people, along with the count
+    /// Returns the location on a level of the map with the most people, along with the count
     /// Returns None if no locations are occupied
-    pub fn get_most_crowded_location(&self) -> Option<(Location, usize)> {
+    pub fn get_most_crowded_location(&self, map: &MapId, z: i32) -> Option<(Location, usize)> {
         self.occupancy
+            .get(&(map.clone(), z))?
             .iter()
             .map(|(location, people)| (location.clone(), people.len()))
             .max_by_key(|&(_, count)| count)
@@ -78,24 +130,97 @@ impl Projection for LocationOccupancyProjection {
                 self.remove_person_from_location(*person_id, from_location);
                 self.add_person_to_location(*person_id, to_location.clone());
             }
+            DomainEvent::Person(PersonEvent::PersonsMoved { moves }) => {
+                for person_move in moves {
+                    self.remove_person_from_location(
+                        person_move.person_id,
+                        &person_move.from_location,
+                    );
+                    self.add_person_to_location(
+                        person_move.person_id,
+                        person_move.to_location.clone(),
+                    );
+                }
+            }
+            DomainEvent::Stream(StreamEvent::ChunkLoaded { persons, .. }) => {
+                for person in persons {
+                    self.add_person_to_location(person.id, person.location.clone());
+                }
+            }
             _ => {}
         }
     }
 
+    // The persons of the region are on disk, nobody is there until it's loaded again
+    fn region_unloaded(&mut self, region: &Region) {
+        if let Some(map) = self
+            .occupancy
+            .get_mut(&(region.map().clone(), region.level()))
+        {
+            map.retain(|location, _| !region.contains(location));
+        }
+    }
+
     fn name(&self) -> &str {
         "LocationOccupancyProjection"
     }
 }
 
+impl Queryable for LocationOccupancyProjection {
+    const MODULE: &'static str = "occupancy";
+
+    fn queries(queries: &mut QueryList<Self>) {
+        queries.add(
+            "people_at",
+            "Ids of the people on a tile of any map and level",
+            &[
+                ("map", "string"),
+                ("z", "integer"),
+                ("x", "integer"),
+                ("y", "integer"),
+            ],
+            "integer[]",
+            |projection, (map, z, x, y): (String, i32, i32, i32)| {
+                let location = Location::on(&MapId::new(&map), x, y).at_level(z);
+                projection.get_people_at_location(&location)
+            },
+        );
+        queries.add(
+            "occupied_count",
+            "Number of tiles with people on a level of a map",
+            &[("map", "string"), ("z", "integer")],
+            "integer",
+            |projection, (map, z): (String, i32)| {
+                projection.get_occupied_location_count(&MapId::new(&map), z)
+            },
+        );
+        queries.add(
+            "all",
+            "Every occupied tile of every map and level with the ids of its people",
+            &[],
+            "{ location: Location, people: integer[] }[]",
+            |projection, ()| {
+                let occupied = projection.get_all_occupied().into_iter();
+                occupied
+                    .map(|(location, people)| json!({ "location": location, "people": people }))
+                    .collect::<Vec<_>>()
+            },
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::domain::entity::person::Person;
+    use crate::domain::event::person_event::PersonMove;
+    use crate::domain::value_object::chunk::Chunk;
 
     fn create_person_created_event(id: u32, x: i32, y: i32) -> DomainEvent {
         DomainEvent::Person(PersonEvent::PersonCreated {
             person_id: PersonId(id),
             name: format!("Person {}", id),
-            location: Location { x, y },
+            location: Location::new(x, y),
         })
     }
 
@@ -108,11 +233,8 @@ mod tests {
     ) -> DomainEvent {
         DomainEvent::Person(PersonEvent::PersonMoved {
             person_id: PersonId(id),
-            from_location: Location {
-                x: from_x,
-                y: from_y,
-            },
-            to_location: Location { x: to_x, y: to_y },
+            from_location: Location::new(from_x, from_y),
+            to_location: Location::new(to_x, to_y),
         })
     }
 
@@ -120,20 +242,27 @@ mod tests {
     fn test_new_projection_is_empty() {
         let projection = LocationOccupancyProjection::new();
 
-        assert_eq!(projection.get_occupied_location_count(), 0);
-        assert!(projection.get_occupied_locations().is_empty());
-        assert!(projection.get_most_crowded_location().is_none());
+        assert_eq!(projection.get_occupied_location_count(&MapId::main(), 0), 0);
+        assert!(projection
+            .get_occupied_locations(&MapId::main(), 0)
+            .is_empty());
+        assert!(projection
+            .get_most_crowded_location(&MapId::main(), 0)
+            .is_none());
     }
 
     #[test]
     fn test_apply_person_created_event() {
         let mut projection = LocationOccupancyProjection::new();
-        let location = Location { x: 10, y: 20 };
+        let location = Location::new(10, 20);
 
         projection.apply(&create_person_created_event(1, 10, 20));
 
-        assert_eq!(projection.get_occupied_location_count(), 1);
-        assert_eq!(projection.get_occupied_locations(), vec![location.clone()]);
+        assert_eq!(projection.get_occupied_location_count(&MapId::main(), 0), 1);
+        assert_eq!(
+            projection.get_occupied_locations(&MapId::main(), 0),
+            vec![location.clone()]
+        );
         assert_eq!(
             projection.get_people_at_location(&location),
             vec![PersonId(1)]
@@ -143,8 +272,8 @@ mod tests {
     #[test]
     fn test_apply_person_moved_event() {
         let mut projection = LocationOccupancyProjection::new();
-        let location1 = Location { x: 10, y: 20 };
-        let location2 = Location { x: 30, y: 40 };
+        let location1 = Location::new(10, 20);
+        let location2 = Location::new(30, 40);
 
         // Create a person at location1
         projection.apply(&create_person_created_event(1, 10, 20));
@@ -160,14 +289,17 @@ mod tests {
         );
 
         // Verify location1 is no longer in the occupied locations
-        assert_eq!(projection.get_occupied_locations(), vec![location2]);
-        assert_eq!(projection.get_occupied_location_count(), 1);
+        assert_eq!(
+            projection.get_occupied_locations(&MapId::main(), 0),
+            vec![location2]
+        );
+        assert_eq!(projection.get_occupied_location_count(&MapId::main(), 0), 1);
     }
 
     #[test]
     fn test_multiple_people_at_same_location() {
         let mut projection = LocationOccupancyProjection::new();
-        let location = Location { x: 10, y: 20 };
+        let location = Location::new(10, 20);
 
         // Create three people at the same location
         projection.apply(&create_person_created_event(1, 10, 20));
@@ -182,7 +314,9 @@ mod tests {
         assert!(people.contains(&PersonId(3)));
 
         // Verify this is the most crowded location
-        let (crowded_location, count) = projection.get_most_crowded_location().unwrap();
+        let (crowded_location, count) = projection
+            .get_most_crowded_location(&MapId::main(), 0)
+            .unwrap();
         assert_eq!(crowded_location, location);
         assert_eq!(count, 3);
     }
@@ -190,9 +324,9 @@ mod tests {
     #[test]
     fn test_people_at_different_locations() {
         let mut projection = LocationOccupancyProjection::new();
-        let location1 = Location { x: 10, y: 20 };
-        let location2 = Location { x: 30, y: 40 };
-        let location3 = Location { x: 50, y: 60 };
+        let location1 = Location::new(10, 20);
+        let location2 = Location::new(30, 40);
+        let location3 = Location::new(50, 60);
 
         // Create people at different locations
         projection.apply(&create_person_created_event(1, 10, 20));
@@ -214,7 +348,7 @@ mod tests {
         );
 
         // Verify all locations are occupied
-        let occupied_locations = projection.get_occupied_locations();
+        let occupied_locations = projection.get_occupied_locations(&MapId::main(), 0);
         assert_eq!(occupied_locations.len(), 3);
         assert!(occupied_locations.contains(&location1));
         assert!(occupied_locations.contains(&location2));
@@ -234,16 +368,18 @@ mod tests {
         projection.apply(&create_person_created_event(6, 50, 60));
 
         // Verify the most crowded location
-        let (crowded_location, count) = projection.get_most_crowded_location().unwrap();
-        assert_eq!(crowded_location, Location { x: 30, y: 40 });
+        let (crowded_location, count) = projection
+            .get_most_crowded_location(&MapId::main(), 0)
+            .unwrap();
+        assert_eq!(crowded_location, Location::new(30, 40));
         assert_eq!(count, 3);
     }
 
     #[test]
     fn test_moving_last_person_from_location() {
         let mut projection = LocationOccupancyProjection::new();
-        let location1 = Location { x: 10, y: 20 };
-        let location2 = Location { x: 30, y: 40 };
+        let location1 = Location::new(10, 20);
+        let location2 = Location::new(30, 40);
 
         // Create a person at location1
         projection.apply(&create_person_created_event(1, 10, 20));
@@ -252,8 +388,10 @@ mod tests {
         projection.apply(&create_person_moved_event(1, 10, 20, 30, 40));
 
         // Verify location1 is no longer in the occupied locations
-        assert!(!projection.get_occupied_locations().contains(&location1));
-        assert_eq!(projection.get_occupied_location_count(), 1);
+        assert!(!projection
+            .get_occupied_locations(&MapId::main(), 0)
+            .contains(&location1));
+        assert_eq!(projection.get_occupied_location_count(&MapId::main(), 0), 1);
     }
 
     #[test]
@@ -271,18 +409,99 @@ mod tests {
 
         // Verify final state
         assert_eq!(
-            projection.get_people_at_location(&Location { x: 10, y: 20 }),
+            projection.get_people_at_location(&Location::new(10, 20)),
             vec![PersonId(3)]
         );
         assert_eq!(
-            projection.get_people_at_location(&Location { x: 30, y: 40 }),
+            projection.get_people_at_location(&Location::new(30, 40)),
             vec![PersonId(1)]
         );
         assert_eq!(
-            projection.get_people_at_location(&Location { x: 50, y: 60 }),
+            projection.get_people_at_location(&Location::new(50, 60)),
             vec![PersonId(2)]
         );
 
-        assert_eq!(projection.get_occupied_location_count(), 3);
+        assert_eq!(projection.get_occupied_location_count(&MapId::main(), 0), 3);
+    }
+
+    #[test]
+    fn test_apply_persons_moved_event() {
+        let mut projection = LocationOccupancyProjection::new();
+        projection.apply(&create_person_created_event(1, 10, 20));
+        projection.apply(&create_person_created_event(2, 10, 20));
+
+        let person_move = |id: u32, from_x: i32, to_x: i32| PersonMove {
+            person_id: PersonId(id),
+            from_location: Location::new(from_x, 20),
+            to_location: Location::new(to_x, 20),
+        };
+        projection.apply(&DomainEvent::Person(PersonEvent::PersonsMoved {
+            moves: vec![
+                person_move(1, 10, 11),
+                person_move(2, 10, 12),
+                person_move(1, 11, 13),
+            ],
+        }));
+
+        assert_eq!(
+            projection.get_people_at_location(&Location::new(13, 20)),
+            vec![PersonId(1)]
+        );
+        assert_eq!(
+            projection.get_people_at_location(&Location::new(12, 20)),
+            vec![PersonId(2)]
+        );
+        assert_eq!(projection.get_occupied_location_count(&MapId::main(), 0), 2);
+    }
+
+    #[test]
+    fn test_levels_are_kept_apart() {
+        let mut projection = LocationOccupancyProjection::new();
+        projection.apply(&create_person_created_event(1, 10, 20));
+        projection.apply(&DomainEvent::Person(PersonEvent::PersonMoved {
+            person_id: PersonId(1),
+            from_location: Location::new(10, 20),
+            to_location: Location::new(10, 20).at_level(1),
+        }));
+
+        assert!(projection
+            .get_people_at_location(&Location::new(10, 20))
+            .is_empty());
+        assert_eq!(projection.get_occupied_location_count(&MapId::main(), 0), 0);
+        assert_eq!(
+            projection.get_most_crowded_location(&MapId::main(), 1),
+            Some((Location::new(10, 20).at_level(1), 1))
+        );
+        assert_eq!(
+            projection.get_people_in_region(&Region::new(0, 0, 20, 20).at_level(1)),
+            vec![PersonId(1)]
+        );
+    }
+
+    #[test]
+    fn test_unloaded_regions_are_left_out_until_loaded() {
+        let mut projection = LocationOccupancyProjection::new();
+        projection.apply(&create_person_created_event(1, 10, 20));
+        projection.apply(&create_person_created_event(2, 40, 20));
+        let chunk = Chunk::of(&Location::new(10, 20));
+
+        projection.region_unloaded(&chunk.region());
+        assert!(projection
+            .get_people_at_location(&Location::new(10, 20))
+            .is_empty());
+        assert_eq!(projection.get_occupied_location_count(&MapId::main(), 0), 1);
+
+        projection.apply(&DomainEvent::Stream(StreamEvent::ChunkLoaded {
+            chunk,
+            persons: vec![Person {
+                id: PersonId(1),
+                name: "Person 1".to_string(),
+                location: Location::new(10, 20),
+            }],
+        }));
+        assert_eq!(
+            projection.get_people_at_location(&Location::new(10, 20)),
+            vec![PersonId(1)]
+        );
     }
 }
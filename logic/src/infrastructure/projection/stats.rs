@@ -0,0 +1,115 @@
+use crate::domain::event::person_event::PersonEvent;
+use crate::domain::event::DomainEvent;
+use crate::infrastructure::projection::Projection;
+use crate::infrastructure::time_series::Metrics;
+
+/// Projection counting what happens in the world, sampled into the metrics every few ticks
+pub struct StatsProjection {
+    persons: usize,
+    // Moves and events of any kind since the last sample
+    moves: usize,
+    events: usize,
+    // Ticks between two samples
+    interval: u64,
+    last_sample: Option<u64>,
+}
+
+impl StatsProjection {
+    pub fn new() -> Self {
+        StatsProjection {
+            persons: 0,
+            moves: 0,
+            events: 0,
+            interval: 1,
+            last_sample: None,
+        }
+    }
+
+    /// Ticks between two samples, at least 1
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    pub fn set_interval(&mut self, ticks: u64) {
+        self.interval = ticks.max(1);
+    }
+
+    /// Record the counts as the `persons`, `moves`, `occupied` and `event_rate` metrics once
+    /// `interval` ticks passed since the last sample, returns whether it did. Moves are counted
+    /// since the last sample, the event rate is the events per tick since then.
+    pub fn sample(&mut self, metrics: &Metrics, tick: u64, occupied: usize) -> bool {
+        let ticks = match self.last_sample {
+            Some(last) if tick.saturating_sub(last) < self.interval => return false,
+            Some(last) => tick - last,
+            None => 1,
+        };
+        metrics.record("persons", self.persons as f64);
+        metrics.record("moves", self.moves as f64);
+        metrics.record("occupied", occupied as f64);
+        metrics.record("event_rate", self.events as f64 / ticks as f64);
+        self.moves = 0;
+        self.events = 0;
+        self.last_sample = Some(tick);
+        true
+    }
+}
+
+impl Projection for StatsProjection {
+    fn apply(&mut self, event: &DomainEvent) {
+        self.events += 1;
+        match event {
+            DomainEvent::Person(PersonEvent::PersonCreated { .. }) => self.persons += 1,
+            DomainEvent::Person(PersonEvent::PersonMoved { .. }) => self.moves += 1,
+            DomainEvent::Person(PersonEvent::PersonsMoved { moves }) => self.moves += moves.len(),
+            _ => {}
+        }
+    }
+
+    fn name(&self) -> &str {
+        "StatsProjection"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entity::person::PersonId;
+    use crate::domain::value_object::location::Location;
+
+    #[test]
+    fn test_counts_are_sampled_every_interval() {
+        let mut projection = StatsProjection::new();
+        let metrics = Metrics::default();
+        projection.apply(&DomainEvent::Person(PersonEvent::PersonCreated {
+            person_id: PersonId(0),
+            name: "Alice".to_string(),
+            location: Location::new(0, 0),
+        }));
+        for _ in 0..3 {
+            projection.apply(&DomainEvent::Person(PersonEvent::PersonMoved {
+                person_id: PersonId(0),
+                from_location: Location::new(0, 0),
+                to_location: Location::new(1, 0),
+            }));
+        }
+        assert!(projection.sample(&metrics, 0, 1));
+        assert!(projection.sample(&metrics, 1, 1));
+        assert_eq!(metrics.latest("persons"), Some(1.0));
+        assert_eq!(metrics.latest("occupied"), Some(1.0));
+        assert_eq!(metrics.samples("moves"), vec![(0.0, 3.0), (0.0, 0.0)]);
+        assert_eq!(metrics.samples("event_rate"), vec![(0.0, 4.0), (0.0, 0.0)]);
+
+        projection.set_interval(4);
+        for _ in 0..8 {
+            projection.apply(&DomainEvent::Person(PersonEvent::PersonMoved {
+                person_id: PersonId(0),
+                from_location: Location::new(1, 0),
+                to_location: Location::new(0, 0),
+            }));
+        }
+        assert!(!projection.sample(&metrics, 4, 1));
+        assert!(projection.sample(&metrics, 5, 1));
+        assert_eq!(metrics.latest("moves"), Some(8.0));
+        assert_eq!(metrics.latest("event_rate"), Some(2.0));
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+// Samples kept per resolution level before the oldest are merged into the next level
+const SAMPLES_PER_LEVEL: usize = 600;
+// Samples merged into one when moving to the next level
+const DOWNSAMPLE_FACTOR: usize = 10;
+// Resolution levels, the oldest samples of the last one are dropped. With one sample per tick
+// at 60 ticks per second the last level reaches back more than eleven days.
+const LEVELS: usize = 6;
+
+/// Samples of one metric over time, at full resolution for recent samples and averaged over
+/// ever longer spans for older ones, so memory stays bounded however long the game runs
+#[derive(Debug, Clone)]
+pub struct TimeSeries {
+    // Level 0 has the raw samples, every further level samples averaged over
+    // DOWNSAMPLE_FACTOR times as many ticks. Oldest first within each level.
+    levels: Vec<VecDeque<(f64, f64)>>,
+    samples_per_level: usize,
+    factor: usize,
+}
+
+impl Default for TimeSeries {
+    fn default() -> Self {
+        Self::new(SAMPLES_PER_LEVEL, DOWNSAMPLE_FACTOR, LEVELS)
+    }
+}
+
+impl TimeSeries {
+    pub fn new(samples_per_level: usize, factor: usize, levels: usize) -> Self {
+        assert!(factor >= 2 && samples_per_level >= factor && levels >= 1);
+        TimeSeries {
+            levels: vec![VecDeque::with_capacity(samples_per_level); levels],
+            samples_per_level,
+            factor,
+        }
+    }
+
+    /// Add a sample, newer than all samples so far
+    pub fn push(&mut self, time: f64, value: f64) {
+        self.push_to_level(0, (time, value));
+    }
+
+    fn push_to_level(&mut self, level: usize, sample: (f64, f64)) {
+        self.levels[level].push_back(sample);
+        if self.levels[level].len() <= self.samples_per_level {
+            return;
+        }
+        if level + 1 == self.levels.len() {
+            self.levels[level].pop_front();
+            return;
+        }
+        let merged: Vec<_> = self.levels[level].drain(..self.factor).collect();
+        let count = merged.len() as f64;
+        let time = merged.iter().map(|(time, _)| time).sum::<f64>() / count;
+        let value = merged.iter().map(|(_, value)| value).sum::<f64>() / count;
+        self.push_to_level(level + 1, (time, value));
+    }
+
+    /// All samples as (time, value), oldest first
+    pub fn samples(&self) -> Vec<(f64, f64)> {
+        self.levels
+            .iter()
+            .rev()
+            .flat_map(|level| level.iter().copied())
+            .collect()
+    }
+
+    /// The newest sample
+    pub fn latest(&self) -> Option<(f64, f64)> {
+        self.levels.iter().find_map(|level| level.back().copied())
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.iter().map(VecDeque::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Default)]
+struct MetricsState {
+    series: BTreeMap<String, TimeSeries>,
+    tick: u64,
+}
+
+/// Time series of all metrics by name, shared between the logic sampling them every tick and
+/// the frontends plotting them
+#[derive(Clone, Default)]
+pub struct Metrics {
+    state: Arc<Mutex<MetricsState>>,
+}
+
+impl Metrics {
+    /// Record a value of a metric at the current tick
+    pub fn record(&self, name: &str, value: f64) {
+        let mut state = self.state.lock().unwrap();
+        let tick = state.tick as f64;
+        state
+            .series
+            .entry(name.to_string())
+            .or_default()
+            .push(tick, value);
+    }
+
+    /// Samples of a metric as (tick, value), oldest first
+    pub fn samples(&self, name: &str) -> Vec<(f64, f64)> {
+        let state = self.state.lock().unwrap();
+        state
+            .series
+            .get(name)
+            .map(TimeSeries::samples)
+            .unwrap_or_default()
+    }
+
+    /// Newest value of a metric
+    pub fn latest(&self, name: &str) -> Option<f64> {
+        let state = self.state.lock().unwrap();
+        state
+            .series
+            .get(name)
+            .and_then(TimeSeries::latest)
+            .map(|(_, value)| value)
+    }
+
+    /// Names of all metrics in alphabetical order
+    pub fn names(&self) -> Vec<String> {
+        self.state.lock().unwrap().series.keys().cloned().collect()
+    }
+
+    pub(crate) fn set_tick(&self, tick: u64) {
+        self.state.lock().unwrap().tick = tick;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_old_samples_are_downsampled() {
+        let mut series = TimeSeries::new(4, 2, 2);
+        for tick in 0..6 {
+            series.push(tick as f64, tick as f64 * 10.0);
+        }
+        // 0 and 1 were merged into the second level
+        assert_eq!(
+            series.samples(),
+            vec![
+                (0.5, 5.0),
+                (2.0, 20.0),
+                (3.0, 30.0),
+                (4.0, 40.0),
+                (5.0, 50.0)
+            ]
+        );
+
+        for tick in 6..100 {
+            series.push(tick as f64, tick as f64 * 10.0);
+        }
+        assert_eq!(series.len(), 8);
+        assert_eq!(series.latest(), Some((99.0, 990.0)));
+        let samples = series.samples();
+        assert!(samples.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn test_metrics_are_recorded_at_the_current_tick() {
+        let metrics = Metrics::default();
+        metrics.set_tick(3);
+        metrics.record("persons", 1.0);
+        metrics.set_tick(4);
+        metrics.record("persons", 2.0);
+        metrics.record("gold", 7.0);
+
+        assert_eq!(metrics.samples("persons"), vec![(3.0, 1.0), (4.0, 2.0)]);
+        assert_eq!(metrics.latest("gold"), Some(7.0));
+        assert_eq!(metrics.names(), vec!["gold", "persons"]);
+        assert!(metrics.samples("unknown").is_empty());
+    }
+}
@@ -1,7 +1,21 @@
 mod api;
+mod determinism;
 mod domain;
+mod dto;
 mod infrastructure;
+mod query;
+mod read_model;
+mod replay;
 mod repo;
+mod snapshot;
+mod state_hash;
 
 // adjust to what is actually needed later
 pub use api::*;
+pub use determinism::{first_divergence, Divergence};
+pub use query::PersonQuery;
+pub use read_model::{QueryList, QueryParam, Queryable, ReadModel, ReadModels, ReadQuery};
+pub use replay::{Replay, ReplayState};
+pub use repo::RepositoryBackend;
+pub use snapshot::{Snapshots, WorldSnapshot};
+pub use state_hash::{to_hex, StateHash};
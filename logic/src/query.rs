@@ -0,0 +1,83 @@
+use crate::domain::entity::person::Person;
+use crate::domain::value_object::region::Region;
+
+/// Selection of persons narrowed down by chained conditions, run by `QueryApi::persons` against
+/// the indexes so callers only get the matches
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PersonQuery {
+    pub(crate) region: Option<Region>,
+    pub(crate) tags: Vec<String>,
+    pub(crate) limit: Option<usize>,
+}
+
+impl PersonQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only persons inside the rectangle between two corners, both inclusive. Replaces an
+    /// earlier rectangle.
+    pub fn at_rect(mut self, x1: i32, y1: i32, x2: i32, y2: i32) -> Self {
+        self.region = Some(Region::new(x1, y1, x2, y2));
+        self
+    }
+
+    /// Only persons with the tag, chaining several requires all of them
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    /// At most `limit` persons, the ones with the lowest ids
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub(crate) fn contains(&self, person: &Person) -> bool {
+        self.region
+            .as_ref()
+            .is_none_or(|region| region.contains(&person.location))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CoreApi;
+    use std::time::{Duration, Instant};
+
+    fn names(persons: Vec<Person>) -> Vec<String> {
+        persons.into_iter().map(|person| person.name).collect()
+    }
+
+    #[test]
+    fn test_queries_combine_location_tags_and_limit() {
+        let core = CoreApi::new();
+        for (name, x) in [("Ann", 0), ("Bob", 5), ("Cid", 20), ("Dan", 2)] {
+            core.person().create(name.to_string(), x, 0).unwrap();
+        }
+        for entity in ["person:1", "person:2", "person:3"] {
+            core.tags().add(entity, "employee").unwrap();
+        }
+        let run = |query: PersonQuery| names(core.query().persons(&query).unwrap());
+
+        let employees = PersonQuery::new().with_tag("employee");
+        assert_eq!(run(employees.clone()), vec!["Bob", "Cid", "Dan"]);
+        assert_eq!(
+            run(employees.clone().at_rect(10, 10, 0, 0)),
+            vec!["Bob", "Dan"]
+        );
+        assert_eq!(run(employees.clone().limit(1)), vec!["Bob"]);
+        assert!(run(employees.with_tag("vip")).is_empty());
+        assert_eq!(run(PersonQuery::new().limit(2)), vec!["Ann", "Bob"]);
+
+        // Without tags the location projection narrows down, it's updated by its own thread
+        let in_rect = PersonQuery::new().at_rect(0, 0, 10, 0);
+        let started = Instant::now();
+        while run(in_rect.clone()).len() < 3 && started.elapsed() < Duration::from_secs(2) {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(run(in_rect), vec!["Ann", "Bob", "Dan"]);
+    }
+}
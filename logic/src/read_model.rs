@@ -0,0 +1,215 @@
+use crate::infrastructure::projection::Projection;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+
+type Answer = Box<dyn Fn(Vec<Value>) -> Result<Value, String> + Send + Sync>;
+
+/// Parameter of a query, for the docs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryParam {
+    pub name: String,
+    pub type_name: String,
+}
+
+/// A query a projection answers, reached by the scripts as `api.<module>.<name>`
+pub struct ReadQuery {
+    pub name: String,
+    pub description: String,
+    pub params: Vec<QueryParam>,
+    /// Type of the result, for the docs
+    pub returns: String,
+    answer: Answer,
+}
+
+/// The queries of one projection, exposed as a module of the `api` table
+pub struct ReadModel {
+    pub module: String,
+    pub queries: Vec<ReadQuery>,
+}
+
+impl ReadModel {
+    /// The read model of a registered projection, with the queries it declares
+    pub fn of<P: Queryable>(projection: Arc<Mutex<P>>) -> Self {
+        let mut queries = QueryList {
+            projection,
+            queries: Vec::new(),
+        };
+        P::queries(&mut queries);
+        Self {
+            module: P::MODULE.to_string(),
+            queries: queries.queries,
+        }
+    }
+}
+
+/// A projection declaring the queries the scripts can ask it, so no Lua glue needs to be
+/// written for it
+pub trait Queryable: Projection + Sized {
+    /// Name of its module in the `api` table
+    const MODULE: &'static str;
+
+    /// Declare the queries, see `QueryList::add`
+    fn queries(queries: &mut QueryList<Self>);
+}
+
+/// The queries a `Queryable` projection declares
+pub struct QueryList<P> {
+    projection: Arc<Mutex<P>>,
+    queries: Vec<ReadQuery>,
+}
+
+impl<P: Send + 'static> QueryList<P> {
+    /// Declare the query `name`, answered by `answer` with the projection locked. The
+    /// arguments of the script are deserialized into `A`, a tuple with one element per entry
+    /// of `params` given as (name, type), or `()` without any. The result is serialized to
+    /// what the script gets back.
+    pub fn add<A, R>(
+        &mut self,
+        name: &str,
+        description: &str,
+        params: &[(&str, &str)],
+        returns: &str,
+        answer: impl Fn(&P, A) -> R + Send + Sync + 'static,
+    ) where
+        A: DeserializeOwned,
+        R: Serialize,
+    {
+        let projection = self.projection.clone();
+        let query = name.to_string();
+        self.queries.push(ReadQuery {
+            name: name.to_string(),
+            description: description.to_string(),
+            params: params
+                .iter()
+                .map(|(name, type_name)| QueryParam {
+                    name: name.to_string(),
+                    type_name: type_name.to_string(),
+                })
+                .collect(),
+            returns: returns.to_string(),
+            answer: Box::new(move |args| {
+                let args = if args.is_empty() {
+                    Value::Null
+                } else {
+                    Value::Array(args)
+                };
+                let args: A = serde_json::from_value(args)
+                    .map_err(|e| format!("Bad arguments for {}: {}", query, e))?;
+                let result = answer(&projection.lock().unwrap(), args);
+                serde_json::to_value(result)
+                    .map_err(|e| format!("Failed to serialize the result of {}: {}", query, e))
+            }),
+        });
+    }
+}
+
+/// The read models of the projections, shared by the API and the frontends exposing them
+#[derive(Clone, Default)]
+pub struct ReadModels {
+    models: Arc<Mutex<Vec<ReadModel>>>,
+}
+
+impl ReadModels {
+    /// Add a read model, fails when there is one with its module name already
+    pub fn register(&self, model: ReadModel) -> Result<(), String> {
+        let mut models = self.models.lock().unwrap();
+        if models.iter().any(|other| other.module == model.module) {
+            return Err(format!("There is a read model '{}' already", model.module));
+        }
+        models.push(model);
+        Ok(())
+    }
+
+    /// Module names of the read models in the order they were registered
+    pub fn modules(&self) -> Vec<String> {
+        let models = self.models.lock().unwrap();
+        models.iter().map(|model| model.module.clone()).collect()
+    }
+
+    /// Look at a read model, None when there is no such module
+    pub fn with_model<T>(&self, module: &str, f: impl FnOnce(&ReadModel) -> T) -> Option<T> {
+        let models = self.models.lock().unwrap();
+        models.iter().find(|model| model.module == module).map(f)
+    }
+
+    /// Ask a query of a read model with the arguments of the script
+    pub fn call(&self, module: &str, query: &str, args: Vec<Value>) -> Result<Value, String> {
+        let models = self.models.lock().unwrap();
+        let model = models
+            .iter()
+            .find(|model| model.module == module)
+            .ok_or_else(|| format!("There is no read model '{}'", module))?;
+        let query = model
+            .queries
+            .iter()
+            .find(|other| other.name == query)
+            .ok_or_else(|| format!("api.{} has no query '{}'", module, query))?;
+        (query.answer)(args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::event::DomainEvent;
+    use serde_json::json;
+
+    #[derive(Default)]
+    struct Counter {
+        events: usize,
+    }
+
+    impl Projection for Counter {
+        fn apply(&mut self, _event: &DomainEvent) {
+            self.events += 1;
+        }
+
+        fn name(&self) -> &str {
+            "Counter"
+        }
+    }
+
+    impl Queryable for Counter {
+        const MODULE: &'static str = "counter";
+
+        fn queries(queries: &mut QueryList<Self>) {
+            queries.add("count", "Events seen", &[], "integer", |counter, ()| {
+                counter.events
+            });
+            queries.add(
+                "times",
+                "Events seen times the factor",
+                &[("factor", "integer")],
+                "integer",
+                |counter, (factor,): (usize,)| counter.events * factor,
+            );
+        }
+    }
+
+    #[test]
+    fn test_queries_deserialize_their_arguments() {
+        let models = ReadModels::default();
+        let counter = Arc::new(Mutex::new(Counter { events: 3 }));
+        models.register(ReadModel::of(counter.clone())).unwrap();
+        assert!(models.register(ReadModel::of(counter)).is_err());
+
+        assert_eq!(models.call("counter", "count", vec![]), Ok(json!(3)));
+        assert_eq!(
+            models.call("counter", "times", vec![json!(2)]),
+            Ok(json!(6))
+        );
+        assert!(models
+            .call("counter", "times", vec![json!("two")])
+            .unwrap_err()
+            .starts_with("Bad arguments for times"));
+        assert_eq!(
+            models.with_model("counter", |model| model.queries[1].params.clone()),
+            Some(vec![QueryParam {
+                name: "factor".to_string(),
+                type_name: "integer".to_string(),
+            }])
+        );
+    }
+}
@@ -0,0 +1,323 @@
+//! Replay of a recorded event log, for frontends to show the world as it was at any event
+
+use crate::domain::entity::person::Person;
+use crate::domain::event::map_event::MapEvent;
+use crate::domain::event::person_event::PersonEvent;
+use crate::domain::event::stream_event::StreamEvent;
+use crate::domain::event::vehicle_event::VehicleEvent;
+use crate::domain::event::world_event::WorldEvent;
+use crate::domain::event::DomainEvent;
+use crate::domain::value_object::location::Location;
+use crate::domain::value_object::map_id::MapId;
+use crate::infrastructure::codec::Codec;
+use crate::infrastructure::event_log::read_event;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+// Events between two states kept, seeking replays at most this many
+const KEYFRAME_INTERVAL: usize = 1000;
+
+// Name of a layer with its tiles by cell
+type Layer = (String, HashMap<(i32, i32), u32>);
+
+/// The world as far as the events replayed tell
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayState {
+    persons: BTreeMap<u32, Person>,
+    vehicles: BTreeMap<u32, Location>,
+    // Layers of each level of each map in the order they first got a tile
+    tiles: BTreeMap<(MapId, i32), Vec<Layer>>,
+    map: MapId,
+    level: i32,
+}
+
+impl ReplayState {
+    /// Replay one more event
+    pub fn apply(&mut self, event: &DomainEvent) {
+        match event {
+            DomainEvent::Person(PersonEvent::PersonCreated {
+                person_id,
+                name,
+                location,
+            }) => {
+                self.persons.insert(
+                    person_id.0,
+                    Person {
+                        id: *person_id,
+                        name: name.clone(),
+                        location: location.clone(),
+                    },
+                );
+            }
+            DomainEvent::Person(PersonEvent::PersonMoved {
+                person_id,
+                to_location,
+                ..
+            }) => self.move_person(person_id.0, to_location),
+            DomainEvent::Person(PersonEvent::PersonsMoved { moves }) => {
+                for person_move in moves {
+                    self.move_person(person_move.person_id.0, &person_move.to_location);
+                }
+            }
+            DomainEvent::Stream(StreamEvent::ChunkUnloaded { persons, .. }) => {
+                for person_id in persons {
+                    self.persons.remove(&person_id.0);
+                }
+            }
+            DomainEvent::Stream(StreamEvent::ChunkLoaded { persons, .. }) => {
+                for person in persons {
+                    self.persons.insert(person.id.0, person.clone());
+                }
+            }
+            DomainEvent::Vehicle(
+                VehicleEvent::VehicleCreated { vehicle, location }
+                | VehicleEvent::VehicleArrived { vehicle, location }
+                | VehicleEvent::VehicleStranded { vehicle, location }
+                | VehicleEvent::VehicleMoved {
+                    vehicle,
+                    to_location: location,
+                    ..
+                },
+            ) => {
+                self.vehicles.insert(vehicle.0, location.clone());
+            }
+            DomainEvent::Map(MapEvent::TilePlaced {
+                location,
+                layer,
+                tile,
+            }) => {
+                self.layer_mut(location, layer)
+                    .insert((location.x, location.y), *tile);
+            }
+            DomainEvent::Map(MapEvent::TileRemoved { location, layer }) => {
+                self.layer_mut(location, layer)
+                    .remove(&(location.x, location.y));
+            }
+            DomainEvent::World(WorldEvent::MapSwitched { to, .. }) => {
+                self.map = to.clone();
+                self.level = 0;
+            }
+            DomainEvent::World(WorldEvent::LevelSwitched { map, to, .. }) => {
+                self.map = map.clone();
+                self.level = *to;
+            }
+            _ => {}
+        }
+    }
+
+    fn move_person(&mut self, person_id: u32, to: &Location) {
+        if let Some(person) = self.persons.get_mut(&person_id) {
+            person.location = to.clone();
+        }
+    }
+
+    fn layer_mut(&mut self, location: &Location, layer: &str) -> &mut HashMap<(i32, i32), u32> {
+        let layers = self
+            .tiles
+            .entry((location.map.clone(), location.z))
+            .or_default();
+        let index = match layers.iter().position(|(name, _)| name == layer) {
+            Some(index) => index,
+            None => {
+                layers.push((layer.to_string(), HashMap::new()));
+                layers.len() - 1
+            }
+        };
+        &mut layers[index].1
+    }
+
+    /// Every person in the world, by id
+    pub fn persons(&self) -> impl Iterator<Item = &Person> {
+        self.persons.values()
+    }
+
+    /// Every vehicle with where it is, by id
+    pub fn vehicles(&self) -> impl Iterator<Item = (u32, &Location)> {
+        self.vehicles
+            .iter()
+            .map(|(vehicle, location)| (*vehicle, location))
+    }
+
+    /// The layers with tiles of a level of a map, bottom first
+    pub fn layers(&self, map: &MapId, level: i32) -> &[Layer] {
+        self.tiles
+            .get(&(map.clone(), level))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Map shown when the event replayed last happened
+    pub fn map(&self) -> &MapId {
+        &self.map
+    }
+
+    /// Level of the map shown, 0 is the ground level
+    pub fn level(&self) -> i32 {
+        self.level
+    }
+}
+
+/// A recorded run, seeked to any event without replaying it all from the start. Record a whole
+/// run with `--keep-events 0 --event-archive <path>`. Only what the events tell is replayed,
+/// tiles that were on the map when the run started aren't in the log.
+pub struct Replay {
+    events: Vec<DomainEvent>,
+    // State before every `KEYFRAME_INTERVAL`th event, the first is the empty world
+    keyframes: Vec<ReplayState>,
+    // The log ended in a broken record
+    torn: bool,
+}
+
+impl Replay {
+    pub fn new(events: Vec<DomainEvent>) -> Self {
+        let mut keyframes = vec![ReplayState::default()];
+        let mut state = ReplayState::default();
+        for (index, event) in events.iter().enumerate() {
+            if index > 0 && index % KEYFRAME_INTERVAL == 0 {
+                keyframes.push(state.clone());
+            }
+            state.apply(event);
+        }
+        Self {
+            events,
+            keyframes,
+            torn: false,
+        }
+    }
+
+    /// Load an event log written with the codec. A log torn by a crash or damaged on disk is
+    /// replayed up to its last whole event, without touching the file.
+    pub fn load(path: &Path, codec: Codec) -> io::Result<Self> {
+        let mut input = BufReader::new(File::open(path)?);
+        let mut events = Vec::new();
+        let torn = loop {
+            match read_event(&mut input, codec) {
+                Ok(Some(event)) => events.push(event),
+                Ok(None) => break false,
+                Err(e)
+                    if e.kind() == io::ErrorKind::UnexpectedEof
+                        || e.kind() == io::ErrorKind::InvalidData =>
+                {
+                    break true;
+                }
+                Err(e) => return Err(e),
+            }
+        };
+        Ok(Self {
+            torn,
+            ..Self::new(events)
+        })
+    }
+
+    /// Number of events recorded
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Whether the log ended in a broken record, the events after it are missing
+    pub fn is_torn(&self) -> bool {
+        self.torn
+    }
+
+    /// The event at the index, None past the end
+    pub fn event(&self, index: usize) -> Option<&DomainEvent> {
+        self.events.get(index)
+    }
+
+    /// The world once the first `position` events happened, the whole run past the end
+    pub fn state_at(&self, position: usize) -> ReplayState {
+        let position = position.min(self.events.len());
+        let keyframe = (position / KEYFRAME_INTERVAL).min(self.keyframes.len() - 1);
+        let mut state = self.keyframes[keyframe].clone();
+        for event in &self.events[keyframe * KEYFRAME_INTERVAL..position] {
+            state.apply(event);
+        }
+        state
+    }
+
+    /// Events in each of `buckets` equal stretches of the run, for drawing how busy it was
+    pub fn density(&self, buckets: usize) -> Vec<usize> {
+        let mut density = vec![0; buckets];
+        if buckets == 0 || self.events.is_empty() {
+            return density;
+        }
+        for index in 0..self.events.len() {
+            density[index * buckets / self.events.len()] += 1;
+        }
+        density
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entity::person::PersonId;
+    use crate::infrastructure::event_log::{EventLog, FsyncPolicy};
+
+    fn created(id: u32) -> DomainEvent {
+        DomainEvent::Person(PersonEvent::PersonCreated {
+            person_id: PersonId(id),
+            name: format!("P{}", id),
+            location: Location::new(0, 0),
+        })
+    }
+
+    fn moved(id: u32, x: i32) -> DomainEvent {
+        DomainEvent::Person(PersonEvent::PersonMoved {
+            person_id: PersonId(id),
+            from_location: Location::new(x - 1, 0),
+            to_location: Location::new(x, 0),
+        })
+    }
+
+    #[test]
+    fn test_seeking_matches_replaying_from_the_start() {
+        let mut events = vec![created(1), created(2)];
+        events.extend((1..2500).map(|x| moved(1 + x as u32 % 2, x)));
+        events.push(DomainEvent::Map(MapEvent::TilePlaced {
+            location: Location::new(3, 4),
+            layer: "ground".to_string(),
+            tile: 7,
+        }));
+        let replay = Replay::new(events.clone());
+
+        for position in [0, 1, 999, 1000, 1001, 2345, events.len(), events.len() + 5] {
+            let mut expected = ReplayState::default();
+            for event in events.iter().take(position) {
+                expected.apply(event);
+            }
+            assert_eq!(replay.state_at(position), expected, "at {}", position);
+        }
+        let end = replay.state_at(replay.len());
+        let locations: Vec<i32> = end.persons().map(|person| person.location.x).collect();
+        assert_eq!(locations, vec![2498, 2499]);
+        let layers = end.layers(&MapId::main(), 0);
+        assert_eq!(layers[0].0, "ground");
+        assert_eq!(layers[0].1.get(&(3, 4)), Some(&7));
+
+        assert_eq!(replay.density(4), vec![626, 625, 626, 625]);
+        assert_eq!(Replay::new(Vec::new()).density(3), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn test_a_torn_log_is_replayed_up_to_its_last_whole_event() {
+        let path = std::env::temp_dir().join(format!("replay_{}.bin", std::process::id()));
+        let mut log = EventLog::create(&path, Codec::Binary, FsyncPolicy::Never).unwrap();
+        log.append(&[created(1), moved(1, 1), moved(1, 2)]).unwrap();
+        drop(log);
+        let whole = std::fs::read(&path).unwrap();
+        assert!(!Replay::load(&path, Codec::Binary).unwrap().is_torn());
+
+        std::fs::write(&path, &whole[..whole.len() - 2]).unwrap();
+        let replay = Replay::load(&path, Codec::Binary).unwrap();
+        assert!(replay.is_torn());
+        assert_eq!(replay.len(), 2);
+        assert_eq!(std::fs::read(&path).unwrap().len(), whole.len() - 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,9 +1,15 @@
+mod map_repository;
 mod vec_repository;
+
+use crate::domain::entity::person::{Person, PersonId};
+
 pub(crate) trait Repository<ID, Entity> {
     type Error;
     fn get(&self, id: ID) -> Result<Entity, Self::Error>;
     fn add(&mut self, entity: Entity) -> Result<ID, Self::Error>;
     fn remove(&mut self, id: ID) -> Result<Entity, Self::Error>;
+    /// Put a removed entity back under its id
+    fn restore(&mut self, id: ID, entity: Entity) -> Result<(), Self::Error>;
     fn update(&mut self, id: ID, entity: Entity) -> Result<Entity, Self::Error>;
     fn get_all(&self) -> Result<Vec<Entity>, Self::Error>;
     fn create<F>(&mut self, entity_factory: F) -> Result<Entity, Self::Error>
@@ -16,4 +22,93 @@ pub(crate) trait NumericId: Copy + Eq + std::fmt::Debug {
     fn from_value(value: u32) -> Self;
 }
 
+#[derive(Debug)]
+pub(crate) enum RepositoryError {
+    NotFound,
+    // The id is held by another entity
+    Taken,
+}
+
+/// Where the persons are kept, see `CoreApiBuilder::repository`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RepositoryBackend {
+    /// A slot for every id ever handed out, the fastest to look up
+    #[default]
+    Vec,
+    /// Only the ids in use, for worlds streaming many persons in and out
+    Map,
+}
+
+/// The repository of the persons, of the backend the API was built with
+pub(crate) enum PersonRepository {
+    Vec(VecRepository<PersonId, Person>),
+    Map(MapRepository<PersonId, Person>),
+}
+
+impl PersonRepository {
+    pub(crate) fn new(backend: RepositoryBackend) -> Self {
+        match backend {
+            RepositoryBackend::Vec => PersonRepository::Vec(VecRepository::new()),
+            RepositoryBackend::Map => PersonRepository::Map(MapRepository::new()),
+        }
+    }
+}
+
+impl Repository<PersonId, Person> for PersonRepository {
+    type Error = RepositoryError;
+
+    fn get(&self, id: PersonId) -> Result<Person, Self::Error> {
+        match self {
+            PersonRepository::Vec(repo) => repo.get(id),
+            PersonRepository::Map(repo) => repo.get(id),
+        }
+    }
+
+    fn add(&mut self, entity: Person) -> Result<PersonId, Self::Error> {
+        match self {
+            PersonRepository::Vec(repo) => repo.add(entity),
+            PersonRepository::Map(repo) => repo.add(entity),
+        }
+    }
+
+    fn remove(&mut self, id: PersonId) -> Result<Person, Self::Error> {
+        match self {
+            PersonRepository::Vec(repo) => repo.remove(id),
+            PersonRepository::Map(repo) => repo.remove(id),
+        }
+    }
+
+    fn restore(&mut self, id: PersonId, entity: Person) -> Result<(), Self::Error> {
+        match self {
+            PersonRepository::Vec(repo) => repo.restore(id, entity),
+            PersonRepository::Map(repo) => repo.restore(id, entity),
+        }
+    }
+
+    fn update(&mut self, id: PersonId, entity: Person) -> Result<Person, Self::Error> {
+        match self {
+            PersonRepository::Vec(repo) => repo.update(id, entity),
+            PersonRepository::Map(repo) => repo.update(id, entity),
+        }
+    }
+
+    fn get_all(&self) -> Result<Vec<Person>, Self::Error> {
+        match self {
+            PersonRepository::Vec(repo) => repo.get_all(),
+            PersonRepository::Map(repo) => repo.get_all(),
+        }
+    }
+
+    fn create<F>(&mut self, entity_factory: F) -> Result<Person, Self::Error>
+    where
+        F: FnOnce(PersonId) -> Person,
+    {
+        match self {
+            PersonRepository::Vec(repo) => repo.create(entity_factory),
+            PersonRepository::Map(repo) => repo.create(entity_factory),
+        }
+    }
+}
+
+pub(crate) use map_repository::MapRepository;
 pub(crate) use vec_repository::VecRepository;
@@ -0,0 +1,120 @@
+use crate::repo::{NumericId, Repository, RepositoryError};
+use std::collections::BTreeMap;
+
+/// Entities by id, holding nothing for the ids no longer in use. Ids are handed out like the
+/// `VecRepository` does, one past the highest ever used.
+pub(crate) struct MapRepository<ID: NumericId, T> {
+    data: BTreeMap<u32, T>,
+    next_id: u32,
+    _id_type: std::marker::PhantomData<ID>,
+}
+
+impl<ID: NumericId, T> MapRepository<ID, T> {
+    pub(crate) fn new() -> Self {
+        MapRepository {
+            data: BTreeMap::new(),
+            next_id: 0,
+            _id_type: Default::default(),
+        }
+    }
+}
+
+impl<ID: NumericId, T: Clone> Repository<ID, T> for MapRepository<ID, T> {
+    type Error = RepositoryError;
+
+    fn get(&self, id: ID) -> Result<T, Self::Error> {
+        self.data
+            .get(&id.value())
+            .cloned()
+            .ok_or(RepositoryError::NotFound)
+    }
+
+    fn add(&mut self, entity: T) -> Result<ID, Self::Error> {
+        let id = ID::from_value(self.next_id);
+        self.data.insert(self.next_id, entity);
+        self.next_id += 1;
+        Ok(id)
+    }
+
+    fn remove(&mut self, id: ID) -> Result<T, Self::Error> {
+        self.data
+            .remove(&id.value())
+            .ok_or(RepositoryError::NotFound)
+    }
+
+    fn restore(&mut self, id: ID, entity: T) -> Result<(), Self::Error> {
+        if self.data.contains_key(&id.value()) {
+            return Err(RepositoryError::Taken);
+        }
+        self.data.insert(id.value(), entity);
+        self.next_id = self.next_id.max(id.value() + 1);
+        Ok(())
+    }
+
+    fn update(&mut self, id: ID, entity: T) -> Result<T, Self::Error> {
+        match self.data.get_mut(&id.value()) {
+            Some(old_entity) => Ok(std::mem::replace(old_entity, entity)),
+            None => Err(RepositoryError::NotFound),
+        }
+    }
+
+    fn get_all(&self) -> Result<Vec<T>, Self::Error> {
+        Ok(self.data.values().cloned().collect())
+    }
+
+    fn create<F>(&mut self, entity_factory: F) -> Result<T, Self::Error>
+    where
+        F: FnOnce(ID) -> T,
+    {
+        let entity = entity_factory(ID::from_value(self.next_id));
+        self.data.insert(self.next_id, entity.clone());
+        self.next_id += 1;
+        Ok(entity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct TestId(u32);
+
+    impl NumericId for TestId {
+        fn value(&self) -> u32 {
+            self.0
+        }
+
+        fn from_value(value: u32) -> Self {
+            TestId(value)
+        }
+    }
+
+    #[test]
+    fn test_removed_ids_leave_nothing_behind_and_are_not_reused() {
+        let mut repo = MapRepository::<TestId, String>::new();
+        let first = repo.add("first".to_string()).unwrap();
+        let second = repo.create(|id| format!("second {}", id.0)).unwrap();
+        assert_eq!(second, "second 1");
+
+        assert_eq!(repo.remove(first).unwrap(), "first");
+        assert!(matches!(repo.get(first), Err(RepositoryError::NotFound)));
+        assert_eq!(repo.data.len(), 1);
+        assert_eq!(repo.add("third".to_string()).unwrap(), TestId(2));
+
+        repo.restore(TestId(7), "restored".to_string()).unwrap();
+        assert!(matches!(
+            repo.restore(TestId(7), "again".to_string()),
+            Err(RepositoryError::Taken)
+        ));
+        assert_eq!(repo.add("next".to_string()).unwrap(), TestId(8));
+        assert_eq!(
+            repo.update(TestId(2), "updated".to_string()).unwrap(),
+            "third"
+        );
+        assert_eq!(
+            repo.get_all().unwrap(),
+            vec!["second 1", "updated", "restored", "next"]
+        );
+    }
+}
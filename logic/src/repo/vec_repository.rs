@@ -1,9 +1,4 @@
-use crate::repo::{NumericId, Repository};
-
-#[derive(Debug)]
-pub(crate) enum VecRepositoryError {
-    NotFound,
-}
+use crate::repo::{NumericId, Repository, RepositoryError};
 
 pub(crate) struct VecRepository<ID: NumericId, T> {
     data: Vec<Option<T>>,
@@ -20,17 +15,17 @@ impl<ID: NumericId, T> VecRepository<ID, T> {
 }
 
 impl<ID: NumericId, T: Clone> Repository<ID, T> for VecRepository<ID, T> {
-    type Error = VecRepositoryError;
+    type Error = RepositoryError;
 
     fn get(&self, id: ID) -> Result<T, Self::Error> {
         let index = id.value() as usize;
         if index >= self.data.len() {
-            return Err(VecRepositoryError::NotFound);
+            return Err(RepositoryError::NotFound);
         }
 
         match &self.data[index] {
             Some(entity) => Ok(entity.clone()),
-            None => Err(VecRepositoryError::NotFound),
+            None => Err(RepositoryError::NotFound),
         }
     }
 
@@ -43,19 +38,34 @@ impl<ID: NumericId, T: Clone> Repository<ID, T> for VecRepository<ID, T> {
     fn remove(&mut self, id: ID) -> Result<T, Self::Error> {
         let index = id.value() as usize;
         if index >= self.data.len() {
-            return Err(VecRepositoryError::NotFound);
+            return Err(RepositoryError::NotFound);
         }
 
         match self.data[index].take() {
             Some(entity) => Ok(entity),
-            None => Err(VecRepositoryError::NotFound),
+            None => Err(RepositoryError::NotFound),
+        }
+    }
+
+    fn restore(&mut self, id: ID, entity: T) -> Result<(), Self::Error> {
+        let index = id.value() as usize;
+        if index >= self.data.len() {
+            self.data.resize_with(index + 1, || None);
+        }
+
+        match self.data[index] {
+            Some(_) => Err(RepositoryError::Taken),
+            None => {
+                self.data[index] = Some(entity);
+                Ok(())
+            }
         }
     }
 
     fn update(&mut self, id: ID, entity: T) -> Result<T, Self::Error> {
         let index = id.value() as usize;
         if index >= self.data.len() {
-            return Err(VecRepositoryError::NotFound);
+            return Err(RepositoryError::NotFound);
         }
 
         match self.data[index].take() {
@@ -63,7 +73,7 @@ impl<ID: NumericId, T: Clone> Repository<ID, T> for VecRepository<ID, T> {
                 self.data[index] = Some(entity);
                 Ok(old_entity)
             }
-            None => Err(VecRepositoryError::NotFound),
+            None => Err(RepositoryError::NotFound),
         }
     }
 
@@ -145,7 +155,7 @@ mod tests {
 
         // Try to get an entity with an ID that doesn't exist
         let result = repo.get(TestId(0));
-        assert!(matches!(result, Err(VecRepositoryError::NotFound)));
+        assert!(matches!(result, Err(RepositoryError::NotFound)));
     }
 
     #[test]
@@ -160,7 +170,7 @@ mod tests {
 
         // Try to get the removed entity
         let result = repo.get(id);
-        assert!(matches!(result, Err(VecRepositoryError::NotFound)));
+        assert!(matches!(result, Err(RepositoryError::NotFound)));
     }
 
     #[test]
@@ -169,7 +179,7 @@ mod tests {
 
         // Try to remove an entity with an ID that doesn't exist
         let result = repo.remove(TestId(0));
-        assert!(matches!(result, Err(VecRepositoryError::NotFound)));
+        assert!(matches!(result, Err(RepositoryError::NotFound)));
     }
 
     #[test]
@@ -182,7 +192,30 @@ mod tests {
 
         // Try to remove it again
         let result = repo.remove(id);
-        assert!(matches!(result, Err(VecRepositoryError::NotFound)));
+        assert!(matches!(result, Err(RepositoryError::NotFound)));
+    }
+
+    #[test]
+    fn test_restore_removed() {
+        let mut repo = create_string_repo();
+
+        // Add and remove an entity
+        let id = repo.add("test entity".to_string()).unwrap();
+        let removed = repo.remove(id).unwrap();
+
+        // Put it back, but only once
+        repo.restore(id, removed.clone()).unwrap();
+        assert_eq!(repo.get(id).unwrap(), "test entity");
+        let result = repo.restore(id, removed);
+        assert!(matches!(result, Err(RepositoryError::Taken)));
+
+        // Ids past the end leave the ones between free
+        repo.restore(TestId(3), "restored".to_string()).unwrap();
+        assert!(matches!(
+            repo.get(TestId(2)),
+            Err(RepositoryError::NotFound)
+        ));
+        assert_eq!(repo.add("next".to_string()).unwrap(), TestId(4));
     }
 
     #[test]
@@ -205,7 +238,7 @@ mod tests {
 
         // Try to update an entity with an ID that doesn't exist
         let result = repo.update(TestId(0), "updated".to_string());
-        assert!(matches!(result, Err(VecRepositoryError::NotFound)));
+        assert!(matches!(result, Err(RepositoryError::NotFound)));
     }
 
     #[test]
@@ -218,7 +251,7 @@ mod tests {
 
         // Try to update the removed entity
         let result = repo.update(id, "updated".to_string());
-        assert!(matches!(result, Err(VecRepositoryError::NotFound)));
+        assert!(matches!(result, Err(RepositoryError::NotFound)));
     }
 
     #[test]
@@ -265,7 +298,7 @@ mod tests {
         assert_eq!("entity 1".to_string(), repo.get(id1).unwrap());
         assert_eq!("entity 3".to_string(), repo.get(id3).unwrap());
         // test that getting id2 now gives an error:
-        assert!(matches!(repo.get(id2), Err(VecRepositoryError::NotFound)));
+        assert!(matches!(repo.get(id2), Err(RepositoryError::NotFound)));
     }
 
     #[test]
@@ -287,7 +320,7 @@ mod tests {
 
         // Check the state
         assert_eq!(repo.get(id1).unwrap(), "updated entity 1");
-        assert!(matches!(repo.get(id2), Err(VecRepositoryError::NotFound)));
+        assert!(matches!(repo.get(id2), Err(RepositoryError::NotFound)));
         assert_eq!(repo.get(id3).unwrap(), "entity 3");
 
         let all = repo.get_all().unwrap();
@@ -444,7 +477,7 @@ mod tests {
         // Verify it was removed
         assert!(matches!(
             repo.get(TestId(0)),
-            Err(VecRepositoryError::NotFound)
+            Err(RepositoryError::NotFound)
         ));
     }
 
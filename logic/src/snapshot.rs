@@ -0,0 +1,114 @@
+use crate::domain::entity::person::Person;
+use crate::domain::value_object::map_id::MapId;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Immutable view of the world as it was at the end of a tick, for frontends to draw from
+/// without holding any lock of the domain. Shared as `Arc`, so cloning is cheap.
+#[derive(Debug, Default)]
+pub struct WorldSnapshot {
+    tick: u64,
+    persons: Vec<Person>,
+    occupancy: HashMap<(i32, i32), Vec<u32>>,
+}
+
+impl WorldSnapshot {
+    // The tiles are indexed for the level of the map that's active, the one the frontends show
+    pub(crate) fn new(tick: u64, mut persons: Vec<Person>, map: &MapId, z: i32) -> Self {
+        persons.sort_by_key(|person| person.id);
+        let mut occupancy: HashMap<(i32, i32), Vec<u32>> = HashMap::new();
+        for person in persons
+            .iter()
+            .filter(|person| person.location.map == *map && person.location.z == z)
+        {
+            occupancy
+                .entry((person.location.x, person.location.y))
+                .or_default()
+                .push(person.id.0);
+        }
+        Self {
+            tick,
+            persons,
+            occupancy,
+        }
+    }
+
+    /// Tick the snapshot was taken at
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// All persons ordered by id
+    pub fn persons(&self) -> &[Person] {
+        &self.persons
+    }
+
+    /// Person with the id, if it existed when the snapshot was taken
+    pub fn person(&self, person_id: u32) -> Option<&Person> {
+        self.persons
+            .binary_search_by_key(&person_id, |person| person.id.0)
+            .ok()
+            .map(|index| &self.persons[index])
+    }
+
+    /// Ids of the persons on a tile of the level that was active
+    pub fn people_at(&self, x: i32, y: i32) -> &[u32] {
+        self.occupancy.get(&(x, y)).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Handle to the latest snapshot, cloned by the frontends so they can read it from their own
+/// thread while the logic takes the next one
+#[derive(Clone, Default)]
+pub struct Snapshots {
+    latest: Arc<RwLock<Arc<WorldSnapshot>>>,
+}
+
+impl Snapshots {
+    /// The snapshot of the last tick, empty before the first one
+    pub fn latest(&self) -> Arc<WorldSnapshot> {
+        self.latest.read().unwrap().clone()
+    }
+
+    pub(crate) fn publish(&self, snapshot: WorldSnapshot) {
+        *self.latest.write().unwrap() = Arc::new(snapshot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entity::person::PersonId;
+    use crate::domain::value_object::location::Location;
+
+    fn person(id: u32, x: i32, y: i32) -> Person {
+        Person {
+            id: PersonId(id),
+            name: format!("Person {}", id),
+            location: Location::new(x, y),
+        }
+    }
+
+    #[test]
+    fn test_snapshots_stay_unchanged_when_a_new_one_is_published() {
+        let snapshots = Snapshots::default();
+        assert!(snapshots.latest().persons().is_empty());
+
+        snapshots.publish(WorldSnapshot::new(
+            1,
+            vec![person(2, 0, 0), person(0, 3, 4), person(1, 0, 0)],
+            &MapId::main(),
+            0,
+        ));
+        let first = snapshots.latest();
+        snapshots.publish(WorldSnapshot::new(2, vec![], &MapId::main(), 0));
+
+        assert_eq!(first.tick(), 1);
+        assert_eq!(first.persons()[0].id, PersonId(0));
+        assert_eq!(first.person(2).unwrap().name, "Person 2");
+        assert!(first.person(5).is_none());
+        assert_eq!(first.people_at(0, 0), &[1, 2]);
+        assert!(first.people_at(9, 9).is_empty());
+        assert_eq!(snapshots.latest().tick(), 2);
+    }
+}
@@ -0,0 +1,80 @@
+use serde::Serialize;
+use std::io;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+// FNV-1a over the bytes written to it, the same on every platform and Rust version unlike the
+// hashers of std
+struct Fnv1a(u64);
+
+impl io::Write for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(FNV_PRIME);
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Hash of a value by its bincode encoding, which is little endian on every platform
+pub fn hash_value(value: &impl Serialize) -> u64 {
+    let mut hasher = Fnv1a(FNV_OFFSET_BASIS);
+    bincode::serialize_into(&mut hasher, value).expect("hashing doesn't fail");
+    hasher.0
+}
+
+/// Hash of the world state by part, equal for runs, replays and peers that agree on it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateHash {
+    pub parts: Vec<(&'static str, u64)>,
+}
+
+impl StateHash {
+    /// One hash over all the parts
+    pub fn combined(&self) -> u64 {
+        hash_value(&self.parts)
+    }
+
+    /// Names of the parts hashed differently in the other hash
+    pub fn differing(&self, other: &StateHash) -> Vec<&'static str> {
+        self.parts
+            .iter()
+            .filter(|(name, hash)| {
+                other
+                    .parts
+                    .iter()
+                    .all(|(other_name, other_hash)| other_name != name || other_hash != hash)
+            })
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+/// A hash as the 16 hex digits scripts and tools compare, Lua numbers can't hold all 64 bits
+pub fn to_hex(hash: u64) -> String {
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashes_are_stable_and_tell_which_part_differs() {
+        // FNV-1a of the bincode encoding of the 3 bytes, pinned so it can't change between builds
+        assert_eq!(hash_value(&[1u8, 2, 3]), 0xd0aa_6218_672c_f5ab);
+        let first = StateHash {
+            parts: vec![("persons", hash_value(&"Ann")), ("tags", hash_value(&1u32))],
+        };
+        let mut second = first.clone();
+        assert_eq!(first.combined(), second.combined());
+        second.parts[1].1 = hash_value(&2u32);
+        assert_ne!(first.combined(), second.combined());
+        assert_eq!(first.differing(&second), vec!["tags"]);
+    }
+}
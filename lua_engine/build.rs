@@ -140,8 +140,10 @@ fn find_api_files(dir: &str) -> Vec<String> {
                 if path.is_dir() {
                     let mut subdir_files = find_api_files(path.to_str().unwrap());
                     result.append(&mut subdir_files);
-                } else if let Some(ext) = path.extension() {
-                    if ext == "rs" && path.to_str().unwrap().contains("api") {
+                } else if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                    // Only `<module>_api.rs` are modules of the scripting API, the builder next to
+                    // them isn't
+                    if name.ends_with("_api.rs") {
                         result.push(path.to_str().unwrap().to_string());
                     }
                 }
@@ -0,0 +1,447 @@
+//! Input actions shared by the frontends, and the key bindings mapping raw device input to them
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+
+/// Bindings file read by both frontends, in the working directory. Keys are named like in the
+/// pixel UI ("a", "pageup", "leftbracket"), mouse buttons as "mouse_left", "mouse_right" and
+/// "mouse_middle", each frontend skips the names it doesn't know:
+///
+/// ```json
+/// { "toggle_console": ["graveaccent", "f1"], "pan_up": ["w", "up"], "select": ["mouse_left"] }
+/// ```
+pub const KEY_BINDINGS_PATH: &str = "keybindings.json";
+
+/// Something the player asks for, independent of the key or button it was bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    /// Click a tile or person
+    Select,
+    /// Paint with the brush while held
+    Paint,
+    ToggleConsole,
+    ToggleDebug,
+    /// Switch how people are batched for drawing, to compare the draw calls
+    TogglePeopleBatching,
+    /// Start recording the edits as a Lua macro, again to stop and save it
+    ToggleMacroRecording,
+    /// Watch without editing, see `api.permissions`
+    ToggleSpectator,
+    SaveMap,
+    PeopleTool,
+    EraseTool,
+    FillTool,
+    MeasureTool,
+    /// Add and edit patrol routes, again to start a new route
+    RouteTool,
+    /// Pick the hovered tile as the brush
+    Eyedropper,
+    /// Zoom and center so the whole map is visible
+    FitMap,
+    /// Picture-in-picture view of the person closest to the cursor
+    PictureInPicture,
+    /// Off-screen indicator for the person closest to the cursor
+    TrackPerson,
+    ToggleLayers,
+    ToggleGoals,
+    ToggleNotifications,
+    ToggleModSettings,
+    /// Search the commands of the tools and the scripts and run one
+    CommandPalette,
+    /// Browse the docs of the scripting API
+    ApiDocs,
+    EndTurn,
+    LevelUp,
+    LevelDown,
+    BrushGrow,
+    BrushShrink,
+    BrushShape,
+    ZoomReset,
+    ZoomDouble,
+    ZoomHalf,
+    /// Zoom one step in around the center of the view
+    ZoomIn,
+    ZoomOut,
+}
+
+impl Action {
+    pub const ALL: [Action; 38] = [
+        Action::PanUp,
+        Action::PanDown,
+        Action::PanLeft,
+        Action::PanRight,
+        Action::Select,
+        Action::Paint,
+        Action::ToggleConsole,
+        Action::ToggleDebug,
+        Action::TogglePeopleBatching,
+        Action::ToggleMacroRecording,
+        Action::ToggleSpectator,
+        Action::SaveMap,
+        Action::PeopleTool,
+        Action::EraseTool,
+        Action::FillTool,
+        Action::MeasureTool,
+        Action::RouteTool,
+        Action::Eyedropper,
+        Action::FitMap,
+        Action::PictureInPicture,
+        Action::TrackPerson,
+        Action::ToggleLayers,
+        Action::ToggleGoals,
+        Action::ToggleNotifications,
+        Action::ToggleModSettings,
+        Action::CommandPalette,
+        Action::ApiDocs,
+        Action::EndTurn,
+        Action::LevelUp,
+        Action::LevelDown,
+        Action::BrushGrow,
+        Action::BrushShrink,
+        Action::BrushShape,
+        Action::ZoomReset,
+        Action::ZoomDouble,
+        Action::ZoomHalf,
+        Action::ZoomIn,
+        Action::ZoomOut,
+    ];
+
+    /// Name used in the bindings file and in Lua
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::PanUp => "pan_up",
+            Action::PanDown => "pan_down",
+            Action::PanLeft => "pan_left",
+            Action::PanRight => "pan_right",
+            Action::Select => "select",
+            Action::Paint => "paint",
+            Action::ToggleConsole => "toggle_console",
+            Action::ToggleDebug => "toggle_debug",
+            Action::TogglePeopleBatching => "toggle_people_batching",
+            Action::ToggleMacroRecording => "toggle_macro_recording",
+            Action::ToggleSpectator => "toggle_spectator",
+            Action::SaveMap => "save_map",
+            Action::PeopleTool => "people_tool",
+            Action::EraseTool => "erase_tool",
+            Action::FillTool => "fill_tool",
+            Action::MeasureTool => "measure_tool",
+            Action::RouteTool => "route_tool",
+            Action::Eyedropper => "eyedropper",
+            Action::FitMap => "fit_map",
+            Action::PictureInPicture => "picture_in_picture",
+            Action::TrackPerson => "track_person",
+            Action::ToggleLayers => "toggle_layers",
+            Action::ToggleGoals => "toggle_goals",
+            Action::ToggleNotifications => "toggle_notifications",
+            Action::ToggleModSettings => "toggle_mod_settings",
+            Action::CommandPalette => "command_palette",
+            Action::ApiDocs => "api_docs",
+            Action::EndTurn => "end_turn",
+            Action::LevelUp => "level_up",
+            Action::LevelDown => "level_down",
+            Action::BrushGrow => "brush_grow",
+            Action::BrushShrink => "brush_shrink",
+            Action::BrushShape => "brush_shape",
+            Action::ZoomReset => "zoom_reset",
+            Action::ZoomDouble => "zoom_double",
+            Action::ZoomHalf => "zoom_half",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    /// Unknown names are an error listing the known ones
+    pub fn parse(name: &str) -> Result<Self, String> {
+        Self::from_name(name).ok_or_else(|| {
+            format!(
+                "Unknown action '{}', expected one of: {}",
+                name,
+                Self::ALL.map(Action::name).join(", ")
+            )
+        })
+    }
+
+    // Bindings used when the bindings file doesn't mention the action
+    fn default_chords(self) -> &'static [&'static str] {
+        match self {
+            Action::PanUp => &["w", "up"],
+            Action::PanDown => &["s", "down"],
+            Action::PanLeft => &["a", "left"],
+            Action::PanRight => &["d", "right"],
+            Action::Select => &["mouse_left"],
+            Action::Paint => &["mouse_right"],
+            Action::ToggleConsole => &["graveaccent"],
+            Action::ToggleDebug => &["shift+d"],
+            Action::TogglePeopleBatching => &["shift+b"],
+            Action::ToggleMacroRecording => &["ctrl+m"],
+            Action::ToggleSpectator => &["shift+v"],
+            Action::SaveMap => &["ctrl+s"],
+            Action::PeopleTool => &["e"],
+            Action::EraseTool => &["x"],
+            Action::FillTool => &["f"],
+            Action::MeasureTool => &["m"],
+            Action::RouteTool => &["r"],
+            Action::Eyedropper => &["i"],
+            Action::FitMap => &["home"],
+            Action::PictureInPicture => &["p"],
+            Action::TrackPerson => &["t"],
+            Action::ToggleLayers => &["l"],
+            Action::ToggleGoals => &["g"],
+            Action::ToggleNotifications => &["n"],
+            Action::ToggleModSettings => &["o"],
+            Action::CommandPalette => &["ctrl+p"],
+            Action::ApiDocs => &["f1"],
+            Action::EndTurn => &["enter"],
+            Action::LevelUp => &["pageup"],
+            Action::LevelDown => &["pagedown"],
+            Action::BrushGrow => &["rightbracket"],
+            Action::BrushShrink => &["leftbracket"],
+            Action::BrushShape => &["b"],
+            Action::ZoomReset => &["1"],
+            Action::ZoomDouble => &["2"],
+            Action::ZoomHalf => &["3"],
+            Action::ZoomIn => &["equal"],
+            Action::ZoomOut => &["minus"],
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Key or mouse button with the modifiers that have to be held, like "ctrl+shift+p".
+/// The key is only checked to be non-empty, the frontends know which names exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chord {
+    pub key: String,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl Chord {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let parts: Vec<String> = spec
+            .split('+')
+            .map(|part| part.trim().to_lowercase())
+            .collect();
+        let Some((key, modifiers)) = parts.split_last() else {
+            return Err(format!("Empty binding '{}'", spec));
+        };
+        if key.is_empty() {
+            return Err(format!("Missing key in '{}'", spec));
+        }
+        let mut chord = Chord {
+            key: key.clone(),
+            ctrl: false,
+            shift: false,
+            alt: false,
+        };
+        for modifier in modifiers {
+            match modifier.as_str() {
+                "ctrl" | "control" => chord.ctrl = true,
+                "shift" => chord.shift = true,
+                "alt" => chord.alt = true,
+                _ => return Err(format!("Unknown modifier '{}' in '{}'", modifier, spec)),
+            }
+        }
+        Ok(chord)
+    }
+
+    /// Name of the mouse button for "mouse_left" and the like
+    pub fn mouse_button(&self) -> Option<&str> {
+        self.key.strip_prefix("mouse_")
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            f.write_str("ctrl+")?;
+        }
+        if self.shift {
+            f.write_str("shift+")?;
+        }
+        if self.alt {
+            f.write_str("alt+")?;
+        }
+        f.write_str(&self.key)
+    }
+}
+
+/// The chords bound to every action, an action can have several or none
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBindings {
+    bindings: BTreeMap<Action, Vec<Chord>>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let bindings = Action::ALL
+            .into_iter()
+            .map(|action| {
+                let chords = action
+                    .default_chords()
+                    .iter()
+                    .map(|spec| Chord::parse(spec).unwrap())
+                    .collect();
+                (action, chords)
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl KeyBindings {
+    /// Defaults overridden by the actions in the file, a missing file gives the defaults
+    pub fn load(path: &str) -> Self {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        Self::from_json(&content).unwrap_or_else(|e| {
+            println!("Failed to parse {}: {}", path, e);
+            Self::default()
+        })
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+        let Value::Object(entries) = value else {
+            return Err("Expected an object of action names to bindings".to_string());
+        };
+        let mut bindings = Self::default();
+        for (name, specs) in entries {
+            let action = Action::parse(&name)?;
+            let specs = match specs {
+                Value::String(spec) => vec![Value::String(spec)],
+                Value::Array(specs) => specs,
+                _ => return Err(format!("Bindings of '{}' must be a list of strings", name)),
+            };
+            let chords = specs
+                .iter()
+                .map(|spec| match spec {
+                    Value::String(spec) => Chord::parse(spec),
+                    _ => Err(format!("Bindings of '{}' must be a list of strings", name)),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            bindings.bindings.insert(action, chords);
+        }
+        Ok(bindings)
+    }
+
+    pub fn to_json(&self) -> String {
+        let entries: serde_json::Map<String, Value> = self
+            .bindings
+            .iter()
+            .map(|(action, chords)| {
+                let specs = chords
+                    .iter()
+                    .map(|chord| Value::String(chord.to_string()))
+                    .collect();
+                (action.name().to_string(), Value::Array(specs))
+            })
+            .collect();
+        serde_json::to_string_pretty(&Value::Object(entries)).unwrap()
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_json()).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+
+    pub fn chords(&self, action: Action) -> &[Chord] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Replace the chords of the action, no chords leave it unbound
+    pub fn bind(&mut self, action: Action, chords: Vec<Chord>) {
+        self.bindings.insert(action, chords);
+    }
+
+    /// Every action with its chords, in the order of `Action::ALL`
+    pub fn iter(&self) -> impl Iterator<Item = (Action, &[Chord])> {
+        self.bindings
+            .iter()
+            .map(|(action, chords)| (*action, chords.as_slice()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_actions_are_found_by_name() {
+        for action in Action::ALL {
+            assert_eq!(Action::from_name(action.name()), Some(action));
+        }
+        assert!(Action::parse("fly").is_err());
+    }
+
+    #[test]
+    fn test_parse_chord() {
+        let chord = Chord::parse("Ctrl+Shift+P").unwrap();
+
+        assert_eq!(chord.key, "p");
+        assert!(chord.ctrl && chord.shift && !chord.alt);
+        assert_eq!(chord.to_string(), "ctrl+shift+p");
+        assert_eq!(
+            Chord::parse("mouse_left").unwrap().mouse_button(),
+            Some("left")
+        );
+        assert!(Chord::parse("").is_err());
+        assert!(Chord::parse("hyper+p").is_err());
+    }
+
+    #[test]
+    fn test_every_action_has_a_default_binding() {
+        let bindings = KeyBindings::default();
+        for action in Action::ALL {
+            assert!(!bindings.chords(action).is_empty(), "{}", action);
+        }
+    }
+
+    #[test]
+    fn test_file_overrides_only_the_actions_it_names() {
+        let bindings = KeyBindings::from_json(
+            r#"{ "toggle_console": ["f1", "ctrl+graveaccent"], "paint": [] }"#,
+        )
+        .unwrap();
+
+        let console: Vec<String> = bindings
+            .chords(Action::ToggleConsole)
+            .iter()
+            .map(Chord::to_string)
+            .collect();
+        assert_eq!(console, ["f1", "ctrl+graveaccent"]);
+        assert!(bindings.chords(Action::Paint).is_empty());
+        assert_eq!(
+            bindings.chords(Action::SaveMap),
+            KeyBindings::default().chords(Action::SaveMap)
+        );
+        assert!(KeyBindings::from_json(r#"{ "fly": ["f"] }"#).is_err());
+    }
+
+    #[test]
+    fn test_bindings_survive_a_round_trip() {
+        let mut bindings = KeyBindings::default();
+        bindings.bind(Action::EndTurn, vec![Chord::parse("shift+space").unwrap()]);
+
+        assert_eq!(
+            KeyBindings::from_json(&bindings.to_json()).unwrap(),
+            bindings
+        );
+    }
+}
@@ -0,0 +1,212 @@
+//! Opponents scripted in Lua through the `on_ai_tick` hook of the mods
+
+use crate::lifecycle::{Hook, LifecycleHooks};
+use logic::{CoreApi, FactionKind};
+use mlua::{Function, Lua, Table};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Commands every AI faction may issue per tick
+pub const AI_COMMAND_BUDGET: u32 = 8;
+
+// The faction whose hook is running with the commands it has left
+struct Turn {
+    faction: String,
+    remaining: u32,
+}
+
+/// Calls the `on_ai_tick` hook for every AI faction and keeps it to its command budget. The hook
+/// gets a table of the faction with the commands it may issue, they only act on what the faction
+/// owns and return false once the budget is used up. AI scripts read the world through `api`
+/// but shouldn't change it there, that would bypass the budget:
+///
+/// ```lua
+/// mods.register("rival_ai", { on_ai_tick = function(faction)
+///     for _, entity in ipairs(api.faction.owned(faction.id)) do
+///         local id = tonumber(entity:match("^person:(%d+)$"))
+///         if id and not faction.move(id, math.random(-5, 5), math.random(-5, 5)) then break end
+///     end
+/// end })
+/// ```
+#[derive(Clone)]
+pub struct AiDirector {
+    turn: Arc<Mutex<Option<Turn>>>,
+    core: Arc<RwLock<CoreApi>>,
+    // Shared by the faction tables passed to the hook
+    commands: Table,
+}
+
+impl AiDirector {
+    pub(crate) fn install(lua: &Lua, core: Arc<RwLock<CoreApi>>) -> Self {
+        let turn: Arc<Mutex<Option<Turn>>> = Default::default();
+        let commands = lua.create_table().unwrap();
+        let director = Self {
+            turn,
+            core,
+            commands: commands.clone(),
+        };
+
+        // faction.move(person_id, x, y) moves a person of the faction
+        let move_person = director.command(lua, |core, faction, (id, x, y): (u32, i32, i32)| {
+            let person = format!("person:{}", id);
+            check_owner(core, &person, Some(faction))?;
+            core.person().move_to(id, x, y).map(|_| ())
+        });
+        commands.set("move", move_person).unwrap();
+
+        // faction.claim(zone) takes over a zone nobody owns
+        let claim = director.command(lua, |core, faction, zone: String| {
+            if !zone.starts_with("zone:") {
+                return Err(format!("{} is no zone", zone));
+            }
+            check_owner(core, &zone, None)?;
+            core.faction().transfer(&zone, faction).map(|_| ())
+        });
+        commands.set("claim", claim).unwrap();
+
+        // faction.release(entity) gives up an entity of the faction
+        let release = director.command(lua, |core, faction, entity: String| {
+            check_owner(core, &entity, Some(faction))?;
+            core.faction().release(&entity).map(|_| ())
+        });
+        commands.set("release", release).unwrap();
+
+        // faction.remaining() tells how many commands are left this tick
+        let turn = director.turn.clone();
+        let remaining = lua
+            .create_function(move |_, ()| {
+                Ok(turn
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .map_or(0, |turn| turn.remaining))
+            })
+            .unwrap();
+        commands.set("remaining", remaining).unwrap();
+
+        director
+    }
+
+    // A command of the faction whose turn it is, returning false once the budget is used up
+    fn command<A: mlua::FromLuaMulti>(
+        &self,
+        lua: &Lua,
+        action: impl Fn(&CoreApi, &str, A) -> Result<(), String> + Send + 'static,
+    ) -> Function {
+        let turn = self.turn.clone();
+        let core = self.core.clone();
+        lua.create_function(move |_, args: A| {
+            let faction = {
+                let mut turn = turn.lock().unwrap();
+                let Some(turn) = turn.as_mut() else {
+                    return Err(mlua::Error::RuntimeError(
+                        "AI commands can only be issued from on_ai_tick".to_string(),
+                    ));
+                };
+                if turn.remaining == 0 {
+                    return Ok(false);
+                }
+                turn.remaining -= 1;
+                turn.faction.clone()
+            };
+            action(&core.read().unwrap(), &faction, args).map_err(mlua::Error::RuntimeError)?;
+            Ok(true)
+        })
+        .unwrap()
+    }
+
+    /// Call `on_ai_tick` of the mods for every AI faction in the order they were founded
+    pub(crate) fn update(&self, lua: &Lua, hooks: &LifecycleHooks) {
+        if !hooks.has(Hook::AiTick) {
+            return;
+        }
+        let factions = self.core.read().unwrap().faction().all();
+        for faction in factions
+            .into_iter()
+            .filter(|faction| faction.kind == FactionKind::Ai)
+        {
+            let Ok(table) = self.faction_table(lua, &faction.id, &faction.name) else {
+                continue;
+            };
+            *self.turn.lock().unwrap() = Some(Turn {
+                faction: faction.id,
+                remaining: AI_COMMAND_BUDGET,
+            });
+            hooks.call(Hook::AiTick, table);
+            *self.turn.lock().unwrap() = None;
+        }
+    }
+
+    fn faction_table(&self, lua: &Lua, id: &str, name: &str) -> mlua::Result<Table> {
+        let table = lua.create_table()?;
+        table.set("id", id)?;
+        table.set("name", name)?;
+        let metatable = lua.create_table()?;
+        metatable.set("__index", self.commands.clone())?;
+        table.set_metatable(Some(metatable));
+        Ok(table)
+    }
+}
+
+// Fail unless the entity is owned by the faction, or by nobody for None
+fn check_owner(core: &CoreApi, entity: &str, faction: Option<&str>) -> Result<(), String> {
+    let owner = core.faction().owner(entity)?;
+    if owner.as_deref() == faction {
+        return Ok(());
+    }
+    Err(match faction {
+        Some(faction) => format!("{} doesn't belong to faction {}", entity, faction),
+        None => format!("{} belongs to faction {} already", entity, owner.unwrap()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lua_engine::LuaEngine;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_ai_factions_act_within_their_budget() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r##"
+                api.faction.create("rival", "Rival Corp", "ai", "#ff0000")
+                api.faction.create("pirates", "Pirates", "ai", "#000000")
+                ann = api.person.create("Ann", 0, 0)
+                api.faction.transfer("person:" .. ann.id, "rival")
+                zone = api.faction.create_zone(0, 0, 3, 3)
+                moves, turns = 0, {}
+                mods.register("ai", { on_ai_tick = function(faction)
+                    table.insert(turns, faction.id)
+                    last = faction
+                    if faction.id == "pirates" then
+                        faction.move(ann.id, 5, 5)
+                    end
+                    while faction.move(ann.id, moves, 0) do
+                        moves = moves + 1
+                    end
+                    faction.claim(zone)
+                end })
+                "##,
+            )
+            .unwrap();
+        engine.tick(0.1, 1);
+
+        let lua = &engine.lua;
+        let turns: Vec<String> = lua.load("turns").eval().unwrap();
+        assert_eq!(turns, vec!["rival", "pirates"]);
+        // The rival spent its whole budget moving Ann, so it had none left for the zone
+        let moves: u32 = lua.load("moves").eval().unwrap();
+        assert_eq!(moves, super::AI_COMMAND_BUDGET);
+        let owner: Option<String> = lua.load("api.faction.owner(zone)").eval().unwrap();
+        assert_eq!(owner, None);
+        // Ann isn't the pirates' to move
+        let errors = engine.error_log.errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].context, "on_ai_tick of mod 'ai'");
+        assert!(errors[0].error.message.contains("person:0"));
+        // Commands are only accepted while the hook runs
+        assert!(lua.load("last.claim(zone)").exec().is_err());
+    }
+}
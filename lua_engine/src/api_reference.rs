@@ -0,0 +1,216 @@
+//! The scripting API as the docs browsers of the frontends show it
+
+use crate::commands::fuzzy_score;
+use mlua::{Lua, Table, Value};
+
+/// A parameter of a method as its signature declares it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamPage {
+    pub name: String,
+    pub type_name: String,
+}
+
+/// Everything the docs tell about a method
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodPage {
+    pub module: String,
+    pub name: String,
+    pub description: String,
+    pub params: Vec<ParamPage>,
+    pub returns: String,
+    /// A call with a placeholder for every parameter, None for methods the scripts can't reach
+    /// as `api.<module>.<name>`
+    pub example: Option<String>,
+}
+
+impl MethodPage {
+    /// Like "person.create(name: String, x: i32, y: i32) -> Result<Person, String>"
+    pub fn signature(&self) -> String {
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .map(|param| format!("{}: {}", param.name, param.type_name))
+            .collect();
+        let mut signature = format!("{}.{}({})", self.module, self.name, params.join(", "));
+        if !self.returns.is_empty() {
+            signature.push_str(&format!(" -> {}", self.returns));
+        }
+        signature
+    }
+}
+
+/// A documented module with its methods ordered by name
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModulePage {
+    pub name: String,
+    pub methods: Vec<MethodPage>,
+}
+
+/// Every documented module of the API, ordered by name. Read from the `docs` table `help()`
+/// reads, so the read models are in it along with the modules of the logic crate.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApiReference {
+    pub modules: Vec<ModulePage>,
+}
+
+impl ApiReference {
+    /// Read the docs as they are now, read models and plugins may add modules later on
+    pub fn read(lua: &Lua) -> mlua::Result<Self> {
+        let docs: Table = lua.globals().get("docs")?;
+        let api: Option<Table> = lua.globals().get("api")?;
+        let mut modules = Vec::new();
+        for pair in docs.pairs::<String, Table>() {
+            let (module, methods) = pair?;
+            let functions: Option<Table> = match &api {
+                Some(api) => api.get(module.as_str())?,
+                None => None,
+            };
+            let mut pages = Vec::new();
+            for pair in methods.pairs::<String, Table>() {
+                let (name, doc) = pair?;
+                let params: Table = doc.get("params")?;
+                let params = params
+                    .sequence_values::<Table>()
+                    .map(|param| {
+                        let param = param?;
+                        Ok(ParamPage {
+                            name: param.get("name")?,
+                            type_name: param.get("type")?,
+                        })
+                    })
+                    .collect::<mlua::Result<Vec<_>>>()?;
+                let reachable = match &functions {
+                    Some(functions) => !matches!(functions.get(name.as_str())?, Value::Nil),
+                    None => false,
+                };
+                pages.push(MethodPage {
+                    example: reachable.then(|| example_call(&module, &name, &params)),
+                    module: module.clone(),
+                    description: doc
+                        .get::<Option<String>>("description")?
+                        .unwrap_or_default(),
+                    // The extracted signatures end with the brace opening the body
+                    returns: doc
+                        .get::<Option<String>>("returns")?
+                        .unwrap_or_default()
+                        .trim_end_matches('{')
+                        .trim()
+                        .to_string(),
+                    name,
+                    params,
+                });
+            }
+            pages.sort_by(|a, b| a.name.cmp(&b.name));
+            modules.push(ModulePage {
+                name: module,
+                methods: pages,
+            });
+        }
+        modules.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(Self { modules })
+    }
+
+    /// The methods whose "module.name" matches the query fuzzily, best first, then the ones
+    /// whose description contains it
+    pub fn search(&self, query: &str) -> Vec<&MethodPage> {
+        let lowercase = query.to_lowercase();
+        let mut found: Vec<(i32, &MethodPage)> = self
+            .modules
+            .iter()
+            .flat_map(|module| &module.methods)
+            .filter_map(|method| {
+                let path = format!("{}.{}", method.module, method.name);
+                fuzzy_score(query, &path)
+                    .map(|score| (score, method))
+                    .or_else(|| {
+                        method
+                            .description
+                            .to_lowercase()
+                            .contains(&lowercase)
+                            .then_some((i32::MIN, method))
+                    })
+            })
+            .collect();
+        found.sort_by_key(|(score, _)| -(*score as i64));
+        found.into_iter().map(|(_, method)| method).collect()
+    }
+}
+
+// A call of the method with placeholders the user replaces, quoted names for strings
+fn example_call(module: &str, name: &str, params: &[ParamPage]) -> String {
+    let args: Vec<String> = params
+        .iter()
+        .map(|param| {
+            let type_name = param.type_name.as_str();
+            if type_name.starts_with("Option<") {
+                "nil".to_string()
+            } else if type_name.contains("str") || type_name.contains("String") {
+                format!("{:?}", param.name)
+            } else if type_name == "bool" {
+                "false".to_string()
+            } else if type_name.starts_with('f') {
+                "0.0".to_string()
+            } else if type_name.starts_with('i') || type_name.starts_with('u') {
+                "0".to_string()
+            } else {
+                param.name.clone()
+            }
+        })
+        .collect();
+    format!("api.{}.{}({})", module, name, args.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lua_engine::LuaEngine;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_methods_are_read_from_the_docs_with_examples() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        let reference = ApiReference::read(&engine.lua).unwrap();
+
+        let names: Vec<&str> = reference
+            .modules
+            .iter()
+            .map(|module| module.name.as_str())
+            .collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names, sorted);
+        let create = reference
+            .modules
+            .iter()
+            .find(|module| module.name == "person")
+            .and_then(|module| module.methods.iter().find(|method| method.name == "create"))
+            .unwrap();
+        assert_eq!(
+            create.signature(),
+            "person.create(name: String, x: i32, y: i32) -> Result<Person, String>"
+        );
+        assert_eq!(
+            create.example.as_deref(),
+            Some(r#"api.person.create("name", 0, 0)"#)
+        );
+        assert!(engine
+            .run_script(create.example.as_deref().unwrap())
+            .is_ok());
+        // The builder of the logic is documented but not part of the scripting API
+        assert!(reference
+            .modules
+            .iter()
+            .all(|module| module.name != "core_builder"));
+
+        let found = reference.search("person.crea");
+        assert_eq!(
+            (found[0].module.as_str(), found[0].name.as_str()),
+            ("person", "create")
+        );
+        assert!(reference
+            .search("single event")
+            .iter()
+            .any(|method| method.name == "move_all"));
+    }
+}
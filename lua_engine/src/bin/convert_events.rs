@@ -0,0 +1,68 @@
+//! Rewrites an event log with another codec, to read a binary log or shrink a JSON one
+
+use logic::{convert, Codec};
+use std::env;
+use std::path::Path;
+use std::process::ExitCode;
+
+const USAGE: &str =
+    "Usage: convert_events <input> <output> [--from json|binary] [--to json|binary]";
+
+// The codecs follow the file extensions, `.jsonl` for JSON and `.bin` for binary, unless given
+// with `--from` and `--to`
+fn main() -> ExitCode {
+    let mut paths = Vec::new();
+    let mut from = None;
+    let mut to = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let parsed = match arg.as_str() {
+            "--from" => args
+                .next()
+                .and_then(|name| Codec::from_name(&name))
+                .map(|codec| from = Some(codec)),
+            "--to" => args
+                .next()
+                .and_then(|name| Codec::from_name(&name))
+                .map(|codec| to = Some(codec)),
+            _ if paths.len() < 2 && !arg.starts_with("--") => {
+                paths.push(arg.clone());
+                Some(())
+            }
+            _ => None,
+        };
+        if parsed.is_none() {
+            eprintln!("Invalid argument '{}'\n{}", arg, USAGE);
+            return ExitCode::from(2);
+        }
+    }
+    let [input, output] = paths.as_slice() else {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    };
+    let (input, output) = (Path::new(input), Path::new(output));
+    let (Some(from), Some(to)) = (
+        from.or_else(|| Codec::from_path(input)),
+        to.or_else(|| Codec::from_path(output)),
+    ) else {
+        eprintln!("Can't tell the codecs from the file extensions\n{}", USAGE);
+        return ExitCode::from(2);
+    };
+
+    match convert(input, from, output, to) {
+        Ok(converted) => {
+            println!(
+                "Converted {} events from {} to {}",
+                converted,
+                from.name(),
+                to.name()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Failed to convert {}: {}", input.display(), e);
+            ExitCode::FAILURE
+        }
+    }
+}
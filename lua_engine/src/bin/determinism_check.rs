@@ -0,0 +1,90 @@
+//! Runs a scenario script twice with the same seed and reports where the runs differ
+
+use lua_engine::determinism::check_determinism;
+use lua_engine::script_args;
+use std::process::ExitCode;
+use std::{env, fs};
+
+const USAGE: &str =
+    "Usage: determinism_check <scenario.lua> [--seed N] [--frames N] [--script-arg key=value ...]";
+
+fn main() -> ExitCode {
+    let mut path = None;
+    let mut seed = 0;
+    let mut frames = 600;
+    let mut script_args = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let parsed = match arg.as_str() {
+            "--seed" => args
+                .next()
+                .and_then(|value| value.parse().ok())
+                .map(|value| seed = value),
+            "--frames" => args
+                .next()
+                .and_then(|value| value.parse().ok())
+                .map(|value| frames = value),
+            "--script-arg" => args
+                .next()
+                .and_then(|value| script_args::parse_pair(&value).ok())
+                .map(|pair| script_args.push(pair)),
+            _ if path.is_none() && !arg.starts_with("--") => {
+                path = Some(arg.clone());
+                Some(())
+            }
+            _ => None,
+        };
+        if parsed.is_none() {
+            eprintln!("Invalid argument '{}'\n{}", arg, USAGE);
+            return ExitCode::from(2);
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    };
+
+    let script = match fs::read_to_string(&path) {
+        Ok(script) => script,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", path, e);
+            return ExitCode::from(2);
+        }
+    };
+    // Same search path as the game, so scenarios can require the mod scripts
+    let script = format!(
+        "package.path = \"./scripts/?.lua;\" .. package.path\n{}",
+        script
+    );
+
+    match check_determinism(&script, &script_args, seed, frames) {
+        Ok(report) => {
+            println!(
+                "Ran {} twice with seed {} for {} frames: {} and {} events",
+                path, seed, frames, report.first_events, report.second_events
+            );
+            if !report.differing_state.is_empty() {
+                println!(
+                    "The runs ended with different {}",
+                    report.differing_state.join(", ")
+                );
+            }
+            match report.divergence {
+                Some(divergence) => {
+                    println!("{}", divergence);
+                    ExitCode::FAILURE
+                }
+                None if report.differing_state.is_empty() => {
+                    println!("Both runs produced the same events and world state");
+                    ExitCode::SUCCESS
+                }
+                None => ExitCode::FAILURE,
+            }
+        }
+        Err(e) => {
+            eprintln!("Scenario failed: {}\n{}", e, e.traceback);
+            ExitCode::FAILURE
+        }
+    }
+}
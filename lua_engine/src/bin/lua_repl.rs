@@ -0,0 +1,145 @@
+//! Terminal REPL into a Lua engine of its own, or into a game started with `--socket`
+
+#[cfg(unix)]
+use lua_engine::command_socket;
+use lua_engine::lua_client::LuaClient;
+use lua_engine::lua_engine::{LuaCommand, LuaEngine};
+use mlua::{Error, Lua};
+use std::io::{self, BufRead, BufReader, Write};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::sync::mpsc;
+use std::{env, fs, thread};
+
+// Where the chunks run, None from `run` once it's gone
+enum Target {
+    Embedded(LuaClient),
+    #[cfg(unix)]
+    Game(BufReader<UnixStream>),
+}
+
+impl Target {
+    fn run(&mut self, chunk: &str) -> Option<String> {
+        match self {
+            Target::Embedded(client) => match client.execute_non_blocking(chunk).recv() {
+                Ok(Ok(result)) => Some(result),
+                Ok(Err(e)) => Some(format!("Error: {}\n{}", e, e.traceback)),
+                Err(_) => None,
+            },
+            #[cfg(unix)]
+            Target::Game(stream) => {
+                writeln!(stream.get_mut(), "{}", command_socket::escape(chunk)).ok()?;
+                let mut response = String::new();
+                stream
+                    .read_line(&mut response)
+                    .ok()
+                    .filter(|&read| read > 0)?;
+                let response = command_socket::unescape(response.trim_end());
+                Some(match response.strip_prefix("error ") {
+                    Some(error) => format!("Error: {}", error),
+                    None => response
+                        .strip_prefix("ok ")
+                        .unwrap_or(&response)
+                        .to_string(),
+                })
+            }
+        }
+    }
+}
+
+// lua_repl [script.lua ...] or lua_repl --connect <socket>
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    #[cfg(unix)]
+    if let [flag, path] = args.as_slice()
+        && flag == "--connect"
+    {
+        match UnixStream::connect(path) {
+            Ok(stream) => repl(Target::Game(BufReader::new(stream))),
+            Err(e) => eprintln!("Failed to connect to {}: {}", path, e),
+        }
+        return;
+    }
+
+    let (command_tx, command_rx) = mpsc::channel();
+    let mut engine = LuaEngine::new(command_rx);
+    if let Err(e) = engine.run_script(r#"package.path = "./scripts/?.lua;" .. package.path"#) {
+        eprintln!("Failed to set the script path: {}", e);
+    }
+    #[cfg(feature = "plugins")]
+    for error in engine.load_plugins("plugins") {
+        eprintln!("{}", error);
+    }
+    for path in &args {
+        let result = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|script| engine.run_script(&script).map_err(|e| e.to_string()));
+        match result {
+            Ok(()) => println!("Loaded {}", path),
+            Err(e) => eprintln!("Failed to load {}: {}", path, e),
+        }
+    }
+    thread::spawn(move || engine.run());
+
+    repl(Target::Embedded(LuaClient::new(command_tx.clone())));
+    let _ = command_tx.send(LuaCommand::Shutdown);
+}
+
+// Multi-line chunks like `for ... do` keep reading lines until they are complete, an empty line
+// gives up on them. `.exit` or Ctrl+D quits.
+fn repl(mut target: Target) {
+    // Only used to tell whether a chunk is complete, never runs anything
+    let syntax_check = Lua::new();
+    let mut chunk = String::new();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        print!("{}", if chunk.is_empty() { "> " } else { ">> " });
+        io::stdout().flush().unwrap();
+        let Some(Ok(line)) = lines.next() else {
+            break;
+        };
+
+        if chunk.is_empty() && line.trim() == ".exit" {
+            break;
+        }
+        if !chunk.is_empty() && line.trim().is_empty() {
+            println!("Discarded the incomplete chunk");
+            chunk.clear();
+            continue;
+        }
+        if !chunk.is_empty() {
+            chunk.push('\n');
+        }
+        chunk.push_str(&line);
+        if is_incomplete(&syntax_check, &chunk) {
+            continue;
+        }
+
+        match target.run(&chunk) {
+            Some(result) => println!("{}", result),
+            None => {
+                eprintln!("The Lua engine stopped");
+                break;
+            }
+        }
+        chunk.clear();
+    }
+}
+
+// The engine runs chunks as `return <chunk>` when that compiles, as they are otherwise.
+// Incomplete when neither compiles and one of them only fails because the input ended.
+fn is_incomplete(lua: &Lua, chunk: &str) -> bool {
+    let check = |code: &str| match lua.load(code).into_function() {
+        Ok(_) => None,
+        Err(Error::SyntaxError {
+            incomplete_input, ..
+        }) => Some(incomplete_input),
+        Err(_) => Some(false),
+    };
+    match (check(chunk), check(&format!("return {}", chunk))) {
+        (Some(statement), Some(expression)) => statement || expression,
+        _ => false,
+    }
+}
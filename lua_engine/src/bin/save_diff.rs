@@ -0,0 +1,56 @@
+//! Compares two saves or event logs and lists what differs
+
+use lua_engine::save_diff::diff_files;
+use std::env;
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage: save_diff <left> <right> [--limit N]";
+
+fn main() -> ExitCode {
+    let mut paths = Vec::new();
+    let mut limit = 50;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let parsed = match arg.as_str() {
+            "--limit" => args
+                .next()
+                .and_then(|value| value.parse().ok())
+                .map(|value| limit = value),
+            _ if paths.len() < 2 && !arg.starts_with("--") => {
+                paths.push(arg.clone());
+                Some(())
+            }
+            _ => None,
+        };
+        if parsed.is_none() {
+            eprintln!("Invalid argument '{}'\n{}", arg, USAGE);
+            return ExitCode::from(2);
+        }
+    }
+    let [left, right] = paths.as_slice() else {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    };
+
+    match diff_files(left, right) {
+        Ok(differences) if differences.is_empty() => {
+            println!("{} and {} are the same", left, right);
+            ExitCode::SUCCESS
+        }
+        Ok(differences) => {
+            for difference in differences.iter().take(limit) {
+                println!("{}", difference);
+            }
+            if differences.len() > limit {
+                println!("... and {} more", differences.len() - limit);
+            }
+            println!("{} differences", differences.len());
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Runs a scenario script without a frontend until it ends and prints the result
+
+use lua_engine::scenario::run_headless;
+use lua_engine::script_args;
+use std::process::ExitCode;
+use std::{env, fs};
+
+const USAGE: &str = "Usage: scenario_runner <scenario.lua> [--seed N] [--frames N] [--results path] [--import persons.csv ...] [--script-arg key=value ...]";
+
+// Fails when the scenario was lost. Files given with `--import` seed the world with their persons
+// before the scenario runs, see `api.import`.
+fn main() -> ExitCode {
+    let mut path = None;
+    let mut seed = 0;
+    let mut frames = 36000;
+    let mut results = None;
+    let mut imports = Vec::new();
+    let mut script_args = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let parsed = match arg.as_str() {
+            "--seed" => args
+                .next()
+                .and_then(|value| value.parse().ok())
+                .map(|value| seed = value),
+            "--frames" => args
+                .next()
+                .and_then(|value| value.parse().ok())
+                .map(|value| frames = value),
+            "--results" => args.next().map(|value| results = Some(value)),
+            "--import" => args.next().map(|value| imports.push(value)),
+            "--script-arg" => args
+                .next()
+                .and_then(|value| script_args::parse_pair(&value).ok())
+                .map(|pair| script_args.push(pair)),
+            _ if path.is_none() && !arg.starts_with("--") => {
+                path = Some(arg.clone());
+                Some(())
+            }
+            _ => None,
+        };
+        if parsed.is_none() {
+            eprintln!("Invalid argument '{}'\n{}", arg, USAGE);
+            return ExitCode::from(2);
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    };
+
+    let script = match fs::read_to_string(&path) {
+        Ok(script) => script,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", path, e);
+            return ExitCode::from(2);
+        }
+    };
+    // The imported persons are there before the scenario runs
+    let imports: String = imports
+        .iter()
+        .map(|path| format!("api.import.persons({:?})\n", path))
+        .collect();
+    // Same search path as the game, so scenarios can require the mod scripts
+    let script = format!(
+        "package.path = \"./scripts/?.lua;\" .. package.path\n{}{}",
+        imports, script
+    );
+
+    let result = match run_headless(&script, &script_args, seed, frames) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Scenario failed: {}\n{}", e, e.traceback);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!(
+        "{} {} after {} ticks ({:.1}s): {}",
+        path, result.outcome, result.ticks, result.duration, result.reason
+    );
+    println!(
+        "{} events, {} persons on {} locations",
+        result.events, result.persons, result.occupied_locations
+    );
+    for (metric, value) in &result.metrics {
+        println!("  {} = {}", metric, value);
+    }
+
+    if let Some(results) = results {
+        let content = serde_json::to_string_pretty(&result).unwrap();
+        if let Err(e) = fs::write(&results, content) {
+            eprintln!("Failed to write {}: {}", results, e);
+            return ExitCode::FAILURE;
+        }
+        println!("Wrote {}", results);
+    }
+    if result.outcome == "lost" {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,316 @@
+//! The simulation clock, `api.time`, advancing in real time or in turns
+
+use crate::error_log::ErrorLog;
+use crate::lua_engine::API_VERSION;
+use crate::params::{Params, DAY_LENGTH, SPEED};
+use mlua::{Function, Lua, Table};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+/// Simulated seconds a turn lasts
+pub const TURN_DURATION: f64 = 1.0;
+
+/// Simulated seconds a day lasts unless a script sets it
+pub const DEFAULT_DAY_LENGTH: f64 = 240.0;
+
+/// How the simulation clock advances
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeMode {
+    /// On every tick by the time since the previous one
+    RealTime,
+    /// Only when a turn is ended
+    Turns,
+}
+
+impl fmt::Display for TimeMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeMode::RealTime => write!(f, "realtime"),
+            TimeMode::Turns => write!(f, "turns"),
+        }
+    }
+}
+
+impl FromStr for TimeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "realtime" => Ok(TimeMode::RealTime),
+            "turns" => Ok(TimeMode::Turns),
+            _ => Err(format!(
+                "'{}' is no time mode, expected realtime or turns",
+                s
+            )),
+        }
+    }
+}
+
+struct QueuedCommand {
+    order: i64,
+    // Keeps commands of the same order in the order they were queued
+    sequence: u64,
+    command: Function,
+}
+
+struct ClockState {
+    mode: TimeMode,
+    turn: u64,
+    turn_ended: bool,
+    queue: Vec<QueuedCommand>,
+    next_sequence: u64,
+    // Simulated seconds so far
+    elapsed: f64,
+}
+
+/// Mode and turn of the simulation clock with the commands queued for its next step. Queued
+/// commands run at the start of the next step, ordered by their order, 0 when left out, then by
+/// when they were queued:
+///
+/// ```lua
+/// api.time.set_mode("turns")
+/// api.time.queue(function() api.person.move_to(1, 4, 2) end, 10)
+/// api.time.end_turn()
+/// ```
+///
+/// Days last `api.time.set_day_length(seconds)` and start at midnight, `api.time.hour()` and
+/// `api.time.day()` tell where in them the world is. In real time the `speed` parameter scales
+/// the simulated seconds per tick.
+#[derive(Clone)]
+pub struct Clock {
+    state: Arc<Mutex<ClockState>>,
+    params: Params,
+    error_log: ErrorLog,
+}
+
+impl Clock {
+    pub(crate) fn install(lua: &Lua, error_log: ErrorLog, params: Params) -> Self {
+        let clock = Self {
+            state: Arc::new(Mutex::new(ClockState {
+                mode: TimeMode::RealTime,
+                turn: 0,
+                turn_ended: false,
+                queue: Vec::new(),
+                next_sequence: 0,
+                elapsed: 0.0,
+            })),
+            params,
+            error_log,
+        };
+
+        let table = lua.create_table().unwrap();
+        {
+            let clock = clock.clone();
+            lua.create_function(move |_, ()| Ok(clock.mode().to_string()))
+                .and_then(|f| table.set("mode", f))
+                .unwrap();
+        }
+        {
+            let clock = clock.clone();
+            lua.create_function(move |_, mode: String| {
+                let mode = mode.parse().map_err(mlua::Error::RuntimeError)?;
+                clock.set_mode(mode);
+                Ok(())
+            })
+            .and_then(|f| table.set("set_mode", f))
+            .unwrap();
+        }
+        {
+            let clock = clock.clone();
+            lua.create_function(move |_, ()| Ok(clock.turn()))
+                .and_then(|f| table.set("turn", f))
+                .unwrap();
+        }
+        {
+            let clock = clock.clone();
+            lua.create_function(move |_, ()| Ok(clock.end_turn()))
+                .and_then(|f| table.set("end_turn", f))
+                .unwrap();
+        }
+        {
+            let clock = clock.clone();
+            lua.create_function(move |_, (command, order): (Function, Option<i64>)| {
+                clock.queue(command, order.unwrap_or(0));
+                Ok(())
+            })
+            .and_then(|f| table.set("queue", f))
+            .unwrap();
+        }
+        {
+            let clock = clock.clone();
+            lua.create_function(move |_, ()| Ok(clock.elapsed()))
+                .and_then(|f| table.set("now", f))
+                .unwrap();
+        }
+        {
+            let clock = clock.clone();
+            lua.create_function(move |_, ()| Ok(clock.hour()))
+                .and_then(|f| table.set("hour", f))
+                .unwrap();
+        }
+        {
+            let clock = clock.clone();
+            lua.create_function(move |_, ()| Ok(clock.day()))
+                .and_then(|f| table.set("day", f))
+                .unwrap();
+        }
+        {
+            let clock = clock.clone();
+            lua.create_function(move |_, seconds: f64| {
+                if !seconds.is_finite() || seconds <= 0.0 {
+                    return Err(mlua::Error::RuntimeError(format!(
+                        "A day can't last {} seconds",
+                        seconds
+                    )));
+                }
+                clock
+                    .params
+                    .set(DAY_LENGTH, seconds)
+                    .map_err(mlua::Error::RuntimeError)?;
+                Ok(())
+            })
+            .and_then(|f| table.set("set_day_length", f))
+            .unwrap();
+        }
+        let api: Table = lua.globals().get("api").unwrap();
+        api.get::<Table>(format!("v{}", API_VERSION))
+            .and_then(|latest| latest.set("time", table.clone()))
+            .and_then(|_| api.set("time", table))
+            .unwrap();
+
+        clock
+    }
+
+    pub fn mode(&self) -> TimeMode {
+        self.state.lock().unwrap().mode
+    }
+
+    /// Switch the mode, a turn ended before switching to real time is dropped
+    pub fn set_mode(&self, mode: TimeMode) {
+        let mut state = self.state.lock().unwrap();
+        state.mode = mode;
+        state.turn_ended = false;
+    }
+
+    /// Turns ended so far
+    pub fn turn(&self) -> u64 {
+        self.state.lock().unwrap().turn
+    }
+
+    /// End the turn, the world advances on the next tick. Returns false outside of turn mode
+    /// or when the turn was ended already.
+    pub fn end_turn(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.mode != TimeMode::Turns || state.turn_ended {
+            return false;
+        }
+        state.turn_ended = true;
+        true
+    }
+
+    /// Simulated seconds so far
+    pub fn elapsed(&self) -> f64 {
+        self.state.lock().unwrap().elapsed
+    }
+
+    /// Hour of the simulated day, from 0 up to 24
+    pub fn hour(&self) -> f64 {
+        let day_length = self.day_length();
+        (self.elapsed() % day_length) / day_length * 24.0
+    }
+
+    /// Simulated days passed
+    pub fn day(&self) -> u64 {
+        (self.elapsed() / self.day_length()) as u64
+    }
+
+    fn day_length(&self) -> f64 {
+        self.params.get(DAY_LENGTH).unwrap_or(DEFAULT_DAY_LENGTH)
+    }
+
+    fn queue(&self, command: Function, order: i64) {
+        let mut state = self.state.lock().unwrap();
+        let sequence = state.next_sequence;
+        state.next_sequence += 1;
+        state.queue.push(QueuedCommand {
+            order,
+            sequence,
+            command,
+        });
+    }
+
+    /// Seconds to simulate on a tick `dt` seconds after the previous one, None while the world
+    /// waits for the turn to end
+    pub(crate) fn advance(&self, dt: f64) -> Option<f64> {
+        let speed = self.params.get(SPEED).unwrap_or(1.0);
+        let mut state = self.state.lock().unwrap();
+        let simulated = match state.mode {
+            TimeMode::RealTime => Some(dt * speed),
+            TimeMode::Turns if state.turn_ended => {
+                state.turn_ended = false;
+                state.turn += 1;
+                Some(TURN_DURATION)
+            }
+            TimeMode::Turns => None,
+        };
+        state.elapsed += simulated.unwrap_or(0.0);
+        simulated
+    }
+
+    /// Run the queued commands in their order, errors go to the error log
+    pub(crate) fn resolve(&self) {
+        // Commands run without the lock, they may well queue further ones for the next step
+        let mut commands = std::mem::take(&mut self.state.lock().unwrap().queue);
+        commands.sort_by_key(|queued| (queued.order, queued.sequence));
+        for queued in commands {
+            if let Err(e) = queued.command.call::<()>(()) {
+                self.error_log.report("api.time.queue", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lua_engine::LuaEngine;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_turns_resolve_queued_commands_in_order() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                api.time.set_mode("turns")
+                resolved, frames = {}, 0
+                mods.register("count", { on_frame = function() frames = frames + 1 end })
+                api.time.queue(function() table.insert(resolved, "b") end, 5)
+                api.time.queue(function() table.insert(resolved, "a") end)
+                api.time.queue(function() table.insert(resolved, "c") end, 5)
+                "#,
+            )
+            .unwrap();
+
+        engine.tick(0.1, 1);
+        engine.tick(0.1, 2);
+        let frames: u32 = engine.lua.load("frames").eval().unwrap();
+        assert_eq!(frames, 0);
+
+        let ended: Vec<bool> = engine
+            .lua
+            .load("{ api.time.end_turn(), api.time.end_turn() }")
+            .eval()
+            .unwrap();
+        assert_eq!(ended, vec![true, false]);
+        engine.tick(0.1, 3);
+        engine.tick(0.1, 4);
+        let result: String = engine
+            .lua
+            .load("table.concat(resolved) .. frames .. api.time.turn()")
+            .eval()
+            .unwrap();
+        assert_eq!(result, "abc11");
+    }
+}
@@ -0,0 +1,200 @@
+//! Colors for the scripts, as the `color` global
+
+use mlua::{FromLua, IntoLua, Lua, Result as LuaResult, Table, Value};
+
+/// The named colors of `color.palette`
+pub const PALETTE: &[(&str, Rgba)] = &[
+    ("black", Rgba::rgb(0.0, 0.0, 0.0)),
+    ("white", Rgba::rgb(1.0, 1.0, 1.0)),
+    ("gray", Rgba::rgb(0.5, 0.5, 0.5)),
+    ("red", Rgba::rgb(0.9, 0.2, 0.2)),
+    ("orange", Rgba::rgb(0.95, 0.55, 0.1)),
+    ("yellow", Rgba::rgb(0.95, 0.85, 0.2)),
+    ("green", Rgba::rgb(0.25, 0.75, 0.3)),
+    ("teal", Rgba::rgb(0.15, 0.65, 0.65)),
+    ("blue", Rgba::rgb(0.2, 0.45, 0.9)),
+    ("purple", Rgba::rgb(0.6, 0.3, 0.8)),
+    ("pink", Rgba::rgb(0.95, 0.5, 0.7)),
+    ("brown", Rgba::rgb(0.55, 0.35, 0.2)),
+    ("transparent", Rgba::new(0.0, 0.0, 0.0, 0.0)),
+];
+
+/// A color with channels from 0 to 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rgba {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Rgba {
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self::new(r, g, b, 1.0)
+    }
+
+    /// A color of the palette
+    pub fn named(name: &str) -> Option<Self> {
+        PALETTE
+            .iter()
+            .find(|(palette_name, _)| *palette_name == name)
+            .map(|(_, color)| *color)
+    }
+
+    /// A color like "#ff8800" or, with alpha, "#ff880080"
+    pub fn from_hex(hex: &str) -> Result<Self, String> {
+        let invalid = || format!("'{}' is no color like #ff8800 or #ff880080", hex);
+        let digits = hex.strip_prefix('#').ok_or_else(invalid)?;
+        if !matches!(digits.len(), 6 | 8) || !digits.is_ascii() {
+            return Err(invalid());
+        }
+        let channel = |i: usize| {
+            u8::from_str_radix(&digits[i..i + 2], 16)
+                .map(|value| value as f32 / 255.0)
+                .map_err(|_| invalid())
+        };
+        let a = if digits.len() == 8 { channel(6)? } else { 1.0 };
+        Ok(Self::new(channel(0)?, channel(2)?, channel(4)?, a))
+    }
+
+    /// "#rrggbb", with the alpha as "#rrggbbaa" unless it's opaque
+    pub fn to_hex(self) -> String {
+        let byte = |channel: f32| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let rgb = format!(
+            "#{:02x}{:02x}{:02x}",
+            byte(self.r),
+            byte(self.g),
+            byte(self.b)
+        );
+        if self.a < 1.0 {
+            format!("{}{:02x}", rgb, byte(self.a))
+        } else {
+            rgb
+        }
+    }
+
+    /// The color `t` of the way from this one to `other`, `t` is clamped to 0..1
+    pub fn lerp(self, other: Self, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |from: f32, to: f32| from + (to - from) * t;
+        Self::new(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+            mix(self.a, other.a),
+        )
+    }
+
+    /// A palette name, hex string or color table
+    pub fn parse(value: &str) -> Result<Self, String> {
+        Self::named(value).map_or_else(|| Self::from_hex(value), Ok)
+    }
+}
+
+impl IntoLua for Rgba {
+    fn into_lua(self, lua: &Lua) -> LuaResult<Value> {
+        let table = lua.create_table()?;
+        table.set("r", self.r)?;
+        table.set("g", self.g)?;
+        table.set("b", self.b)?;
+        table.set("a", self.a)?;
+        Ok(Value::Table(table))
+    }
+}
+
+impl FromLua for Rgba {
+    fn from_lua(value: Value, _: &Lua) -> LuaResult<Self> {
+        match value {
+            Value::Table(table) => Ok(Self::new(
+                table.get("r")?,
+                table.get("g")?,
+                table.get("b")?,
+                table.get::<Option<f32>>("a")?.unwrap_or(1.0),
+            )),
+            Value::String(text) => Self::parse(&text.to_str()?).map_err(mlua::Error::RuntimeError),
+            value => Err(mlua::Error::RuntimeError(format!(
+                "Expected a color table, palette name or hex string, got a {}",
+                value.type_name()
+            ))),
+        }
+    }
+}
+
+/// Set the `color` global. Colors are tables `{ r, g, b, a }` with channels from 0 to 1, wherever
+/// a color is taken a name of `color.palette` or a hex string works as well:
+///
+/// ```lua
+/// local mid = color.lerp(color.hex("#3080ff"), color.rgb(1, 0.5, 0), 0.5)
+/// ui.tile.tint(3, 4, color.with_alpha(mid, 0.4))
+/// ```
+pub fn install(lua: &Lua) -> LuaResult<()> {
+    let table = lua.create_table()?;
+    table.set(
+        "rgb",
+        lua.create_function(|_, (r, g, b, a): (f32, f32, f32, Option<f32>)| {
+            Ok(Rgba::new(r, g, b, a.unwrap_or(1.0)))
+        })?,
+    )?;
+    table.set(
+        "hex",
+        lua.create_function(|_, hex: String| {
+            Rgba::from_hex(&hex).map_err(mlua::Error::RuntimeError)
+        })?,
+    )?;
+    table.set(
+        "to_hex",
+        lua.create_function(|_, color: Rgba| Ok(color.to_hex()))?,
+    )?;
+    table.set(
+        "lerp",
+        lua.create_function(|_, (from, to, t): (Rgba, Rgba, f32)| Ok(from.lerp(to, t)))?,
+    )?;
+    table.set(
+        "with_alpha",
+        lua.create_function(|_, (color, a): (Rgba, f32)| Ok(Rgba { a, ..color }))?,
+    )?;
+    let palette: Table = lua.create_table()?;
+    for (name, color) in PALETTE {
+        palette.set(*name, *color)?;
+    }
+    table.set("palette", palette)?;
+    lua.globals().set("color", table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_colors_round_trip() {
+        let color = Rgba::from_hex("#ff8000").unwrap();
+        assert_eq!((color.r, color.b, color.a), (1.0, 0.0, 1.0));
+        assert_eq!(color.to_hex(), "#ff8000");
+        assert_eq!(Rgba::from_hex("#ff800080").unwrap().to_hex(), "#ff800080");
+        assert!(Rgba::from_hex("ff8000").is_err());
+        assert!(Rgba::from_hex("#ff80").is_err());
+        assert_eq!(Rgba::parse("white").unwrap(), Rgba::rgb(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_colors_mix_in_lua() {
+        let lua = Lua::new();
+        install(&lua).unwrap();
+
+        let mixed: String = lua
+            .load(r#"return color.to_hex(color.lerp("black", color.rgb(1, 1, 1, 0), 0.5))"#)
+            .eval()
+            .unwrap();
+        assert_eq!(mixed, "#80808080");
+        let red: f32 = lua.load("return color.palette.red.r").eval().unwrap();
+        assert_eq!(red, 0.9);
+        assert!(lua
+            .load(r#"color.lerp("mauve", "red", 0.5)"#)
+            .exec()
+            .is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use crate::lua_client::LuaClient;
+use crate::lua_engine::LuaCommand;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::mpsc;
+use std::{fs, thread};
+
+/// Local socket that passes commands of external tools (tests, editors, `lua_repl --connect`)
+/// on to the Lua engine of a running game.
+///
+/// The protocol is line based, every request line gets exactly one response line:
+/// - `.run <path>` runs a script file, anything else is run as a Lua chunk like in the console
+/// - the response is `ok <result>` or `error <message>`
+/// - both are `escape`d, so chunks and results spanning several lines fit on one
+pub struct CommandSocket {
+    path: String,
+}
+
+impl CommandSocket {
+    /// Listen at `path`, replacing the socket file left behind by a game that didn't shut down.
+    /// Anything at `path` that isn't a socket is left alone and fails with `AlreadyExists`.
+    pub fn bind(path: &str, command_tx: mpsc::Sender<LuaCommand>) -> io::Result<Self> {
+        if let Ok(metadata) = fs::symlink_metadata(path) {
+            if !metadata.file_type().is_socket() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists and isn't a socket", path),
+                ));
+            }
+            if UnixStream::connect(path).is_err() {
+                fs::remove_file(path)?;
+            }
+        }
+        let listener = UnixListener::bind(path)?;
+        println!("Listening for Lua commands on {}", path);
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let client = LuaClient::new(command_tx.clone());
+                thread::spawn(move || {
+                    if let Err(e) = serve(stream, &client) {
+                        println!("Command socket connection failed: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            path: path.to_string(),
+        })
+    }
+}
+
+impl Drop for CommandSocket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Put text on one line of the protocol, with `\` as `\\` and line breaks as `\n` and `\r`
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Read text back from a line of the protocol, unknown escapes are kept as they are
+pub fn unescape(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => text.push('\\'),
+            Some('n') => text.push('\n'),
+            Some('r') => text.push('\r'),
+            Some(other) => {
+                text.push('\\');
+                text.push(other);
+            }
+            None => text.push('\\'),
+        }
+    }
+    text
+}
+
+fn serve(stream: UnixStream, client: &LuaClient) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let request = unescape(line?.trim_end());
+        writeln!(writer, "{}", escape(&respond(client, &request)))?;
+    }
+    Ok(())
+}
+
+fn respond(client: &LuaClient, request: &str) -> String {
+    // The engine only goes away when the game is shutting down
+    let stopped = || "error The Lua engine stopped".to_string();
+    match request.strip_prefix(".run ") {
+        Some(path) => match client.run_file_non_blocking(path.trim()).recv() {
+            Ok(Ok(())) => "ok".to_string(),
+            Ok(Err(e)) => format!("error {}", e),
+            Err(_) => stopped(),
+        },
+        None => match client.execute_non_blocking(request).recv() {
+            Ok(Ok(result)) => format!("ok {}", result),
+            Ok(Err(e)) => format!("error {}", e),
+            Err(_) => stopped(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lua_engine::LuaEngine;
+
+    #[test]
+    fn test_each_request_line_gets_a_response_line() {
+        let path = std::env::temp_dir().join(format!("commands_{}.sock", std::process::id()));
+        let path = path.to_str().unwrap();
+        let (command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        thread::spawn(move || engine.run());
+        let _socket = CommandSocket::bind(path, command_tx).unwrap();
+
+        let mut stream = UnixStream::connect(path).unwrap();
+        writeln!(stream, "answer = 42").unwrap();
+        writeln!(stream, "answer + 1").unwrap();
+        writeln!(stream, "error('boom')").unwrap();
+        writeln!(stream, "{}", escape("'two\\nlines'")).unwrap();
+        writeln!(
+            stream,
+            "{}",
+            escape("for _ = 1, 2 do\n  answer = answer * 2\nend")
+        )
+        .unwrap();
+        writeln!(stream, "answer").unwrap();
+        let mut lines = BufReader::new(stream).lines().map(Result::unwrap);
+
+        assert_eq!(lines.next().unwrap(), "ok nil");
+        assert_eq!(lines.next().unwrap(), "ok 43");
+        assert!(lines.next().unwrap().starts_with("error "));
+        assert_eq!(lines.next().unwrap(), "ok two\\nlines");
+        assert_eq!(lines.next().unwrap(), "ok nil");
+        assert_eq!(lines.next().unwrap(), "ok 168");
+    }
+
+    #[test]
+    fn test_backslashes_pass_both_ways() {
+        let path = std::env::temp_dir().join(format!("backslashes_{}.sock", std::process::id()));
+        let path = path.to_str().unwrap();
+        let (command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        thread::spawn(move || engine.run());
+        let _socket = CommandSocket::bind(path, command_tx).unwrap();
+
+        let mut stream = UnixStream::connect(path).unwrap();
+        // A chunk ending in a backslash, and results ending in one or holding a `\n`
+        writeln!(stream, "{}", escape(r"dir = 'C:\\temp\\' -- \")).unwrap();
+        writeln!(stream, "dir").unwrap();
+        writeln!(stream, "{}", escape(r"dir .. 'n\\n'")).unwrap();
+        let mut lines = BufReader::new(stream).lines().map(Result::unwrap);
+
+        assert_eq!(lines.next().unwrap(), "ok nil");
+        let dir = lines.next().unwrap();
+        assert_eq!(dir, r"ok C:\\temp\\");
+        assert_eq!(unescape(&dir), r"ok C:\temp\");
+        assert_eq!(unescape(&lines.next().unwrap()), r"ok C:\temp\n\n");
+    }
+
+    #[test]
+    fn test_only_sockets_are_replaced() {
+        let path = std::env::temp_dir().join(format!("not_a_socket_{}", std::process::id()));
+        fs::write(&path, "keep me").unwrap();
+        let (command_tx, _command_rx) = mpsc::channel();
+
+        let error = CommandSocket::bind(path.to_str().unwrap(), command_tx)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "keep me");
+        fs::remove_file(path).unwrap();
+    }
+}
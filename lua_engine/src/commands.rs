@@ -0,0 +1,209 @@
+//! Commands of the scripts, listed by the command palettes of the frontends next to their own
+
+use crate::error_log::ErrorLog;
+use mlua::{Function, Lua, Table};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+// Function of each command with its description, by name
+type Registered = BTreeMap<String, (Function, Option<String>)>;
+
+/// A command of the scripts as the palettes list it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// Commands registered with `api.commands`, by name. Registering a name again replaces its
+/// command, so scripts can be reloaded:
+///
+/// ```lua
+/// api.commands.register("Clear the tints", clear_tints, "Remove every tint the mods put on tiles")
+/// api.commands.run("Clear the tints")
+/// ```
+#[derive(Clone)]
+pub struct Commands {
+    commands: Arc<Mutex<Registered>>,
+    error_log: ErrorLog,
+}
+
+impl Commands {
+    pub(crate) fn install(lua: &Lua, error_log: ErrorLog) -> Self {
+        let commands = Self {
+            commands: Default::default(),
+            error_log,
+        };
+        let table: Table = lua
+            .globals()
+            .get::<Table>("api")
+            .and_then(|api| api.get("commands"))
+            .unwrap();
+
+        // Expose api.commands.register to Lua, with an optional description
+        {
+            let commands = commands.clone();
+            lua.create_function(
+                move |_, (name, function, description): (String, Function, Option<String>)| {
+                    if name.trim().is_empty() {
+                        return Err(mlua::Error::RuntimeError(
+                            "A command needs a name".to_string(),
+                        ));
+                    }
+                    commands
+                        .commands
+                        .lock()
+                        .unwrap()
+                        .insert(name, (function, description));
+                    Ok(())
+                },
+            )
+            .and_then(|f| table.set("register", f))
+            .unwrap();
+        }
+        // Expose api.commands.unregister to Lua, returns false if there was no such command
+        {
+            let commands = commands.clone();
+            lua.create_function(move |_, name: String| {
+                Ok(commands.commands.lock().unwrap().remove(&name).is_some())
+            })
+            .and_then(|f| table.set("unregister", f))
+            .unwrap();
+        }
+        // Expose api.commands.run to Lua, errors of the command are raised to the caller
+        {
+            let commands = commands.clone();
+            lua.create_function(move |_, name: String| commands.function(&name)?.call::<()>(()))
+                .and_then(|f| table.set("run", f))
+                .unwrap();
+        }
+        // Expose api.commands.list to Lua, as { name = ..., description = ... } by name
+        {
+            let commands = commands.clone();
+            lua.create_function(move |lua, ()| {
+                let list = lua.create_table()?;
+                for command in commands.list() {
+                    let entry = lua.create_table()?;
+                    entry.set("name", command.name)?;
+                    entry.set("description", command.description)?;
+                    list.push(entry)?;
+                }
+                Ok(list)
+            })
+            .and_then(|f| table.set("list", f))
+            .unwrap();
+        }
+
+        commands
+    }
+
+    fn function(&self, name: &str) -> mlua::Result<Function> {
+        self.commands
+            .lock()
+            .unwrap()
+            .get(name)
+            .map(|(function, _)| function.clone())
+            .ok_or_else(|| mlua::Error::RuntimeError(format!("There is no command '{}'", name)))
+    }
+
+    /// Every command ordered by name
+    pub fn list(&self) -> Vec<Command> {
+        self.commands
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, (_, description))| Command {
+                name: name.clone(),
+                description: description.clone(),
+            })
+            .collect()
+    }
+
+    /// Run a command for a palette, its errors go to the error log like those of handlers
+    pub fn run(&self, name: &str) {
+        // The command runs without the lock, it may well register others
+        if let Err(e) = self.function(name).and_then(|f| f.call::<()>(())) {
+            self.error_log.report(&format!("command '{}'", name), e);
+        }
+    }
+}
+
+/// How well `query` matches `candidate` in a fuzzy search, None if it doesn't. The letters of
+/// the query have to appear in order, ignoring case; runs of them and word starts score higher,
+/// so "et" ranks "erase tool" above "delete".
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = next + candidate[next..].iter().position(|&c| c == wanted)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !candidate[found - 1].is_alphanumeric() {
+            score += 10;
+        }
+        previous = Some(found);
+        next = found + 1;
+    }
+    // Shorter candidates first among equal matches
+    Some(score * 100 - candidate.len() as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lua_engine::LuaEngine;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_fuzzy_score_prefers_word_starts() {
+        assert_eq!(fuzzy_score("xyz", "erase tool"), None);
+        assert!(fuzzy_score("", "anything").is_some());
+        let erase = fuzzy_score("et", "erase tool").unwrap();
+        let delete = fuzzy_score("et", "delete").unwrap();
+        assert!(erase > delete, "{} {}", erase, delete);
+        assert!(fuzzy_score("ZOOM in", "zoom_in").is_some());
+    }
+
+    #[test]
+    fn test_registered_commands_are_listed_and_run() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                spawned = 0
+                api.commands.register("Spawn", function() spawned = spawned + 1 end, "Add someone")
+                api.commands.register("Fail", function() error("nope") end)
+                api.commands.run("Spawn")
+                "#,
+            )
+            .unwrap();
+        assert_eq!(
+            engine.commands.list(),
+            vec![
+                Command {
+                    name: "Fail".to_string(),
+                    description: None
+                },
+                Command {
+                    name: "Spawn".to_string(),
+                    description: Some("Add someone".to_string())
+                },
+            ]
+        );
+
+        engine.commands.run("Spawn");
+        engine.commands.run("Fail");
+        assert_eq!(engine.lua.globals().get::<i32>("spawned").unwrap(), 2);
+        assert_eq!(engine.error_log.errors()[0].context, "command 'Fail'");
+        assert!(engine.run_script("api.commands.run('Missing')").is_err());
+        assert!(engine
+            .run_script("assert(api.commands.unregister('Fail'))")
+            .is_ok());
+        assert_eq!(engine.commands.list().len(), 1);
+    }
+}
@@ -0,0 +1,132 @@
+//! Checks of the projections against the repositories, as `api.debug.verify` and `api.debug.watch`
+
+use crate::error_log::ErrorLog;
+use crate::script_error::ScriptError;
+use logic::{CoreApi, Discrepancy};
+use mlua::{Lua, Table};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Discrepancies listed in the error log, the rest are counted
+const REPORTED_DISCREPANCIES: usize = 5;
+
+/// The watch mode of `api.debug.watch`, verifying the projections every few ticks against the
+/// persons, once they caught up with the events published so far:
+///
+/// ```lua
+/// api.debug.watch(60) -- verify every 60 ticks, discrepancies go to the error log
+/// api.debug.watch(0)  -- stop watching
+/// ```
+#[derive(Clone)]
+pub struct ConsistencyWatch {
+    core: Arc<RwLock<CoreApi>>,
+    error_log: ErrorLog,
+    // Ticks between the checks, 0 when not watching
+    interval: Arc<AtomicU64>,
+}
+
+impl ConsistencyWatch {
+    pub(crate) fn install(lua: &Lua, core: Arc<RwLock<CoreApi>>, error_log: ErrorLog) -> Self {
+        let watch = Self {
+            core,
+            error_log,
+            interval: Default::default(),
+        };
+        let api: Table = lua.globals().get("api").unwrap();
+        let table: Table = api.get("debug").unwrap();
+        {
+            let watch = watch.clone();
+            // A list of { map, x, y, z, projected = { ids }, actual = { ids } }, empty when the
+            // projections agree with the persons
+            lua.create_function(move |lua, ()| {
+                let list = lua.create_table()?;
+                for discrepancy in watch.verify() {
+                    let entry = lua.create_table()?;
+                    entry.set("map", discrepancy.location.map.to_string())?;
+                    entry.set("x", discrepancy.location.x)?;
+                    entry.set("y", discrepancy.location.y)?;
+                    entry.set("z", discrepancy.location.z)?;
+                    entry.set("projected", discrepancy.projected)?;
+                    entry.set("actual", discrepancy.actual)?;
+                    list.push(entry)?;
+                }
+                Ok(list)
+            })
+            .and_then(|f| table.set("verify", f))
+            .unwrap();
+        }
+        {
+            let watch = watch.clone();
+            lua.create_function(move |_, ticks: Option<u64>| {
+                watch.interval.store(ticks.unwrap_or(0), Ordering::Relaxed);
+                Ok(())
+            })
+            .and_then(|f| table.set("watch", f))
+            .unwrap();
+        }
+        watch
+    }
+
+    fn verify(&self) -> Vec<Discrepancy> {
+        let core = self.core.read().unwrap();
+        core.wait_for_projections(Duration::from_millis(100));
+        core.debug().verify()
+    }
+
+    /// Verify the projections if the watch is on and its interval passed, called once per tick
+    pub(crate) fn update(&self, frame: u64) {
+        let interval = self.interval.load(Ordering::Relaxed);
+        if interval == 0 || !frame.is_multiple_of(interval) {
+            return;
+        }
+        let discrepancies = self.verify();
+        if discrepancies.is_empty() {
+            return;
+        }
+        let mut message = format!(
+            "LocationOccupancyProjection disagrees with the persons at {} locations",
+            discrepancies.len()
+        );
+        for discrepancy in discrepancies.iter().take(REPORTED_DISCREPANCIES) {
+            message.push_str(&format!("\n  {}", discrepancy));
+        }
+        if discrepancies.len() > REPORTED_DISCREPANCIES {
+            message.push_str(&format!(
+                "\n  and {} more",
+                discrepancies.len() - REPORTED_DISCREPANCIES
+            ));
+        }
+        self.error_log
+            .report("api.debug.watch", ScriptError::new(&message, ""));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lua_engine::LuaEngine;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_consistent_world_verifies_clean() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                api.person.create("Ann", 1, 2)
+                local moved = api.person.create("Bob", 1, 2)
+                api.person.move_to(moved.id, 4, 4)
+                discrepancies = #api.debug.verify()
+                api.debug.watch(1)
+                "#,
+            )
+            .unwrap();
+        assert_eq!(
+            engine.lua.globals().get::<usize>("discrepancies").unwrap(),
+            0
+        );
+        engine.tick(0.1, 1);
+        assert!(engine.error_log.is_empty());
+    }
+}
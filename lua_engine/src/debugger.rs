@@ -0,0 +1,324 @@
+use mlua::{ffi, Compiler, Lua, Result as LuaResult, VmState};
+use std::ffi::{c_int, CStr};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, ThreadId};
+
+// Debug level keeping local and upvalue names in compiled chunks
+const FULL_DEBUG_INFO: u8 = 2;
+
+/// Variable names with their printed values
+pub type Variables = Vec<(String, String)>;
+
+/// Breakpoint set by file (matched against the end of the chunk name) and line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub file: String,
+    pub line: i32,
+}
+
+impl Breakpoint {
+    fn matches(&self, location: &Location) -> bool {
+        self.line == location.line && location.file.ends_with(&self.file)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Location {
+    file: String,
+    line: i32,
+}
+
+/// Snapshot of the function execution is paused in, taken before pausing so the UI
+/// can show it without touching the (blocked) Lua state
+#[derive(Debug, Clone)]
+pub struct PausedFrame {
+    pub file: String,
+    pub line: i32,
+    pub locals: Variables,
+    pub upvalues: Variables,
+}
+
+#[derive(Default)]
+struct DebuggerState {
+    breakpoints: Vec<Breakpoint>,
+    // Pause again as soon as execution leaves this location
+    step_from: Option<Location>,
+    paused: Option<PausedFrame>,
+    // Thread drawing the debugger UI, pausing it would freeze the UI for good
+    ui_thread: Option<ThreadId>,
+}
+
+/// Breakpoint debugger driven by the Luau interrupt callback.
+///
+/// Luau only interrupts on calls and loop back-edges, so a breakpoint is hit when its
+/// line calls a function or closes a loop. Pausing blocks the thread running the script
+/// until the UI continues or steps.
+#[derive(Clone)]
+pub struct Debugger {
+    state: Arc<(Mutex<DebuggerState>, Condvar)>,
+}
+
+impl Debugger {
+    /// Install the interrupt callback and the Lua `debugger` global
+    pub(crate) fn install(lua: &Lua) -> Self {
+        let debugger = Self {
+            state: Default::default(),
+        };
+        lua.set_compiler(Compiler::new().set_debug_level(FULL_DEBUG_INFO));
+
+        {
+            let debugger = debugger.clone();
+            lua.set_interrupt(move |lua| debugger.on_interrupt(lua));
+        }
+
+        let table = lua.create_table().unwrap();
+        {
+            let debugger = debugger.clone();
+            let set_breakpoint = lua
+                .create_function(move |_, (file, line): (String, i32)| {
+                    debugger.set_breakpoint(Breakpoint { file, line });
+                    Ok(())
+                })
+                .unwrap();
+            // `break` is a keyword, so scripts have to use debugger["break"] or the alias
+            table.set("break", set_breakpoint.clone()).unwrap();
+            table.set("breakpoint", set_breakpoint).unwrap();
+        }
+        {
+            let debugger = debugger.clone();
+            lua.create_function(move |_, (file, line): (String, i32)| {
+                debugger.clear_breakpoint(&Breakpoint { file, line });
+                Ok(())
+            })
+            .and_then(|f| table.set("clear", f))
+            .unwrap();
+        }
+        {
+            let debugger = debugger.clone();
+            lua.create_function(move |_, ()| {
+                debugger.lock().breakpoints.clear();
+                Ok(())
+            })
+            .and_then(|f| table.set("clear_all", f))
+            .unwrap();
+        }
+        {
+            let debugger = debugger.clone();
+            lua.create_function(move |_, ()| {
+                Ok(debugger
+                    .breakpoints()
+                    .iter()
+                    .map(|b| format!("{}:{}", b.file, b.line))
+                    .collect::<Vec<_>>())
+            })
+            .and_then(|f| table.set("breakpoints", f))
+            .unwrap();
+        }
+        lua.globals().set("debugger", table).unwrap();
+
+        debugger
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, DebuggerState> {
+        self.state.0.lock().unwrap()
+    }
+
+    /// Never pause scripts called from the current thread (the one drawing the debugger UI)
+    pub fn set_ui_thread(&self) {
+        self.lock().ui_thread = Some(thread::current().id());
+    }
+
+    pub fn set_breakpoint(&self, breakpoint: Breakpoint) {
+        let mut state = self.lock();
+        if !state.breakpoints.contains(&breakpoint) {
+            state.breakpoints.push(breakpoint);
+        }
+    }
+
+    pub fn clear_breakpoint(&self, breakpoint: &Breakpoint) {
+        self.lock().breakpoints.retain(|b| b != breakpoint);
+    }
+
+    pub fn breakpoints(&self) -> Vec<Breakpoint> {
+        self.lock().breakpoints.clone()
+    }
+
+    /// Where execution is paused, if it is
+    pub fn paused(&self) -> Option<PausedFrame> {
+        self.lock().paused.clone()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.lock().paused.is_some()
+    }
+
+    /// Resume execution until the next breakpoint
+    pub fn resume(&self) {
+        self.lock().paused = None;
+        self.state.1.notify_all();
+    }
+
+    /// Resume execution and pause again on the next line reached
+    pub fn step(&self) {
+        {
+            let mut state = self.lock();
+            if let Some(frame) = state.paused.take() {
+                state.step_from = Some(Location {
+                    file: frame.file,
+                    line: frame.line,
+                });
+            }
+        }
+        self.state.1.notify_all();
+    }
+
+    fn on_interrupt(&self, lua: &Lua) -> LuaResult<VmState> {
+        {
+            let state = self.lock();
+            let idle = state.breakpoints.is_empty() && state.step_from.is_none();
+            if idle || state.ui_thread == Some(thread::current().id()) {
+                return Ok(VmState::Continue);
+            }
+        }
+
+        let Some(location) = current_location(lua) else {
+            return Ok(VmState::Continue);
+        };
+        let hit = {
+            let state = self.lock();
+            match &state.step_from {
+                Some(from) => *from != location,
+                None => state.breakpoints.iter().any(|b| b.matches(&location)),
+            }
+        };
+        if !hit {
+            return Ok(VmState::Continue);
+        }
+
+        let (locals, upvalues) = inspect_variables(lua)?;
+        let mut state = self.lock();
+        state.step_from = None;
+        state.paused = Some(PausedFrame {
+            file: location.file,
+            line: location.line,
+            locals,
+            upvalues,
+        });
+        while state.paused.is_some() {
+            state = self.state.1.wait(state).unwrap();
+        }
+        Ok(VmState::Continue)
+    }
+}
+
+// Chunk name and line of the function being interrupted
+fn current_location(lua: &Lua) -> Option<Location> {
+    let frame = lua.inspect_stack(0)?;
+    let file = frame
+        .source()
+        .source?
+        .trim_start_matches(['@', '='])
+        .to_string();
+    Some(Location {
+        file,
+        line: frame.curr_line(),
+    })
+}
+
+// Names and printed values of the locals and upvalues of the interrupted function
+fn inspect_variables(lua: &Lua) -> LuaResult<(Variables, Variables)> {
+    let mut locals = Vec::new();
+    let mut upvalues = Vec::new();
+    unsafe {
+        // The raw closure runs as a C function, so the interrupted function is one level up
+        lua.exec_raw::<()>((), |state| {
+            let level: c_int = 1;
+            let mut n = 1;
+            loop {
+                let name = ffi::lua_getlocal(state, level, n);
+                if name.is_null() {
+                    break;
+                }
+                let name = CStr::from_ptr(name).to_string_lossy().into_owned();
+                let value = pop_as_string(state);
+                // Compiler temporaries like "(for index)"
+                if !name.starts_with('(') {
+                    locals.push((name, value));
+                }
+                n += 1;
+            }
+
+            let mut ar: ffi::lua_Debug = std::mem::zeroed();
+            if ffi::lua_getinfo(state, level, c"f".as_ptr(), &mut ar) != 0 {
+                let mut n = 1;
+                loop {
+                    let name = ffi::lua_getupvalue(state, -1, n);
+                    if name.is_null() {
+                        break;
+                    }
+                    let name = CStr::from_ptr(name).to_string_lossy().into_owned();
+                    upvalues.push((name, pop_as_string(state)));
+                    n += 1;
+                }
+                ffi::lua_pop(state, 1);
+            }
+        })?;
+    }
+    Ok((locals, upvalues))
+}
+
+// Print the value on top of the stack like `tostring` and pop it
+unsafe fn pop_as_string(state: *mut ffi::lua_State) -> String {
+    unsafe {
+        let text = ffi::luaL_tolstring(state, -1, std::ptr::null_mut());
+        let text = CStr::from_ptr(text).to_string_lossy().into_owned();
+        ffi::lua_pop(state, 2);
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn wait_for_pause(debugger: &Debugger) -> PausedFrame {
+        for _ in 0..500 {
+            if let Some(frame) = debugger.paused() {
+                return frame;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("script never paused");
+    }
+
+    #[test]
+    fn test_breakpoint_pauses_and_steps() {
+        let lua = Lua::new();
+        let debugger = Debugger::install(&lua);
+        lua.load(r#"debugger.breakpoint("test.lua", 3)"#)
+            .exec()
+            .unwrap();
+
+        let script = thread::spawn(move || {
+            lua.load("local count = 41\nlocal function inc(x) return x + 1 end\ncount = inc(count)\nlocal text = tostring(count)")
+                .set_name("@test.lua")
+                .exec()
+                .unwrap();
+        });
+
+        let frame = wait_for_pause(&debugger);
+        assert_eq!((frame.file.as_str(), frame.line), ("test.lua", 3));
+        assert!(frame
+            .locals
+            .contains(&("count".to_string(), "41".to_string())));
+
+        debugger.step();
+        let frame = wait_for_pause(&debugger);
+        assert_ne!(frame.line, 3);
+
+        debugger.resume();
+        script.join().unwrap();
+        assert!(!debugger.is_paused());
+    }
+}
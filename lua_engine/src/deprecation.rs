@@ -0,0 +1,141 @@
+use mlua::{Function, Lua, MultiValue, Result as LuaResult, Table, Value};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct DeprecationState {
+    // Deprecated function and the call site it was used from
+    seen: HashSet<(String, String)>,
+    warnings: Vec<String>,
+}
+
+/// Keeps renamed and removed API functions callable so older mods still run, warning once per
+/// call site. The warnings are printed and available to scripts as `api.deprecations()`.
+#[derive(Clone, Default)]
+pub struct Deprecations {
+    state: Arc<Mutex<DeprecationState>>,
+}
+
+impl Deprecations {
+    pub fn install(lua: &Lua) -> Self {
+        let deprecations = Self::default();
+        {
+            let deprecations = deprecations.clone();
+            lua.create_function(move |_, ()| Ok(deprecations.warnings()))
+                .and_then(|f| lua.globals().get::<Table>("api")?.set("deprecations", f))
+                .unwrap();
+        }
+        deprecations
+    }
+
+    /// Make `module.old` call `module.new`, looked up on every call so replacements still apply
+    pub fn renamed(
+        &self,
+        lua: &Lua,
+        table: &Table,
+        module: &str,
+        old: &str,
+        new: &str,
+    ) -> LuaResult<()> {
+        let deprecations = self.clone();
+        let target = table.clone();
+        let message = format!(
+            "api.{}.{} is deprecated, use api.{}.{}",
+            module, old, module, new
+        );
+        let new = new.to_string();
+        let function = lua.create_function(move |lua, args: MultiValue| {
+            deprecations.warn(lua, &message);
+            target
+                .get::<Function>(new.as_str())?
+                .call::<MultiValue>(args)
+        })?;
+        table.set(old, function)
+    }
+
+    /// Make `module.old` return nil, with `hint` telling what to use instead
+    pub fn removed(
+        &self,
+        lua: &Lua,
+        table: &Table,
+        module: &str,
+        old: &str,
+        hint: &str,
+    ) -> LuaResult<()> {
+        let deprecations = self.clone();
+        let message = format!("api.{}.{} was removed, {}", module, old, hint);
+        let function = lua.create_function(move |lua, _: MultiValue| {
+            deprecations.warn(lua, &message);
+            Ok(Value::Nil)
+        })?;
+        table.set(old, function)
+    }
+
+    /// The warnings so far, in the order they were first given
+    pub fn warnings(&self) -> Vec<String> {
+        self.state.lock().unwrap().warnings.clone()
+    }
+
+    fn warn(&self, lua: &Lua, message: &str) {
+        // Level 1 is the script calling the deprecated function
+        let call_site = lua
+            .inspect_stack(1)
+            .map(|frame| {
+                let source = frame.source().short_src.unwrap_or_default().to_string();
+                format!("{}:{}", source, frame.curr_line())
+            })
+            .unwrap_or_else(|| "?".to_string());
+        let mut state = self.state.lock().unwrap();
+        if state.seen.insert((message.to_string(), call_site.clone())) {
+            let warning = format!("{} ({})", message, call_site);
+            println!("Warning: {}", warning);
+            state.warnings.push(warning);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deprecated_functions_warn_once_per_call_site() {
+        let lua = Lua::new();
+        lua.globals()
+            .set("api", lua.create_table().unwrap())
+            .unwrap();
+        let deprecations = Deprecations::install(&lua);
+        let module = lua.create_table().unwrap();
+        lua.load("function double(x) return x * 2 end")
+            .exec()
+            .unwrap();
+        module
+            .set("double", lua.globals().get::<Function>("double").unwrap())
+            .unwrap();
+        deprecations
+            .renamed(&lua, &module, "math", "twice", "double")
+            .unwrap();
+        deprecations
+            .removed(&lua, &module, "math", "half", "divide by 2 instead")
+            .unwrap();
+        lua.globals().set("m", module).unwrap();
+
+        let result: i32 = lua
+            .load("local r = 0\nfor _ = 1, 3 do r = m.twice(r + 1) end\nreturn r + m.twice(1)")
+            .set_name("=mod")
+            .eval()
+            .unwrap();
+        assert_eq!(result, 16);
+        let removed: Value = lua.load("m.half(4)").set_name("=mod").eval().unwrap();
+        assert_eq!(removed, Value::Nil);
+
+        assert_eq!(
+            deprecations.warnings(),
+            vec![
+                "api.math.twice is deprecated, use api.math.double (mod:2)",
+                "api.math.twice is deprecated, use api.math.double (mod:3)",
+                "api.math.half was removed, divide by 2 instead (mod:1)",
+            ]
+        );
+    }
+}
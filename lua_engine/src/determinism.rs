@@ -0,0 +1,120 @@
+use crate::lifecycle::Hook;
+use crate::lua_engine::LuaEngine;
+use crate::script_error::ScriptError;
+use logic::{first_divergence, Divergence, DomainEvent, StateHash};
+use std::sync::mpsc;
+use std::time::Duration;
+
+// Fixed frame time of the simulated frames, real frame times would differ between runs
+const FRAME_TIME: f32 = 1.0 / 60.0;
+// How long the event store may stay silent before a run counts as finished
+const EVENTS_QUIET_TIME: Duration = Duration::from_millis(100);
+
+/// Outcome of running a scenario twice
+pub struct DeterminismReport {
+    pub first_events: usize,
+    pub second_events: usize,
+    pub divergence: Option<Divergence>,
+    /// Parts of the world state the runs ended with differently, see `api.debug.state_hashes`
+    pub differing_state: Vec<&'static str>,
+}
+
+/// Run the scenario script on a fresh engine with `math.randomseed(seed)` and the script
+/// arguments in `env`, then advance `frames` frames (hooks and timers) and return the domain
+/// events it produced along with the hash of the world state it ended with
+pub fn run_scenario(
+    script: &str,
+    script_args: &[(String, String)],
+    seed: u32,
+    frames: u64,
+) -> Result<(Vec<DomainEvent>, StateHash), ScriptError> {
+    let (_command_tx, command_rx) = mpsc::channel();
+    let mut engine = LuaEngine::new(command_rx);
+    engine.set_script_args(script_args)?;
+    engine.run_script(&format!("math.randomseed({})", seed))?;
+    engine.run_script(script)?;
+    for frame in 0..frames {
+        engine.hooks.call(Hook::Frame, (FRAME_TIME, frame));
+        engine.timers.update(FRAME_TIME as f64);
+    }
+    // Errors in hooks and timers don't stop the run, but they likely explain a divergence
+    for logged in engine.error_log.errors() {
+        println!("{}: {}", logged.context, logged.error);
+    }
+    let events = engine.events.drain(EVENTS_QUIET_TIME);
+    let core = engine.core.read().unwrap();
+    core.wait_for_projections(EVENTS_QUIET_TIME);
+    Ok((events, core.debug().hash()))
+}
+
+/// Run the scenario twice with the same seed and compare the event streams of the runs
+pub fn check_determinism(
+    script: &str,
+    script_args: &[(String, String)],
+    seed: u32,
+    frames: u64,
+) -> Result<DeterminismReport, ScriptError> {
+    let (first, first_state) = run_scenario(script, script_args, seed, frames)?;
+    let (second, second_state) = run_scenario(script, script_args, seed, frames)?;
+    Ok(DeterminismReport {
+        first_events: first.len(),
+        second_events: second.len(),
+        divergence: first_divergence(&first, &second),
+        differing_state: first_state.differing(&second_state),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_scenario_is_deterministic() {
+        let report = check_determinism(
+            r#"
+            for i = 1, 5 do
+                api.person.create("P" .. i, math.random(1, 100), math.random(1, 100))
+            end
+            timer.after(0.05, function() api.person.move_to(1, math.random(1, 100), 0) end)
+            "#,
+            &[],
+            7,
+            10,
+        )
+        .unwrap();
+
+        assert_eq!(report.first_events, 6);
+        assert_eq!(report.second_events, 6);
+        assert!(report.divergence.is_none());
+        assert!(report.differing_state.is_empty());
+    }
+
+    #[test]
+    fn test_state_hash_tells_which_part_differs() {
+        let hashes = |script: &str| {
+            let (_command_tx, command_rx) = mpsc::channel();
+            let mut engine = LuaEngine::new(command_rx);
+            engine.run_script(script).unwrap();
+            let (hash, parts): (String, std::collections::HashMap<String, String>) = engine
+                .lua
+                .load("return api.debug.state_hash(), api.debug.state_hashes()")
+                .eval()
+                .unwrap();
+            (hash, parts)
+        };
+        let (first, first_parts) = hashes("api.person.create('Ann', 1, 1)");
+        let (same, _) = hashes("api.person.create('Ann', 1, 1)");
+        let (moved, moved_parts) = hashes("api.person.create('Ann', 2, 1)");
+
+        assert_eq!(first.len(), 16);
+        assert_eq!(first, same);
+        assert_ne!(first, moved);
+        let mut differing: Vec<_> = first_parts
+            .iter()
+            .filter(|(part, hash)| moved_parts[*part] != **hash)
+            .map(|(part, _)| part.as_str())
+            .collect();
+        differing.sort();
+        assert_eq!(differing, vec!["locations", "persons"]);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::error_log::ErrorLog;
+use crate::lua_client::LuaClient;
+use crate::script_error::ScriptError;
+use std::fs;
+use std::sync::mpsc;
+use std::time::SystemTime;
+
+const SCRATCH_TEMPLATE: &str = "-- Scratch script, runs in the game every time it is saved\n";
+
+/// Scratch script of the dev mode, run again whenever it is saved, so mods can be written in an
+/// external editor. Errors go to the error log, like the errors of other background scripts.
+pub struct DevScript {
+    path: String,
+    modified: Option<SystemTime>,
+    poll_interval: f64,
+    last_poll: f64,
+    pending: Option<mpsc::Receiver<Result<(), ScriptError>>>,
+    error_log: ErrorLog,
+}
+
+impl DevScript {
+    /// Watch the script at `path`, creating an empty one when there is none yet
+    pub fn new(path: &str, poll_interval: f64, error_log: ErrorLog) -> Self {
+        if fs::metadata(path).is_err() {
+            match fs::write(path, SCRATCH_TEMPLATE) {
+                Ok(()) => println!("Created the scratch script {}", path),
+                Err(e) => println!("Failed to create the scratch script {}: {}", path, e),
+            }
+        }
+        println!("Dev mode: running {} whenever it is saved", path);
+        Self {
+            path: path.to_string(),
+            modified: None,
+            poll_interval,
+            last_poll: f64::NEG_INFINITY,
+            pending: None,
+            error_log,
+        }
+    }
+
+    /// Call every frame with the current time in seconds, runs the script when it changed
+    pub fn update(&mut self, client: &LuaClient, now: f64) {
+        if let Some(pending) = &self.pending {
+            match pending.try_recv() {
+                Ok(Ok(())) => println!("Ran {}", self.path),
+                Ok(Err(e)) => self.error_log.report(&self.path, e),
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => {}
+            }
+            self.pending = None;
+        }
+
+        if now - self.last_poll < self.poll_interval {
+            return;
+        }
+        self.last_poll = now;
+
+        // Editors save in several steps, a missing file is skipped until it's back
+        let Ok(modified) = fs::metadata(&self.path).and_then(|metadata| metadata.modified()) else {
+            return;
+        };
+        if self.modified != Some(modified) {
+            self.modified = Some(modified);
+            self.pending = Some(client.run_file_non_blocking(&self.path));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lua_engine::LuaEngine;
+    use std::time::Duration;
+
+    #[test]
+    fn test_scratch_script_runs_again_when_saved() {
+        let path = std::env::temp_dir().join(format!("scratch_{}.lua", std::process::id()));
+        let path = path.to_str().unwrap();
+        fs::write(path, "runs = (runs or 0) + 1").unwrap();
+
+        let (command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        std::thread::spawn(move || engine.run());
+        let client = LuaClient::new(command_tx);
+        let runs = || client.execute_non_blocking("runs").recv().unwrap().unwrap();
+
+        let mut dev_script = DevScript::new(path, 0.0, ErrorLog::default());
+        dev_script.update(&client, 0.0);
+        assert_eq!(runs(), "1");
+        dev_script.update(&client, 1.0);
+        assert_eq!(runs(), "1");
+
+        // Saving changes the modification time
+        let saved = SystemTime::now() + Duration::from_secs(5);
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_modified(saved))
+            .unwrap();
+        dev_script.update(&client, 2.0);
+        assert_eq!(runs(), "2");
+        fs::remove_file(path).unwrap();
+    }
+}
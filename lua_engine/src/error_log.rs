@@ -0,0 +1,95 @@
+use crate::script_error::ScriptError;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+// Oldest errors are dropped beyond this
+const MAX_LOGGED_ERRORS: usize = 20;
+
+/// Error raised outside of the console, e.g. in a UI handler
+#[derive(Debug, Clone)]
+pub struct LoggedError {
+    /// What was running when the error happened
+    pub context: String,
+    pub error: ScriptError,
+    /// How many times the same error was reported, handlers called every frame repeat them
+    pub count: usize,
+}
+
+/// Errors of scripts running in the background, shown by the frontends in an error overlay
+#[derive(Clone, Default)]
+pub struct ErrorLog {
+    errors: Arc<Mutex<VecDeque<LoggedError>>>,
+}
+
+impl ErrorLog {
+    pub fn report(&self, context: &str, error: impl Into<ScriptError>) {
+        let error = error.into();
+        let mut errors = self.errors.lock().unwrap();
+        if let Some(logged) = errors
+            .iter_mut()
+            .find(|logged| logged.context == context && logged.error == error)
+        {
+            logged.count += 1;
+            return;
+        }
+
+        eprintln!("{}: {}", context, error);
+        errors.push_back(LoggedError {
+            context: context.to_string(),
+            error,
+            count: 1,
+        });
+        if errors.len() > MAX_LOGGED_ERRORS {
+            errors.pop_front();
+        }
+    }
+
+    /// Logged errors, oldest first
+    pub fn errors(&self) -> Vec<LoggedError> {
+        self.errors.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.lock().unwrap().is_empty()
+    }
+
+    pub fn clear(&self) {
+        self.errors.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_errors_are_counted() {
+        let log = ErrorLog::default();
+        for _ in 0..3 {
+            log.report("Label handler", ScriptError::new("init.lua:3: boom", ""));
+        }
+        log.report("Button handler", ScriptError::new("init.lua:3: boom", ""));
+
+        let errors = log.errors();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].count, 3);
+        assert_eq!(errors[1].context, "Button handler");
+    }
+
+    #[test]
+    fn test_oldest_errors_are_dropped() {
+        let log = ErrorLog::default();
+        for i in 0..MAX_LOGGED_ERRORS + 5 {
+            log.report("Timer", ScriptError::new(&format!("error {}", i), ""));
+        }
+        assert_eq!(log.len(), MAX_LOGGED_ERRORS);
+        assert_eq!(log.errors()[0].error.message, "error 5");
+
+        log.clear();
+        assert!(log.is_empty());
+    }
+}
@@ -0,0 +1,507 @@
+use crate::error_log::ErrorLog;
+use crate::lua_engine::meta_value_to_lua;
+use crate::notifications::Notifications;
+use crate::tutorial::Tutorials;
+use logic::{
+    Chunk, DomainEvent, EntityRef, FactionEvent, FogEvent, GoalEvent, GroupEvent, Load, Location,
+    MapEvent, PersonEvent, PortalEvent, ScenarioEvent, StreamEvent, TagEvent, TaskEvent,
+    VehicleEvent, WorldEvent,
+};
+use mlua::{Function, Lua, Table};
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+/// Global table mapping event kinds to their handlers, defined by the scripts
+pub const EVENT_HANDLERS_TABLE: &str = "event_effects";
+
+/// Forwards domain events to the Lua handlers in `event_effects`, so scripts decide how the
+/// frontends show them, e.g. `event_effects.PersonCreated = function(event) ... end`
+pub struct EventBridge {
+    events: Receiver<DomainEvent>,
+    error_log: ErrorLog,
+}
+
+impl EventBridge {
+    pub(crate) fn new(events: Receiver<DomainEvent>, error_log: ErrorLog) -> Self {
+        Self { events, error_log }
+    }
+
+    /// Call the handlers of all events published since the last dispatch, raise the
+    /// notifications routed from them and let the tutorial waiting for them go on
+    pub(crate) fn dispatch(&self, lua: &Lua, notifications: &Notifications, tutorials: &Tutorials) {
+        let handlers = match lua.globals().get::<Option<Table>>(EVENT_HANDLERS_TABLE) {
+            Ok(handlers) => handlers,
+            Err(e) => {
+                self.error_log.report(EVENT_HANDLERS_TABLE, e);
+                None
+            }
+        };
+
+        // Events are drained even without handlers, they would only pile up otherwise
+        for event in self.events.try_iter() {
+            let kind = event_kind(&event);
+            let handler = match &handlers {
+                Some(handlers) => handlers.get::<Option<Function>>(kind),
+                None => Ok(None),
+            };
+            let routed = notifications.routes(kind);
+            let awaited = tutorials.waits_for(kind);
+            let result = handler.and_then(|handler| {
+                if handler.is_none() && !routed && !awaited {
+                    return Ok(());
+                }
+                let table = event_table(lua, &event)?;
+                if let Some(handler) = handler {
+                    handler.call::<()>(table.clone())?;
+                }
+                if routed {
+                    notifications.notify(kind, &table);
+                }
+                if awaited {
+                    tutorials.observe(kind, &table);
+                }
+                Ok(())
+            });
+            if let Err(e) = result {
+                self.error_log
+                    .report(&format!("{}.{}", EVENT_HANDLERS_TABLE, kind), e);
+            }
+        }
+    }
+}
+
+impl EventBridge {
+    /// Take the published events instead of dispatching them, waiting until none arrived for
+    /// `quiet`, as the event store publishes from its own thread
+    pub(crate) fn drain(&self, quiet: Duration) -> Vec<DomainEvent> {
+        let mut events = Vec::new();
+        while let Ok(event) = self.events.recv_timeout(quiet) {
+            events.push(event);
+        }
+        events
+    }
+}
+
+pub(crate) fn event_kind(event: &DomainEvent) -> &'static str {
+    match event {
+        DomainEvent::Person(PersonEvent::PersonCreated { .. }) => "PersonCreated",
+        DomainEvent::Person(PersonEvent::PersonMoved { .. }) => "PersonMoved",
+        DomainEvent::Person(PersonEvent::PersonsMoved { .. }) => "PersonsMoved",
+        DomainEvent::Tag(TagEvent::TagAdded { .. }) => "TagAdded",
+        DomainEvent::Tag(TagEvent::TagRemoved { .. }) => "TagRemoved",
+        DomainEvent::Tag(TagEvent::MetadataSet { .. }) => "MetadataSet",
+        DomainEvent::Tag(TagEvent::MetadataRemoved { .. }) => "MetadataRemoved",
+        DomainEvent::Scenario(ScenarioEvent::ScenarioEnded { .. }) => "ScenarioEnded",
+        DomainEvent::Goal(GoalEvent::GoalProgressed { .. }) => "GoalProgressed",
+        DomainEvent::Goal(GoalEvent::GoalCompleted { .. }) => "GoalCompleted",
+        DomainEvent::Fog(FogEvent::TilesRevealed { .. }) => "TilesRevealed",
+        DomainEvent::Fog(FogEvent::TilesHidden { .. }) => "TilesHidden",
+        DomainEvent::Faction(FactionEvent::FactionCreated { .. }) => "FactionCreated",
+        DomainEvent::Faction(FactionEvent::ZoneCreated { .. }) => "ZoneCreated",
+        DomainEvent::Faction(FactionEvent::OwnershipTransferred { .. }) => "OwnershipTransferred",
+        DomainEvent::Task(TaskEvent::TaskStarted { .. }) => "TaskStarted",
+        DomainEvent::Task(TaskEvent::TaskCompleted { .. }) => "TaskCompleted",
+        DomainEvent::Task(TaskEvent::TaskAbandoned { .. }) => "TaskAbandoned",
+        DomainEvent::Vehicle(VehicleEvent::VehicleCreated { .. }) => "VehicleCreated",
+        DomainEvent::Vehicle(VehicleEvent::VehicleMoved { .. }) => "VehicleMoved",
+        DomainEvent::Vehicle(VehicleEvent::VehicleArrived { .. }) => "VehicleArrived",
+        DomainEvent::Vehicle(VehicleEvent::VehicleStranded { .. }) => "VehicleStranded",
+        DomainEvent::Vehicle(VehicleEvent::VehicleLoaded { .. }) => "VehicleLoaded",
+        DomainEvent::Vehicle(VehicleEvent::VehicleUnloaded { .. }) => "VehicleUnloaded",
+        DomainEvent::Portal(PortalEvent::PortalCreated { .. }) => "PortalCreated",
+        DomainEvent::Portal(PortalEvent::PortalRemoved { .. }) => "PortalRemoved",
+        DomainEvent::Portal(PortalEvent::PortalTraversed { .. }) => "PortalTraversed",
+        DomainEvent::World(WorldEvent::MapCreated { .. }) => "MapCreated",
+        DomainEvent::World(WorldEvent::MapSwitched { .. }) => "MapSwitched",
+        DomainEvent::World(WorldEvent::LevelSwitched { .. }) => "LevelSwitched",
+        DomainEvent::Group(GroupEvent::GroupCreated { .. }) => "GroupCreated",
+        DomainEvent::Group(GroupEvent::GroupJoined { .. }) => "GroupJoined",
+        DomainEvent::Group(GroupEvent::GroupLeft { .. }) => "GroupLeft",
+        DomainEvent::Group(GroupEvent::GroupDisbanded { .. }) => "GroupDisbanded",
+        DomainEvent::Group(GroupEvent::GroupItemStored { .. }) => "GroupItemStored",
+        DomainEvent::Group(GroupEvent::GroupItemTaken { .. }) => "GroupItemTaken",
+        DomainEvent::Map(MapEvent::TilePlaced { .. }) => "TilePlaced",
+        DomainEvent::Map(MapEvent::TileRemoved { .. }) => "TileRemoved",
+        DomainEvent::Map(MapEvent::RegionLocked { .. }) => "RegionLocked",
+        DomainEvent::Map(MapEvent::RegionUnlocked { .. }) => "RegionUnlocked",
+        DomainEvent::Stream(StreamEvent::ChunkUnloaded { .. }) => "ChunkUnloaded",
+        DomainEvent::Stream(StreamEvent::ChunkLoaded { .. }) => "ChunkLoaded",
+    }
+}
+
+// Every event has `kind` plus its own fields, person events the tile they happened at as `x`
+// and `y`, tag events the `entity` they are about. `PersonsMoved` has a list of `moves` shaped
+// like `PersonMoved` events instead, fog events the `viewer` and a list of `tiles` as { x, y }.
+// Zones are given by their corners `min_x`, `min_y`, `max_x` and `max_y`, a transfer from or to
+// nobody has no `from` or `to`. Task events have the `task` id and the `person_id`, vehicle
+// events the `vehicle` id and where it is as `x` and `y`, or what it (un)loaded as `person_id`
+// or `item`. Portal events have the `portal` id and the ends it links or was stepped through as
+// `from_x`, `from_y` and `x`, `y` with the `map` and level `z` of the latter. Map events have
+// the name of the `map` created or the one switched `from` and `to`, level events the `map` and
+// the levels switched `from` and `to`. Group events have the `group` id and the `person_id` who
+// joined or left or the `item` stored or taken, a created group its `name`, `group_kind`
+// and `shared_inventory`. Tile events have the cell as `x`, `y`, `map` and `z`, its `layer` and
+// the `tile` placed, lock events the `lock` id and a new lock its `owner` and corners like a zone.
+// Chunk events have the `map` and level `z` of the chunk, its tiles as corners like a zone and
+// the ids of the `persons` written or read back with it.
+pub(crate) fn event_table(lua: &Lua, event: &DomainEvent) -> mlua::Result<Table> {
+    let table = lua.create_table()?;
+    table.set("kind", event_kind(event))?;
+    match event {
+        DomainEvent::Person(PersonEvent::PersonCreated {
+            person_id,
+            name,
+            location,
+        }) => {
+            table.set("person_id", person_id.0)?;
+            table.set("name", name.as_str())?;
+            table.set("x", location.x)?;
+            table.set("y", location.y)?;
+        }
+        DomainEvent::Person(PersonEvent::PersonMoved {
+            person_id,
+            from_location,
+            to_location,
+        }) => {
+            table.set("person_id", person_id.0)?;
+            table.set("from_x", from_location.x)?;
+            table.set("from_y", from_location.y)?;
+            table.set("x", to_location.x)?;
+            table.set("y", to_location.y)?;
+            table.set("map", to_location.map.as_str())?;
+            table.set("z", to_location.z)?;
+        }
+        DomainEvent::Person(PersonEvent::PersonsMoved { moves }) => {
+            let moves_table = lua.create_table_with_capacity(moves.len(), 0)?;
+            for person_move in moves {
+                let move_table = lua.create_table()?;
+                move_table.set("person_id", person_move.person_id.0)?;
+                move_table.set("from_x", person_move.from_location.x)?;
+                move_table.set("from_y", person_move.from_location.y)?;
+                move_table.set("x", person_move.to_location.x)?;
+                move_table.set("y", person_move.to_location.y)?;
+                moves_table.push(move_table)?;
+            }
+            table.set("moves", moves_table)?;
+        }
+        DomainEvent::Tag(TagEvent::TagAdded { entity, tag })
+        | DomainEvent::Tag(TagEvent::TagRemoved { entity, tag }) => {
+            table.set("entity", entity.to_string())?;
+            table.set("tag", tag.as_str())?;
+        }
+        DomainEvent::Tag(TagEvent::MetadataSet { entity, key, value }) => {
+            table.set("entity", entity.to_string())?;
+            table.set("key", key.as_str())?;
+            table.set("value", meta_value_to_lua(lua, value)?)?;
+        }
+        DomainEvent::Tag(TagEvent::MetadataRemoved { entity, key }) => {
+            table.set("entity", entity.to_string())?;
+            table.set("key", key.as_str())?;
+        }
+        DomainEvent::Scenario(ScenarioEvent::ScenarioEnded {
+            outcome,
+            reason,
+            tick,
+        }) => {
+            table.set("outcome", outcome.to_string())?;
+            table.set("reason", reason.as_str())?;
+            table.set("tick", *tick)?;
+        }
+        DomainEvent::Goal(GoalEvent::GoalProgressed {
+            goal,
+            progress,
+            target,
+        }) => {
+            table.set("goal", goal.as_str())?;
+            table.set("progress", *progress)?;
+            table.set("target", *target)?;
+        }
+        DomainEvent::Goal(GoalEvent::GoalCompleted { goal }) => {
+            table.set("goal", goal.as_str())?;
+        }
+        DomainEvent::Fog(FogEvent::TilesRevealed { viewer, tiles })
+        | DomainEvent::Fog(FogEvent::TilesHidden { viewer, tiles }) => {
+            table.set("viewer", viewer.as_str())?;
+            let tiles_table = lua.create_table_with_capacity(tiles.len(), 0)?;
+            for tile in tiles {
+                let tile_table = lua.create_table()?;
+                tile_table.set("x", tile.x)?;
+                tile_table.set("y", tile.y)?;
+                tiles_table.push(tile_table)?;
+            }
+            table.set("tiles", tiles_table)?;
+        }
+        DomainEvent::Faction(FactionEvent::FactionCreated { faction, kind }) => {
+            table.set("faction", faction.as_str())?;
+            // `kind` is the kind of the event already
+            table.set("faction_kind", kind.to_string())?;
+        }
+        DomainEvent::Faction(FactionEvent::ZoneCreated { zone, region }) => {
+            let (min, max) = region.corners();
+            table.set("zone", EntityRef::Zone(*zone).to_string())?;
+            table.set("min_x", min.x)?;
+            table.set("min_y", min.y)?;
+            table.set("max_x", max.x)?;
+            table.set("max_y", max.y)?;
+        }
+        DomainEvent::Faction(FactionEvent::OwnershipTransferred { entity, from, to }) => {
+            table.set("entity", entity.to_string())?;
+            table.set("from", from.as_deref())?;
+            table.set("to", to.as_deref())?;
+        }
+        DomainEvent::Task(TaskEvent::TaskStarted {
+            task,
+            person_id,
+            kind,
+        }) => {
+            table.set("task", task.0)?;
+            table.set("person_id", person_id.0)?;
+            // `kind` is the kind of the event already
+            table.set("task_kind", kind.as_str())?;
+        }
+        DomainEvent::Task(TaskEvent::TaskCompleted { task, person_id }) => {
+            table.set("task", task.0)?;
+            table.set("person_id", person_id.0)?;
+        }
+        DomainEvent::Task(TaskEvent::TaskAbandoned {
+            task,
+            person_id,
+            reason,
+        }) => {
+            table.set("task", task.0)?;
+            table.set("person_id", person_id.0)?;
+            table.set("reason", reason.as_str())?;
+        }
+        DomainEvent::Vehicle(VehicleEvent::VehicleCreated { vehicle, location })
+        | DomainEvent::Vehicle(VehicleEvent::VehicleArrived { vehicle, location })
+        | DomainEvent::Vehicle(VehicleEvent::VehicleStranded { vehicle, location }) => {
+            table.set("vehicle", vehicle.0)?;
+            table.set("x", location.x)?;
+            table.set("y", location.y)?;
+        }
+        DomainEvent::Vehicle(VehicleEvent::VehicleMoved {
+            vehicle,
+            from_location,
+            to_location,
+        }) => {
+            table.set("vehicle", vehicle.0)?;
+            table.set("from_x", from_location.x)?;
+            table.set("from_y", from_location.y)?;
+            table.set("x", to_location.x)?;
+            table.set("y", to_location.y)?;
+            table.set("map", to_location.map.as_str())?;
+            table.set("z", to_location.z)?;
+        }
+        DomainEvent::Vehicle(VehicleEvent::VehicleLoaded { vehicle, load })
+        | DomainEvent::Vehicle(VehicleEvent::VehicleUnloaded { vehicle, load }) => {
+            table.set("vehicle", vehicle.0)?;
+            match load {
+                Load::Passenger(person_id) => table.set("person_id", person_id.0)?,
+                Load::Item(item) => table.set("item", item.as_str())?,
+            }
+        }
+        DomainEvent::Portal(PortalEvent::PortalCreated {
+            portal,
+            from_location,
+            to_location,
+        }) => {
+            table.set("portal", portal.0)?;
+            table.set("from_x", from_location.x)?;
+            table.set("from_y", from_location.y)?;
+            table.set("x", to_location.x)?;
+            table.set("y", to_location.y)?;
+            table.set("map", to_location.map.as_str())?;
+            table.set("z", to_location.z)?;
+        }
+        DomainEvent::Portal(PortalEvent::PortalRemoved { portal }) => {
+            table.set("portal", portal.0)?;
+        }
+        DomainEvent::Portal(PortalEvent::PortalTraversed {
+            portal,
+            person_id,
+            from_location,
+            to_location,
+        }) => {
+            table.set("portal", portal.0)?;
+            table.set("person_id", person_id.0)?;
+            table.set("from_x", from_location.x)?;
+            table.set("from_y", from_location.y)?;
+            table.set("x", to_location.x)?;
+            table.set("y", to_location.y)?;
+            table.set("map", to_location.map.as_str())?;
+            table.set("z", to_location.z)?;
+        }
+        DomainEvent::World(WorldEvent::MapCreated { map }) => {
+            table.set("map", map.as_str())?;
+        }
+        DomainEvent::World(WorldEvent::MapSwitched { from, to }) => {
+            table.set("from", from.as_str())?;
+            table.set("to", to.as_str())?;
+        }
+        DomainEvent::World(WorldEvent::LevelSwitched { map, from, to }) => {
+            table.set("map", map.as_str())?;
+            table.set("from", *from)?;
+            table.set("to", *to)?;
+        }
+        DomainEvent::Group(GroupEvent::GroupCreated {
+            group,
+            kind,
+            name,
+            shared_inventory,
+        }) => {
+            table.set("group", group.0)?;
+            // `kind` is the kind of the event already
+            table.set("group_kind", kind.to_string())?;
+            table.set("name", name.as_str())?;
+            table.set("shared_inventory", *shared_inventory)?;
+        }
+        DomainEvent::Group(GroupEvent::GroupJoined { group, person_id })
+        | DomainEvent::Group(GroupEvent::GroupLeft { group, person_id }) => {
+            table.set("group", group.0)?;
+            table.set("person_id", person_id.0)?;
+        }
+        DomainEvent::Group(GroupEvent::GroupDisbanded { group }) => {
+            table.set("group", group.0)?;
+        }
+        DomainEvent::Group(GroupEvent::GroupItemStored { group, item })
+        | DomainEvent::Group(GroupEvent::GroupItemTaken { group, item }) => {
+            table.set("group", group.0)?;
+            table.set("item", item.as_str())?;
+        }
+        DomainEvent::Map(MapEvent::TilePlaced {
+            location,
+            layer,
+            tile,
+        }) => {
+            set_cell(&table, location, layer)?;
+            table.set("tile", *tile)?;
+        }
+        DomainEvent::Map(MapEvent::TileRemoved { location, layer }) => {
+            set_cell(&table, location, layer)?;
+        }
+        DomainEvent::Map(MapEvent::RegionLocked {
+            lock,
+            region,
+            owner,
+        }) => {
+            let (min, max) = region.corners();
+            table.set("lock", lock.0)?;
+            table.set("owner", owner.as_str())?;
+            table.set("min_x", min.x)?;
+            table.set("min_y", min.y)?;
+            table.set("max_x", max.x)?;
+            table.set("max_y", max.y)?;
+        }
+        DomainEvent::Map(MapEvent::RegionUnlocked { lock }) => {
+            table.set("lock", lock.0)?;
+        }
+        DomainEvent::Stream(StreamEvent::ChunkUnloaded { chunk, persons }) => {
+            set_chunk(&table, chunk)?;
+            let ids: Vec<u32> = persons.iter().map(|person| person.0).collect();
+            table.set("persons", ids)?;
+        }
+        DomainEvent::Stream(StreamEvent::ChunkLoaded { chunk, persons }) => {
+            set_chunk(&table, chunk)?;
+            let ids: Vec<u32> = persons.iter().map(|person| person.id.0).collect();
+            table.set("persons", ids)?;
+        }
+    }
+    Ok(table)
+}
+
+fn set_chunk(table: &Table, chunk: &Chunk) -> mlua::Result<()> {
+    let region = chunk.region();
+    let (min, max) = region.corners();
+    table.set("map", chunk.map.as_str())?;
+    table.set("z", chunk.z)?;
+    table.set("min_x", min.x)?;
+    table.set("min_y", min.y)?;
+    table.set("max_x", max.x)?;
+    table.set("max_y", max.y)
+}
+
+fn set_cell(table: &Table, location: &Location, layer: &str) -> mlua::Result<()> {
+    table.set("x", location.x)?;
+    table.set("y", location.y)?;
+    table.set("map", location.map.as_str())?;
+    table.set("z", location.z)?;
+    table.set("layer", layer)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lua_engine::LuaEngine;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_events_reach_their_handlers() {
+        let (_, command_rx) = std::sync::mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                created = nil
+                event_effects = {
+                    PersonCreated = function(event) created = event end,
+                    PersonMoved = function(event) error("moved") end,
+                }
+                local person = api.person.create("Ann", 3, 4)
+                api.person.move_to(person.id, 5, 6)
+                "#,
+            )
+            .unwrap();
+
+        // Events are published from the event store thread
+        let started = Instant::now();
+        while engine.error_log.is_empty() && started.elapsed() < Duration::from_secs(2) {
+            engine
+                .events
+                .dispatch(&engine.lua, &engine.notifications, &engine.tutorials);
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        let created: mlua::Table = engine.lua.globals().get("created").unwrap();
+        assert_eq!(created.get::<String>("name").unwrap(), "Ann");
+        assert_eq!(created.get::<i32>("x").unwrap(), 3);
+        assert_eq!(
+            engine.error_log.errors()[0].context,
+            "event_effects.PersonMoved"
+        );
+    }
+
+    #[test]
+    fn test_batched_moves_arrive_as_one_event() {
+        let (_, command_rx) = std::sync::mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                batches = {}
+                event_effects = {
+                    PersonsMoved = function(event) table.insert(batches, event.moves) end,
+                }
+                local ann = api.person.create("Ann", 0, 0)
+                local bob = api.person.create("Bob", 1, 1)
+                api.person.move_all({ { id = ann.id, x = 2, y = 0 }, { id = bob.id, x = 3, y = 1 } })
+                "#,
+            )
+            .unwrap();
+
+        let started = Instant::now();
+        let mut batches = 0;
+        while batches == 0 && started.elapsed() < Duration::from_secs(2) {
+            engine
+                .events
+                .dispatch(&engine.lua, &engine.notifications, &engine.tutorials);
+            batches = engine.lua.load("#batches").eval().unwrap();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        let (count, from_x, x): (usize, i32, i32) = engine
+            .lua
+            .load("return #batches[1], batches[1][2].from_x, batches[1][2].x")
+            .eval()
+            .unwrap();
+        assert_eq!((batches, count, from_x, x), (1, 2, 1, 3));
+    }
+}
@@ -0,0 +1,295 @@
+//! Export of the stored events for analysis outside the game, as `api.event.export`
+
+use crate::event_bridge::{event_kind, event_table};
+use logic::{CoreApi, DomainEvent};
+use mlua::{Lua, LuaSerdeExt, Table, Value};
+use serde_json::{Map, Value as Json};
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    JsonLines,
+    Csv,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "jsonl" | "json" => Some(Format::JsonLines),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+}
+
+// Reading the archived events back can fail as well as turning them into tables
+enum ExportError {
+    Lua(mlua::Error),
+    Archive(std::io::Error),
+}
+
+impl From<mlua::Error> for ExportError {
+    fn from(e: mlua::Error) -> Self {
+        ExportError::Lua(e)
+    }
+}
+
+impl From<std::io::Error> for ExportError {
+    fn from(e: std::io::Error) -> Self {
+        ExportError::Archive(e)
+    }
+}
+
+/// Add `export` to the `api.event` table. Every event is written with the fields the
+/// `event_effects` handlers get plus its `seq`, the position in the event log starting at 1. The
+/// format follows the file extension unless given as `format = "jsonl"` or `"csv"`, CSV leaves
+/// out events with lists like `PersonsMoved`:
+///
+/// ```lua
+/// api.event.export("run1.jsonl")
+/// api.event.export("moves.csv", { kinds = { "PersonMoved" } })
+/// ```
+pub(crate) fn setup_export_api(lua: &Lua, table: &Table, core: Arc<RwLock<CoreApi>>) {
+    // Returns the number of events written
+    let export = lua
+        .create_function(move |lua, (path, options): (String, Option<Table>)| {
+            let format = match options
+                .as_ref()
+                .map(|options| options.get::<Option<String>>("format"))
+                .transpose()?
+                .flatten()
+            {
+                Some(name) => Format::from_name(&name).ok_or_else(|| {
+                    mlua::Error::RuntimeError(format!(
+                        "Unknown export format '{}', expected jsonl or csv",
+                        name
+                    ))
+                })?,
+                None if path.ends_with(".csv") => Format::Csv,
+                None => Format::JsonLines,
+            };
+            let kinds: Option<Vec<String>> = options
+                .as_ref()
+                .map(|options| options.get("kinds"))
+                .transpose()?
+                .flatten();
+
+            let core = core.read().unwrap();
+            // Events still on their way to the store belong to the run as well
+            core.wait_for_projections(Duration::from_millis(100));
+            let mut events = Vec::new();
+            // Events dropped from memory without an archive are gone, the rest keep their place
+            let mut seq = core.event().memory().dropped;
+            core.event()
+                .try_for_each(|event| {
+                    seq += 1;
+                    if kinds
+                        .as_ref()
+                        .is_none_or(|kinds| kinds.iter().any(|kind| kind == event_kind(event)))
+                    {
+                        events.push(event_json(lua, seq, event)?);
+                    }
+                    Ok::<_, ExportError>(())
+                })
+                .map_err(|e| match e {
+                    ExportError::Lua(e) => e,
+                    ExportError::Archive(e) => mlua::Error::RuntimeError(format!(
+                        "Failed to read the event archive: {}",
+                        e
+                    )),
+                })?;
+            write_events(&path, format, &events)
+                .map_err(|e| mlua::Error::RuntimeError(format!("Failed to write {}: {}", path, e)))
+        })
+        .unwrap();
+    table.set("export", export).unwrap();
+}
+
+// The event as the handlers see it, with whole numbers written without a fraction
+fn event_json(lua: &Lua, seq: usize, event: &DomainEvent) -> mlua::Result<Map<String, Json>> {
+    let table = event_table(lua, event)?;
+    table.set("seq", seq)?;
+    match whole_numbers(lua.from_value(Value::Table(table))?) {
+        Json::Object(fields) => Ok(fields),
+        _ => unreachable!("events are tables"),
+    }
+}
+
+// Luau numbers are all floats
+pub(crate) fn whole_numbers(value: Json) -> Json {
+    match value {
+        Json::Number(number) => match number.as_f64() {
+            Some(n) if n.fract() == 0.0 && n.abs() < i64::MAX as f64 => Json::from(n as i64),
+            _ => Json::Number(number),
+        },
+        Json::Array(values) => Json::Array(values.into_iter().map(whole_numbers).collect()),
+        Json::Object(fields) => Json::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key, whole_numbers(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+fn write_events(
+    path: &str,
+    format: Format,
+    events: &[Map<String, Json>],
+) -> std::io::Result<usize> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let written = match format {
+        Format::JsonLines => {
+            for event in events {
+                serde_json::to_writer(&mut out, event)?;
+                out.write_all(b"\n")?;
+            }
+            events.len()
+        }
+        Format::Csv => write_csv(&mut out, events)?,
+    };
+    out.flush()?;
+    Ok(written)
+}
+
+fn write_csv(out: &mut impl Write, events: &[Map<String, Json>]) -> std::io::Result<usize> {
+    let flat: Vec<&Map<String, Json>> = events
+        .iter()
+        .filter(|event| {
+            event
+                .values()
+                .all(|value| !matches!(value, Json::Array(_) | Json::Object(_)))
+        })
+        .collect();
+
+    // seq and kind first, the other fields in alphabetical order
+    let mut columns = vec!["seq", "kind"];
+    let fields: BTreeSet<&str> = flat
+        .iter()
+        .flat_map(|event| event.keys().map(String::as_str))
+        .filter(|field| !columns.contains(field))
+        .collect();
+    columns.extend(fields);
+
+    writeln!(out, "{}", columns.join(","))?;
+    for event in &flat {
+        let row: Vec<String> = columns
+            .iter()
+            .map(|column| match event.get(*column) {
+                None | Some(Json::Null) => String::new(),
+                Some(Json::String(text)) => csv_field(text),
+                Some(value) => value.to_string(),
+            })
+            .collect();
+        writeln!(out, "{}", row.join(","))?;
+    }
+    Ok(flat.len())
+}
+
+// Quoted when it holds a separator, quote or line break, quotes doubled
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lua_engine::LuaEngine;
+    use std::fs;
+
+    fn export(engine: &mut LuaEngine, name: &str, options: &str) -> (usize, String) {
+        let path = std::env::temp_dir().join(format!("{}_{}", std::process::id(), name));
+        let path = path.to_str().unwrap().replace('\\', "/");
+        let written = engine
+            .lua
+            .load(format!("return api.event.export('{}', {})", path, options))
+            .eval()
+            .unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        (written, content)
+    }
+
+    #[test]
+    fn test_events_are_exported_as_json_lines() {
+        let (_, command_rx) = std::sync::mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                local ann = api.person.create("Ann", 3, 4)
+                api.person.move_to(ann.id, 5, 4)
+                "#,
+            )
+            .unwrap();
+
+        let (written, content) = export(&mut engine, "events.jsonl", "nil");
+        let lines: Vec<Json> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(written, 2);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["seq"], 1);
+        assert_eq!(lines[0]["kind"], "PersonCreated");
+        assert_eq!(lines[0]["x"], 3);
+        assert_eq!(lines[1]["kind"], "PersonMoved");
+        assert_eq!(lines[1]["from_x"], 3);
+    }
+
+    #[test]
+    fn test_csv_leaves_out_events_with_lists() {
+        let (_, command_rx) = std::sync::mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                local ann = api.person.create("Ann, the first", 0, 0)
+                api.person.move_all({ { id = ann.id, x = 1, y = 0 } })
+                "#,
+            )
+            .unwrap();
+
+        let (written, content) = export(&mut engine, "events.csv", "nil");
+
+        assert_eq!(written, 1);
+        assert_eq!(
+            content,
+            "seq,kind,name,person_id,x,y\n1,PersonCreated,\"Ann, the first\",0,0,0\n"
+        );
+    }
+
+    #[test]
+    fn test_export_picks_kinds() {
+        let (_, command_rx) = std::sync::mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                local ann = api.person.create("Ann", 0, 0)
+                api.person.move_to(ann.id, 1, 0)
+                api.person.move_to(ann.id, 2, 0)
+                "#,
+            )
+            .unwrap();
+
+        let (written, content) = export(
+            &mut engine,
+            "moves.txt",
+            "{ kinds = { 'PersonMoved' }, format = 'csv' }",
+        );
+
+        assert_eq!(written, 2);
+        assert!(content.starts_with("seq,kind,from_x,from_y,map,person_id,x,y,z\n2,PersonMoved"));
+    }
+}
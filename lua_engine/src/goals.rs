@@ -0,0 +1,162 @@
+//! Goals of the mods with their progress tracked on every tick
+
+use crate::error_log::ErrorLog;
+use logic::{CoreApi, Goal};
+use mlua::{Function, Lua, Table};
+use std::sync::{Arc, Mutex, RwLock};
+
+#[derive(Clone)]
+enum Progress {
+    Function(Function),
+    Metric(String),
+}
+
+/// Goals of the mods and how their progress is measured, as a function or a metric name. Changes
+/// of the progress become `GoalProgressed` events, reaching the target `GoalCompleted`:
+///
+/// ```lua
+/// api.goals.define("town", "Grow a town of 50", 50)
+/// api.goals.track("town", "persons")
+/// api.goals.track("fed", function() return #api.tags.find("fed") end)
+/// ```
+#[derive(Clone)]
+pub struct Goals {
+    tracked: Arc<Mutex<Vec<(String, Progress)>>>,
+    core: Arc<RwLock<CoreApi>>,
+    error_log: ErrorLog,
+}
+
+impl Goals {
+    pub(crate) fn install(lua: &Lua, core: Arc<RwLock<CoreApi>>, error_log: ErrorLog) -> Self {
+        let goals = Self {
+            tracked: Default::default(),
+            core,
+            error_log,
+        };
+
+        // Expose api.goals.track to Lua, a goal is measured by one function or metric
+        let goals_clone = goals.clone();
+        let track = lua
+            .create_function(move |_, (id, progress): (String, mlua::Value)| {
+                let progress = match progress {
+                    mlua::Value::Function(function) => Progress::Function(function),
+                    mlua::Value::String(metric) => Progress::Metric(metric.to_str()?.to_string()),
+                    value => {
+                        return Err(mlua::Error::RuntimeError(format!(
+                            "Goal {} needs a function or a metric name, got a {}",
+                            id,
+                            value.type_name()
+                        )));
+                    }
+                };
+                if goals_clone.core.read().unwrap().goals().get(&id).is_none() {
+                    return Err(mlua::Error::RuntimeError(format!(
+                        "Goal {} is not defined",
+                        id
+                    )));
+                }
+                let mut tracked = goals_clone.tracked.lock().unwrap();
+                tracked.retain(|(tracked_id, _)| *tracked_id != id);
+                tracked.push((id, progress));
+                Ok(())
+            })
+            .unwrap();
+        lua.globals()
+            .get::<Table>("api")
+            .and_then(|api| api.get::<Table>("goals"))
+            .and_then(|table| table.set("track", track))
+            .unwrap();
+
+        goals
+    }
+
+    /// Measure the progress of every tracked goal that isn't complete yet
+    pub(crate) fn update(&self) {
+        // Functions run without the lock, they may well track other goals
+        let tracked = self.tracked.lock().unwrap().clone();
+        for (id, progress) in tracked {
+            let done = self
+                .core
+                .read()
+                .unwrap()
+                .goals()
+                .get(&id)
+                .is_none_or(|goal| goal.completed);
+            if done {
+                continue;
+            }
+            let value = match &progress {
+                Progress::Metric(metric) => self.core.read().unwrap().metrics().latest(metric),
+                Progress::Function(function) => match function.call::<f64>(()) {
+                    Ok(value) => Some(value),
+                    Err(e) => {
+                        self.error_log.report(&format!("goal '{}'", id), e);
+                        None
+                    }
+                },
+            };
+            let Some(value) = value else {
+                continue;
+            };
+            if let Err(e) = self.core.read().unwrap().goals().progress(&id, value) {
+                self.error_log
+                    .report(&format!("goal '{}'", id), mlua::Error::RuntimeError(e));
+            }
+        }
+    }
+
+    /// All goals in the order they were defined, for the frontends to list
+    pub fn all(&self) -> Vec<Goal> {
+        self.core.read().unwrap().goals().all()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lua_engine::LuaEngine;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_tracked_goals_complete_from_their_progress() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                completed = {}
+                event_effects = { GoalCompleted = function(e) table.insert(completed, e.goal) end }
+                api.goals.define("pair", "Two persons", 2)
+                api.goals.track("pair", "persons")
+                api.goals.define("gold", "Collect gold", 10)
+                gold = 0
+                api.goals.track("gold", function() gold = gold + 4 return gold end)
+                api.person.create("Ann", 0, 0)
+                api.person.create("Bob", 1, 0)
+                "#,
+            )
+            .unwrap();
+        assert!(engine
+            .run_script("api.goals.track('none', 'persons')")
+            .is_err());
+
+        for frame in 1..=4 {
+            let core = engine.core.clone();
+            core.read()
+                .unwrap()
+                .wait_for_projections(Duration::from_millis(100));
+            engine.tick(0.1, frame);
+        }
+
+        let goals = engine.goals.all();
+        assert!(goals.iter().all(|goal| goal.completed), "{:?}", goals);
+        // Completed goals keep the first progress past their target
+        assert_eq!(goals[1].progress, 12.0);
+        let completed: String = engine
+            .lua
+            .load("table.concat(completed, ',')")
+            .eval()
+            .unwrap();
+        assert_eq!(completed, "pair,gold");
+    }
+}
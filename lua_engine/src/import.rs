@@ -0,0 +1,279 @@
+//! Seeding the world from files made outside the game, as `api.import`
+
+use logic::{CoreApi, MetaValue};
+use mlua::{Lua, Table};
+use serde_json::Value as Json;
+use std::fs;
+use std::sync::{Arc, RwLock};
+
+/// A person as read from the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedPerson {
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub attributes: Vec<(String, MetaValue)>,
+}
+
+/// Read the persons of a CSV or JSON file, by its extension
+pub fn read_persons(path: &str) -> Result<Vec<ImportedPerson>, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let persons = if path.ends_with(".json") {
+        persons_from_json(&content)
+    } else {
+        persons_from_csv(&content)
+    };
+    persons.map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Create the persons of the file with their attributes, returns their ids in file order
+pub fn import_persons(core: &CoreApi, path: &str) -> Result<Vec<u32>, String> {
+    let persons = read_persons(path)?;
+    let mut ids = Vec::with_capacity(persons.len());
+    for person in persons {
+        let id = core.person().create(person.name, person.x, person.y)?.id.0;
+        let entity = format!("person:{}", id);
+        for (key, value) in person.attributes {
+            core.meta().set(&entity, &key, value)?;
+        }
+        ids.push(id);
+    }
+    Ok(ids)
+}
+
+fn persons_from_csv(content: &str) -> Result<Vec<ImportedPerson>, String> {
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let Some((_, header)) = lines.next() else {
+        return Ok(Vec::new());
+    };
+    let columns = csv_fields(header)?;
+    let column = |name: &str| {
+        columns
+            .iter()
+            .position(|column| column == name)
+            .ok_or_else(|| format!("Missing the '{}' column", name))
+    };
+    let (name, x, y) = (column("name")?, column("x")?, column("y")?);
+
+    lines
+        .map(|(index, line)| {
+            let line_number = index + 1;
+            let fields = csv_fields(line).map_err(|e| format!("line {}: {}", line_number, e))?;
+            if fields.len() != columns.len() {
+                return Err(format!(
+                    "line {}: {} fields, the header has {}",
+                    line_number,
+                    fields.len(),
+                    columns.len()
+                ));
+            }
+            let coordinate = |index: usize| {
+                fields[index].trim().parse::<i32>().map_err(|_| {
+                    format!(
+                        "line {}: '{}' is no valid {}",
+                        line_number, fields[index], columns[index]
+                    )
+                })
+            };
+            Ok(ImportedPerson {
+                name: fields[name].clone(),
+                x: coordinate(x)?,
+                y: coordinate(y)?,
+                attributes: columns
+                    .iter()
+                    .zip(&fields)
+                    .enumerate()
+                    .filter(|(index, (_, value))| {
+                        ![name, x, y].contains(index) && !value.is_empty()
+                    })
+                    .map(|(_, (column, value))| (column.clone(), csv_value(value)))
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+// Split a CSV line, fields in double quotes may hold commas and doubled quotes
+fn csv_fields(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("Unterminated quote".to_string());
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+fn csv_value(value: &str) -> MetaValue {
+    match value {
+        "true" => MetaValue::Boolean(true),
+        "false" => MetaValue::Boolean(false),
+        _ => value
+            .parse()
+            .map(MetaValue::Number)
+            .unwrap_or_else(|_| MetaValue::Text(value.to_string())),
+    }
+}
+
+fn persons_from_json(content: &str) -> Result<Vec<ImportedPerson>, String> {
+    let value: Json = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let Json::Array(entries) = value else {
+        return Err("Expected a list of persons".to_string());
+    };
+    entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let Json::Object(mut fields) = entry else {
+                return Err(format!("person {}: expected an object", index + 1));
+            };
+            let name = match fields.remove("name") {
+                Some(Json::String(name)) => name,
+                _ => return Err(format!("person {}: 'name' must be a string", index + 1)),
+            };
+            let mut coordinate = |field: &str| {
+                fields
+                    .remove(field)
+                    .and_then(|value| value.as_i64())
+                    .and_then(|value| i32::try_from(value).ok())
+                    .ok_or_else(|| {
+                        format!("person {}: '{}' must be a whole number", index + 1, field)
+                    })
+            };
+            let (x, y) = (coordinate("x")?, coordinate("y")?);
+            let attributes = fields
+                .into_iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        Json::Bool(value) => MetaValue::Boolean(value),
+                        Json::Number(value) => MetaValue::Number(value.as_f64().unwrap_or(0.0)),
+                        Json::String(value) => MetaValue::Text(value),
+                        _ => {
+                            return Err(format!(
+                                "person {}: '{}' must be a boolean, number or string",
+                                index + 1,
+                                key
+                            ))
+                        }
+                    };
+                    Ok((key, value))
+                })
+                .collect::<Result<_, String>>()?;
+            Ok(ImportedPerson {
+                name,
+                x,
+                y,
+                attributes,
+            })
+        })
+        .collect()
+}
+
+/// Fill the `api.import` table. Persons come as CSV with a header row or as a JSON list of
+/// objects, both with `name`, `x` and `y`, every other column becomes a metadata entry of the
+/// person. A broken row creates nobody:
+///
+/// ```lua
+/// local ids = api.import.persons("colonists.csv")
+/// print(api.meta.get("person:" .. ids[1], "age"))
+/// ```
+pub(crate) fn setup_import_api(lua: &Lua, table: &Table, core: Arc<RwLock<CoreApi>>) {
+    // Returns the ids of the persons created, in file order
+    let persons = lua
+        .create_function(move |_, path: String| {
+            import_persons(&core.read().unwrap(), &path).map_err(mlua::Error::RuntimeError)
+        })
+        .unwrap();
+    table.set("persons", persons).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lua_engine::LuaEngine;
+
+    #[test]
+    fn test_csv_columns_besides_name_and_position_are_attributes() {
+        let persons = persons_from_csv(
+            "name,x,y,age,role,leader\n\"Ann, the first\",1,2,34,pilot,true\nBob,3,4,,\"say \"\"hi\"\"\",false\n",
+        )
+        .unwrap();
+
+        assert_eq!(persons.len(), 2);
+        assert_eq!(persons[0].name, "Ann, the first");
+        assert_eq!((persons[0].x, persons[0].y), (1, 2));
+        assert_eq!(
+            persons[0].attributes,
+            [
+                ("age".to_string(), MetaValue::Number(34.0)),
+                ("role".to_string(), MetaValue::Text("pilot".to_string())),
+                ("leader".to_string(), MetaValue::Boolean(true)),
+            ]
+        );
+        // Empty fields are left out
+        assert_eq!(persons[1].attributes.len(), 2);
+        assert_eq!(
+            persons[1].attributes[0].1,
+            MetaValue::Text("say \"hi\"".to_string())
+        );
+    }
+
+    #[test]
+    fn test_broken_rows_are_reported_with_their_line() {
+        assert_eq!(
+            persons_from_csv("name,x\nAnn,1\n").unwrap_err(),
+            "Missing the 'y' column"
+        );
+        assert_eq!(
+            persons_from_csv("name,x,y\nAnn,1,2\nBob,one,2\n").unwrap_err(),
+            "line 3: 'one' is no valid x"
+        );
+        assert!(persons_from_csv("name,x,y\nAnn,1\n").is_err());
+        assert!(persons_from_json(r#"[{ "name": "Ann", "x": 1.5, "y": 0 }]"#).is_err());
+    }
+
+    #[test]
+    fn test_imported_persons_are_created_with_their_attributes() {
+        let path = std::env::temp_dir().join(format!("persons_{}.json", std::process::id()));
+        fs::write(
+            &path,
+            r#"[{ "name": "Ann", "x": 1, "y": 2, "age": 34 }, { "name": "Bob", "x": 3, "y": 4 }]"#,
+        )
+        .unwrap();
+        let (_, command_rx) = std::sync::mpsc::channel();
+        let engine = LuaEngine::new(command_rx);
+
+        let (count, name, age): (usize, String, f64) = engine
+            .lua
+            .load(format!(
+                r#"
+                local ids = api.import.persons("{}")
+                return #ids, api.person.get(ids[2]).name, api.meta.get("person:" .. ids[1], "age")
+                "#,
+                path.to_str().unwrap().replace('\\', "/")
+            ))
+            .eval()
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((count, name.as_str(), age), (2, "Bob", 34.0));
+    }
+}
@@ -1,7 +1,57 @@
+pub mod actions;
+pub mod ai_director;
+pub mod api_reference;
+pub mod clock;
+pub mod color;
+#[cfg(unix)]
+pub mod command_socket;
+pub mod commands;
+pub mod consistency;
+pub mod debugger;
+pub mod deprecation;
+pub mod determinism;
+pub mod dev_script;
 mod docs;
+pub mod error_log;
+pub mod event_bridge;
+mod event_export;
+pub mod goals;
+pub mod import;
+pub mod lifecycle;
 pub mod lua_client;
 pub mod lua_engine;
+pub mod mod_budget;
+pub mod mod_settings;
+pub mod notifications;
+pub mod overlay;
+pub mod params;
+pub mod permissions;
+#[cfg(feature = "plugins")]
+pub mod plugins;
+mod query;
+mod read_models;
+pub mod routines;
+pub mod save_diff;
+pub mod scenario;
+pub mod script_args;
+pub mod script_error;
+pub mod text_format;
+pub mod timers;
+pub mod triggers;
+pub mod tutorial;
+#[cfg(feature = "wasm")]
+pub mod wasm_mods;
+
+// Read by the frontends, so they don't need the logic crate
+pub use logic::{
+    Chunk, Chunks, Codec, Factions, Fog, FsyncPolicy, MapEdits, MapId, Metrics, Portals,
+    Projections, RebuildProgress, Recovery, Replay, ReplayState, RetentionPolicy, Snapshots,
+    TileChange, Vehicles, Visibility, World, WorldSnapshot, CHUNK_SIZE, MAIN_MAP, PLAYER_VIEWER,
+};
 
 // Re-export needed mlua types
 pub use mlua::prelude::LuaValue;
-pub use mlua::{Error as LuaError, Function as LuaFunction, Result as LuaResult, Table, Value};
+pub use mlua::{
+    Error as LuaError, Function as LuaFunction, IntoLuaMulti, Lua, Result as LuaResult,
+    String as LuaString, Table, Value,
+};
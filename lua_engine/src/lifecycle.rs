@@ -0,0 +1,350 @@
+use crate::error_log::ErrorLog;
+use crate::mod_budget::ModBudget;
+use crate::mod_settings::ModSettings;
+use mlua::{Function, IntoLuaMulti, Lua, LuaSerdeExt, Table, Value};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Lifecycle hooks the engine calls on the mods that registered them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    /// After all scripts were loaded
+    Init,
+    /// Once per tick, with the seconds since the previous tick and the frame number
+    Frame,
+    /// Before the application exits
+    Shutdown,
+    /// Before the game is saved, with the save path. What the mod returns is stored in the save,
+    /// see `LifecycleHooks::save_state`.
+    Save,
+    /// After a saved game was loaded, with the save path and what the mod returned when saving
+    Load,
+    /// Once per tick for every AI faction, with the faction and its commands, see `ai_director`
+    AiTick,
+}
+
+impl Hook {
+    pub const ALL: [Hook; 6] = [
+        Hook::Init,
+        Hook::Frame,
+        Hook::Shutdown,
+        Hook::Save,
+        Hook::Load,
+        Hook::AiTick,
+    ];
+
+    /// Name of the hook function in the table passed to `mods.register`
+    pub fn name(self) -> &'static str {
+        match self {
+            Hook::Init => "on_init",
+            Hook::Frame => "on_frame",
+            Hook::Shutdown => "on_shutdown",
+            Hook::Save => "on_save",
+            Hook::Load => "on_load",
+            Hook::AiTick => "on_ai_tick",
+        }
+    }
+}
+
+// Keys of the table passed to `mods.register` besides the hooks
+const MANIFEST_KEYS: [&str; 4] = ["settings", "backend", "module", "limits"];
+
+// Backends mods can select with `backend`
+#[cfg(feature = "wasm")]
+const BACKENDS: [&str; 2] = ["lua", "wasm"];
+#[cfg(not(feature = "wasm"))]
+const BACKENDS: [&str; 1] = ["lua"];
+
+// Hooks of one mod, in the order of `Hook::ALL`
+struct ModHooks {
+    name: String,
+    hooks: Vec<(Hook, Function)>,
+}
+
+/// Mods registered through the Lua `mods` global, called from any thread without the engine lock
+#[derive(Clone)]
+pub struct LifecycleHooks {
+    mods: Arc<Mutex<Vec<ModHooks>>>,
+    error_log: ErrorLog,
+    // Takes the `settings` a mod registers with
+    settings: ModSettings,
+    // Times the hooks for their mods
+    budget: ModBudget,
+}
+
+impl LifecycleHooks {
+    pub(crate) fn install(
+        lua: &Lua,
+        error_log: ErrorLog,
+        settings: ModSettings,
+        budget: ModBudget,
+    ) -> Self {
+        let lifecycle = Self {
+            mods: Default::default(),
+            error_log,
+            settings,
+            budget,
+        };
+
+        let table = lua.create_table().unwrap();
+        {
+            let lifecycle = lifecycle.clone();
+            lua.create_function(move |lua, (name, hooks): (String, Table)| {
+                lifecycle.register(lua, name, hooks)
+            })
+            .and_then(|f| table.set("register", f))
+            .unwrap();
+        }
+        {
+            let lifecycle = lifecycle.clone();
+            lua.create_function(move |_, ()| Ok(lifecycle.mod_names()))
+                .and_then(|f| table.set("list", f))
+                .unwrap();
+        }
+        lua.globals().set("mods", table).unwrap();
+
+        lifecycle
+    }
+
+    // Registering a mod again replaces its hooks, so scripts can be reloaded. Besides the hooks
+    // a mod may declare its `settings`, see `mod_settings`, and run untrusted code as a
+    // WebAssembly `module` with `backend = "wasm"` and its `limits`, see `wasm_mods`.
+    fn register(&self, lua: &Lua, name: String, hooks: Table) -> mlua::Result<()> {
+        for pair in hooks.pairs::<Value, Value>() {
+            let (key, _) = pair?;
+            let key = key.to_string()?;
+            if !MANIFEST_KEYS.contains(&key.as_str())
+                && !Hook::ALL.iter().any(|hook| hook.name() == key)
+            {
+                return Err(mlua::Error::RuntimeError(format!(
+                    "Unknown hook '{}' in mod '{}', expected {} or one of: {}",
+                    key,
+                    name,
+                    MANIFEST_KEYS.join(", "),
+                    Hook::ALL.map(Hook::name).join(", ")
+                )));
+            }
+        }
+        if let Some(settings) = hooks.get::<Option<Table>>("settings")? {
+            self.settings.declare(&name, settings)?;
+        }
+
+        let mod_hooks = match hooks.get::<Option<String>>("backend")?.as_deref() {
+            None | Some("lua") => {
+                let mut mod_hooks = Vec::new();
+                for hook in Hook::ALL {
+                    if let Some(function) = hooks.get::<Option<Function>>(hook.name())? {
+                        mod_hooks.push((hook, function));
+                    }
+                }
+                mod_hooks
+            }
+            #[cfg(feature = "wasm")]
+            Some("wasm") => {
+                if Hook::ALL
+                    .iter()
+                    .any(|hook| hooks.contains_key(hook.name()).unwrap_or(false))
+                {
+                    return Err(mlua::Error::RuntimeError(format!(
+                        "WebAssembly mod '{}' takes its hooks from its module",
+                        name
+                    )));
+                }
+                crate::wasm_mods::hooks(lua, &name, &hooks)?
+            }
+            Some(backend) => {
+                return Err(mlua::Error::RuntimeError(format!(
+                    "Unknown backend '{}' of mod '{}', this build supports: {}",
+                    backend,
+                    name,
+                    BACKENDS.join(", ")
+                )));
+            }
+        };
+
+        let mut mods = self.mods.lock().unwrap();
+        let hooks = ModHooks {
+            name: name.clone(),
+            hooks: mod_hooks,
+        };
+        match mods.iter_mut().find(|registered| registered.name == name) {
+            Some(registered) => *registered = hooks,
+            None => mods.push(hooks),
+        }
+        Ok(())
+    }
+
+    /// Names of the registered mods, in registration order
+    pub fn mod_names(&self) -> Vec<String> {
+        self.mods
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|registered| registered.name.clone())
+            .collect()
+    }
+
+    /// Whether any mod defines the hook
+    pub fn has(&self, hook: Hook) -> bool {
+        self.mods
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|registered| registered.hooks.iter().any(|(h, _)| *h == hook))
+    }
+
+    // Clone the functions so hooks can register mods while being called
+    fn functions(&self, hook: Hook) -> Vec<(String, Function)> {
+        self.mods
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|registered| {
+                registered
+                    .hooks
+                    .iter()
+                    .filter(|(registered_hook, _)| *registered_hook == hook)
+                    .map(|(_, function)| (registered.name.clone(), function.clone()))
+            })
+            .collect()
+    }
+
+    /// Call the hook of every mod defining it, errors go to the error log
+    pub fn call(&self, hook: Hook, args: impl IntoLuaMulti + Clone) {
+        for (mod_name, function) in self.functions(hook) {
+            let called = self
+                .budget
+                .measure(Some(&mod_name), || function.call::<()>(args.clone()));
+            if let Err(e) = called {
+                self.error_log
+                    .report(&format!("{} of mod '{}'", hook.name(), mod_name), e);
+            }
+        }
+    }
+
+    /// Call `on_save` of every mod defining it. What they return, tables of plain values, is
+    /// their state to store in the save, by mod name. Mods returning nothing store nothing.
+    pub fn save_state(&self, path: &str) -> BTreeMap<String, serde_json::Value> {
+        let mut state = BTreeMap::new();
+        for (mod_name, function) in self.functions(Hook::Save) {
+            let saved = self
+                .budget
+                .measure(Some(&mod_name), || function.call::<Value>(path))
+                .and_then(|value| serde_json::to_value(&value).map_err(mlua::Error::external));
+            match saved {
+                Ok(serde_json::Value::Null) => {}
+                Ok(saved) => {
+                    state.insert(mod_name, saved);
+                }
+                Err(e) => self
+                    .error_log
+                    .report(&format!("on_save of mod '{}'", mod_name), e),
+            }
+        }
+        state
+    }
+
+    /// Call `on_load` of every mod defining it with the save path and the state it stored, nil
+    /// for mods that stored none
+    pub fn load_state(&self, lua: &Lua, path: &str, state: &BTreeMap<String, serde_json::Value>) {
+        for (mod_name, function) in self.functions(Hook::Load) {
+            let saved = state
+                .get(&mod_name)
+                .map(|saved| lua.to_value(saved))
+                .unwrap_or(Ok(Value::Nil));
+            let loaded = saved.and_then(|saved| {
+                self.budget
+                    .measure(Some(&mod_name), || function.call::<()>((path, saved)))
+            });
+            if let Err(e) = loaded {
+                self.error_log
+                    .report(&format!("on_load of mod '{}'", mod_name), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hooks_are_called_per_mod() {
+        let lua = Lua::new();
+        let error_log = ErrorLog::default();
+        let hooks = LifecycleHooks::install(
+            &lua,
+            error_log.clone(),
+            ModSettings::default(),
+            ModBudget::default(),
+        );
+        lua.load(
+            r#"
+            frames = 0
+            mods.register("a", { on_frame = function(dt) frames = frames + dt end })
+            mods.register("b", { on_frame = function(dt) frames = frames + dt end, on_init = function() error("broken") end })
+            "#,
+        )
+        .exec()
+        .unwrap();
+
+        hooks.call(Hook::Frame, 0.5);
+        assert_eq!(lua.globals().get::<f64>("frames").unwrap(), 1.0);
+        assert_eq!(hooks.mod_names(), vec!["a", "b"]);
+
+        hooks.call(Hook::Init, ());
+        assert_eq!(error_log.errors()[0].context, "on_init of mod 'b'");
+    }
+
+    #[test]
+    fn test_mod_state_survives_saving_and_loading() {
+        let lua = Lua::new();
+        let error_log = ErrorLog::default();
+        let hooks = LifecycleHooks::install(
+            &lua,
+            error_log.clone(),
+            ModSettings::default(),
+            ModBudget::default(),
+        );
+        lua.load(
+            r#"
+            quests = { done = { "bridge" }, gold = 12 }
+            mods.register("quests", {
+                on_save = function(path) return quests end,
+                on_load = function(path, state) loaded = state end,
+            })
+            mods.register("stateless", { on_save = function() end })
+            mods.register("broken", { on_save = function() return { print } end })
+            "#,
+        )
+        .exec()
+        .unwrap();
+
+        let state = hooks.save_state("map.json");
+        assert_eq!(state.keys().collect::<Vec<_>>(), vec!["quests"]);
+        assert_eq!(error_log.errors()[0].context, "on_save of mod 'broken'");
+
+        lua.load("quests = nil").exec().unwrap();
+        hooks.load_state(&lua, "map.json", &state);
+        let loaded: String = lua
+            .load("return loaded.done[1] .. ' ' .. loaded.gold")
+            .eval()
+            .unwrap();
+        assert_eq!(loaded, "bridge 12");
+    }
+
+    #[test]
+    fn test_register_rejects_unknown_hooks() {
+        let lua = Lua::new();
+        LifecycleHooks::install(
+            &lua,
+            ErrorLog::default(),
+            ModSettings::default(),
+            ModBudget::default(),
+        );
+        let result = lua
+            .load(r#"mods.register("a", { on_tick = function() end })"#)
+            .exec();
+        assert!(result.unwrap_err().to_string().contains("on_tick"));
+    }
+}
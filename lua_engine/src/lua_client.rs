@@ -1,4 +1,5 @@
 use crate::lua_engine::LuaCommand;
+use crate::script_error::ScriptError;
 use std::sync::mpsc;
 
 pub struct LuaClient {
@@ -10,7 +11,7 @@ impl LuaClient {
         Self { command_tx }
     }
 
-    pub fn execute_non_blocking(&self, code: &str) -> mpsc::Receiver<Result<String, String>> {
+    pub fn execute_non_blocking(&self, code: &str) -> mpsc::Receiver<Result<String, ScriptError>> {
         let (response_tx, response_rx) = mpsc::channel();
 
         self.command_tx
@@ -23,4 +24,93 @@ impl LuaClient {
         // Return the receiver immediately without waiting
         response_rx
     }
+
+    pub fn run_file_non_blocking(&self, path: &str) -> mpsc::Receiver<Result<(), ScriptError>> {
+        let (response_tx, response_rx) = mpsc::channel();
+        self.command_tx
+            .send(LuaCommand::RunFile {
+                path: path.to_string(),
+                response_tx,
+            })
+            .unwrap();
+        response_rx
+    }
+}
+
+/// Sends a `LuaCommand::Tick` per frame, at most every `min_interval` seconds and only once the
+/// previous tick was handled, so a fast frontend or a busy engine doesn't pile up ticks
+pub struct FrameTicker {
+    min_interval: f32,
+    // Time of the frames not sent yet, passed on with the next tick
+    pending_dt: f32,
+    frame: u64,
+    in_flight: Option<mpsc::Receiver<()>>,
+}
+
+impl FrameTicker {
+    pub fn new(min_interval: f32) -> Self {
+        Self {
+            min_interval,
+            pending_dt: 0.0,
+            frame: 0,
+            in_flight: None,
+        }
+    }
+
+    /// Call once per frame with the frame time
+    pub fn update(&mut self, client: &LuaClient, dt: f32) {
+        self.frame += 1;
+        self.pending_dt += dt;
+        if let Some(done_rx) = &self.in_flight
+            && done_rx.try_recv() == Err(mpsc::TryRecvError::Empty)
+        {
+            return;
+        }
+        if self.pending_dt < self.min_interval {
+            return;
+        }
+
+        let (done_tx, done_rx) = mpsc::channel();
+        let tick = LuaCommand::Tick {
+            dt: self.pending_dt,
+            frame: self.frame,
+            done_tx,
+        };
+        // The engine is gone when sending fails, nothing left to tick
+        if client.command_tx.send(tick).is_ok() {
+            self.in_flight = Some(done_rx);
+            self.pending_dt = 0.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ticks_wait_for_the_engine() {
+        let (command_tx, command_rx) = mpsc::channel();
+        let client = LuaClient::new(command_tx);
+        let mut ticker = FrameTicker::new(0.1);
+
+        // Frames below the interval are collected into one tick
+        ticker.update(&client, 0.05);
+        ticker.update(&client, 0.05);
+        let Ok(LuaCommand::Tick { dt, frame, done_tx }) = command_rx.try_recv() else {
+            panic!("expected a tick");
+        };
+        assert_eq!((dt, frame), (0.1, 2));
+
+        // Nothing is sent while the engine is still busy with the last tick
+        ticker.update(&client, 0.2);
+        assert!(command_rx.try_recv().is_err());
+
+        done_tx.send(()).unwrap();
+        ticker.update(&client, 0.2);
+        let Ok(LuaCommand::Tick { dt, frame, .. }) = command_rx.try_recv() else {
+            panic!("expected a tick");
+        };
+        assert_eq!((dt, frame), (0.4, 4));
+    }
 }
@@ -1,14 +1,78 @@
+use crate::ai_director::AiDirector;
+use crate::clock::Clock;
+use crate::color;
+use crate::commands::Commands;
+use crate::consistency::ConsistencyWatch;
+use crate::debugger::Debugger;
+use crate::deprecation::Deprecations;
 use crate::docs;
-use logic::CoreApi;
-use mlua::{Function, Lua, Result as LuaResult, Table, Value};
+use crate::error_log::ErrorLog;
+use crate::event_bridge::EventBridge;
+use crate::event_export::setup_export_api;
+use crate::goals::Goals;
+use crate::import::setup_import_api;
+use crate::lifecycle::{Hook, LifecycleHooks};
+use crate::mod_budget::ModBudget;
+use crate::mod_settings::ModSettings;
+use crate::notifications::Notifications;
+use crate::overlay::Overlay;
+use crate::params::Params;
+use crate::permissions::{Permissions, MUTATING_API};
+#[cfg(feature = "plugins")]
+use crate::plugins::Plugins;
+use crate::query::setup_query_api;
+use crate::read_models;
+use crate::routines::Routines;
+use crate::scenario::Scenario;
+use crate::script_args;
+use crate::script_error::ScriptError;
+use crate::text_format::setup_fmt_api;
+use crate::timers::Timers;
+use crate::triggers::Triggers;
+use crate::tutorial::Tutorials;
+use dto::{
+    ChunkDto, FactionDto, GoalDto, GroupDto, MapLockDto, PersonDto, PortalDto, TaskDto, VehicleDto,
+};
+use logic::{
+    Chunks, CoreApi, Factions, Fog, MapEdits, MetaValue, Metrics, Portals, Projections, Recovery,
+    RetentionPolicy, Snapshots, Vehicles, World, PLAYER_VIEWER,
+};
+use mlua::{Function, Lua, LuaSerdeExt, MultiValue, Result as LuaResult, Table, Value};
 use std::collections::HashMap;
 use std::sync::{mpsc, Arc, RwLock};
+use std::time::Duration;
+
+/// Version of the `api` modules, older versions stay reachable as `api.v1`, `api.v2`, ...
+pub const API_VERSION: u32 = 1;
+
+// Functions renamed since an earlier API version as (module, old name, new name), still
+// callable under the old name with a deprecation warning
+const RENAMED_API: &[(&str, &str, &str)] = &[];
+
+// Message handler for xpcall turning an error into a table with its message and traceback
+const TRACEBACK_HANDLER: &str = r#"
+return function(message)
+    return { message = tostring(message), traceback = debug.traceback(nil, 2) }
+end
+"#;
 
 // Commands that can be sent to the Lua worker
 pub enum LuaCommand {
     Execute {
         code: String,
-        response_tx: mpsc::Sender<Result<String, String>>,
+        response_tx: mpsc::Sender<Result<String, ScriptError>>,
+    },
+    /// Run a script file, see `LuaEngine::run_file`
+    RunFile {
+        path: String,
+        response_tx: mpsc::Sender<Result<(), ScriptError>>,
+    },
+    /// Sent by the frontends once per frame, see `FrameTicker`
+    Tick {
+        /// Seconds since the previous tick
+        dt: f32,
+        frame: u64,
+        done_tx: mpsc::Sender<()>,
     },
     Shutdown,
 }
@@ -20,9 +84,78 @@ pub enum LuaResponse {
 
 pub struct LuaEngine {
     pub lua: Lua,
+    pub debugger: Debugger,
+    /// Errors of handlers and callbacks running outside of the console
+    pub error_log: ErrorLog,
+    /// Lifecycle hooks of the mods, see `mods.register`
+    pub hooks: LifecycleHooks,
+    /// Settings the mods declared, for the frontends to show, see `api.settings`
+    pub settings: ModSettings,
+    /// Commands of the scripts, for the command palettes of the frontends, see `api.commands`
+    pub commands: Commands,
+    /// Simulation parameters the frontends bind sliders to, see `api.params`
+    pub params: Params,
+    /// Timers of the `timer` global, advanced on every tick
+    pub timers: Timers,
+    /// Lua time of the mods in the last frames, for the frontends to show
+    pub budget: ModBudget,
+    /// Real time or turns, see `api.time`
+    pub clock: Clock,
+    /// Daily routines switching the tasks of the persons, see `api.routine`
+    pub routines: Routines,
+    /// Spectator mode, denying the scripts the functions that change the world
+    pub permissions: Permissions,
+    /// Renamed and removed API functions used by the scripts
+    pub deprecations: Deprecations,
+    /// World snapshot taken after every tick, for the frontends to draw from
+    pub snapshots: Snapshots,
+    /// Metrics sampled every tick, for the frontends to plot
+    pub metrics: Metrics,
+    /// Rebuild progress of the projections, for the frontends to show while they load
+    pub projections: Projections,
+    /// What the viewers explored of the map, for the frontends to draw the fog of
+    pub fog: Fog,
+    /// Factions and the zones they own, for the frontends to draw in their colors
+    pub factions: Factions,
+    /// Vehicles for the frontends to draw, which also tell them where the roads are
+    pub vehicles: Vehicles,
+    /// Portals for the frontends to draw the ends of
+    pub portals: Portals,
+    /// Edits of the tiles, for the frontends to apply to their maps and publish theirs through
+    pub map_edits: MapEdits,
+    /// Chunks unloaded to disk, for the frontends streaming the parts of the map their cameras
+    /// left
+    pub chunks: Chunks,
+    /// The map the frontends show, see `api.world`
+    pub world: World,
+    /// End conditions and result of the scenario, see the `scenario` global
+    pub scenario: Scenario,
+    /// Goals of the mods, see `api.goals`
+    pub goals: Goals,
+    /// Toasts and event log of the `notify` global
+    pub notifications: Notifications,
+    /// Tutorials of the `tutorial` global, for the frontends to show and tell the actions
+    pub tutorials: Tutorials,
+    /// Values of the `overlay` global, for the frontends to draw above the tiles
+    pub overlay: Overlay,
+    /// Zones of the `triggers` global, checked for persons stepping in or out on every tick
+    pub triggers: Triggers,
+    /// Runs the `on_ai_tick` hook of the AI factions
+    pub ai: AiDirector,
+    /// Checks of the projections against the persons, see `api.debug.watch`
+    pub consistency: ConsistencyWatch,
+    // Domain events waiting for the next tick to be handed to the scripts
+    pub(crate) events: EventBridge,
+    // Captured at startup so scripts replacing the globals don't break error reporting
+    xpcall: Function,
+    traceback_handler: Function,
     callbacks: HashMap<u32, Function>,
     next_callback_id: u32,
     command_rx: mpsc::Receiver<LuaCommand>,
+    pub(crate) core: Arc<RwLock<CoreApi>>,
+    // Last, so the Lua functions of the plugins are gone before their libraries are closed
+    #[cfg(feature = "plugins")]
+    pub plugins: Plugins,
 }
 
 impl LuaEngine {
@@ -38,34 +171,264 @@ impl LuaEngine {
         let person_table = lua.create_table().unwrap();
         let location_table = lua.create_table().unwrap();
         let event_table = lua.create_table().unwrap();
+        let tags_table = lua.create_table().unwrap();
+        let meta_table = lua.create_table().unwrap();
+        let query_table = lua.create_table().unwrap();
+        let metrics_table = lua.create_table().unwrap();
+        let goals_table = lua.create_table().unwrap();
+        let fog_table = lua.create_table().unwrap();
+        let faction_table = lua.create_table().unwrap();
+        let task_table = lua.create_table().unwrap();
+        let vehicle_table = lua.create_table().unwrap();
+        let portal_table = lua.create_table().unwrap();
+        let group_table = lua.create_table().unwrap();
+        let map_table = lua.create_table().unwrap();
+        let stream_table = lua.create_table().unwrap();
+        let world_table = lua.create_table().unwrap();
+        let debug_table = lua.create_table().unwrap();
+        let import_table = lua.create_table().unwrap();
+        let settings_table = lua.create_table().unwrap();
+        let commands_table = lua.create_table().unwrap();
+        let fmt_table = lua.create_table().unwrap();
 
         // Setup the APIs
         Self::setup_person_api(&lua, &person_table, Arc::clone(&core));
         Self::setup_location_api(&lua, &location_table, Arc::clone(&core));
         Self::setup_event_api(&lua, &event_table, Arc::clone(&core));
+        Self::setup_tags_api(&lua, &tags_table, Arc::clone(&core));
+        Self::setup_meta_api(&lua, &meta_table, Arc::clone(&core));
+        setup_query_api(&lua, &query_table, Arc::clone(&core));
+        Self::setup_metrics_api(&lua, &metrics_table, Arc::clone(&core));
+        Self::setup_goals_api(&lua, &goals_table, Arc::clone(&core));
+        Self::setup_fog_api(&lua, &fog_table, Arc::clone(&core));
+        Self::setup_faction_api(&lua, &faction_table, Arc::clone(&core));
+        Self::setup_task_api(&lua, &task_table, Arc::clone(&core));
+        Self::setup_vehicle_api(&lua, &vehicle_table, Arc::clone(&core));
+        Self::setup_portal_api(&lua, &portal_table, Arc::clone(&core));
+        Self::setup_group_api(&lua, &group_table, Arc::clone(&core));
+        Self::setup_map_api(&lua, &map_table, Arc::clone(&core));
+        Self::setup_stream_api(&lua, &stream_table, Arc::clone(&core));
+        Self::setup_world_api(&lua, &world_table, Arc::clone(&core));
+        Self::setup_debug_api(&lua, &debug_table, Arc::clone(&core));
+        setup_import_api(&lua, &import_table, Arc::clone(&core));
+        setup_fmt_api(&lua, &fmt_table);
 
-        // Create main API table
+        // Create main API table, the unversioned modules are the ones of the latest version
         let api_table = lua.create_table().unwrap();
-        api_table.set("person", person_table).unwrap();
-        api_table.set("location", location_table).unwrap();
-        api_table.set("event", event_table).unwrap();
+        let latest = lua.create_table().unwrap();
+        for (name, module) in [
+            ("person", person_table),
+            ("location", location_table),
+            ("event", event_table),
+            ("tags", tags_table),
+            ("meta", meta_table),
+            ("query", query_table),
+            ("metrics", metrics_table),
+            ("goals", goals_table),
+            ("fog", fog_table),
+            ("faction", faction_table),
+            ("task", task_table),
+            ("vehicle", vehicle_table),
+            ("portal", portal_table),
+            ("group", group_table),
+            ("map", map_table),
+            ("stream", stream_table),
+            ("world", world_table),
+            ("debug", debug_table),
+            ("import", import_table),
+            ("settings", settings_table),
+            ("commands", commands_table),
+            ("fmt", fmt_table),
+        ] {
+            latest.set(name, module.clone()).unwrap();
+            api_table.set(name, module).unwrap();
+        }
+        api_table.set(format!("v{}", API_VERSION), latest).unwrap();
+        api_table.set("version", API_VERSION).unwrap();
 
         // Set API as global
-        globals.set("api", api_table).unwrap();
+        globals.set("api", api_table.clone()).unwrap();
+
+        let deprecations = Deprecations::install(&lua);
+        for (module, old, new) in RENAMED_API {
+            let table: Table = api_table.get(*module).unwrap();
+            deprecations
+                .renamed(&lua, &table, module, old, new)
+                .unwrap();
+        }
 
         // Setup documentation
         Self::setup_documentation(&lua);
+        read_models::install(&lua, &core.read().unwrap().read_models()).unwrap();
+        script_args::install(&lua, &[]).unwrap();
+        color::install(&lua).unwrap();
+
+        let debugger = Debugger::install(&lua);
+        let error_log = ErrorLog::default();
+        let budget = ModBudget::default();
+        let settings = ModSettings::install(&lua, error_log.clone(), budget.clone());
+        let commands = Commands::install(&lua, error_log.clone());
+        let hooks =
+            LifecycleHooks::install(&lua, error_log.clone(), settings.clone(), budget.clone());
+        let timers = Timers::install(&lua, error_log.clone(), budget.clone());
+        timers.install_util(&lua);
+        let params = Params::install(&lua, error_log.clone());
+        let clock = Clock::install(&lua, error_log.clone(), params.clone());
+        let routines = Routines::install(&lua, Arc::clone(&core), clock.clone());
+        // Once every module is there, the frontends guard their own globals
+        let permissions = Permissions::install(&lua);
+        for (path, names) in MUTATING_API {
+            permissions.guard(&lua, path, names).unwrap();
+        }
+        let scenario = Scenario::install(&lua, Arc::clone(&core), error_log.clone());
+        let goals = Goals::install(&lua, Arc::clone(&core), error_log.clone());
+        let notifications = Notifications::install(&lua, error_log.clone());
+        let tutorials = Tutorials::install(&lua, error_log.clone());
+        let overlay = Overlay::install(&lua);
+        let triggers = Triggers::install(&lua, Arc::clone(&core), error_log.clone());
+        let ai = AiDirector::install(&lua, Arc::clone(&core));
+        let consistency = ConsistencyWatch::install(&lua, Arc::clone(&core), error_log.clone());
+        let events = EventBridge::new(core.read().unwrap().event().subscribe(), error_log.clone());
+        let snapshots = core.read().unwrap().snapshots();
+        let metrics = core.read().unwrap().metrics().shared();
+        let projections = core.read().unwrap().projections();
+        let fog = core.read().unwrap().fog().shared();
+        let factions = core.read().unwrap().faction().shared();
+        let vehicles = core.read().unwrap().vehicle().shared();
+        let portals = core.read().unwrap().portal().shared();
+        let map_edits = core.read().unwrap().map().shared();
+        let chunks = core.read().unwrap().stream().shared();
+        let world = core.read().unwrap().world().shared();
+        let xpcall = globals.get("xpcall").unwrap();
+        let traceback_handler = lua.load(TRACEBACK_HANDLER).eval().unwrap();
 
         Self {
             lua,
+            debugger,
+            error_log,
+            hooks,
+            settings,
+            commands,
+            params,
+            timers,
+            budget,
+            clock,
+            routines,
+            permissions,
+            deprecations,
+            snapshots,
+            metrics,
+            projections,
+            fog,
+            factions,
+            vehicles,
+            portals,
+            map_edits,
+            chunks,
+            world,
+            scenario,
+            goals,
+            notifications,
+            tutorials,
+            overlay,
+            triggers,
+            ai,
+            consistency,
+            events,
+            xpcall,
+            traceback_handler,
             callbacks: HashMap::new(),
             next_callback_id: 1,
             command_rx,
+            core,
+            #[cfg(feature = "plugins")]
+            plugins: Plugins::default(),
+        }
+    }
+
+    /// Add the module `api.<name>`, also reachable as `api.v<API_VERSION>.<name>`, so other
+    /// crates can contribute modules without touching the engine. `builder` fills the module
+    /// table with its functions. Fails when there is a module of that name already.
+    pub fn register_module(
+        &self,
+        name: &str,
+        builder: impl FnOnce(&Lua, &Table) -> LuaResult<()>,
+    ) -> LuaResult<()> {
+        let api: Table = self.lua.globals().get("api")?;
+        if api.contains_key(name)? {
+            return Err(mlua::Error::RuntimeError(format!(
+                "api.{} is already registered",
+                name
+            )));
+        }
+        let module = self.lua.create_table()?;
+        builder(&self.lua, &module)?;
+        api.get::<Table>(format!("v{}", API_VERSION))?
+            .set(name, module.clone())?;
+        api.set(name, module)
+    }
+
+    /// Keep only the latest events in memory, see `RetentionPolicy`. Tells what was recovered of
+    /// the archive an earlier run left at the path.
+    pub fn set_event_retention(
+        &self,
+        retention: RetentionPolicy,
+    ) -> std::io::Result<Option<Recovery>> {
+        self.core.read().unwrap().event().set_retention(retention)
+    }
+
+    /// Replace the read-only `env` table, call before loading the scripts reading it
+    pub fn set_script_args(&self, args: &[(String, String)]) -> LuaResult<()> {
+        script_args::install(&self.lua, args)
+    }
+
+    pub fn run_script(&mut self, script: &str) -> Result<(), ScriptError> {
+        let function = self.lua.load(script).set_name("=script").into_function()?;
+        self.call_traced(function).map(|_| ())
+    }
+
+    /// Run the script file at `path`, errors point into the file rather than at `script`
+    pub fn run_file(&mut self, path: &str) -> Result<(), ScriptError> {
+        let script = std::fs::read_to_string(path)
+            .map_err(|e| ScriptError::new(&format!("Failed to read {}: {}", path, e), ""))?;
+        let function = self
+            .lua
+            .load(script)
+            .set_name(format!("@{}", path))
+            .into_function()?;
+        self.call_traced(function).map(|_| ())
+    }
+
+    // Call the function through xpcall, so errors keep the traceback of where they were raised
+    fn call_traced(&self, function: Function) -> Result<Value, ScriptError> {
+        let mut results = self
+            .xpcall
+            .call::<MultiValue>((function, self.traceback_handler.clone()))?;
+        let succeeded = matches!(results.pop_front(), Some(Value::Boolean(true)));
+        let value = results.pop_front().unwrap_or(Value::Nil);
+        if succeeded {
+            return Ok(value);
+        }
+        match value {
+            Value::Table(error) => Err(ScriptError::new(
+                &error.get::<String>("message")?,
+                &error.get::<String>("traceback")?,
+            )),
+            value => Err(ScriptError::new(&format!("{:?}", value), "")),
         }
     }
 
-    pub fn run_script(&mut self, script: &str) -> mlua::Result<()> {
-        self.lua.load(script).exec()
+    // Console input is evaluated as an expression when possible, like `eval` does
+    fn load_console_input(&self, code: &str) -> Result<Function, ScriptError> {
+        let expression = self
+            .lua
+            .load(format!("return {}", code))
+            .set_name("=console")
+            .into_function();
+        match expression {
+            Ok(function) => Ok(function),
+            Err(_) => Ok(self.lua.load(code).set_name("=console").into_function()?),
+        }
     }
 
     // Process a single command - call this in a loop from your thread
@@ -74,10 +437,12 @@ impl LuaEngine {
             Ok(cmd) => {
                 match cmd {
                     LuaCommand::Execute { code, response_tx } => {
-                        let result = match self.lua.load(&code).eval::<Value>() {
-                            Ok(value) => {
+                        let result = self
+                            .load_console_input(&code)
+                            .and_then(|function| self.call_traced(function))
+                            .map(|value| {
                                 // Convert Lua value to string representation
-                                let result = match value {
+                                match value {
                                     Value::Nil => "nil".to_string(),
                                     Value::Boolean(b) => b.to_string(),
                                     Value::Integer(i) => i.to_string(),
@@ -86,13 +451,17 @@ impl LuaEngine {
                                     Value::Table(_) => "table".to_string(),
                                     Value::Function(_) => "[function]".to_string(),
                                     _ => "[value]".to_string(),
-                                };
-                                Ok(result)
-                            }
-                            Err(e) => Err(e.to_string()),
-                        };
+                                }
+                            });
                         let _ = response_tx.send(result);
                     }
+                    LuaCommand::RunFile { path, response_tx } => {
+                        let _ = response_tx.send(self.run_file(&path));
+                    }
+                    LuaCommand::Tick { dt, frame, done_tx } => {
+                        self.tick(dt, frame);
+                        let _ = done_tx.send(());
+                    }
                     LuaCommand::Shutdown => return false,
                     _ => {}
                 }
@@ -101,28 +470,50 @@ impl LuaEngine {
             Err(_) => false, // Channel closed
         }
     }
+    /// Advance the game by a frame. While the clock waits for a turn to end only the events and
+    /// notifications are handled.
+    pub(crate) fn tick(&mut self, dt: f32, frame: u64) {
+        let simulated = self.clock.advance(dt as f64);
+        if let Some(dt) = simulated {
+            self.clock.resolve();
+            self.routines.update();
+            self.core.read().unwrap().sample_metrics(frame);
+            self.core.read().unwrap().task().step();
+            self.core.read().unwrap().vehicle().step(dt);
+            self.hooks.call(Hook::Frame, (dt, frame));
+            self.timers.update(dt);
+        }
+        self.settings.dispatch();
+        self.params.dispatch();
+        self.notifications.update(dt as f64, frame);
+        // The handlers of the events and zones belong to no mod
+        self.budget.measure(None, || {
+            self.events
+                .dispatch(&self.lua, &self.notifications, &self.tutorials);
+            self.triggers.update(&self.lua);
+        });
+        if let Some(dt) = simulated {
+            self.ai.update(&self.lua, &self.hooks);
+            self.goals.update();
+            self.scenario.update(dt, frame);
+        }
+        self.consistency.update(frame);
+        self.core.read().unwrap().refresh_snapshot(frame);
+        self.budget.end_frame();
+    }
+
     pub fn run(&mut self) {
         while self.process_command() {}
     }
     fn setup_person_api(lua: &Lua, table: &Table, core: Arc<RwLock<CoreApi>>) {
+        // Persons reach Lua as their DTO, { id = ..., name = ..., location = { x = ..., y = ... } }
+
         // Expose api.person.create to Lua
         let core_clone = Arc::clone(&core);
         let create_person = lua
             .create_function(move |lua_ctx, (name, x, y): (String, i32, i32)| {
                 match core_clone.read().unwrap().person().create(name, x, y) {
-                    Ok(person) => {
-                        // Convert Person to Lua table using the provided lua context
-                        let person_table = lua_ctx.create_table()?;
-                        person_table.set("id", person.id.0)?;
-                        person_table.set("name", person.name)?;
-
-                        let location_table = lua_ctx.create_table()?;
-                        location_table.set("x", person.location.x)?;
-                        location_table.set("y", person.location.y)?;
-
-                        person_table.set("location", location_table)?;
-                        Ok(person_table)
-                    }
+                    Ok(person) => lua_ctx.to_value(&PersonDto::from(&person)),
                     Err(e) => Err(mlua::Error::RuntimeError(e)),
                 }
             })
@@ -134,43 +525,38 @@ impl LuaEngine {
         let move_person = lua
             .create_function(move |lua_ctx, (id, x, y): (u32, i32, i32)| {
                 match core_clone.read().unwrap().person().move_to(id, x, y) {
-                    Ok(person) => {
-                        // Convert Person to Lua table using the provided lua context
-                        let person_table = lua_ctx.create_table()?;
-                        person_table.set("id", person.id.0)?;
-                        person_table.set("name", person.name)?;
-
-                        let location_table = lua_ctx.create_table()?;
-                        location_table.set("x", person.location.x)?;
-                        location_table.set("y", person.location.y)?;
-
-                        person_table.set("location", location_table)?;
-                        Ok(person_table)
-                    }
+                    Ok(person) => lua_ctx.to_value(&PersonDto::from(&person)),
                     Err(e) => Err(mlua::Error::RuntimeError(e)),
                 }
             })
             .unwrap();
         table.set("move_to", move_person).unwrap();
 
+        // Expose api.person.move_all to Lua, taking a list of { id = ..., x = ..., y = ... }
+        let core_clone = Arc::clone(&core);
+        let move_all = lua
+            .create_function(move |lua_ctx, moves: Vec<Table>| {
+                let moves = moves
+                    .iter()
+                    .map(|m| Ok((m.get("id")?, m.get("x")?, m.get("y")?)))
+                    .collect::<LuaResult<_>>()?;
+                match core_clone.read().unwrap().person().move_all(moves) {
+                    Ok(persons) => {
+                        let persons: Vec<PersonDto> = persons.iter().map(PersonDto::from).collect();
+                        lua_ctx.to_value(&persons)
+                    }
+                    Err(e) => Err(mlua::Error::RuntimeError(e)),
+                }
+            })
+            .unwrap();
+        table.set("move_all", move_all).unwrap();
+
         // Expose api.person.get to Lua
         let core_clone = Arc::clone(&core);
         let get_person = lua
             .create_function(move |lua_ctx, id: u32| {
                 match core_clone.read().unwrap().person().get(id) {
-                    Ok(person) => {
-                        // Convert Person to Lua table using the provided lua context
-                        let person_table = lua_ctx.create_table()?;
-                        person_table.set("id", person.id.0)?;
-                        person_table.set("name", person.name)?;
-
-                        let location_table = lua_ctx.create_table()?;
-                        location_table.set("x", person.location.x)?;
-                        location_table.set("y", person.location.y)?;
-
-                        person_table.set("location", location_table)?;
-                        Ok(person_table)
-                    }
+                    Ok(person) => lua_ctx.to_value(&PersonDto::from(&person)),
                     Err(e) => Err(mlua::Error::RuntimeError(e)),
                 }
             })
@@ -183,23 +569,8 @@ impl LuaEngine {
             .create_function(move |lua_ctx, ()| {
                 match core_clone.read().unwrap().person().get_all() {
                     Ok(persons) => {
-                        // Convert Vec<Person> to Lua table using the provided lua context
-                        let persons_table = lua_ctx.create_table()?;
-
-                        for (i, person) in persons.iter().enumerate() {
-                            let person_table = lua_ctx.create_table()?;
-                            person_table.set("id", person.id.0)?;
-                            person_table.set("name", person.name.clone())?;
-
-                            let location_table = lua_ctx.create_table()?;
-                            location_table.set("x", person.location.x)?;
-                            location_table.set("y", person.location.y)?;
-
-                            person_table.set("location", location_table)?;
-                            persons_table.set(i + 1, person_table)?;
-                        }
-
-                        Ok(persons_table)
+                        let persons: Vec<PersonDto> = persons.iter().map(PersonDto::from).collect();
+                        lua_ctx.to_value(&persons)
                     }
                     Err(e) => Err(mlua::Error::RuntimeError(e)),
                 }
@@ -289,139 +660,1923 @@ impl LuaEngine {
             })
             .unwrap();
         table.set("count", event_count).unwrap();
+
+        // Expose api.event.memory to Lua
+        let core_clone = Arc::clone(&core);
+        let memory = lua
+            .create_function(move |lua, ()| {
+                let memory = core_clone.read().unwrap().event().memory();
+                let result = lua.create_table()?;
+                result.set("in_memory", memory.in_memory)?;
+                result.set("bytes", memory.bytes)?;
+                result.set("archived", memory.archived)?;
+                result.set("dropped", memory.dropped)?;
+                Ok(result)
+            })
+            .unwrap();
+        table.set("memory", memory).unwrap();
+
+        // Expose api.event.export to Lua
+        setup_export_api(lua, table, core);
     }
 
-    fn setup_documentation(lua: &Lua) {
-        // Create the docs table
-        let docs_table = lua.create_table().unwrap();
-        let globals = lua.globals();
-        globals.set("docs", docs_table.clone()).unwrap();
+    fn setup_tags_api(lua: &Lua, table: &Table, core: Arc<RwLock<CoreApi>>) {
+        // Entities are referenced as strings like "person:3"
 
-        // Get the API documentation from the generated code
-        let api_docs = docs::get_api_docs();
+        // Expose api.tags.add to Lua
+        let core_clone = Arc::clone(&core);
+        let add_tag = lua
+            .create_function(move |_, (entity, tag): (String, String)| {
+                let core = core_clone.read().unwrap();
+                core.tags()
+                    .add(&entity, &tag)
+                    .map_err(mlua::Error::RuntimeError)
+            })
+            .unwrap();
+        table.set("add", add_tag).unwrap();
 
-        // Convert API docs to Lua tables
-        for (module_name, module_docs) in api_docs {
-            let module_table = lua.create_table().unwrap();
-            docs_table
-                .set(module_name.clone(), module_table.clone())
-                .unwrap();
+        // Expose api.tags.remove to Lua
+        let core_clone = Arc::clone(&core);
+        let remove_tag = lua
+            .create_function(move |_, (entity, tag): (String, String)| {
+                let core = core_clone.read().unwrap();
+                core.tags()
+                    .remove(&entity, &tag)
+                    .map_err(mlua::Error::RuntimeError)
+            })
+            .unwrap();
+        table.set("remove", remove_tag).unwrap();
 
-            for (method_name, method_doc) in module_docs.methods {
-                let method_table = lua.create_table().unwrap();
-                method_table
-                    .set("description", method_doc.description)
-                    .unwrap();
+        // Expose api.tags.has to Lua
+        let core_clone = Arc::clone(&core);
+        let has_tag = lua
+            .create_function(move |_, (entity, tag): (String, String)| {
+                let core = core_clone.read().unwrap();
+                core.tags()
+                    .has(&entity, &tag)
+                    .map_err(mlua::Error::RuntimeError)
+            })
+            .unwrap();
+        table.set("has", has_tag).unwrap();
 
-                // Set parameters
-                let params_table = lua.create_table().unwrap();
-                for (i, param) in method_doc.params.iter().enumerate() {
-                    let param_table = lua.create_table().unwrap();
-                    param_table.set("name", param.name.clone()).unwrap();
-                    param_table.set("type", param.type_name.clone()).unwrap();
-                    param_table
-                        .set("description", param.description.clone())
-                        .unwrap();
-                    params_table.set(i + 1, &param_table).unwrap();
-                    params_table.set(param.name.clone(), param_table).unwrap();
+        // Expose api.tags.of to Lua
+        let core_clone = Arc::clone(&core);
+        let tags_of = lua
+            .create_function(move |_, entity: String| {
+                let core = core_clone.read().unwrap();
+                core.tags().of(&entity).map_err(mlua::Error::RuntimeError)
+            })
+            .unwrap();
+        table.set("of", tags_of).unwrap();
+
+        // Expose api.tags.find to Lua
+        let core_clone = Arc::clone(&core);
+        let find_tagged = lua
+            .create_function(move |_, tag: String| Ok(core_clone.read().unwrap().tags().find(&tag)))
+            .unwrap();
+        table.set("find", find_tagged).unwrap();
+    }
+
+    fn setup_meta_api(lua: &Lua, table: &Table, core: Arc<RwLock<CoreApi>>) {
+        // Expose api.meta.set to Lua
+        let core_clone = Arc::clone(&core);
+        let set_meta = lua
+            .create_function(move |_, (entity, key, value): (String, String, Value)| {
+                let value = meta_value_from_lua(value)?;
+                let core = core_clone.read().unwrap();
+                core.meta()
+                    .set(&entity, &key, value)
+                    .map_err(mlua::Error::RuntimeError)
+            })
+            .unwrap();
+        table.set("set", set_meta).unwrap();
+
+        // Expose api.meta.get to Lua
+        let core_clone = Arc::clone(&core);
+        let get_meta = lua
+            .create_function(move |lua_ctx, (entity, key): (String, String)| {
+                let value = core_clone
+                    .read()
+                    .unwrap()
+                    .meta()
+                    .get(&entity, &key)
+                    .map_err(mlua::Error::RuntimeError)?;
+                match value {
+                    Some(value) => meta_value_to_lua(lua_ctx, &value),
+                    None => Ok(Value::Nil),
                 }
+            })
+            .unwrap();
+        table.set("get", get_meta).unwrap();
 
-                method_table.set("params", params_table).unwrap();
-                method_table.set("returns", method_doc.returns).unwrap();
+        // Expose api.meta.remove to Lua
+        let core_clone = Arc::clone(&core);
+        let remove_meta = lua
+            .create_function(move |_, (entity, key): (String, String)| {
+                let core = core_clone.read().unwrap();
+                core.meta()
+                    .remove(&entity, &key)
+                    .map_err(mlua::Error::RuntimeError)
+            })
+            .unwrap();
+        table.set("remove", remove_meta).unwrap();
 
-                module_table.set(method_name, method_table).unwrap();
-            }
-        }
+        // Expose api.meta.all to Lua
+        let core_clone = Arc::clone(&core);
+        let all_meta = lua
+            .create_function(move |lua_ctx, entity: String| {
+                let entries = core_clone
+                    .read()
+                    .unwrap()
+                    .meta()
+                    .all(&entity)
+                    .map_err(mlua::Error::RuntimeError)?;
+                let entries_table = lua_ctx.create_table()?;
+                for (key, value) in &entries {
+                    entries_table.set(key.as_str(), meta_value_to_lua(lua_ctx, value)?)?;
+                }
+                Ok(entries_table)
+            })
+            .unwrap();
+        table.set("all", all_meta).unwrap();
+    }
 
-        // Add help function
-        let help_fn = lua.create_function(|ctx, topic: Option<String>| {
-            let docs: Table = ctx.globals().get("docs")?;
+    fn setup_metrics_api(lua: &Lua, table: &Table, core: Arc<RwLock<CoreApi>>) {
+        // Expose api.metrics.record to Lua
+        let core_clone = Arc::clone(&core);
+        let record = lua
+            .create_function(move |_, (name, value): (String, f64)| {
+                let core = core_clone.read().unwrap();
+                core.metrics()
+                    .record(&name, value)
+                    .map_err(mlua::Error::RuntimeError)
+            })
+            .unwrap();
+        table.set("record", record).unwrap();
 
-            match topic {
-                None => {
-                    // Level 1: List all modules
-                    let mut result = String::from("Available modules:\n");
+        // Expose api.metrics.series to Lua
+        let core_clone = Arc::clone(&core);
+        let series = lua
+            .create_function(move |lua_ctx, name: String| {
+                let samples = core_clone.read().unwrap().metrics().series(&name);
+                let samples_table = lua_ctx.create_table_with_capacity(samples.len(), 0)?;
+                for (tick, value) in samples {
+                    let sample_table = lua_ctx.create_table()?;
+                    sample_table.set("tick", tick)?;
+                    sample_table.set("value", value)?;
+                    samples_table.push(sample_table)?;
+                }
+                Ok(samples_table)
+            })
+            .unwrap();
+        table.set("series", series).unwrap();
 
-                    for pair in docs.pairs::<String, Table>() {
-                        let (module, _) = pair?;
-                        result.push_str(&format!("  {}\n", module));
-                    }
+        // Expose api.metrics.latest to Lua
+        let core_clone = Arc::clone(&core);
+        let latest = lua
+            .create_function(move |_, name: String| {
+                Ok(core_clone.read().unwrap().metrics().latest(&name))
+            })
+            .unwrap();
+        table.set("latest", latest).unwrap();
 
-                    result.push_str("\nUse help(\"module\") to see available methods.");
-                    Ok(result)
+        // Expose api.metrics.names to Lua
+        let core_clone = Arc::clone(&core);
+        let names = lua
+            .create_function(move |_, ()| Ok(core_clone.read().unwrap().metrics().names()))
+            .unwrap();
+        table.set("names", names).unwrap();
+
+        // Expose api.metrics.set_sample_interval to Lua
+        let core_clone = Arc::clone(&core);
+        let set_sample_interval = lua
+            .create_function(move |_, ticks: u64| {
+                core_clone
+                    .read()
+                    .unwrap()
+                    .metrics()
+                    .set_sample_interval(ticks);
+                Ok(())
+            })
+            .unwrap();
+        table
+            .set("set_sample_interval", set_sample_interval)
+            .unwrap();
+
+        // Expose api.metrics.sample_interval to Lua
+        let core_clone = Arc::clone(&core);
+        let sample_interval = lua
+            .create_function(move |_, ()| {
+                Ok(core_clone.read().unwrap().metrics().sample_interval())
+            })
+            .unwrap();
+        table.set("sample_interval", sample_interval).unwrap();
+    }
+
+    fn setup_goals_api(lua: &Lua, table: &Table, core: Arc<RwLock<CoreApi>>) {
+        // Goals reach Lua as their DTO, { id, name, progress, target, completed }
+
+        // Expose api.goals.define to Lua
+        let core_clone = Arc::clone(&core);
+        let define = lua
+            .create_function(move |lua_ctx, (id, name, target): (String, String, f64)| {
+                match core_clone
+                    .read()
+                    .unwrap()
+                    .goals()
+                    .define(&id, &name, target)
+                {
+                    Ok(goal) => lua_ctx.to_value(&GoalDto::from(&goal)),
+                    Err(e) => Err(mlua::Error::RuntimeError(e)),
                 }
-                Some(topic) => {
-                    // Check if this is a module name or a method name
-                    let parts: Vec<&str> = topic.split('.').collect();
+            })
+            .unwrap();
+        table.set("define", define).unwrap();
 
-                    if parts.len() == 1 {
-                        // Level 2: List all methods in a module
-                        let module = parts[0];
-                        let module_docs: LuaResult<Table> = docs.get(module);
+        // Expose api.goals.progress to Lua
+        let core_clone = Arc::clone(&core);
+        let progress = lua
+            .create_function(move |lua_ctx, (id, progress): (String, f64)| {
+                match core_clone.read().unwrap().goals().progress(&id, progress) {
+                    Ok(goal) => lua_ctx.to_value(&GoalDto::from(&goal)),
+                    Err(e) => Err(mlua::Error::RuntimeError(e)),
+                }
+            })
+            .unwrap();
+        table.set("progress", progress).unwrap();
 
-                        if let Ok(module_table) = module_docs {
-                            let mut result = format!("Methods in {} module:\n", module);
+        // Expose api.goals.get to Lua
+        let core_clone = Arc::clone(&core);
+        let get = lua
+            .create_function(move |lua_ctx, id: String| {
+                match core_clone.read().unwrap().goals().get(&id) {
+                    Some(goal) => lua_ctx.to_value(&GoalDto::from(&goal)),
+                    None => Ok(Value::Nil),
+                }
+            })
+            .unwrap();
+        table.set("get", get).unwrap();
 
-                            for pair in module_table.pairs::<String, Table>() {
-                                let (method, _) = pair?;
-                                result.push_str(&format!("  {}.{}\n", module, method));
-                            }
+        // Expose api.goals.all to Lua
+        let core_clone = Arc::clone(&core);
+        let all = lua
+            .create_function(move |lua_ctx, ()| {
+                let goals: Vec<GoalDto> = core_clone
+                    .read()
+                    .unwrap()
+                    .goals()
+                    .all()
+                    .iter()
+                    .map(GoalDto::from)
+                    .collect();
+                lua_ctx.to_value(&goals)
+            })
+            .unwrap();
+        table.set("all", all).unwrap();
+    }
 
-                            result.push_str("\nUse help(\"module.method\") to see method details.");
-                            Ok(result)
-                        } else {
-                            Ok(format!("Module '{}' not found. Use help() to see available modules.", module))
-                        }
-                    } else if parts.len() == 2 {
-                        // Level 3: Show details of a specific method
-                        let module = parts[0];
-                        let method = parts[1];
+    fn setup_fog_api(lua: &Lua, table: &Table, core: Arc<RwLock<CoreApi>>) {
+        // The viewer is the last argument everywhere and "player" when left out
 
-                        // Get the module table
-                        let module_docs: LuaResult<Table> = docs.get(module);
-                        if let Ok(module_table) = module_docs {
-                            // Get the method documentation
-                            let method_docs: LuaResult<Table> = module_table.get(method);
-                            if let Ok(doc) = method_docs {
-                                // Format and return documentation
-                                let desc: String = doc.get("description")?;
-                                let params: Table = doc.get("params")?;
-                                let returns: String = doc.get("returns")?;
+        // Expose api.fog.reveal to Lua
+        let core_clone = Arc::clone(&core);
+        let reveal = lua
+            .create_function(
+                move |_, (x1, y1, x2, y2, viewer): (i32, i32, i32, i32, Option<String>)| {
+                    let viewer = viewer.unwrap_or_else(|| PLAYER_VIEWER.to_string());
+                    core_clone
+                        .read()
+                        .unwrap()
+                        .fog()
+                        .reveal(x1, y1, x2, y2, &viewer)
+                        .map_err(mlua::Error::RuntimeError)
+                },
+            )
+            .unwrap();
+        table.set("reveal", reveal).unwrap();
 
-                                let mut result = format!("--- {}\n\n", desc);
-                                result.push_str("Parameters:\n");
+        // Expose api.fog.hide to Lua
+        let core_clone = Arc::clone(&core);
+        let hide = lua
+            .create_function(
+                move |_, (x1, y1, x2, y2, viewer): (i32, i32, i32, i32, Option<String>)| {
+                    let viewer = viewer.unwrap_or_else(|| PLAYER_VIEWER.to_string());
+                    core_clone
+                        .read()
+                        .unwrap()
+                        .fog()
+                        .hide(x1, y1, x2, y2, &viewer)
+                        .map_err(mlua::Error::RuntimeError)
+                },
+            )
+            .unwrap();
+        table.set("hide", hide).unwrap();
 
-                                // List parameters
-                                let param_count: i32 = params.len()?;
-                                for i in 1..=param_count {
-                                    let param: Table = params.get(i)?;
-                                    let name: String = param.get("name")?;
-                                    let type_name: String = param.get("type")?;
-                                    let param_desc: String = param.get("description").unwrap_or_default();
+        // Expose api.fog.visibility to Lua
+        let core_clone = Arc::clone(&core);
+        let visibility = lua
+            .create_function(move |_, (x, y, viewer): (i32, i32, Option<String>)| {
+                let viewer = viewer.unwrap_or_else(|| PLAYER_VIEWER.to_string());
+                Ok(core_clone.read().unwrap().fog().visibility(x, y, &viewer))
+            })
+            .unwrap();
+        table.set("visibility", visibility).unwrap();
 
-                                    result.push_str(&format!("  {} ({})", name, type_name));
-                                    if !param_desc.is_empty() {
-                                        result.push_str(&format!(" - {}", param_desc));
-                                    }
-                                    result.push('\n');
-                                }
+        // Expose api.fog.explored to Lua
+        let core_clone = Arc::clone(&core);
+        let explored = lua
+            .create_function(move |_, viewer: Option<String>| {
+                let viewer = viewer.unwrap_or_else(|| PLAYER_VIEWER.to_string());
+                Ok(core_clone.read().unwrap().fog().explored(&viewer))
+            })
+            .unwrap();
+        table.set("explored", explored).unwrap();
+    }
 
-                                result.push_str(&format!("\nReturns: {}", returns));
-                                Ok(result)
-                            } else {
-                                Ok(format!("Method '{}.{}' not found. Use help('{}') to see available methods.",
-                                           module, method, module))
-                            }
-                        } else {
-                            Ok(format!("Module '{}' not found. Use help() to see available modules.", module))
-                        }
-                    } else {
-                        Ok(format!("Invalid topic format: '{}'. Use help(), help(\"module\"), or help(\"module.method\").", topic))
+    fn setup_faction_api(lua: &Lua, table: &Table, core: Arc<RwLock<CoreApi>>) {
+        // Factions reach Lua as their DTO, { id, kind, name, color }, entities as "zone:1"
+
+        // Expose api.faction.create to Lua
+        let core_clone = Arc::clone(&core);
+        let create = lua
+            .create_function(
+                move |lua_ctx, (id, name, kind, color): (String, String, String, String)| {
+                    match core_clone
+                        .read()
+                        .unwrap()
+                        .faction()
+                        .create(&id, &name, &kind, &color)
+                    {
+                        Ok(faction) => lua_ctx.to_value(&FactionDto::from(&faction)),
+                        Err(e) => Err(mlua::Error::RuntimeError(e)),
                     }
-                }
-            }
-        }).unwrap();
+                },
+            )
+            .unwrap();
+        table.set("create", create).unwrap();
 
-        globals.set("help", help_fn).unwrap();
+        // Expose api.faction.get to Lua
+        let core_clone = Arc::clone(&core);
+        let get = lua
+            .create_function(move |lua_ctx, id: String| {
+                match core_clone.read().unwrap().faction().get(&id) {
+                    Some(faction) => lua_ctx.to_value(&FactionDto::from(&faction)),
+                    None => Ok(Value::Nil),
+                }
+            })
+            .unwrap();
+        table.set("get", get).unwrap();
+
+        // Expose api.faction.all to Lua
+        let core_clone = Arc::clone(&core);
+        let all = lua
+            .create_function(move |lua_ctx, ()| {
+                let factions: Vec<FactionDto> = core_clone
+                    .read()
+                    .unwrap()
+                    .faction()
+                    .all()
+                    .iter()
+                    .map(FactionDto::from)
+                    .collect();
+                lua_ctx.to_value(&factions)
+            })
+            .unwrap();
+        table.set("all", all).unwrap();
+
+        // Expose api.faction.create_zone to Lua
+        let core_clone = Arc::clone(&core);
+        let create_zone = lua
+            .create_function(move |_, (x1, y1, x2, y2): (i32, i32, i32, i32)| {
+                Ok(core_clone
+                    .read()
+                    .unwrap()
+                    .faction()
+                    .create_zone(x1, y1, x2, y2))
+            })
+            .unwrap();
+        table.set("create_zone", create_zone).unwrap();
+
+        // Expose api.faction.zones_at to Lua
+        let core_clone = Arc::clone(&core);
+        let zones_at = lua
+            .create_function(move |_, (x, y): (i32, i32)| {
+                Ok(core_clone.read().unwrap().faction().zones_at(x, y))
+            })
+            .unwrap();
+        table.set("zones_at", zones_at).unwrap();
+
+        // Expose api.faction.transfer to Lua
+        let core_clone = Arc::clone(&core);
+        let transfer = lua
+            .create_function(move |_, (entity, faction): (String, String)| {
+                let core = core_clone.read().unwrap();
+                core.faction()
+                    .transfer(&entity, &faction)
+                    .map_err(mlua::Error::RuntimeError)
+            })
+            .unwrap();
+        table.set("transfer", transfer).unwrap();
+
+        // Expose api.faction.release to Lua
+        let core_clone = Arc::clone(&core);
+        let release = lua
+            .create_function(move |_, entity: String| {
+                let core = core_clone.read().unwrap();
+                core.faction()
+                    .release(&entity)
+                    .map_err(mlua::Error::RuntimeError)
+            })
+            .unwrap();
+        table.set("release", release).unwrap();
+
+        // Expose api.faction.owner to Lua
+        let core_clone = Arc::clone(&core);
+        let owner = lua
+            .create_function(move |_, entity: String| {
+                let core = core_clone.read().unwrap();
+                core.faction()
+                    .owner(&entity)
+                    .map_err(mlua::Error::RuntimeError)
+            })
+            .unwrap();
+        table.set("owner", owner).unwrap();
+
+        // Expose api.faction.owned to Lua
+        let core_clone = Arc::clone(&core);
+        let owned = lua
+            .create_function(move |_, faction: String| {
+                let core = core_clone.read().unwrap();
+                core.faction()
+                    .owned(&faction)
+                    .map_err(mlua::Error::RuntimeError)
+            })
+            .unwrap();
+        table.set("owned", owned).unwrap();
+    }
+
+    fn setup_task_api(lua: &Lua, table: &Table, core: Arc<RwLock<CoreApi>>) {
+        // Tasks reach Lua as their DTO, { id, person_id, kind, priority, location, ... }
+
+        // Expose api.task.go_to to Lua
+        let core_clone = Arc::clone(&core);
+        let go_to = lua
+            .create_function(move |lua_ctx, (person_id, x, y): (u32, i32, i32)| {
+                match core_clone.read().unwrap().task().go_to(person_id, x, y) {
+                    Ok(task) => lua_ctx.to_value(&TaskDto::from(&task)),
+                    Err(e) => Err(mlua::Error::RuntimeError(e)),
+                }
+            })
+            .unwrap();
+        table.set("go_to", go_to).unwrap();
+
+        // Expose api.task.work_at to Lua
+        let core_clone = Arc::clone(&core);
+        let work_at = lua
+            .create_function(
+                move |lua_ctx, (person_id, x, y, steps): (u32, i32, i32, u32)| match core_clone
+                    .read()
+                    .unwrap()
+                    .task()
+                    .work_at(person_id, x, y, steps)
+                {
+                    Ok(task) => lua_ctx.to_value(&TaskDto::from(&task)),
+                    Err(e) => Err(mlua::Error::RuntimeError(e)),
+                },
+            )
+            .unwrap();
+        table.set("work_at", work_at).unwrap();
+
+        // Expose api.task.haul to Lua
+        let core_clone = Arc::clone(&core);
+        let haul = lua
+            .create_function(
+                move |lua_ctx,
+                      (person_id, item, x1, y1, x2, y2): (u32, String, i32, i32, i32, i32)| {
+                    match core_clone
+                        .read()
+                        .unwrap()
+                        .task()
+                        .haul(person_id, &item, x1, y1, x2, y2)
+                    {
+                        Ok(task) => lua_ctx.to_value(&TaskDto::from(&task)),
+                        Err(e) => Err(mlua::Error::RuntimeError(e)),
+                    }
+                },
+            )
+            .unwrap();
+        table.set("haul", haul).unwrap();
+
+        // Expose api.task.patrol to Lua, taking the route as a list of { x = ..., y = ... }
+        let core_clone = Arc::clone(&core);
+        let patrol = lua
+            .create_function(move |lua_ctx, (person_id, route): (u32, Vec<Table>)| {
+                let route = route
+                    .iter()
+                    .map(|waypoint| Ok((waypoint.get("x")?, waypoint.get("y")?)))
+                    .collect::<LuaResult<Vec<_>>>()?;
+                match core_clone.read().unwrap().task().patrol(person_id, &route) {
+                    Ok(task) => lua_ctx.to_value(&TaskDto::from(&task)),
+                    Err(e) => Err(mlua::Error::RuntimeError(e)),
+                }
+            })
+            .unwrap();
+        table.set("patrol", patrol).unwrap();
+
+        // Expose api.task.prioritize to Lua
+        let core_clone = Arc::clone(&core);
+        let prioritize = lua
+            .create_function(
+                move |lua_ctx, (id, priority, interruptible): (u32, i32, bool)| match core_clone
+                    .read()
+                    .unwrap()
+                    .task()
+                    .prioritize(id, priority, interruptible)
+                {
+                    Ok(task) => lua_ctx.to_value(&TaskDto::from(&task)),
+                    Err(e) => Err(mlua::Error::RuntimeError(e)),
+                },
+            )
+            .unwrap();
+        table.set("prioritize", prioritize).unwrap();
+
+        // Expose api.task.get to Lua
+        let core_clone = Arc::clone(&core);
+        let get = lua
+            .create_function(move |lua_ctx, id: u32| {
+                match core_clone.read().unwrap().task().get(id) {
+                    Some(task) => lua_ctx.to_value(&TaskDto::from(&task)),
+                    None => Ok(Value::Nil),
+                }
+            })
+            .unwrap();
+        table.set("get", get).unwrap();
+
+        // Expose api.task.queue_of to Lua
+        let core_clone = Arc::clone(&core);
+        let queue_of = lua
+            .create_function(move |lua_ctx, person_id: u32| {
+                let tasks: Vec<TaskDto> = core_clone
+                    .read()
+                    .unwrap()
+                    .task()
+                    .queue_of(person_id)
+                    .iter()
+                    .map(TaskDto::from)
+                    .collect();
+                lua_ctx.to_value(&tasks)
+            })
+            .unwrap();
+        table.set("queue_of", queue_of).unwrap();
+
+        // Expose api.task.cancel to Lua
+        let core_clone = Arc::clone(&core);
+        let cancel = lua
+            .create_function(move |_, id: u32| Ok(core_clone.read().unwrap().task().cancel(id)))
+            .unwrap();
+        table.set("cancel", cancel).unwrap();
+    }
+
+    fn setup_vehicle_api(lua: &Lua, table: &Table, core: Arc<RwLock<CoreApi>>) {
+        // Vehicles reach Lua as their DTO, { id, location, capacity, passengers, cargo, ... }
+        fn vehicle_result(lua: &Lua, result: Result<logic::Vehicle, String>) -> LuaResult<Value> {
+            match result {
+                Ok(vehicle) => lua.to_value(&VehicleDto::from(&vehicle)),
+                Err(e) => Err(mlua::Error::RuntimeError(e)),
+            }
+        }
+
+        // Expose api.vehicle.create to Lua
+        let core_clone = Arc::clone(&core);
+        let create = lua
+            .create_function(move |lua_ctx, (x, y, capacity): (i32, i32, u32)| {
+                let vehicle = core_clone.read().unwrap().vehicle().create(x, y, capacity);
+                lua_ctx.to_value(&VehicleDto::from(&vehicle))
+            })
+            .unwrap();
+        table.set("create", create).unwrap();
+
+        // Expose api.vehicle.get to Lua
+        let core_clone = Arc::clone(&core);
+        let get = lua
+            .create_function(move |lua_ctx, id: u32| {
+                match core_clone.read().unwrap().vehicle().get(id) {
+                    Some(vehicle) => lua_ctx.to_value(&VehicleDto::from(&vehicle)),
+                    None => Ok(Value::Nil),
+                }
+            })
+            .unwrap();
+        table.set("get", get).unwrap();
+
+        // Expose api.vehicle.all to Lua
+        let core_clone = Arc::clone(&core);
+        let all = lua
+            .create_function(move |lua_ctx, ()| {
+                let vehicles: Vec<VehicleDto> = core_clone
+                    .read()
+                    .unwrap()
+                    .vehicle()
+                    .all()
+                    .iter()
+                    .map(VehicleDto::from)
+                    .collect();
+                lua_ctx.to_value(&vehicles)
+            })
+            .unwrap();
+        table.set("all", all).unwrap();
+
+        // Expose api.vehicle.set_road to Lua
+        let core_clone = Arc::clone(&core);
+        let set_road = lua
+            .create_function(move |_, (x, y, cost): (i32, i32, f64)| {
+                let core = core_clone.read().unwrap();
+                core.vehicle()
+                    .set_road(x, y, cost)
+                    .map_err(mlua::Error::RuntimeError)
+            })
+            .unwrap();
+        table.set("set_road", set_road).unwrap();
+
+        // Expose api.vehicle.clear_road to Lua
+        let core_clone = Arc::clone(&core);
+        let clear_road = lua
+            .create_function(move |_, (x, y): (i32, i32)| {
+                Ok(core_clone.read().unwrap().vehicle().clear_road(x, y))
+            })
+            .unwrap();
+        table.set("clear_road", clear_road).unwrap();
+
+        // Expose api.vehicle.drive_to to Lua
+        let core_clone = Arc::clone(&core);
+        let drive_to = lua
+            .create_function(move |lua_ctx, (id, x, y): (u32, i32, i32)| {
+                let result = core_clone.read().unwrap().vehicle().drive_to(id, x, y);
+                vehicle_result(lua_ctx, result)
+            })
+            .unwrap();
+        table.set("drive_to", drive_to).unwrap();
+
+        // Expose api.vehicle.board to Lua
+        let core_clone = Arc::clone(&core);
+        let board = lua
+            .create_function(move |lua_ctx, (id, person_id): (u32, u32)| {
+                let result = core_clone.read().unwrap().vehicle().board(id, person_id);
+                vehicle_result(lua_ctx, result)
+            })
+            .unwrap();
+        table.set("board", board).unwrap();
+
+        // Expose api.vehicle.alight to Lua
+        let core_clone = Arc::clone(&core);
+        let alight = lua
+            .create_function(move |lua_ctx, (id, person_id): (u32, u32)| {
+                let result = core_clone.read().unwrap().vehicle().alight(id, person_id);
+                vehicle_result(lua_ctx, result)
+            })
+            .unwrap();
+        table.set("alight", alight).unwrap();
+
+        // Expose api.vehicle.load to Lua
+        let core_clone = Arc::clone(&core);
+        let load = lua
+            .create_function(move |lua_ctx, (id, item): (u32, String)| {
+                let result = core_clone.read().unwrap().vehicle().load(id, &item);
+                vehicle_result(lua_ctx, result)
+            })
+            .unwrap();
+        table.set("load", load).unwrap();
+
+        // Expose api.vehicle.unload to Lua
+        let core_clone = Arc::clone(&core);
+        let unload = lua
+            .create_function(move |lua_ctx, (id, item): (u32, String)| {
+                let result = core_clone.read().unwrap().vehicle().unload(id, &item);
+                vehicle_result(lua_ctx, result)
+            })
+            .unwrap();
+        table.set("unload", unload).unwrap();
+    }
+
+    fn setup_portal_api(lua: &Lua, table: &Table, core: Arc<RwLock<CoreApi>>) {
+        // Portals reach Lua as their DTO, { id, from, to }
+        fn portal_value(lua: &Lua, portal: Option<logic::Portal>) -> LuaResult<Value> {
+            match portal {
+                Some(portal) => lua.to_value(&PortalDto::from(&portal)),
+                None => Ok(Value::Nil),
+            }
+        }
+
+        // Expose api.portal.create to Lua
+        let core_clone = Arc::clone(&core);
+        let create = lua
+            .create_function(
+                move |lua_ctx, args: (i32, i32, i32, i32, Option<String>, Option<i32>)| {
+                    let (x1, y1, x2, y2, map, z) = args;
+                    let portal = core_clone
+                        .read()
+                        .unwrap()
+                        .portal()
+                        .create(x1, y1, x2, y2, map.as_deref(), z)
+                        .map_err(mlua::Error::RuntimeError)?;
+                    lua_ctx.to_value(&PortalDto::from(&portal))
+                },
+            )
+            .unwrap();
+        table.set("create", create).unwrap();
+
+        // Expose api.portal.remove to Lua
+        let core_clone = Arc::clone(&core);
+        let remove = lua
+            .create_function(move |_, id: u32| Ok(core_clone.read().unwrap().portal().remove(id)))
+            .unwrap();
+        table.set("remove", remove).unwrap();
+
+        // Expose api.portal.get to Lua
+        let core_clone = Arc::clone(&core);
+        let get = lua
+            .create_function(move |lua_ctx, id: u32| {
+                portal_value(lua_ctx, core_clone.read().unwrap().portal().get(id))
+            })
+            .unwrap();
+        table.set("get", get).unwrap();
+
+        // Expose api.portal.all to Lua
+        let core_clone = Arc::clone(&core);
+        let all = lua
+            .create_function(move |lua_ctx, ()| {
+                let portals: Vec<PortalDto> = core_clone
+                    .read()
+                    .unwrap()
+                    .portal()
+                    .all()
+                    .iter()
+                    .map(PortalDto::from)
+                    .collect();
+                lua_ctx.to_value(&portals)
+            })
+            .unwrap();
+        table.set("all", all).unwrap();
+
+        // Expose api.portal.at to Lua
+        let core_clone = Arc::clone(&core);
+        let at = lua
+            .create_function(move |lua_ctx, (x, y): (i32, i32)| {
+                portal_value(lua_ctx, core_clone.read().unwrap().portal().at(x, y))
+            })
+            .unwrap();
+        table.set("at", at).unwrap();
+    }
+
+    fn setup_group_api(lua: &Lua, table: &Table, core: Arc<RwLock<CoreApi>>) {
+        // Groups reach Lua as their DTO, { id, name, kind, members, inventory }
+        fn group_result(lua: &Lua, result: Result<logic::Group, String>) -> LuaResult<Value> {
+            match result {
+                Ok(group) => lua.to_value(&GroupDto::from(&group)),
+                Err(e) => Err(mlua::Error::RuntimeError(e)),
+            }
+        }
+
+        // Expose api.group.create to Lua, without a shared inventory unless asked for
+        let core_clone = Arc::clone(&core);
+        let create = lua
+            .create_function(
+                move |lua_ctx, (name, kind, shared_inventory): (String, String, Option<bool>)| {
+                    let result = core_clone.read().unwrap().group().create(
+                        &name,
+                        &kind,
+                        shared_inventory.unwrap_or(false),
+                    );
+                    group_result(lua_ctx, result)
+                },
+            )
+            .unwrap();
+        table.set("create", create).unwrap();
+
+        // Expose api.group.join to Lua
+        let core_clone = Arc::clone(&core);
+        let join = lua
+            .create_function(move |lua_ctx, (id, person_id): (u32, u32)| {
+                let result = core_clone.read().unwrap().group().join(id, person_id);
+                group_result(lua_ctx, result)
+            })
+            .unwrap();
+        table.set("join", join).unwrap();
+
+        // Expose api.group.leave to Lua
+        let core_clone = Arc::clone(&core);
+        let leave = lua
+            .create_function(move |lua_ctx, (id, person_id): (u32, u32)| {
+                let result = core_clone.read().unwrap().group().leave(id, person_id);
+                group_result(lua_ctx, result)
+            })
+            .unwrap();
+        table.set("leave", leave).unwrap();
+
+        // Expose api.group.disband to Lua
+        let core_clone = Arc::clone(&core);
+        let disband = lua
+            .create_function(move |_, id: u32| Ok(core_clone.read().unwrap().group().disband(id)))
+            .unwrap();
+        table.set("disband", disband).unwrap();
+
+        // Expose api.group.get to Lua
+        let core_clone = Arc::clone(&core);
+        let get = lua
+            .create_function(move |lua_ctx, id: u32| {
+                match core_clone.read().unwrap().group().get(id) {
+                    Some(group) => lua_ctx.to_value(&GroupDto::from(&group)),
+                    None => Ok(Value::Nil),
+                }
+            })
+            .unwrap();
+        table.set("get", get).unwrap();
+
+        // Expose api.group.all to Lua
+        let core_clone = Arc::clone(&core);
+        let all = lua
+            .create_function(move |lua_ctx, ()| {
+                let groups: Vec<GroupDto> = core_clone
+                    .read()
+                    .unwrap()
+                    .group()
+                    .all()
+                    .iter()
+                    .map(GroupDto::from)
+                    .collect();
+                lua_ctx.to_value(&groups)
+            })
+            .unwrap();
+        table.set("all", all).unwrap();
+
+        // Expose api.group.of to Lua
+        let core_clone = Arc::clone(&core);
+        let of = lua
+            .create_function(move |_, person_id: u32| {
+                Ok(core_clone.read().unwrap().group().of(person_id))
+            })
+            .unwrap();
+        table.set("of", of).unwrap();
+
+        // Expose api.group.move_to to Lua, returns the members moved
+        let core_clone = Arc::clone(&core);
+        let move_to = lua
+            .create_function(move |lua_ctx, (id, x, y): (u32, i32, i32)| {
+                let persons = core_clone
+                    .read()
+                    .unwrap()
+                    .group()
+                    .move_to(id, x, y)
+                    .map_err(mlua::Error::RuntimeError)?;
+                let persons: Vec<PersonDto> = persons.iter().map(PersonDto::from).collect();
+                lua_ctx.to_value(&persons)
+            })
+            .unwrap();
+        table.set("move_to", move_to).unwrap();
+
+        // Expose api.group.go_to to Lua, returns the tasks queued
+        let core_clone = Arc::clone(&core);
+        let go_to = lua
+            .create_function(move |lua_ctx, (id, x, y): (u32, i32, i32)| {
+                let tasks = core_clone
+                    .read()
+                    .unwrap()
+                    .group()
+                    .go_to(id, x, y)
+                    .map_err(mlua::Error::RuntimeError)?;
+                let tasks: Vec<TaskDto> = tasks.iter().map(TaskDto::from).collect();
+                lua_ctx.to_value(&tasks)
+            })
+            .unwrap();
+        table.set("go_to", go_to).unwrap();
+
+        // Expose api.group.store to Lua
+        let core_clone = Arc::clone(&core);
+        let store = lua
+            .create_function(move |lua_ctx, (id, item): (u32, String)| {
+                let result = core_clone.read().unwrap().group().store(id, &item);
+                group_result(lua_ctx, result)
+            })
+            .unwrap();
+        table.set("store", store).unwrap();
+
+        // Expose api.group.take to Lua
+        let core_clone = Arc::clone(&core);
+        let take = lua
+            .create_function(move |lua_ctx, (id, item): (u32, String)| {
+                let result = core_clone.read().unwrap().group().take(id, &item);
+                group_result(lua_ctx, result)
+            })
+            .unwrap();
+        table.set("take", take).unwrap();
+    }
+
+    fn setup_map_api(lua: &Lua, table: &Table, core: Arc<RwLock<CoreApi>>) {
+        // Expose api.map.place to Lua, on the "ground" layer unless another one is given
+        let core_clone = Arc::clone(&core);
+        let place = lua
+            .create_function(
+                move |_, (x, y, tile, layer): (i32, i32, u32, Option<String>)| {
+                    core_clone
+                        .read()
+                        .unwrap()
+                        .map()
+                        .place(x, y, tile, layer.as_deref())
+                        .map_err(mlua::Error::RuntimeError)
+                },
+            )
+            .unwrap();
+        table.set("place", place).unwrap();
+
+        // Expose api.map.remove to Lua
+        let core_clone = Arc::clone(&core);
+        let remove = lua
+            .create_function(move |_, (x, y, layer): (i32, i32, Option<String>)| {
+                core_clone
+                    .read()
+                    .unwrap()
+                    .map()
+                    .remove(x, y, layer.as_deref())
+                    .map_err(mlua::Error::RuntimeError)
+            })
+            .unwrap();
+        table.set("remove", remove).unwrap();
+
+        // Expose api.map.lock to Lua, locked for the scripts unless another owner is given
+        let core_clone = Arc::clone(&core);
+        let lock = lua
+            .create_function(
+                move |lua_ctx, (x1, y1, x2, y2, owner): (i32, i32, i32, i32, Option<String>)| {
+                    let lock = core_clone
+                        .read()
+                        .unwrap()
+                        .map()
+                        .lock(x1, y1, x2, y2, owner.as_deref())
+                        .map_err(mlua::Error::RuntimeError)?;
+                    lua_ctx.to_value(&MapLockDto::from(&lock))
+                },
+            )
+            .unwrap();
+        table.set("lock", lock).unwrap();
+
+        // Expose api.map.unlock to Lua
+        let core_clone = Arc::clone(&core);
+        let unlock = lua
+            .create_function(move |_, id: u32| Ok(core_clone.read().unwrap().map().unlock(id)))
+            .unwrap();
+        table.set("unlock", unlock).unwrap();
+
+        // Expose api.map.lock_at to Lua
+        let core_clone = Arc::clone(&core);
+        let lock_at = lua
+            .create_function(move |lua_ctx, (x, y): (i32, i32)| {
+                match core_clone.read().unwrap().map().lock_at(x, y) {
+                    Some(lock) => lua_ctx.to_value(&MapLockDto::from(&lock)),
+                    None => Ok(Value::Nil),
+                }
+            })
+            .unwrap();
+        table.set("lock_at", lock_at).unwrap();
+
+        // Expose api.map.locks to Lua
+        let core_clone = Arc::clone(&core);
+        let locks = lua
+            .create_function(move |lua_ctx, ()| {
+                let locks: Vec<MapLockDto> = core_clone
+                    .read()
+                    .unwrap()
+                    .map()
+                    .locks()
+                    .iter()
+                    .map(MapLockDto::from)
+                    .collect();
+                lua_ctx.to_value(&locks)
+            })
+            .unwrap();
+        table.set("locks", locks).unwrap();
+    }
+
+    fn setup_stream_api(lua: &Lua, table: &Table, core: Arc<RwLock<CoreApi>>) {
+        // Expose api.stream.unload to Lua
+        let core_clone = Arc::clone(&core);
+        let unload = lua
+            .create_function(move |_, (x, y): (i32, i32)| {
+                core_clone
+                    .read()
+                    .unwrap()
+                    .stream()
+                    .unload(x, y)
+                    .map_err(mlua::Error::RuntimeError)
+            })
+            .unwrap();
+        table.set("unload", unload).unwrap();
+
+        // Expose api.stream.load to Lua
+        let core_clone = Arc::clone(&core);
+        let load = lua
+            .create_function(move |_, (x, y): (i32, i32)| {
+                core_clone
+                    .read()
+                    .unwrap()
+                    .stream()
+                    .load(x, y)
+                    .map_err(mlua::Error::RuntimeError)
+            })
+            .unwrap();
+        table.set("load", load).unwrap();
+
+        // Expose api.stream.is_loaded to Lua
+        let core_clone = Arc::clone(&core);
+        let is_loaded = lua
+            .create_function(move |_, (x, y): (i32, i32)| {
+                Ok(core_clone.read().unwrap().stream().is_loaded(x, y))
+            })
+            .unwrap();
+        table.set("is_loaded", is_loaded).unwrap();
+
+        // Expose api.stream.unloaded to Lua
+        let core_clone = Arc::clone(&core);
+        let unloaded = lua
+            .create_function(move |lua_ctx, ()| {
+                let chunks: Vec<ChunkDto> = core_clone
+                    .read()
+                    .unwrap()
+                    .stream()
+                    .unloaded()
+                    .iter()
+                    .map(ChunkDto::from)
+                    .collect();
+                lua_ctx.to_value(&chunks)
+            })
+            .unwrap();
+        table.set("unloaded", unloaded).unwrap();
+    }
+
+    fn setup_debug_api(lua: &Lua, table: &Table, core: Arc<RwLock<CoreApi>>) {
+        // Expose api.debug.state_hash to Lua, after the projections caught up so the locations
+        // hash the same as in a run that waited longer
+        let core_clone = Arc::clone(&core);
+        let state_hash = lua
+            .create_function(move |_, ()| {
+                let core = core_clone.read().unwrap();
+                core.wait_for_projections(Duration::from_millis(100));
+                Ok(core.debug().state_hash())
+            })
+            .unwrap();
+        table.set("state_hash", state_hash).unwrap();
+
+        // Expose api.debug.state_hashes to Lua
+        let core_clone = Arc::clone(&core);
+        let state_hashes = lua
+            .create_function(move |lua, ()| {
+                let core = core_clone.read().unwrap();
+                core.wait_for_projections(Duration::from_millis(100));
+                lua.create_table_from(core.debug().state_hashes())
+            })
+            .unwrap();
+        table.set("state_hashes", state_hashes).unwrap();
+    }
+
+    fn setup_world_api(lua: &Lua, table: &Table, core: Arc<RwLock<CoreApi>>) {
+        // Expose api.world.create to Lua
+        let core_clone = Arc::clone(&core);
+        let create = lua
+            .create_function(move |_, name: String| {
+                core_clone
+                    .read()
+                    .unwrap()
+                    .world()
+                    .create(&name)
+                    .map_err(mlua::Error::RuntimeError)
+            })
+            .unwrap();
+        table.set("create", create).unwrap();
+
+        // Expose api.world.switch to Lua
+        let core_clone = Arc::clone(&core);
+        let switch = lua
+            .create_function(move |_, name: String| {
+                core_clone
+                    .read()
+                    .unwrap()
+                    .world()
+                    .switch(&name)
+                    .map_err(mlua::Error::RuntimeError)
+            })
+            .unwrap();
+        table.set("switch", switch).unwrap();
+
+        // Expose api.world.current to Lua
+        let core_clone = Arc::clone(&core);
+        let current = lua
+            .create_function(move |_, ()| Ok(core_clone.read().unwrap().world().current()))
+            .unwrap();
+        table.set("current", current).unwrap();
+
+        // Expose api.world.switch_level to Lua
+        let core_clone = Arc::clone(&core);
+        let switch_level = lua
+            .create_function(move |_, z: i32| {
+                Ok(core_clone.read().unwrap().world().switch_level(z))
+            })
+            .unwrap();
+        table.set("switch_level", switch_level).unwrap();
+
+        // Expose api.world.level to Lua
+        let core_clone = Arc::clone(&core);
+        let level = lua
+            .create_function(move |_, ()| Ok(core_clone.read().unwrap().world().level()))
+            .unwrap();
+        table.set("level", level).unwrap();
+
+        // Expose api.world.all to Lua
+        let core_clone = Arc::clone(&core);
+        let all = lua
+            .create_function(move |_, ()| Ok(core_clone.read().unwrap().world().all()))
+            .unwrap();
+        table.set("all", all).unwrap();
+    }
+
+    fn setup_documentation(lua: &Lua) {
+        // Create the docs table
+        let docs_table = lua.create_table().unwrap();
+        let globals = lua.globals();
+        globals.set("docs", docs_table.clone()).unwrap();
+
+        // Get the API documentation from the generated code
+        let api_docs = docs::get_api_docs();
+
+        // Convert API docs to Lua tables
+        for (module_name, module_docs) in api_docs {
+            let module_table = lua.create_table().unwrap();
+            docs_table
+                .set(module_name.clone(), module_table.clone())
+                .unwrap();
+
+            for (method_name, method_doc) in module_docs.methods {
+                let method_table = lua.create_table().unwrap();
+                method_table
+                    .set("description", method_doc.description)
+                    .unwrap();
+
+                // Set parameters
+                let params_table = lua.create_table().unwrap();
+                for (i, param) in method_doc.params.iter().enumerate() {
+                    let param_table = lua.create_table().unwrap();
+                    param_table.set("name", param.name.clone()).unwrap();
+                    param_table.set("type", param.type_name.clone()).unwrap();
+                    param_table
+                        .set("description", param.description.clone())
+                        .unwrap();
+                    params_table.set(i + 1, &param_table).unwrap();
+                    params_table.set(param.name.clone(), param_table).unwrap();
+                }
+
+                method_table.set("params", params_table).unwrap();
+                method_table.set("returns", method_doc.returns).unwrap();
+
+                module_table.set(method_name, method_table).unwrap();
+            }
+        }
+
+        // Add help function
+        let help_fn = lua.create_function(|ctx, topic: Option<String>| {
+            let docs: Table = ctx.globals().get("docs")?;
+
+            match topic {
+                None => {
+                    // Level 1: List all modules
+                    let mut result = String::from("Available modules:\n");
+
+                    for pair in docs.pairs::<String, Table>() {
+                        let (module, _) = pair?;
+                        result.push_str(&format!("  {}\n", module));
+                    }
+
+                    result.push_str("\nUse help(\"module\") to see available methods.");
+                    Ok(result)
+                }
+                Some(topic) => {
+                    // Check if this is a module name or a method name
+                    let parts: Vec<&str> = topic.split('.').collect();
+
+                    if parts.len() == 1 {
+                        // Level 2: List all methods in a module
+                        let module = parts[0];
+                        let module_docs: LuaResult<Table> = docs.get(module);
+
+                        if let Ok(module_table) = module_docs {
+                            let mut result = format!("Methods in {} module:\n", module);
+
+                            for pair in module_table.pairs::<String, Table>() {
+                                let (method, _) = pair?;
+                                result.push_str(&format!("  {}.{}\n", module, method));
+                            }
+
+                            result.push_str("\nUse help(\"module.method\") to see method details.");
+                            Ok(result)
+                        } else {
+                            Ok(format!("Module '{}' not found. Use help() to see available modules.", module))
+                        }
+                    } else if parts.len() == 2 {
+                        // Level 3: Show details of a specific method
+                        let module = parts[0];
+                        let method = parts[1];
+
+                        // Get the module table
+                        let module_docs: LuaResult<Table> = docs.get(module);
+                        if let Ok(module_table) = module_docs {
+                            // Get the method documentation
+                            let method_docs: LuaResult<Table> = module_table.get(method);
+                            if let Ok(doc) = method_docs {
+                                // Format and return documentation
+                                let desc: String = doc.get("description")?;
+                                let params: Table = doc.get("params")?;
+                                let returns: String = doc.get("returns")?;
+
+                                let mut result = format!("--- {}\n\n", desc);
+                                result.push_str("Parameters:\n");
+
+                                // List parameters
+                                let param_count: i32 = params.len()?;
+                                for i in 1..=param_count {
+                                    let param: Table = params.get(i)?;
+                                    let name: String = param.get("name")?;
+                                    let type_name: String = param.get("type")?;
+                                    let param_desc: String = param.get("description").unwrap_or_default();
+
+                                    result.push_str(&format!("  {} ({})", name, type_name));
+                                    if !param_desc.is_empty() {
+                                        result.push_str(&format!(" - {}", param_desc));
+                                    }
+                                    result.push('\n');
+                                }
+
+                                result.push_str(&format!("\nReturns: {}", returns));
+                                Ok(result)
+                            } else {
+                                Ok(format!("Method '{}.{}' not found. Use help('{}') to see available methods.",
+                                           module, method, module))
+                            }
+                        } else {
+                            Ok(format!("Module '{}' not found. Use help() to see available modules.", module))
+                        }
+                    } else {
+                        Ok(format!("Invalid topic format: '{}'. Use help(), help(\"module\"), or help(\"module.method\").", topic))
+                    }
+                }
+            }
+        }).unwrap();
+
+        globals.set("help", help_fn).unwrap();
+    }
+}
+
+// Metadata values are booleans, numbers or strings, removing an entry takes api.meta.remove
+fn meta_value_from_lua(value: Value) -> LuaResult<MetaValue> {
+    match value {
+        Value::Boolean(b) => Ok(MetaValue::Boolean(b)),
+        Value::Integer(i) => Ok(MetaValue::Number(i as f64)),
+        Value::Number(n) => Ok(MetaValue::Number(n)),
+        Value::String(s) => Ok(MetaValue::Text(s.to_str()?.to_string())),
+        value => Err(mlua::Error::RuntimeError(format!(
+            "metadata values are booleans, numbers or strings, not a {}",
+            value.type_name()
+        ))),
+    }
+}
+
+pub(crate) fn meta_value_to_lua(lua: &Lua, value: &MetaValue) -> LuaResult<Value> {
+    match value {
+        MetaValue::Boolean(b) => Ok(Value::Boolean(*b)),
+        MetaValue::Number(n) => Ok(Value::Number(*n)),
+        MetaValue::Text(s) => Ok(Value::String(lua.create_string(s)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registered_modules_join_the_api() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let engine = LuaEngine::new(command_rx);
+        engine
+            .register_module("audio", |lua, table| {
+                table.set("volume", lua.create_function(|_, ()| Ok(0.5))?)
+            })
+            .unwrap();
+
+        let volume: f64 = engine.lua.load("api.audio.volume()").eval().unwrap();
+        assert_eq!(volume, 0.5);
+        let same: bool = engine.lua.load("api.v1.audio == api.audio").eval().unwrap();
+        assert!(same);
+        assert!(engine.register_module("person", |_, _| Ok(())).is_err());
+    }
+
+    #[test]
+    fn test_entities_take_tags_and_metadata() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let engine = LuaEngine::new(command_rx);
+        let result: String = engine
+            .lua
+            .load(
+                r#"
+                local ann = "person:" .. api.person.create("Ann", 0, 0).id
+                api.person.create("Bob", 1, 0)
+                api.tags.add(ann, "vip")
+                api.tags.add("person:1", "vip")
+                api.tags.remove("person:1", "vip")
+                api.meta.set(ann, "gold", 12)
+                api.meta.set(ann, "mood", "happy")
+                return table.concat(api.tags.find("vip"), ",") .. " "
+                    .. api.meta.get(ann, "gold") .. " " .. api.meta.all(ann).mood
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!(result, "person:0 12 happy");
+
+        let missing = engine.lua.load(r#"api.tags.add("person:7", "vip")"#).exec();
+        assert!(missing
+            .unwrap_err()
+            .to_string()
+            .contains("There is no person:7"));
+        let table = engine
+            .lua
+            .load(r#"api.meta.set("person:0", "x", {})"#)
+            .exec();
+        assert!(table.is_err());
+    }
+
+    #[test]
+    fn test_ticks_refresh_the_snapshot() {
+        let (command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        let snapshots = engine.snapshots.clone();
+        engine.run_script("api.person.create('Ann', 3, 4)").unwrap();
+        assert!(snapshots.latest().persons().is_empty());
+
+        let (done_tx, done_rx) = mpsc::channel();
+        command_tx
+            .send(LuaCommand::Tick {
+                dt: 0.1,
+                frame: 7,
+                done_tx,
+            })
+            .unwrap();
+        assert!(engine.process_command());
+        done_rx.recv().unwrap();
+
+        let snapshot = snapshots.latest();
+        assert_eq!(snapshot.tick(), 7);
+        assert_eq!(snapshot.people_at(3, 4), &[0]);
+    }
+
+    #[test]
+    fn test_fog_is_revealed_per_viewer() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let engine = LuaEngine::new(command_rx);
+        let result: String = engine
+            .lua
+            .load(
+                r#"
+                local revealed = api.fog.reveal(0, 0, 2, 1)
+                api.fog.hide(2, 0, 2, 1)
+                api.fog.reveal(5, 5, 5, 5, "pirates")
+                return revealed .. " " .. api.fog.visibility(1, 1) .. " "
+                    .. api.fog.visibility(2, 1) .. " " .. api.fog.visibility(5, 5) .. " "
+                    .. api.fog.explored("pirates")
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!(result, "6 visible explored unexplored 1");
+        assert!(engine.fog.has_viewer(PLAYER_VIEWER));
+        assert!(engine
+            .lua
+            .load("api.fog.reveal(0, 0, 1000, 1000)")
+            .exec()
+            .is_err());
+    }
+
+    #[test]
+    fn test_factions_own_persons_and_zones() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let engine = LuaEngine::new(command_rx);
+        let result: String = engine
+            .lua
+            .load(
+                r##"
+                local rival = api.faction.create("rival", "Rival Corp", "ai", "#ff0000")
+                local ann = api.person.create("Ann", 0, 0)
+                local zone = api.faction.create_zone(0, 0, 3, 3)
+                api.faction.transfer("person:" .. ann.id, "player")
+                api.faction.transfer(zone, "rival")
+                return rival.kind .. " " .. api.faction.owner(zone) .. " "
+                    .. table.concat(api.faction.owned("player"), ",") .. " "
+                    .. table.concat(api.faction.zones_at(2, 2), ",") .. " "
+                    .. #api.faction.all()
+                "##,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!(result, "ai rival person:0 zone:1 3");
+        assert_eq!(
+            engine.factions.owned_zones(),
+            vec![((0, 0, 3, 3), [255, 0, 0])]
+        );
+        for script in [
+            "api.faction.transfer('zone:9', 'player')",
+            "api.faction.transfer('zone:1', 'pirates')",
+            "api.faction.create('pirates', 'Pirates', 'robots', '#000000')",
+        ] {
+            assert!(engine.lua.load(script).exec().is_err(), "{}", script);
+        }
+    }
+
+    #[test]
+    fn test_persons_work_on_their_tasks_every_tick() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                ann = api.person.create("Ann", 0, 0)
+                walk = api.task.go_to(ann.id, 2, 2)
+                haul = api.task.haul(ann.id, "crate", 1, 1, 0, 0)
+                api.task.prioritize(haul.id, 5, false)
+                done = {}
+                event_effects = { TaskCompleted = function(e) table.insert(done, e.task) end }
+                "#,
+            )
+            .unwrap();
+        for frame in 1..=4 {
+            engine.tick(0.1, frame);
+        }
+
+        let lua = &engine.lua;
+        let result: String = lua
+            .load(
+                r#"
+                local ann = api.person.get(ann.id)
+                local queue = api.task.queue_of(ann.id)
+                return ann.location.x .. "," .. ann.location.y .. " " .. #queue .. " "
+                    .. queue[1].kind .. " " .. tostring(queue[1].started)
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!(result, "1,1 1 go_to true");
+        // The haul ran first, the walk arrives on the next tick
+        for frame in 5..=6 {
+            let core = engine.core.clone();
+            core.read()
+                .unwrap()
+                .wait_for_projections(std::time::Duration::from_millis(100));
+            engine.tick(0.1, frame);
+        }
+        let done: Vec<u32> = engine.lua.load("done").eval().unwrap();
+        assert_eq!(done, vec![2, 1]);
+        assert!(!engine
+            .lua
+            .load("api.task.cancel(walk.id)")
+            .eval::<bool>()
+            .unwrap());
+        assert!(engine.lua.load("api.task.go_to(9, 0, 0)").exec().is_err());
+    }
+
+    #[test]
+    fn test_vehicles_carry_passengers_along_the_roads() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                for x = 0, 3 do api.vehicle.set_road(x, 0, 1) end
+                ann = api.person.create("Ann", 0, 0)
+                cart = api.vehicle.create(0, 0, 2)
+                api.vehicle.board(cart.id, ann.id)
+                api.vehicle.load(cart.id, "wood")
+                api.vehicle.drive_to(cart.id, 3, 0)
+                "#,
+            )
+            .unwrap();
+        // A second at 4 tiles per second gets the cart there
+        for frame in 1..=10 {
+            engine.tick(0.1, frame);
+        }
+
+        let result: String = engine
+            .lua
+            .load(
+                r#"
+                local cart = api.vehicle.get(cart.id)
+                api.vehicle.alight(cart.id, ann.id)
+                local ann = api.person.get(ann.id)
+                return cart.location.x .. " " .. ann.location.x .. " "
+                    .. #cart.passengers .. " " .. cart.cargo[1]
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!(result, "3 3 1 wood");
+        assert_eq!(engine.vehicles.positions(), vec![((3.0, 0.0), 1)]);
+        for script in [
+            "api.vehicle.drive_to(cart.id, 5, 5)",
+            "api.vehicle.board(cart.id, ann.id) api.vehicle.load(cart.id, 'stone')",
+            "api.vehicle.unload(cart.id, 'gold')",
+        ] {
+            assert!(engine.lua.load(script).exec().is_err(), "{}", script);
+        }
+    }
+
+    #[test]
+    fn test_groups_move_and_share_together() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                ann = api.person.create("Ann", 0, 0)
+                bob = api.person.create("Bob", 4, 4)
+                smiths = api.group.create("Smiths", "household", true)
+                api.group.join(smiths.id, ann.id)
+                api.group.join(smiths.id, bob.id)
+                api.group.store(smiths.id, "bread")
+                moved = #api.group.move_to(smiths.id, 2, 3)
+                crew = api.group.create("Miners", "crew")
+                api.group.join(crew.id, ann.id)
+                "#,
+            )
+            .unwrap();
+        // Memberships are read from their projection
+        assert!(engine
+            .core
+            .read()
+            .unwrap()
+            .wait_for_projections(std::time::Duration::from_secs(5)));
+
+        let result: String = engine
+            .lua
+            .load(
+                r#"
+                local smiths = api.group.get(smiths.id)
+                local bob = api.person.get(bob.id)
+                return moved .. " " .. bob.location.x .. "," .. bob.location.y .. " "
+                    .. smiths.inventory[1] .. " " .. table.concat(api.group.of(ann.id), ",")
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!(result, "2 2,3 bread 1,2");
+        for script in [
+            "api.group.create('Joneses', 'guild')",
+            "api.group.join(api.group.create('Joneses', 'household').id, ann.id)",
+            "api.group.store(crew.id, 'pickaxe')",
+        ] {
+            assert!(engine.lua.load(script).exec().is_err(), "{}", script);
+        }
+    }
+
+    #[test]
+    fn test_map_edits_are_published_for_the_frontends() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                placed = {}
+                event_effects = {
+                    TilePlaced = function(event) table.insert(placed, event.layer .. event.tile) end,
+                }
+                api.map.place(2, 3, 17)
+                api.map.place(2, 3, 5, "objects")
+                api.map.place(4, 4, 9)
+                api.map.remove(4, 4)
+                "#,
+            )
+            .unwrap();
+
+        let changes = engine.map_edits.take_changes();
+        let cells: Vec<_> = changes
+            .iter()
+            .map(|change| {
+                let location = &change.location;
+                (location.x, location.y, change.layer.as_str(), change.tile)
+            })
+            .collect();
+        assert_eq!(
+            cells,
+            vec![
+                (2, 3, "ground", Some(17)),
+                (2, 3, "objects", Some(5)),
+                (4, 4, "ground", None),
+            ]
+        );
+        assert!(engine.map_edits.take_changes().is_empty());
+
+        // Events are published from the event store thread
+        let placed = || -> Vec<String> { engine.lua.globals().get("placed").unwrap() };
+        let started = std::time::Instant::now();
+        while placed().len() < 3 && started.elapsed() < std::time::Duration::from_secs(2) {
+            engine
+                .events
+                .dispatch(&engine.lua, &engine.notifications, &engine.tutorials);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert_eq!(placed(), vec!["ground17", "objects5", "ground9"]);
+    }
+
+    #[test]
+    fn test_locked_regions_keep_edits_of_others_out() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                site = api.map.lock(0, 0, 3, 3, "editor")
+                ok, reason = pcall(api.map.place, 1, 1, 17)
+                reason = tostring(reason)
+                owner = api.map.lock_at(2, 2).owner
+                "#,
+            )
+            .unwrap();
+        let globals = engine.lua.globals();
+        assert!(!globals.get::<bool>("ok").unwrap());
+        assert!(globals
+            .get::<String>("reason")
+            .unwrap()
+            .contains("Tile 1,1 is locked by editor (lock 1)"));
+        assert_eq!(globals.get::<String>("owner").unwrap(), "editor");
+
+        // The editor of the frontend owns the lock, the scripts get in once it's gone
+        let edits = engine.map_edits.clone();
+        edits.place_at("main", 0, "ground", &[(1, 1)], 5).unwrap();
+        assert!(edits
+            .as_editor("peer")
+            .remove_at("main", 0, "ground", 1, 1)
+            .is_err());
+        engine
+            .run_script("api.map.unlock(site.id) api.map.place(1, 1, 17)")
+            .unwrap();
+        let tiles: Vec<_> = edits
+            .take_changes()
+            .iter()
+            .map(|change| change.tile)
+            .collect();
+        assert_eq!(tiles, vec![Some(17)]);
+    }
+
+    #[test]
+    fn test_unloaded_chunks_take_their_persons_along() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                ann = api.person.create("Ann", 3, 4)
+                bob = api.person.create("Bob", 40, 4)
+                unloaded = {}
+                event_effects = {
+                    ChunkUnloaded = function(e) table.insert(unloaded, e.max_x .. ":" .. #e.persons) end,
+                }
+                written = api.stream.unload(0, 0)
+                found = pcall(api.person.get, ann.id)
+                loaded = api.stream.is_loaded(3, 4)
+                chunk = api.stream.unloaded()[1]
+                discrepancies = #api.debug.verify()
+                crowd = #api.occupancy.people_at("main", 0, 3, 4)
+                "#,
+            )
+            .unwrap();
+        let globals = engine.lua.globals();
+        assert_eq!(globals.get::<usize>("written").unwrap(), 1);
+        assert!(!globals.get::<bool>("found").unwrap());
+        assert!(!globals.get::<bool>("loaded").unwrap());
+        assert_eq!(globals.get::<usize>("discrepancies").unwrap(), 0);
+        assert_eq!(globals.get::<usize>("crowd").unwrap(), 0);
+        assert!(engine
+            .chunks
+            .is_unloaded(&logic::Chunk::new(&logic::MapId::main(), 0, 0, 0)));
+        assert!(engine.run_script("api.stream.unload(1, 1)").is_err());
+        for _ in 0..100 {
+            engine
+                .events
+                .dispatch(&engine.lua, &engine.notifications, &engine.tutorials);
+            if engine.lua.load("return #unloaded").eval::<usize>().unwrap() > 0 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        let result: String = engine
+            .lua
+            .load(
+                r#"
+                local back = api.stream.load(3, 4)
+                local ann = api.person.get(ann.id)
+                return chunk.x .. "," .. chunk.y .. " " .. back .. " " .. ann.location.x .. " "
+                    .. unloaded[1] .. " " .. #api.debug.verify()
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!(result, format!("0,0 1 3 {}:1 0", logic::CHUNK_SIZE - 1));
+    }
+
+    #[test]
+    fn test_persons_take_portals_on_their_way() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                ann = api.person.create("Ann", 0, 0)
+                dock = api.portal.create(1, 0, 50, 50)
+                api.task.go_to(ann.id, 52, 50)
+                traversed = {}
+                event_effects = {
+                    PortalTraversed = function(e) table.insert(traversed, e.portal .. ":" .. e.x) end,
+                }
+                "#,
+            )
+            .unwrap();
+        // To the dock, through it and two tiles on
+        for frame in 1..=4 {
+            let core = engine.core.clone();
+            core.read()
+                .unwrap()
+                .wait_for_projections(std::time::Duration::from_millis(100));
+            engine.tick(0.1, frame);
+        }
+
+        let result: String = engine
+            .lua
+            .load(
+                r#"
+                local ann = api.person.get(ann.id)
+                return ann.location.x .. "," .. ann.location.y .. " " .. table.concat(traversed)
+                    .. " " .. api.portal.at(50, 50).id
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!(result, "52,50 1:50 1");
+        assert!(engine
+            .lua
+            .load("api.portal.create(1, 0, 7, 7)")
+            .exec()
+            .is_err());
+    }
+
+    #[test]
+    fn test_persons_reach_other_maps_through_portals() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                api.world.create("station_2")
+                ann = api.person.create("Ann", 0, 0)
+                api.portal.create(1, 0, 5, 5, "station_2")
+                switched = {}
+                event_effects = {
+                    MapSwitched = function(e) table.insert(switched, e.from .. ">" .. e.to) end,
+                }
+                api.world.switch("station_2")
+                api.task.go_to(ann.id, 6, 5)
+                "#,
+            )
+            .unwrap();
+        // To the portal, through it and a tile on
+        for frame in 1..=3 {
+            let core = engine.core.clone();
+            core.read()
+                .unwrap()
+                .wait_for_projections(std::time::Duration::from_millis(100));
+            engine.tick(0.1, frame);
+        }
+        engine
+            .core
+            .read()
+            .unwrap()
+            .wait_for_projections(std::time::Duration::from_millis(100));
+
+        let result: String = engine
+            .lua
+            .load(
+                r#"
+                local ann = api.person.get(ann.id)
+                local here = #api.location.get_people_at(6, 5)
+                api.world.switch("main")
+                return ann.location.map .. " " .. ann.location.x .. "," .. ann.location.y .. " "
+                    .. here .. #api.location.get_people_at(6, 5) .. " "
+                    .. table.concat(api.world.all(), ",")
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!(result, "station_2 6,5 10 main,station_2");
+        assert_eq!(engine.world.active(), "main");
+        for script in [
+            "api.world.switch('station_3')",
+            "api.world.create('station_2')",
+        ] {
+            assert!(engine.lua.load(script).exec().is_err(), "{}", script);
+        }
+        engine
+            .core
+            .read()
+            .unwrap()
+            .wait_for_projections(std::time::Duration::from_millis(100));
+        engine.tick(0.1, 4);
+        let switched: Vec<String> = engine.lua.load("switched").eval().unwrap();
+        assert_eq!(switched, vec!["main>station_2", "station_2>main"]);
+    }
+
+    #[test]
+    fn test_persons_take_the_stairs_to_other_levels() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                ann = api.person.create("Ann", 0, 0)
+                api.portal.create(1, 0, 1, 0, nil, 1)
+                levels = {}
+                event_effects = {
+                    LevelSwitched = function(e) table.insert(levels, e.from .. ">" .. e.to) end,
+                }
+                api.world.switch_level(1)
+                api.task.go_to(ann.id, 3, 0)
+                "#,
+            )
+            .unwrap();
+        // To the stairs, up them and two tiles on
+        for frame in 1..=4 {
+            let core = engine.core.clone();
+            core.read()
+                .unwrap()
+                .wait_for_projections(std::time::Duration::from_millis(100));
+            engine.tick(0.1, frame);
+        }
+        engine
+            .core
+            .read()
+            .unwrap()
+            .wait_for_projections(std::time::Duration::from_millis(100));
+
+        let result: String = engine
+            .lua
+            .load(
+                r#"
+                local ann = api.person.get(ann.id)
+                local upstairs = #api.location.get_people_at(3, 0)
+                api.world.switch_level(0)
+                return ann.location.x .. "," .. ann.location.y .. "," .. ann.location.z .. " "
+                    .. upstairs .. #api.location.get_people_at(3, 0) .. " " .. table.concat(levels)
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!(result, "3,0,1 10 0>1");
+        assert_eq!(engine.world.level(), 0);
+    }
+
+    #[test]
+    fn test_metrics_are_sampled_every_tick() {
+        let (command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                "api.person.create('Ann', 0, 0)\n\
+                 mods.register('bank', { on_frame = function() api.metrics.record('gold', 5) end })",
+            )
+            .unwrap();
+        // The stats projection catches up from its own thread
+        std::thread::sleep(std::time::Duration::from_millis(50));
+
+        for frame in 1..=2 {
+            let (done_tx, done_rx) = mpsc::channel();
+            command_tx
+                .send(LuaCommand::Tick {
+                    dt: 0.1,
+                    frame,
+                    done_tx,
+                })
+                .unwrap();
+            assert!(engine.process_command());
+            done_rx.recv().unwrap();
+        }
+
+        assert_eq!(engine.metrics.samples("gold"), vec![(1.0, 5.0), (2.0, 5.0)]);
+        assert_eq!(engine.metrics.latest("persons"), Some(1.0));
+        let tick: f64 = engine
+            .lua
+            .load("api.metrics.series('persons')[2].tick")
+            .eval()
+            .unwrap();
+        assert_eq!(tick, 2.0);
     }
 }
@@ -0,0 +1,156 @@
+//! Lua time of the mods frame by frame, so the frontends can show which mod makes the game stutter
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+/// Frames the breakdown is kept for
+pub const MOD_BUDGET_FRAMES: usize = 120;
+
+/// Owner of the time of code no mod owns
+pub const UNOWNED: &str = "scripts";
+
+/// Milliseconds each owner's code ran in a frame, by owner
+pub type FrameTimes = BTreeMap<String, f64>;
+
+// Code of an owner running on a thread, with the time spent in code of others it called
+struct Running {
+    thread: ThreadId,
+    owner: String,
+    started: Instant,
+    nested: Duration,
+}
+
+#[derive(Default)]
+struct BudgetState {
+    // Innermost last, hooks and timers run on the engine thread but commands and UI handlers
+    // may call into the mods from the frontends at the same time
+    running: Vec<Running>,
+    current: FrameTimes,
+    frames: VecDeque<FrameTimes>,
+}
+
+/// Time of the Lua callbacks per owning mod over the last `MOD_BUDGET_FRAMES` frames. The hooks
+/// and settings handlers of a mod count for it, and so do the timers started while its code ran.
+#[derive(Clone, Default)]
+pub struct ModBudget {
+    state: Arc<Mutex<BudgetState>>,
+}
+
+impl ModBudget {
+    /// Mod whose code runs on this thread right now, None outside of any
+    pub fn owner(&self) -> Option<String> {
+        let thread = thread::current().id();
+        self.state
+            .lock()
+            .unwrap()
+            .running
+            .iter()
+            .rev()
+            .find(|running| running.thread == thread)
+            .map(|running| running.owner.clone())
+            .filter(|owner| owner != UNOWNED)
+    }
+
+    /// Run `f` as code of the mod, None for code no mod owns. The time of code of other owners
+    /// it calls counts for them only.
+    pub fn measure<R>(&self, owner: Option<&str>, f: impl FnOnce() -> R) -> R {
+        let thread = thread::current().id();
+        self.state.lock().unwrap().running.push(Running {
+            thread,
+            owner: owner.unwrap_or(UNOWNED).to_string(),
+            started: Instant::now(),
+            nested: Duration::ZERO,
+        });
+        let result = f();
+
+        let mut state = self.state.lock().unwrap();
+        let Some(index) = state
+            .running
+            .iter()
+            .rposition(|running| running.thread == thread)
+        else {
+            return result;
+        };
+        let running = state.running.remove(index);
+        let elapsed = running.started.elapsed();
+        if let Some(caller) = state
+            .running
+            .iter_mut()
+            .rev()
+            .find(|caller| caller.thread == thread)
+        {
+            caller.nested += elapsed;
+        }
+        *state.current.entry(running.owner).or_default() +=
+            elapsed.saturating_sub(running.nested).as_secs_f64() * 1000.0;
+        result
+    }
+
+    /// Close the frame measured, the engine calls it at the end of every tick
+    pub(crate) fn end_frame(&self) {
+        let mut state = self.state.lock().unwrap();
+        let frame = std::mem::take(&mut state.current);
+        state.frames.push_back(frame);
+        if state.frames.len() > MOD_BUDGET_FRAMES {
+            state.frames.pop_front();
+        }
+    }
+
+    /// The breakdown of the frames kept, oldest first
+    pub fn frames(&self) -> Vec<FrameTimes> {
+        self.state.lock().unwrap().frames.iter().cloned().collect()
+    }
+
+    /// Average milliseconds per frame of every owner over the frames kept, slowest first
+    pub fn averages(&self) -> Vec<(String, f64)> {
+        let state = self.state.lock().unwrap();
+        let mut totals = FrameTimes::new();
+        for frame in &state.frames {
+            for (owner, ms) in frame {
+                *totals.entry(owner.clone()).or_default() += ms;
+            }
+        }
+        let count = state.frames.len().max(1) as f64;
+        let mut averages: Vec<(String, f64)> = totals
+            .into_iter()
+            .map(|(owner, total)| (owner, total / count))
+            .collect();
+        averages.sort_by(|a, b| b.1.total_cmp(&a.1));
+        averages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lua_engine::LuaEngine;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_callbacks_count_for_the_mod_that_owns_them() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                local function busy() local x = 0 for i = 1, 200000 do x = x + i end end
+                mods.register("slow", { on_init = function() timer.every(0.05, busy) end })
+                mods.register("fast", { on_frame = function() end })
+                timer.every(0.05, function() end)
+                "#,
+            )
+            .unwrap();
+        engine.hooks.call(crate::lifecycle::Hook::Init, ());
+        engine.tick(0.1, 1);
+
+        let frames = engine.budget.frames();
+        let last = frames.last().unwrap();
+        assert_eq!(
+            last.keys().map(String::as_str).collect::<Vec<_>>(),
+            vec!["fast", "scripts", "slow"]
+        );
+        assert_eq!(engine.budget.averages()[0].0, "slow");
+        assert_eq!(engine.budget.owner(), None);
+    }
+}
@@ -0,0 +1,545 @@
+//! Settings of the mods, declared with the mod and shown as a settings panel by the frontends
+
+use crate::error_log::ErrorLog;
+use crate::mod_budget::ModBudget;
+use mlua::{FromLua, Function, IntoLua, Lua, Table, Value};
+use serde_json::{Map, Value as Json};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+/// Settings file of the frontends, in the working directory
+pub const MOD_SETTINGS_PATH: &str = "mod_settings.json";
+
+/// What a setting holds and which values it accepts
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingKind {
+    Boolean,
+    Number {
+        min: f64,
+        max: f64,
+        /// Granularity of the slider, None for any value in the range
+        step: Option<f64>,
+    },
+    Text,
+    /// One of the options
+    Choice(Vec<String>),
+}
+
+impl SettingKind {
+    // The value as this setting stores it, numbers are clamped to the range
+    fn accept(&self, value: SettingValue) -> Result<SettingValue, String> {
+        match (self, value) {
+            (SettingKind::Boolean, value @ SettingValue::Boolean(_)) => Ok(value),
+            (SettingKind::Number { min, max, .. }, SettingValue::Number(n)) => {
+                Ok(SettingValue::Number(n.clamp(*min, *max)))
+            }
+            (SettingKind::Text, value @ SettingValue::Text(_)) => Ok(value),
+            (SettingKind::Choice(options), SettingValue::Text(text)) => {
+                if options.contains(&text) {
+                    Ok(SettingValue::Text(text))
+                } else {
+                    Err(format!("'{}' is not one of: {}", text, options.join(", ")))
+                }
+            }
+            (kind, value) => Err(format!("Expected a {}, got {}", kind.type_name(), value)),
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            SettingKind::Boolean => "boolean",
+            SettingKind::Number { .. } => "number",
+            SettingKind::Text => "text",
+            SettingKind::Choice(_) => "choice",
+        }
+    }
+}
+
+/// Value of a setting
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingValue {
+    Boolean(bool),
+    Number(f64),
+    Text(String),
+}
+
+impl SettingValue {
+    fn to_json(&self) -> Json {
+        match self {
+            SettingValue::Boolean(b) => Json::Bool(*b),
+            SettingValue::Number(n) => Json::from(*n),
+            SettingValue::Text(text) => Json::String(text.clone()),
+        }
+    }
+
+    fn from_json(value: &Json) -> Option<Self> {
+        match value {
+            Json::Bool(b) => Some(SettingValue::Boolean(*b)),
+            Json::Number(n) => n.as_f64().map(SettingValue::Number),
+            Json::String(text) => Some(SettingValue::Text(text.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SettingValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingValue::Boolean(b) => write!(f, "{}", b),
+            SettingValue::Number(n) => write!(f, "{}", n),
+            SettingValue::Text(text) => write!(f, "'{}'", text),
+        }
+    }
+}
+
+impl IntoLua for SettingValue {
+    fn into_lua(self, lua: &Lua) -> mlua::Result<Value> {
+        match self {
+            SettingValue::Boolean(b) => Ok(Value::Boolean(b)),
+            SettingValue::Number(n) => Ok(Value::Number(n)),
+            SettingValue::Text(text) => text.into_lua(lua),
+        }
+    }
+}
+
+impl FromLua for SettingValue {
+    fn from_lua(value: Value, _: &Lua) -> mlua::Result<Self> {
+        match value {
+            Value::Boolean(b) => Ok(SettingValue::Boolean(b)),
+            Value::Integer(i) => Ok(SettingValue::Number(i as f64)),
+            Value::Number(n) => Ok(SettingValue::Number(n)),
+            Value::String(text) => Ok(SettingValue::Text(text.to_str()?.to_string())),
+            value => Err(mlua::Error::RuntimeError(format!(
+                "Settings are booleans, numbers or strings, got a {}",
+                value.type_name()
+            ))),
+        }
+    }
+}
+
+/// A setting of a mod with its current value
+#[derive(Debug, Clone, PartialEq)]
+pub struct Setting {
+    pub key: String,
+    /// Label shown in the settings panel
+    pub name: String,
+    pub kind: SettingKind,
+    pub default: SettingValue,
+    pub value: SettingValue,
+}
+
+#[derive(Default)]
+struct SettingsState {
+    // Settings of every mod in the order the mods and their settings were declared
+    mods: Vec<(String, Vec<Setting>)>,
+    // Values read from the settings file, by mod and key
+    saved: BTreeMap<String, BTreeMap<String, Json>>,
+    handlers: Vec<(String, Function)>,
+    // Settings changed since the handlers ran last, as (mod, key)
+    changed: Vec<(String, String)>,
+    path: Option<String>,
+}
+
+impl SettingsState {
+    fn setting_mut(&mut self, mod_name: &str, key: &str) -> Result<&mut Setting, String> {
+        self.mods
+            .iter_mut()
+            .find(|(name, _)| name == mod_name)
+            .ok_or_else(|| format!("Mod '{}' has no settings", mod_name))?
+            .1
+            .iter_mut()
+            .find(|setting| setting.key == key)
+            .ok_or_else(|| format!("Mod '{}' has no setting '{}'", mod_name, key))
+    }
+
+    // The saved values with the current ones of the loaded mods
+    fn to_json(&self) -> Json {
+        let mut saved = self.saved.clone();
+        for (mod_name, settings) in &self.mods {
+            let values = saved.entry(mod_name.clone()).or_default();
+            for setting in settings {
+                values.insert(setting.key.clone(), setting.value.to_json());
+            }
+        }
+        Json::Object(
+            saved
+                .into_iter()
+                .map(|(mod_name, values)| (mod_name, Json::Object(Map::from_iter(values))))
+                .collect(),
+        )
+    }
+}
+
+/// Settings of the mods, shared by the scripts and the settings panels of the frontends. Numbers
+/// are clamped to their range, change handlers run on the next tick:
+///
+/// ```lua
+/// mods.register("weather", { settings = {
+///     { key = "rain", name = "Chance of rain", type = "number", min = 0, max = 1, default = 0.2 },
+///     { key = "season", type = "choice", options = { "spring", "summer" } },
+/// } })
+/// local rain = api.settings.get("weather", "rain")
+/// api.settings.on_change("weather", function(key, value) print(key, "is now", value) end)
+/// ```
+#[derive(Clone, Default)]
+pub struct ModSettings {
+    state: Arc<Mutex<SettingsState>>,
+    error_log: ErrorLog,
+    // Times the change handlers for their mods
+    budget: ModBudget,
+}
+
+impl ModSettings {
+    pub(crate) fn install(lua: &Lua, error_log: ErrorLog, budget: ModBudget) -> Self {
+        let settings = Self {
+            state: Default::default(),
+            error_log,
+            budget,
+        };
+        let table: Table = lua
+            .globals()
+            .get::<Table>("api")
+            .and_then(|api| api.get("settings"))
+            .unwrap();
+
+        // Expose api.settings.get to Lua
+        {
+            let settings = settings.clone();
+            lua.create_function(move |_, (mod_name, key): (String, String)| {
+                settings
+                    .get(&mod_name, &key)
+                    .map_err(mlua::Error::RuntimeError)
+            })
+            .and_then(|f| table.set("get", f))
+            .unwrap();
+        }
+        // Expose api.settings.set to Lua
+        {
+            let settings = settings.clone();
+            lua.create_function(
+                move |_, (mod_name, key, value): (String, String, SettingValue)| {
+                    settings
+                        .set(&mod_name, &key, value)
+                        .map_err(mlua::Error::RuntimeError)
+                },
+            )
+            .and_then(|f| table.set("set", f))
+            .unwrap();
+        }
+        // Expose api.settings.on_change to Lua, the handler gets the key and the new value
+        {
+            let settings = settings.clone();
+            lua.create_function(move |_, (mod_name, handler): (String, Function)| {
+                settings
+                    .state
+                    .lock()
+                    .unwrap()
+                    .handlers
+                    .push((mod_name, handler));
+                Ok(())
+            })
+            .and_then(|f| table.set("on_change", f))
+            .unwrap();
+        }
+
+        settings
+    }
+
+    /// Declare the settings of a mod from the `settings` list it registered with. Declaring
+    /// them again keeps the values that still fit, so scripts can be reloaded.
+    pub(crate) fn declare(&self, mod_name: &str, schema: Table) -> mlua::Result<()> {
+        let declared = schema
+            .sequence_values::<Table>()
+            .map(|entry| parse_setting(mod_name, entry?))
+            .collect::<mlua::Result<Vec<Setting>>>()?;
+
+        let mut state = self.state.lock().unwrap();
+        let previous = state
+            .mods
+            .iter()
+            .position(|(name, _)| name == mod_name)
+            .map(|index| state.mods.remove(index).1)
+            .unwrap_or_default();
+        let saved = state.saved.get(mod_name).cloned().unwrap_or_default();
+        let settings = declared
+            .into_iter()
+            .map(|mut setting| {
+                let kept = previous
+                    .iter()
+                    .find(|old| old.key == setting.key)
+                    .map(|old| old.value.clone())
+                    .or_else(|| saved.get(&setting.key).and_then(SettingValue::from_json));
+                if let Some(value) = kept.and_then(|value| setting.kind.accept(value).ok()) {
+                    setting.value = value;
+                }
+                setting
+            })
+            .collect();
+        state.mods.push((mod_name.to_string(), settings));
+        Ok(())
+    }
+
+    /// Keep the values in the file at `path`, restoring the ones it has. A missing or broken
+    /// file starts with the defaults.
+    pub fn persist_to(&self, path: &str) {
+        let saved = match fs::read_to_string(path) {
+            Ok(content) => match serde_json::from_str::<Map<String, Json>>(&content) {
+                Ok(saved) => saved,
+                Err(e) => {
+                    println!("Failed to parse mod settings {}: {}", path, e);
+                    Map::new()
+                }
+            },
+            Err(_) => Map::new(),
+        };
+        let mut state = self.state.lock().unwrap();
+        state.saved = saved
+            .into_iter()
+            .filter_map(|(mod_name, values)| match values {
+                Json::Object(values) => Some((mod_name, values.into_iter().collect())),
+                _ => None,
+            })
+            .collect();
+        state.path = Some(path.to_string());
+        let SettingsState { mods, saved, .. } = &mut *state;
+        for (mod_name, settings) in mods {
+            for setting in settings {
+                let value = saved
+                    .get(mod_name)
+                    .and_then(|values| values.get(&setting.key))
+                    .and_then(SettingValue::from_json)
+                    .and_then(|value| setting.kind.accept(value).ok());
+                if let Some(value) = value {
+                    setting.value = value;
+                }
+            }
+        }
+    }
+
+    /// Every mod with settings and its settings, in declaration order
+    pub fn mods(&self) -> Vec<(String, Vec<Setting>)> {
+        self.state.lock().unwrap().mods.clone()
+    }
+
+    pub fn get(&self, mod_name: &str, key: &str) -> Result<SettingValue, String> {
+        let mut state = self.state.lock().unwrap();
+        Ok(state.setting_mut(mod_name, key)?.value.clone())
+    }
+
+    /// Change a setting, its handlers run on the next tick
+    pub fn set(&self, mod_name: &str, key: &str, value: SettingValue) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap();
+        let setting = state.setting_mut(mod_name, key)?;
+        let value = setting
+            .kind
+            .accept(value)
+            .map_err(|e| format!("Setting '{}' of mod '{}': {}", key, mod_name, e))?;
+        if setting.value == value {
+            return Ok(());
+        }
+        setting.value = value;
+        state.changed.push((mod_name.to_string(), key.to_string()));
+        if let Some(path) = &state.path {
+            let json = serde_json::to_string_pretty(&state.to_json()).unwrap();
+            fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        }
+        Ok(())
+    }
+
+    /// Set every setting of the mod back to its default
+    pub fn reset(&self, mod_name: &str) -> Result<(), String> {
+        let defaults: Vec<(String, SettingValue)> = self
+            .mods()
+            .into_iter()
+            .filter(|(name, _)| name == mod_name)
+            .flat_map(|(_, settings)| settings)
+            .map(|setting| (setting.key, setting.default))
+            .collect();
+        for (key, default) in defaults {
+            self.set(mod_name, &key, default)?;
+        }
+        Ok(())
+    }
+
+    /// Call the change handlers of the settings changed since the last call
+    pub(crate) fn dispatch(&self) {
+        // Handlers run without the lock, they may well change settings themselves
+        let (changed, handlers) = {
+            let mut state = self.state.lock().unwrap();
+            if state.changed.is_empty() {
+                return;
+            }
+            (std::mem::take(&mut state.changed), state.handlers.clone())
+        };
+        for (mod_name, key) in changed {
+            let Ok(value) = self.get(&mod_name, &key) else {
+                continue;
+            };
+            for (_, handler) in handlers.iter().filter(|(name, _)| *name == mod_name) {
+                let called = self.budget.measure(Some(&mod_name), || {
+                    handler.call::<()>((key.as_str(), value.clone()))
+                });
+                if let Err(e) = called {
+                    self.error_log
+                        .report(&format!("api.settings.on_change('{}')", mod_name), e);
+                }
+            }
+        }
+    }
+}
+
+fn parse_setting(mod_name: &str, entry: Table) -> mlua::Result<Setting> {
+    let key: String = entry.get("key")?;
+    let error = |message: String| {
+        mlua::Error::RuntimeError(format!(
+            "Setting '{}' of mod '{}': {}",
+            key, mod_name, message
+        ))
+    };
+    let kind = match entry.get::<String>("type")?.as_str() {
+        "boolean" => SettingKind::Boolean,
+        "number" => {
+            let min = entry.get::<Option<f64>>("min")?.unwrap_or(f64::MIN);
+            let max = entry.get::<Option<f64>>("max")?.unwrap_or(f64::MAX);
+            if min > max {
+                return Err(error(format!("min {} is above max {}", min, max)));
+            }
+            SettingKind::Number {
+                min,
+                max,
+                step: entry.get("step")?,
+            }
+        }
+        "text" => SettingKind::Text,
+        "choice" => {
+            let options: Vec<String> = entry.get("options")?;
+            if options.is_empty() {
+                return Err(error("a choice needs options".to_string()));
+            }
+            SettingKind::Choice(options)
+        }
+        other => {
+            return Err(error(format!(
+                "unknown type '{}', expected boolean, number, text or choice",
+                other
+            )))
+        }
+    };
+    let default = match entry.get::<Option<SettingValue>>("default")? {
+        Some(value) => kind.accept(value).map_err(error)?,
+        None => match &kind {
+            SettingKind::Boolean => SettingValue::Boolean(false),
+            SettingKind::Number { min, max, .. } => SettingValue::Number(0.0_f64.clamp(*min, *max)),
+            SettingKind::Text => SettingValue::Text(String::new()),
+            SettingKind::Choice(options) => SettingValue::Text(options[0].clone()),
+        },
+    };
+    Ok(Setting {
+        name: entry
+            .get::<Option<String>>("name")?
+            .unwrap_or_else(|| key.clone()),
+        key,
+        kind,
+        value: default.clone(),
+        default,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lua_engine::LuaEngine;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_registered_settings_start_at_their_defaults() {
+        let (_, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                mods.register("weather", { settings = {
+                    { key = "rain", type = "number", min = 0, max = 1, default = 0.2 },
+                    { key = "season", name = "Season", type = "choice", options = { "spring", "summer" } },
+                } })
+                "#,
+            )
+            .unwrap();
+
+        let (rain, season): (f64, String) = engine
+            .lua
+            .load(r#"return api.settings.get("weather", "rain"), api.settings.get("weather", "season")"#)
+            .eval()
+            .unwrap();
+        assert_eq!((rain, season.as_str()), (0.2, "spring"));
+        assert_eq!(engine.settings.mods()[0].1[1].name, "Season");
+    }
+
+    #[test]
+    fn test_changes_are_checked_and_reach_the_handlers_on_the_next_tick() {
+        let (_, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                changes = {}
+                mods.register("weather", { settings = {
+                    { key = "rain", type = "number", min = 0, max = 1 },
+                    { key = "season", type = "choice", options = { "spring", "summer" } },
+                } })
+                api.settings.on_change("weather", function(key, value)
+                    table.insert(changes, key .. "=" .. tostring(value))
+                end)
+                api.settings.set("weather", "rain", 5)
+                "#,
+            )
+            .unwrap();
+        assert!(engine
+            .run_script(r#"api.settings.set("weather", "season", "winter")"#)
+            .is_err());
+        assert!(engine
+            .run_script(r#"api.settings.set("weather", "rain", true)"#)
+            .is_err());
+        engine
+            .settings
+            .set(
+                "weather",
+                "season",
+                super::SettingValue::Text("summer".to_string()),
+            )
+            .unwrap();
+
+        engine.tick(0.1, 1);
+
+        let changes: Vec<String> = engine.lua.globals().get("changes").unwrap();
+        assert_eq!(changes, ["rain=1", "season=summer"]);
+    }
+
+    #[test]
+    fn test_persisted_values_outlive_the_engine() {
+        let path = std::env::temp_dir().join(format!("mod_settings_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let script =
+            r#"mods.register("weather", { settings = { { key = "storms", type = "boolean" } } })"#;
+        for value in [true, false] {
+            let (_, command_rx) = mpsc::channel();
+            let mut engine = LuaEngine::new(command_rx);
+            engine.settings.persist_to(path);
+            engine.run_script(script).unwrap();
+            let storms: bool = engine
+                .lua
+                .load(r#"return api.settings.get("weather", "storms")"#)
+                .eval()
+                .unwrap();
+            assert_eq!(storms, !value);
+            engine
+                .run_script(&format!(
+                    r#"api.settings.set("weather", "storms", {})"#,
+                    value
+                ))
+                .unwrap();
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,326 @@
+//! Notifications for the player, routed from domain events through the `notify` global
+
+use crate::error_log::ErrorLog;
+use mlua::{Function, Lua, Table, Value};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// Seconds a toast is shown
+pub const TOAST_DURATION: f64 = 4.0;
+// Toasts shown at once, older ones make room for new ones
+const MAX_TOASTS: usize = 5;
+// Notifications kept in the event log
+const LOG_SIZE: usize = 200;
+
+/// A message for the player
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub category: String,
+    pub text: String,
+    /// Tick it was raised at
+    pub tick: u64,
+}
+
+/// A notification shown on screen for a while
+#[derive(Debug, Clone)]
+pub struct Toast {
+    pub notification: Notification,
+    /// Seconds until it disappears
+    pub remaining: f64,
+}
+
+#[derive(Clone)]
+enum Template {
+    Text(String),
+    Function(Function),
+}
+
+#[derive(Clone)]
+struct Route {
+    category: String,
+    template: Template,
+}
+
+#[derive(Default)]
+struct NotificationState {
+    routes: HashMap<String, Vec<Route>>,
+    // Every category seen so far and whether it's muted
+    categories: BTreeMap<String, bool>,
+    toasts: Vec<Toast>,
+    log: VecDeque<Notification>,
+    tick: u64,
+}
+
+/// Routes of the `notify` global with the toasts and the event log they fill. Templates fill
+/// `{field}` with the fields of the event, template functions may return nil to skip it. Every
+/// notification goes to the event log, only those of categories that aren't muted become toasts:
+///
+/// ```lua
+/// notify.route("PersonCreated", { category = "people", template = "{name} arrived at {x},{y}" })
+/// notify.post("weather", "It starts to rain")
+/// notify.mute("people")
+/// ```
+#[derive(Clone)]
+pub struct Notifications {
+    state: Arc<Mutex<NotificationState>>,
+    error_log: ErrorLog,
+}
+
+impl Notifications {
+    pub(crate) fn install(lua: &Lua, error_log: ErrorLog) -> Self {
+        let notifications = Self {
+            state: Default::default(),
+            error_log,
+        };
+
+        let table = lua.create_table().unwrap();
+        {
+            let notifications = notifications.clone();
+            lua.create_function(move |_, (kind, options): (String, Table)| {
+                let template = match options.get::<Value>("template")? {
+                    Value::String(text) => Template::Text(text.to_str()?.to_string()),
+                    Value::Function(function) => Template::Function(function),
+                    value => {
+                        return Err(mlua::Error::RuntimeError(format!(
+                            "Route of {} needs a template string or function, got a {}",
+                            kind,
+                            value.type_name()
+                        )));
+                    }
+                };
+                let category = options
+                    .get::<Option<String>>("category")?
+                    .unwrap_or_else(|| kind.clone());
+                notifications.route(&kind, category, template);
+                Ok(())
+            })
+            .and_then(|f| table.set("route", f))
+            .unwrap();
+        }
+        {
+            let notifications = notifications.clone();
+            lua.create_function(move |_, (category, text): (String, String)| {
+                notifications.post(&category, &text);
+                Ok(())
+            })
+            .and_then(|f| table.set("post", f))
+            .unwrap();
+        }
+        for (name, muted) in [("mute", true), ("unmute", false)] {
+            let notifications = notifications.clone();
+            lua.create_function(move |_, category: String| {
+                notifications.set_muted(&category, muted);
+                Ok(())
+            })
+            .and_then(|f| table.set(name, f))
+            .unwrap();
+        }
+        {
+            let notifications = notifications.clone();
+            lua.create_function(move |_, category: String| Ok(notifications.is_muted(&category)))
+                .and_then(|f| table.set("is_muted", f))
+                .unwrap();
+        }
+        lua.globals().set("notify", table).unwrap();
+
+        notifications
+    }
+
+    fn route(&self, kind: &str, category: String, template: Template) {
+        let mut state = self.state.lock().unwrap();
+        state.categories.entry(category.clone()).or_insert(false);
+        state
+            .routes
+            .entry(kind.to_string())
+            .or_default()
+            .push(Route { category, template });
+    }
+
+    /// Whether events of the kind have routes, so their tables are only built when needed
+    pub(crate) fn routes(&self, kind: &str) -> bool {
+        self.state.lock().unwrap().routes.contains_key(kind)
+    }
+
+    /// Raise the notifications of the routes of an event, `event` is its table
+    pub(crate) fn notify(&self, kind: &str, event: &Table) {
+        // Template functions run without the lock, they may well post themselves
+        let routes = self
+            .state
+            .lock()
+            .unwrap()
+            .routes
+            .get(kind)
+            .cloned()
+            .unwrap_or_default();
+        for route in routes {
+            let text = match &route.template {
+                Template::Text(template) => format_template(template, event).map(Some),
+                Template::Function(function) => function.call::<Option<String>>(event.clone()),
+            };
+            match text {
+                Ok(Some(text)) => self.post(&route.category, &text),
+                Ok(None) => {}
+                Err(e) => self
+                    .error_log
+                    .report(&format!("notify.route('{}')", kind), e),
+            }
+        }
+    }
+
+    /// Add a notification to the event log, and as toast unless its category is muted
+    pub fn post(&self, category: &str, text: &str) {
+        let mut state = self.state.lock().unwrap();
+        let notification = Notification {
+            category: category.to_string(),
+            text: text.to_string(),
+            tick: state.tick,
+        };
+        if state.log.len() == LOG_SIZE {
+            state.log.pop_front();
+        }
+        state.log.push_back(notification.clone());
+        if *state
+            .categories
+            .entry(category.to_string())
+            .or_insert(false)
+        {
+            return;
+        }
+        if state.toasts.len() == MAX_TOASTS {
+            state.toasts.remove(0);
+        }
+        state.toasts.push(Toast {
+            notification,
+            remaining: TOAST_DURATION,
+        });
+    }
+
+    /// Mute or unmute the toasts of a category
+    pub fn set_muted(&self, category: &str, muted: bool) {
+        let mut state = self.state.lock().unwrap();
+        state.categories.insert(category.to_string(), muted);
+        if muted {
+            state
+                .toasts
+                .retain(|toast| toast.notification.category != category);
+        }
+    }
+
+    pub fn is_muted(&self, category: &str) -> bool {
+        let state = self.state.lock().unwrap();
+        state.categories.get(category).copied().unwrap_or(false)
+    }
+
+    /// Every category routed or posted to so far with whether it's muted, by name
+    pub fn categories(&self) -> Vec<(String, bool)> {
+        let state = self.state.lock().unwrap();
+        state
+            .categories
+            .iter()
+            .map(|(category, muted)| (category.clone(), *muted))
+            .collect()
+    }
+
+    /// Toasts to show, oldest first
+    pub fn toasts(&self) -> Vec<Toast> {
+        self.state.lock().unwrap().toasts.clone()
+    }
+
+    /// The event log, oldest first
+    pub fn log(&self) -> Vec<Notification> {
+        self.state.lock().unwrap().log.iter().cloned().collect()
+    }
+
+    /// Age the toasts by `dt` seconds and drop the expired ones
+    pub(crate) fn update(&self, dt: f64, tick: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.tick = tick;
+        state.toasts.retain_mut(|toast| {
+            toast.remaining -= dt;
+            toast.remaining > 0.0
+        });
+    }
+}
+
+// Fill the `{field}` placeholders of a template, unknown fields stay as they are
+pub(crate) fn format_template(template: &str, event: &Table) -> mlua::Result<String> {
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(length) = rest[start..].find('}') else {
+            break;
+        };
+        text.push_str(&rest[..start]);
+        let placeholder = &rest[start..start + length + 1];
+        match event.get::<Value>(&placeholder[1..placeholder.len() - 1])? {
+            Value::String(s) => text.push_str(&s.to_str()?),
+            Value::Integer(i) => text.push_str(&i.to_string()),
+            Value::Number(n) => text.push_str(&n.to_string()),
+            Value::Boolean(b) => text.push_str(&b.to_string()),
+            _ => text.push_str(placeholder),
+        }
+        rest = &rest[start + length + 1..];
+    }
+    text.push_str(rest);
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routed_events_become_toasts_unless_muted() {
+        let lua = Lua::new();
+        let notifications = Notifications::install(&lua, ErrorLog::default());
+        lua.load(
+            r#"
+            notify.route("PersonCreated", { category = "people", template = "{name} at {x},{y} {missing}" })
+            notify.route("PersonCreated", { template = function(event)
+                if event.name ~= "Bob" then return nil end
+                return "Welcome " .. event.name
+            end })
+            "#,
+        )
+        .exec()
+        .unwrap();
+        assert!(notifications.routes("PersonCreated"));
+        assert!(!notifications.routes("PersonMoved"));
+
+        for (name, x) in [("Ann", 1), ("Bob", 2)] {
+            let event = lua.create_table().unwrap();
+            event.set("name", name).unwrap();
+            event.set("x", x).unwrap();
+            event.set("y", 4).unwrap();
+            notifications.notify("PersonCreated", &event);
+        }
+        let texts: Vec<_> = notifications
+            .toasts()
+            .into_iter()
+            .map(|toast| toast.notification.text)
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                "Ann at 1,4 {missing}",
+                "Bob at 2,4 {missing}",
+                "Welcome Bob"
+            ]
+        );
+
+        lua.load("notify.mute('people')").exec().unwrap();
+        notifications.post("people", "Carl at 0,0");
+        assert_eq!(notifications.toasts().len(), 1);
+        assert_eq!(notifications.log().len(), 4);
+        assert_eq!(
+            notifications.categories(),
+            vec![
+                ("PersonCreated".to_string(), false),
+                ("people".to_string(), true)
+            ]
+        );
+
+        notifications.update(TOAST_DURATION, 1);
+        assert!(notifications.toasts().is_empty());
+    }
+}
@@ -0,0 +1,355 @@
+//! A data overlay drawn translucently above the tiles, as the `overlay` global
+
+use crate::color::Rgba;
+use mlua::{Lua, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Opacity of the overlay unless a script sets one
+pub const DEFAULT_OPACITY: f32 = 0.5;
+
+/// Named color ramps of `overlay.ramp`, low values first
+pub const RAMPS: &[(&str, &[Rgba])] = &[
+    (
+        "heat",
+        &[
+            Rgba::rgb(0.2, 0.3, 0.9),
+            Rgba::rgb(0.95, 0.85, 0.2),
+            Rgba::rgb(0.9, 0.2, 0.2),
+        ],
+    ),
+    (
+        "value",
+        &[
+            Rgba::rgb(0.9, 0.2, 0.2),
+            Rgba::rgb(0.95, 0.85, 0.2),
+            Rgba::rgb(0.25, 0.75, 0.3),
+        ],
+    ),
+    (
+        "pollution",
+        &[Rgba::rgb(0.25, 0.75, 0.3), Rgba::rgb(0.45, 0.3, 0.15)],
+    ),
+    (
+        "gray",
+        &[Rgba::rgb(0.0, 0.0, 0.0), Rgba::rgb(1.0, 1.0, 1.0)],
+    ),
+];
+
+/// What the frontends draw next to the overlay
+#[derive(Debug, Clone, PartialEq)]
+pub struct Legend {
+    pub title: String,
+    pub min: f64,
+    pub max: f64,
+    /// Colors of the ramp, low values first
+    pub ramp: Vec<Rgba>,
+}
+
+struct OverlayState {
+    values: HashMap<(i32, i32), f64>,
+    ramp: Vec<Rgba>,
+    // A fixed range, the values span it otherwise
+    range: Option<(f64, f64)>,
+    title: String,
+    opacity: f32,
+    visible: bool,
+}
+
+impl Default for OverlayState {
+    fn default() -> Self {
+        Self {
+            values: HashMap::new(),
+            ramp: RAMPS[0].1.to_vec(),
+            range: None,
+            title: String::new(),
+            opacity: DEFAULT_OPACITY,
+            visible: false,
+        }
+    }
+}
+
+impl OverlayState {
+    fn range(&self) -> (f64, f64) {
+        self.range.unwrap_or_else(|| {
+            self.values
+                .values()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+                    (min.min(*value), max.max(*value))
+                })
+        })
+    }
+}
+
+/// Color of the way `t` from 0 to 1 along a ramp
+pub fn sample(ramp: &[Rgba], t: f64) -> Rgba {
+    match ramp {
+        [] => Rgba::new(0.0, 0.0, 0.0, 0.0),
+        [color] => *color,
+        _ => {
+            let position = t.clamp(0.0, 1.0) * (ramp.len() - 1) as f64;
+            let i = (position.floor() as usize).min(ramp.len() - 2);
+            ramp[i].lerp(ramp[i + 1], (position - i as f64) as f32)
+        }
+    }
+}
+
+/// Values of the `overlay` global with the ramp, range and legend they are drawn with. Values are
+/// colored along the ramp between the ends of the range, which spans the values set unless
+/// given. Setting a value shows the overlay:
+///
+/// ```lua
+/// overlay.title("Land value")
+/// overlay.ramp("heat")
+/// overlay.set(3, 0, 9)
+/// overlay.range(0, 100)
+/// ```
+#[derive(Clone, Default)]
+pub struct Overlay {
+    state: Arc<Mutex<OverlayState>>,
+}
+
+impl Overlay {
+    pub(crate) fn install(lua: &Lua) -> Self {
+        let overlay = Self::default();
+
+        let table = lua.create_table().unwrap();
+        {
+            let overlay = overlay.clone();
+            lua.create_function(move |_, (x, y, value): (i32, i32, Option<f64>)| {
+                overlay.set(x, y, value);
+                Ok(())
+            })
+            .and_then(|f| table.set("set", f))
+            .unwrap();
+        }
+        {
+            let overlay = overlay.clone();
+            lua.create_function(move |_, (x, y): (i32, i32)| {
+                Ok(overlay.state.lock().unwrap().values.get(&(x, y)).copied())
+            })
+            .and_then(|f| table.set("get", f))
+            .unwrap();
+        }
+        {
+            let overlay = overlay.clone();
+            lua.create_function(move |_, ()| {
+                overlay.state.lock().unwrap().values.clear();
+                Ok(())
+            })
+            .and_then(|f| table.set("clear", f))
+            .unwrap();
+        }
+        {
+            let overlay = overlay.clone();
+            // A name of RAMPS or a list of at least two colors
+            lua.create_function(move |lua, ramp: Value| {
+                let ramp = match ramp {
+                    Value::String(name) => {
+                        let name = name.to_str()?;
+                        RAMPS
+                            .iter()
+                            .find(|(ramp_name, _)| *ramp_name == &*name)
+                            .map(|(_, colors)| colors.to_vec())
+                            .ok_or_else(|| {
+                                mlua::Error::RuntimeError(format!(
+                                    "There is no ramp '{}', try one of {}",
+                                    &*name,
+                                    RAMPS
+                                        .iter()
+                                        .map(|(name, _)| *name)
+                                        .collect::<Vec<_>>()
+                                        .join(", ")
+                                ))
+                            })?
+                    }
+                    value => {
+                        let colors: Vec<Rgba> = lua.unpack(value)?;
+                        if colors.len() < 2 {
+                            return Err(mlua::Error::RuntimeError(
+                                "A ramp needs at least two colors".to_string(),
+                            ));
+                        }
+                        colors
+                    }
+                };
+                overlay.state.lock().unwrap().ramp = ramp;
+                Ok(())
+            })
+            .and_then(|f| table.set("ramp", f))
+            .unwrap();
+        }
+        {
+            let overlay = overlay.clone();
+            // Without arguments the range spans the values again
+            lua.create_function(move |_, (min, max): (Option<f64>, Option<f64>)| {
+                let range = min.zip(max);
+                if let Some((min, max)) = range
+                    && min >= max
+                {
+                    return Err(mlua::Error::RuntimeError(format!(
+                        "The range {}..{} is empty",
+                        min, max
+                    )));
+                }
+                overlay.state.lock().unwrap().range = range;
+                Ok(())
+            })
+            .and_then(|f| table.set("range", f))
+            .unwrap();
+        }
+        {
+            let overlay = overlay.clone();
+            lua.create_function(move |_, title: String| {
+                overlay.state.lock().unwrap().title = title;
+                Ok(())
+            })
+            .and_then(|f| table.set("title", f))
+            .unwrap();
+        }
+        {
+            let overlay = overlay.clone();
+            lua.create_function(move |_, opacity: f32| {
+                overlay.state.lock().unwrap().opacity = opacity.clamp(0.0, 1.0);
+                Ok(())
+            })
+            .and_then(|f| table.set("opacity", f))
+            .unwrap();
+        }
+        {
+            let overlay = overlay.clone();
+            lua.create_function(move |_, visible: bool| {
+                overlay.state.lock().unwrap().visible = visible;
+                Ok(())
+            })
+            .and_then(|f| table.set("show", f))
+            .unwrap();
+        }
+        lua.globals().set("overlay", table).unwrap();
+
+        overlay
+    }
+
+    /// Put a value on a tile, `None` takes it off. Shows the overlay.
+    pub fn set(&self, x: i32, y: i32, value: Option<f64>) {
+        let mut state = self.state.lock().unwrap();
+        match value {
+            Some(value) => {
+                state.values.insert((x, y), value);
+                state.visible = true;
+            }
+            None => {
+                state.values.remove(&(x, y));
+            }
+        }
+    }
+
+    /// The tiles with a value in their color, nothing while the overlay is hidden
+    pub fn tiles(&self) -> Vec<((i32, i32), Rgba)> {
+        let state = self.state.lock().unwrap();
+        if !state.visible {
+            return Vec::new();
+        }
+        let (min, max) = state.range();
+        state
+            .values
+            .iter()
+            .map(|(pos, value)| {
+                // All values alike sit in the middle of the ramp
+                let t = if max > min {
+                    (value - min) / (max - min)
+                } else {
+                    0.5
+                };
+                let color = sample(&state.ramp, t);
+                (
+                    *pos,
+                    Rgba {
+                        a: color.a * state.opacity,
+                        ..color
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// The legend to draw, none while the overlay is hidden or has no values
+    pub fn legend(&self) -> Option<Legend> {
+        let state = self.state.lock().unwrap();
+        if !state.visible || (state.values.is_empty() && state.range.is_none()) {
+            return None;
+        }
+        let (min, max) = state.range();
+        Some(Legend {
+            title: state.title.clone(),
+            min,
+            max,
+            ramp: state.ramp.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values_are_colored_along_the_ramp() {
+        let lua = Lua::new();
+        crate::color::install(&lua).unwrap();
+        let overlay = Overlay::install(&lua);
+        assert!(overlay.legend().is_none());
+
+        lua.load(
+            r#"
+            overlay.title("Pollution")
+            overlay.ramp({ "black", "white" })
+            overlay.opacity(1)
+            overlay.set(0, 0, 10)
+            overlay.set(1, 0, 20)
+            overlay.set(2, 0, 30)
+            "#,
+        )
+        .exec()
+        .unwrap();
+        let colors: HashMap<_, _> = overlay.tiles().into_iter().collect();
+        assert_eq!(colors[&(0, 0)], Rgba::rgb(0.0, 0.0, 0.0));
+        assert_eq!(colors[&(1, 0)], Rgba::rgb(0.5, 0.5, 0.5));
+        assert_eq!(colors[&(2, 0)], Rgba::rgb(1.0, 1.0, 1.0));
+        let legend = overlay.legend().unwrap();
+        assert_eq!(
+            (legend.title.as_str(), legend.min, legend.max),
+            ("Pollution", 10.0, 30.0)
+        );
+
+        // A fixed range clamps the values outside of it
+        lua.load("overlay.range(20, 25) overlay.set(1, 0, nil)")
+            .exec()
+            .unwrap();
+        let colors: HashMap<_, _> = overlay.tiles().into_iter().collect();
+        assert_eq!(colors.len(), 2);
+        assert_eq!(colors[&(0, 0)], Rgba::rgb(0.0, 0.0, 0.0));
+        assert_eq!(colors[&(2, 0)], Rgba::rgb(1.0, 1.0, 1.0));
+
+        lua.load("overlay.show(false)").exec().unwrap();
+        assert!(overlay.tiles().is_empty());
+        assert!(overlay.legend().is_none());
+    }
+
+    #[test]
+    fn test_ramps_are_checked() {
+        let lua = Lua::new();
+        crate::color::install(&lua).unwrap();
+        Overlay::install(&lua);
+
+        assert!(lua.load(r#"overlay.ramp("value")"#).exec().is_ok());
+        assert!(lua.load(r#"overlay.ramp("rainbow")"#).exec().is_err());
+        assert!(lua.load(r#"overlay.ramp({ "red" })"#).exec().is_err());
+        assert!(lua.load("overlay.range(5, 5)").exec().is_err());
+        assert_eq!(
+            sample(RAMPS[0].1, 0.5),
+            Rgba::rgb(0.95, 0.85, 0.2),
+            "the middle of a three color ramp is its middle color"
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Parameters tuning the running simulation, `api.params`
+
+use crate::error_log::ErrorLog;
+use crate::lua_engine::API_VERSION;
+use mlua::{Function, Lua, Table};
+use std::sync::{Arc, Mutex};
+
+/// How many simulated seconds pass per second in real time
+pub const SPEED: &str = "speed";
+/// Simulated seconds a day lasts
+pub const DAY_LENGTH: &str = "day_length";
+/// Persons added per simulated minute
+pub const SPAWN_RATE: &str = "spawn_rate";
+
+/// The parameters every game has as (key, name, min, max, default)
+const BUILT_IN: [(&str, &str, f64, f64, f64); 3] = [
+    (SPEED, "Simulation speed", 0.1, 8.0, 1.0),
+    (
+        DAY_LENGTH,
+        "Day length",
+        1.0,
+        3600.0,
+        crate::clock::DEFAULT_DAY_LENGTH,
+    ),
+    (SPAWN_RATE, "Spawns per minute", 0.0, 120.0, 0.0),
+];
+
+/// A simulation parameter with its current value
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub key: String,
+    /// Label shown next to its slider
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub default: f64,
+    pub value: f64,
+}
+
+#[derive(Default)]
+struct ParamsState {
+    // In the order they were defined, the built-in ones first
+    params: Vec<Param>,
+    handlers: Vec<(String, Function)>,
+    // Parameters changed since the handlers ran last, as (key, value before the change)
+    changed: Vec<(String, f64)>,
+}
+
+/// Simulation parameters, shared by the engine, the scripts and the sliders of the frontends.
+/// Values are clamped to their range, change handlers run on the next tick with the new and the
+/// previous value:
+///
+/// ```lua
+/// api.params.define("rain", { name = "Chance of rain", min = 0, max = 1, default = 0.2 })
+/// api.params.on_change("rain", function(value, old) print("rain", old, "->", value) end)
+/// api.params.set("speed", 2)
+/// ```
+#[derive(Clone, Default)]
+pub struct Params {
+    state: Arc<Mutex<ParamsState>>,
+    error_log: ErrorLog,
+}
+
+impl Params {
+    pub(crate) fn install(lua: &Lua, error_log: ErrorLog) -> Self {
+        let params = Self {
+            state: Default::default(),
+            error_log,
+        };
+        for (key, name, min, max, default) in BUILT_IN {
+            params.define(key, name, min, max, default).unwrap();
+        }
+
+        let table = lua.create_table().unwrap();
+        {
+            let params = params.clone();
+            lua.create_function(move |_, key: String| Ok(params.get(&key)))
+                .and_then(|f| table.set("get", f))
+                .unwrap();
+        }
+        {
+            let params = params.clone();
+            lua.create_function(move |_, (key, value): (String, f64)| {
+                params.set(&key, value).map_err(mlua::Error::RuntimeError)
+            })
+            .and_then(|f| table.set("set", f))
+            .unwrap();
+        }
+        {
+            let params = params.clone();
+            lua.create_function(move |_, (key, options): (String, Table)| {
+                let min = options.get::<Option<f64>>("min")?.unwrap_or(0.0);
+                let max = options.get::<Option<f64>>("max")?.unwrap_or(1.0);
+                let name = options
+                    .get::<Option<String>>("name")?
+                    .unwrap_or(key.clone());
+                let default = options.get::<Option<f64>>("default")?.unwrap_or(min);
+                params
+                    .define(&key, &name, min, max, default)
+                    .map_err(mlua::Error::RuntimeError)
+            })
+            .and_then(|f| table.set("define", f))
+            .unwrap();
+        }
+        {
+            let params = params.clone();
+            lua.create_function(move |lua, ()| {
+                let list = lua.create_table()?;
+                for param in params.list() {
+                    let entry = lua.create_table()?;
+                    entry.set("key", param.key)?;
+                    entry.set("name", param.name)?;
+                    entry.set("min", param.min)?;
+                    entry.set("max", param.max)?;
+                    entry.set("default", param.default)?;
+                    entry.set("value", param.value)?;
+                    list.push(entry)?;
+                }
+                Ok(list)
+            })
+            .and_then(|f| table.set("list", f))
+            .unwrap();
+        }
+        // The handler gets the new and the previous value
+        {
+            let params = params.clone();
+            lua.create_function(move |_, (key, handler): (String, Function)| {
+                params.state.lock().unwrap().handlers.push((key, handler));
+                Ok(())
+            })
+            .and_then(|f| table.set("on_change", f))
+            .unwrap();
+        }
+        let api: Table = lua.globals().get("api").unwrap();
+        api.get::<Table>(format!("v{}", API_VERSION))
+            .and_then(|latest| latest.set("params", table.clone()))
+            .and_then(|_| api.set("params", table))
+            .unwrap();
+
+        params
+    }
+
+    /// Add a parameter. Defining one again keeps its value if it still fits the range, so
+    /// scripts can be reloaded.
+    pub fn define(
+        &self,
+        key: &str,
+        name: &str,
+        min: f64,
+        max: f64,
+        default: f64,
+    ) -> Result<(), String> {
+        if !(min.is_finite() && max.is_finite() && default.is_finite()) || min > max {
+            return Err(format!(
+                "Parameter '{}' can't range from {} to {} with {} by default",
+                key, min, max, default
+            ));
+        }
+        let mut param = Param {
+            key: key.to_string(),
+            name: name.to_string(),
+            min,
+            max,
+            default: default.clamp(min, max),
+            value: default.clamp(min, max),
+        };
+        let mut state = self.state.lock().unwrap();
+        match state.params.iter_mut().find(|old| old.key == key) {
+            Some(old) => {
+                if (min..=max).contains(&old.value) {
+                    param.value = old.value;
+                }
+                *old = param;
+            }
+            None => state.params.push(param),
+        }
+        Ok(())
+    }
+
+    /// Value of a parameter, None if there is no such parameter
+    pub fn get(&self, key: &str) -> Option<f64> {
+        let state = self.state.lock().unwrap();
+        state
+            .params
+            .iter()
+            .find(|param| param.key == key)
+            .map(|param| param.value)
+    }
+
+    /// Change a parameter, clamped to its range. Returns the value it got, its handlers run on
+    /// the next tick.
+    pub fn set(&self, key: &str, value: f64) -> Result<f64, String> {
+        if !value.is_finite() {
+            return Err(format!("Parameter '{}' can't be {}", key, value));
+        }
+        let mut state = self.state.lock().unwrap();
+        let param = state
+            .params
+            .iter_mut()
+            .find(|param| param.key == key)
+            .ok_or_else(|| format!("There is no parameter '{}'", key))?;
+        let value = value.clamp(param.min, param.max);
+        if param.value == value {
+            return Ok(value);
+        }
+        let old = std::mem::replace(&mut param.value, value);
+        // Several changes before the handlers run are one change from the first value
+        if !state.changed.iter().any(|(changed, _)| changed == key) {
+            state.changed.push((key.to_string(), old));
+        }
+        Ok(value)
+    }
+
+    /// Every parameter in the order they were defined
+    pub fn list(&self) -> Vec<Param> {
+        self.state.lock().unwrap().params.clone()
+    }
+
+    /// Call the change handlers of the parameters changed since the last call
+    pub(crate) fn dispatch(&self) {
+        // Handlers run without the lock, they may well change parameters themselves
+        let (changed, handlers) = {
+            let mut state = self.state.lock().unwrap();
+            if state.changed.is_empty() {
+                return;
+            }
+            (std::mem::take(&mut state.changed), state.handlers.clone())
+        };
+        for (key, old) in changed {
+            let Some(value) = self.get(&key) else {
+                continue;
+            };
+            if value == old {
+                continue;
+            }
+            for (_, handler) in handlers.iter().filter(|(name, _)| *name == key) {
+                if let Err(e) = handler.call::<()>((value, old)) {
+                    self.error_log
+                        .report(&format!("api.params.on_change('{}')", key), e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lua_engine::LuaEngine;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_changes_are_clamped_and_notified_on_the_next_tick() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                changes = {}
+                api.params.define("rain", { min = 0, max = 1, default = 0.2 })
+                api.params.on_change("rain", function(value, old)
+                    table.insert(changes, old .. "->" .. value)
+                end)
+                clamped = api.params.set("rain", 5)
+                api.params.set("speed", 2)
+                "#,
+            )
+            .unwrap();
+        let changes =
+            |engine: &LuaEngine| engine.lua.globals().get::<Vec<String>>("changes").unwrap();
+        assert!(changes(&engine).is_empty());
+        assert_eq!(engine.lua.globals().get::<f64>("clamped").unwrap(), 1.0);
+
+        engine.tick(0.5, 1);
+        assert_eq!(changes(&engine), vec!["0.2->1"]);
+        // Twice the speed, a second simulated
+        assert_eq!(engine.clock.elapsed(), 1.0);
+        assert_eq!(engine.params.get("rain"), Some(1.0));
+        let keys: Vec<String> = engine.params.list().into_iter().map(|p| p.key).collect();
+        assert_eq!(keys, vec!["speed", "day_length", "spawn_rate", "rain"]);
+    }
+}
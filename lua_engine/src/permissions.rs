@@ -0,0 +1,145 @@
+//! What the scripts are allowed to do, `api.permissions`
+
+use crate::lua_engine::API_VERSION;
+use mlua::{Function, Lua, MultiValue, Result as LuaResult, Table};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Functions of the `api` modules that change the world, by module
+pub const MUTATING_API: &[(&str, &[&str])] = &[
+    ("api.person", &["create", "move_to", "move_all"]),
+    ("api.tags", &["add", "remove"]),
+    ("api.meta", &["set", "remove"]),
+    ("api.fog", &["reveal", "hide"]),
+    (
+        "api.faction",
+        &["create", "create_zone", "transfer", "release"],
+    ),
+    (
+        "api.task",
+        &["go_to", "work_at", "haul", "patrol", "cancel", "prioritize"],
+    ),
+    (
+        "api.vehicle",
+        &[
+            "create",
+            "board",
+            "alight",
+            "load",
+            "unload",
+            "drive_to",
+            "set_road",
+            "clear_road",
+        ],
+    ),
+    ("api.portal", &["create", "remove"]),
+    (
+        "api.group",
+        &[
+            "create", "join", "leave", "disband", "move_to", "go_to", "store", "take",
+        ],
+    ),
+    ("api.map", &["place", "remove", "lock", "unlock"]),
+    ("api.stream", &["unload", "load"]),
+    ("api.world", &["create"]),
+    ("api.import", &["persons"]),
+    ("api.routine", &["define", "assign", "unassign"]),
+    ("api.params", &["set"]),
+    ("api.time", &["set_day_length"]),
+];
+
+/// Whether the world may be changed, shared between the engine and the frontend. In spectator
+/// mode the functions of `MUTATING_API` fail while the ones reading the world keep working, only
+/// the frontend turns it on and off:
+///
+/// ```lua
+/// if api.permissions.spectating() then print(#api.person.get_all()) end
+/// ```
+#[derive(Clone, Default)]
+pub struct Permissions {
+    spectating: Arc<AtomicBool>,
+}
+
+impl Permissions {
+    pub(crate) fn install(lua: &Lua) -> Self {
+        let permissions = Self::default();
+
+        let table = lua.create_table().unwrap();
+        {
+            let permissions = permissions.clone();
+            lua.create_function(move |_, ()| Ok(permissions.spectating()))
+                .and_then(|f| table.set("spectating", f))
+                .unwrap();
+        }
+        let api: Table = lua.globals().get("api").unwrap();
+        api.get::<Table>(format!("v{}", API_VERSION))
+            .and_then(|latest| latest.set("permissions", table.clone()))
+            .and_then(|_| api.set("permissions", table))
+            .unwrap();
+
+        permissions
+    }
+
+    pub fn spectating(&self) -> bool {
+        self.spectating.load(Ordering::Relaxed)
+    }
+
+    pub fn set_spectating(&self, spectating: bool) {
+        self.spectating.store(spectating, Ordering::Relaxed);
+    }
+
+    /// Make the functions `names` of the table at `path`, like "api.person" or "map", fail in
+    /// spectator mode. Tables reachable under several names, like `api.v1.person`, are guarded
+    /// under all of them.
+    pub fn guard(&self, lua: &Lua, path: &str, names: &[&str]) -> LuaResult<()> {
+        let table = path
+            .split('.')
+            .try_fold(lua.globals(), |table, key| table.get::<Table>(key))?;
+        for name in names {
+            let function: Function = table.get(*name)?;
+            let permissions = self.clone();
+            let message = format!("{}.{} is not allowed in spectator mode", path, name);
+            let guarded = lua.create_function(move |_, args: MultiValue| {
+                if permissions.spectating() {
+                    return Err(mlua::Error::RuntimeError(message.clone()));
+                }
+                function.call::<MultiValue>(args)
+            })?;
+            table.set(*name, guarded)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lua_engine::LuaEngine;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_spectators_can_read_but_not_change_the_world() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script("ann = api.person.create('Ann', 0, 0)")
+            .unwrap();
+        engine.permissions.set_spectating(true);
+
+        let error = engine
+            .run_script("api.v1.person.create('Bob', 1, 0)")
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("api.person.create is not allowed in spectator mode"));
+        let (name, spectating): (String, bool) = engine
+            .lua
+            .load("return api.person.get(ann.id).name, api.permissions.spectating()")
+            .eval()
+            .unwrap();
+        assert_eq!(name, "Ann");
+        assert!(spectating);
+
+        engine.permissions.set_spectating(false);
+        engine.run_script("api.person.create('Bob', 1, 0)").unwrap();
+    }
+}
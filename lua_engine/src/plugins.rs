@@ -0,0 +1,284 @@
+//! Native plugins, shared libraries in the plugin directory extending the game without forking it
+
+use crate::lua_engine::LuaEngine;
+use libloading::Library;
+use logic::{CoreApi, DomainEvent};
+use mlua::{FromLua, IntoLua, Lua, MultiValue, Result as LuaResult, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, RwLock};
+use std::{env, fs};
+
+/// Changed whenever `PluginRegistrar` or `PluginValue` change in a way old plugins can't handle.
+/// Rust has no stable ABI, plugins must be built with the same compiler and `lua_engine` as the
+/// game, this only catches the obvious mismatches.
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Entry point every plugin exports, defined by `declare_plugin!`
+pub type PluginEntry = fn(&mut PluginRegistrar) -> Result<(), String>;
+
+/// Function of a plugin callable from Lua, errors become Lua errors
+pub type PluginFunction = Box<dyn Fn(Vec<PluginValue>) -> Result<PluginValue, String> + Send>;
+
+/// Export the entry point `register` of a plugin, a `cdylib` crate, together with the ABI version
+/// it was built for:
+///
+/// ```ignore
+/// fn register(registrar: &mut PluginRegistrar) -> Result<(), String> {
+///     registrar.register_function("weather", "is_raining", |_args| Ok(PluginValue::Boolean(false)));
+///     Ok(())
+/// }
+/// lua_engine::declare_plugin!(register);
+/// ```
+#[macro_export]
+macro_rules! declare_plugin {
+    ($register:path) => {
+        #[unsafe(no_mangle)]
+        pub static SB5S_PLUGIN_ABI_VERSION: u32 = $crate::plugins::PLUGIN_ABI_VERSION;
+
+        #[unsafe(no_mangle)]
+        pub fn sb5s_plugin_register(
+            registrar: &mut $crate::plugins::PluginRegistrar,
+        ) -> Result<(), String> {
+            let register: $crate::plugins::PluginEntry = $register;
+            register(registrar)
+        }
+    };
+}
+
+/// Lua values as plugins see them, their copy of mlua can't touch the game's Lua state. Tables
+/// with a sequence part become lists, other tables maps.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PluginValue {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    List(Vec<PluginValue>),
+    Map(BTreeMap<String, PluginValue>),
+}
+
+impl FromLua for PluginValue {
+    fn from_lua(value: Value, _: &Lua) -> LuaResult<Self> {
+        PluginValue::from_value(value)
+    }
+}
+
+impl PluginValue {
+    fn from_value(value: Value) -> LuaResult<Self> {
+        Ok(match value {
+            Value::Nil => PluginValue::Nil,
+            Value::Boolean(b) => PluginValue::Boolean(b),
+            Value::Integer(i) => PluginValue::Number(i as f64),
+            Value::Number(n) => PluginValue::Number(n),
+            Value::String(s) => PluginValue::String(s.to_str()?.to_string()),
+            Value::Table(table) if table.raw_len() > 0 => PluginValue::List(
+                table
+                    .sequence_values::<Value>()
+                    .map(|value| PluginValue::from_value(value?))
+                    .collect::<LuaResult<_>>()?,
+            ),
+            Value::Table(table) => PluginValue::Map(
+                table
+                    .pairs::<String, Value>()
+                    .map(|pair| {
+                        let (key, value) = pair?;
+                        Ok((key, PluginValue::from_value(value)?))
+                    })
+                    .collect::<LuaResult<_>>()?,
+            ),
+            value => {
+                return Err(mlua::Error::RuntimeError(format!(
+                    "a {} can't be passed to a plugin",
+                    value.type_name()
+                )));
+            }
+        })
+    }
+}
+
+impl IntoLua for PluginValue {
+    fn into_lua(self, lua: &Lua) -> LuaResult<Value> {
+        match self {
+            PluginValue::Nil => Ok(Value::Nil),
+            PluginValue::Boolean(b) => Ok(Value::Boolean(b)),
+            PluginValue::Number(n) => Ok(Value::Number(n)),
+            PluginValue::String(s) => s.into_lua(lua),
+            PluginValue::List(values) => lua.create_sequence_from(values)?.into_lua(lua),
+            PluginValue::Map(entries) => lua.create_table_from(entries)?.into_lua(lua),
+        }
+    }
+}
+
+/// What a plugin can extend the game with while it's loaded
+pub struct PluginRegistrar {
+    core: Arc<RwLock<CoreApi>>,
+    modules: BTreeMap<String, Vec<(String, PluginFunction)>>,
+}
+
+impl PluginRegistrar {
+    /// Add the function `api.<module>.<name>`, the module is created with its first function
+    pub fn register_function(
+        &mut self,
+        module: &str,
+        name: &str,
+        function: impl Fn(Vec<PluginValue>) -> Result<PluginValue, String> + Send + 'static,
+    ) {
+        self.modules
+            .entry(module.to_string())
+            .or_default()
+            .push((name.to_string(), Box::new(function)));
+    }
+
+    /// The domain services, for plugins acting on the world like scripts do
+    pub fn core(&self) -> Arc<RwLock<CoreApi>> {
+        self.core.clone()
+    }
+
+    /// Receive every domain event published from now on, for projections of the plugin.
+    /// Plugins load before any script runs, so they see the whole history of a new game.
+    pub fn subscribe_events(&self) -> Receiver<DomainEvent> {
+        self.core.read().unwrap().event().subscribe()
+    }
+}
+
+/// Libraries of the loaded plugins, kept open as long as the engine may call into them
+#[derive(Default)]
+pub struct Plugins {
+    libraries: Vec<(String, Library)>,
+}
+
+impl Plugins {
+    /// Names of the loaded plugins
+    pub fn names(&self) -> Vec<String> {
+        self.libraries
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+}
+
+impl LuaEngine {
+    /// Load every shared library in `dir` as a plugin, before the scripts using them run.
+    /// A missing directory means no plugins, broken plugins are skipped with the returned errors.
+    pub fn load_plugins(&mut self, dir: &str) -> Vec<String> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext == env::consts::DLL_EXTENSION)
+            })
+            .collect();
+        paths.sort();
+
+        let mut errors = Vec::new();
+        for path in paths {
+            match self.load_plugin(&path) {
+                Ok(name) => println!("Loaded plugin {}", name),
+                Err(e) => errors.push(format!("Failed to load plugin {}: {}", path.display(), e)),
+            }
+        }
+        errors
+    }
+
+    fn load_plugin(&mut self, path: &Path) -> Result<String, String> {
+        let stem = path
+            .file_stem()
+            .map_or_else(String::new, |stem| stem.to_string_lossy().to_string());
+        let name = stem
+            .strip_prefix(env::consts::DLL_PREFIX)
+            .unwrap_or(&stem)
+            .to_string();
+        // Safety: loading runs the initializers of the library, plugins are trusted native code
+        let library = unsafe { Library::new(path) }.map_err(|e| e.to_string())?;
+        // Safety: the symbols have the types `declare_plugin!` gives them
+        let register = unsafe {
+            let version = library
+                .get::<*const u32>(b"SB5S_PLUGIN_ABI_VERSION")
+                .map_err(|_| "not a plugin, SB5S_PLUGIN_ABI_VERSION is missing".to_string())?;
+            if **version != PLUGIN_ABI_VERSION {
+                return Err(format!(
+                    "built for plugin ABI {}, the game needs {}",
+                    **version, PLUGIN_ABI_VERSION
+                ));
+            }
+            *library
+                .get::<PluginEntry>(b"sb5s_plugin_register")
+                .map_err(|e| e.to_string())?
+        };
+
+        let mut registrar = PluginRegistrar {
+            core: self.core.clone(),
+            modules: BTreeMap::new(),
+        };
+        let result = register(&mut registrar);
+        // Kept open even when registering failed, the functions may be registered already
+        self.plugins.libraries.push((name.clone(), library));
+        result?;
+        for (module, functions) in registrar.modules {
+            self.register_module(&module, |lua, table| {
+                for (name, function) in functions {
+                    let function = lua.create_function(move |lua, args: MultiValue| {
+                        let args = args
+                            .into_iter()
+                            .map(|arg| PluginValue::from_lua(arg, lua))
+                            .collect::<LuaResult<_>>()?;
+                        function(args).map_err(mlua::Error::RuntimeError)
+                    })?;
+                    table.set(name, function)?;
+                }
+                Ok(())
+            })
+            .map_err(|e| e.to_string())?;
+        }
+        Ok(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_files_that_are_no_plugins_are_reported() {
+        let dir = env::temp_dir().join(format!("plugins_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let library = dir.join(format!("broken.{}", env::consts::DLL_EXTENSION));
+        fs::write(&library, "not a library").unwrap();
+        fs::write(dir.join("readme.txt"), "skipped").unwrap();
+
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        let errors = engine.load_plugins(dir.to_str().unwrap());
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("broken"));
+        assert!(engine.plugins.names().is_empty());
+        assert!(engine.load_plugins("no/such/dir").is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_plugin_values_convert_both_ways() {
+        let lua = Lua::new();
+        let value: PluginValue = lua.load("{ 1, 'two', { x = true } }").eval().unwrap();
+        let expected = PluginValue::List(vec![
+            PluginValue::Number(1.0),
+            PluginValue::String("two".to_string()),
+            PluginValue::Map(BTreeMap::from([(
+                "x".to_string(),
+                PluginValue::Boolean(true),
+            )])),
+        ]);
+        assert_eq!(value, expected);
+
+        lua.globals().set("value", expected).unwrap();
+        let x: bool = lua.load("value[3].x").eval().unwrap();
+        assert!(x);
+        assert!(lua.load("print").eval::<PluginValue>().is_err());
+    }
+}
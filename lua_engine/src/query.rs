@@ -0,0 +1,108 @@
+//! `api.query`, selections of persons built in Lua and run in Rust against the indexes
+
+use dto::PersonDto;
+use logic::{CoreApi, Person, PersonQuery};
+use mlua::{Lua, LuaSerdeExt, Result as LuaResult, Table, UserData, UserDataMethods};
+use std::sync::{Arc, RwLock};
+
+#[derive(Clone)]
+struct LuaPersonQuery {
+    query: PersonQuery,
+    core: Arc<RwLock<CoreApi>>,
+}
+
+impl LuaPersonQuery {
+    fn narrowed(&self, narrow: impl FnOnce(PersonQuery) -> PersonQuery) -> Self {
+        Self {
+            query: narrow(self.query.clone()),
+            core: Arc::clone(&self.core),
+        }
+    }
+
+    fn run(&self) -> LuaResult<Vec<Person>> {
+        self.core
+            .read()
+            .unwrap()
+            .query()
+            .persons(&self.query)
+            .map_err(mlua::Error::RuntimeError)
+    }
+}
+
+impl UserData for LuaPersonQuery {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method(
+            "at_rect",
+            |_, this, (x1, y1, x2, y2): (i32, i32, i32, i32)| {
+                Ok(this.narrowed(|query| query.at_rect(x1, y1, x2, y2)))
+            },
+        );
+        methods.add_method("with_tag", |_, this, tag: String| {
+            Ok(this.narrowed(|query| query.with_tag(&tag)))
+        });
+        methods.add_method("limit", |_, this, limit: usize| {
+            Ok(this.narrowed(|query| query.limit(limit)))
+        });
+        methods.add_method("get", |lua, this, ()| {
+            let persons: Vec<PersonDto> = this.run()?.iter().map(PersonDto::from).collect();
+            lua.to_value(&persons)
+        });
+        methods.add_method("ids", |_, this, ()| {
+            Ok(this
+                .run()?
+                .iter()
+                .map(|person| person.id.0)
+                .collect::<Vec<_>>())
+        });
+        methods.add_method("count", |_, this, ()| Ok(this.run()?.len()));
+    }
+}
+
+/// Add `api.query`. Every condition returns a new query, so a query can be kept and narrowed
+/// down in different ways. `get` returns the persons, `ids` their ids and `count` how many
+/// there are:
+///
+/// ```lua
+/// local staff = api.query.persons():at_rect(0, 0, 10, 10):with_tag("employee"):limit(50):get()
+/// ```
+pub(crate) fn setup_query_api(lua: &Lua, table: &Table, core: Arc<RwLock<CoreApi>>) {
+    // Expose api.query.persons to Lua
+    let persons = lua
+        .create_function(move |_, ()| {
+            Ok(LuaPersonQuery {
+                query: PersonQuery::new(),
+                core: Arc::clone(&core),
+            })
+        })
+        .unwrap();
+    table.set("persons", persons).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lua_engine::LuaEngine;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_queries_chain_in_lua() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let engine = LuaEngine::new(command_rx);
+        let result: String = engine
+            .lua
+            .load(
+                r#"
+                for i, name in ipairs({ "Ann", "Bob", "Cid" }) do
+                    local person = api.person.create(name, i * 5, 0)
+                    api.tags.add("person:" .. person.id, "employee")
+                end
+                local employees = api.query.persons():with_tag("employee")
+                local near = employees:at_rect(0, 0, 10, 10)
+                return near:get()[2].name .. " " .. near:count() .. " "
+                    .. employees:count() .. " " .. table.concat(employees:limit(1):ids(), ",")
+                "#,
+            )
+            .eval()
+            .unwrap();
+        assert_eq!(result, "Bob 2 3 0");
+    }
+}
@@ -0,0 +1,117 @@
+//! The queries the projections declare as read models, added to the `api` table and the docs
+
+use crate::event_export::whole_numbers;
+use crate::lua_engine::API_VERSION;
+use logic::ReadModels;
+use mlua::{Lua, LuaSerdeExt, MultiValue, Table};
+
+/// Add a module to `api`, and to `api.v<API_VERSION>`, for every read model with a function
+/// per query, and their docs to `docs`. Read models named like a module of the API already are
+/// left out:
+///
+/// ```lua
+/// print(#api.occupancy.people_at("main", 0, 3, 4))
+/// help("occupancy")
+/// ```
+pub(crate) fn install(lua: &Lua, read_models: &ReadModels) -> mlua::Result<()> {
+    let api: Table = lua.globals().get("api")?;
+    let latest: Table = api.get(format!("v{}", API_VERSION))?;
+    let docs: Table = lua.globals().get("docs")?;
+    for module in read_models.modules() {
+        if api.contains_key(module.as_str())? {
+            eprintln!(
+                "The read model '{}' is named like a module of the API",
+                module
+            );
+            continue;
+        }
+        let table = lua.create_table()?;
+        let module_docs = lua.create_table()?;
+        let queries = read_models
+            .with_model(&module, |model| {
+                model
+                    .queries
+                    .iter()
+                    .map(|query| {
+                        let params: Vec<(String, String)> = query
+                            .params
+                            .iter()
+                            .map(|param| (param.name.clone(), param.type_name.clone()))
+                            .collect();
+                        (
+                            query.name.clone(),
+                            query.description.clone(),
+                            params,
+                            query.returns.clone(),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        for (query, description, params, returns) in queries {
+            let read_models = read_models.clone();
+            let (module_name, query_name) = (module.clone(), query.clone());
+            let function = lua.create_function(move |lua, args: MultiValue| {
+                let args = args
+                    .into_iter()
+                    .map(|arg| lua.from_value(arg).map(whole_numbers))
+                    .collect::<mlua::Result<Vec<_>>>()?;
+                let result = read_models
+                    .call(&module_name, &query_name, args)
+                    .map_err(mlua::Error::RuntimeError)?;
+                lua.to_value(&result)
+            })?;
+            table.set(query.as_str(), function)?;
+
+            // The same shape as the docs of the other modules, see `help`
+            let method_docs = lua.create_table()?;
+            method_docs.set("description", description)?;
+            let params_docs = lua.create_table()?;
+            for (i, (name, type_name)) in params.into_iter().enumerate() {
+                let param = lua.create_table()?;
+                param.set("name", name.as_str())?;
+                param.set("type", type_name)?;
+                param.set("description", "")?;
+                params_docs.set(i + 1, &param)?;
+                params_docs.set(name, param)?;
+            }
+            method_docs.set("params", params_docs)?;
+            method_docs.set("returns", returns)?;
+            module_docs.set(query, method_docs)?;
+        }
+        latest.set(module.as_str(), table.clone())?;
+        api.set(module.as_str(), table)?;
+        docs.set(module, module_docs)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lua_engine::LuaEngine;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_read_models_are_queried_from_lua() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                local ann = api.person.create("Ann", 3, 4)
+                api.debug.verify() -- waits for the projections
+                people = api.occupancy.people_at("main", 0, 3, 4)
+                count = api.occupancy.occupied_count("main", 0)
+                described = docs.occupancy.people_at.params[3].name
+                "#,
+            )
+            .unwrap();
+        let globals = engine.lua.globals();
+        assert_eq!(globals.get::<Vec<u32>>("people").unwrap(), vec![0]);
+        assert_eq!(globals.get::<usize>("count").unwrap(), 1);
+        assert_eq!(globals.get::<String>("described").unwrap(), "x");
+        assert!(engine
+            .run_script("api.occupancy.people_at('main', 0)")
+            .is_err());
+    }
+}
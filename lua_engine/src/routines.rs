@@ -0,0 +1,315 @@
+//! Daily routines of the persons, `api.routine`
+
+use crate::clock::Clock;
+use crate::lua_engine::API_VERSION;
+use logic::CoreApi;
+use mlua::{Lua, Table};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, RwLock};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Activity {
+    GoTo(i32, i32),
+    WorkAt(i32, i32),
+    Patrol(Vec<(i32, i32)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    from: f64,
+    to: f64,
+    activity: Activity,
+}
+
+impl Entry {
+    // Entries ending before they start run over midnight
+    fn covers(&self, hour: f64) -> bool {
+        if self.from <= self.to {
+            (self.from..self.to).contains(&hour)
+        } else {
+            hour >= self.from || hour < self.to
+        }
+    }
+}
+
+struct Assignment {
+    routine: String,
+    // Entry followed and the task queued for it
+    entry: Option<usize>,
+    task: Option<u32>,
+}
+
+#[derive(Default)]
+struct RoutineState {
+    routines: HashMap<String, Vec<Entry>>,
+    assignments: BTreeMap<u32, Assignment>,
+}
+
+// An entry like { from = 9, to = 17, task = "work_at", x = 12, y = 4 }
+fn parse_entry(entry: &Table) -> mlua::Result<Entry> {
+    let hour = |key: &str| -> mlua::Result<f64> {
+        let hour: f64 = entry.get(key)?;
+        if !(0.0..=24.0).contains(&hour) {
+            return Err(mlua::Error::RuntimeError(format!(
+                "Hour {} = {} is not between 0 and 24",
+                key, hour
+            )));
+        }
+        Ok(hour)
+    };
+    let (from, to) = (hour("from")?, hour("to")?);
+    let task: String = entry.get("task")?;
+    let activity = match task.as_str() {
+        "go_to" => Activity::GoTo(entry.get("x")?, entry.get("y")?),
+        "work_at" => Activity::WorkAt(entry.get("x")?, entry.get("y")?),
+        "patrol" => Activity::Patrol(
+            entry
+                .get::<Vec<Table>>("route")?
+                .iter()
+                .map(|waypoint| Ok((waypoint.get("x")?, waypoint.get("y")?)))
+                .collect::<mlua::Result<_>>()?,
+        ),
+        _ => {
+            return Err(mlua::Error::RuntimeError(format!(
+                "Unknown routine task '{}', expected go_to, work_at or patrol",
+                task
+            )));
+        }
+    };
+    Ok(Entry { from, to, activity })
+}
+
+/// Routines of `api.routine` and the persons following them. When the hour of the day reaches an
+/// entry its task is queued for the person and the task of the entry before is cancelled, hours
+/// no entry covers leave the person to the other tasks:
+///
+/// ```lua
+/// api.routine.define("smith", {
+///     { from = 9, to = 17, task = "work_at", x = 12, y = 4 },
+///     { from = 22, to = 7, task = "go_to", x = 2, y = 3 },
+/// })
+/// api.routine.assign(ann.id, "smith")
+/// ```
+#[derive(Clone)]
+pub struct Routines {
+    state: Arc<Mutex<RoutineState>>,
+    core: Arc<RwLock<CoreApi>>,
+    clock: Clock,
+}
+
+impl Routines {
+    pub(crate) fn install(lua: &Lua, core: Arc<RwLock<CoreApi>>, clock: Clock) -> Self {
+        let routines = Self {
+            state: Default::default(),
+            core,
+            clock,
+        };
+
+        let table = lua.create_table().unwrap();
+        {
+            let routines = routines.clone();
+            // Defining a routine again changes it for everyone following it
+            lua.create_function(move |_, (name, entries): (String, Vec<Table>)| {
+                let entries = entries
+                    .iter()
+                    .map(parse_entry)
+                    .collect::<mlua::Result<Vec<_>>>()?;
+                let mut state = routines.state.lock().unwrap();
+                for assignment in state.assignments.values_mut() {
+                    if assignment.routine == name {
+                        assignment.entry = None;
+                    }
+                }
+                state.routines.insert(name, entries);
+                Ok(())
+            })
+            .and_then(|f| table.set("define", f))
+            .unwrap();
+        }
+        {
+            let routines = routines.clone();
+            // Replaces the routine the person followed, from the next tick on
+            lua.create_function(move |_, (person_id, name): (u32, String)| {
+                let mut state = routines.state.lock().unwrap();
+                if !state.routines.contains_key(&name) {
+                    return Err(mlua::Error::RuntimeError(format!(
+                        "There is no routine '{}'",
+                        name
+                    )));
+                }
+                let previous = state.assignments.insert(
+                    person_id,
+                    Assignment {
+                        routine: name,
+                        entry: None,
+                        task: None,
+                    },
+                );
+                drop(state);
+                routines.cancel(previous);
+                Ok(())
+            })
+            .and_then(|f| table.set("assign", f))
+            .unwrap();
+        }
+        {
+            let routines = routines.clone();
+            // Returns false if the person followed no routine, the task of it is cancelled
+            lua.create_function(move |_, person_id: u32| {
+                let previous = routines
+                    .state
+                    .lock()
+                    .unwrap()
+                    .assignments
+                    .remove(&person_id);
+                let followed = previous.is_some();
+                routines.cancel(previous);
+                Ok(followed)
+            })
+            .and_then(|f| table.set("unassign", f))
+            .unwrap();
+        }
+        {
+            let routines = routines.clone();
+            lua.create_function(move |_, person_id: u32| {
+                let state = routines.state.lock().unwrap();
+                Ok(state
+                    .assignments
+                    .get(&person_id)
+                    .map(|assignment| assignment.routine.clone()))
+            })
+            .and_then(|f| table.set("of", f))
+            .unwrap();
+        }
+        let api: Table = lua.globals().get("api").unwrap();
+        api.get::<Table>(format!("v{}", API_VERSION))
+            .and_then(|latest| latest.set("routine", table.clone()))
+            .and_then(|_| api.set("routine", table))
+            .unwrap();
+
+        routines
+    }
+
+    fn cancel(&self, assignment: Option<Assignment>) {
+        if let Some(task) = assignment.and_then(|assignment| assignment.task) {
+            self.core.read().unwrap().task().cancel(task);
+        }
+    }
+
+    /// Switch the persons whose routine reached another entry to its task. Persons that are gone
+    /// stop following their routine.
+    pub(crate) fn update(&self) {
+        let hour = self.clock.hour();
+        let core = self.core.read().unwrap();
+        let tasks = core.task();
+        let mut state = self.state.lock().unwrap();
+        let RoutineState {
+            routines,
+            assignments,
+        } = &mut *state;
+        assignments.retain(|person_id, assignment| {
+            let entries = &routines[&assignment.routine];
+            let entry = entries.iter().position(|entry| entry.covers(hour));
+            if entry == assignment.entry {
+                return true;
+            }
+            if let Some(task) = assignment.task.take() {
+                tasks.cancel(task);
+            }
+            assignment.entry = entry;
+            let Some(entry) = entry else {
+                return true;
+            };
+            // Work lasts until the entry is over and cancels it
+            let queued = match &entries[entry].activity {
+                Activity::GoTo(x, y) => tasks.go_to(*person_id, *x, *y),
+                Activity::WorkAt(x, y) => tasks.work_at(*person_id, *x, *y, u32::MAX),
+                Activity::Patrol(route) => tasks.patrol(*person_id, route),
+            };
+            match queued {
+                Ok(task) => {
+                    assignment.task = Some(task.id.0);
+                    true
+                }
+                Err(_) => false,
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lua_engine::LuaEngine;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_entries_cover_their_hours() {
+        let entry = |from, to| Entry {
+            from,
+            to,
+            activity: Activity::GoTo(0, 0),
+        };
+        assert!(entry(9.0, 17.0).covers(9.0));
+        assert!(!entry(9.0, 17.0).covers(17.0));
+        assert!(entry(22.0, 7.0).covers(23.5) && entry(22.0, 7.0).covers(3.0));
+        assert!(!entry(22.0, 7.0).covers(12.0));
+    }
+
+    #[test]
+    fn test_persons_switch_tasks_on_schedule() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                api.time.set_day_length(24)
+                ann = api.person.create("Ann", 0, 0)
+                api.routine.define("smith", {
+                    { from = 2, to = 6, task = "work_at", x = 3, y = 0 },
+                    { from = 6, to = 2, task = "go_to", x = 0, y = 0 },
+                })
+                api.routine.assign(ann.id, "smith")
+                "#,
+            )
+            .unwrap();
+        // An hour a tick
+        let mut kinds = Vec::new();
+        for frame in 1..=8 {
+            engine
+                .core
+                .read()
+                .unwrap()
+                .wait_for_projections(Duration::from_millis(100));
+            engine.tick(1.0, frame);
+            let kind: Option<String> = engine
+                .lua
+                .load("local queue = api.task.queue_of(ann.id) return queue[1] and queue[1].kind")
+                .eval()
+                .unwrap();
+            kinds.push(kind.unwrap_or_default());
+        }
+        let x: i32 = engine
+            .lua
+            .load("api.person.get(ann.id).location.x")
+            .eval()
+            .unwrap();
+        // At home until 2, working from 2 to 6, then heading home again
+        assert_eq!(
+            kinds,
+            vec!["", "work_at", "work_at", "work_at", "work_at", "go_to", "go_to", ""]
+        );
+        assert_eq!(x, 0);
+        assert!(engine
+            .lua
+            .load("api.routine.assign(ann.id, 'baker')")
+            .exec()
+            .is_err());
+        assert!(engine
+            .lua
+            .load("return api.routine.unassign(ann.id)")
+            .eval::<bool>()
+            .unwrap());
+    }
+}
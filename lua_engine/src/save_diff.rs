@@ -0,0 +1,292 @@
+//! Comparing two saves or event logs, to find where runs drifted apart or what a mod changed
+
+use serde_json::{Map, Value as Json};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+
+/// One thing that differs, `path` tells where, like `layers[0].tiles[3,4]` or `event 12.x`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Difference {
+    /// Only in the first file
+    Removed {
+        path: String,
+        value: Json,
+    },
+    /// Only in the second file
+    Added {
+        path: String,
+        value: Json,
+    },
+    Changed {
+        path: String,
+        from: Json,
+        to: Json,
+    },
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difference::Removed { path, value } => write!(f, "- {}: {}", path, value),
+            Difference::Added { path, value } => write!(f, "+ {}: {}", path, value),
+            Difference::Changed { path, from, to } => write!(f, "~ {}: {} -> {}", path, from, to),
+        }
+    }
+}
+
+/// Compare two files, event logs when they end in `.jsonl`, JSON saves otherwise. Lists of
+/// objects with an `id` are compared by their ids and lists of tiles `[x, y, ...]` by their
+/// position, so one added entity or tile doesn't shift everything after it.
+pub fn diff_files(left: &str, right: &str) -> Result<Vec<Difference>, String> {
+    let read = |path: &str| {
+        fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))
+    };
+    let (left_content, right_content) = (read(left)?, read(right)?);
+    if left.ends_with(".jsonl") && right.ends_with(".jsonl") {
+        let parse = |path: &str, content: &str| {
+            parse_event_log(content).map_err(|e| format!("Failed to parse {}: {}", path, e))
+        };
+        Ok(diff_event_logs(
+            &parse(left, &left_content)?,
+            &parse(right, &right_content)?,
+        ))
+    } else {
+        let parse = |path: &str, content: &str| {
+            serde_json::from_str(content).map_err(|e| format!("Failed to parse {}: {}", path, e))
+        };
+        Ok(diff_json(
+            &parse(left, &left_content)?,
+            &parse(right, &right_content)?,
+        ))
+    }
+}
+
+/// One event per non-empty line
+pub fn parse_event_log(content: &str) -> Result<Vec<Json>, String> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|e| format!("line {}: {}", index + 1, e))
+        })
+        .collect()
+}
+
+/// Compare the events at the same position, events are numbered from 1 like their `seq`
+pub fn diff_event_logs(left: &[Json], right: &[Json]) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    for index in 0..left.len().max(right.len()) {
+        let (left, right) = (left.get(index), right.get(index));
+        let kind = left.or(right).and_then(|event| event["kind"].as_str());
+        let path = match kind {
+            Some(kind) => format!("event {} ({})", index + 1, kind),
+            None => format!("event {}", index + 1),
+        };
+        match (left, right) {
+            (Some(left), Some(right)) if left["kind"] != right["kind"] => {
+                differences.push(Difference::Changed {
+                    path: format!("event {}", index + 1),
+                    from: left.clone(),
+                    to: right.clone(),
+                })
+            }
+            (Some(left), Some(right)) => diff_value(&path, left, right, &mut differences),
+            (Some(left), None) => differences.push(Difference::Removed {
+                path,
+                value: left.clone(),
+            }),
+            (None, Some(right)) => differences.push(Difference::Added {
+                path,
+                value: right.clone(),
+            }),
+            (None, None) => unreachable!(),
+        }
+    }
+    differences
+}
+
+/// Compare two JSON documents field by field
+pub fn diff_json(left: &Json, right: &Json) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    diff_value("", left, right, &mut differences);
+    differences
+}
+
+fn diff_value(path: &str, left: &Json, right: &Json, differences: &mut Vec<Difference>) {
+    match (left, right) {
+        (Json::Object(left), Json::Object(right)) => diff_objects(path, left, right, differences),
+        (Json::Array(left), Json::Array(right)) => diff_arrays(path, left, right, differences),
+        _ if left != right => differences.push(Difference::Changed {
+            path: path.to_string(),
+            from: left.clone(),
+            to: right.clone(),
+        }),
+        _ => {}
+    }
+}
+
+fn diff_objects(
+    path: &str,
+    left: &Map<String, Json>,
+    right: &Map<String, Json>,
+    differences: &mut Vec<Difference>,
+) {
+    let field_path = |key: &str| match path {
+        "" => key.to_string(),
+        _ => format!("{}.{}", path, key),
+    };
+    for (key, left_value) in left {
+        match right.get(key) {
+            Some(right_value) => diff_value(&field_path(key), left_value, right_value, differences),
+            None => differences.push(Difference::Removed {
+                path: field_path(key),
+                value: left_value.clone(),
+            }),
+        }
+    }
+    for (key, right_value) in right {
+        if !left.contains_key(key) {
+            differences.push(Difference::Added {
+                path: field_path(key),
+                value: right_value.clone(),
+            });
+        }
+    }
+}
+
+fn diff_arrays(path: &str, left: &[Json], right: &[Json], differences: &mut Vec<Difference>) {
+    let (Some(left_keyed), Some(right_keyed)) = (keyed(left), keyed(right)) else {
+        for index in 0..left.len().max(right.len()) {
+            let item_path = format!("{}[{}]", path, index);
+            match (left.get(index), right.get(index)) {
+                (Some(left), Some(right)) => diff_value(&item_path, left, right, differences),
+                (Some(left), None) => differences.push(Difference::Removed {
+                    path: item_path,
+                    value: left.clone(),
+                }),
+                (None, Some(right)) => differences.push(Difference::Added {
+                    path: item_path,
+                    value: right.clone(),
+                }),
+                (None, None) => unreachable!(),
+            }
+        }
+        return;
+    };
+
+    for (key, left) in &left_keyed {
+        let item_path = format!("{}[{}]", path, key);
+        match right_keyed.get(key) {
+            Some(right) => diff_value(&item_path, left, right, differences),
+            None => differences.push(Difference::Removed {
+                path: item_path,
+                value: (*left).clone(),
+            }),
+        }
+    }
+    for (key, right) in &right_keyed {
+        if !left_keyed.contains_key(key) {
+            differences.push(Difference::Added {
+                path: format!("{}[{}]", path, key),
+                value: (*right).clone(),
+            });
+        }
+    }
+}
+
+// Items by their key when every item has a distinct one: objects by their `id` ("id=3") and
+// tiles `[x, y, ...]` by their position ("3,4"), None for other lists
+fn keyed(items: &[Json]) -> Option<BTreeMap<String, &Json>> {
+    if items.is_empty() {
+        return None;
+    }
+    let mut keyed = BTreeMap::new();
+    for item in items {
+        let key = match item {
+            Json::Object(fields) => format!("id={}", fields.get("id")?),
+            Json::Array(values) if values.len() >= 2 => {
+                format!("{},{}", values[0].as_i64()?, values[1].as_i64()?)
+            }
+            _ => return None,
+        };
+        if keyed.insert(key, item).is_some() {
+            return None;
+        }
+    }
+    Some(keyed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_entities_are_matched_by_id() {
+        let left = json!({ "persons": [{ "id": 1, "name": "Ann" }, { "id": 2, "name": "Bob" }] });
+        let right = json!({ "persons": [{ "id": 2, "name": "Bob" }, { "id": 3, "name": "Cid" }] });
+
+        let differences = diff_json(&left, &right);
+
+        assert_eq!(
+            differences,
+            [
+                Difference::Removed {
+                    path: "persons[id=1]".to_string(),
+                    value: json!({ "id": 1, "name": "Ann" })
+                },
+                Difference::Added {
+                    path: "persons[id=3]".to_string(),
+                    value: json!({ "id": 3, "name": "Cid" })
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tiles_are_matched_by_position() {
+        let left = json!({ "layers": [{ "name": "ground", "tiles": [[0, 0, 1], [1, 0, 1]] }] });
+        let right =
+            json!({ "layers": [{ "name": "ground", "tiles": [[5, 5, 2], [0, 0, 1], [1, 0, 3]] }] });
+
+        let differences: Vec<String> = diff_json(&left, &right)
+            .iter()
+            .map(Difference::to_string)
+            .collect();
+
+        assert_eq!(
+            differences,
+            [
+                "~ layers[0].tiles[1,0][2]: 1 -> 3",
+                "+ layers[0].tiles[5,5]: [5,5,2]"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_event_logs_are_compared_event_by_event() {
+        let left = parse_event_log(
+            "{\"seq\":1,\"kind\":\"PersonCreated\",\"x\":1}\n{\"seq\":2,\"kind\":\"PersonMoved\",\"x\":2}\n",
+        )
+        .unwrap();
+        let right = parse_event_log(
+            "{\"seq\":1,\"kind\":\"PersonCreated\",\"x\":1}\n{\"seq\":2,\"kind\":\"PersonMoved\",\"x\":5}\n{\"seq\":3,\"kind\":\"TagAdded\"}\n",
+        )
+        .unwrap();
+
+        let differences: Vec<String> = diff_event_logs(&left, &right)
+            .iter()
+            .map(Difference::to_string)
+            .collect();
+
+        assert_eq!(
+            differences,
+            [
+                "~ event 2 (PersonMoved).x: 2 -> 5",
+                "+ event 3 (TagAdded): {\"kind\":\"TagAdded\",\"seq\":3}"
+            ]
+        );
+    }
+}
@@ -0,0 +1,361 @@
+//! End conditions and spawn points of a scenario, through the `scenario` global
+
+use crate::error_log::ErrorLog;
+use crate::lifecycle::Hook;
+use crate::lua_engine::LuaEngine;
+use crate::script_error::ScriptError;
+use dto::{PersonDto, ScenarioResultDto};
+use logic::{CoreApi, ScenarioOutcome};
+use mlua::{Function, Lua, LuaSerdeExt, Table, Value};
+use std::collections::BTreeMap;
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::time::Duration;
+
+// Fixed frame time of the headless runs
+const FRAME_TIME: f32 = 1.0 / 60.0;
+// How long a headless frame waits for the projections to catch up with the events
+const PROJECTION_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Clone)]
+enum Condition {
+    Predicate(Function),
+    MetricAtLeast(String, f64),
+    MetricAtMost(String, f64),
+    TickReached(u64),
+}
+
+#[derive(Clone)]
+struct EndCondition {
+    condition: Condition,
+    outcome: ScenarioOutcome,
+    reason: String,
+}
+
+#[derive(Default)]
+struct ScenarioState {
+    conditions: Vec<EndCondition>,
+    tick: u64,
+    duration: f64,
+    result: Option<ScenarioResultDto>,
+    spawn_points: BTreeMap<String, (i32, i32)>,
+}
+
+/// End conditions and the result of the running scenario. Conditions are checked on every tick
+/// in the order they were added, the first one met ends the scenario with a `ScenarioEnded`
+/// event. Spawn points are named tiles of the active map the scenario brings persons in at:
+///
+/// ```lua
+/// scenario.win_when(function() return #api.tags.find("fed") >= 10 end, "Everyone is fed")
+/// scenario.rules({ { tick = 36000, outcome = "ended", reason = "Time is up" } })
+/// scenario.spawn_point("gate", 0, 12)
+/// timer.every(5, function() scenario.spawn("gate", "Settler") end)
+/// ```
+#[derive(Clone)]
+pub struct Scenario {
+    state: Arc<Mutex<ScenarioState>>,
+    core: Arc<RwLock<CoreApi>>,
+    error_log: ErrorLog,
+}
+
+impl Scenario {
+    pub(crate) fn install(lua: &Lua, core: Arc<RwLock<CoreApi>>, error_log: ErrorLog) -> Self {
+        let scenario = Self {
+            state: Default::default(),
+            core,
+            error_log,
+        };
+
+        let table = lua.create_table().unwrap();
+        for (name, outcome) in [
+            ("win_when", ScenarioOutcome::Won),
+            ("lose_when", ScenarioOutcome::Lost),
+            ("end_when", ScenarioOutcome::Ended),
+        ] {
+            let scenario = scenario.clone();
+            lua.create_function(move |_, (predicate, reason): (Function, String)| {
+                scenario.add(EndCondition {
+                    condition: Condition::Predicate(predicate),
+                    outcome,
+                    reason,
+                });
+                Ok(())
+            })
+            .and_then(|f| table.set(name, f))
+            .unwrap();
+        }
+        {
+            let scenario = scenario.clone();
+            lua.create_function(move |_, rules: Vec<Table>| {
+                for rule in rules {
+                    scenario.add(parse_rule(&rule)?);
+                }
+                Ok(())
+            })
+            .and_then(|f| table.set("rules", f))
+            .unwrap();
+        }
+        {
+            let scenario = scenario.clone();
+            lua.create_function(move |_, (outcome, reason): (String, String)| {
+                let outcome = outcome.parse().map_err(mlua::Error::RuntimeError)?;
+                scenario.finish(outcome, &reason);
+                Ok(())
+            })
+            .and_then(|f| table.set("finish", f))
+            .unwrap();
+        }
+        {
+            let scenario = scenario.clone();
+            lua.create_function(move |lua, ()| match scenario.result() {
+                Some(result) => lua.to_value(&result),
+                None => Ok(Value::Nil),
+            })
+            .and_then(|f| table.set("result", f))
+            .unwrap();
+        }
+        {
+            let scenario = scenario.clone();
+            // Defining a spawn point again moves it
+            lua.create_function(move |_, (name, x, y): (String, i32, i32)| {
+                let mut state = scenario.state.lock().unwrap();
+                state.spawn_points.insert(name, (x, y));
+                Ok(())
+            })
+            .and_then(|f| table.set("spawn_point", f))
+            .unwrap();
+        }
+        {
+            let scenario = scenario.clone();
+            // The spawn points by name as { x = ..., y = ... }
+            lua.create_function(move |lua, ()| {
+                let result = lua.create_table()?;
+                for (name, (x, y)) in &scenario.state.lock().unwrap().spawn_points {
+                    let point = lua.create_table()?;
+                    point.set("x", *x)?;
+                    point.set("y", *y)?;
+                    result.set(name.as_str(), point)?;
+                }
+                Ok(result)
+            })
+            .and_then(|f| table.set("spawn_points", f))
+            .unwrap();
+        }
+        {
+            let scenario = scenario.clone();
+            // Create a person at a spawn point, returns it like api.person.create
+            lua.create_function(move |lua, (point, name): (String, String)| {
+                let spawn_point = scenario
+                    .state
+                    .lock()
+                    .unwrap()
+                    .spawn_points
+                    .get(&point)
+                    .copied();
+                let (x, y) = spawn_point.ok_or_else(|| {
+                    mlua::Error::RuntimeError(format!("There is no spawn point '{}'", point))
+                })?;
+                match scenario.core.read().unwrap().person().create(name, x, y) {
+                    Ok(person) => lua.to_value(&PersonDto::from(&person)),
+                    Err(e) => Err(mlua::Error::RuntimeError(e)),
+                }
+            })
+            .and_then(|f| table.set("spawn", f))
+            .unwrap();
+        }
+        lua.globals().set("scenario", table).unwrap();
+
+        scenario
+    }
+
+    fn add(&self, condition: EndCondition) {
+        self.state.lock().unwrap().conditions.push(condition);
+    }
+
+    /// Advance the scenario by a tick and end it if one of its conditions is met
+    pub(crate) fn update(&self, dt: f64, tick: u64) {
+        let conditions = {
+            let mut state = self.state.lock().unwrap();
+            if state.result.is_some() {
+                return;
+            }
+            state.tick = tick;
+            state.duration += dt;
+            state.conditions.clone()
+        };
+        // Predicates run without the lock, they may well add conditions themselves
+        for condition in conditions {
+            if self.is_met(&condition) {
+                self.finish(condition.outcome, &condition.reason);
+                return;
+            }
+        }
+    }
+
+    fn is_met(&self, end: &EndCondition) -> bool {
+        let latest = |metric: &str| self.core.read().unwrap().metrics().latest(metric);
+        match &end.condition {
+            Condition::Predicate(predicate) => match predicate.call::<bool>(()) {
+                Ok(met) => met,
+                Err(e) => {
+                    let context = format!("scenario condition '{}'", end.reason);
+                    self.error_log.report(&context, e);
+                    false
+                }
+            },
+            Condition::MetricAtLeast(metric, value) => latest(metric).is_some_and(|v| v >= *value),
+            Condition::MetricAtMost(metric, value) => latest(metric).is_some_and(|v| v <= *value),
+            Condition::TickReached(tick) => self.state.lock().unwrap().tick >= *tick,
+        }
+    }
+
+    /// End the scenario now, does nothing when it ended already
+    pub fn finish(&self, outcome: ScenarioOutcome, reason: &str) {
+        let (tick, duration) = {
+            let state = self.state.lock().unwrap();
+            (state.tick, state.duration)
+        };
+        let result = self
+            .core
+            .read()
+            .unwrap()
+            .scenario()
+            .end(outcome, reason, tick, duration);
+        if let Some(result) = result {
+            println!("Scenario {}: {}", result.outcome, result.reason);
+            self.state.lock().unwrap().result = Some(result);
+        }
+    }
+
+    /// Summary of the scenario once it ended
+    pub fn result(&self) -> Option<ScenarioResultDto> {
+        self.state.lock().unwrap().result.clone()
+    }
+}
+
+// A declarative end condition: `metric` with `at_least` or `at_most`, or `tick`, plus the
+// `outcome` ("ended" if left out) and the `reason` shown to the player
+fn parse_rule(rule: &Table) -> mlua::Result<EndCondition> {
+    let outcome = match rule.get::<Option<String>>("outcome")? {
+        Some(outcome) => outcome.parse().map_err(mlua::Error::RuntimeError)?,
+        None => ScenarioOutcome::Ended,
+    };
+    let metric: Option<String> = rule.get("metric")?;
+    let tick: Option<u64> = rule.get("tick")?;
+    let (condition, description) = match (metric, tick) {
+        (Some(metric), None) => {
+            match (
+                rule.get::<Option<f64>>("at_least")?,
+                rule.get::<Option<f64>>("at_most")?,
+            ) {
+                (Some(value), None) => (
+                    Condition::MetricAtLeast(metric.clone(), value),
+                    format!("{} reached {}", metric, value),
+                ),
+                (None, Some(value)) => (
+                    Condition::MetricAtMost(metric.clone(), value),
+                    format!("{} fell to {}", metric, value),
+                ),
+                _ => {
+                    return Err(mlua::Error::RuntimeError(format!(
+                        "Rule for metric {} needs either at_least or at_most",
+                        metric
+                    )));
+                }
+            }
+        }
+        (None, Some(tick)) => (
+            Condition::TickReached(tick),
+            format!("Tick {} reached", tick),
+        ),
+        _ => {
+            return Err(mlua::Error::RuntimeError(
+                "Rule needs either a metric or a tick".to_string(),
+            ));
+        }
+    };
+    let reason = rule.get::<Option<String>>("reason")?.unwrap_or(description);
+    Ok(EndCondition {
+        condition,
+        outcome,
+        reason,
+    })
+}
+
+/// Run the scenario script without a frontend, ticking until it ends or `max_frames` passed,
+/// when it ends as "ended". Like the game the script sees `env` and a seeded `math.random`.
+/// In turn mode every frame ends a turn, nobody else would.
+pub fn run_headless(
+    script: &str,
+    script_args: &[(String, String)],
+    seed: u32,
+    max_frames: u64,
+) -> Result<ScenarioResultDto, ScriptError> {
+    let (_command_tx, command_rx) = mpsc::channel();
+    let mut engine = LuaEngine::new(command_rx);
+    engine.set_script_args(script_args)?;
+    engine.run_script(&format!("math.randomseed({})", seed))?;
+    engine.run_script(script)?;
+    engine.hooks.call(Hook::Init, ());
+    for frame in 1..=max_frames {
+        // Frames take no time here, end conditions would see the projections lag behind
+        engine
+            .core
+            .read()
+            .unwrap()
+            .wait_for_projections(PROJECTION_TIMEOUT);
+        engine.clock.end_turn();
+        engine.tick(FRAME_TIME, frame);
+        if let Some(result) = engine.scenario.result() {
+            return Ok(result);
+        }
+    }
+    let reason = format!("Ran for {} frames", max_frames);
+    engine.scenario.finish(ScenarioOutcome::Ended, &reason);
+    Ok(engine.scenario.result().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_met_condition_ends_the_scenario() {
+        let result = run_headless(
+            r#"
+            scenario.spawn_point("gate", 0, 0)
+            scenario.spawn("gate", "Ann")
+            scenario.lose_when(function() return false end, "Never")
+            scenario.rules({
+                { metric = "persons", at_least = 2, outcome = "won" },
+                { tick = 30, reason = "Time is up" },
+            })
+            timer.after(0.1, function() api.person.create("Bob", 1, 0) end)
+            "#,
+            &[],
+            1,
+            100,
+        )
+        .unwrap();
+
+        assert_eq!(result.outcome, "won", "{:?}", result);
+        assert_eq!(result.reason, "persons reached 2");
+        assert_eq!(result.persons, 2);
+        assert!(result.ticks < 30);
+    }
+
+    #[test]
+    fn test_scenarios_without_end_stop_after_the_frames() {
+        let result = run_headless("scenario.finish('lost', 'Gave up')", &[], 1, 10).unwrap();
+        assert_eq!(result.outcome, "lost");
+        assert_eq!(result.ticks, 0);
+
+        let result = run_headless("", &[], 1, 10).unwrap();
+        assert_eq!(result.outcome, "ended");
+        assert_eq!(result.ticks, 10);
+        assert!((result.duration - 10.0 * FRAME_TIME as f64).abs() < 1e-6);
+
+        let invalid = run_headless("scenario.rules({ { metric = 'persons' } })", &[], 1, 10);
+        assert!(invalid.is_err());
+        assert!(run_headless("scenario.spawn('nowhere', 'Ann')", &[], 1, 10).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use mlua::{Lua, Result as LuaResult};
+
+/// Split a `key=value` script argument
+pub fn parse_pair(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!(
+            "Script arguments look like key=value, got '{}'",
+            arg
+        )),
+    }
+}
+
+/// The `--script-arg key=value` pairs of a command line, other arguments are skipped
+pub fn from_command_line(
+    args: impl IntoIterator<Item = String>,
+) -> Result<Vec<(String, String)>, String> {
+    let mut args = args.into_iter();
+    let mut pairs = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "--script-arg" {
+            let pair = args
+                .next()
+                .ok_or("--script-arg needs a key=value argument")?;
+            pairs.push(parse_pair(&pair)?);
+        }
+    }
+    Ok(pairs)
+}
+
+/// Set the `env` global to a read-only table of the script arguments, so one init script can set
+/// up different scenarios. Values stay strings, a key given twice keeps the last value.
+pub fn install(lua: &Lua, args: &[(String, String)]) -> LuaResult<()> {
+    let env = lua.create_table()?;
+    for (key, value) in args {
+        env.set(key.as_str(), value.as_str())?;
+    }
+    env.set_readonly(true);
+    lua.globals().set("env", env)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_args_are_collected_from_the_command_line() {
+        let args = [
+            "game",
+            "--dev",
+            "--script-arg",
+            "difficulty=hard",
+            "--script-arg",
+            "seed=4",
+        ];
+        let pairs = from_command_line(args.map(String::from)).unwrap();
+        assert_eq!(
+            pairs,
+            vec![
+                ("difficulty".to_string(), "hard".to_string()),
+                ("seed".to_string(), "4".to_string())
+            ]
+        );
+        assert!(from_command_line(["--script-arg", "hard"].map(String::from)).is_err());
+        assert!(from_command_line(["--script-arg"].map(String::from)).is_err());
+    }
+
+    #[test]
+    fn test_env_is_read_only() {
+        let lua = Lua::new();
+        install(&lua, &[("difficulty".to_string(), "hard".to_string())]).unwrap();
+        assert_eq!(lua.load("env.difficulty").eval::<String>().unwrap(), "hard");
+        assert!(lua.load("env.difficulty = 'easy'").exec().is_err());
+        assert!(lua.load("env.missing == nil").eval::<bool>().unwrap());
+    }
+}
@@ -0,0 +1,119 @@
+use std::fmt;
+
+/// Error raised by a script, with the location it was raised at and the Lua traceback
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptError {
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub message: String,
+    pub traceback: String,
+}
+
+impl ScriptError {
+    /// Split the `file:line: ` prefix Lua puts in front of error messages into its own fields
+    pub fn new(message: &str, traceback: &str) -> Self {
+        let (file, line, message) = match split_location(message) {
+            Some((file, line, message)) => (Some(file.to_string()), Some(line), message),
+            None => (None, None, message),
+        };
+        Self {
+            file,
+            line,
+            message: message.to_string(),
+            traceback: traceback.trim().to_string(),
+        }
+    }
+
+    /// Location as `file:line`, if known
+    pub fn location(&self) -> Option<String> {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => Some(format!("{}:{}", file, line)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.location() {
+            Some(location) => write!(f, "{}: {}", location, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl From<mlua::Error> for ScriptError {
+    fn from(error: mlua::Error) -> Self {
+        match error {
+            mlua::Error::SyntaxError { message, .. } => Self::new(&message, ""),
+            // Errors of functions called from Rust come with the traceback appended
+            mlua::Error::RuntimeError(message) => match message.split_once("\nstack traceback:") {
+                Some((message, traceback)) => Self::new(message, traceback),
+                None => Self::new(&message, ""),
+            },
+            mlua::Error::CallbackError { traceback, cause } => {
+                Self::new(&cause.to_string(), &traceback)
+            }
+            error => Self::new(&error.to_string(), ""),
+        }
+    }
+}
+
+// "file:line: message" -> (file, line, message), the file itself may contain colons
+fn split_location(text: &str) -> Option<(&str, u32, &str)> {
+    text.match_indices(':').find_map(|(colon, _)| {
+        let rest = &text[colon + 1..];
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let line = rest[..digits].parse().ok()?;
+        let message = rest[digits..].strip_prefix(": ")?;
+        Some((&text[..colon], line, message))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location_is_split_from_message() {
+        let error = ScriptError::new("./scripts/init.lua:12: boom", "stack");
+        assert_eq!(error.file.as_deref(), Some("./scripts/init.lua"));
+        assert_eq!(error.line, Some(12));
+        assert_eq!(error.message, "boom");
+        assert_eq!(error.to_string(), "./scripts/init.lua:12: boom");
+    }
+
+    #[test]
+    fn test_message_without_location() {
+        let error = ScriptError::new("attempt to call a nil value", "");
+        assert_eq!(error.location(), None);
+        assert_eq!(error.to_string(), "attempt to call a nil value");
+    }
+
+    #[test]
+    fn test_script_errors_carry_location_and_traceback() {
+        let (_, command_rx) = std::sync::mpsc::channel();
+        let mut engine = crate::lua_engine::LuaEngine::new(command_rx);
+        let error = engine
+            .run_script("local function fail()\n    error('boom')\nend\nfail()")
+            .unwrap_err();
+        assert_eq!(error.file.as_deref(), Some("script"));
+        assert_eq!(error.line, Some(2));
+        assert_eq!(error.message, "boom");
+        assert!(error.traceback.contains("fail"), "{}", error.traceback);
+
+        let error = engine.run_script("if then").unwrap_err();
+        assert_eq!(error.file.as_deref(), Some("script"));
+
+        let handler: mlua::Function = engine
+            .lua
+            .load("return function() error('from handler') end")
+            .set_name("=handlers.lua")
+            .eval()
+            .unwrap();
+        let error = ScriptError::from(handler.call::<()>(()).unwrap_err());
+        assert_eq!(error.location().as_deref(), Some("handlers.lua:1"));
+        assert_eq!(error.message, "from handler");
+        assert!(!error.traceback.is_empty());
+    }
+}
@@ -0,0 +1,462 @@
+//! Formatting helpers for the text of UI scripts, as `api.fmt`
+
+use crate::notifications::format_template;
+use mlua::{Lua, Table, Value, Variadic};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Largest width, precision or number of decimals a script may ask for, so it can't make the
+/// host allocate without bounds
+pub const MAX_WIDTH: usize = 1024;
+
+// Refuse sizes past `MAX_WIDTH`, `what` names the size in the error
+fn limit(size: usize, what: &str) -> Result<usize, String> {
+    if size > MAX_WIDTH {
+        return Err(format!(
+            "The {} is more than {}, got {}",
+            what, MAX_WIDTH, size
+        ));
+    }
+    Ok(size)
+}
+
+/// The number with `decimals` decimals and a comma between every three digits
+pub fn format_number(n: f64, decimals: usize) -> String {
+    if !n.is_finite() {
+        return n.to_string();
+    }
+    let text = format!("{:.*}", decimals, n.abs());
+    let (whole, fraction) = match text.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (text.as_str(), None),
+    };
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        grouped.push('.');
+        grouped.push_str(fraction);
+    }
+    // No "-0" for numbers rounding to zero
+    if n < 0.0 && grouped.chars().any(|c| c.is_ascii_digit() && c != '0') {
+        grouped.insert(0, '-');
+    }
+    grouped
+}
+
+/// The amount with the currency symbol in front, the sign before the symbol
+pub fn format_currency(amount: f64, symbol: &str, decimals: usize) -> String {
+    let number = format_number(amount, decimals);
+    match number.strip_prefix('-') {
+        Some(number) => format!("-{}{}", symbol, number),
+        None => format!("{}{}", symbol, number),
+    }
+}
+
+/// Seconds as days, hours, minutes and seconds, like "1h 2m", showing at most `parts` units
+/// starting at the largest one that isn't zero
+pub fn format_duration(seconds: f64, parts: usize) -> String {
+    let sign = if seconds < 0.0 { "-" } else { "" };
+    let mut rest = seconds.abs().floor() as u64;
+    let mut units = Vec::new();
+    for (size, unit) in [(86_400, "d"), (3_600, "h"), (60, "m"), (1, "s")] {
+        let count = rest / size;
+        rest %= size;
+        if count > 0 || !units.is_empty() || size == 1 {
+            units.push(format!("{}{}", count, unit));
+        }
+    }
+    units.truncate(parts.max(1));
+    format!("{}{}", sign, units.join(" "))
+}
+
+/// Where `pad` puts the text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+impl Align {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "left" => Some(Align::Left),
+            "right" => Some(Align::Right),
+            "center" => Some(Align::Center),
+            _ => None,
+        }
+    }
+}
+
+/// The text filled up to `width` characters, longer text is kept as it is
+pub fn pad(text: &str, width: usize, align: Align, fill: char) -> String {
+    let missing = width.saturating_sub(text.chars().count());
+    let (before, after) = match align {
+        Align::Left => (0, missing),
+        Align::Right => (missing, 0),
+        Align::Center => (missing / 2, missing - missing / 2),
+    };
+    let fill = |count| std::iter::repeat_n(fill, count).collect::<String>();
+    format!("{}{}{}", fill(before), text, fill(after))
+}
+
+/// The text cut to `width` characters, ending in the ellipsis when it was cut
+pub fn truncate(text: &str, width: usize, ellipsis: &str) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let kept = width.saturating_sub(ellipsis.chars().count());
+    if kept == 0 {
+        return text.chars().take(width).collect();
+    }
+    text.chars().take(kept).chain(ellipsis.chars()).collect()
+}
+
+// A `%` conversion of sprintf with its flags
+#[derive(Default)]
+struct Spec {
+    left: bool,
+    zero: bool,
+    plus: bool,
+    thousands: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    // The digits of a number with its sign, padded to the width
+    fn number(&self, negative: bool, digits: String) -> String {
+        let sign = if negative {
+            "-"
+        } else if self.plus {
+            "+"
+        } else {
+            ""
+        };
+        let length = sign.len() + digits.chars().count();
+        if self.zero && !self.left && length < self.width {
+            format!("{}{}{}", sign, "0".repeat(self.width - length), digits)
+        } else {
+            self.text(&format!("{}{}", sign, digits))
+        }
+    }
+
+    fn text(&self, text: &str) -> String {
+        let align = if self.left { Align::Left } else { Align::Right };
+        pad(text, self.width, align, ' ')
+    }
+}
+
+// The digits of a width or precision, at most `MAX_WIDTH`
+fn size(chars: &mut Peekable<Chars>, what: &str) -> Result<usize, String> {
+    let mut size: usize = 0;
+    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+        size = size
+            .checked_mul(10)
+            .and_then(|size| size.checked_add(digit.to_digit(10).unwrap() as usize))
+            .filter(|size| *size <= MAX_WIDTH)
+            .ok_or_else(|| format!("The {} of a conversion is more than {}", what, MAX_WIDTH))?;
+    }
+    Ok(size)
+}
+
+/// Fill a printf-style template with the arguments. It knows `%s`, `%d`/`%i`, `%f`, `%x`/`%X`
+/// and `%%` with the flags `-` (align left), `0` (pad with zeros), `+` (always a sign) and `,`
+/// (thousands separators), widths count characters. Widths and precisions go up to `MAX_WIDTH`.
+pub fn sprintf(template: &str, args: &[Value]) -> Result<String, String> {
+    let mut text = String::new();
+    let mut args = args.iter();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        let mut spec = Spec::default();
+        while let Some(flag) = chars.next_if(|c| "-0+,".contains(*c)) {
+            match flag {
+                '-' => spec.left = true,
+                '0' => spec.zero = true,
+                '+' => spec.plus = true,
+                _ => spec.thousands = true,
+            }
+        }
+        spec.width = size(&mut chars, "width")?;
+        if chars.next_if_eq(&'.').is_some() {
+            spec.precision = Some(size(&mut chars, "precision")?);
+        }
+        let conversion = chars
+            .next()
+            .ok_or_else(|| "Template ends in the middle of a conversion".to_string())?;
+        if conversion == '%' {
+            text.push('%');
+            continue;
+        }
+        let arg = args
+            .next()
+            .ok_or_else(|| format!("No argument left for %{}", conversion))?;
+        let number = || match arg {
+            Value::Integer(i) => Ok(*i as f64),
+            Value::Number(n) => Ok(*n),
+            Value::String(s) => s
+                .to_str()
+                .ok()
+                .and_then(|s| s.trim().parse().ok())
+                .ok_or_else(|| format!("%{} needs a number, got '{}'", conversion, s.display())),
+            arg => Err(format!(
+                "%{} needs a number, got a {}",
+                conversion,
+                arg.type_name()
+            )),
+        };
+        let formatted = match conversion {
+            's' => {
+                let value = match arg {
+                    Value::String(s) => s.to_string_lossy(),
+                    Value::Integer(i) => i.to_string(),
+                    Value::Number(n) => n.to_string(),
+                    Value::Boolean(b) => b.to_string(),
+                    Value::Nil => "nil".to_string(),
+                    arg => arg.type_name().to_string(),
+                };
+                let value = match spec.precision {
+                    Some(precision) => value.chars().take(precision).collect(),
+                    None => value,
+                };
+                spec.text(&value)
+            }
+            'd' | 'i' => {
+                let n = number()?.trunc();
+                let digits = if spec.thousands {
+                    format_number(n.abs(), 0)
+                } else {
+                    format!("{:.0}", n.abs())
+                };
+                spec.number(n < 0.0, digits)
+            }
+            'f' => {
+                let n = number()?;
+                let precision = spec.precision.unwrap_or(6);
+                let digits = if spec.thousands {
+                    format_number(n.abs(), precision)
+                } else {
+                    format!("{:.*}", precision, n.abs())
+                };
+                spec.number(n < 0.0, digits)
+            }
+            'x' | 'X' => {
+                let n = number()?.trunc() as i64;
+                let digits = if conversion == 'x' {
+                    format!("{:x}", n.unsigned_abs())
+                } else {
+                    format!("{:X}", n.unsigned_abs())
+                };
+                spec.number(n < 0, digits)
+            }
+            other => return Err(format!("Unknown conversion %{}", other)),
+        };
+        text.push_str(&formatted);
+    }
+    Ok(text)
+}
+
+/// Fill the `api.fmt` table:
+///
+/// ```lua
+/// api.fmt.number(1234567.891, 2)                          -- "1,234,567.89"
+/// api.fmt.currency(-1234.5)                               -- "-$1,234.50"
+/// api.fmt.duration(3725)                                  -- "1h 2m"
+/// api.fmt.pad("Ann", 6, "right")                          -- "   Ann"
+/// api.fmt.sprintf("%-6s|%,8.1f|%03d", "Ann", 12345.67, 7) -- "Ann   |12,345.7|007"
+/// api.fmt.template("{name} lives at {x},{y}", person)
+/// ```
+pub(crate) fn setup_fmt_api(lua: &Lua, table: &Table) {
+    let error = mlua::Error::RuntimeError;
+
+    // Expose api.fmt.number to Lua
+    let number = lua
+        .create_function(move |_, (n, decimals): (f64, Option<usize>)| {
+            let decimals = limit(decimals.unwrap_or(0), "number of decimals").map_err(error)?;
+            Ok(format_number(n, decimals))
+        })
+        .unwrap();
+    table.set("number", number).unwrap();
+
+    // Expose api.fmt.currency to Lua
+    let currency = lua
+        .create_function(
+            move |_, (amount, symbol, decimals): (f64, Option<String>, Option<usize>)| {
+                let decimals = limit(decimals.unwrap_or(2), "number of decimals").map_err(error)?;
+                Ok(format_currency(
+                    amount,
+                    symbol.as_deref().unwrap_or("$"),
+                    decimals,
+                ))
+            },
+        )
+        .unwrap();
+    table.set("currency", currency).unwrap();
+
+    // Expose api.fmt.duration to Lua
+    let duration = lua
+        .create_function(|_, (seconds, parts): (f64, Option<usize>)| {
+            Ok(format_duration(seconds, parts.unwrap_or(2)))
+        })
+        .unwrap();
+    table.set("duration", duration).unwrap();
+
+    // Expose api.fmt.pad to Lua
+    let pad_text =
+        lua
+            .create_function(
+                move |_,
+                      (text, width, align, fill): (
+                    String,
+                    usize,
+                    Option<String>,
+                    Option<String>,
+                )| {
+                    let align = match align.as_deref() {
+                        None => Align::Left,
+                        Some(name) => Align::from_name(name).ok_or_else(|| {
+                            error(format!(
+                                "Unknown alignment '{}', expected left, right or center",
+                                name
+                            ))
+                        })?,
+                    };
+                    let fill = match fill.as_deref() {
+                        None => ' ',
+                        Some(fill) if fill.chars().count() == 1 => fill.chars().next().unwrap(),
+                        Some(fill) => {
+                            return Err(error(format!(
+                                "Fill must be a single character, got '{}'",
+                                fill
+                            )))
+                        }
+                    };
+                    let width = limit(width, "width").map_err(error)?;
+                    Ok(pad(&text, width, align, fill))
+                },
+            )
+            .unwrap();
+    table.set("pad", pad_text).unwrap();
+
+    // Expose api.fmt.truncate to Lua
+    let truncate_text = lua
+        .create_function(
+            |_, (text, width, ellipsis): (String, usize, Option<String>)| {
+                Ok(truncate(&text, width, ellipsis.as_deref().unwrap_or("…")))
+            },
+        )
+        .unwrap();
+    table.set("truncate", truncate_text).unwrap();
+
+    // Expose api.fmt.sprintf to Lua
+    let sprintf_text = lua
+        .create_function(move |_, (template, args): (String, Variadic<Value>)| {
+            sprintf(&template, &args).map_err(error)
+        })
+        .unwrap();
+    table.set("sprintf", sprintf_text).unwrap();
+
+    // Expose api.fmt.template to Lua, the same `{field}` placeholders as notification templates
+    let template = lua
+        .create_function(|_, (template, values): (String, Table)| {
+            format_template(&template, &values)
+        })
+        .unwrap();
+    table.set("template", template).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lua_engine::LuaEngine;
+
+    #[test]
+    fn test_numbers_get_thousands_separators() {
+        assert_eq!(format_number(1234567.891, 2), "1,234,567.89");
+        assert_eq!(format_number(-999.7, 0), "-1,000");
+        assert_eq!(format_number(-0.001, 2), "0.00");
+        assert_eq!(format_currency(-1234.5, "$", 2), "-$1,234.50");
+        assert_eq!(format_duration(3725.0, 2), "1h 2m");
+        assert_eq!(format_duration(3725.0, 3), "1h 2m 5s");
+        assert_eq!(format_duration(90_061.0, 4), "1d 1h 1m 1s");
+        assert_eq!(format_duration(0.4, 2), "0s");
+    }
+
+    #[test]
+    fn test_padding_and_truncation_count_characters() {
+        assert_eq!(pad("Ann", 6, Align::Right, ' '), "   Ann");
+        assert_eq!(pad("Ann", 6, Align::Center, '*'), "*Ann**");
+        assert_eq!(pad("Annabelle", 6, Align::Left, ' '), "Annabelle");
+        assert_eq!(truncate("A very long name", 8, "…"), "A very …");
+        assert_eq!(truncate("Zoë", 3, "…"), "Zoë");
+        assert_eq!(truncate("Hello", 2, "..."), "He");
+    }
+
+    #[test]
+    fn test_sprintf_in_lua() {
+        let (_, command_rx) = std::sync::mpsc::channel();
+        let engine = LuaEngine::new(command_rx);
+
+        let text: String = engine
+            .lua
+            .load(r#"return api.fmt.sprintf("%-6s|%,8.1f|%03d|%+d|%x|%.2s|100%%", "Ann", 12345.67, 7, 5, 255, "Bob")"#)
+            .eval()
+            .unwrap();
+        assert_eq!(text, "Ann   |12,345.7|007|+5|ff|Bo|100%");
+
+        let error = engine
+            .lua
+            .load(r#"return api.fmt.sprintf("%d", "many")"#)
+            .eval::<String>()
+            .unwrap_err();
+        assert!(error.to_string().contains("%d needs a number"));
+
+        // Sizes overflowing a usize and ones past the limit are errors instead of panics
+        for template in [
+            "%99999999999999999999d",
+            "%.99999999999999999999f",
+            "%999999999d",
+        ] {
+            let error = engine
+                .lua
+                .load(format!("return api.fmt.sprintf({:?}, 1)", template))
+                .eval::<String>()
+                .unwrap_err();
+            assert!(error.to_string().contains("is more than 1024"), "{}", error);
+        }
+        let text: String = engine
+            .lua
+            .load(r#"return api.fmt.sprintf("%1024d", 1)"#)
+            .eval()
+            .unwrap();
+        assert_eq!(text.len(), 1024);
+        for call in [
+            "pad('Ann', 999999999)",
+            "number(1, 999999999)",
+            "currency(1, '$', 5000)",
+        ] {
+            let error = engine
+                .lua
+                .load(format!("return api.fmt.{}", call))
+                .eval::<String>()
+                .unwrap_err();
+            assert!(error.to_string().contains("is more than 1024"), "{}", error);
+        }
+
+        let text: String = engine
+            .lua
+            .load(r#"return api.fmt.template("{name} is {age}", { name = "Ann", age = 34 })"#)
+            .eval()
+            .unwrap();
+        assert_eq!(text, "Ann is 34");
+    }
+}
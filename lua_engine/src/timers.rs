@@ -0,0 +1,316 @@
+use crate::error_log::ErrorLog;
+use crate::lua_engine::API_VERSION;
+use crate::mod_budget::ModBudget;
+use mlua::{Function, Lua, MultiValue, Result as LuaResult, Table};
+use std::sync::{Arc, Mutex};
+
+struct Timer {
+    id: u32,
+    remaining: f64,
+    // Repeating timers are rescheduled with their interval after firing
+    interval: Option<f64>,
+    callback: Function,
+    // Mod whose code started the timer, its time counts for it
+    owner: Option<String>,
+}
+
+// A throttled function waiting for its interval to pass, with the arguments of the last call
+// made meanwhile
+#[derive(Default)]
+struct ThrottleState {
+    waiting: bool,
+    queued: Option<MultiValue>,
+}
+
+#[derive(Default)]
+struct TimerState {
+    timers: Vec<Timer>,
+    next_id: u32,
+}
+
+/// Timers started through the Lua `timer` global, advanced by the engine on every tick
+#[derive(Clone)]
+pub struct Timers {
+    state: Arc<Mutex<TimerState>>,
+    error_log: ErrorLog,
+    budget: ModBudget,
+}
+
+impl Timers {
+    pub(crate) fn install(lua: &Lua, error_log: ErrorLog, budget: ModBudget) -> Self {
+        let timers = Self {
+            state: Default::default(),
+            error_log,
+            budget,
+        };
+
+        let table = lua.create_table().unwrap();
+        {
+            let timers = timers.clone();
+            lua.create_function(move |_, (seconds, callback): (f64, Function)| {
+                Ok(timers.start(seconds, None, callback))
+            })
+            .and_then(|f| table.set("after", f))
+            .unwrap();
+        }
+        {
+            let timers = timers.clone();
+            lua.create_function(move |_, (seconds, callback): (f64, Function)| {
+                if seconds <= 0.0 {
+                    return Err(mlua::Error::RuntimeError(format!(
+                        "Repeating timer needs a positive interval, got {}",
+                        seconds
+                    )));
+                }
+                Ok(timers.start(seconds, Some(seconds), callback))
+            })
+            .and_then(|f| table.set("every", f))
+            .unwrap();
+        }
+        {
+            let timers = timers.clone();
+            lua.create_function(move |_, id: u32| Ok(timers.cancel(id)))
+                .and_then(|f| table.set("cancel", f))
+                .unwrap();
+        }
+        lua.globals().set("timer", table).unwrap();
+
+        timers
+    }
+
+    /// Register `api.util.debounce(fn, ms)` and `api.util.throttle(fn, ms)`, wrapping a function
+    /// so calling it every frame, like from a slider, doesn't flood the world with commands:
+    ///
+    /// ```lua
+    /// local set_wage = api.util.debounce(function(wage) api.meta.set("town", "wage", wage) end, 300)
+    /// local follow = api.util.throttle(function(x, y) api.task.go_to(id, x, y) end, 500)
+    /// ```
+    ///
+    /// A debounced function runs once the calls stopped for `ms`, with the arguments of the last
+    /// one. A throttled one runs at once, then at most once per `ms` with the arguments of the
+    /// last call made meanwhile. Both run on the timers, so they wait while the game is paused.
+    pub(crate) fn install_util(&self, lua: &Lua) {
+        let table = lua.create_table().unwrap();
+        {
+            let timers = self.clone();
+            lua.create_function(move |lua, (callback, ms): (Function, f64)| {
+                timers.debounce(lua, callback, ms / 1000.0)
+            })
+            .and_then(|f| table.set("debounce", f))
+            .unwrap();
+        }
+        {
+            let timers = self.clone();
+            lua.create_function(move |lua, (callback, ms): (Function, f64)| {
+                timers.throttle(lua, callback, ms / 1000.0)
+            })
+            .and_then(|f| table.set("throttle", f))
+            .unwrap();
+        }
+        let api: Table = lua.globals().get("api").unwrap();
+        api.get::<Table>(format!("v{}", API_VERSION))
+            .and_then(|latest| latest.set("util", table.clone()))
+            .and_then(|_| api.set("util", table))
+            .unwrap();
+    }
+
+    fn debounce(&self, lua: &Lua, callback: Function, seconds: f64) -> LuaResult<Function> {
+        let timers = self.clone();
+        let pending = Arc::new(Mutex::new(None));
+        lua.create_function(move |lua, args: MultiValue| {
+            let callback = callback.clone();
+            let fire = lua.create_function(move |_, ()| callback.call::<()>(args.clone()))?;
+            let mut pending = pending.lock().unwrap();
+            if let Some(id) = pending.take() {
+                timers.cancel(id);
+            }
+            *pending = Some(timers.start(seconds, None, fire));
+            Ok(())
+        })
+    }
+
+    fn throttle(&self, lua: &Lua, callback: Function, seconds: f64) -> LuaResult<Function> {
+        let timers = self.clone();
+        let state = Arc::new(Mutex::new(ThrottleState::default()));
+        lua.create_function(move |lua, args: MultiValue| {
+            {
+                let mut state = state.lock().unwrap();
+                if state.waiting {
+                    state.queued = Some(args);
+                    return Ok(());
+                }
+                state.waiting = true;
+            }
+            timers.throttle_window(lua, &state, &callback, seconds)?;
+            callback.call::<()>(args)
+        })
+    }
+
+    // Wait `seconds` after a throttled call, then run the call queued meanwhile if there is one
+    fn throttle_window(
+        &self,
+        lua: &Lua,
+        state: &Arc<Mutex<ThrottleState>>,
+        callback: &Function,
+        seconds: f64,
+    ) -> LuaResult<()> {
+        let timers = self.clone();
+        let state = Arc::clone(state);
+        let callback = callback.clone();
+        let release = lua.create_function(move |lua, ()| {
+            let queued = state.lock().unwrap().queued.take();
+            match queued {
+                Some(args) => {
+                    timers.throttle_window(lua, &state, &callback, seconds)?;
+                    callback.call::<()>(args)
+                }
+                None => {
+                    state.lock().unwrap().waiting = false;
+                    Ok(())
+                }
+            }
+        })?;
+        self.start(seconds, None, release);
+        Ok(())
+    }
+
+    fn start(&self, seconds: f64, interval: Option<f64>, callback: Function) -> u32 {
+        let owner = self.budget.owner();
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        let id = state.next_id;
+        state.timers.push(Timer {
+            id,
+            remaining: seconds,
+            interval,
+            callback,
+            owner,
+        });
+        id
+    }
+
+    /// Stop a timer, returns false when it already fired or was cancelled
+    pub fn cancel(&self, id: u32) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let count = state.timers.len();
+        state.timers.retain(|timer| timer.id != id);
+        state.timers.len() != count
+    }
+
+    /// Number of running timers
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().timers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Advance all timers by `dt` seconds and call the ones that are due
+    pub fn update(&self, dt: f64) {
+        // Collect first, callbacks may start or cancel timers
+        let mut due = Vec::new();
+        {
+            let mut state = self.state.lock().unwrap();
+            state.timers.retain_mut(|timer| {
+                timer.remaining -= dt;
+                if timer.remaining > 0.0 {
+                    return true;
+                }
+                due.push((timer.id, timer.callback.clone(), timer.owner.clone()));
+                match timer.interval {
+                    // Fire once per update even after a long frame, instead of catching up in a burst
+                    Some(interval) => {
+                        timer.remaining = (timer.remaining + interval).max(f64::EPSILON);
+                        true
+                    }
+                    None => false,
+                }
+            });
+        }
+
+        for (id, callback, owner) in due {
+            let called = self
+                .budget
+                .measure(owner.as_deref(), || callback.call::<()>(()));
+            if let Err(e) = called {
+                self.error_log.report(&format!("Timer {}", id), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timers_fire_after_their_delay() {
+        let lua = Lua::new();
+        let timers = Timers::install(&lua, ErrorLog::default(), ModBudget::default());
+        lua.load(
+            r#"
+            fired = 0
+            ticks = 0
+            timer.after(1.0, function() fired = fired + 1 end)
+            local cancelled = timer.after(0.5, function() fired = fired + 100 end)
+            timer.every(0.25, function() ticks = ticks + 1 end)
+            timer.cancel(cancelled)
+            "#,
+        )
+        .exec()
+        .unwrap();
+
+        timers.update(0.5);
+        assert_eq!(lua.globals().get::<i32>("fired").unwrap(), 0);
+        timers.update(0.5);
+        assert_eq!(lua.globals().get::<i32>("fired").unwrap(), 1);
+        assert_eq!(lua.globals().get::<i32>("ticks").unwrap(), 2);
+        assert_eq!(timers.len(), 1);
+    }
+
+    #[test]
+    fn test_debounced_and_throttled_functions_skip_calls() {
+        let (_command_tx, command_rx) = std::sync::mpsc::channel();
+        let engine = crate::lua_engine::LuaEngine::new(command_rx);
+        engine
+            .lua
+            .load(
+                r#"
+                debounced, throttled = {}, {}
+                local debounce = api.util.debounce(function(v) table.insert(debounced, v) end, 100)
+                throttle = api.util.throttle(function(v) table.insert(throttled, v) end, 100)
+                for v = 1, 3 do
+                    debounce(v)
+                    throttle(v)
+                end
+                "#,
+            )
+            .exec()
+            .unwrap();
+        let calls = |name: &str| engine.lua.globals().get::<Vec<i32>>(name).unwrap();
+        assert_eq!(calls("debounced"), Vec::<i32>::new());
+        assert_eq!(calls("throttled"), vec![1]);
+
+        engine.timers.update(0.1);
+        assert_eq!(calls("debounced"), vec![3]);
+        assert_eq!(calls("throttled"), vec![1, 3]);
+        engine.timers.update(0.1);
+        engine.lua.load("throttle(4)").exec().unwrap();
+        assert_eq!(calls("throttled"), vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn test_timer_errors_are_logged() {
+        let lua = Lua::new();
+        let error_log = ErrorLog::default();
+        let timers = Timers::install(&lua, error_log.clone(), ModBudget::default());
+        lua.load(r#"timer.after(0, function() error("late") end)"#)
+            .exec()
+            .unwrap();
+
+        timers.update(0.1);
+        assert_eq!(error_log.errors()[0].context, "Timer 1");
+        assert!(timers.is_empty());
+    }
+}
@@ -0,0 +1,319 @@
+//! Zones of the map calling back into the scripts when persons step in or out
+
+use crate::error_log::ErrorLog;
+use logic::{CoreApi, DomainEvent, Location, MapId, PersonEvent, Region};
+use mlua::{Function, Lua};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Width and height in tiles of the cells the zones are looked up by
+pub const TRIGGER_CELL_SIZE: i32 = 16;
+
+struct TriggerZone {
+    region: Region,
+    on_enter: Vec<Function>,
+    on_leave: Vec<Function>,
+}
+
+// A cell of the grid on a level of a map
+type Cell = (MapId, i32, i32, i32);
+
+#[derive(Default)]
+struct TriggerState {
+    zones: BTreeMap<String, TriggerZone>,
+    // Names of the zones overlapping every cell, so a step only checks the zones around it
+    cells: HashMap<Cell, Vec<String>>,
+}
+
+fn cell_of(map: &MapId, z: i32, x: i32, y: i32) -> Cell {
+    (
+        map.clone(),
+        z,
+        x.div_euclid(TRIGGER_CELL_SIZE),
+        y.div_euclid(TRIGGER_CELL_SIZE),
+    )
+}
+
+// The cells a region overlaps
+fn cells_of(region: &Region) -> Vec<Cell> {
+    let (min, max) = region.corners();
+    let (from, to) = (
+        cell_of(&min.map, min.z, min.x, min.y),
+        cell_of(&max.map, max.z, max.x, max.y),
+    );
+    (from.3..=to.3)
+        .flat_map(|y| (from.2..=to.2).map(move |x| (min.map.clone(), min.z, x, y)))
+        .collect()
+}
+
+impl TriggerState {
+    fn remove(&mut self, name: &str) -> Option<TriggerZone> {
+        let zone = self.zones.remove(name)?;
+        for cell in cells_of(&zone.region) {
+            if let Some(names) = self.cells.get_mut(&cell) {
+                names.retain(|other| other != name);
+                if names.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+        Some(zone)
+    }
+
+    fn insert(&mut self, name: String, zone: TriggerZone) {
+        for cell in cells_of(&zone.region) {
+            self.cells.entry(cell).or_default().push(name.clone());
+        }
+        self.zones.insert(name, zone);
+    }
+
+    fn zones_at(&self, location: &Location) -> Vec<&str> {
+        let cell = cell_of(&location.map, location.z, location.x, location.y);
+        self.cells
+            .get(&cell)
+            .into_iter()
+            .flatten()
+            .filter(|name| self.zones[*name].region.contains(location))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Trigger zones of the `triggers` global with their handlers, marked out on the active map and
+/// level. Handlers get the `zone`, the `person_id` and the tile stepped onto as `x` and `y` on
+/// the tick after the step, a person standing in a zone when it's marked out fires nothing:
+///
+/// ```lua
+/// triggers.zone("market", 10, 10, 20, 15)
+/// triggers.on_enter("market", function(event) print(event.person_id .. " came to the market") end)
+/// ```
+#[derive(Clone)]
+pub struct Triggers {
+    state: Arc<Mutex<TriggerState>>,
+    events: Arc<Mutex<Receiver<DomainEvent>>>,
+    error_log: ErrorLog,
+}
+
+impl Triggers {
+    pub(crate) fn install(lua: &Lua, core: Arc<RwLock<CoreApi>>, error_log: ErrorLog) -> Self {
+        let triggers = Self {
+            state: Default::default(),
+            events: Arc::new(Mutex::new(core.read().unwrap().event().subscribe())),
+            error_log,
+        };
+
+        let table = lua.create_table().unwrap();
+        {
+            let triggers = triggers.clone();
+            // Marking out a zone again moves it, its handlers stay
+            lua.create_function(
+                move |_, (name, x1, y1, x2, y2): (String, i32, i32, i32, i32)| {
+                    let region = {
+                        let core = core.read().unwrap();
+                        let map = MapId::new(&core.world().current());
+                        Region::on(&map, x1, y1, x2, y2).at_level(core.world().level())
+                    };
+                    let mut state = triggers.state.lock().unwrap();
+                    let (on_enter, on_leave) = match state.remove(&name) {
+                        Some(zone) => (zone.on_enter, zone.on_leave),
+                        None => (Vec::new(), Vec::new()),
+                    };
+                    let zone = TriggerZone {
+                        region,
+                        on_enter,
+                        on_leave,
+                    };
+                    state.insert(name, zone);
+                    Ok(())
+                },
+            )
+            .and_then(|f| table.set("zone", f))
+            .unwrap();
+        }
+        {
+            let triggers = triggers.clone();
+            // Returns false if there is no such zone
+            lua.create_function(move |_, name: String| {
+                Ok(triggers.state.lock().unwrap().remove(&name).is_some())
+            })
+            .and_then(|f| table.set("remove", f))
+            .unwrap();
+        }
+        for (function, entering) in [("on_enter", true), ("on_leave", false)] {
+            let triggers = triggers.clone();
+            lua.create_function(move |_, (name, handler): (String, Function)| {
+                let mut state = triggers.state.lock().unwrap();
+                let zone = state.zones.get_mut(&name).ok_or_else(|| {
+                    mlua::Error::RuntimeError(format!("There is no trigger zone '{}'", name))
+                })?;
+                if entering {
+                    zone.on_enter.push(handler);
+                } else {
+                    zone.on_leave.push(handler);
+                }
+                Ok(())
+            })
+            .and_then(|f| table.set(function, f))
+            .unwrap();
+        }
+        {
+            let triggers = triggers.clone();
+            lua.create_function(move |_, ()| {
+                let state = triggers.state.lock().unwrap();
+                Ok(state.zones.keys().cloned().collect::<Vec<_>>())
+            })
+            .and_then(|f| table.set("zones", f))
+            .unwrap();
+        }
+        lua.globals().set("triggers", table).unwrap();
+
+        triggers
+    }
+
+    /// Call the handlers of the zones persons stepped in or out of since the last update
+    pub(crate) fn update(&self, lua: &Lua) {
+        let steps: Vec<(u32, Option<Location>, Location)> = self
+            .events
+            .lock()
+            .unwrap()
+            .try_iter()
+            .flat_map(|event| match event {
+                DomainEvent::Person(PersonEvent::PersonCreated {
+                    person_id,
+                    location,
+                    ..
+                }) => vec![(person_id.0, None, location)],
+                DomainEvent::Person(PersonEvent::PersonMoved {
+                    person_id,
+                    from_location,
+                    to_location,
+                }) => vec![(person_id.0, Some(from_location), to_location)],
+                DomainEvent::Person(PersonEvent::PersonsMoved { moves }) => moves
+                    .into_iter()
+                    .map(|step| (step.person_id.0, Some(step.from_location), step.to_location))
+                    .collect(),
+                _ => Vec::new(),
+            })
+            .collect();
+
+        // Handlers run without the lock, they may well mark out zones themselves
+        let mut calls = Vec::new();
+        {
+            let state = self.state.lock().unwrap();
+            for (person_id, from, to) in &steps {
+                let left = from
+                    .as_ref()
+                    .map(|from| state.zones_at(from))
+                    .unwrap_or_default();
+                let entered = state.zones_at(to);
+                for (zones, others, entering) in [(&entered, &left, true), (&left, &entered, false)]
+                {
+                    for name in zones.iter().filter(|name| !others.contains(name)) {
+                        let zone = &state.zones[*name];
+                        let handlers = if entering {
+                            &zone.on_enter
+                        } else {
+                            &zone.on_leave
+                        };
+                        calls.extend(handlers.iter().map(|handler| {
+                            (
+                                handler.clone(),
+                                name.to_string(),
+                                *person_id,
+                                entering,
+                                to.x,
+                                to.y,
+                            )
+                        }));
+                    }
+                }
+            }
+        }
+
+        for (handler, zone, person_id, entering, x, y) in calls {
+            let result = lua.create_table().and_then(|event| {
+                event.set("zone", zone.as_str())?;
+                event.set("person_id", person_id)?;
+                event.set("x", x)?;
+                event.set("y", y)?;
+                handler.call::<()>(event)
+            });
+            if let Err(e) = result {
+                let function = if entering { "on_enter" } else { "on_leave" };
+                self.error_log
+                    .report(&format!("triggers.{}('{}')", function, zone), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lua_engine::LuaEngine;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_handlers_fire_on_entering_and_leaving() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine
+            .run_script(
+                r#"
+                log = {}
+                triggers.zone("market", 2, 0, 3, 40)
+                triggers.zone("far", 100, 100, 120, 120)
+                for _, zone in ipairs(triggers.zones()) do
+                    triggers.on_enter(zone, function(e)
+                        table.insert(log, "enter " .. e.zone .. " " .. e.x)
+                    end)
+                    triggers.on_leave(zone, function(e)
+                        table.insert(log, "leave " .. e.zone .. " " .. e.x)
+                    end)
+                end
+                ann = api.person.create("Ann", 0, 0)
+                bob = api.person.create("Bob", 2, 20)
+                "#,
+            )
+            .unwrap();
+        let mut frame = 0;
+        let mut step = |engine: &mut LuaEngine, script: &str| {
+            engine.run_script(script).unwrap();
+            engine
+                .core
+                .read()
+                .unwrap()
+                .wait_for_projections(Duration::from_millis(100));
+            frame += 1;
+            engine.tick(0.1, frame);
+        };
+        step(&mut engine, "");
+        for x in 1..=4 {
+            step(
+                &mut engine,
+                &format!("api.person.move_to(ann.id, {}, 0)", x),
+            );
+        }
+        step(
+            &mut engine,
+            "api.person.move_all({ { id = bob.id, x = 3, y = 20 } })",
+        );
+
+        let log: Vec<String> = engine.lua.load("log").eval().unwrap();
+        assert_eq!(
+            log,
+            vec!["enter market 2", "enter market 2", "leave market 4"]
+        );
+        assert!(engine
+            .lua
+            .load("triggers.on_enter('nowhere', print)")
+            .exec()
+            .is_err());
+        assert!(engine
+            .lua
+            .load("return triggers.remove('far')")
+            .eval::<bool>()
+            .unwrap());
+    }
+}
@@ -0,0 +1,539 @@
+//! Tutorials walking the player through the game step by step, as the `tutorial` global
+
+use crate::actions::Action;
+use crate::error_log::ErrorLog;
+use mlua::{Function, Lua, Table};
+use serde_json::{json, Map, Value as Json};
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+/// Progress file of the frontends, in the working directory
+pub const TUTORIAL_PROGRESS_PATH: &str = "tutorial_progress.json";
+
+/// What a step points the player at
+#[derive(Debug, Clone, PartialEq)]
+pub enum Highlight {
+    /// A part of the UI by the name the frontend gives it, like "goals"
+    Ui(String),
+    /// Screen rectangle as x, y, width and height
+    Rect(f32, f32, f32, f32),
+    /// Tiles between the corners, on the map shown
+    Region {
+        min_x: i32,
+        min_y: i32,
+        max_x: i32,
+        max_y: i32,
+    },
+}
+
+#[derive(Clone)]
+enum Wait {
+    /// Goes on with `tutorial.next()`
+    Next,
+    Action(Action),
+    Event {
+        kind: String,
+        check: Option<Function>,
+    },
+}
+
+#[derive(Clone)]
+struct Step {
+    text: String,
+    highlight: Option<Highlight>,
+    wait: Wait,
+}
+
+/// The step of the running tutorial, for the frontends to show
+#[derive(Debug, Clone, PartialEq)]
+pub struct TutorialStep {
+    pub tutorial: String,
+    /// Index of the step, from 0
+    pub index: usize,
+    /// Steps of the tutorial
+    pub count: usize,
+    pub text: String,
+    pub highlight: Option<Highlight>,
+    /// Whether the step goes on by itself once the player did what it asks, otherwise the
+    /// frontends offer to go on
+    pub waits: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Progress {
+    // Steps done so far
+    step: usize,
+    finished: bool,
+    skipped: bool,
+}
+
+impl Progress {
+    fn to_json(self) -> Json {
+        json!({ "step": self.step, "finished": self.finished, "skipped": self.skipped })
+    }
+
+    fn from_json(value: &Json) -> Self {
+        Self {
+            step: value["step"].as_u64().unwrap_or(0) as usize,
+            finished: value["finished"].as_bool().unwrap_or(false),
+            skipped: value["skipped"].as_bool().unwrap_or(false),
+        }
+    }
+}
+
+#[derive(Default)]
+struct TutorialState {
+    tutorials: BTreeMap<String, Vec<Step>>,
+    // Progress of every tutorial started so far, also of those not defined right now
+    progress: BTreeMap<String, Progress>,
+    running: Option<String>,
+    path: Option<String>,
+}
+
+impl TutorialState {
+    // The running tutorial with the step it's at
+    fn current(&self) -> Option<(&str, usize, &Step, usize)> {
+        let name = self.running.as_deref()?;
+        let steps = self.tutorials.get(name)?;
+        let index = self.progress.get(name).copied().unwrap_or_default().step;
+        steps
+            .get(index)
+            .map(|step| (name, index, step, steps.len()))
+    }
+
+    // Go on to the next step, the tutorial is finished after its last one
+    fn advance(&mut self) {
+        let Some(name) = self.running.clone() else {
+            return;
+        };
+        let count = self.tutorials.get(&name).map_or(0, Vec::len);
+        let progress = self.progress.entry(name).or_default();
+        progress.step += 1;
+        if progress.step >= count {
+            progress.finished = true;
+            self.running = None;
+        }
+        self.save();
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let progress: Map<String, Json> = self
+            .progress
+            .iter()
+            .map(|(name, progress)| (name.clone(), progress.to_json()))
+            .collect();
+        let json = serde_json::to_string_pretty(&Json::Object(progress)).unwrap();
+        if let Err(e) = fs::write(path, json) {
+            println!("Failed to write {}: {}", path, e);
+        }
+    }
+}
+
+/// Tutorials of the `tutorial` global and how far the player got in them. A step waits for an
+/// input action or a domain event passing its `check`, steps without `wait` go on with
+/// `tutorial.next()`. `highlight` points at a part of the UI by name, a `rect` of the screen, or
+/// a `region` or `tile` of the map:
+///
+/// ```lua
+/// tutorial.define("basics", {
+///     { text = "Pan the view with WASD", wait = { action = "pan_up" } },
+///     { text = "Your goals are listed here", highlight = { ui = "goals" } },
+///     { text = "Bring someone to the well", highlight = { tile = { 5, 5 } },
+///       wait = { event = "PersonMoved", check = function(e) return e.x == 5 and e.y == 5 end } },
+/// })
+/// tutorial.start("basics")
+/// ```
+#[derive(Clone)]
+pub struct Tutorials {
+    state: Arc<Mutex<TutorialState>>,
+    error_log: ErrorLog,
+}
+
+impl Tutorials {
+    pub(crate) fn install(lua: &Lua, error_log: ErrorLog) -> Self {
+        let tutorials = Self {
+            state: Default::default(),
+            error_log,
+        };
+
+        let table = lua.create_table().unwrap();
+        {
+            let tutorials = tutorials.clone();
+            lua.create_function(move |_, (name, steps): (String, Table)| {
+                let steps = steps
+                    .sequence_values::<Table>()
+                    .enumerate()
+                    .map(|(i, step)| parse_step(&name, i + 1, step?))
+                    .collect::<mlua::Result<Vec<Step>>>()?;
+                tutorials
+                    .state
+                    .lock()
+                    .unwrap()
+                    .tutorials
+                    .insert(name, steps);
+                Ok(())
+            })
+            .and_then(|f| table.set("define", f))
+            .unwrap();
+        }
+        {
+            let tutorials = tutorials.clone();
+            lua.create_function(move |_, name: String| {
+                tutorials.start(&name).map_err(mlua::Error::RuntimeError)
+            })
+            .and_then(|f| table.set("start", f))
+            .unwrap();
+        }
+        {
+            let tutorials = tutorials.clone();
+            lua.create_function(move |_, name: String| {
+                tutorials.restart(&name).map_err(mlua::Error::RuntimeError)
+            })
+            .and_then(|f| table.set("restart", f))
+            .unwrap();
+        }
+        {
+            let tutorials = tutorials.clone();
+            lua.create_function(move |_, ()| {
+                tutorials.next();
+                Ok(())
+            })
+            .and_then(|f| table.set("next", f))
+            .unwrap();
+        }
+        {
+            let tutorials = tutorials.clone();
+            lua.create_function(move |_, ()| {
+                tutorials.skip();
+                Ok(())
+            })
+            .and_then(|f| table.set("skip", f))
+            .unwrap();
+        }
+        {
+            let tutorials = tutorials.clone();
+            // { tutorial = "basics", step = 1, count = 4, text = "..." }, step counted from 1
+            lua.create_function(move |lua, ()| {
+                let Some(step) = tutorials.current() else {
+                    return Ok(None);
+                };
+                let result = lua.create_table()?;
+                result.set("tutorial", step.tutorial)?;
+                result.set("step", step.index + 1)?;
+                result.set("count", step.count)?;
+                result.set("text", step.text)?;
+                Ok(Some(result))
+            })
+            .and_then(|f| table.set("current", f))
+            .unwrap();
+        }
+        {
+            let tutorials = tutorials.clone();
+            lua.create_function(move |_, name: String| Ok(tutorials.is_finished(&name)))
+                .and_then(|f| table.set("is_finished", f))
+                .unwrap();
+        }
+        lua.globals().set("tutorial", table).unwrap();
+
+        tutorials
+    }
+
+    /// Keep the progress in the file at `path`, restoring what it has. A missing or broken file
+    /// starts every tutorial from the beginning.
+    pub fn persist_to(&self, path: &str) {
+        let saved = match fs::read_to_string(path) {
+            Ok(content) => match serde_json::from_str::<Map<String, Json>>(&content) {
+                Ok(saved) => saved,
+                Err(e) => {
+                    println!("Failed to parse tutorial progress {}: {}", path, e);
+                    Map::new()
+                }
+            },
+            Err(_) => Map::new(),
+        };
+        let mut state = self.state.lock().unwrap();
+        state.progress = saved
+            .iter()
+            .map(|(name, progress)| (name.clone(), Progress::from_json(progress)))
+            .collect();
+        state.path = Some(path.to_string());
+    }
+
+    /// Run a tutorial from where the player left it, returns false if it was finished or
+    /// skipped before
+    pub fn start(&self, name: &str) -> Result<bool, String> {
+        let mut state = self.state.lock().unwrap();
+        let count = state
+            .tutorials
+            .get(name)
+            .ok_or_else(|| format!("There is no tutorial '{}'", name))?
+            .len();
+        let progress = state.progress.entry(name.to_string()).or_default();
+        if progress.finished || progress.skipped {
+            return Ok(false);
+        }
+        if progress.step >= count {
+            progress.finished = true;
+            state.save();
+            return Ok(false);
+        }
+        state.running = Some(name.to_string());
+        state.save();
+        Ok(true)
+    }
+
+    /// Run a tutorial from its first step, finished or not
+    pub fn restart(&self, name: &str) -> Result<bool, String> {
+        self.state
+            .lock()
+            .unwrap()
+            .progress
+            .insert(name.to_string(), Progress::default());
+        self.start(name)
+    }
+
+    /// Go on to the next step of the running tutorial, whatever the step waits for
+    pub fn next(&self) {
+        self.state.lock().unwrap().advance();
+    }
+
+    /// Stop the running tutorial for good, it doesn't start again until restarted
+    pub fn skip(&self) {
+        let mut state = self.state.lock().unwrap();
+        let Some(name) = state.running.take() else {
+            return;
+        };
+        state.progress.entry(name).or_default().skipped = true;
+        state.save();
+    }
+
+    pub fn is_finished(&self, name: &str) -> bool {
+        let state = self.state.lock().unwrap();
+        state
+            .progress
+            .get(name)
+            .is_some_and(|progress| progress.finished)
+    }
+
+    /// The step the running tutorial is at, None when none is running
+    pub fn current(&self) -> Option<TutorialStep> {
+        let state = self.state.lock().unwrap();
+        let (name, index, step, count) = state.current()?;
+        Some(TutorialStep {
+            tutorial: name.to_string(),
+            index,
+            count,
+            text: step.text.clone(),
+            highlight: step.highlight.clone(),
+            waits: !matches!(step.wait, Wait::Next),
+        })
+    }
+
+    /// Tell the running tutorial the player pressed an action, the frontends call it for every
+    /// action of a frame
+    pub fn action(&self, action: Action) {
+        let mut state = self.state.lock().unwrap();
+        if matches!(state.current(), Some((_, _, step, _)) if matches!(step.wait, Wait::Action(waited) if waited == action))
+        {
+            state.advance();
+        }
+    }
+
+    /// Whether the running tutorial waits for events of the kind, so their tables are only built
+    /// when needed
+    pub(crate) fn waits_for(&self, kind: &str) -> bool {
+        let state = self.state.lock().unwrap();
+        matches!(state.current(), Some((_, _, step, _)) if matches!(&step.wait, Wait::Event { kind: waited, .. } if waited == kind))
+    }
+
+    /// Go on if the running tutorial waits for the event, `event` is its table
+    pub(crate) fn observe(&self, kind: &str, event: &Table) {
+        // The check runs without the lock, it may well look at the tutorials itself
+        let (name, index, check) = {
+            let state = self.state.lock().unwrap();
+            match state.current() {
+                Some((name, index, step, _)) => match &step.wait {
+                    Wait::Event {
+                        kind: waited,
+                        check,
+                    } if waited == kind => (name.to_string(), index, check.clone()),
+                    _ => return,
+                },
+                None => return,
+            }
+        };
+        let passed = match check {
+            Some(check) => match check.call::<bool>(event.clone()) {
+                Ok(passed) => passed,
+                Err(e) => {
+                    self.error_log
+                        .report(&format!("tutorial '{}' step {}", name, index + 1), e);
+                    false
+                }
+            },
+            None => true,
+        };
+        let mut state = self.state.lock().unwrap();
+        // Unless the check went on or skipped already
+        let still =
+            matches!(state.current(), Some((running, at, _, _)) if running == name && at == index);
+        if passed && still {
+            state.advance();
+        }
+    }
+}
+
+fn parse_step(tutorial: &str, number: usize, step: Table) -> mlua::Result<Step> {
+    let error = |message: String| {
+        mlua::Error::RuntimeError(format!(
+            "Step {} of tutorial '{}': {}",
+            number, tutorial, message
+        ))
+    };
+    let highlight = match step.get::<Option<Table>>("highlight")? {
+        None => None,
+        Some(highlight) => {
+            if let Some(name) = highlight.get::<Option<String>>("ui")? {
+                Some(Highlight::Ui(name))
+            } else if let Some(rect) = highlight.get::<Option<Vec<f32>>>("rect")? {
+                let [x, y, w, h] = rect[..] else {
+                    return Err(error("a rect is { x, y, w, h }".to_string()));
+                };
+                Some(Highlight::Rect(x, y, w, h))
+            } else if let Some(region) = highlight.get::<Option<Vec<i32>>>("region")? {
+                let [x1, y1, x2, y2] = region[..] else {
+                    return Err(error("a region is { x1, y1, x2, y2 }".to_string()));
+                };
+                Some(Highlight::Region {
+                    min_x: x1.min(x2),
+                    min_y: y1.min(y2),
+                    max_x: x1.max(x2),
+                    max_y: y1.max(y2),
+                })
+            } else if let Some(tile) = highlight.get::<Option<Vec<i32>>>("tile")? {
+                let [x, y] = tile[..] else {
+                    return Err(error("a tile is { x, y }".to_string()));
+                };
+                Some(Highlight::Region {
+                    min_x: x,
+                    min_y: y,
+                    max_x: x,
+                    max_y: y,
+                })
+            } else {
+                return Err(error(
+                    "a highlight needs a ui, rect, region or tile".to_string(),
+                ));
+            }
+        }
+    };
+    let wait = match step.get::<Option<Table>>("wait")? {
+        None => Wait::Next,
+        Some(wait) => {
+            if let Some(action) = wait.get::<Option<String>>("action")? {
+                Wait::Action(Action::parse(&action).map_err(error)?)
+            } else if let Some(kind) = wait.get::<Option<String>>("event")? {
+                Wait::Event {
+                    kind,
+                    check: wait.get("check")?,
+                }
+            } else {
+                return Err(error("a wait needs an action or an event".to_string()));
+            }
+        }
+    };
+    Ok(Step {
+        text: step.get("text")?,
+        highlight,
+        wait,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lua_engine::LuaEngine;
+    use std::sync::mpsc;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_tutorials_advance_on_actions_and_events_and_resume() {
+        let path =
+            std::env::temp_dir().join(format!("tutorial_progress_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut engine = LuaEngine::new(command_rx);
+        engine.tutorials.persist_to(path);
+        engine
+            .run_script(
+                r#"
+                tutorial.define("basics", {
+                    { text = "Pan up", wait = { action = "pan_up" } },
+                    { text = "Meet Bob", highlight = { tile = { 3, 4 } },
+                      wait = { event = "PersonCreated", check = function(e) return e.name == "Bob" end } },
+                    { text = "Done", highlight = { ui = "goals" } },
+                })
+                started = tutorial.start("basics")
+                "#,
+            )
+            .unwrap();
+        assert!(engine.lua.globals().get::<bool>("started").unwrap());
+        assert!(engine
+            .run_script("tutorial.define('bad', { { text = 'x', wait = { action = 'fly' } } })")
+            .is_err());
+
+        let tutorials = engine.tutorials.clone();
+        tutorials.action(Action::PanDown);
+        assert_eq!(tutorials.current().unwrap().index, 0);
+        tutorials.action(Action::PanUp);
+        let step = tutorials.current().unwrap();
+        assert_eq!(
+            step.highlight,
+            Some(Highlight::Region {
+                min_x: 3,
+                min_y: 4,
+                max_x: 3,
+                max_y: 4
+            })
+        );
+        assert!(step.waits);
+
+        engine
+            .run_script("api.person.create('Ann', 0, 0) api.person.create('Bob', 3, 4)")
+            .unwrap();
+        // Events are published from the event store thread
+        let started = Instant::now();
+        while tutorials.current().unwrap().index == 1 && started.elapsed() < Duration::from_secs(2)
+        {
+            engine.tick(0.1, 1);
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        let step = tutorials.current().unwrap();
+        assert_eq!((step.index, step.count, step.waits), (2, 3, false));
+
+        // The progress is picked up again by another session
+        let (_command_tx, command_rx) = mpsc::channel();
+        let mut resumed = LuaEngine::new(command_rx);
+        resumed.tutorials.persist_to(path);
+        resumed
+            .run_script(
+                "tutorial.define('basics', { { text = 'a' }, { text = 'b' }, { text = 'c' } })",
+            )
+            .unwrap();
+        assert_eq!(resumed.tutorials.start("basics"), Ok(true));
+        assert_eq!(resumed.tutorials.current().unwrap().text, "c");
+        resumed.tutorials.next();
+        assert!(resumed.tutorials.is_finished("basics"));
+        assert_eq!(resumed.tutorials.start("basics"), Ok(false));
+
+        resumed.tutorials.restart("basics").unwrap();
+        resumed.tutorials.skip();
+        assert_eq!(resumed.tutorials.current(), None);
+        assert_eq!(resumed.tutorials.start("basics"), Ok(false));
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,485 @@
+use crate::lifecycle::Hook;
+use mlua::{Function, Lua, LuaSerdeExt, MultiValue, Table, Value};
+use std::sync::{Arc, Mutex, OnceLock};
+use wasmtime::{
+    AsContext, AsContextMut, Caller, Config, Engine, Extern, Instance, Linker, Memory, Module,
+    Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+};
+
+/// Fuel a hook may burn per call, unless the mod sets `limits.fuel`
+pub const DEFAULT_FUEL: u64 = 10_000_000;
+/// Bytes the memory of a mod may grow to, unless the mod sets `limits.memory`
+pub const DEFAULT_MEMORY: usize = 16 << 20;
+/// Bytes of JSON the host reads out of a mod at once, for the api arguments and hook results
+pub const MAX_MESSAGE: usize = 4 << 20;
+
+/// What the runtime allows a mod, read from the `limits` it registers with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WasmLimits {
+    pub fuel: u64,
+    pub memory: usize,
+}
+
+impl Default for WasmLimits {
+    fn default() -> Self {
+        Self {
+            fuel: DEFAULT_FUEL,
+            memory: DEFAULT_MEMORY,
+        }
+    }
+}
+
+impl WasmLimits {
+    fn read(limits: Option<Table>) -> mlua::Result<Self> {
+        let default = Self::default();
+        let Some(limits) = limits else {
+            return Ok(default);
+        };
+        Ok(Self {
+            fuel: limits.get::<Option<u64>>("fuel")?.unwrap_or(default.fuel),
+            memory: limits
+                .get::<Option<usize>>("memory")?
+                .unwrap_or(default.memory),
+        })
+    }
+}
+
+struct Host {
+    limits: StoreLimits,
+    // Only set while a hook runs, the hooks are Lua functions themselves
+    lua: Option<Lua>,
+}
+
+/// A mod compiled to WebAssembly, registered with `backend = "wasm"` and the path of its
+/// `module`. It exports `memory`, `alloc(len) -> ptr` and its hooks as `(ptr, len) -> i64`
+/// taking the hook arguments as a JSON array and returning JSON packed as `ptr << 32 | len`, or
+/// 0 for nothing. The documented `api` functions are reached through the import
+/// `sb5s.api(module_ptr, module_len, name_ptr, name_len, args_ptr, args_len) -> i64`, which
+/// returns `{"ok": result}` or `{"err": message}`.
+struct WasmMod {
+    store: Store<Host>,
+    instance: Instance,
+    fuel: u64,
+}
+
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut config = Config::new();
+        config.consume_fuel(true).wasm_backtrace(false);
+        Engine::new(&config).expect("WebAssembly engine")
+    })
+}
+
+fn pack(ptr: i32, len: usize) -> i64 {
+    ((ptr as u32 as i64) << 32) | len as u32 as i64
+}
+
+fn read_guest(
+    store: impl AsContext,
+    memory: Memory,
+    ptr: i32,
+    len: i32,
+) -> wasmtime::Result<Vec<u8>> {
+    // The module picks the range, so it's checked before anything is allocated for it
+    let (Ok(start), Ok(len)) = (usize::try_from(ptr), usize::try_from(len)) else {
+        return Err(wasmtime::Error::msg(format!(
+            "the module passed a negative range {}, {}",
+            ptr, len
+        )));
+    };
+    if len > MAX_MESSAGE {
+        return Err(wasmtime::Error::msg(format!(
+            "the module passed {} bytes, more than the {} allowed",
+            len, MAX_MESSAGE
+        )));
+    }
+    match start.checked_add(len) {
+        Some(end) if end <= memory.data_size(&store) => {
+            Ok(memory.data(&store)[start..end].to_vec())
+        }
+        _ => Err(wasmtime::Error::msg(format!(
+            "the module passed the range {}, {} outside its memory",
+            ptr, len
+        ))),
+    }
+}
+
+fn write_guest(
+    mut store: impl AsContextMut,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    bytes: &[u8],
+) -> wasmtime::Result<i64> {
+    let ptr = alloc.call(&mut store, bytes.len() as i32)?;
+    memory.write(&mut store, ptr as u32 as usize, bytes)?;
+    Ok(pack(ptr, bytes.len()))
+}
+
+fn exports(caller: &mut Caller<'_, Host>) -> wasmtime::Result<(Memory, TypedFunc<i32, i32>)> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmtime::Error::msg("the module exports no memory"))?;
+    let alloc = caller
+        .get_export("alloc")
+        .and_then(Extern::into_func)
+        .ok_or_else(|| wasmtime::Error::msg("the module exports no alloc"))?
+        .typed(&caller)?;
+    Ok((memory, alloc))
+}
+
+// Call `api.<module>.<name>` if the docs know it, the api surface of Lua scripts
+fn call_api(lua: &Lua, module: &str, name: &str, args: &[u8]) -> Result<serde_json::Value, String> {
+    let documented = lua
+        .globals()
+        .get::<Table>("docs")
+        .and_then(|docs| docs.get::<Option<Table>>(module))
+        .and_then(|methods| match methods {
+            Some(methods) => methods.contains_key(name),
+            None => Ok(false),
+        })
+        .map_err(|e| e.to_string())?;
+    if !documented {
+        return Err(format!(
+            "api.{}.{} isn't a documented function",
+            module, name
+        ));
+    }
+    let function: Function = lua
+        .load(format!("return api.{}.{}", module, name))
+        .eval()
+        .map_err(|e| e.to_string())?;
+    let args: Vec<serde_json::Value> = serde_json::from_slice(args).map_err(|e| e.to_string())?;
+    let args = args
+        .iter()
+        .map(|arg| lua.to_value(arg))
+        .collect::<mlua::Result<MultiValue>>()
+        .map_err(|e| e.to_string())?;
+    let result: Value = function.call(args).map_err(|e| e.to_string())?;
+    serde_json::to_value(result.to_serializable().deny_unsupported_types(false))
+        .map_err(|e| e.to_string())
+}
+
+fn api(
+    mut caller: Caller<'_, Host>,
+    module_ptr: i32,
+    module_len: i32,
+    name_ptr: i32,
+    name_len: i32,
+    args_ptr: i32,
+    args_len: i32,
+) -> wasmtime::Result<i64> {
+    let (memory, alloc) = exports(&mut caller)?;
+    let module = read_guest(&caller, memory, module_ptr, module_len)?;
+    let name = read_guest(&caller, memory, name_ptr, name_len)?;
+    let args = read_guest(&caller, memory, args_ptr, args_len)?;
+    let lua = caller
+        .data()
+        .lua
+        .clone()
+        .ok_or_else(|| wasmtime::Error::msg("the api is only available in hooks"))?;
+    let response = match call_api(
+        &lua,
+        &String::from_utf8_lossy(&module),
+        &String::from_utf8_lossy(&name),
+        &args,
+    ) {
+        Ok(result) => serde_json::json!({ "ok": result }),
+        Err(e) => serde_json::json!({ "err": e }),
+    };
+    write_guest(&mut caller, memory, alloc, &serde_json::to_vec(&response)?)
+}
+
+impl WasmMod {
+    fn load(path: &str, limits: WasmLimits) -> wasmtime::Result<Self> {
+        let module = Module::from_file(engine(), path)?;
+        let mut store = Store::new(
+            engine(),
+            Host {
+                limits: StoreLimitsBuilder::new()
+                    .memory_size(limits.memory)
+                    .instances(1)
+                    .trap_on_grow_failure(true)
+                    .build(),
+                lua: None,
+            },
+        );
+        store.limiter(|host| &mut host.limits);
+        store.set_fuel(limits.fuel)?;
+        let mut linker = Linker::new(engine());
+        linker.func_wrap("sb5s", "api", api)?;
+        let instance = linker.instantiate(&mut store, &module)?;
+        Ok(Self {
+            store,
+            instance,
+            fuel: limits.fuel,
+        })
+    }
+
+    fn hook(&mut self, hook: Hook) -> Option<TypedFunc<(i32, i32), i64>> {
+        self.instance
+            .get_typed_func(&mut self.store, hook.name())
+            .ok()
+    }
+
+    fn call(&mut self, lua: &Lua, hook: Hook, args: &[u8]) -> wasmtime::Result<Vec<u8>> {
+        self.store.set_fuel(self.fuel)?;
+        self.store.data_mut().lua = Some(lua.clone());
+        let called = self.call_hook(hook, args);
+        self.store.data_mut().lua = None;
+        called
+    }
+
+    fn call_hook(&mut self, hook: Hook, args: &[u8]) -> wasmtime::Result<Vec<u8>> {
+        let function = self.hook(hook).ok_or_else(|| {
+            wasmtime::Error::msg(format!("the module exports no {}", hook.name()))
+        })?;
+        let memory = self
+            .instance
+            .get_memory(&mut self.store, "memory")
+            .ok_or_else(|| wasmtime::Error::msg("the module exports no memory"))?;
+        let alloc = self.instance.get_typed_func(&mut self.store, "alloc")?;
+        let packed = write_guest(&mut self.store, memory, alloc, args)?;
+        let returned = function.call(&mut self.store, ((packed >> 32) as i32, packed as i32))?;
+        if returned == 0 {
+            return Ok(Vec::new());
+        }
+        read_guest(
+            &self.store,
+            memory,
+            (returned >> 32) as i32,
+            returned as i32,
+        )
+    }
+}
+
+/// Load the module of a mod registered with `backend = "wasm"` and wrap the hooks it exports in
+/// Lua functions, so they are called, timed and logged like the hooks of Lua mods
+pub(crate) fn hooks(
+    lua: &Lua,
+    name: &str,
+    manifest: &Table,
+) -> mlua::Result<Vec<(Hook, Function)>> {
+    let path: String = manifest.get::<Option<String>>("module")?.ok_or_else(|| {
+        mlua::Error::RuntimeError(format!("WebAssembly mod '{}' has no module", name))
+    })?;
+    let limits = WasmLimits::read(manifest.get("limits")?)?;
+    let mut wasm_mod = WasmMod::load(&path, limits).map_err(|e| {
+        mlua::Error::RuntimeError(format!(
+            "Failed to load the module of mod '{}': {:#}",
+            name, e
+        ))
+    })?;
+    let exported: Vec<Hook> = Hook::ALL
+        .into_iter()
+        .filter(|hook| wasm_mod.hook(*hook).is_some())
+        .collect();
+
+    let wasm_mod = Arc::new(Mutex::new(wasm_mod));
+    exported
+        .into_iter()
+        .map(|hook| {
+            let wasm_mod = wasm_mod.clone();
+            let function = lua.create_function(move |lua, args: MultiValue| {
+                let args: Vec<_> = args
+                    .iter()
+                    .map(|arg| arg.to_serializable().deny_unsupported_types(false))
+                    .collect();
+                let args = serde_json::to_vec(&args).map_err(mlua::Error::external)?;
+                let returned = wasm_mod
+                    .try_lock()
+                    .map_err(|_| {
+                        mlua::Error::RuntimeError("the mod is already running".to_string())
+                    })?
+                    .call(lua, hook, &args)
+                    .map_err(|e| mlua::Error::RuntimeError(format!("{:#}", e)))?;
+                if returned.is_empty() {
+                    return Ok(Value::Nil);
+                }
+                let returned: serde_json::Value =
+                    serde_json::from_slice(&returned).map_err(mlua::Error::external)?;
+                lua.to_value(&returned)
+            })?;
+            Ok((hook, function))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error_log::ErrorLog;
+    use crate::lifecycle::LifecycleHooks;
+    use crate::mod_budget::ModBudget;
+    use crate::mod_settings::ModSettings;
+    use std::path::{Path, PathBuf};
+    use std::{fs, process};
+
+    // A module exporting `hook` with the body, and a bump allocator
+    fn module(name: &str, hook: &str, body: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("sb5s_{}_{}.wat", name, process::id()));
+        let wat = format!(
+            r#"(module
+                (import "sb5s" "api" (func $api (param i32 i32 i32 i32 i32 i32) (result i64)))
+                (memory (export "memory") 1)
+                (global $next (mut i32) (i32.const 1024))
+                (func (export "alloc") (param $len i32) (result i32)
+                    global.get $next
+                    global.get $next
+                    local.get $len
+                    i32.add
+                    global.set $next)
+                (data (i32.const 0) "person")
+                (data (i32.const 8) "create")
+                (data (i32.const 16) "delete")
+                (data (i32.const 24) "[\"Ada\",3,4]")
+                (func (export "{}") (param i32 i32) (result i64) {}))"#,
+            hook, body
+        );
+        fs::write(&path, wat).unwrap();
+        path
+    }
+
+    fn install(lua: &Lua) -> (LifecycleHooks, ErrorLog) {
+        let error_log = ErrorLog::default();
+        let hooks = LifecycleHooks::install(
+            lua,
+            error_log.clone(),
+            ModSettings::default(),
+            ModBudget::default(),
+        );
+        lua.load(
+            r#"
+            docs = { person = { create = { params = {} } } }
+            api = { person = {
+                create = function(name, x, y) return { name = name, at = x + y } end,
+                delete = function() deleted = true end,
+            } }
+            "#,
+        )
+        .exec()
+        .unwrap();
+        (hooks, error_log)
+    }
+
+    fn register(lua: &Lua, name: &str, path: &Path, limits: &str) -> mlua::Result<()> {
+        lua.load(format!(
+            r#"mods.register("{}", {{ backend = "wasm", module = [[{}]], limits = {{ {} }} }})"#,
+            name,
+            path.display(),
+            limits
+        ))
+        .exec()
+    }
+
+    #[test]
+    fn test_hooks_reach_the_documented_api() {
+        let lua = Lua::new();
+        let (hooks, _) = install(&lua);
+        let calls = |name| {
+            format!(
+                "(call $api (i32.const 0) (i32.const 6) (i32.const {}) (i32.const 6) (i32.const 24) (i32.const 11))",
+                name
+            )
+        };
+        let create = module("create", "on_save", &calls(8));
+        let delete = module("delete", "on_save", &calls(16));
+        register(&lua, "create", &create, "").unwrap();
+        register(&lua, "delete", &delete, "").unwrap();
+        fs::remove_file(create).unwrap();
+        fs::remove_file(delete).unwrap();
+
+        let state = hooks.save_state("map.json");
+        assert_eq!(
+            state["create"],
+            serde_json::json!({ "ok": { "name": "Ada", "at": 7 } })
+        );
+        assert!(state["delete"]["err"]
+            .as_str()
+            .unwrap()
+            .contains("api.person.delete isn't a documented function"));
+        assert_eq!(lua.globals().get::<Value>("deleted").unwrap(), Value::Nil);
+    }
+
+    #[test]
+    fn test_limits_are_enforced_per_mod() {
+        let lua = Lua::new();
+        let (hooks, error_log) = install(&lua);
+        let spin = module("spin", "on_frame", "(loop (br 0)) (i64.const 0)");
+        let grow = module(
+            "grow",
+            "on_init",
+            "(drop (memory.grow (i32.const 2))) (i64.const 0)",
+        );
+        register(&lua, "spin", &spin, "fuel = 10000").unwrap();
+        register(&lua, "grow", &grow, "memory = 131072").unwrap();
+        fs::remove_file(spin).unwrap();
+        fs::remove_file(grow).unwrap();
+
+        hooks.call(Hook::Frame, 0.5);
+        hooks.call(Hook::Init, ());
+        let errors = error_log.errors();
+        assert_eq!(errors[0].context, "on_frame of mod 'spin'");
+        assert!(
+            errors[0].error.message.contains("fuel"),
+            "{}",
+            errors[0].error.message
+        );
+        assert_eq!(errors[1].context, "on_init of mod 'grow'");
+        assert!(
+            errors[1].error.message.contains("memory"),
+            "{}",
+            errors[1].error.message
+        );
+    }
+
+    #[test]
+    fn test_ranges_outside_the_memory_are_errors() {
+        let lua = Lua::new();
+        let (hooks, error_log) = install(&lua);
+        let returned = |name, packed: i64| {
+            let path = module(name, "on_frame", &format!("(i64.const {})", packed));
+            register(&lua, name, &path, "").unwrap();
+            fs::remove_file(path).unwrap();
+        };
+        // Past the end of the single page, negative and larger than a message may be
+        returned("past", pack(65_000, 1_000));
+        returned("negative", pack(0, u32::MAX as usize));
+        returned("huge", pack(0, MAX_MESSAGE + 1));
+        let calls = "(call $api (i32.const 0) (i32.const -1) (i32.const 8) (i32.const 6) (i32.const 24) (i32.const 11))";
+        let path = module(
+            "api",
+            "on_frame",
+            &format!("(drop {}) (i64.const 0)", calls),
+        );
+        register(&lua, "api", &path, "").unwrap();
+        fs::remove_file(path).unwrap();
+
+        hooks.call(Hook::Frame, 0.5);
+        let messages: Vec<_> = error_log
+            .errors()
+            .into_iter()
+            .map(|error| error.error.message)
+            .collect();
+        assert_eq!(messages.len(), 4, "{:?}", messages);
+        assert!(
+            messages[0].contains("outside its memory"),
+            "{}",
+            messages[0]
+        );
+        assert!(messages[1].contains("negative range"), "{}", messages[1]);
+        assert!(messages[2].contains("more than the"), "{}", messages[2]);
+        assert!(messages[3].contains("negative range"), "{}", messages[3]);
+    }
+
+    #[test]
+    fn test_wasm_mods_need_a_module() {
+        let lua = Lua::new();
+        install(&lua);
+        let result = lua
+            .load(r#"mods.register("a", { backend = "wasm" })"#)
+            .exec();
+        assert!(result.unwrap_err().to_string().contains("has no module"));
+    }
+}
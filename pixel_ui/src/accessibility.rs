@@ -0,0 +1,219 @@
+use crate::config::{ACCESSIBILITY_CONFIG_PATH, TEXT_SCALE_MAX, TEXT_SCALE_MIN};
+use crate::utils::set_text_scale;
+use lua_engine::lua_engine::LuaEngine;
+use lua_engine::LuaError;
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+/// Colors the selected and hovered tiles and people are highlighted with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Palette {
+    /// Magenta selection, yellow hover
+    #[default]
+    Default,
+    /// Blue selection, orange hover, told apart with any of the common color vision deficiencies
+    ColorBlind,
+    /// White selection, cyan hover, the brightest colors against the dark tiles
+    HighContrast,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 3] = [Palette::Default, Palette::ColorBlind, Palette::HighContrast];
+
+    /// Name used in the settings file and in Lua
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Default => "default",
+            Palette::ColorBlind => "color_blind",
+            Palette::HighContrast => "high_contrast",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|palette| palette.name() == name)
+    }
+
+    pub fn selected(self) -> Color {
+        match self {
+            Palette::Default => MAGENTA,
+            // Blue and orange of the Okabe-Ito palette
+            Palette::ColorBlind => Color::from_rgba(0, 114, 178, 255),
+            Palette::HighContrast => WHITE,
+        }
+    }
+
+    pub fn hovered(self) -> Color {
+        match self {
+            Palette::Default => YELLOW,
+            Palette::ColorBlind => Color::from_rgba(230, 159, 0, 255),
+            Palette::HighContrast => Color::from_rgba(0, 255, 255, 255),
+        }
+    }
+
+    /// Tint of the tiles something can't be placed on
+    pub fn invalid(self) -> Color {
+        match self {
+            Palette::Default | Palette::HighContrast => RED,
+            // Vermillion of the Okabe-Ito palette
+            Palette::ColorBlind => Color::from_rgba(213, 94, 0, 255),
+        }
+    }
+}
+
+/// Accessibility options kept between sessions
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    pub palette: Palette,
+    /// Size of the HUD texts relative to the default
+    pub text_scale: f32,
+    /// Opaque console background and no dimmed lines
+    pub high_contrast_console: bool,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            palette: Palette::Default,
+            text_scale: 1.0,
+            high_contrast_console: false,
+        }
+    }
+}
+
+impl AccessibilitySettings {
+    /// Missing or broken files give the defaults
+    pub fn load(path: &str) -> Self {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        let mut settings: Self = serde_json::from_str(&content).unwrap_or_else(|e| {
+            println!("Failed to parse {}: {}", path, e);
+            Self::default()
+        });
+        settings.text_scale = settings.text_scale.clamp(TEXT_SCALE_MIN, TEXT_SCALE_MAX);
+        settings
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize the accessibility settings: {}", e))?;
+        fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+}
+
+/// Accessibility settings shared with Lua as `accessibility`, saved whenever a script changes them
+#[derive(Clone)]
+pub struct Accessibility {
+    settings: Arc<Mutex<AccessibilitySettings>>,
+}
+
+impl Accessibility {
+    pub(crate) fn new(lua_engine: &Arc<Mutex<LuaEngine>>) -> Self {
+        let settings = AccessibilitySettings::load(ACCESSIBILITY_CONFIG_PATH);
+        set_text_scale(settings.text_scale);
+        let accessibility = Self {
+            settings: Arc::new(Mutex::new(settings)),
+        };
+
+        let lua = &lua_engine.lock().unwrap().lua;
+        let table = lua.create_table().unwrap();
+        {
+            let accessibility = accessibility.clone();
+            // { palette = "default", text_scale = 1.0, high_contrast_console = false }
+            lua.create_function(move |lua, ()| {
+                let settings = accessibility.settings();
+                let result = lua.create_table()?;
+                result.set("palette", settings.palette.name())?;
+                result.set("text_scale", settings.text_scale)?;
+                result.set("high_contrast_console", settings.high_contrast_console)?;
+                Ok(result)
+            })
+            .and_then(|f| table.set("get", f))
+            .unwrap();
+        }
+        lua.create_function(|_, ()| Ok(Palette::ALL.map(Palette::name).to_vec()))
+            .and_then(|f| table.set("palettes", f))
+            .unwrap();
+        {
+            let accessibility = accessibility.clone();
+            lua.create_function(move |_, name: String| {
+                let palette = Palette::from_name(&name).ok_or_else(|| {
+                    LuaError::RuntimeError(format!(
+                        "Unknown palette '{}', expected one of: {}",
+                        name,
+                        Palette::ALL.map(Palette::name).join(", ")
+                    ))
+                })?;
+                accessibility.change(|settings| settings.palette = palette)
+            })
+            .and_then(|f| table.set("set_palette", f))
+            .unwrap();
+        }
+        {
+            let accessibility = accessibility.clone();
+            // Clamped to TEXT_SCALE_MIN..TEXT_SCALE_MAX, returns the scale set
+            lua.create_function(move |_, scale: f32| {
+                let scale = scale.clamp(TEXT_SCALE_MIN, TEXT_SCALE_MAX);
+                accessibility.change(|settings| settings.text_scale = scale)?;
+                Ok(scale)
+            })
+            .and_then(|f| table.set("set_text_scale", f))
+            .unwrap();
+        }
+        {
+            let accessibility = accessibility.clone();
+            lua.create_function(move |_, enabled: bool| {
+                accessibility.change(|settings| settings.high_contrast_console = enabled)
+            })
+            .and_then(|f| table.set("set_high_contrast_console", f))
+            .unwrap();
+        }
+        lua.globals().set("accessibility", table).unwrap();
+
+        accessibility
+    }
+
+    pub(crate) fn settings(&self) -> AccessibilitySettings {
+        *self.settings.lock().unwrap()
+    }
+
+    pub(crate) fn palette(&self) -> Palette {
+        self.settings().palette
+    }
+
+    // Applies the change right away, fails when it couldn't be saved
+    fn change(&self, change: impl FnOnce(&mut AccessibilitySettings)) -> Result<(), LuaError> {
+        let mut settings = self.settings.lock().unwrap();
+        change(&mut settings);
+        set_text_scale(settings.text_scale);
+        settings
+            .save(ACCESSIBILITY_CONFIG_PATH)
+            .map_err(LuaError::RuntimeError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palettes_are_found_by_name() {
+        for palette in Palette::ALL {
+            assert_eq!(Palette::from_name(palette.name()), Some(palette));
+        }
+        assert_eq!(Palette::from_name("magenta"), None);
+    }
+
+    #[test]
+    fn test_missing_settings_fall_back_to_defaults() {
+        let settings: AccessibilitySettings =
+            serde_json::from_str(r#"{ "palette": "color_blind" }"#).unwrap();
+        assert_eq!(settings.palette, Palette::ColorBlind);
+        assert_eq!(settings.text_scale, 1.0);
+        assert!(!settings.high_contrast_console);
+    }
+}
@@ -0,0 +1,483 @@
+use crate::batch::QuadBatch;
+use crate::config::{PERSON_SOURCE_TILE_SIZE, PERSON_TILE_SIZE};
+use crate::Direction;
+use macroquad::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Named animation states of a person, how each is played comes from the character manifest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AnimationState {
+    Idle,
+    Walk,
+    Work,
+    Sleep,
+}
+
+impl AnimationState {
+    pub const ALL: [AnimationState; 4] = [
+        AnimationState::Idle,
+        AnimationState::Walk,
+        AnimationState::Work,
+        AnimationState::Sleep,
+    ];
+
+    /// Name used in the manifest and in Lua
+    pub fn name(self) -> &'static str {
+        match self {
+            AnimationState::Idle => "idle",
+            AnimationState::Walk => "walk",
+            AnimationState::Work => "work",
+            AnimationState::Sleep => "sleep",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|state| state.name() == name)
+    }
+}
+
+// How a state is played, as declared in the manifest
+#[derive(Deserialize, Clone)]
+struct StateClip {
+    // Suffix of the sheet next to the walk sheet, "Idle" means GoblinIdle.png for GoblinWalk.png
+    sheet: String,
+    #[serde(default = "default_frame_time")]
+    frame_time: f32,
+    // Frames of the row to play, the whole row when missing
+    #[serde(default)]
+    frames: Option<usize>,
+    // Always use this row, otherwise every facing direction has its own row
+    #[serde(default)]
+    row: Option<usize>,
+    // Play once and switch to this state, instead of looping
+    #[serde(default)]
+    next: Option<String>,
+}
+
+fn default_frame_time() -> f32 {
+    0.15
+}
+
+impl Default for StateClip {
+    fn default() -> Self {
+        Self {
+            sheet: "Walk".to_string(),
+            frame_time: default_frame_time(),
+            frames: None,
+            row: None,
+            next: None,
+        }
+    }
+}
+
+// Extra sprites drawn with the body, using the same frames, e.g. shadows or hats
+#[derive(Deserialize, Clone)]
+struct LayerConfig {
+    name: String,
+    // Folders next to the walk sheet that may hold the layer's sheets
+    dirs: Vec<String>,
+    // The sheet of a state is the file starting with `prefix` and ending with the state's sheet
+    #[serde(default)]
+    prefix: String,
+    // Drawn under the bodies of all people instead of over the person's own body
+    #[serde(default)]
+    below: bool,
+    // Whether people show the layer until told otherwise, false for optional accessories
+    #[serde(default = "default_visible")]
+    visible: bool,
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+#[derive(Deserialize, Default)]
+struct ManifestFile {
+    #[serde(default)]
+    states: HashMap<String, StateClip>,
+    #[serde(default)]
+    layers: Vec<LayerConfig>,
+}
+
+/// Character manifest declaring the sheet and rows of every animation state, and the layers
+/// drawn with the body. States it leaves out are played with the walk sheet.
+pub struct CharacterManifest {
+    states: HashMap<AnimationState, StateClip>,
+    layers: Vec<LayerConfig>,
+}
+
+impl CharacterManifest {
+    /// Load the manifest from a JSON file, falling back to defaults when it is missing or invalid
+    pub fn load(path: &str) -> Self {
+        let file = match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str::<ManifestFile>(&content).unwrap_or_else(|e| {
+                println!("Failed to parse character manifest {}: {}", path, e);
+                ManifestFile::default()
+            }),
+            Err(e) => {
+                println!("Failed to read character manifest {}: {}", path, e);
+                ManifestFile::default()
+            }
+        };
+
+        let mut states = HashMap::new();
+        for (name, clip) in file.states {
+            match AnimationState::from_name(&name) {
+                Some(state) => {
+                    states.insert(state, clip);
+                }
+                None => println!("Unknown animation state '{}' in {}", name, path),
+            }
+        }
+
+        let mut layers = file.layers;
+        if layers.len() > MAX_LAYERS {
+            println!(
+                "Only the first {} character layers in {} are used",
+                MAX_LAYERS, path
+            );
+            layers.truncate(MAX_LAYERS);
+        }
+        Self { states, layers }
+    }
+
+    fn clip(&self, state: AnimationState) -> StateClip {
+        self.states
+            .get(&state)
+            .or_else(|| self.states.get(&AnimationState::Walk))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+// Layer visibility of a person is kept as bits
+const MAX_LAYERS: usize = u32::BITS as usize;
+
+struct StateSprites {
+    texture: Texture2D,
+    frames: usize,
+    frame_time: f32,
+    row: Option<usize>,
+    next: Option<AnimationState>,
+}
+
+struct SpriteLayer {
+    name: String,
+    below: bool,
+    visible: bool,
+    // Indexed by `AnimationState as usize`, None when the character has no sheet for the state
+    sheets: Vec<Option<Texture2D>>,
+}
+
+/// The sheets of one character for all animation states, shared by everyone looking like it
+pub struct CharacterSprites {
+    /// Name of the character, the walk sheet's file name without "Walk.png", like "Dwarf"
+    pub name: String,
+    // Indexed by `AnimationState as usize`
+    states: Vec<StateSprites>,
+    layers: Vec<SpriteLayer>,
+}
+
+impl CharacterSprites {
+    /// Load the sheets of the character whose walk sheet is at `walk_path`, the other sheets
+    /// are looked up next to it. States whose sheet can't be loaded use the walk sheet.
+    pub async fn load(walk_path: &Path, manifest: &CharacterManifest) -> Option<Self> {
+        let folder = walk_path.parent()?;
+        let walk_path = walk_path.to_str()?;
+        let prefix = walk_path.strip_suffix("Walk.png")?;
+        let name = Path::new(prefix).file_name()?.to_str()?.to_string();
+        let mut textures = SheetCache::default();
+        let walk = textures.load(walk_path).await?;
+        let mut states = Vec::new();
+        for state in AnimationState::ALL {
+            let clip = manifest.clip(state);
+            let path = format!("{}{}.png", prefix, clip.sheet);
+            let texture = textures.load(&path).await.unwrap_or_else(|| walk.clone());
+
+            let columns = ((texture.width() / PERSON_SOURCE_TILE_SIZE) as usize).max(1);
+            let next = clip.next.as_deref().and_then(|name| {
+                let next = AnimationState::from_name(name);
+                if next.is_none() {
+                    println!("Unknown next state '{}' of '{}'", name, state.name());
+                }
+                next
+            });
+            states.push(StateSprites {
+                texture,
+                frames: clip.frames.unwrap_or(columns).clamp(1, columns),
+                frame_time: clip.frame_time,
+                row: clip.row,
+                next,
+            });
+        }
+
+        let mut layers = Vec::new();
+        for config in &manifest.layers {
+            let mut sheets = Vec::new();
+            for state in AnimationState::ALL {
+                let sheet = manifest.clip(state).sheet;
+                let texture = match find_layer_sheet(folder, config, &sheet) {
+                    Some(path) => textures.load(&path).await,
+                    None => None,
+                };
+                sheets.push(texture);
+            }
+            layers.push(SpriteLayer {
+                name: config.name.clone(),
+                below: config.below,
+                visible: config.visible,
+                sheets,
+            });
+        }
+
+        Some(Self {
+            name,
+            states,
+            layers,
+        })
+    }
+
+    /// A character of one frame per state on a texture that is never drawn, for the tests that
+    /// simulate people without a window
+    #[cfg(test)]
+    pub(crate) fn blank(name: &str) -> Self {
+        use macroquad::miniquad::{RawId, TextureId};
+        let texture = Texture2D::from_miniquad_texture(TextureId::from_raw_id(RawId::OpenGl(0)));
+        Self {
+            name: name.to_string(),
+            states: AnimationState::ALL
+                .iter()
+                .map(|_| StateSprites {
+                    texture: texture.clone(),
+                    frames: 1,
+                    frame_time: 0.1,
+                    row: None,
+                    next: None,
+                })
+                .collect(),
+            layers: Vec::new(),
+        }
+    }
+
+    fn state(&self, state: AnimationState) -> &StateSprites {
+        &self.states[state as usize]
+    }
+
+    fn layer_index(&self, name: &str) -> Option<usize> {
+        self.layers.iter().position(|layer| layer.name == name)
+    }
+
+    // Bits of the layers people show when they are created
+    fn default_layers(&self) -> u32 {
+        self.layers
+            .iter()
+            .enumerate()
+            .filter(|(_, layer)| layer.visible)
+            .fold(0, |bits, (index, _)| bits | 1 << index)
+    }
+}
+
+// Several states share sheets, each file is loaded once per character
+#[derive(Default)]
+struct SheetCache {
+    textures: HashMap<String, Option<Texture2D>>,
+}
+
+impl SheetCache {
+    async fn load(&mut self, path: &str) -> Option<Texture2D> {
+        if let Some(texture) = self.textures.get(path) {
+            return texture.clone();
+        }
+        let texture = load_sheet(path).await;
+        self.textures.insert(path.to_string(), texture.clone());
+        texture
+    }
+}
+
+// The asset packs name layer sheets inconsistently (ShadowHumanoidWalk, ShadowDwarWalk,
+// ShadowWalk), so they are matched by prefix and suffix. The shortest name wins, as
+// the suffix "Attack" also matches "ChargedAttack".
+fn find_layer_sheet(folder: &Path, config: &LayerConfig, sheet: &str) -> Option<String> {
+    let suffix = format!("{}.png", sheet);
+    config
+        .dirs
+        .iter()
+        .filter_map(|dir| fs::read_dir(folder.join(dir)).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter_map(|entry| entry.path().to_str().map(str::to_string))
+        .filter(|path| {
+            Path::new(path)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(&config.prefix) && name.ends_with(&suffix))
+        })
+        .min_by_key(|path| path.len())
+}
+
+async fn load_sheet(path: &str) -> Option<Texture2D> {
+    match load_texture(path).await {
+        Ok(texture) => {
+            texture.set_filter(FilterMode::Nearest);
+            Some(texture)
+        }
+        Err(e) => {
+            println!("Failed to load texture {}: {:?}", path, e);
+            None
+        }
+    }
+}
+
+/// Plays the animation of the current state, switching to the `next` state of one-shot states
+pub struct AnimationController {
+    sprites: Arc<CharacterSprites>,
+    state: AnimationState,
+    frame: usize,
+    timer: f32,
+    // Bit per layer of the character, set when the person shows it
+    layers: u32,
+    // Multiplied with the body's colors, e.g. the color of the person's faction
+    tint: Color,
+    // Tints of single layers like a hat, most people have none
+    layer_tints: Vec<(usize, Color)>,
+}
+
+impl AnimationController {
+    pub fn new(sprites: Arc<CharacterSprites>) -> Self {
+        Self {
+            layers: sprites.default_layers(),
+            sprites,
+            state: AnimationState::Idle,
+            frame: 0,
+            timer: 0.0,
+            tint: WHITE,
+            layer_tints: Vec::new(),
+        }
+    }
+
+    pub fn state(&self) -> AnimationState {
+        self.state
+    }
+
+    /// Switch to `state`, starting it from its first frame unless it is already playing
+    pub fn set_state(&mut self, state: AnimationState) {
+        if state != self.state {
+            self.state = state;
+            self.frame = 0;
+            self.timer = 0.0;
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let sprites = self.sprites.state(self.state);
+        self.timer += dt;
+
+        if self.timer >= sprites.frame_time {
+            self.timer -= sprites.frame_time;
+            self.frame += 1;
+            if self.frame >= sprites.frames {
+                match sprites.next {
+                    Some(next) => self.set_state(next),
+                    None => self.frame = 0,
+                }
+            }
+        }
+    }
+
+    /// Show or hide the layer of the given name, returns false when the character has no such layer
+    pub fn set_layer_visible(&mut self, name: &str, visible: bool) -> bool {
+        let Some(index) = self.sprites.layer_index(name) else {
+            return false;
+        };
+        if visible {
+            self.layers |= 1 << index;
+        } else {
+            self.layers &= !(1 << index);
+        }
+        true
+    }
+
+    /// Tint the body, or the layer of the given name, WHITE removes the tint.
+    /// Returns false when the character has no such layer.
+    pub fn set_tint(&mut self, layer: Option<&str>, tint: Color) -> bool {
+        let Some(layer) = layer else {
+            self.tint = tint;
+            return true;
+        };
+        let Some(index) = self.sprites.layer_index(layer) else {
+            return false;
+        };
+        self.layer_tints.retain(|(tinted, _)| *tinted != index);
+        if tint != WHITE {
+            self.layer_tints.push((index, tint));
+        }
+        true
+    }
+
+    /// Draw the layers that go under the bodies of everyone, like shadows
+    pub fn draw_below(&self, batch: &mut QuadBatch, position: Vec2, direction: &Direction) {
+        self.draw_layers(batch, position, direction, true);
+    }
+
+    /// Draw the body with the layers on top of it
+    pub fn draw(&self, batch: &mut QuadBatch, position: Vec2, direction: &Direction) {
+        let sprites = self.sprites.state(self.state);
+        self.draw_frame(batch, &sprites.texture, position, direction, self.tint);
+        self.draw_layers(batch, position, direction, false);
+    }
+
+    fn draw_layers(
+        &self,
+        batch: &mut QuadBatch,
+        position: Vec2,
+        direction: &Direction,
+        below: bool,
+    ) {
+        for (index, layer) in self.sprites.layers.iter().enumerate() {
+            if layer.below != below || self.layers & (1 << index) == 0 {
+                continue;
+            }
+            if let Some(texture) = &layer.sheets[self.state as usize] {
+                let tint = self
+                    .layer_tints
+                    .iter()
+                    .find(|(tinted, _)| *tinted == index)
+                    .map_or(WHITE, |(_, tint)| *tint);
+                self.draw_frame(batch, texture, position, direction, tint);
+            }
+        }
+    }
+
+    // Layers are drawn with the frame of the body, their sheets have the same layout
+    fn draw_frame(
+        &self,
+        batch: &mut QuadBatch,
+        texture: &Texture2D,
+        position: Vec2,
+        direction: &Direction,
+        tint: Color,
+    ) {
+        let sprites = self.sprites.state(self.state);
+        let row = sprites.row.unwrap_or_else(|| direction.row());
+
+        batch.push(
+            texture,
+            Rect::new(
+                position.x - PERSON_TILE_SIZE / 2.0,
+                position.y - PERSON_TILE_SIZE / 2.0,
+                PERSON_TILE_SIZE,
+                PERSON_TILE_SIZE,
+            ),
+            Rect::new(
+                self.frame as f32 * PERSON_SOURCE_TILE_SIZE,
+                row as f32 * PERSON_SOURCE_TILE_SIZE,
+                PERSON_SOURCE_TILE_SIZE,
+                PERSON_SOURCE_TILE_SIZE,
+            ),
+            tint,
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use crate::config::{TILE_CHUNK_SIZE, TILE_SIZE};
+use crate::layers::TileLayer;
+use crate::tileset::TileAtlas;
+use macroquad::miniquad::TextureId;
+use macroquad::models::{draw_mesh, Mesh, Vertex};
+use macroquad::prelude::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// macroquad clamps a single submission to 10000 vertices and 5000 indices
+const MAX_BATCH_QUADS: usize = 800;
+// A chunk is drawn with a single submission
+const _: () = assert!((TILE_CHUNK_SIZE * TILE_CHUNK_SIZE) as usize <= MAX_BATCH_QUADS);
+
+static DRAW_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+// Every mesh goes out through here, so the submissions can be counted
+fn submit(mesh: &Mesh) {
+    draw_mesh(mesh);
+    DRAW_CALLS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Meshes submitted by the batches since the last call. macroquad may still merge some of them,
+/// and whatever is drawn with `draw_texture_ex` or the shape functions directly isn't counted.
+pub(crate) fn take_draw_calls() -> usize {
+    DRAW_CALLS.swap(0, Ordering::Relaxed)
+}
+
+/// Quads waiting for a texture of their own to come up in a grouped batch
+struct TextureGroup {
+    texture: Texture2D,
+    quads: Vec<(Rect, Rect, Color)>,
+}
+
+/// Textured quads collected into one mesh and submitted with a single `draw_mesh`, instead of
+/// one `draw_texture_ex` call each. Switching to another texture submits what was collected.
+pub struct QuadBatch {
+    mesh: Mesh,
+    // Quads by texture when grouped, submitted texture by texture on `flush`
+    groups: Option<Vec<TextureGroup>>,
+    group_of: HashMap<TextureId, usize>,
+}
+
+impl QuadBatch {
+    pub(crate) fn new() -> Self {
+        Self {
+            mesh: Mesh {
+                vertices: Vec::with_capacity(MAX_BATCH_QUADS * 4),
+                indices: Vec::with_capacity(MAX_BATCH_QUADS * 6),
+                texture: None,
+            },
+            groups: None,
+            group_of: HashMap::new(),
+        }
+    }
+
+    /// A batch keeping the quads until `flush` and submitting them texture by texture, in as few
+    /// meshes as there are textures however the quads came. Quads of different textures may end
+    /// up drawn in another order than they were pushed.
+    pub(crate) fn grouped() -> Self {
+        Self {
+            groups: Some(Vec::new()),
+            ..Self::new()
+        }
+    }
+
+    /// Add the `source` part of `texture` drawn into `dest` in world (or screen) units
+    pub(crate) fn push(&mut self, texture: &Texture2D, dest: Rect, source: Rect, color: Color) {
+        if let Some(groups) = &mut self.groups {
+            let index = *self
+                .group_of
+                .entry(texture.raw_miniquad_id())
+                .or_insert_with(|| {
+                    groups.push(TextureGroup {
+                        texture: texture.clone(),
+                        quads: Vec::new(),
+                    });
+                    groups.len() - 1
+                });
+            groups[index].quads.push((dest, source, color));
+            return;
+        }
+        self.push_now(texture, dest, source, color);
+    }
+
+    fn push_now(&mut self, texture: &Texture2D, dest: Rect, source: Rect, color: Color) {
+        if self.mesh.texture.as_ref() != Some(texture) || self.quads() >= MAX_BATCH_QUADS {
+            self.submit();
+            self.mesh.texture = Some(texture.clone());
+        }
+        push_quad(&mut self.mesh, texture.size(), dest, source, color);
+    }
+
+    /// Submit the quads collected so far
+    pub(crate) fn flush(&mut self) {
+        if let Some(mut groups) = self.groups.take() {
+            for group in &mut groups {
+                for (dest, source, color) in group.quads.drain(..) {
+                    self.push_now(&group.texture, dest, source, color);
+                }
+            }
+            self.groups = Some(groups);
+        }
+        self.submit();
+    }
+
+    fn submit(&mut self) {
+        if !self.mesh.indices.is_empty() {
+            submit(&self.mesh);
+        }
+        self.mesh.vertices.clear();
+        self.mesh.indices.clear();
+    }
+
+    fn quads(&self) -> usize {
+        self.mesh.vertices.len() / 4
+    }
+}
+
+fn push_quad(mesh: &mut Mesh, texture_size: Vec2, dest: Rect, source: Rect, color: Color) {
+    let first = mesh.vertices.len() as u16;
+    let (u0, v0) = (source.x / texture_size.x, source.y / texture_size.y);
+    let (u1, v1) = (
+        source.right() / texture_size.x,
+        source.bottom() / texture_size.y,
+    );
+    mesh.vertices.extend([
+        Vertex::new(dest.x, dest.y, 0.0, u0, v0, color),
+        Vertex::new(dest.right(), dest.y, 0.0, u1, v0, color),
+        Vertex::new(dest.right(), dest.bottom(), 0.0, u1, v1, color),
+        Vertex::new(dest.x, dest.bottom(), 0.0, u0, v1, color),
+    ]);
+    mesh.indices
+        .extend([0, 1, 2, 0, 2, 3].map(|index| first + index));
+}
+
+// Mesh of the tiles of one layer in one chunk, kept until they change
+struct TileChunk {
+    mesh: Mesh,
+    tiles: usize,
+    opacity: f32,
+}
+
+/// The tiles of every layer baked into one mesh per chunk of `TILE_CHUNK_SIZE`² tiles, so drawing
+/// a large visible area submits a few prepared meshes instead of building every tile each frame.
+/// Chunks are built when they are first drawn and rebuilt after their tiles change.
+#[derive(Default)]
+pub struct TileBatches {
+    // By chunk position, then by layer index
+    chunks: HashMap<(i32, i32), HashMap<usize, TileChunk>>,
+}
+
+impl TileBatches {
+    /// Forget the chunk holding the tile on every layer, it is rebuilt when drawn next
+    pub(crate) fn invalidate(&mut self, x: i32, y: i32) {
+        self.chunks.remove(&chunk_of(x, y));
+    }
+
+    /// Forget every chunk, after the layers were replaced or a large area changed
+    pub(crate) fn invalidate_all(&mut self) {
+        self.chunks.clear();
+    }
+
+    /// Draw the chunks of `layer` (at `index`) overlapping the tile range, returns the number of
+    /// tiles drawn
+    pub(crate) fn draw_layer(
+        &mut self,
+        index: usize,
+        layer: &TileLayer,
+        tileset: &Texture2D,
+        atlas: TileAtlas,
+        (min_x, min_y, max_x, max_y): (i32, i32, i32, i32),
+    ) -> usize {
+        let (min_chunk_x, min_chunk_y) = chunk_of(min_x, min_y);
+        let (max_chunk_x, max_chunk_y) = chunk_of(max_x, max_y);
+        let mut tiles = 0;
+        for chunk_y in min_chunk_y..=max_chunk_y {
+            for chunk_x in min_chunk_x..=max_chunk_x {
+                let chunk = self
+                    .chunks
+                    .entry((chunk_x, chunk_y))
+                    .or_default()
+                    .entry(index)
+                    .or_insert_with(|| build_chunk(layer, tileset, atlas, chunk_x, chunk_y));
+                // Opacity lives in the vertex colors, the layers panel changes it without an edit
+                if chunk.opacity != layer.opacity {
+                    *chunk = build_chunk(layer, tileset, atlas, chunk_x, chunk_y);
+                }
+                if chunk.tiles > 0 {
+                    submit(&chunk.mesh);
+                    tiles += chunk.tiles;
+                }
+            }
+        }
+        tiles
+    }
+}
+
+// Chunk holding the tile, chunk (0, 0) covers tiles 0..TILE_CHUNK_SIZE on both axes
+fn chunk_of(x: i32, y: i32) -> (i32, i32) {
+    (x.div_euclid(TILE_CHUNK_SIZE), y.div_euclid(TILE_CHUNK_SIZE))
+}
+
+fn build_chunk(
+    layer: &TileLayer,
+    tileset: &Texture2D,
+    atlas: TileAtlas,
+    chunk_x: i32,
+    chunk_y: i32,
+) -> TileChunk {
+    let mut mesh = Mesh {
+        vertices: Vec::new(),
+        indices: Vec::new(),
+        texture: Some(tileset.clone()),
+    };
+    let mut color = WHITE;
+    color.a = layer.opacity;
+    let mut tiles = 0;
+    for y in chunk_y * TILE_CHUNK_SIZE..(chunk_y + 1) * TILE_CHUNK_SIZE {
+        for x in chunk_x * TILE_CHUNK_SIZE..(chunk_x + 1) * TILE_CHUNK_SIZE {
+            let Some(tile) = layer.tiles.get(&(x, y)) else {
+                continue;
+            };
+            push_quad(
+                &mut mesh,
+                tileset.size(),
+                Rect::new(
+                    x as f32 * TILE_SIZE,
+                    y as f32 * TILE_SIZE,
+                    TILE_SIZE,
+                    TILE_SIZE,
+                ),
+                atlas.source(tile.id),
+                color,
+            );
+            tiles += 1;
+        }
+    }
+    TileChunk {
+        mesh,
+        tiles,
+        opacity: layer.opacity,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_split_at_multiples_of_the_chunk_size() {
+        assert_eq!(chunk_of(0, 0), (0, 0));
+        assert_eq!(chunk_of(TILE_CHUNK_SIZE - 1, TILE_CHUNK_SIZE), (0, 1));
+        assert_eq!(chunk_of(-1, -TILE_CHUNK_SIZE), (-1, -1));
+        assert_eq!(chunk_of(-TILE_CHUNK_SIZE - 1, 0), (-2, 0));
+    }
+}
@@ -0,0 +1,141 @@
+use crate::config::BRUSH_MAX_SIZE;
+use crate::TilePosition;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrushShape {
+    Square,
+    Circle,
+}
+
+impl BrushShape {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BrushShape::Square => "square",
+            BrushShape::Circle => "circle",
+        }
+    }
+}
+
+/// Brush used for painting tiles, covering an NxN area around the cursor
+pub struct Brush {
+    pub(crate) size: i32,
+    pub(crate) shape: BrushShape,
+    pub(crate) tile_id: Option<usize>,
+}
+
+impl Brush {
+    pub(crate) fn new() -> Self {
+        Self {
+            size: 1,
+            shape: BrushShape::Square,
+            tile_id: None,
+        }
+    }
+
+    pub(crate) fn grow(&mut self) {
+        self.size = (self.size + 1).min(BRUSH_MAX_SIZE);
+    }
+
+    pub(crate) fn shrink(&mut self) {
+        self.size = (self.size - 1).max(1);
+    }
+
+    pub(crate) fn toggle_shape(&mut self) {
+        self.shape = match self.shape {
+            BrushShape::Square => BrushShape::Circle,
+            BrushShape::Circle => BrushShape::Square,
+        };
+    }
+
+    /// All tile positions covered by the brush when centered at the given tile
+    pub(crate) fn footprint(&self, center: TilePosition) -> Vec<TilePosition> {
+        // Even sizes extend one tile further towards positive coordinates
+        let min_offset = -(self.size - 1) / 2;
+        let max_offset = self.size / 2;
+        let middle = (min_offset + max_offset) as f32 / 2.0;
+        // Slightly smaller than half the size so that small circles don't degrade into squares
+        let radius = self.size as f32 / 2.0 - 0.25;
+
+        let mut positions = Vec::new();
+        for dy in min_offset..=max_offset {
+            for dx in min_offset..=max_offset {
+                let covered = match self.shape {
+                    BrushShape::Square => true,
+                    BrushShape::Circle => {
+                        let fx = dx as f32 - middle;
+                        let fy = dy as f32 - middle;
+                        fx * fx + fy * fy <= radius * radius
+                    }
+                };
+                if covered {
+                    positions.push(TilePosition::new(center.x + dx, center.y + dy));
+                }
+            }
+        }
+        positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brush(size: i32, shape: BrushShape) -> Brush {
+        Brush {
+            size,
+            shape,
+            tile_id: None,
+        }
+    }
+
+    #[test]
+    fn test_single_tile_brush() {
+        let center = TilePosition::new(3, 4);
+
+        assert_eq!(brush(1, BrushShape::Square).footprint(center), vec![center]);
+        assert_eq!(brush(1, BrushShape::Circle).footprint(center), vec![center]);
+    }
+
+    #[test]
+    fn test_square_brush_covers_full_area() {
+        let footprint = brush(3, BrushShape::Square).footprint(TilePosition::new(0, 0));
+
+        assert_eq!(footprint.len(), 9);
+        assert!(footprint.contains(&TilePosition::new(-1, -1)));
+        assert!(footprint.contains(&TilePosition::new(1, 1)));
+    }
+
+    #[test]
+    fn test_even_square_brush_extends_towards_positive() {
+        let footprint = brush(2, BrushShape::Square).footprint(TilePosition::new(0, 0));
+
+        assert_eq!(footprint.len(), 4);
+        assert!(footprint.contains(&TilePosition::new(0, 0)));
+        assert!(footprint.contains(&TilePosition::new(1, 1)));
+        assert!(!footprint.contains(&TilePosition::new(-1, -1)));
+    }
+
+    #[test]
+    fn test_circle_brush_skips_corners() {
+        let footprint = brush(5, BrushShape::Circle).footprint(TilePosition::new(0, 0));
+
+        assert!(footprint.contains(&TilePosition::new(0, 0)));
+        assert!(footprint.contains(&TilePosition::new(2, 0)));
+        assert!(footprint.contains(&TilePosition::new(2, 1)));
+        assert!(!footprint.contains(&TilePosition::new(2, 2)));
+        assert!(!footprint.contains(&TilePosition::new(-2, -2)));
+        assert!(footprint.len() < 25);
+    }
+
+    #[test]
+    fn test_size_is_clamped() {
+        let mut brush = Brush::new();
+        brush.shrink();
+        assert_eq!(brush.size, 1);
+
+        for _ in 0..BRUSH_MAX_SIZE * 2 {
+            brush.grow();
+        }
+        assert_eq!(brush.size, BRUSH_MAX_SIZE);
+    }
+}
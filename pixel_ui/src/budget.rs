@@ -0,0 +1,128 @@
+use crate::config::{
+    FRAME_BUDGET_HEADROOM, FRAME_BUDGET_MS, FRAME_BUDGET_OVER_FRAMES, FRAME_BUDGET_RECOVER_FRAMES,
+};
+
+/// Work left out while frames run over the budget, in the order it's given up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Degradation {
+    /// People no camera shows aren't updated at all, they catch up once it's lifted
+    OffscreenPeople,
+    /// The handlers of the Lua UI components run every other frame only
+    LuaUiRate,
+    /// Sparkles are neither spawned nor drawn
+    Particles,
+}
+
+impl Degradation {
+    const ALL: [Degradation; 3] = [
+        Degradation::OffscreenPeople,
+        Degradation::LuaUiRate,
+        Degradation::Particles,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Degradation::OffscreenPeople => "offscreen people",
+            Degradation::LuaUiRate => "half rate Lua UI",
+            Degradation::Particles => "particles",
+        }
+    }
+}
+
+/// Keeps frames within `FRAME_BUDGET_MS` by giving up work step by step. Frames over the budget
+/// for a while add the next degradation, frames well within it for longer lift the last one
+/// again, so a single slow frame doesn't change anything and the guard doesn't flicker.
+pub struct FrameBudget {
+    // How many of `Degradation::ALL` are active
+    level: usize,
+    over: u32,
+    under: u32,
+    frame: u64,
+}
+
+impl FrameBudget {
+    pub(crate) fn new() -> Self {
+        Self {
+            level: 0,
+            over: 0,
+            under: 0,
+            frame: 0,
+        }
+    }
+
+    /// Account for a frame that took `ms` milliseconds
+    pub(crate) fn record(&mut self, ms: f64) {
+        self.frame = self.frame.wrapping_add(1);
+        if ms > FRAME_BUDGET_MS {
+            self.over += 1;
+            self.under = 0;
+        } else if ms < FRAME_BUDGET_MS * FRAME_BUDGET_HEADROOM {
+            self.under += 1;
+            self.over = 0;
+        } else {
+            self.over = 0;
+            self.under = 0;
+        }
+
+        if self.over >= FRAME_BUDGET_OVER_FRAMES && self.level < Degradation::ALL.len() {
+            self.level += 1;
+            self.over = 0;
+        } else if self.under >= FRAME_BUDGET_RECOVER_FRAMES && self.level > 0 {
+            self.level -= 1;
+            self.under = 0;
+        }
+    }
+
+    pub(crate) fn is_active(&self, degradation: Degradation) -> bool {
+        self.active().contains(&degradation)
+    }
+
+    pub(crate) fn active(&self) -> &[Degradation] {
+        &Degradation::ALL[..self.level]
+    }
+
+    // Whether the Lua UI handlers run this frame
+    pub(crate) fn refresh_lua_ui(&self) -> bool {
+        !self.is_active(Degradation::LuaUiRate) || self.frame.is_multiple_of(2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_degrades_step_by_step_and_recovers() {
+        let mut budget = FrameBudget::new();
+        // A single slow frame changes nothing
+        budget.record(FRAME_BUDGET_MS * 2.0);
+        budget.record(1.0);
+        assert!(budget.active().is_empty());
+
+        for _ in 0..FRAME_BUDGET_OVER_FRAMES * 2 {
+            budget.record(FRAME_BUDGET_MS * 2.0);
+        }
+        assert_eq!(
+            budget.active(),
+            &[Degradation::OffscreenPeople, Degradation::LuaUiRate]
+        );
+        assert!(!budget.is_active(Degradation::Particles));
+        let refreshed = (0..4)
+            .filter(|_| {
+                budget.record(FRAME_BUDGET_MS);
+                budget.refresh_lua_ui()
+            })
+            .count();
+        assert_eq!(refreshed, 2);
+
+        // Frames just within the budget keep everything as it is
+        for _ in 0..FRAME_BUDGET_RECOVER_FRAMES {
+            budget.record(FRAME_BUDGET_MS);
+        }
+        assert_eq!(budget.active().len(), 2);
+        for _ in 0..FRAME_BUDGET_RECOVER_FRAMES {
+            budget.record(1.0);
+        }
+        assert_eq!(budget.active(), &[Degradation::OffscreenPeople]);
+    }
+}
@@ -1,11 +1,52 @@
-use crate::config::{CAMERA_SPEED, ZOOM_MAX, ZOOM_MIN, ZOOM_SPEED};
+use crate::config::{
+    CAMERA_PAN_MAX_SPEED, CAMERA_PAN_RAMP_TIME, CAMERA_PAN_SPEED, FIT_MARGIN, TILE_SIZE, ZOOM_MAX,
+    ZOOM_MIN, ZOOM_SMOOTHING,
+};
 use crate::input::InputManager;
+use crate::view_settings::ViewSettings;
+use crate::MapBounds;
+use lua_engine::actions::Action;
 
 use macroquad::prelude::*;
 
+// Effect fading out over a fixed duration
+struct TimedEffect {
+    strength: f32,
+    duration: f32,
+    remaining: f32,
+}
+
+impl TimedEffect {
+    // Current strength, linearly decaying to zero
+    fn current(&self) -> f32 {
+        self.strength * self.remaining / self.duration
+    }
+}
+
+// Smooth camera movement towards a target position
+struct Pan {
+    from: Vec2,
+    to: Vec2,
+    duration: f32,
+    elapsed: f32,
+}
+
 pub struct CameraController {
     pub(crate) position: Vec2,
     pub(crate) zoom: f32,
+    // Zoom the wheel asked for, `zoom` eases towards it around `zoom_anchor` on the screen
+    zoom_target: f32,
+    zoom_anchor: Vec2,
+    // Seconds a direction key has been held, panning speeds up the longer it is
+    pan_held: f32,
+    /// Screen area the camera renders into, the whole screen when None
+    pub(crate) viewport: Option<Rect>,
+    /// How the wheel and the zoom keys zoom
+    pub(crate) view: ViewSettings,
+    shake: Option<TimedEffect>,
+    shake_offset: Vec2,
+    zoom_punch: Option<TimedEffect>,
+    pan: Option<Pan>,
 }
 
 impl CameraController {
@@ -13,13 +54,100 @@ impl CameraController {
         Self {
             position,
             zoom: 1.0,
+            zoom_target: 1.0,
+            zoom_anchor: Vec2::ZERO,
+            pan_held: 0.0,
+            viewport: None,
+            view: ViewSettings::default(),
+            shake: None,
+            shake_offset: Vec2::ZERO,
+            zoom_punch: None,
+            pan: None,
+        }
+    }
+
+    /// Shake the view for `duration` seconds, `amplitude` is the initial offset in screen pixels
+    pub(crate) fn shake(&mut self, duration: f32, amplitude: f32) {
+        if duration > 0.0 {
+            self.shake = Some(TimedEffect {
+                strength: amplitude,
+                duration,
+                remaining: duration,
+            });
+        }
+    }
+
+    /// Briefly zoom in by `amount` (0.1 = 10%) and ease back over `duration` seconds
+    pub(crate) fn zoom_punch(&mut self, amount: f32, duration: f32) {
+        if duration > 0.0 {
+            self.zoom_punch = Some(TimedEffect {
+                strength: amount,
+                duration,
+                remaining: duration,
+            });
+        }
+    }
+
+    /// Smoothly move the view center to the given world position
+    pub(crate) fn pan_to(&mut self, target: Vec2, duration: f32) {
+        if duration <= 0.0 {
+            self.position = target;
+            self.pan = None;
+            return;
+        }
+        self.pan = Some(Pan {
+            from: self.position,
+            to: target,
+            duration,
+            elapsed: 0.0,
+        });
+    }
+
+    /// Advance running effects, called every frame even when user input is blocked
+    pub(crate) fn update_effects(&mut self, dt: f32) {
+        self.shake_offset = Vec2::ZERO;
+        if let Some(shake) = &mut self.shake {
+            shake.remaining -= dt;
+            if shake.remaining <= 0.0 {
+                self.shake = None;
+            } else {
+                let strength = shake.current();
+                self.shake_offset = Vec2::new(
+                    rand::gen_range(-strength, strength),
+                    rand::gen_range(-strength, strength),
+                );
+            }
+        }
+
+        if let Some(punch) = &mut self.zoom_punch {
+            punch.remaining -= dt;
+            if punch.remaining <= 0.0 {
+                self.zoom_punch = None;
+            }
+        }
+
+        if let Some(pan) = &mut self.pan {
+            pan.elapsed = (pan.elapsed + dt).min(pan.duration);
+            // Smoothstep easing
+            let t = pan.elapsed / pan.duration;
+            let t = t * t * (3.0 - 2.0 * t);
+            self.position = pan.from.lerp(pan.to, t);
+            if pan.elapsed >= pan.duration {
+                self.pan = None;
+            }
         }
     }
 
-    pub(crate) fn update(&mut self, input: &InputManager) {
+    pub(crate) fn update(&mut self, input: &InputManager, dt: f32) {
+        // Manual movement takes over from a running pan
+        if input.is_direction_pressed() || input.get_drag_delta().is_some() {
+            self.pan = None;
+        }
+
         // Handle keyboard movement
         if input.is_direction_pressed() {
-            let move_speed = CAMERA_SPEED / self.zoom;
+            self.pan_held += dt;
+            let move_speed = pan_speed(self.pan_held) * dt / self.zoom;
             if input.is_up_pressed() {
                 self.position.y -= move_speed;
             }
@@ -32,6 +160,8 @@ impl CameraController {
             if input.is_right_pressed() {
                 self.position.x += move_speed;
             }
+        } else {
+            self.pan_held = 0.0;
         }
 
         // Handle drag movement
@@ -40,44 +170,242 @@ impl CameraController {
             self.position.y -= drag_delta.y / self.zoom;
         }
 
-        // Handle zoom
-        if let Some(zoom_delta) = input.get_zoom_delta() {
-            // Store pre-zoom mouse world position
-            let pre_zoom_pos = self.screen_to_world(input.get_mouse_position());
+        // Zoom presets
+        if input.action_pressed(Action::ZoomReset) {
+            self.set_zoom(1.0);
+        }
+        if input.action_pressed(Action::ZoomDouble) {
+            self.set_zoom(2.0);
+        }
+        if input.action_pressed(Action::ZoomHalf) {
+            self.set_zoom(0.5);
+        }
 
-            // Apply zoom
-            if zoom_delta < 0.0 {
-                self.zoom /= ZOOM_SPEED;
+        // Handle zoom, the wheel zooms around the cursor and the keys around the center
+        if let Some(zoom_delta) = input.get_zoom_delta() {
+            self.zoom_target = if zoom_delta < 0.0 {
+                self.view.zoom_out(self.zoom_target)
             } else {
-                self.zoom *= ZOOM_SPEED;
-            }
-            self.zoom = self.zoom.clamp(ZOOM_MIN, ZOOM_MAX);
+                self.view.zoom_in(self.zoom_target)
+            };
+            self.zoom_anchor = input.get_mouse_position();
+        }
+        if input.action_pressed(Action::ZoomIn) {
+            self.zoom_target = self.view.zoom_in(self.zoom_target);
+            self.zoom_anchor = self.view_center();
+        }
+        if input.action_pressed(Action::ZoomOut) {
+            self.zoom_target = self.view.zoom_out(self.zoom_target);
+            self.zoom_anchor = self.view_center();
+        }
+        if self.zoom != self.zoom_target {
+            self.zoom_at(
+                smooth_zoom(self.zoom, self.zoom_target, dt),
+                self.zoom_anchor,
+            );
+        }
+
+        // Two finger pinch zooms around the fingers and moving them pans
+        if let Some(pinch) = input.get_pinch() {
+            self.pan = None;
+            self.position -= pinch.pan / self.zoom;
+            self.zoom_at(self.zoom * pinch.scale, pinch.center);
+            self.zoom_target = self.zoom;
+        }
+    }
 
-            // Get post-zoom mouse world position
-            let post_zoom_pos = self.screen_to_world(input.get_mouse_position());
+    // Zoom to `zoom` keeping the world position under `screen_pos` in place
+    fn zoom_at(&mut self, zoom: f32, screen_pos: Vec2) {
+        let pre_zoom_pos = self.screen_to_world(screen_pos);
+        self.zoom = zoom.clamp(ZOOM_MIN, ZOOM_MAX);
+        let post_zoom_pos = self.screen_to_world(screen_pos);
+        self.position += pre_zoom_pos - post_zoom_pos;
+    }
+
+    /// Set the zoom level keeping the current center of the view, right away
+    pub(crate) fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.clamp(ZOOM_MIN, ZOOM_MAX);
+        self.zoom_target = self.zoom;
+    }
+
+    /// Center the view on the map and zoom so that all of it fits on screen
+    pub(crate) fn fit(&mut self, bounds: &MapBounds) {
+        let (min_x, min_y, max_x, max_y) = bounds.as_tuple();
+        let width = (max_x - min_x + 1) as f32 * TILE_SIZE;
+        let height = (max_y - min_y + 1) as f32 * TILE_SIZE;
+
+        self.position = Vec2::new(
+            min_x as f32 * TILE_SIZE + width / 2.0,
+            min_y as f32 * TILE_SIZE + height / 2.0,
+        );
+        self.set_zoom(
+            self.view
+                .fitting_zoom((screen_width() / width).min(screen_height() / height) * FIT_MARGIN),
+        );
+        self.pan = None;
+    }
 
-            // Adjust to keep world position under cursor
-            self.position.x += pre_zoom_pos.x - post_zoom_pos.x;
-            self.position.y += pre_zoom_pos.y - post_zoom_pos.y;
+    /// Size of the rendered area in screen pixels
+    pub(crate) fn view_size(&self) -> Vec2 {
+        match self.viewport {
+            Some(rect) => rect.size(),
+            None => Vec2::new(screen_width(), screen_height()),
         }
     }
 
+    // Center of the rendered area in screen pixels
+    fn view_center(&self) -> Vec2 {
+        match self.viewport {
+            Some(rect) => rect.center(),
+            None => Vec2::new(screen_width(), screen_height()) / 2.0,
+        }
+    }
+
+    /// Part of the world inside the view, ignoring the running effects
+    pub(crate) fn visible_world_rect(&self) -> Rect {
+        let size = self.view_size() / self.zoom;
+        Rect::new(
+            self.position.x - size.x / 2.0,
+            self.position.y - size.y / 2.0,
+            size.x,
+            size.y,
+        )
+    }
+
+    // Viewport in physical pixels with the origin in the bottom left corner, as macroquad expects it
+    fn gl_viewport(&self) -> Option<(i32, i32, i32, i32)> {
+        self.viewport.map(|rect| {
+            let dpi = screen_dpi_scale();
+            (
+                (rect.x * dpi) as i32,
+                ((screen_height() - rect.y - rect.h) * dpi) as i32,
+                (rect.w * dpi) as i32,
+                (rect.h * dpi) as i32,
+            )
+        })
+    }
+
     fn get_macroquad_camera(&self) -> Camera2D {
+        let view_size = self.view_size();
         Camera2D {
             target: self.position,
-            zoom: Vec2::new(
-                self.zoom * 2.0 / screen_width(),
-                self.zoom * 2.0 / screen_height(),
-            ),
+            zoom: Vec2::new(self.zoom * 2.0 / view_size.x, self.zoom * 2.0 / view_size.y),
+            viewport: self.gl_viewport(),
+            ..Default::default()
+        }
+    }
+
+    // Camera including the running effects, only used for rendering so that picking stays stable
+    fn get_effect_camera(&self) -> Camera2D {
+        let zoom = match &self.zoom_punch {
+            Some(punch) => self.zoom * (1.0 + punch.current()),
+            None => self.zoom,
+        };
+        let view_size = self.view_size();
+        let mut target = self.position + self.shake_offset / zoom;
+        if self.view.pixel_perfect {
+            let dpi = screen_dpi_scale();
+            target = snap_target(target, view_size * dpi, 1.0 / (zoom * dpi));
+        }
+        Camera2D {
+            target,
+            zoom: Vec2::new(zoom * 2.0 / view_size.x, zoom * 2.0 / view_size.y),
+            viewport: self.gl_viewport(),
             ..Default::default()
         }
     }
 
+    /// Size of a screen pixel in world units when rendering pixel perfect, for `snap_to_pixel`
+    pub(crate) fn pixel_snap(&self) -> Option<f32> {
+        self.view
+            .pixel_perfect
+            .then(|| 1.0 / (self.zoom * screen_dpi_scale()))
+    }
+
     pub(crate) fn screen_to_world(&self, screen_pos: Vec2) -> Vec2 {
         self.get_macroquad_camera().screen_to_world(screen_pos)
     }
 
+    pub(crate) fn world_to_screen(&self, world_pos: Vec2) -> Vec2 {
+        self.get_macroquad_camera().world_to_screen(world_pos)
+    }
+
     pub(crate) fn apply(&self) {
-        set_camera(&self.get_macroquad_camera());
+        set_camera(&self.get_effect_camera());
+    }
+}
+
+/// World position moved onto the closest whole screen pixel, `pixel` is the size of one in world
+/// units as given by `CameraController::pixel_snap`
+pub(crate) fn snap_to_pixel(position: Vec2, pixel: Option<f32>) -> Vec2 {
+    match pixel {
+        Some(pixel) => (position / pixel).round() * pixel,
+        None => position,
+    }
+}
+
+// Camera target close to `target` that puts the world positions at whole multiples of `pixel` on
+// whole screen pixels. The target is in the middle of the view, which is off by half a pixel
+// along the sides with an odd number of `view_pixels`.
+fn snap_target(target: Vec2, view_pixels: Vec2, pixel: f32) -> Vec2 {
+    let half = view_pixels / 2.0;
+    ((target / pixel - half).round() + half) * pixel
+}
+
+// World pixels per second at zoom 1 after holding a direction key for `held` seconds, easing in
+// from the initial to the top speed
+fn pan_speed(held: f32) -> f32 {
+    let t = (held / CAMERA_PAN_RAMP_TIME).clamp(0.0, 1.0);
+    CAMERA_PAN_SPEED + (CAMERA_PAN_MAX_SPEED - CAMERA_PAN_SPEED) * t * t
+}
+
+// Zoom eased towards `target` over `dt` seconds. The ratio between the two shrinks exponentially,
+// so the same time passed gives the same zoom however it is split into frames.
+fn smooth_zoom(zoom: f32, target: f32, dt: f32) -> f32 {
+    let remaining = (zoom / target).ln() * (-ZOOM_SMOOTHING * dt).exp();
+    // Close enough not to keep moving by fractions of a pixel
+    if remaining.abs() < 0.001 {
+        target
+    } else {
+        target * remaining.exp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panning_speeds_up_while_held() {
+        assert_eq!(pan_speed(0.0), CAMERA_PAN_SPEED);
+        assert!(pan_speed(CAMERA_PAN_RAMP_TIME / 2.0) > CAMERA_PAN_SPEED);
+        assert_eq!(pan_speed(CAMERA_PAN_RAMP_TIME * 2.0), CAMERA_PAN_MAX_SPEED);
+    }
+
+    #[test]
+    fn test_snapped_positions_land_on_whole_screen_pixels() {
+        let pixel = 1.0 / 1.7;
+        let view_pixels = Vec2::new(1281.0, 720.0);
+        let target = snap_target(Vec2::new(103.37, -48.91), view_pixels, pixel);
+        for position in [Vec2::new(12.34, 5.67), Vec2::new(-300.1, 77.7)] {
+            let screen =
+                (snap_to_pixel(position, Some(pixel)) - target) / pixel + view_pixels / 2.0;
+            assert!((screen - screen.round()).abs().max_element() < 1e-3);
+        }
+        assert_eq!(
+            snap_to_pixel(Vec2::new(0.3, 0.6), None),
+            Vec2::new(0.3, 0.6)
+        );
+    }
+
+    #[test]
+    fn test_zoom_smoothing_does_not_depend_on_the_frame_rate() {
+        let after = |frames: usize| {
+            let dt = 0.1 / frames as f32;
+            (0..frames).fold(1.0, |zoom, _| smooth_zoom(zoom, 2.0, dt))
+        };
+        assert!((after(3) - after(12)).abs() < 1e-4);
+        assert!(after(3) > 1.0 && after(3) < 2.0);
+        assert_eq!(smooth_zoom(1.0, 2.0, 10.0), 2.0);
     }
 }
@@ -0,0 +1,282 @@
+//! Charts drawn with the macroquad primitives, for the chart components of the `ui` global
+
+use crate::config::{
+    CHART_AXIS_COLOR, CHART_BACKGROUND_COLOR, CHART_FONT_SIZE, CHART_LINE_COLOR, CHART_TICKS,
+};
+use lua_engine::text_format::format_number;
+use macroquad::prelude::*;
+
+/// Lowest and highest value, widened around a single value so it still gets a height
+pub fn value_range(values: impl IntoIterator<Item = f64>) -> Option<(f64, f64)> {
+    let (min, max) = values.into_iter().filter(|value| value.is_finite()).fold(
+        None,
+        |range, value| match range {
+            None => Some((value, value)),
+            Some((min, max)) => Some((f64::min(min, value), f64::max(max, value))),
+        },
+    )?;
+    if max > min {
+        Some((min, max))
+    } else {
+        Some((min - 1.0, max + 1.0))
+    }
+}
+
+/// Round tick values covering `min..=max`, about `count` of them at steps of 1, 2 or 5 times a
+/// power of ten
+pub fn axis_ticks(min: f64, max: f64, count: usize) -> Vec<f64> {
+    if max <= min || count < 2 {
+        return vec![min];
+    }
+    let rough = (max - min) / (count - 1) as f64;
+    let magnitude = 10f64.powf(rough.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|factor| factor * magnitude)
+        .find(|step| *step >= rough)
+        .unwrap_or(10.0 * magnitude);
+    let first = (min / step).ceil() as i64;
+    let last = (max / step).floor() as i64;
+    (first..=last).map(|i| i as f64 * step).collect()
+}
+
+// Decimals to tell the ticks apart
+fn tick_decimals(ticks: &[f64]) -> usize {
+    match ticks {
+        [first, second, ..] => (-(second - first).log10().floor()).max(0.0) as usize,
+        _ => 0,
+    }
+}
+
+fn to_screen(
+    rect: Rect,
+    (x_min, x_max): (f64, f64),
+    (y_min, y_max): (f64, f64),
+    x: f64,
+    y: f64,
+) -> Vec2 {
+    Vec2::new(
+        rect.x + ((x - x_min) / (x_max - x_min)) as f32 * rect.w,
+        rect.y + rect.h - ((y - y_min) / (y_max - y_min)) as f32 * rect.h,
+    )
+}
+
+fn draw_polyline(points: &[Vec2], color: Color) {
+    for pair in points.windows(2) {
+        draw_line(pair[0].x, pair[0].y, pair[1].x, pair[1].y, 1.5, color);
+    }
+}
+
+/// Values as a line filling the rect, without axes, the last value marked
+pub fn draw_sparkline(rect: Rect, values: &[f64]) {
+    draw_rectangle(rect.x, rect.y, rect.w, rect.h, CHART_BACKGROUND_COLOR);
+    let Some(y_range) = value_range(values.iter().copied()) else {
+        return;
+    };
+    let x_range = (0.0, (values.len().max(2) - 1) as f64);
+    let points: Vec<Vec2> = values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| to_screen(rect, x_range, y_range, i as f64, *value))
+        .collect();
+    draw_polyline(&points, CHART_LINE_COLOR);
+    if let Some(last) = points.last() {
+        draw_circle(last.x, last.y, 2.5, CHART_LINE_COLOR);
+    }
+}
+
+/// One bar per value from a baseline at 0, labelled below
+pub fn draw_bar_chart(rect: Rect, bars: &[(String, f64)]) {
+    draw_rectangle(rect.x, rect.y, rect.w, rect.h, CHART_BACKGROUND_COLOR);
+    if bars.is_empty() {
+        return;
+    }
+    let label_height = CHART_FONT_SIZE + 4.0;
+    let plot = Rect::new(rect.x, rect.y, rect.w, rect.h - label_height);
+    let Some((min, max)) = value_range(bars.iter().map(|(_, value)| *value).chain([0.0])) else {
+        return;
+    };
+    let baseline = to_screen(plot, (0.0, 1.0), (min, max), 0.0, 0.0).y;
+    let slot = plot.w / bars.len() as f32;
+    for (i, (label, value)) in bars.iter().enumerate() {
+        let top = to_screen(plot, (0.0, 1.0), (min, max), 0.0, *value).y;
+        let x = plot.x + i as f32 * slot + slot * 0.15;
+        draw_rectangle(
+            x,
+            top.min(baseline),
+            slot * 0.7,
+            (baseline - top).abs(),
+            CHART_LINE_COLOR,
+        );
+        let text = if label.is_empty() {
+            format_number(*value, 0)
+        } else {
+            label.clone()
+        };
+        let width = measure_text(&text, None, CHART_FONT_SIZE as u16, 1.0).width;
+        draw_text(
+            &text,
+            plot.x + i as f32 * slot + (slot - width) / 2.0,
+            rect.y + rect.h - 4.0,
+            CHART_FONT_SIZE,
+            WHITE,
+        );
+    }
+    draw_line(
+        plot.x,
+        baseline,
+        plot.x + plot.w,
+        baseline,
+        1.0,
+        CHART_AXIS_COLOR,
+    );
+}
+
+/// Columns stacked from the bottom, one per value in a frame, the value at an index drawn in the
+/// color at that index. The highest column fills the rect and its total is written in the corner.
+pub fn draw_stacked_columns(rect: Rect, columns: &[Vec<f64>], colors: &[Color], unit: &str) {
+    draw_rectangle(rect.x, rect.y, rect.w, rect.h, CHART_BACKGROUND_COLOR);
+    let highest = columns
+        .iter()
+        .map(|column| column.iter().sum::<f64>())
+        .fold(0.0, f64::max);
+    if colors.is_empty() || highest <= 0.0 {
+        return;
+    }
+    let slot = rect.w / columns.len() as f32;
+    for (i, column) in columns.iter().enumerate() {
+        let mut bottom = rect.bottom();
+        for (j, value) in column.iter().enumerate() {
+            let height = (value / highest) as f32 * rect.h;
+            draw_rectangle(
+                rect.x + i as f32 * slot,
+                bottom - height,
+                slot.max(1.0),
+                height,
+                colors[j % colors.len()],
+            );
+            bottom -= height;
+        }
+    }
+    draw_text(
+        &format!("{} {}", format_number(highest, 2), unit),
+        rect.x + 4.0,
+        rect.y + CHART_FONT_SIZE,
+        CHART_FONT_SIZE,
+        WHITE,
+    );
+}
+
+/// Points as a line with labelled axes and the title above
+pub fn draw_line_chart(rect: Rect, title: &str, points: &[(f64, f64)]) {
+    draw_rectangle(rect.x, rect.y, rect.w, rect.h, CHART_BACKGROUND_COLOR);
+    draw_text(
+        title,
+        rect.x + 4.0,
+        rect.y + CHART_FONT_SIZE,
+        CHART_FONT_SIZE,
+        WHITE,
+    );
+    let (Some(x_range), Some(y_range)) = (
+        value_range(points.iter().map(|(x, _)| *x)),
+        value_range(points.iter().map(|(_, y)| *y)),
+    ) else {
+        return;
+    };
+
+    let y_ticks = axis_ticks(y_range.0, y_range.1, CHART_TICKS);
+    let y_decimals = tick_decimals(&y_ticks);
+    let y_labels: Vec<String> = y_ticks
+        .iter()
+        .map(|tick| format_number(*tick, y_decimals))
+        .collect();
+    let label_width = y_labels
+        .iter()
+        .map(|label| measure_text(label, None, CHART_FONT_SIZE as u16, 1.0).width)
+        .fold(0.0, f32::max);
+    let plot = Rect::new(
+        rect.x + label_width + 8.0,
+        rect.y + CHART_FONT_SIZE + 8.0,
+        rect.w - label_width - 16.0,
+        rect.h - CHART_FONT_SIZE * 2.0 - 16.0,
+    );
+
+    for (tick, label) in y_ticks.iter().zip(&y_labels) {
+        let y = to_screen(plot, x_range, y_range, x_range.0, *tick).y;
+        draw_line(plot.x - 3.0, y, plot.x + plot.w, y, 1.0, CHART_AXIS_COLOR);
+        draw_text(
+            label,
+            rect.x + 4.0,
+            y + CHART_FONT_SIZE / 3.0,
+            CHART_FONT_SIZE,
+            GRAY,
+        );
+    }
+    let x_ticks = axis_ticks(x_range.0, x_range.1, CHART_TICKS);
+    let x_decimals = tick_decimals(&x_ticks);
+    for tick in &x_ticks {
+        let x = to_screen(plot, x_range, y_range, *tick, y_range.0).x;
+        let label = format_number(*tick, x_decimals);
+        let width = measure_text(&label, None, CHART_FONT_SIZE as u16, 1.0).width;
+        draw_line(
+            x,
+            plot.y + plot.h,
+            x,
+            plot.y + plot.h + 3.0,
+            1.0,
+            CHART_AXIS_COLOR,
+        );
+        draw_text(
+            &label,
+            x - width / 2.0,
+            plot.y + plot.h + CHART_FONT_SIZE + 2.0,
+            CHART_FONT_SIZE,
+            GRAY,
+        );
+    }
+    draw_line(
+        plot.x,
+        plot.y,
+        plot.x,
+        plot.y + plot.h,
+        1.0,
+        CHART_AXIS_COLOR,
+    );
+    draw_line(
+        plot.x,
+        plot.y + plot.h,
+        plot.x + plot.w,
+        plot.y + plot.h,
+        1.0,
+        CHART_AXIS_COLOR,
+    );
+
+    let line: Vec<Vec2> = points
+        .iter()
+        .map(|(x, y)| to_screen(plot, x_range, y_range, *x, *y))
+        .collect();
+    draw_polyline(&line, CHART_LINE_COLOR);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_range_gives_flat_data_a_height() {
+        assert_eq!(value_range([3.0, -1.0, f64::NAN, 7.0]), Some((-1.0, 7.0)));
+        assert_eq!(value_range([5.0, 5.0]), Some((4.0, 6.0)));
+        assert_eq!(value_range([]), None);
+    }
+
+    #[test]
+    fn test_axis_ticks_are_round() {
+        assert_eq!(
+            axis_ticks(0.0, 100.0, 6),
+            vec![0.0, 20.0, 40.0, 60.0, 80.0, 100.0]
+        );
+        assert_eq!(axis_ticks(3.0, 17.0, 4), vec![5.0, 10.0, 15.0]);
+        assert_eq!(axis_ticks(0.0, 0.9, 4), vec![0.0, 0.5]);
+        assert_eq!(tick_decimals(&axis_ticks(0.0, 1.0, 6)), 1);
+    }
+}
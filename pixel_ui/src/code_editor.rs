@@ -0,0 +1,289 @@
+use crate::config::{CONSOLE_LINE_HEIGHT, TEXT_FONT_SIZE};
+use crate::lua_syntax::{analyze, open_depth, starts_with_closer, TokenKind};
+use macroquad::prelude::*;
+
+const INDENT: &str = "    ";
+const MATCH_HIGHLIGHT_COLOR: Color = Color::new(0.4, 0.4, 0.1, 0.8);
+
+fn token_color(kind: TokenKind) -> Color {
+    match kind {
+        TokenKind::Keyword => ORANGE,
+        TokenKind::Number => SKYBLUE,
+        TokenKind::String => GREEN,
+        TokenKind::Comment => GRAY,
+        TokenKind::OpenBracket | TokenKind::CloseBracket => YELLOW,
+        TokenKind::Identifier | TokenKind::Operator | TokenKind::Whitespace => WHITE,
+    }
+}
+
+fn text_width(text: &str) -> f32 {
+    measure_text(text, None, TEXT_FONT_SIZE as u16, 1.0).width
+}
+
+pub enum EditorAction {
+    None,
+    /// The chunk should be run
+    Submit,
+    /// Up on the first line, for the previous command of the history
+    HistoryPrevious,
+    /// Down on the last line, for the next command of the history
+    HistoryNext,
+}
+
+/// Multi-line Lua editor with highlighting, bracket matching and auto-indent
+pub struct CodeEditor {
+    text: String,
+    // Byte offset of the cursor, always on a char boundary
+    cursor: usize,
+}
+
+impl CodeEditor {
+    pub(crate) fn new() -> Self {
+        Self {
+            text: String::new(),
+            cursor: 0,
+        }
+    }
+
+    pub(crate) fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub(crate) fn take_text(&mut self) -> String {
+        self.cursor = 0;
+        std::mem::take(&mut self.text)
+    }
+
+    /// Replace the text, with the cursor at its end
+    pub(crate) fn set_text(&mut self, text: &str) {
+        self.text.clear();
+        self.cursor = 0;
+        self.insert_str(text);
+    }
+
+    pub(crate) fn insert_str(&mut self, text: &str) {
+        let text = text
+            .replace("\r\n", "\n")
+            .replace('\r', "\n")
+            .replace('\t', INDENT);
+        self.text.insert_str(self.cursor, &text);
+        self.cursor += text.len();
+    }
+
+    fn line_start(&self, pos: usize) -> usize {
+        self.text[..pos].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    fn line_end(&self, pos: usize) -> usize {
+        self.text[pos..]
+            .find('\n')
+            .map_or(self.text.len(), |i| pos + i)
+    }
+
+    fn prev_boundary(&self, pos: usize) -> usize {
+        self.text[..pos]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self, pos: usize) -> usize {
+        self.text[pos..]
+            .chars()
+            .next()
+            .map_or(pos, |c| pos + c.len_utf8())
+    }
+
+    // Move the cursor to the same column (in chars) of the line starting at `line_start`
+    fn move_to_column(&mut self, line_start: usize, column: usize) {
+        let line_end = self.line_end(line_start);
+        self.cursor = self.text[line_start..line_end]
+            .char_indices()
+            .nth(column)
+            .map_or(line_end, |(i, _)| line_start + i);
+    }
+
+    // Fix the indentation of a line starting with `end`, `else` etc. before leaving it
+    fn reindent_current_line(&mut self) {
+        let line_start = self.line_start(self.cursor);
+        let line_end = self.line_end(self.cursor);
+        let line = &self.text[line_start..line_end];
+        if !starts_with_closer(line) {
+            return;
+        }
+
+        let current_indent = line.len() - line.trim_start().len();
+        let new_indent = INDENT.repeat(open_depth(&self.text, line_start).saturating_sub(1));
+        self.text
+            .replace_range(line_start..line_start + current_indent, &new_indent);
+        self.cursor = if self.cursor >= line_start + current_indent {
+            self.cursor + new_indent.len() - current_indent
+        } else {
+            line_start + new_indent.len()
+        };
+    }
+
+    fn new_line(&mut self) {
+        self.reindent_current_line();
+        let depth = open_depth(&self.text, self.cursor);
+        self.insert_str(&format!("\n{}", INDENT.repeat(depth)));
+    }
+
+    /// Whether the text is a complete chunk that can be run
+    pub(crate) fn is_complete(&self) -> bool {
+        analyze(&self.text).is_complete()
+    }
+
+    pub(crate) fn update(&mut self) -> EditorAction {
+        let ctrl_down = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        let shift_down = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+        let alt_down = is_key_down(KeyCode::LeftAlt) || is_key_down(KeyCode::RightAlt);
+
+        while let Some(c) = get_char_pressed() {
+            // Shortcuts and the console toggle key don't type anything
+            if c.is_control() || c == '`' || ctrl_down || alt_down {
+                continue;
+            }
+            self.insert_str(c.encode_utf8(&mut [0; 4]));
+        }
+
+        if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter) {
+            // Enter runs complete chunks, Ctrl+Enter always runs, Shift+Enter always breaks the line
+            let run =
+                ctrl_down || (!shift_down && !self.text.trim().is_empty() && self.is_complete());
+            if run {
+                return EditorAction::Submit;
+            }
+            self.new_line();
+        }
+
+        if is_key_pressed(KeyCode::Tab) {
+            self.insert_str(INDENT);
+        }
+        if is_key_pressed(KeyCode::Backspace) && self.cursor > 0 {
+            let start = self.prev_boundary(self.cursor);
+            self.text.replace_range(start..self.cursor, "");
+            self.cursor = start;
+        }
+        if is_key_pressed(KeyCode::Delete) && self.cursor < self.text.len() {
+            let end = self.next_boundary(self.cursor);
+            self.text.replace_range(self.cursor..end, "");
+        }
+        if is_key_pressed(KeyCode::Left) {
+            self.cursor = self.prev_boundary(self.cursor);
+        }
+        if is_key_pressed(KeyCode::Right) {
+            self.cursor = self.next_boundary(self.cursor);
+        }
+        if is_key_pressed(KeyCode::Home) {
+            self.cursor = self.line_start(self.cursor);
+        }
+        if is_key_pressed(KeyCode::End) {
+            self.cursor = self.line_end(self.cursor);
+        }
+        if is_key_pressed(KeyCode::Up) {
+            let line_start = self.line_start(self.cursor);
+            if line_start == 0 {
+                return EditorAction::HistoryPrevious;
+            }
+            let column = self.text[line_start..self.cursor].chars().count();
+            let prev_line_start = self.line_start(line_start - 1);
+            self.move_to_column(prev_line_start, column);
+        }
+        if is_key_pressed(KeyCode::Down) {
+            let line_end = self.line_end(self.cursor);
+            if line_end == self.text.len() {
+                return EditorAction::HistoryNext;
+            }
+            let column = self.text[self.line_start(self.cursor)..self.cursor]
+                .chars()
+                .count();
+            self.move_to_column(line_end + 1, column);
+        }
+
+        EditorAction::None
+    }
+
+    pub(crate) fn draw(&self, area: Rect) {
+        let analysis = analyze(&self.text);
+
+        // Highlight the bracket or keyword at the cursor together with its partner
+        let matched: Vec<usize> = analysis
+            .token_at(self.cursor)
+            .and_then(|i| analysis.pairs.get(&i).map(|&pair| vec![i, pair]))
+            .unwrap_or_default();
+
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(self.text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        let cursor_line = line_starts
+            .iter()
+            .rposition(|&start| start <= self.cursor)
+            .unwrap_or(0);
+        let visible_lines = ((area.h / CONSOLE_LINE_HEIGHT) as usize).max(1);
+        let first_line = (cursor_line + 1).saturating_sub(visible_lines);
+
+        for (row, &line_start) in line_starts
+            .iter()
+            .enumerate()
+            .skip(first_line)
+            .take(visible_lines)
+        {
+            let line_end = self.line_end(line_start);
+            let baseline = area.y + (row - first_line + 1) as f32 * CONSOLE_LINE_HEIGHT;
+            let mut x = area.x;
+
+            // Tokens may span lines (long strings and comments), only draw the part on this line
+            for (i, token) in analysis.tokens.iter().enumerate() {
+                let start = token.start.max(line_start);
+                let end = token.end.min(line_end);
+                if start >= end {
+                    continue;
+                }
+                let piece = &self.text[start..end];
+                let width = text_width(piece);
+                if matched.contains(&i) {
+                    draw_rectangle(
+                        x,
+                        baseline - CONSOLE_LINE_HEIGHT + 4.0,
+                        width,
+                        CONSOLE_LINE_HEIGHT,
+                        MATCH_HIGHLIGHT_COLOR,
+                    );
+                }
+                let color = if analysis.unmatched.contains(&i) {
+                    RED
+                } else {
+                    token_color(token.kind)
+                };
+                draw_text(piece, x, baseline, TEXT_FONT_SIZE, color);
+                x += width;
+            }
+
+            // Blinking cursor
+            if row == cursor_line && get_time().fract() < 0.5 {
+                let cursor_x = area.x + text_width(&self.text[line_start..self.cursor]);
+                draw_line(
+                    cursor_x,
+                    baseline - CONSOLE_LINE_HEIGHT + 4.0,
+                    cursor_x,
+                    baseline + 4.0,
+                    1.0,
+                    WHITE,
+                );
+            }
+        }
+
+        // Tell the user the chunk won't run yet
+        if let Some(closer) = analysis.expected_closer(&self.text) {
+            let hint = format!("... waiting for {}", closer);
+            draw_text(
+                &hint,
+                area.x + area.w - text_width(&hint) - 10.0,
+                area.y + area.h - 5.0,
+                TEXT_FONT_SIZE,
+                ORANGE,
+            );
+        }
+    }
+}
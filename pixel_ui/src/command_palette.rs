@@ -0,0 +1,283 @@
+use crate::config::{
+    BUTTON_ACTIVE_COLOR, COMMAND_PALETTE_ROWS, COMMAND_PALETTE_WIDTH, CONSOLE_LINE_HEIGHT,
+    TEXT_BACKGROUND_COLOR,
+};
+use lua_engine::actions::{Action, Chord, KeyBindings};
+use lua_engine::commands::{fuzzy_score, Commands};
+use macroquad::prelude::*;
+
+// Actions that make no sense without their key or mouse button held, or that open the palette
+const LEFT_OUT: [Action; 7] = [
+    Action::PanUp,
+    Action::PanDown,
+    Action::PanLeft,
+    Action::PanRight,
+    Action::Select,
+    Action::Paint,
+    Action::CommandPalette,
+];
+
+/// What the palette runs
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PaletteCommand {
+    /// Pressed as if its binding was
+    Action(Action),
+    /// Registered by the scripts with `api.commands.register`
+    Script(String),
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    label: String,
+    // Bindings of an action or the description of a script command
+    hint: String,
+    command: PaletteCommand,
+}
+
+/// Searchable list of the built-in actions and the commands of the scripts, opened with Ctrl+P.
+/// Typing filters it fuzzily, Up and Down pick an entry and Enter or a click runs it.
+pub struct CommandPalette {
+    commands: Commands,
+    open: bool,
+    query: String,
+    entries: Vec<Entry>,
+    // Index into the matches of the query
+    selected: usize,
+}
+
+impl CommandPalette {
+    pub(crate) fn new(commands: Commands) -> Self {
+        Self {
+            commands,
+            open: false,
+            query: String::new(),
+            entries: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    pub(crate) fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Open with the commands registered right now, showing the bindings of the actions
+    pub(crate) fn open(&mut self, bindings: &KeyBindings) {
+        self.entries = Action::ALL
+            .into_iter()
+            .filter(|action| !LEFT_OUT.contains(action))
+            .map(|action| Entry {
+                label: action_label(action),
+                hint: bindings
+                    .chords(action)
+                    .iter()
+                    .map(Chord::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                command: PaletteCommand::Action(action),
+            })
+            .chain(self.commands.list().into_iter().map(|command| Entry {
+                label: command.name.clone(),
+                hint: command.description.unwrap_or_default(),
+                command: PaletteCommand::Script(command.name),
+            }))
+            .collect();
+        self.query.clear();
+        self.selected = 0;
+        self.open = true;
+    }
+
+    pub(crate) fn close(&mut self) {
+        self.open = false;
+    }
+
+    /// Run a command of the scripts, errors go to the error log
+    pub(crate) fn run(&self, name: &str) {
+        self.commands.run(name);
+    }
+
+    /// The query and the best matches while open
+    pub(crate) fn describe(&self) -> Option<String> {
+        if !self.open {
+            return None;
+        }
+        let matches = self.matches();
+        let best: Vec<&str> = matches
+            .iter()
+            .take(3)
+            .map(|entry| entry.label.as_str())
+            .collect();
+        Some(format!(
+            "Command palette '{}': {} matches, {}",
+            self.query,
+            matches.len(),
+            best.join(", ")
+        ))
+    }
+
+    fn matches(&self) -> Vec<&Entry> {
+        ranked(&self.entries, &self.query)
+    }
+
+    fn rect(&self) -> Rect {
+        let height = (COMMAND_PALETTE_ROWS + 1) as f32 * CONSOLE_LINE_HEIGHT + 10.0;
+        Rect::new(
+            (screen_width() - COMMAND_PALETTE_WIDTH) / 2.0,
+            screen_height() * 0.2,
+            COMMAND_PALETTE_WIDTH,
+            height,
+        )
+    }
+
+    // Match under the screen position, the first row is the query
+    fn match_at(&self, pos: Vec2) -> Option<usize> {
+        let rect = self.rect();
+        if !rect.contains(pos) {
+            return None;
+        }
+        let row = ((pos.y - rect.y - 5.0) / CONSOLE_LINE_HEIGHT) as usize;
+        (1..=COMMAND_PALETTE_ROWS).contains(&row).then(|| row - 1)
+    }
+
+    /// Handle the keys and clicks while open, returns the command picked. The palette closes
+    /// once it returns one, on Escape or on a click outside of it.
+    pub(crate) fn update(&mut self) -> Option<PaletteCommand> {
+        let ctrl_down = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        while let Some(c) = get_char_pressed() {
+            if !c.is_control() && !ctrl_down {
+                self.query.push(c);
+                self.selected = 0;
+            }
+        }
+        if is_key_pressed(KeyCode::Backspace) && self.query.pop().is_some() {
+            self.selected = 0;
+        }
+        if is_key_pressed(KeyCode::Escape) {
+            self.close();
+            return None;
+        }
+
+        let count = self.matches().len().min(COMMAND_PALETTE_ROWS);
+        if is_key_pressed(KeyCode::Down) && self.selected + 1 < count {
+            self.selected += 1;
+        }
+        if is_key_pressed(KeyCode::Up) {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        let mut picked = (is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter))
+            .then_some(self.selected);
+        if is_mouse_button_pressed(MouseButton::Left) {
+            let pos = Vec2::from(mouse_position());
+            match self.match_at(pos) {
+                Some(index) => picked = Some(index),
+                None if !self.rect().contains(pos) => self.close(),
+                None => {}
+            }
+        }
+
+        let command = picked
+            .and_then(|index| self.matches().get(index).copied())
+            .map(|entry| entry.command.clone());
+        if command.is_some() {
+            self.close();
+        }
+        command
+    }
+
+    pub(crate) fn draw(&self) {
+        if !self.open {
+            return;
+        }
+        let rect = self.rect();
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, TEXT_BACKGROUND_COLOR);
+        draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 1.0, GRAY);
+
+        let matches = self.matches();
+        let query = if self.query.is_empty() {
+            "Type to search the commands".to_string()
+        } else {
+            format!("> {}_", self.query)
+        };
+        draw_text(
+            &query,
+            rect.x + 8.0,
+            rect.y + CONSOLE_LINE_HEIGHT,
+            20.0,
+            YELLOW,
+        );
+        if matches.is_empty() {
+            draw_text(
+                "No command matches",
+                rect.x + 8.0,
+                rect.y + 2.0 * CONSOLE_LINE_HEIGHT,
+                20.0,
+                GRAY,
+            );
+        }
+        let hovered = self.match_at(Vec2::from(mouse_position()));
+        for (i, entry) in matches.iter().take(COMMAND_PALETTE_ROWS).enumerate() {
+            let y = rect.y + (i as f32 + 2.0) * CONSOLE_LINE_HEIGHT;
+            if i == self.selected {
+                draw_rectangle(
+                    rect.x + 2.0,
+                    y - CONSOLE_LINE_HEIGHT + 5.0,
+                    rect.w - 4.0,
+                    CONSOLE_LINE_HEIGHT,
+                    BUTTON_ACTIVE_COLOR,
+                );
+            }
+            let color = if hovered == Some(i) { YELLOW } else { WHITE };
+            draw_text(&entry.label, rect.x + 8.0, y, 20.0, color);
+            let hint_width = measure_text(&entry.hint, None, 18, 1.0).width;
+            draw_text(&entry.hint, rect.right() - hint_width - 8.0, y, 18.0, GRAY);
+        }
+    }
+}
+
+// Entries matching the query, best first, in the order of the list among equal ones
+fn ranked<'a>(entries: &'a [Entry], query: &str) -> Vec<&'a Entry> {
+    let mut scored: Vec<(i32, &Entry)> = entries
+        .iter()
+        .filter_map(|entry| fuzzy_score(query, &entry.label).map(|score| (score, entry)))
+        .collect();
+    scored.sort_by_key(|(score, _)| -score);
+    scored.into_iter().map(|(_, entry)| entry).collect()
+}
+
+// "toggle_goals" as "Toggle goals"
+fn action_label(action: Action) -> String {
+    let words = action.name().replace('_', " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => words,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_actions_are_searched_by_their_labels() {
+        assert_eq!(action_label(Action::EraseTool), "Erase tool");
+        let entries: Vec<Entry> = [Action::ZoomIn, Action::EraseTool, Action::EndTurn]
+            .into_iter()
+            .map(|action| Entry {
+                label: action_label(action),
+                hint: String::new(),
+                command: PaletteCommand::Action(action),
+            })
+            .collect();
+        let found: Vec<&PaletteCommand> = ranked(&entries, "et")
+            .into_iter()
+            .map(|entry| &entry.command)
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                &PaletteCommand::Action(Action::EndTurn),
+                &PaletteCommand::Action(Action::EraseTool)
+            ]
+        );
+    }
+}
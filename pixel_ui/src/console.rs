@@ -1,21 +1,241 @@
+use crate::code_editor::{CodeEditor, EditorAction};
+use crate::config::{
+    CONSOLE_CONFIG_PATH, CONSOLE_HEIGHT_RATIO, CONSOLE_HISTORY_SIZE, CONSOLE_INPUT_HEIGHT,
+    CONSOLE_LINE_HEIGHT, CONSOLE_RECENT_SCRIPTS, SCRIPTS_DIR, WATCH_INTERVAL, WATCH_PANEL_WIDTH,
+};
+use crate::console_config::{is_alias_name, ConsoleConfig};
+use crate::script_menu::ScriptMenu;
+use crate::watch::WatchList;
 use arboard::Clipboard;
 use lua_engine::lua_client::LuaClient;
-use macroquad::hash;
+use lua_engine::lua_engine::LuaEngine;
+use lua_engine::script_error::ScriptError;
+use lua_engine::LuaError;
 use macroquad::prelude::*;
-use macroquad::ui::{root_ui, widgets};
-use std::sync::{mpsc, Arc};
+use std::fs;
+use std::sync::{mpsc, Arc, Mutex};
+
+/// A command entered in the console together with its result once it arrived
+struct TranscriptEntry {
+    command: String,
+    result: Option<Result<String, String>>,
+}
+
+/// Everything entered in the console during the session, saved through the Lua `console` global
+#[derive(Default)]
+struct Transcript {
+    entries: Vec<TranscriptEntry>,
+}
+
+impl Transcript {
+    // Full input/output transcript
+    fn session_text(&self) -> String {
+        let mut text = String::new();
+        for entry in &self.entries {
+            text.push_str(&format!("> {}\n", entry.command));
+            match &entry.result {
+                Some(Ok(output)) if !output.is_empty() => text.push_str(&format!("{}\n", output)),
+                Some(Ok(_)) => {}
+                Some(Err(err)) => text.push_str(&format!("Error: {}\n", err)),
+                None => text.push_str("(no result)\n"),
+            }
+        }
+        text
+    }
+
+    // Successful commands only, as a script that replays the session
+    fn script_text(&self) -> String {
+        let mut text = String::from("-- Script saved from a console session\n\n");
+        for entry in &self.entries {
+            let succeeded = matches!(entry.result, Some(Ok(_)));
+            if succeeded && !entry.command.trim_start().starts_with("console.save_") {
+                text.push_str(entry.command.trim_end());
+                text.push_str("\n\n");
+            }
+        }
+        text
+    }
+}
+
+// Message first, then where it was raised and the traceback, dimmed
+fn error_lines(error: &ScriptError) -> Vec<(String, Color)> {
+    let mut lines = vec![(format!("Error: {}", error.message), RED)];
+    if let Some(location) = error.location() {
+        lines.push((format!("  at {}", location), ORANGE));
+    }
+    for line in error
+        .traceback
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+    {
+        lines.push((format!("    {}", line.trim()), GRAY));
+    }
+    lines
+}
 
 pub struct Console {
     pub(crate) visible: bool,
-    history: Vec<String>,
-    editbox: String,
+    /// Opaque background and no dimmed lines, from the accessibility settings
+    pub(crate) high_contrast: bool,
+    // Lines with the color they are drawn in
+    history: Vec<(String, Color)>,
+    last_result: Option<String>,
+    editor: CodeEditor,
     clipboard: Option<Clipboard>,
     lua_client: Arc<LuaClient>,
-    pending_commands: Vec<mpsc::Receiver<Result<String, String>>>,
+    transcript: Arc<Mutex<Transcript>>,
+    watches: Arc<Mutex<WatchList>>,
+    // Aliases and the commands of this and earlier sessions
+    config: Arc<Mutex<ConsoleConfig>>,
+    // Command of the history shown in the editor while browsing it with Up/Down
+    history_position: Option<usize>,
+    // What was typed before browsing the history, back after moving past the newest command
+    draft: String,
+    // Receivers paired with the index of their transcript entry
+    pending_commands: Vec<(usize, mpsc::Receiver<Result<String, ScriptError>>)>,
+    script_menu: ScriptMenu,
+    // Scripts run from the menu with the receivers of their outcome, in the order they run
+    pending_scripts: Vec<(String, mpsc::Receiver<Result<(), ScriptError>>)>,
 }
 
 impl Console {
-    pub(crate) fn new(lua_client: Arc<LuaClient>) -> Self {
+    pub(crate) fn new(lua_client: Arc<LuaClient>, lua_engine: Arc<Mutex<LuaEngine>>) -> Self {
+        let transcript = Arc::new(Mutex::new(Transcript::default()));
+        let watches = Arc::new(Mutex::new(WatchList::new(WATCH_INTERVAL)));
+        let config = Arc::new(Mutex::new(ConsoleConfig::load(CONSOLE_CONFIG_PATH)));
+        {
+            let lua = &lua_engine.lock().unwrap().lua;
+            let save_config = |config: &ConsoleConfig| {
+                config
+                    .save(CONSOLE_CONFIG_PATH)
+                    .map_err(LuaError::RuntimeError)
+            };
+            {
+                // alias("p", "api.person") makes `p.get(1)` run `api.person.get(1)` in the console
+                let config = config.clone();
+                lua.create_function(move |_, (name, expansion): (String, String)| {
+                    if !is_alias_name(&name) {
+                        return Err(LuaError::RuntimeError(format!(
+                            "'{}' is not a valid alias name",
+                            name
+                        )));
+                    }
+                    let mut config = config.lock().unwrap();
+                    config.aliases.insert(name, expansion);
+                    save_config(&config)
+                })
+                .and_then(|f| lua.globals().set("alias", f))
+                .unwrap();
+            }
+            {
+                let config = config.clone();
+                lua.create_function(move |_, name: String| {
+                    let mut config = config.lock().unwrap();
+                    if config.aliases.remove(&name).is_none() {
+                        return Err(LuaError::RuntimeError(format!(
+                            "'{}' is not an alias",
+                            name
+                        )));
+                    }
+                    save_config(&config)
+                })
+                .and_then(|f| lua.globals().set("unalias", f))
+                .unwrap();
+            }
+            let console = lua.create_table().unwrap();
+            {
+                let transcript = transcript.clone();
+                lua.create_function(move |_, path: String| {
+                    let text = transcript.lock().unwrap().session_text();
+                    fs::write(&path, text).map_err(|e| {
+                        LuaError::RuntimeError(format!("Failed to write {}: {}", path, e))
+                    })
+                })
+                .and_then(|f| console.set("save_session", f))
+                .unwrap();
+            }
+            {
+                let transcript = transcript.clone();
+                lua.create_function(move |_, path: String| {
+                    let text = transcript.lock().unwrap().script_text();
+                    fs::write(&path, text).map_err(|e| {
+                        LuaError::RuntimeError(format!("Failed to write {}: {}", path, e))
+                    })
+                })
+                .and_then(|f| console.set("save_script", f))
+                .unwrap();
+            }
+            {
+                let watches = watches.clone();
+                lua.create_function(move |_, expression: String| {
+                    if !watches.lock().unwrap().add(&expression) {
+                        return Err(LuaError::RuntimeError(format!(
+                            "'{}' is already watched",
+                            expression
+                        )));
+                    }
+                    Ok(())
+                })
+                .and_then(|f| console.set("watch", f))
+                .unwrap();
+            }
+            {
+                let watches = watches.clone();
+                lua.create_function(move |_, expression: String| {
+                    if !watches.lock().unwrap().remove(&expression) {
+                        return Err(LuaError::RuntimeError(format!(
+                            "'{}' is not watched",
+                            expression
+                        )));
+                    }
+                    Ok(())
+                })
+                .and_then(|f| console.set("unwatch", f))
+                .unwrap();
+            }
+            {
+                let watches = watches.clone();
+                lua.create_function(move |_, ()| Ok(watches.lock().unwrap().expressions()))
+                    .and_then(|f| console.set("watches", f))
+                    .unwrap();
+            }
+            {
+                let watches = watches.clone();
+                lua.create_function(move |_, ()| {
+                    watches.lock().unwrap().clear();
+                    Ok(())
+                })
+                .and_then(|f| console.set("clear_watches", f))
+                .unwrap();
+            }
+            {
+                let watches = watches.clone();
+                lua.create_function(move |_, seconds: f64| {
+                    watches.lock().unwrap().interval = seconds.max(0.0);
+                    Ok(())
+                })
+                .and_then(|f| console.set("set_watch_interval", f))
+                .unwrap();
+            }
+            {
+                let config = config.clone();
+                lua.create_function(move |_, ()| Ok(config.lock().unwrap().aliases.clone()))
+                    .and_then(|f| console.set("aliases", f))
+                    .unwrap();
+            }
+            {
+                let config = config.clone();
+                lua.create_function(move |_, ()| {
+                    let mut config = config.lock().unwrap();
+                    config.history.clear();
+                    save_config(&config)
+                })
+                .and_then(|f| console.set("clear_history", f))
+                .unwrap();
+            }
+            lua.globals().set("console", console).unwrap();
+        }
+
         // Initialize clipboard
         let clipboard = match Clipboard::new() {
             Ok(clipboard) => Some(clipboard),
@@ -27,45 +247,166 @@ impl Console {
 
         Self {
             visible: false,
-            history: vec![
-                "Welcome to the console! Type help() to start exploring the api.".to_string(),
-            ],
-            editbox: String::new(),
+            high_contrast: false,
+            history: [
+                "Welcome to the console! Type help() to start exploring the api.",
+                "Ctrl+C: copy input, Alt+C: copy last result, Ctrl+Shift+C: copy history, click a line to copy it",
+                "Enter: run a complete chunk, Shift+Enter: new line, Ctrl+Enter: run anyway",
+                "Up/Down: earlier commands, alias(name, expansion): shortcut for a name or command",
+                "Ctrl+O: run a script from scripts/ or one run recently",
+            ]
+            .map(|line| (line.to_string(), WHITE))
+            .to_vec(),
+            last_result: None,
+            editor: CodeEditor::new(),
             clipboard,
             lua_client,
+            transcript,
+            watches,
+            config,
+            history_position: None,
+            draft: String::new(),
             pending_commands: Default::default(),
+            script_menu: ScriptMenu::default(),
+            pending_scripts: Vec::new(),
         }
     }
 
     fn execute_command(&mut self) {
-        let command = self.editbox.clone();
-        if command.is_empty() {
+        let command = self.editor.take_text();
+        if command.trim().is_empty() {
             return;
         }
 
         // Add user input to history
-        self.history.push(format!("> {}", command));
+        self.log(format!("> {}", command));
+        self.history_position = None;
+        let expanded = {
+            let mut config = self.config.lock().unwrap();
+            config.push_history(&command, CONSOLE_HISTORY_SIZE);
+            if let Err(e) = config.save(CONSOLE_CONFIG_PATH) {
+                println!("{}", e);
+            }
+            config.expand(&command)
+        };
+        // The transcript gets the expanded command so saved scripts work without the aliases
+        let entry_index = {
+            let mut transcript = self.transcript.lock().unwrap();
+            transcript.entries.push(TranscriptEntry {
+                command: expanded.clone(),
+                result: None,
+            });
+            transcript.entries.len() - 1
+        };
 
         // Execute the script with LuaEngine
-        let pending_result = self.lua_client.execute_non_blocking(command.as_str());
-        self.pending_commands.push(pending_result);
+        let pending_result = self.lua_client.execute_non_blocking(&expanded);
+        self.pending_commands.push((entry_index, pending_result));
+    }
+
+    // Scripts of the quick-run menu run one after the other as the engine gets to them
+    fn run_scripts(&mut self, paths: Vec<String>) {
+        let mut config = self.config.lock().unwrap();
+        for path in paths.iter().rev() {
+            config.push_recent_script(path, CONSOLE_RECENT_SCRIPTS);
+        }
+        if let Err(e) = config.save(CONSOLE_CONFIG_PATH) {
+            println!("{}", e);
+        }
+        drop(config);
+        for path in paths {
+            self.log(format!("> run {}", path));
+            let pending = self.lua_client.run_file_non_blocking(&path);
+            self.pending_scripts.push((path, pending));
+        }
+    }
+
+    // Move through the command history, `older` towards the first command
+    fn browse_history(&mut self, older: bool) {
+        let config = self.config.lock().unwrap();
+        let len = config.history.len();
+        let position = match (self.history_position, older) {
+            (None, true) if len > 0 => Some(len - 1),
+            (None, _) => return,
+            (Some(position), true) => Some(position.saturating_sub(1)),
+            (Some(position), false) => (position + 1 < len).then_some(position + 1),
+        };
+        let text = match position {
+            Some(position) => config.history[position].clone(),
+            None => std::mem::take(&mut self.draft),
+        };
+        drop(config);
+        if self.history_position.is_none() {
+            self.draft = self.editor.text().to_string();
+        }
+        self.history_position = position;
+        self.editor.set_text(&text);
+    }
+
+    fn copy_to_clipboard(&mut self, text: String, what: &str) {
+        if let Some(ref mut ctx) = self.clipboard {
+            match ctx.set_text(text) {
+                Ok(()) => self.log(format!("{} copied to clipboard", what)),
+                Err(e) => self.log(format!("Failed to copy {}: {}", what, e)),
+            }
+        }
+    }
+
+    fn log(&mut self, line: String) {
+        self.history.push((line, WHITE));
+    }
+
+    // Layout of the history area shared by drawing and click handling
+    fn history_layout(&self) -> (usize, f32, f32) {
+        let console_height = screen_height() * CONSOLE_HEIGHT_RATIO;
+        let history_height = console_height - CONSOLE_INPUT_HEIGHT;
+        let visible_lines = (history_height / CONSOLE_LINE_HEIGHT) as usize;
+        let start_idx = self.history.len().saturating_sub(visible_lines);
+        (start_idx, history_height, console_height)
+    }
+
+    // History line under the screen position, if any
+    fn history_line_at(&self, pos: Vec2) -> Option<usize> {
+        let (start_idx, history_height, _) = self.history_layout();
+        let over_watches =
+            !self.watches.lock().unwrap().is_empty() && pos.x >= screen_width() - WATCH_PANEL_WIDTH;
+        if pos.y < 0.0 || pos.y >= history_height || over_watches {
+            return None;
+        }
+        // Lines are drawn with their baseline at (i + 1) * line height
+        let index = start_idx + (pos.y / CONSOLE_LINE_HEIGHT) as usize;
+        (index < self.history.len()).then_some(index)
     }
 
     pub(crate) fn toggle(&mut self) {
         self.visible = !self.visible;
     }
 
+    /// Show the console with the text typed in at the cursor, left for the user to run
+    pub(crate) fn insert(&mut self, text: &str) {
+        self.visible = true;
+        self.editor.insert_str(text);
+    }
+
     pub(crate) fn update(&mut self) {
         // Check all pending command results without blocking
         let mut completed = Vec::new();
 
-        for (i, receiver) in self.pending_commands.iter().enumerate() {
+        for (i, (entry_index, receiver)) in self.pending_commands.iter().enumerate() {
             match receiver.try_recv() {
                 Ok(result) => {
+                    self.transcript.lock().unwrap().entries[*entry_index].result =
+                        Some(result.clone().map_err(|err| err.to_string()));
                     // Process the result
                     match result {
-                        Ok(output) => self.history.push(output),
-                        Err(err) => self.history.push(format!("Error: {}", err)),
+                        Ok(output) => {
+                            self.last_result = Some(output.clone());
+                            self.history.push((output, WHITE));
+                        }
+                        Err(err) => {
+                            self.last_result = Some(format!("Error: {}", err));
+                            self.history.extend(error_lines(&err));
+                        }
                     }
                     // Mark this receiver as completed
                     completed.push(i);
@@ -76,7 +417,8 @@ impl Console {
                 }
                 Err(mpsc::TryRecvError::Disconnected) => {
                     // Sender was dropped without sending
-                    self.history.push("Command processing failed".to_string());
+                    self.history
+                        .push(("Command processing failed".to_string(), WHITE));
                     completed.push(i);
                 }
             }
@@ -85,6 +427,33 @@ impl Console {
         for i in completed.into_iter().rev() {
             self.pending_commands.remove(i);
         }
+        let mut finished = Vec::new();
+        self.pending_scripts
+            .retain(|(path, receiver)| match receiver.try_recv() {
+                Ok(result) => {
+                    finished.push((path.clone(), result));
+                    false
+                }
+                Err(mpsc::TryRecvError::Empty) => true,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    finished.push((
+                        path.clone(),
+                        Err(ScriptError::new("Script processing failed", "")),
+                    ));
+                    false
+                }
+            });
+        for (path, result) in finished {
+            match result {
+                Ok(()) => self.log(format!("Ran {}", path)),
+                Err(err) => {
+                    self.last_result = Some(format!("Error: {}", err));
+                    self.history.extend(error_lines(&err));
+                }
+            }
+        }
+        self.watches.lock().unwrap().update(&self.lua_client);
+
         // Limit history size
         while self.history.len() > 100 {
             self.history.remove(0);
@@ -94,16 +463,53 @@ impl Console {
             return;
         }
 
+        let ctrl_down = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        if ctrl_down && is_key_pressed(KeyCode::O) {
+            if self.script_menu.is_open() {
+                self.script_menu.close();
+            } else {
+                let recent = self.config.lock().unwrap().recent_scripts.clone();
+                self.script_menu.open(SCRIPTS_DIR, &recent);
+            }
+        }
+        // The menu takes the keys and clicks while it is open
+        if self.script_menu.is_open() {
+            let (_, _, console_height) = self.history_layout();
+            let scripts = self
+                .script_menu
+                .update(console_height - CONSOLE_INPUT_HEIGHT);
+            self.run_scripts(scripts);
+            return;
+        }
+
         // Handle clipboard operations
+        let shift_down = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
         let copy_requested = is_key_down(KeyCode::LeftControl) && is_key_pressed(KeyCode::C)
             || (is_key_down(KeyCode::LeftControl) && is_key_pressed(KeyCode::Insert));
-        if copy_requested {
-            if let Some(ref mut ctx) = self.clipboard {
-                let _ = ctx.set_text(self.editbox.clone());
-                self.history.push("Text copied to clipboard".to_string());
+        if copy_requested && shift_down {
+            let history: Vec<&str> = self.history.iter().map(|(line, _)| line.as_str()).collect();
+            self.copy_to_clipboard(history.join("\n"), "History");
+        } else if copy_requested {
+            self.copy_to_clipboard(self.editor.text().to_string(), "Text");
+        }
+
+        let copy_result_requested = (is_key_down(KeyCode::LeftAlt)
+            || is_key_down(KeyCode::RightAlt))
+            && is_key_pressed(KeyCode::C);
+        if copy_result_requested {
+            match self.last_result.clone() {
+                Some(result) => self.copy_to_clipboard(result, "Last result"),
+                None => self.log("No result to copy yet".to_string()),
             }
         }
 
+        // Click a history line to copy it
+        if is_mouse_button_pressed(MouseButton::Left)
+            && let Some(index) = self.history_line_at(Vec2::from(mouse_position()))
+        {
+            self.copy_to_clipboard(self.history[index].0.clone(), "Line");
+        }
+
         let paste_requested = (is_key_down(KeyCode::LeftControl) && is_key_pressed(KeyCode::V))
             || (is_key_down(KeyCode::LeftShift) && is_key_pressed(KeyCode::Insert))
             || (is_key_down(KeyCode::RightShift) && is_key_pressed(KeyCode::Insert));
@@ -111,16 +517,17 @@ impl Console {
         if paste_requested {
             if let Some(ref mut ctx) = self.clipboard {
                 if let Ok(clipboard_text) = ctx.get_text() {
-                    self.editbox.push_str(&clipboard_text);
+                    self.editor.insert_str(&clipboard_text);
                 }
             }
         }
 
-        // Execute command on Shift+Enter
-        if is_key_pressed(KeyCode::Enter)
-            && (is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl))
-        {
-            self.execute_command();
+        // Complete chunks run on Enter, incomplete ones keep the editor open for more lines
+        match self.editor.update() {
+            EditorAction::Submit => self.execute_command(),
+            EditorAction::HistoryPrevious => self.browse_history(true),
+            EditorAction::HistoryNext => self.browse_history(false),
+            EditorAction::None => {}
         }
     }
 
@@ -130,17 +537,19 @@ impl Console {
         }
 
         // Calculate console dimensions
-        let console_height = screen_height() * 0.4;
-        let input_area_height = 180.0;
+        let (start_idx, history_height, console_height) = self.history_layout();
+        let input_area_height = CONSOLE_INPUT_HEIGHT;
 
         // Draw semi-transparent background
-        draw_rectangle(
-            0.0,
-            0.0,
-            screen_width(),
-            console_height,
-            Color::new(0.1, 0.1, 0.1, 0.7), // TEXT_BACKGROUND_COLOR
-        );
+        let (background, input_background) = if self.high_contrast {
+            (BLACK, BLACK)
+        } else {
+            (
+                Color::new(0.1, 0.1, 0.1, 0.7), // TEXT_BACKGROUND_COLOR
+                Color::new(0.0, 0.0, 0.0, 0.8),
+            )
+        };
+        draw_rectangle(0.0, 0.0, screen_width(), console_height, background);
 
         // Draw input area with slightly darker background
         draw_rectangle(
@@ -148,8 +557,18 @@ impl Console {
             console_height - input_area_height,
             screen_width(),
             input_area_height,
-            Color::new(0.0, 0.0, 0.0, 0.8),
+            input_background,
         );
+        if self.high_contrast {
+            draw_line(
+                0.0,
+                console_height - input_area_height,
+                screen_width(),
+                console_height - input_area_height,
+                1.0,
+                WHITE,
+            );
+        }
 
         // Draw command prompt
         draw_text(
@@ -160,35 +579,31 @@ impl Console {
             WHITE,
         );
 
-        // Draw command history (most recent at the bottom)
-        let line_height = 20.0;
-        let visible_lines = ((console_height - input_area_height) / line_height) as usize;
-        let start_idx = if self.history.len() > visible_lines {
-            self.history.len() - visible_lines
-        } else {
-            0
-        };
-        for (i, line) in self.history[start_idx..].iter().enumerate() {
-            let y = (i as f32) * line_height + 20.0;
-            draw_text(line, 10.0, y, 20.0, WHITE);
+        // Draw command history (most recent at the bottom), highlighting the line under the mouse
+        let hovered_line = self.history_line_at(Vec2::from(mouse_position()));
+        for (i, (line, line_color)) in self.history[start_idx..].iter().enumerate() {
+            let y = (i as f32) * CONSOLE_LINE_HEIGHT + CONSOLE_LINE_HEIGHT;
+            let color = if hovered_line == Some(start_idx + i) {
+                YELLOW
+            } else if self.high_contrast && *line_color == GRAY {
+                WHITE
+            } else {
+                *line_color
+            };
+            draw_text(line, 10.0, y, 20.0, color);
         }
+        self.watches
+            .lock()
+            .unwrap()
+            .draw(screen_width(), 0.0, history_height);
 
-        // Use Editbox for input (placed after background drawing)
-        let mut ui = root_ui();
-
-        // Position the editbox in the input area - using Vec2 for size
-        let editbox_width = screen_width() - 40.0;
-
-        // Create editbox with proper size (Vec2)
-        let size = Vec2::new(editbox_width, input_area_height);
-        let pos_x = 35.0; // After the prompt
-        let pos_y = console_height - input_area_height + 5.0;
-
-        widgets::Editbox::new(hash!(), size)
-            .position(Vec2::new(pos_x, pos_y))
-            .multiline(true)
-            .ui(&mut ui, &mut self.editbox);
-
-        ui.pop_skin();
+        // Editor area after the prompt
+        self.editor.draw(Rect::new(
+            35.0,
+            console_height - input_area_height + 5.0,
+            screen_width() - 40.0,
+            input_area_height - 10.0,
+        ));
+        self.script_menu.draw(console_height - input_area_height);
     }
 }
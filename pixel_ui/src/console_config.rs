@@ -0,0 +1,130 @@
+use crate::lua_syntax::{tokenize, TokenKind};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+/// What the console keeps between sessions: the aliases defined with `alias(name, expansion)`,
+/// the commands entered, oldest first, and the scripts run from the quick-run menu, most recent
+/// first
+#[derive(Serialize, Deserialize, Default)]
+pub struct ConsoleConfig {
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+    #[serde(default)]
+    pub history: Vec<String>,
+    #[serde(default)]
+    pub recent_scripts: Vec<String>,
+}
+
+impl ConsoleConfig {
+    /// Missing or broken files give an empty config, the console works without one
+    pub fn load(path: &str) -> Self {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            println!("Failed to parse {}: {}", path, e);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize the console config: {}", e))?;
+        fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+
+    /// Remember a command, repeating the previous one doesn't add it again
+    pub fn push_history(&mut self, command: &str, max_len: usize) {
+        if self.history.last().is_some_and(|last| last == command) {
+            return;
+        }
+        self.history.push(command.to_string());
+        let excess = self.history.len().saturating_sub(max_len);
+        self.history.drain(..excess);
+    }
+
+    /// Move a script to the front of the recent ones
+    pub fn push_recent_script(&mut self, path: &str, max_len: usize) {
+        self.recent_scripts.retain(|recent| recent != path);
+        self.recent_scripts.insert(0, path.to_string());
+        self.recent_scripts.truncate(max_len);
+    }
+
+    /// Replace the aliases in a command by their expansion. Only names standing on their own
+    /// are replaced, not fields (`x.p`), locals being declared or text in strings and comments.
+    pub fn expand(&self, command: &str) -> String {
+        if self.aliases.is_empty() {
+            return command.to_string();
+        }
+        let (tokens, _) = tokenize(command);
+        let mut expanded = String::with_capacity(command.len());
+        let mut previous: Vec<&str> = Vec::new();
+        for token in &tokens {
+            let text = &command[token.start..token.end];
+            let is_field = previous.last() == Some(&".")
+                && previous.iter().rev().nth(1) != Some(&".")
+                || previous.last() == Some(&":");
+            let expansion = match token.kind {
+                TokenKind::Identifier if !is_field && previous.last() != Some(&"local") => {
+                    self.aliases.get(text)
+                }
+                _ => None,
+            };
+            expanded.push_str(expansion.map_or(text, String::as_str));
+            if token.kind != TokenKind::Whitespace {
+                previous.push(text);
+            }
+        }
+        expanded
+    }
+}
+
+/// Whether `name` can be used as an alias, a plain identifier that isn't a keyword
+pub fn is_alias_name(name: &str) -> bool {
+    matches!(tokenize(name).0.as_slice(), [token] if token.kind == TokenKind::Identifier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(aliases: &[(&str, &str)]) -> ConsoleConfig {
+        ConsoleConfig {
+            aliases: aliases
+                .iter()
+                .map(|(name, expansion)| (name.to_string(), expansion.to_string()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_only_standalone_names_are_expanded() {
+        let config = config(&[("p", "api.person"), ("heal", "print('healed')")]);
+        assert_eq!(config.expand("p.get(1)"), "api.person.get(1)");
+        assert_eq!(config.expand("heal"), "print('healed')");
+        assert_eq!(
+            config.expand("x.p + y:p() .. p"),
+            "x.p + y:p() .. api.person"
+        );
+        assert_eq!(config.expand("local p = 'p' -- p"), "local p = 'p' -- p");
+    }
+
+    #[test]
+    fn test_history_skips_repeats_and_keeps_the_newest() {
+        let mut config = config(&[]);
+        for command in ["a", "b", "b", "c"] {
+            config.push_history(command, 2);
+        }
+        assert_eq!(config.history, vec!["b", "c"]);
+    }
+
+    #[test]
+    fn test_alias_names_are_identifiers() {
+        assert!(is_alias_name("p2"));
+        assert!(!is_alias_name("end"));
+        assert!(!is_alias_name("a.b"));
+        assert!(!is_alias_name(""));
+    }
+}
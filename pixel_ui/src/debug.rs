@@ -1,11 +1,35 @@
+use crate::budget::Degradation;
 use crate::camera::CameraController;
-use crate::config::{FPS_HISTORY_SIZE, TILE_SIZE};
+use crate::charts::draw_stacked_columns;
+use crate::config::{
+    COMPONENT_TIMINGS_SHOWN, FPS_HISTORY_SIZE, MOD_BUDGET_CHART_HEIGHT, MOD_BUDGET_COLORS,
+    TEXT_PADDING, TILE_SIZE,
+};
+use crate::hud::{Anchor, HudLayout};
 use crate::input::InputManager;
-use crate::utils::draw_text_list;
+use crate::lua_ui_integration::ComponentTiming;
+use crate::pool::PoolStats;
+use crate::profiler::PhaseTiming;
+use crate::utils::{draw_text_list, text_list_size, text_scale};
 use crate::{TileMap, TilePosition};
+use lua_engine::mod_budget::ModBudget;
 use macroquad::prelude::*;
 use std::collections::VecDeque;
 
+/// What the debug window shows of the current frame
+pub(crate) struct DebugFrame<'a> {
+    pub(crate) map: &'a TileMap,
+    pub(crate) camera: &'a CameraController,
+    pub(crate) selected_pos: Option<&'a TilePosition>,
+    pub(crate) input: &'a InputManager,
+    pub(crate) pools: &'a [(&'a str, PoolStats)],
+    pub(crate) profile: &'a [PhaseTiming],
+    pub(crate) components: &'a [ComponentTiming],
+    pub(crate) mod_budget: &'a ModBudget,
+    pub(crate) degradations: &'a [Degradation],
+    pub(crate) people_grouped: bool,
+}
+
 pub struct DebugWindow {
     enabled: bool,
     fps_history: VecDeque<i32>,
@@ -31,16 +55,26 @@ impl DebugWindow {
         self.enabled = !self.enabled;
     }
 
-    pub(crate) fn draw(
-        &self,
-        map: &TileMap,
-        camera: &CameraController,
-        selected_pos: Option<&TilePosition>,
-        input: &InputManager,
-    ) {
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub(crate) fn draw(&self, layout: &mut HudLayout, anchor: Anchor, frame: &DebugFrame) {
         if !self.enabled {
             return;
         }
+        let DebugFrame {
+            map,
+            camera,
+            selected_pos,
+            input,
+            pools,
+            profile,
+            components,
+            mod_budget,
+            degradations,
+            people_grouped,
+        } = *frame;
 
         let mut debug_texts = Vec::new();
 
@@ -49,7 +83,7 @@ impl DebugWindow {
             let hover_pos =
                 TilePosition::from_world_pos(camera.screen_to_world(input.get_mouse_position()));
 
-            if let Some(tile) = map.get_tile(&hover_pos) {
+            if let Some(tile) = map.get_visible_tile(&hover_pos) {
                 debug_texts.push((
                     format!("Hover: ({}, {}) ID: {}", hover_pos.x, hover_pos.y, tile.id),
                     WHITE,
@@ -64,7 +98,7 @@ impl DebugWindow {
 
         // Add selected tile info
         if let Some(pos) = selected_pos {
-            if let Some(tile) = map.get_tile(pos) {
+            if let Some(tile) = map.get_visible_tile(pos) {
                 debug_texts.push((
                     format!("Selected: ({}, {}) ID: {}", pos.x, pos.y, tile.id),
                     RED,
@@ -77,8 +111,18 @@ impl DebugWindow {
             format!(
                 "Visible tiles: {}/{} ({:.1}%)",
                 map.visible_tiles_count,
-                map.tiles.len(),
-                100.0 * map.visible_tiles_count as f32 / map.tiles.len() as f32
+                map.tile_count(),
+                100.0 * map.visible_tiles_count as f32 / map.tile_count() as f32
+            ),
+            BLUE,
+        ));
+
+        debug_texts.push((
+            format!(
+                "Active layer: {} ({}/{})",
+                map.layers[map.active_layer].name,
+                map.active_layer + 1,
+                map.layers.len()
             ),
             BLUE,
         ));
@@ -94,6 +138,91 @@ impl DebugWindow {
             GREEN,
         ));
 
+        for timing in profile {
+            debug_texts.push((
+                format!(
+                    "Draw {}: {:.2} ms, {} drawn, {} calls",
+                    timing.phase, timing.ms, timing.draws, timing.draw_calls
+                ),
+                YELLOW,
+            ));
+        }
+        // A/B comparison of the people batching on the same crowd
+        debug_texts.push((
+            format!(
+                "Draw calls: {}, people {} (Shift+B to switch)",
+                profile
+                    .iter()
+                    .map(|timing| timing.draw_calls)
+                    .sum::<usize>(),
+                if people_grouped {
+                    "grouped by texture"
+                } else {
+                    "sorted by sheet"
+                }
+            ),
+            YELLOW,
+        ));
+
+        // The UI components whose Lua handlers take the longest
+        let mut slowest: Vec<&ComponentTiming> = components.iter().collect();
+        slowest.sort_by(|a, b| b.average_ms.total_cmp(&a.average_ms));
+        for timing in slowest.into_iter().take(COMPONENT_TIMINGS_SHOWN) {
+            debug_texts.push((
+                format!(
+                    "Lua {}: {:.2} ms, avg {:.2} ms",
+                    timing.name, timing.last_ms, timing.average_ms
+                ),
+                ORANGE,
+            ));
+        }
+
+        // The Lua time of the mods stacked per frame below the text, the slowest at the bottom
+        let averages = mod_budget.averages();
+        let frames = mod_budget.frames();
+        for (i, (owner, average)) in averages.iter().enumerate() {
+            let last = frames
+                .last()
+                .and_then(|frame| frame.get(owner))
+                .copied()
+                .unwrap_or(0.0);
+            debug_texts.push((
+                format!("Mod {}: {:.2} ms, avg {:.2} ms", owner, last, average),
+                MOD_BUDGET_COLORS[i % MOD_BUDGET_COLORS.len()],
+            ));
+        }
+        let columns: Vec<Vec<f64>> = frames
+            .iter()
+            .map(|frame| {
+                averages
+                    .iter()
+                    .map(|(owner, _)| frame.get(owner).copied().unwrap_or(0.0))
+                    .collect()
+            })
+            .collect();
+
+        for (name, stats) in pools {
+            debug_texts.push((
+                format!(
+                    "Pool {}: {} created, {} reused ({:.0}%), {} free",
+                    name,
+                    stats.created,
+                    stats.reused,
+                    100.0 * stats.reuse_ratio(),
+                    stats.free
+                ),
+                GRAY,
+            ));
+        }
+
+        if !degradations.is_empty() {
+            let names: Vec<&str> = degradations.iter().map(Degradation::name).collect();
+            debug_texts.push((
+                format!("Over frame budget, left out: {}", names.join(", ")),
+                RED,
+            ));
+        }
+
         let avg_fps: f32 =
             self.fps_history.iter().sum::<i32>() as f32 / self.fps_history.len().max(1) as f32;
         debug_texts.push((format!("FPS: {} (Avg: {:.1})", get_fps(), avg_fps), GREEN));
@@ -104,16 +233,32 @@ impl DebugWindow {
             WHITE,
         ));
 
-        // Draw all debug texts with a single background
-        draw_text_list(debug_texts, 20.0, 30.0);
+        // Draw all debug texts with a single background, the chart of the mods below them
+        let chart_height = if averages.is_empty() {
+            0.0
+        } else {
+            MOD_BUDGET_CHART_HEIGHT * text_scale()
+        };
+        let mut size = text_list_size(&debug_texts);
+        size.y += chart_height;
+        let rect = layout.place(anchor, size);
+        draw_text_list(debug_texts, rect.x + TEXT_PADDING * text_scale(), rect.y);
+        if chart_height > 0.0 {
+            draw_stacked_columns(
+                Rect::new(rect.x, rect.bottom() - chart_height, rect.w, chart_height),
+                &columns,
+                &MOD_BUDGET_COLORS,
+                "ms",
+            );
+        }
     }
 
-    pub(crate) fn draw_tile_highlight(&self, pos: &TilePosition) {
+    pub(crate) fn draw_tile_highlight(&self, pos: &TilePosition, color: Color) {
         if !self.enabled {
             return;
         }
 
         let world_pos = pos.to_world_pos();
-        draw_rectangle_lines(world_pos.x, world_pos.y, TILE_SIZE, TILE_SIZE, 2.0, YELLOW);
+        draw_rectangle_lines(world_pos.x, world_pos.y, TILE_SIZE, TILE_SIZE, 2.0, color);
     }
 }
@@ -0,0 +1,74 @@
+use crate::config::{DEBUGGER_PANEL_HEIGHT, DEBUGGER_PANEL_WIDTH};
+use lua_engine::debugger::Debugger;
+use macroquad::hash;
+use macroquad::prelude::*;
+use macroquad::ui::{root_ui, widgets};
+
+/// Panel shown while a script is paused at a breakpoint, with its variables and step controls
+pub struct DebuggerPanel {
+    debugger: Debugger,
+}
+
+impl DebuggerPanel {
+    pub(crate) fn new(debugger: Debugger) -> Self {
+        // Scripts called from the main thread (UI handlers) can't be paused, the panel would freeze
+        debugger.set_ui_thread();
+        Self { debugger }
+    }
+
+    /// Lua called from the main thread would block until the paused script continues
+    pub(crate) fn is_paused(&self) -> bool {
+        self.debugger.is_paused()
+    }
+
+    pub(crate) fn captures_mouse(&self, screen_pos: Vec2) -> bool {
+        self.is_paused() && root_ui().is_mouse_over(screen_pos)
+    }
+
+    pub(crate) fn update(&self) {
+        if !self.is_paused() {
+            return;
+        }
+        if is_key_pressed(KeyCode::F10) {
+            self.debugger.step();
+        }
+        if is_key_pressed(KeyCode::F5) {
+            self.debugger.resume();
+        }
+    }
+
+    pub(crate) fn draw(&self) {
+        let Some(frame) = self.debugger.paused() else {
+            return;
+        };
+
+        let position = Vec2::new(
+            (screen_width() - DEBUGGER_PANEL_WIDTH) / 2.0,
+            screen_height() - DEBUGGER_PANEL_HEIGHT - 120.0,
+        );
+        widgets::Window::new(
+            hash!(),
+            position,
+            Vec2::new(DEBUGGER_PANEL_WIDTH, DEBUGGER_PANEL_HEIGHT),
+        )
+        .label(&format!("Paused at {}:{}", frame.file, frame.line))
+        .ui(&mut root_ui(), |ui| {
+            if ui.button(None, "Step (F10)") {
+                self.debugger.step();
+            }
+            ui.same_line(0.0);
+            if ui.button(None, "Continue (F5)") {
+                self.debugger.resume();
+            }
+            ui.separator();
+            ui.label(None, "Locals");
+            for (name, value) in &frame.locals {
+                ui.label(None, &format!("  {} = {}", name, value));
+            }
+            ui.label(None, "Upvalues");
+            for (name, value) in &frame.upvalues {
+                ui.label(None, &format!("  {} = {}", name, value));
+            }
+        });
+    }
+}
@@ -0,0 +1,34 @@
+use lua_engine::lua_engine::LuaEngine;
+use lua_engine::Table;
+use std::sync::{Arc, Mutex};
+
+/// Plain text account of what the screen shows, one line per HUD item or UI component, for
+/// screen readers and for tests checking the UI. Rebuilt at the end of every frame and
+/// available to scripts as `ui.describe()`.
+#[derive(Clone, Default)]
+pub struct UiDescription {
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+impl UiDescription {
+    /// Adds `describe` to the `ui` table, which has to be there already
+    pub(crate) fn new(lua_engine: &Arc<Mutex<LuaEngine>>) -> Self {
+        let description = Self::default();
+
+        let lua = &lua_engine.lock().unwrap().lua;
+        let ui: Table = lua.globals().get("ui").unwrap();
+        {
+            let description = description.clone();
+            lua.create_function(move |_, ()| Ok(description.lines.lock().unwrap().join("\n")))
+                .and_then(|f| ui.set("describe", f))
+                .unwrap();
+        }
+
+        description
+    }
+
+    /// Replace the description with the one of the frame just drawn
+    pub(crate) fn set(&self, lines: Vec<String>) {
+        *self.lines.lock().unwrap() = lines;
+    }
+}
@@ -0,0 +1,444 @@
+use crate::config::{
+    BUTTON_ACTIVE_COLOR, BUTTON_COLOR, BUTTON_HEIGHT, CONSOLE_LINE_HEIGHT, DOCS_BROWSER_HEIGHT,
+    DOCS_BROWSER_TREE_WIDTH, DOCS_BROWSER_WIDTH, TEXT_BACKGROUND_COLOR, TEXT_FONT_SIZE,
+};
+use crate::speech::wrap;
+use lua_engine::api_reference::{ApiReference, MethodPage};
+use lua_engine::Lua;
+use macroquad::prelude::*;
+
+// Width of the button inserting the example
+const INSERT_WIDTH: f32 = 170.0;
+
+/// A line of the list on the left, by index into the modules and their methods
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Row {
+    Module(usize),
+    Method(usize, usize),
+}
+
+/// Browser of the scripting API opened with F1: the modules on the left, expanded to their
+/// methods with a click, and the page of the selected method on the right. Typing searches the
+/// methods instead, Up and Down pick one and the button under its page puts a call of it into
+/// the console.
+pub struct DocsBrowser {
+    lua: Lua,
+    open: bool,
+    reference: ApiReference,
+    // Modules listed with their methods while not searching
+    expanded: Vec<bool>,
+    query: String,
+    // Index into the rows
+    selected: usize,
+    // Rows scrolled past at the top of the list
+    scroll: usize,
+}
+
+impl DocsBrowser {
+    pub(crate) fn new(lua: Lua) -> Self {
+        Self {
+            lua,
+            open: false,
+            reference: ApiReference::default(),
+            expanded: Vec::new(),
+            query: String::new(),
+            selected: 0,
+            scroll: 0,
+        }
+    }
+
+    pub(crate) fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Open with the docs as they are now, the mods may have added read models since the last
+    /// time, or close
+    pub(crate) fn toggle(&mut self) {
+        if self.open {
+            self.open = false;
+            return;
+        }
+        match ApiReference::read(&self.lua) {
+            Ok(reference) => {
+                if reference != self.reference {
+                    self.expanded = vec![false; reference.modules.len()];
+                    self.reference = reference;
+                    self.selected = 0;
+                    self.scroll = 0;
+                }
+                self.open = true;
+            }
+            Err(e) => println!("Failed to read the API docs: {}", e),
+        }
+    }
+
+    pub(crate) fn close(&mut self) {
+        self.open = false;
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        if !self.query.is_empty() {
+            return self
+                .reference
+                .search(&self.query)
+                .into_iter()
+                .filter_map(|page| self.row_of(page))
+                .collect();
+        }
+        let mut rows = Vec::new();
+        for (m, module) in self.reference.modules.iter().enumerate() {
+            rows.push(Row::Module(m));
+            if self.expanded.get(m).copied().unwrap_or(false) {
+                rows.extend((0..module.methods.len()).map(|i| Row::Method(m, i)));
+            }
+        }
+        rows
+    }
+
+    fn row_of(&self, page: &MethodPage) -> Option<Row> {
+        let m = self
+            .reference
+            .modules
+            .iter()
+            .position(|module| module.name == page.module)?;
+        let i = self.reference.modules[m]
+            .methods
+            .iter()
+            .position(|method| method.name == page.name)?;
+        Some(Row::Method(m, i))
+    }
+
+    fn page(&self, row: Row) -> Option<&MethodPage> {
+        match row {
+            Row::Method(m, i) => self.reference.modules.get(m)?.methods.get(i),
+            Row::Module(_) => None,
+        }
+    }
+
+    fn selected_page(&self) -> Option<&MethodPage> {
+        self.rows()
+            .get(self.selected)
+            .and_then(|row| self.page(*row))
+    }
+
+    fn label(&self, row: Row) -> String {
+        match row {
+            Row::Module(m) => {
+                let sign = if self.expanded[m] { "-" } else { "+" };
+                format!("{} {}", sign, self.reference.modules[m].name)
+            }
+            Row::Method(m, i) if self.query.is_empty() => {
+                format!("    {}", self.reference.modules[m].methods[i].name)
+            }
+            Row::Method(m, i) => {
+                let module = &self.reference.modules[m];
+                format!("{}.{}", module.name, module.methods[i].name)
+            }
+        }
+    }
+
+    fn rect() -> Rect {
+        let width = DOCS_BROWSER_WIDTH.min(screen_width() - 20.0);
+        let height = DOCS_BROWSER_HEIGHT.min(screen_height() - 20.0);
+        Rect::new(
+            (screen_width() - width) / 2.0,
+            (screen_height() - height) / 2.0,
+            width,
+            height,
+        )
+    }
+
+    // Rows of the list that fit below the query
+    fn visible_rows() -> usize {
+        ((Self::rect().h - 10.0) / CONSOLE_LINE_HEIGHT) as usize - 1
+    }
+
+    fn row_at(&self, pos: Vec2) -> Option<usize> {
+        let rect = Self::rect();
+        if pos.x < rect.x || pos.x > rect.x + DOCS_BROWSER_TREE_WIDTH || !rect.contains(pos) {
+            return None;
+        }
+        let line = ((pos.y - rect.y - 5.0) / CONSOLE_LINE_HEIGHT) as usize;
+        (line >= 1 && line <= Self::visible_rows()).then(|| self.scroll + line - 1)
+    }
+
+    fn insert_rect() -> Rect {
+        let rect = Self::rect();
+        Rect::new(
+            rect.right() - INSERT_WIDTH - 10.0,
+            rect.bottom() - BUTTON_HEIGHT - 10.0,
+            INSERT_WIDTH,
+            BUTTON_HEIGHT,
+        )
+    }
+
+    pub(crate) fn captures_mouse(&self, screen_pos: Vec2) -> bool {
+        self.open && Self::rect().contains(screen_pos)
+    }
+
+    /// The method shown while open
+    pub(crate) fn describe(&self) -> Option<String> {
+        if !self.open {
+            return None;
+        }
+        Some(match self.selected_page() {
+            Some(page) => format!("API docs: {}", page.signature()),
+            None => format!("API docs, {} modules", self.reference.modules.len()),
+        })
+    }
+
+    // Keep the selection within the rows and in view
+    fn select(&mut self, index: usize) {
+        let count = self.rows().len();
+        self.selected = index.min(count.saturating_sub(1));
+        let visible = Self::visible_rows();
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + visible {
+            self.scroll = self.selected + 1 - visible;
+        }
+    }
+
+    // Expand or collapse a module, a method is shown once selected
+    fn activate(&mut self, index: usize) {
+        self.select(index);
+        if let Some(Row::Module(m)) = self.rows().get(self.selected).copied() {
+            self.expanded[m] = !self.expanded[m];
+        }
+    }
+
+    /// Handle the keys and clicks while open, returns the example to put into the console.
+    /// Escape or a click outside closes it.
+    pub(crate) fn update(&mut self) -> Option<String> {
+        while let Some(c) = get_char_pressed() {
+            if !c.is_control() {
+                self.query.push(c);
+                self.selected = 0;
+                self.scroll = 0;
+            }
+        }
+        if is_key_pressed(KeyCode::Backspace) && self.query.pop().is_some() {
+            self.selected = 0;
+            self.scroll = 0;
+        }
+        if is_key_pressed(KeyCode::Escape) {
+            self.open = false;
+            return None;
+        }
+        if is_key_pressed(KeyCode::Down) {
+            self.select(self.selected + 1);
+        }
+        if is_key_pressed(KeyCode::Up) {
+            self.select(self.selected.saturating_sub(1));
+        }
+        if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter) {
+            self.activate(self.selected);
+        }
+
+        let wheel = mouse_wheel().1;
+        let max_scroll = self.rows().len().saturating_sub(Self::visible_rows());
+        if wheel < 0.0 {
+            self.scroll = (self.scroll + 3).min(max_scroll);
+        } else if wheel > 0.0 {
+            self.scroll = self.scroll.saturating_sub(3);
+        }
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            let pos = Vec2::from(mouse_position());
+            if !Self::rect().contains(pos) {
+                self.open = false;
+            } else if Self::insert_rect().contains(pos) {
+                return self.selected_page().and_then(|page| page.example.clone());
+            } else if let Some(index) = self.row_at(pos)
+                && index < self.rows().len()
+            {
+                self.activate(index);
+            }
+        }
+        None
+    }
+
+    pub(crate) fn draw(&self) {
+        if !self.open {
+            return;
+        }
+        let rect = Self::rect();
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, TEXT_BACKGROUND_COLOR);
+        draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 1.0, GRAY);
+        let divider = rect.x + DOCS_BROWSER_TREE_WIDTH;
+        draw_line(divider, rect.y, divider, rect.bottom(), 1.0, GRAY);
+
+        let query = if self.query.is_empty() {
+            "Type to search the API".to_string()
+        } else {
+            format!("> {}_", self.query)
+        };
+        draw_text(
+            &query,
+            rect.x + 8.0,
+            rect.y + CONSOLE_LINE_HEIGHT,
+            20.0,
+            YELLOW,
+        );
+        let rows = self.rows();
+        if rows.is_empty() {
+            draw_text(
+                "Nothing matches",
+                rect.x + 8.0,
+                rect.y + 2.0 * CONSOLE_LINE_HEIGHT,
+                20.0,
+                GRAY,
+            );
+        }
+        let hovered = self.row_at(Vec2::from(mouse_position()));
+        for (line, (index, row)) in rows
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(Self::visible_rows())
+            .enumerate()
+        {
+            let y = rect.y + (line as f32 + 2.0) * CONSOLE_LINE_HEIGHT;
+            if index == self.selected {
+                draw_rectangle(
+                    rect.x + 2.0,
+                    y - CONSOLE_LINE_HEIGHT + 5.0,
+                    DOCS_BROWSER_TREE_WIDTH - 4.0,
+                    CONSOLE_LINE_HEIGHT,
+                    BUTTON_ACTIVE_COLOR,
+                );
+            }
+            let color = match row {
+                _ if hovered == Some(index) => YELLOW,
+                Row::Module(_) => SKYBLUE,
+                Row::Method(..) => WHITE,
+            };
+            draw_text(&self.label(*row), rect.x + 8.0, y, 18.0, color);
+        }
+
+        let Some(page) = self.selected_page() else {
+            draw_text(
+                "Pick a method to see its page",
+                divider + 10.0,
+                rect.y + CONSOLE_LINE_HEIGHT,
+                TEXT_FONT_SIZE,
+                GRAY,
+            );
+            return;
+        };
+        let width = rect.right() - divider - 20.0;
+        let measure = |size: u16| move |text: &str| measure_text(text, None, size, 1.0).width;
+        let mut lines: Vec<(String, Color, f32)> = Vec::new();
+        lines.extend(
+            wrap(&page.signature(), width, measure(18))
+                .into_iter()
+                .map(|line| (line, YELLOW, 18.0)),
+        );
+        lines.push((String::new(), WHITE, 18.0));
+        lines.extend(
+            wrap(&page.description.replace('\n', " "), width, measure(18))
+                .into_iter()
+                .map(|line| (line, WHITE, 18.0)),
+        );
+        lines.push((String::new(), WHITE, 18.0));
+        if !page.params.is_empty() {
+            lines.push(("Parameters:".to_string(), GRAY, 18.0));
+            for param in &page.params {
+                lines.push((
+                    format!("  {}: {}", param.name, param.type_name),
+                    WHITE,
+                    18.0,
+                ));
+            }
+        }
+        if !page.returns.is_empty() {
+            lines.push((format!("Returns: {}", page.returns), GRAY, 18.0));
+        }
+        if let Some(example) = &page.example {
+            lines.push((String::new(), WHITE, 18.0));
+            lines.push(("Example:".to_string(), GRAY, 18.0));
+            lines.extend(
+                wrap(example, width, measure(18))
+                    .into_iter()
+                    .map(|line| (line, GREEN, 18.0)),
+            );
+        }
+        for (i, (line, color, size)) in lines.iter().enumerate() {
+            draw_text(
+                line,
+                divider + 10.0,
+                rect.y + (i + 1) as f32 * CONSOLE_LINE_HEIGHT,
+                *size,
+                *color,
+            );
+        }
+
+        if page.example.is_some() {
+            let button = Self::insert_rect();
+            draw_rectangle(button.x, button.y, button.w, button.h, BUTTON_COLOR);
+            draw_rectangle_lines(button.x, button.y, button.w, button.h, 1.0, GRAY);
+            draw_text(
+                "Insert into console",
+                button.x + 10.0,
+                button.y + (BUTTON_HEIGHT + TEXT_FONT_SIZE) / 2.0 - 4.0,
+                TEXT_FONT_SIZE,
+                WHITE,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lua_engine::api_reference::ModulePage;
+
+    fn method(module: &str, name: &str, description: &str) -> MethodPage {
+        MethodPage {
+            module: module.to_string(),
+            name: name.to_string(),
+            description: description.to_string(),
+            params: Vec::new(),
+            returns: String::new(),
+            example: None,
+        }
+    }
+
+    #[test]
+    fn test_rows_list_expanded_modules_or_the_search_results() {
+        let mut browser = DocsBrowser::new(Lua::new());
+        browser.reference = ApiReference {
+            modules: vec![
+                ModulePage {
+                    name: "map".to_string(),
+                    methods: vec![method("map", "place", "Place a tile")],
+                },
+                ModulePage {
+                    name: "person".to_string(),
+                    methods: vec![
+                        method("person", "create", "Create a person"),
+                        method("person", "move_all", "Move persons"),
+                    ],
+                },
+            ],
+        };
+        browser.expanded = vec![false, false];
+        assert_eq!(browser.rows(), vec![Row::Module(0), Row::Module(1)]);
+
+        browser.expanded[1] = true;
+        assert_eq!(
+            browser.rows(),
+            vec![
+                Row::Module(0),
+                Row::Module(1),
+                Row::Method(1, 0),
+                Row::Method(1, 1)
+            ]
+        );
+        assert_eq!(browser.label(Row::Module(1)), "- person");
+
+        browser.query = "pers.move".to_string();
+        assert_eq!(browser.rows()[0], Row::Method(1, 1));
+        assert_eq!(browser.label(Row::Method(1, 1)), "person.move_all");
+    }
+}
@@ -0,0 +1,323 @@
+use crate::camera::CameraController;
+use crate::config::{
+    FLOAT_TEXT_DURATION, FLOAT_TEXT_RISE, MAX_MAP_EFFECTS, SPARKLE_DURATION, TEXT_FONT_SIZE,
+    TILE_SIZE,
+};
+use crate::pool::{Pool, PoolStats};
+use crate::TilePosition;
+use lua_engine::lua_engine::LuaEngine;
+use lua_engine::{LuaError, LuaResult, LuaString, LuaValue};
+use macroquad::prelude::*;
+use std::collections::VecDeque;
+use std::f32::consts::TAU;
+use std::sync::{Arc, Mutex};
+
+const SPARKLE_PARTICLES: usize = 8;
+// Distance in world units the sparkle particles travel over their lifetime
+const SPARKLE_SPREAD: f32 = TILE_SIZE * 0.8;
+
+enum EffectKind {
+    /// Particles bursting out of the center, each with its direction
+    Sparkle { directions: Vec<Vec2> },
+    /// Text rising up from its position, drawn in screen space so it stays readable at any zoom
+    FloatText { text: String },
+}
+
+struct Effect {
+    kind: EffectKind,
+    position: Vec2,
+    color: Color,
+    age: f32,
+    duration: f32,
+}
+
+impl Effect {
+    // 0.0 when spawned, 1.0 when done
+    fn progress(&self) -> f32 {
+        (self.age / self.duration).min(1.0)
+    }
+
+    fn faded_color(&self) -> Color {
+        Color::new(
+            self.color.r,
+            self.color.g,
+            self.color.b,
+            self.color.a * (1.0 - self.progress()),
+        )
+    }
+
+    fn draw(&self) {
+        let progress = self.progress();
+        let color = self.faded_color();
+        match &self.kind {
+            EffectKind::Sparkle { directions } => {
+                // Fast start, slowing down towards the end
+                let distance = SPARKLE_SPREAD * (1.0 - (1.0 - progress).powi(2));
+                let size = 3.0 * (1.0 - progress * 0.5);
+                for direction in directions {
+                    let pos = self.position + *direction * distance;
+                    draw_rectangle(pos.x - size / 2.0, pos.y - size / 2.0, size, size, color);
+                }
+            }
+            EffectKind::FloatText { .. } => {}
+        }
+    }
+}
+
+struct EffectsState {
+    effects: VecDeque<Effect>,
+    // Buffers of expired effects, bursts of events spawn lots of effects in a row
+    particles: Pool<Vec<Vec2>>,
+    texts: Pool<String>,
+    // Off while frames run over the budget, sparkles are neither spawned nor drawn then
+    particles_enabled: bool,
+}
+
+impl EffectsState {
+    fn release(&mut self, effect: Effect) {
+        match effect.kind {
+            EffectKind::Sparkle { directions } => self.particles.release(directions),
+            EffectKind::FloatText { text } => self.texts.release(text),
+        }
+    }
+}
+
+/// Short-lived visual effects on the map, spawned by scripts through the Lua `fx` table
+#[derive(Clone)]
+pub struct MapEffects {
+    state: Arc<Mutex<EffectsState>>,
+}
+
+impl MapEffects {
+    pub(crate) fn new(lua_engine: &Arc<Mutex<LuaEngine>>) -> Self {
+        let map_effects = Self {
+            state: Arc::new(Mutex::new(EffectsState {
+                effects: VecDeque::new(),
+                particles: Pool::new(MAX_MAP_EFFECTS),
+                texts: Pool::new(MAX_MAP_EFFECTS),
+                particles_enabled: true,
+            })),
+        };
+
+        let lua = &lua_engine.lock().unwrap().lua;
+        let fx = lua.create_table().unwrap();
+        {
+            let map_effects = map_effects.clone();
+            lua.create_function(move |_, (x, y, color): (i32, i32, LuaValue)| {
+                map_effects.sparkle(TilePosition::new(x, y), lua_color(color, YELLOW)?);
+                Ok(())
+            })
+            .and_then(|f| fx.set("sparkle", f))
+            .unwrap();
+        }
+        {
+            let map_effects = map_effects.clone();
+            lua.create_function(
+                move |_, (x, y, text, color): (f32, f32, LuaString, LuaValue)| {
+                    map_effects.float_text(
+                        Vec2::new(x, y),
+                        &text.to_str()?,
+                        lua_color(color, WHITE)?,
+                    );
+                    Ok(())
+                },
+            )
+            .and_then(|f| fx.set("float_text", f))
+            .unwrap();
+        }
+        {
+            let map_effects = map_effects.clone();
+            lua.create_function(move |_, ()| Ok(map_effects.state.lock().unwrap().effects.len()))
+                .and_then(|f| fx.set("count", f))
+                .unwrap();
+        }
+        lua.globals().set("fx", fx).unwrap();
+
+        map_effects
+    }
+
+    fn spawn(state: &mut EffectsState, effect: Effect) {
+        // A burst of events must not slow down the frame, the oldest effects make room
+        if state.effects.len() >= MAX_MAP_EFFECTS
+            && let Some(oldest) = state.effects.pop_front()
+        {
+            state.release(oldest);
+        }
+        state.effects.push_back(effect);
+    }
+
+    /// Burst of particles out of the center of the tile
+    pub(crate) fn sparkle(&self, tile: TilePosition, color: Color) {
+        let mut state = self.state.lock().unwrap();
+        if !state.particles_enabled {
+            return;
+        }
+        let offset = rand::gen_range(0.0, TAU);
+        let mut directions = state.particles.acquire();
+        directions.extend(
+            (0..SPARKLE_PARTICLES)
+                .map(|i| Vec2::from_angle(offset + i as f32 * TAU / SPARKLE_PARTICLES as f32)),
+        );
+        Self::spawn(
+            &mut state,
+            Effect {
+                kind: EffectKind::Sparkle { directions },
+                position: tile.center_world_pos(),
+                color,
+                age: 0.0,
+                duration: SPARKLE_DURATION,
+            },
+        );
+    }
+
+    /// Text rising and fading above `tile`, which may be fractional to place it between tiles
+    pub(crate) fn float_text(&self, tile: Vec2, text: &str, color: Color) {
+        let mut state = self.state.lock().unwrap();
+        let mut pooled = state.texts.acquire();
+        pooled.push_str(text);
+        Self::spawn(
+            &mut state,
+            Effect {
+                kind: EffectKind::FloatText { text: pooled },
+                position: (tile + Vec2::splat(0.5)) * TILE_SIZE,
+                color,
+                age: 0.0,
+                duration: FLOAT_TEXT_DURATION,
+            },
+        );
+    }
+
+    pub(crate) fn set_particles(&self, enabled: bool) {
+        self.state.lock().unwrap().particles_enabled = enabled;
+    }
+
+    pub(crate) fn update(&self, dt: f32) {
+        let mut state = self.state.lock().unwrap();
+        for effect in state.effects.iter_mut() {
+            effect.age += dt;
+        }
+        // Hand the buffers of the expired effects back to the pools, keeping the order
+        for _ in 0..state.effects.len() {
+            let Some(effect) = state.effects.pop_front() else {
+                break;
+            };
+            if effect.age < effect.duration {
+                state.effects.push_back(effect);
+            } else {
+                state.release(effect);
+            }
+        }
+    }
+
+    /// Reuse statistics of the particle and text pools, by name
+    pub(crate) fn pool_stats(&self) -> [(&'static str, PoolStats); 2] {
+        let state = self.state.lock().unwrap();
+        [
+            ("particles", state.particles.stats()),
+            ("texts", state.texts.stats()),
+        ]
+    }
+
+    /// Draw the effects in the camera's view, expects the world camera to be applied.
+    /// Texts are drawn separately by `draw_texts`, after switching to the screen camera.
+    /// Both return how many effects they drew.
+    pub(crate) fn draw(&self, camera: &CameraController) -> usize {
+        let visible = camera.visible_world_rect();
+        // Effects reach a bit beyond their position, don't cut them off at the screen edge
+        let margin = SPARKLE_SPREAD;
+        let mut drawn = 0;
+        let state = self.state.lock().unwrap();
+        for effect in state.effects.iter() {
+            if !state.particles_enabled && matches!(effect.kind, EffectKind::Sparkle { .. }) {
+                continue;
+            }
+            let pos = effect.position;
+            if pos.x + margin >= visible.x
+                && pos.x - margin <= visible.right()
+                && pos.y + margin >= visible.y
+                && pos.y - margin <= visible.bottom()
+            {
+                effect.draw();
+                drawn += 1;
+            }
+        }
+        drawn
+    }
+
+    /// Draw the floating texts in screen space, all in one pass over the effects
+    pub(crate) fn draw_texts(&self, camera: &CameraController) -> usize {
+        let mut drawn = 0;
+        let screen = Rect::new(0.0, 0.0, screen_width(), screen_height());
+        for effect in self.state.lock().unwrap().effects.iter() {
+            let EffectKind::FloatText { text } = &effect.kind else {
+                continue;
+            };
+            let rise = FLOAT_TEXT_RISE * effect.progress();
+            let pos = camera.world_to_screen(effect.position - Vec2::new(0.0, rise));
+            let size = measure_text(text, None, TEXT_FONT_SIZE as u16, 1.0);
+            let rect = Rect::new(
+                pos.x - size.width / 2.0,
+                pos.y - size.height,
+                size.width,
+                size.height,
+            );
+            if !screen.overlaps(&rect) {
+                continue;
+            }
+            let color = effect.faded_color();
+            // Drop shadow keeps the text readable on bright tiles
+            let shadow = Color::new(0.0, 0.0, 0.0, color.a * 0.8);
+            draw_text(text, rect.x + 1.0, pos.y + 1.0, TEXT_FONT_SIZE, shadow);
+            draw_text(text, rect.x, pos.y, TEXT_FONT_SIZE, color);
+            drawn += 1;
+        }
+        drawn
+    }
+}
+
+/// Color given from Lua as a name ("red") or as `{r, g, b[, a]}` with components in 0..1
+pub(crate) fn lua_color(value: LuaValue, default: Color) -> LuaResult<Color> {
+    match value {
+        LuaValue::Nil => Ok(default),
+        LuaValue::String(name) => {
+            let name = name.to_str()?;
+            named_color(&name)
+                .ok_or_else(|| LuaError::RuntimeError(format!("Unknown color '{}'", name)))
+        }
+        LuaValue::Table(components) => {
+            let component = |i: usize, default: f32| -> LuaResult<f32> {
+                Ok(components.get::<Option<f32>>(i)?.unwrap_or(default))
+            };
+            Ok(Color::new(
+                component(1, 0.0)?,
+                component(2, 0.0)?,
+                component(3, 0.0)?,
+                component(4, 1.0)?,
+            ))
+        }
+        other => Err(LuaError::RuntimeError(format!(
+            "Expected a color name or {{r, g, b, a}}, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+fn named_color(name: &str) -> Option<Color> {
+    let color = match name {
+        "white" => WHITE,
+        "black" => BLACK,
+        "gray" => GRAY,
+        "red" => RED,
+        "green" => GREEN,
+        "lime" => LIME,
+        "blue" => BLUE,
+        "sky" => SKYBLUE,
+        "yellow" => YELLOW,
+        "gold" => GOLD,
+        "orange" => ORANGE,
+        "pink" => PINK,
+        "purple" => PURPLE,
+        _ => return None,
+    };
+    Some(color)
+}
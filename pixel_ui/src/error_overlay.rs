@@ -0,0 +1,164 @@
+use crate::config::{
+    BUTTON_COLOR, BUTTON_HEIGHT, BUTTON_PADDING, CONSOLE_LINE_HEIGHT, ERROR_PANEL_WIDTH,
+    TEXT_BACKGROUND_COLOR, TEXT_FONT_SIZE,
+};
+use lua_engine::error_log::{ErrorLog, LoggedError};
+use macroquad::prelude::*;
+
+const BADGE_COLOR: Color = Color::new(0.6, 0.1, 0.1, 0.9);
+
+/// Badge in the top right corner counting background script errors, expands into the error list
+pub struct ErrorOverlay {
+    error_log: ErrorLog,
+    expanded: bool,
+}
+
+impl ErrorOverlay {
+    pub(crate) fn new(error_log: ErrorLog) -> Self {
+        Self {
+            error_log,
+            expanded: false,
+        }
+    }
+
+    fn badge_text(&self) -> String {
+        match self.error_log.len() {
+            1 => "1 script error".to_string(),
+            count => format!("{} script errors", count),
+        }
+    }
+
+    fn badge_rect(&self) -> Rect {
+        let width = measure_text(&self.badge_text(), None, TEXT_FONT_SIZE as u16, 1.0).width
+            + BUTTON_PADDING * 2.0;
+        Rect::new(screen_width() - width - 20.0, 20.0, width, BUTTON_HEIGHT)
+    }
+
+    fn clear_button_rect(&self) -> Rect {
+        let badge = self.badge_rect();
+        Rect::new(badge.x - 80.0, badge.y, 70.0, BUTTON_HEIGHT)
+    }
+
+    // Lines of one error: what failed, where, and how often
+    fn error_lines(logged: &LoggedError) -> Vec<(String, Color)> {
+        let repeated = if logged.count > 1 {
+            format!(" (x{})", logged.count)
+        } else {
+            String::new()
+        };
+        let mut lines = vec![(format!("{}{}", logged.context, repeated), ORANGE)];
+        if let Some(location) = logged.error.location() {
+            lines.push((format!("  at {}", location), GRAY));
+        }
+        lines.push((format!("  {}", logged.error.message), WHITE));
+        lines
+    }
+
+    fn panel_lines(&self) -> Vec<(String, Color)> {
+        // Newest first
+        self.error_log
+            .errors()
+            .iter()
+            .rev()
+            .flat_map(Self::error_lines)
+            .collect()
+    }
+
+    fn panel_rect(&self, line_count: usize) -> Rect {
+        let badge = self.badge_rect();
+        let max_height = screen_height() - badge.bottom() - 140.0;
+        let height = (line_count as f32 * CONSOLE_LINE_HEIGHT + 10.0).min(max_height);
+        Rect::new(
+            screen_width() - ERROR_PANEL_WIDTH - 20.0,
+            badge.bottom() + 5.0,
+            ERROR_PANEL_WIDTH,
+            height,
+        )
+    }
+
+    /// What the overlay shows, None while there are no errors
+    pub(crate) fn describe(&self) -> Option<String> {
+        if self.error_log.is_empty() {
+            return None;
+        }
+        let state = if self.expanded {
+            "expanded"
+        } else {
+            "collapsed"
+        };
+        Some(format!("{} ({})", self.badge_text(), state))
+    }
+
+    pub(crate) fn captures_mouse(&self, screen_pos: Vec2) -> bool {
+        if self.error_log.is_empty() {
+            return false;
+        }
+        self.badge_rect().contains(screen_pos)
+            || (self.expanded
+                && (self.clear_button_rect().contains(screen_pos)
+                    || self
+                        .panel_rect(self.panel_lines().len())
+                        .contains(screen_pos)))
+    }
+
+    pub(crate) fn update(&mut self) {
+        if self.error_log.is_empty() {
+            self.expanded = false;
+            return;
+        }
+        if !is_mouse_button_pressed(MouseButton::Left) {
+            return;
+        }
+
+        let mouse = Vec2::from(mouse_position());
+        if self.badge_rect().contains(mouse) {
+            self.expanded = !self.expanded;
+        } else if self.expanded && self.clear_button_rect().contains(mouse) {
+            self.error_log.clear();
+        }
+    }
+
+    pub(crate) fn draw(&self) {
+        if self.error_log.is_empty() {
+            return;
+        }
+
+        let badge = self.badge_rect();
+        draw_rectangle(badge.x, badge.y, badge.w, badge.h, BADGE_COLOR);
+        draw_text(
+            &self.badge_text(),
+            badge.x + BUTTON_PADDING,
+            badge.y + (BUTTON_HEIGHT + TEXT_FONT_SIZE) / 2.0 - 4.0,
+            TEXT_FONT_SIZE,
+            WHITE,
+        );
+        if !self.expanded {
+            return;
+        }
+
+        let clear = self.clear_button_rect();
+        draw_rectangle(clear.x, clear.y, clear.w, clear.h, BUTTON_COLOR);
+        draw_rectangle_lines(clear.x, clear.y, clear.w, clear.h, 1.0, GRAY);
+        draw_text(
+            "Clear",
+            clear.x + BUTTON_PADDING,
+            clear.y + (BUTTON_HEIGHT + TEXT_FONT_SIZE) / 2.0 - 4.0,
+            TEXT_FONT_SIZE,
+            WHITE,
+        );
+
+        let lines = self.panel_lines();
+        let panel = self.panel_rect(lines.len());
+        draw_rectangle(panel.x, panel.y, panel.w, panel.h, TEXT_BACKGROUND_COLOR);
+        let visible_lines = (panel.h / CONSOLE_LINE_HEIGHT) as usize;
+        for (i, (line, color)) in lines.iter().take(visible_lines).enumerate() {
+            draw_text(
+                line,
+                panel.x + 10.0,
+                panel.y + (i + 1) as f32 * CONSOLE_LINE_HEIGHT,
+                TEXT_FONT_SIZE,
+                *color,
+            );
+        }
+    }
+}
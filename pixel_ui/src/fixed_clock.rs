@@ -0,0 +1,71 @@
+/// Turns the frame times into a whole number of fixed steps, so whatever is simulated step by step
+/// (people walking, idling and animating) runs the same at 30, 60 or 240 FPS. Time short of a step
+/// is carried over to the next frame.
+pub struct FixedClock {
+    step: f64,
+    max_steps: u32,
+    // Time not simulated yet, less than a step after every frame that wasn't cut short
+    accumulated: f64,
+}
+
+// Frame times summed up in floating point come out a hair short of the exact total, which would
+// leave a step for the next frame now and then
+const TOLERANCE: f64 = 1e-6;
+
+impl FixedClock {
+    /// Steps of `step` seconds, at most `max_steps` per frame. Time beyond them is dropped, so a
+    /// long hitch slows the simulation down instead of stalling the frames that follow with work.
+    pub(crate) fn new(step: f32, max_steps: u32) -> Self {
+        Self {
+            step: step as f64,
+            max_steps,
+            accumulated: 0.0,
+        }
+    }
+
+    /// Add the time of a frame, returns the number of steps to simulate for it
+    pub(crate) fn advance(&mut self, dt: f32) -> u32 {
+        self.accumulated += dt.max(0.0) as f64;
+        let steps = ((self.accumulated + self.step * TOLERANCE) / self.step).floor();
+        if steps > self.max_steps as f64 {
+            self.accumulated = 0.0;
+            return self.max_steps;
+        }
+        self.accumulated = (self.accumulated - steps * self.step).max(0.0);
+        steps as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Steps taken over `seconds` of frames at `fps`
+    fn steps_at(fps: u32, seconds: u32) -> u32 {
+        let mut clock = FixedClock::new(1.0 / 120.0, 8);
+        (0..fps * seconds)
+            .map(|_| clock.advance(1.0 / fps as f32))
+            .sum()
+    }
+
+    #[test]
+    fn test_steps_dont_depend_on_the_frame_rate() {
+        for fps in [30, 60, 144, 240] {
+            assert_eq!(steps_at(fps, 1), 120, "at {} FPS", fps);
+            assert_eq!(steps_at(fps, 10), 1200, "at {} FPS", fps);
+        }
+    }
+
+    #[test]
+    fn test_time_is_carried_over() {
+        let mut clock = FixedClock::new(0.1, 8);
+        assert_eq!(clock.advance(0.04), 0);
+        assert_eq!(clock.advance(0.04), 0);
+        assert_eq!(clock.advance(0.04), 1);
+        assert_eq!(clock.advance(0.18), 2);
+
+        // A hitch is cut down to the most steps a frame may take
+        assert_eq!(clock.advance(5.0), 8);
+        assert_eq!(clock.advance(0.1), 1);
+    }
+}
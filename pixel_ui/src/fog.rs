@@ -0,0 +1,51 @@
+use crate::config::{FOG_EXPLORED_COLOR, FOG_UNEXPLORED_COLOR, TILE_SIZE};
+use crate::MapBounds;
+use lua_engine::{Fog, Visibility, PLAYER_VIEWER};
+use macroquad::prelude::*;
+
+/// Fog of war of the player over the map: unexplored tiles are hidden, explored ones out of
+/// sight dimmed. There is no fog until the scripts reveal something with `api.fog.reveal`.
+pub struct FogLayer {
+    fog: Fog,
+}
+
+impl FogLayer {
+    pub(crate) fn new(fog: Fog) -> Self {
+        Self { fog }
+    }
+
+    // Returns the number of fogged tiles drawn
+    pub(crate) fn draw(&self, visible: Rect, bounds: &MapBounds) -> usize {
+        if !self.fog.has_viewer(PLAYER_VIEWER) {
+            return 0;
+        }
+        let min_x = ((visible.x / TILE_SIZE).floor() as i32).max(bounds.min_x);
+        let min_y = ((visible.y / TILE_SIZE).floor() as i32).max(bounds.min_y);
+        let max_x = ((visible.right() / TILE_SIZE).ceil() as i32).min(bounds.max_x);
+        let max_y = ((visible.bottom() / TILE_SIZE).ceil() as i32).min(bounds.max_y);
+        if max_x < min_x || max_y < min_y {
+            return 0;
+        }
+
+        let mut drawn = 0;
+        for (x, y, visibility) in self
+            .fog
+            .visibility_in(PLAYER_VIEWER, min_x, min_y, max_x, max_y)
+        {
+            let color = match visibility {
+                Visibility::Visible => continue,
+                Visibility::Explored => FOG_EXPLORED_COLOR,
+                Visibility::Unexplored => FOG_UNEXPLORED_COLOR,
+            };
+            draw_rectangle(
+                x as f32 * TILE_SIZE,
+                y as f32 * TILE_SIZE,
+                TILE_SIZE,
+                TILE_SIZE,
+                color,
+            );
+            drawn += 1;
+        }
+        drawn
+    }
+}
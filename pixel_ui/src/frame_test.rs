@@ -0,0 +1,184 @@
+//! Drives the game through its frame loop with scripted input, to catch what the unit tests cannot
+
+use crate::GameState;
+use lua_engine::actions::Action;
+use lua_engine::error_log::ErrorLog;
+use lua_engine::Lua;
+
+// Frames run unless `--frame-test` is given a number
+const DEFAULT_FRAMES: u64 = 300;
+// Frames the scripts get to set up their UI before the baseline is taken
+const WARMUP_FRAMES: u64 = 60;
+// Frames between the scripted actions
+const ACTION_INTERVAL: u64 = 10;
+// How much the Lua memory may grow over the baseline by the end of the run
+const MAX_MEMORY_GROWTH: f64 = 1.5;
+
+// Pressed in turn, the toggles twice so the run ends where it started
+const SCRIPTED_ACTIONS: [Action; 14] = [
+    Action::PanUp,
+    Action::PanLeft,
+    Action::ToggleDebug,
+    Action::ToggleDebug,
+    Action::PeopleTool,
+    Action::FitMap,
+    Action::ToggleLayers,
+    Action::ToggleLayers,
+    Action::BrushGrow,
+    Action::BrushShrink,
+    Action::ToggleGoals,
+    Action::ToggleGoals,
+    Action::PanDown,
+    Action::PanRight,
+];
+
+// What a healthy run keeps about the same from frame to frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Sample {
+    // Bytes in use by Lua after a full collection
+    lua_memory: usize,
+    // Top level components of the `ui` global
+    components: usize,
+    // Errors reported by scripts, repeats counted
+    errors: usize,
+}
+
+/// Run of `--frame-test [frames]`, built with the `frame-test` feature. Fails on panics in the
+/// frame loop, memory piling up and scripts adding UI components on every frame. Without a
+/// display it runs under a virtual one:
+///
+/// ```sh
+/// xvfb-run cargo run -p sb5s-pixel --features frame-test -- --frame-test 300
+/// ```
+pub(crate) struct FrameTest {
+    frames: u64,
+    frame: u64,
+    lua: Lua,
+    error_log: ErrorLog,
+    baseline: Option<Sample>,
+}
+
+impl FrameTest {
+    /// The test if the game was started with `--frame-test [frames]`
+    pub(crate) fn from_args(lua: Lua, error_log: ErrorLog) -> Option<Self> {
+        std::env::args()
+            .any(|arg| arg == "--frame-test")
+            .then(|| FrameTest {
+                frames: crate::arg_value("--frame-test")
+                    .and_then(|frames| frames.parse().ok())
+                    .unwrap_or(DEFAULT_FRAMES),
+                frame: 0,
+                lua,
+                error_log,
+                baseline: None,
+            })
+    }
+
+    /// Called after every frame, returns the outcome of the test once it ran all its frames
+    pub(crate) fn after_frame(&mut self, game: &GameState) -> Option<Result<String, String>> {
+        self.frame += 1;
+        if self.frame.is_multiple_of(ACTION_INTERVAL) {
+            let action =
+                SCRIPTED_ACTIONS[(self.frame / ACTION_INTERVAL) as usize % SCRIPTED_ACTIONS.len()];
+            game.input.lock().unwrap().trigger(action);
+        }
+        if self.frame == WARMUP_FRAMES.min(self.frames) {
+            self.baseline = Some(self.sample(game));
+        }
+        if self.frame < self.frames {
+            return None;
+        }
+        let baseline = self.baseline.unwrap_or_else(|| self.sample(game));
+        Some(check(self.frames, baseline, self.sample(game)))
+    }
+
+    fn sample(&self, game: &GameState) -> Sample {
+        if let Err(e) = self.lua.gc_collect() {
+            println!("Frame test: Lua garbage collection failed: {}", e);
+        }
+        Sample {
+            lua_memory: self.lua.used_memory(),
+            components: game.lua_ui.component_count(),
+            errors: self
+                .error_log
+                .errors()
+                .iter()
+                .map(|logged| logged.count)
+                .sum(),
+        }
+    }
+}
+
+// The outcome of the run as the line to print, failed if the end differs from the baseline by
+// more than a healthy run would
+fn check(frames: u64, baseline: Sample, end: Sample) -> Result<String, String> {
+    let mut problems = Vec::new();
+    if end.lua_memory as f64 > baseline.lua_memory as f64 * MAX_MEMORY_GROWTH {
+        problems.push(format!(
+            "Lua memory grew from {} to {} bytes",
+            baseline.lua_memory, end.lua_memory
+        ));
+    }
+    if end.components != baseline.components {
+        problems.push(format!(
+            "UI components went from {} to {}",
+            baseline.components, end.components
+        ));
+    }
+    if end.errors > baseline.errors {
+        problems.push(format!(
+            "{} script errors were reported",
+            end.errors - baseline.errors
+        ));
+    }
+    if problems.is_empty() {
+        Ok(format!(
+            "Frame test passed after {} frames: {} UI components, {} bytes of Lua memory",
+            frames, end.components, end.lua_memory
+        ))
+    } else {
+        Err(format!(
+            "Frame test failed after {} frames: {}",
+            frames,
+            problems.join(", ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runs_piling_up_components_or_memory_fail() {
+        let baseline = Sample {
+            lua_memory: 1000,
+            components: 12,
+            errors: 1,
+        };
+        assert!(check(
+            300,
+            baseline,
+            Sample {
+                lua_memory: 1400,
+                ..baseline
+            }
+        )
+        .is_ok());
+
+        let error = check(
+            300,
+            baseline,
+            Sample {
+                lua_memory: 2000,
+                components: 40,
+                errors: 3,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            "Frame test failed after 300 frames: Lua memory grew from 1000 to 2000 bytes, UI components went from 12 to 40, 2 script errors were reported"
+        );
+    }
+}
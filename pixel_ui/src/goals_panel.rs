@@ -0,0 +1,87 @@
+use crate::config::{GOALS_PANEL_ROW_HEIGHT, GOALS_PANEL_WIDTH};
+use lua_engine::goals::Goals;
+use macroquad::hash;
+use macroquad::prelude::*;
+use macroquad::ui::root_ui;
+use macroquad::ui::widgets;
+
+// Characters of the text progress bars
+const BAR_LENGTH: usize = 20;
+
+/// Panel listing the goals of the mods with their progress, shown once there are goals
+pub struct GoalsPanel {
+    goals: Goals,
+    pub(crate) visible: bool,
+}
+
+impl GoalsPanel {
+    pub(crate) fn new(goals: Goals) -> Self {
+        Self {
+            goals,
+            visible: true,
+        }
+    }
+
+    pub(crate) fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub(crate) fn captures_mouse(&self, screen_pos: Vec2) -> bool {
+        self.visible && root_ui().is_mouse_over(screen_pos)
+    }
+
+    /// Where the panel is drawn unless it was dragged away, None while it isn't shown
+    pub(crate) fn rect(&self) -> Option<Rect> {
+        let count = self.goals.all().len();
+        (self.visible && count > 0).then(|| Self::panel_rect(count))
+    }
+
+    fn panel_rect(count: usize) -> Rect {
+        let height = 40.0 + count as f32 * GOALS_PANEL_ROW_HEIGHT;
+        Rect::new(20.0, 400.0, GOALS_PANEL_WIDTH, height)
+    }
+
+    // Progress as a bar of `#` and `-`, full once the goal is complete
+    fn progress_bar(progress: f64, target: f64) -> String {
+        let ratio = if target > 0.0 {
+            (progress / target).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let filled = (ratio * BAR_LENGTH as f64).round() as usize;
+        format!(
+            "[{}{}]",
+            "#".repeat(filled),
+            "-".repeat(BAR_LENGTH - filled)
+        )
+    }
+
+    pub(crate) fn draw(&self) {
+        if !self.visible {
+            return;
+        }
+        let goals = self.goals.all();
+        if goals.is_empty() {
+            return;
+        }
+
+        let rect = Self::panel_rect(goals.len());
+        widgets::Window::new(hash!(), rect.point(), rect.size())
+            .label("Goals (G to hide)")
+            .ui(&mut root_ui(), |ui| {
+                for goal in &goals {
+                    let marker = if goal.completed { "v" } else { " " };
+                    ui.label(None, &format!("{} {}", marker, goal.name));
+                    ui.label(
+                        None,
+                        &format!(
+                            "{} {}/{}",
+                            Self::progress_bar(goal.progress, goal.target),
+                            goal.progress,
+                            goal.target
+                        ),
+                    );
+                }
+            });
+    }
+}
@@ -0,0 +1,107 @@
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Corner or edge of the screen a HUD element sticks to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    // Elements at the bottom stack upwards, all others downwards
+    fn stacks_up(self) -> bool {
+        matches!(
+            self,
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight
+        )
+    }
+}
+
+/// Places the HUD elements of a frame next to their anchors. An element that would overlap one
+/// placed before it, at its own or another anchor, moves away from its edge until it's clear, so
+/// the HUD stacks up instead of piling onto itself on small screens.
+pub struct HudLayout {
+    screen: Rect,
+    margin: f32,
+    placed: Vec<Rect>,
+}
+
+impl HudLayout {
+    /// Layout for the screen area, keeping `margin` pixels to the edges and between elements
+    pub fn new(screen: Rect, margin: f32) -> Self {
+        Self {
+            screen,
+            margin,
+            placed: Vec::new(),
+        }
+    }
+
+    /// Widest an element can be while keeping the margins
+    pub fn max_width(&self) -> f32 {
+        self.screen.w - self.margin * 2.0
+    }
+
+    /// Where the element of `size` goes, the space is taken for the rest of the frame
+    pub fn place(&mut self, anchor: Anchor, size: Vec2) -> Rect {
+        let screen = self.screen;
+        let x = match anchor {
+            Anchor::TopLeft | Anchor::Left | Anchor::BottomLeft => screen.x + self.margin,
+            Anchor::Top | Anchor::Bottom => screen.center().x - size.x / 2.0,
+            Anchor::TopRight | Anchor::Right | Anchor::BottomRight => {
+                screen.right() - self.margin - size.x
+            }
+        };
+        let y = match anchor {
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => screen.y + self.margin,
+            Anchor::Left | Anchor::Right => screen.center().y - size.y / 2.0,
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => {
+                screen.bottom() - self.margin - size.y
+            }
+        };
+        let mut rect = Rect::new(x, y, size.x, size.y);
+        // Every move clears one of the placed elements for good, it only goes further away
+        for _ in 0..self.placed.len() {
+            let Some(other) = self.placed.iter().find(|other| other.overlaps(&rect)) else {
+                break;
+            };
+            rect.y = if anchor.stacks_up() {
+                other.y - self.margin - rect.h
+            } else {
+                other.bottom() + self.margin
+            };
+        }
+        self.placed.push(rect);
+        rect
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elements_stack_away_from_their_anchor() {
+        let mut layout = HudLayout::new(Rect::new(0.0, 0.0, 1280.0, 720.0), 10.0);
+        let first = layout.place(Anchor::BottomLeft, Vec2::new(1200.0, 40.0));
+        assert_eq!(first, Rect::new(10.0, 670.0, 1200.0, 40.0));
+        let second = layout.place(Anchor::BottomLeft, Vec2::new(300.0, 30.0));
+        assert_eq!(second, Rect::new(10.0, 630.0, 300.0, 30.0));
+        // Too wide to pass the bottom left line, so it goes above both
+        let right = layout.place(Anchor::BottomRight, Vec2::new(200.0, 30.0));
+        assert_eq!(right, Rect::new(1070.0, 630.0, 200.0, 30.0));
+
+        let top = layout.place(Anchor::TopRight, Vec2::new(256.0, 256.0));
+        assert_eq!(top, Rect::new(1014.0, 10.0, 256.0, 256.0));
+        let below = layout.place(Anchor::TopRight, Vec2::new(280.0, 100.0));
+        assert_eq!(below, Rect::new(990.0, 276.0, 280.0, 100.0));
+        assert_eq!(layout.max_width(), 1260.0);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::camera::CameraController;
+use crate::config::{INDICATOR_MARGIN, INDICATOR_SIZE, TEXT_FONT_SIZE, TILE_SIZE};
+use crate::people::{People, PersonId};
+use macroquad::prelude::*;
+use std::collections::HashSet;
+
+/// Arrow at the screen edge pointing to a tracked person that is currently off-screen
+pub struct Indicator {
+    pub(crate) person: PersonId,
+    pub(crate) screen_pos: Vec2,
+    direction: Vec2,
+    distance_tiles: f32,
+}
+
+/// Settings for the off-screen person indicators, shared with Lua
+pub struct OffscreenIndicators {
+    pub(crate) enabled: bool,
+    pub(crate) tracked: HashSet<PersonId>,
+    pub(crate) color: Color,
+}
+
+impl OffscreenIndicators {
+    pub(crate) fn new() -> Self {
+        Self {
+            enabled: true,
+            tracked: HashSet::new(),
+            color: YELLOW,
+        }
+    }
+
+    pub(crate) fn toggle_tracked(&mut self, person: PersonId) {
+        if !self.tracked.remove(&person) {
+            self.tracked.insert(person);
+        }
+    }
+
+    /// Indicators for all tracked (plus `extra`, e.g. followed) people that are outside of the view
+    pub(crate) fn collect(
+        &self,
+        camera: &CameraController,
+        people: &People,
+        extra: impl IntoIterator<Item = PersonId>,
+    ) -> Vec<Indicator> {
+        if !self.enabled {
+            return Vec::new();
+        }
+
+        let mut persons: Vec<PersonId> = self.tracked.iter().copied().chain(extra).collect();
+        persons.sort_unstable();
+        persons.dedup();
+
+        let screen_center = Vec2::new(screen_width(), screen_height()) / 2.0;
+        let camera_center = camera.screen_to_world(screen_center);
+        let half_extent = screen_center - Vec2::splat(INDICATOR_MARGIN);
+
+        persons
+            .into_iter()
+            .filter_map(|id| {
+                let position = people.position(id)?;
+                let offset = camera.world_to_screen(position) - screen_center;
+                if offset.x.abs() <= screen_center.x && offset.y.abs() <= screen_center.y {
+                    return None;
+                }
+
+                // Scale the offset down until it touches the inset screen rectangle
+                let scale = (half_extent.x / offset.x.abs()).min(half_extent.y / offset.y.abs());
+                Some(Indicator {
+                    person: id,
+                    screen_pos: screen_center + offset * scale,
+                    direction: offset.normalize_or_zero(),
+                    distance_tiles: camera_center.distance(position) / TILE_SIZE,
+                })
+            })
+            .collect()
+    }
+
+    pub(crate) fn draw(&self, indicators: &[Indicator]) {
+        for indicator in indicators {
+            let tip = indicator.screen_pos + indicator.direction * INDICATOR_SIZE;
+            let side = indicator.direction.perp() * INDICATOR_SIZE / 2.0;
+            draw_triangle(
+                tip,
+                indicator.screen_pos + side,
+                indicator.screen_pos - side,
+                self.color,
+            );
+
+            let text = format!("{:.0}", indicator.distance_tiles);
+            let text_size = measure_text(&text, None, TEXT_FONT_SIZE as u16, 1.0);
+            let text_pos = indicator.screen_pos - indicator.direction * INDICATOR_SIZE;
+            draw_text(
+                &text,
+                text_pos.x - text_size.width / 2.0,
+                text_pos.y + text_size.height / 2.0,
+                TEXT_FONT_SIZE,
+                self.color,
+            );
+        }
+    }
+}
+
+impl Indicator {
+    pub(crate) fn contains(&self, screen_pos: Vec2) -> bool {
+        self.screen_pos.distance(screen_pos) <= INDICATOR_SIZE * 1.5
+    }
+}
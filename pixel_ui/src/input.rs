@@ -1,7 +1,302 @@
-use crate::config::DRAG_THRESHOLD;
+use crate::config::{DOUBLE_CLICK_DISTANCE, DOUBLE_CLICK_TIME, DRAG_THRESHOLD, LONG_PRESS_TIME};
 use crate::TilePosition;
 
+use lua_engine::actions::{Action, Chord, KeyBindings, KEY_BINDINGS_PATH};
 use macroquad::prelude::*;
+use std::collections::HashSet;
+
+/// Input event recorded during `InputManager::update`, consumed by the Lua event dispatcher
+#[derive(Debug, Clone)]
+pub enum InputEvent {
+    KeyPressed(KeyCode),
+    KeyReleased(KeyCode),
+    /// Mouse click in screen coordinates (left clicks are only reported when not dragging)
+    Clicked {
+        button: MouseButton,
+        position: Vec2,
+    },
+    DoubleClicked {
+        position: Vec2,
+    },
+    /// Left button held in place for the long press time, the following release is not a click
+    LongPressed {
+        position: Vec2,
+    },
+}
+
+/// Name used for a key in Lua scripts, e.g. "a", "space" or "leftcontrol"
+pub(crate) fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key).to_lowercase()
+}
+
+pub(crate) fn mouse_button_name(button: MouseButton) -> &'static str {
+    match button {
+        MouseButton::Left => "left",
+        MouseButton::Right => "right",
+        MouseButton::Middle => "middle",
+        MouseButton::Unknown => "unknown",
+    }
+}
+
+// Keys that can be used in shortcuts, named like in `key_name`
+const SHORTCUT_KEYS: [KeyCode; 71] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::Space,
+    KeyCode::Enter,
+    KeyCode::Escape,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::LeftBracket,
+    KeyCode::RightBracket,
+    KeyCode::GraveAccent,
+    KeyCode::Minus,
+    KeyCode::Equal,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Slash,
+];
+
+fn is_ctrl_down() -> bool {
+    is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl)
+}
+
+fn is_shift_down() -> bool {
+    is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift)
+}
+
+fn is_alt_down() -> bool {
+    is_key_down(KeyCode::LeftAlt) || is_key_down(KeyCode::RightAlt)
+}
+
+/// Key chord like "ctrl+shift+p", modifiers have to match exactly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shortcut {
+    key: KeyCode,
+    ctrl: bool,
+    shift: bool,
+    alt: bool,
+}
+
+impl Shortcut {
+    pub(crate) fn parse(spec: &str) -> Result<Self, String> {
+        Self::from_chord(&Chord::parse(spec)?)
+    }
+
+    pub(crate) fn from_chord(chord: &Chord) -> Result<Self, String> {
+        // Digits can be written without the "key" prefix
+        let key_name_candidates = [chord.key.clone(), format!("key{}", chord.key)];
+        let key = SHORTCUT_KEYS
+            .into_iter()
+            .find(|&code| key_name_candidates.contains(&key_name(code)))
+            .ok_or_else(|| format!("Unknown key '{}' in '{}'", chord.key, chord))?;
+        Ok(Shortcut {
+            key,
+            ctrl: chord.ctrl,
+            shift: chord.shift,
+            alt: chord.alt,
+        })
+    }
+
+    pub(crate) fn is_pressed(&self) -> bool {
+        is_key_pressed(self.key)
+            && is_ctrl_down() == self.ctrl
+            && is_shift_down() == self.shift
+            && is_alt_down() == self.alt
+    }
+
+    /// Whether this action binding fires on the exact chord `press`, actions don't care about shift unless they ask for it
+    pub(crate) fn builtin_fires_on(&self, press: &Shortcut) -> bool {
+        self.key == press.key
+            && self.ctrl == press.ctrl
+            && self.alt == press.alt
+            && (self.shift == press.shift || !self.shift)
+    }
+
+    // Pressed as an action binding, Ctrl and Alt chords are left to scripts unless asked for
+    fn action_pressed(&self) -> bool {
+        is_key_pressed(self.key)
+            && is_ctrl_down() == self.ctrl
+            && is_alt_down() == self.alt
+            && (is_shift_down() || !self.shift)
+    }
+
+    // Held with at least the modifiers it asks for
+    fn is_held(&self) -> bool {
+        is_key_down(self.key)
+            && (is_ctrl_down() || !self.ctrl)
+            && (is_shift_down() || !self.shift)
+            && (is_alt_down() || !self.alt)
+    }
+}
+
+// An action binding resolved to the devices of macroquad
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Binding {
+    Key(Shortcut),
+    Mouse(MouseButton),
+}
+
+fn mouse_button_from_name(name: &str) -> Option<MouseButton> {
+    [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+        .into_iter()
+        .find(|&button| mouse_button_name(button) == name)
+}
+
+fn resolve(chord: &Chord) -> Result<Binding, String> {
+    match chord.mouse_button() {
+        Some(name) => mouse_button_from_name(name)
+            .map(Binding::Mouse)
+            .ok_or_else(|| format!("Unknown mouse button '{}' in '{}'", name, chord)),
+        None => Shortcut::from_chord(chord).map(Binding::Key),
+    }
+}
+
+/// Change of a two finger touch gesture since the last frame
+#[derive(Debug, Clone, Copy)]
+pub struct Pinch {
+    /// Ratio of the current and previous finger distance
+    pub(crate) scale: f32,
+    /// Midpoint between the fingers in screen coordinates
+    pub(crate) center: Vec2,
+    /// Movement of the midpoint
+    pub(crate) pan: Vec2,
+}
+
+/// The left mouse button in a frame, as much of it as the gestures go by
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct LeftButton {
+    pub(crate) pressed: bool,
+    pub(crate) down: bool,
+    pub(crate) released: bool,
+    /// The press moved past the drag threshold or turned into a pinch
+    pub(crate) moved: bool,
+    /// No UI element has the mouse
+    pub(crate) in_world: bool,
+    pub(crate) position: Vec2,
+}
+
+/// What the left button did in a frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Gesture {
+    /// Released where it was pressed, not after a long press
+    pub(crate) clicked: bool,
+    pub(crate) double_clicked: bool,
+    pub(crate) long_pressed: bool,
+}
+
+/// Double click and long press detection, thresholds are in seconds
+pub(crate) struct Gestures {
+    pub(crate) double_click_time: f32,
+    pub(crate) long_press_time: f32,
+    press_time: f64,
+    last_click: Option<(f64, Vec2)>,
+    long_press_fired: bool,
+}
+
+impl Gestures {
+    fn new() -> Self {
+        Self {
+            double_click_time: DOUBLE_CLICK_TIME,
+            long_press_time: LONG_PRESS_TIME,
+            press_time: 0.0,
+            last_click: None,
+            long_press_fired: false,
+        }
+    }
+
+    /// Decide what the button did in the frame at `now` seconds
+    pub(crate) fn update(&mut self, now: f64, button: LeftButton) -> Gesture {
+        let mut gesture = Gesture::default();
+        if button.pressed {
+            self.press_time = now;
+            self.long_press_fired = false;
+        }
+
+        if button.down
+            && !button.moved
+            && !self.long_press_fired
+            && button.in_world
+            && now - self.press_time >= self.long_press_time as f64
+        {
+            gesture.long_pressed = true;
+            self.long_press_fired = true;
+        }
+
+        gesture.clicked =
+            button.released && !button.moved && !self.long_press_fired && button.in_world;
+        if gesture.clicked {
+            match self.last_click {
+                Some((time, position))
+                    if now - time <= self.double_click_time as f64
+                        && position.distance(button.position) <= DOUBLE_CLICK_DISTANCE =>
+                {
+                    gesture.double_clicked = true;
+                    // A third click starts a new double click
+                    self.last_click = None;
+                }
+                _ => self.last_click = Some((now, button.position)),
+            }
+        }
+        gesture
+    }
+}
 
 pub struct InputManager {
     mouse_position: Vec2,
@@ -10,7 +305,23 @@ pub struct InputManager {
     drag_start_position: Vec2,
     mouse_moved_during_click: bool,
     zoom_delta: Option<f32>,
-    last_painted_pos: Option<TilePosition>,
+    painted_this_stroke: HashSet<TilePosition>,
+    events: Vec<InputEvent>,
+    // The cursor is over a UI element this frame
+    mouse_captured: bool,
+    // The current mouse press started over a UI element, the world ignores it until release
+    press_captured: bool,
+    pub(crate) gestures: Gestures,
+    // What the left button did this frame
+    gesture: Gesture,
+    // Finger distance and midpoint of the previous frame while two fingers are down
+    prev_pinch: Option<(f32, Vec2)>,
+    pinch: Option<Pinch>,
+    bindings: KeyBindings,
+    resolved: Vec<(Action, Binding)>,
+    // Actions pressed this frame, and the ones triggered by scripts for the next frame
+    actions: Vec<Action>,
+    triggered: Vec<Action>,
 }
 
 impl InputManager {
@@ -23,11 +334,76 @@ impl InputManager {
             drag_start_position: initial_pos,
             mouse_moved_during_click: false,
             zoom_delta: None,
-            last_painted_pos: None,
+            painted_this_stroke: HashSet::new(),
+            events: Vec::new(),
+            mouse_captured: false,
+            press_captured: false,
+            gestures: Gestures::new(),
+            gesture: Gesture::default(),
+            prev_pinch: None,
+            pinch: None,
+            bindings: KeyBindings::default(),
+            resolved: Vec::new(),
+            actions: Vec::new(),
+            triggered: Vec::new(),
         }
+        .with_bindings(KeyBindings::load(KEY_BINDINGS_PATH))
+    }
+
+    // Chords this frontend doesn't know are left out
+    fn with_bindings(mut self, bindings: KeyBindings) -> Self {
+        for (action, chords) in bindings.iter() {
+            for chord in chords {
+                match resolve(chord) {
+                    Ok(binding) => self.resolved.push((action, binding)),
+                    Err(e) => println!("Ignoring the binding of {}: {}", action, e),
+                }
+            }
+        }
+        self.bindings = bindings;
+        self
+    }
+
+    pub(crate) fn bindings(&self) -> &KeyBindings {
+        &self.bindings
+    }
+
+    /// Replace the chords of the action, fails without changing anything when one isn't known
+    pub(crate) fn rebind(&mut self, action: Action, chords: Vec<Chord>) -> Result<(), String> {
+        let bindings = chords.iter().map(resolve).collect::<Result<Vec<_>, _>>()?;
+        self.resolved.retain(|(bound, _)| *bound != action);
+        self.resolved
+            .extend(bindings.into_iter().map(|binding| (action, binding)));
+        self.bindings.bind(action, chords);
+        Ok(())
+    }
+
+    /// Key bindings of all actions, for telling scripts which shortcuts are taken
+    pub(crate) fn key_bindings(&self) -> impl Iterator<Item = (Action, Shortcut)> + '_ {
+        self.resolved
+            .iter()
+            .filter_map(|(action, binding)| match binding {
+                Binding::Key(shortcut) => Some((*action, *shortcut)),
+                Binding::Mouse(_) => None,
+            })
     }
 
-    pub(crate) fn update(&mut self) {
+    /// `mouse_over_ui` tells whether a UI element sits under the cursor and takes precedence over the world
+    pub(crate) fn update(&mut self, mouse_over_ui: bool) {
+        let any_button_active = [MouseButton::Left, MouseButton::Right]
+            .into_iter()
+            .any(|button| is_mouse_button_down(button) || is_mouse_button_released(button));
+        if !any_button_active {
+            self.press_captured = false;
+        }
+        if mouse_over_ui
+            && (is_mouse_button_pressed(MouseButton::Left)
+                || is_mouse_button_pressed(MouseButton::Right))
+        {
+            self.press_captured = true;
+        }
+        self.mouse_captured = mouse_over_ui;
+
         self.prev_mouse_position = self.mouse_position;
         self.mouse_position = Vec2::new(mouse_position().0, mouse_position().1);
         self.zoom_delta = if mouse_wheel().1 != 0.0 {
@@ -58,65 +434,355 @@ impl InputManager {
             // If left button is not down, we're not dragging
             self.is_dragging = false;
         }
+
+        self.update_touches();
+        self.detect_gestures();
+        self.record_events();
+        self.record_actions();
+
+        // A painting stroke ends when painting is let go
+        if !self.action_down(Action::Paint) {
+            self.painted_this_stroke.clear();
+        }
+    }
+
+    // Single finger touches arrive as emulated left mouse button (drag pans, tap selects),
+    // only the two finger pinch needs handling here
+    fn update_touches(&mut self) {
+        let mut touches = touches();
+        touches.retain(|touch| !matches!(touch.phase, TouchPhase::Ended | TouchPhase::Cancelled));
+        touches.sort_by_key(|touch| touch.id);
+
+        self.pinch = None;
+        if touches.len() < 2 {
+            self.prev_pinch = None;
+            return;
+        }
+
+        let (a, b) = (touches[0].position, touches[1].position);
+        let distance = a.distance(b).max(1.0);
+        let center = (a + b) / 2.0;
+        if let Some((prev_distance, prev_center)) = self.prev_pinch {
+            self.pinch = Some(Pinch {
+                scale: distance / prev_distance,
+                center,
+                pan: center - prev_center,
+            });
+        }
+        self.prev_pinch = Some((distance, center));
+
+        // The emulated mouse must not pan or select while pinching
+        self.is_dragging = false;
+        self.mouse_moved_during_click = true;
+    }
+
+    fn detect_gestures(&mut self) {
+        let button = LeftButton {
+            pressed: is_mouse_button_pressed(MouseButton::Left),
+            down: is_mouse_button_down(MouseButton::Left),
+            released: is_mouse_button_released(MouseButton::Left),
+            moved: self.mouse_moved_during_click,
+            in_world: self.world_has_mouse(),
+            position: self.mouse_position,
+        };
+        self.gesture = self.gestures.update(get_time(), button);
+    }
+
+    fn record_events(&mut self) {
+        self.events.clear();
+        for key in get_keys_pressed() {
+            self.events.push(InputEvent::KeyPressed(key));
+        }
+        for key in get_keys_released() {
+            self.events.push(InputEvent::KeyReleased(key));
+        }
+        if self.should_select_tile() {
+            self.events.push(InputEvent::Clicked {
+                button: MouseButton::Left,
+                position: self.mouse_position,
+            });
+        }
+        if self.gesture.double_clicked {
+            self.events.push(InputEvent::DoubleClicked {
+                position: self.mouse_position,
+            });
+        }
+        if self.gesture.long_pressed {
+            self.events.push(InputEvent::LongPressed {
+                position: self.mouse_position,
+            });
+        }
+        if self.world_button_pressed(MouseButton::Right) {
+            self.events.push(InputEvent::Clicked {
+                button: MouseButton::Right,
+                position: self.mouse_position,
+            });
+        }
+    }
+
+    fn record_actions(&mut self) {
+        let mut actions = std::mem::take(&mut self.triggered);
+        for (action, binding) in &self.resolved {
+            let pressed = match *binding {
+                Binding::Key(shortcut) => shortcut.action_pressed(),
+                Binding::Mouse(button) => self.is_clicked(button),
+            };
+            if pressed && !actions.contains(action) {
+                actions.push(*action);
+            }
+        }
+        self.actions = actions;
+    }
+
+    // Left clicks are releases that didn't drag, other buttons click when pressed
+    fn is_clicked(&self, button: MouseButton) -> bool {
+        match button {
+            MouseButton::Left => self.should_select_tile(),
+            _ => self.world_button_pressed(button),
+        }
+    }
+
+    /// Actions pressed in the last update, in the order of the bindings
+    pub(crate) fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    pub(crate) fn action_pressed(&self, action: Action) -> bool {
+        self.actions.contains(&action)
+    }
+
+    /// Pressed this frame or held since, mouse buttons only count over the world
+    pub(crate) fn action_down(&self, action: Action) -> bool {
+        self.action_pressed(action)
+            || self
+                .resolved
+                .iter()
+                .filter(|(bound, _)| *bound == action)
+                .any(|(_, binding)| match *binding {
+                    Binding::Key(shortcut) => shortcut.is_held(),
+                    Binding::Mouse(button) => self.world_button_down(button),
+                })
+    }
+
+    /// Press the action in the next update as if its binding was pressed
+    pub(crate) fn trigger(&mut self, action: Action) {
+        self.triggered.push(action);
+    }
+
+    /// Events recorded in the last update, each event is handed out only once
+    pub(crate) fn take_events(&mut self) -> Vec<InputEvent> {
+        std::mem::take(&mut self.events)
     }
 
     pub(crate) fn is_direction_pressed(&self) -> bool {
-        is_key_down(KeyCode::W)
-            || is_key_down(KeyCode::Up)
-            || is_key_down(KeyCode::S)
-            || is_key_down(KeyCode::Down)
-            || is_key_down(KeyCode::A)
-            || is_key_down(KeyCode::Left)
-            || is_key_down(KeyCode::D)
-            || is_key_down(KeyCode::Right)
+        self.is_up_pressed()
+            || self.is_down_pressed()
+            || self.is_left_pressed()
+            || self.is_right_pressed()
     }
 
     pub(crate) fn is_up_pressed(&self) -> bool {
-        is_key_down(KeyCode::W) || is_key_down(KeyCode::Up)
+        self.action_down(Action::PanUp)
     }
 
     pub(crate) fn is_down_pressed(&self) -> bool {
-        is_key_down(KeyCode::S) || is_key_down(KeyCode::Down)
+        self.action_down(Action::PanDown)
     }
 
     pub(crate) fn is_left_pressed(&self) -> bool {
-        is_key_down(KeyCode::A) || is_key_down(KeyCode::Left)
+        self.action_down(Action::PanLeft)
     }
 
     pub(crate) fn is_right_pressed(&self) -> bool {
-        is_key_down(KeyCode::D) || is_key_down(KeyCode::Right)
+        self.action_down(Action::PanRight)
+    }
+
+    // Whether world tools may react to the mouse
+    pub(crate) fn world_has_mouse(&self) -> bool {
+        !self.mouse_captured && !self.press_captured
     }
 
     pub(crate) fn should_select_tile(&self) -> bool {
-        is_mouse_button_released(MouseButton::Left) && !self.mouse_moved_during_click
+        self.gesture.clicked
     }
 
-    pub(crate) fn should_place_tile(&self, selected_pos: Option<&TilePosition>) -> bool {
-        is_mouse_button_down(MouseButton::Right) && selected_pos.is_some()
+    pub(crate) fn is_double_clicked(&self) -> bool {
+        self.gesture.double_clicked
+    }
+
+    pub(crate) fn should_paint(&self) -> bool {
+        self.action_down(Action::Paint)
+    }
+
+    pub(crate) fn world_button_pressed(&self, button: MouseButton) -> bool {
+        is_mouse_button_pressed(button) && self.world_has_mouse()
+    }
+
+    pub(crate) fn world_button_down(&self, button: MouseButton) -> bool {
+        is_mouse_button_down(button) && self.world_has_mouse()
     }
 
     pub(crate) fn get_drag_delta(&self) -> Option<Vec2> {
-        if self.is_dragging {
+        // Panning continues over UI elements as long as it didn't start on one
+        if self.is_dragging && !self.press_captured {
             Some(self.mouse_position - self.prev_mouse_position)
         } else {
             None
         }
     }
 
+    pub(crate) fn get_pinch(&self) -> Option<Pinch> {
+        self.pinch
+    }
+
     pub(crate) fn get_zoom_delta(&self) -> Option<f32> {
-        self.zoom_delta
+        self.zoom_delta.filter(|_| !self.mouse_captured)
     }
 
     pub(crate) fn get_mouse_position(&self) -> Vec2 {
         self.mouse_position
     }
 
+    // Each position is painted at most once per stroke
     pub(crate) fn can_place_at(&mut self, pos: TilePosition) -> bool {
-        if self.last_painted_pos != Some(pos) {
-            self.last_painted_pos = Some(pos);
-            true
-        } else {
-            false
+        self.painted_this_stroke.insert(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The frames of pressing the left button at `at` and letting go `held` seconds later
+    fn click(gestures: &mut Gestures, at: f64, held: f64, position: Vec2) -> Vec<Gesture> {
+        let button = LeftButton {
+            in_world: true,
+            position,
+            ..LeftButton::default()
+        };
+        let frames = [
+            (
+                at,
+                LeftButton {
+                    pressed: true,
+                    down: true,
+                    ..button
+                },
+            ),
+            (
+                at + held,
+                LeftButton {
+                    down: true,
+                    ..button
+                },
+            ),
+            (
+                at + held,
+                LeftButton {
+                    released: true,
+                    ..button
+                },
+            ),
+        ];
+        frames
+            .into_iter()
+            .map(|(now, button)| gestures.update(now, button))
+            .collect()
+    }
+
+    #[test]
+    fn test_double_and_triple_clicks() {
+        let mut gestures = Gestures::new();
+        let at = Vec2::new(100.0, 100.0);
+        let first = click(&mut gestures, 1.0, 0.05, at);
+        assert!(first[2].clicked && !first[2].double_clicked);
+        let second = click(&mut gestures, 1.2, 0.05, at + Vec2::new(3.0, 0.0));
+        assert!(second[2].clicked && second[2].double_clicked);
+
+        // The third click starts over, the fourth completes another double click
+        let third = click(&mut gestures, 1.4, 0.05, at);
+        assert!(third[2].clicked && !third[2].double_clicked);
+        assert!(click(&mut gestures, 1.6, 0.05, at)[2].double_clicked);
+
+        // Too late for the click before
+        click(&mut gestures, 3.0, 0.05, at);
+        assert!(!click(&mut gestures, 3.5, 0.05, at)[2].double_clicked);
+    }
+
+    #[test]
+    fn test_clicks_apart_are_no_double_click() {
+        let mut gestures = Gestures::new();
+        let at = Vec2::new(100.0, 100.0);
+        click(&mut gestures, 1.0, 0.05, at);
+        let away = at + Vec2::new(DOUBLE_CLICK_DISTANCE + 1.0, 0.0);
+        let second = click(&mut gestures, 1.2, 0.05, away);
+        assert!(second[2].clicked && !second[2].double_clicked);
+    }
+
+    #[test]
+    fn test_long_press_swallows_the_release() {
+        let mut gestures = Gestures::new();
+        let frames = click(&mut gestures, 1.0, LONG_PRESS_TIME as f64 + 0.1, Vec2::ZERO);
+        assert_eq!(
+            frames,
+            vec![
+                Gesture::default(),
+                Gesture {
+                    long_pressed: true,
+                    ..Gesture::default()
+                },
+                Gesture::default(),
+            ]
+        );
+        // A short press after it is a click again, without firing the long press anew
+        let next = click(&mut gestures, 3.0, 0.05, Vec2::ZERO);
+        assert!(next[2].clicked && next.iter().all(|gesture| !gesture.long_pressed));
+    }
+
+    #[test]
+    fn test_parse_shortcut() {
+        let shortcut = Shortcut::parse("Ctrl+Shift+P").unwrap();
+
+        assert_eq!(shortcut.key, KeyCode::P);
+        assert!(shortcut.ctrl);
+        assert!(shortcut.shift);
+        assert!(!shortcut.alt);
+        assert_eq!(Shortcut::parse("alt+1").unwrap().key, KeyCode::Key1);
+    }
+
+    #[test]
+    fn test_parse_invalid_shortcut() {
+        assert!(Shortcut::parse("").is_err());
+        assert!(Shortcut::parse("hyper+p").is_err());
+        assert!(Shortcut::parse("ctrl+nokey").is_err());
+    }
+
+    #[test]
+    fn test_default_bindings_resolve() {
+        for (action, chords) in KeyBindings::default().iter() {
+            for chord in chords {
+                assert!(resolve(chord).is_ok(), "{}: {}", action, chord);
+            }
         }
+        assert_eq!(
+            resolve(&Chord::parse("mouse_right").unwrap()),
+            Ok(Binding::Mouse(MouseButton::Right))
+        );
+        assert!(resolve(&Chord::parse("mouse_fourth").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_builtin_conflicts() {
+        let fires = |builtin: &str, press: &str| {
+            Shortcut::parse(builtin)
+                .unwrap()
+                .builtin_fires_on(&Shortcut::parse(press).unwrap())
+        };
+
+        assert!(fires("p", "p"));
+        assert!(fires("p", "shift+p"));
+        assert!(!fires("p", "ctrl+shift+p"));
+        assert!(fires("ctrl+s", "ctrl+shift+s"));
+        assert!(!fires("shift+d", "d"));
     }
 }
@@ -0,0 +1,79 @@
+use crate::config::{LAYERS_PANEL_ROW_HEIGHT, LAYERS_PANEL_WIDTH};
+use crate::{Tile, TileMap};
+use macroquad::hash;
+use macroquad::prelude::*;
+use macroquad::ui::{root_ui, widgets};
+use std::collections::HashMap;
+
+/// One layer of the tile map, layers are drawn bottom to top
+pub struct TileLayer {
+    pub(crate) name: String,
+    pub(crate) tiles: HashMap<(i32, i32), Tile>,
+    pub(crate) visible: bool,
+    pub(crate) opacity: f32,
+}
+
+impl TileLayer {
+    pub(crate) fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            tiles: HashMap::new(),
+            visible: true,
+            opacity: 1.0,
+        }
+    }
+}
+
+/// Panel listing the map layers with visibility, opacity and active layer controls
+pub struct LayersPanel {
+    pub(crate) visible: bool,
+}
+
+impl LayersPanel {
+    pub(crate) fn new() -> Self {
+        Self { visible: false }
+    }
+
+    pub(crate) fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub(crate) fn captures_mouse(&self, screen_pos: Vec2) -> bool {
+        self.visible && root_ui().is_mouse_over(screen_pos)
+    }
+
+    pub(crate) fn draw(&self, map: &mut TileMap) {
+        if !self.visible {
+            return;
+        }
+
+        let height = 40.0 + map.layers.len() as f32 * LAYERS_PANEL_ROW_HEIGHT;
+        let position = Vec2::new(screen_width() - LAYERS_PANEL_WIDTH - 20.0, 400.0);
+        let mut new_active_layer = None;
+
+        widgets::Window::new(hash!(), position, Vec2::new(LAYERS_PANEL_WIDTH, height))
+            .label("Layers (L to hide)")
+            .ui(&mut root_ui(), |ui| {
+                // Topmost layer first, like in most editors
+                for (i, layer) in map.layers.iter_mut().enumerate().rev() {
+                    let marker = if i == map.active_layer { "*" } else { " " };
+                    ui.label(None, &format!("{} {}", marker, layer.name));
+                    ui.checkbox(hash!("layer_visible", i), "Visible", &mut layer.visible);
+                    ui.slider(
+                        hash!("layer_opacity", i),
+                        "Opacity",
+                        0.0..1.0,
+                        &mut layer.opacity,
+                    );
+                    if i != map.active_layer && ui.button(None, "Edit this layer") {
+                        new_active_layer = Some(i);
+                    }
+                    ui.separator();
+                }
+            });
+
+        if let Some(index) = new_active_layer {
+            map.active_layer = index;
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::config::{
+    BUTTON_COLOR, BUTTON_HEIGHT, BUTTON_PADDING, CONSOLE_LINE_HEIGHT, TEXT_BACKGROUND_COLOR,
+    TEXT_FONT_SIZE,
+};
+use crate::map_file::LoadReport;
+use macroquad::prelude::*;
+
+const PANEL_WIDTH: f32 = 560.0;
+
+/// What loading the saved map ran into, in the middle of the screen until it's closed. Loads
+/// that went through unchanged show nothing.
+#[derive(Default)]
+pub struct LoadReportDialog {
+    report: Option<LoadReport>,
+}
+
+impl LoadReportDialog {
+    /// Show the report, unless there is nothing to tell
+    pub(crate) fn show(&mut self, report: LoadReport) {
+        for line in report.lines() {
+            println!("{}: {}", report.path, line);
+        }
+        if !report.is_empty() {
+            self.report = Some(report);
+        }
+    }
+
+    // Title colored by how bad it is, then the report
+    fn lines(&self) -> Vec<(String, Color)> {
+        let Some(report) = &self.report else {
+            return Vec::new();
+        };
+        let (title, color) = if report.error.is_some() {
+            (format!("Could not load {}", report.path), RED)
+        } else if !report.warnings.is_empty() {
+            (format!("Loaded {} with warnings", report.path), YELLOW)
+        } else {
+            (format!("Loaded {} after migrating it", report.path), GREEN)
+        };
+        let mut lines = vec![(title, color)];
+        lines.extend(report.lines().into_iter().map(|line| (line, WHITE)));
+        lines
+    }
+
+    fn panel_rect(&self, line_count: usize) -> Rect {
+        let height = line_count as f32 * CONSOLE_LINE_HEIGHT + BUTTON_HEIGHT + 30.0;
+        Rect::new(
+            (screen_width() - PANEL_WIDTH) / 2.0,
+            (screen_height() - height) / 2.0,
+            PANEL_WIDTH,
+            height,
+        )
+    }
+
+    fn close_button_rect(&self, panel: Rect) -> Rect {
+        Rect::new(
+            panel.right() - 80.0,
+            panel.bottom() - BUTTON_HEIGHT - 10.0,
+            70.0,
+            BUTTON_HEIGHT,
+        )
+    }
+
+    /// Title of the report while it is shown
+    pub(crate) fn describe(&self) -> Option<String> {
+        self.lines().first().map(|(title, _)| title.clone())
+    }
+
+    pub(crate) fn captures_mouse(&self, screen_pos: Vec2) -> bool {
+        self.report.is_some() && self.panel_rect(self.lines().len()).contains(screen_pos)
+    }
+
+    pub(crate) fn update(&mut self) {
+        if self.report.is_none() || !is_mouse_button_pressed(MouseButton::Left) {
+            return;
+        }
+        let panel = self.panel_rect(self.lines().len());
+        if self
+            .close_button_rect(panel)
+            .contains(Vec2::from(mouse_position()))
+        {
+            self.report = None;
+        }
+    }
+
+    pub(crate) fn draw(&self) {
+        if self.report.is_none() {
+            return;
+        }
+
+        let lines = self.lines();
+        let panel = self.panel_rect(lines.len());
+        draw_rectangle(panel.x, panel.y, panel.w, panel.h, TEXT_BACKGROUND_COLOR);
+        draw_rectangle_lines(panel.x, panel.y, panel.w, panel.h, 1.0, GRAY);
+        for (i, (line, color)) in lines.iter().enumerate() {
+            draw_text(
+                line,
+                panel.x + 10.0,
+                panel.y + 10.0 + (i + 1) as f32 * CONSOLE_LINE_HEIGHT,
+                TEXT_FONT_SIZE,
+                *color,
+            );
+        }
+
+        let close = self.close_button_rect(panel);
+        draw_rectangle(close.x, close.y, close.w, close.h, BUTTON_COLOR);
+        draw_rectangle_lines(close.x, close.y, close.w, close.h, 1.0, GRAY);
+        draw_text(
+            "Close",
+            close.x + BUTTON_PADDING,
+            close.y + (BUTTON_HEIGHT + TEXT_FONT_SIZE) / 2.0 - 4.0,
+            TEXT_FONT_SIZE,
+            WHITE,
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use crate::camera::CameraController;
+use crate::input::{key_name, mouse_button_name, InputEvent, InputManager, Shortcut};
+use crate::TilePosition;
+use lua_engine::actions::{Action, Chord, KEY_BINDINGS_PATH};
+use lua_engine::error_log::ErrorLog;
+use lua_engine::lua_engine::LuaEngine;
+use lua_engine::{LuaError, LuaFunction, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Event names accepted by `input.on`
+const EVENT_NAMES: [&str; 5] = [
+    "key_pressed",
+    "key_released",
+    "tile_clicked",
+    "tile_double_clicked",
+    "tile_long_pressed",
+];
+
+// Handler arguments of a single event, converted to Lua values when called
+enum EventArgs {
+    Key(String),
+    Tile(i32, i32, &'static str),
+}
+
+/// Lua `input` global forwarding recorded input events and actions to registered script handlers
+pub struct LuaInputBindings {
+    handlers: Arc<Mutex<HashMap<String, Vec<LuaFunction>>>>,
+    action_handlers: Arc<Mutex<HashMap<Action, Vec<LuaFunction>>>>,
+    shortcuts: Arc<Mutex<Vec<(Shortcut, LuaFunction)>>>,
+    camera: Arc<Mutex<CameraController>>,
+    input: Arc<Mutex<InputManager>>,
+    error_log: ErrorLog,
+}
+
+impl LuaInputBindings {
+    pub fn new(
+        lua_engine: Arc<Mutex<LuaEngine>>,
+        camera: Arc<Mutex<CameraController>>,
+        input: Arc<Mutex<InputManager>>,
+    ) -> Self {
+        let handlers: Arc<Mutex<HashMap<String, Vec<LuaFunction>>>> = Default::default();
+        let shortcuts: Arc<Mutex<Vec<(Shortcut, LuaFunction)>>> = Default::default();
+        let action_handlers: Arc<Mutex<HashMap<Action, Vec<LuaFunction>>>> = Default::default();
+        let error_log = lua_engine.lock().unwrap().error_log.clone();
+        {
+            let lua = &lua_engine.lock().unwrap().lua;
+            let input_table = lua.create_table().unwrap();
+            {
+                let handlers = handlers.clone();
+                lua.create_function(move |_, (event, handler): (String, LuaFunction)| {
+                    if !EVENT_NAMES.contains(&event.as_str()) {
+                        return Err(LuaError::RuntimeError(format!(
+                            "Unknown input event '{}', expected one of: {}",
+                            event,
+                            EVENT_NAMES.join(", ")
+                        )));
+                    }
+                    handlers
+                        .lock()
+                        .unwrap()
+                        .entry(event)
+                        .or_default()
+                        .push(handler);
+                    Ok(())
+                })
+                .and_then(|f| input_table.set("on", f))
+                .unwrap();
+            }
+            {
+                let action_handlers = action_handlers.clone();
+                lua.create_function(move |_, (name, handler): (String, LuaFunction)| {
+                    let action = Action::parse(&name).map_err(LuaError::RuntimeError)?;
+                    action_handlers
+                        .lock()
+                        .unwrap()
+                        .entry(action)
+                        .or_default()
+                        .push(handler);
+                    Ok(())
+                })
+                .and_then(|f| input_table.set("on_action", f))
+                .unwrap();
+            }
+            {
+                let input = input.clone();
+                // Pressed in the next frame as if its binding was, for scripted input and replays
+                lua.create_function(move |_, name: String| {
+                    let action = Action::parse(&name).map_err(LuaError::RuntimeError)?;
+                    input.lock().unwrap().trigger(action);
+                    Ok(())
+                })
+                .and_then(|f| input_table.set("trigger", f))
+                .unwrap();
+            }
+            {
+                let input = input.clone();
+                // input.bind("end_turn", { "space", "enter" }), saved for the next session
+                lua.create_function(move |_, (name, specs): (String, Value)| {
+                    let action = Action::parse(&name).map_err(LuaError::RuntimeError)?;
+                    let specs: Vec<String> = match specs {
+                        Value::String(spec) => vec![spec.to_str()?.to_string()],
+                        Value::Table(specs) => specs.sequence_values().collect::<Result<_, _>>()?,
+                        _ => {
+                            return Err(LuaError::RuntimeError(
+                                "Expected a binding or a list of bindings".to_string(),
+                            ))
+                        }
+                    };
+                    let chords = specs
+                        .iter()
+                        .map(|spec| Chord::parse(spec))
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(LuaError::RuntimeError)?;
+                    let mut input = input.lock().unwrap();
+                    input
+                        .rebind(action, chords)
+                        .map_err(LuaError::RuntimeError)?;
+                    input
+                        .bindings()
+                        .save(KEY_BINDINGS_PATH)
+                        .map_err(LuaError::RuntimeError)
+                })
+                .and_then(|f| input_table.set("bind", f))
+                .unwrap();
+            }
+            {
+                let input = input.clone();
+                // { pan_up = { "w", "up" }, ... }
+                lua.create_function(move |lua, ()| {
+                    let result = lua.create_table()?;
+                    for (action, chords) in input.lock().unwrap().bindings().iter() {
+                        let chords: Vec<String> = chords.iter().map(Chord::to_string).collect();
+                        result.set(action.name(), chords)?;
+                    }
+                    Ok(result)
+                })
+                .and_then(|f| input_table.set("bindings", f))
+                .unwrap();
+            }
+            {
+                let shortcuts = shortcuts.clone();
+                let input = input.clone();
+                lua.create_function(move |_, (spec, handler): (String, LuaFunction)| {
+                    let shortcut = Shortcut::parse(&spec).map_err(LuaError::RuntimeError)?;
+                    let taken = input
+                        .lock()
+                        .unwrap()
+                        .key_bindings()
+                        .find(|(_, binding)| binding.builtin_fires_on(&shortcut))
+                        .map(|(action, _)| action);
+                    if let Some(action) = taken {
+                        return Err(LuaError::RuntimeError(format!(
+                            "Shortcut '{}' conflicts with the binding of action '{}'",
+                            spec, action
+                        )));
+                    }
+                    let mut shortcuts = shortcuts.lock().unwrap();
+                    if shortcuts.iter().any(|(existing, _)| *existing == shortcut) {
+                        return Err(LuaError::RuntimeError(format!(
+                            "Shortcut '{}' is already registered",
+                            spec
+                        )));
+                    }
+                    shortcuts.push((shortcut, handler));
+                    Ok(())
+                })
+                .and_then(|f| input_table.set("shortcut", f))
+                .unwrap();
+            }
+            {
+                let input = input.clone();
+                lua.create_function(move |_, seconds: f32| {
+                    input.lock().unwrap().gestures.double_click_time = seconds.max(0.0);
+                    Ok(())
+                })
+                .and_then(|f| input_table.set("set_double_click_time", f))
+                .unwrap();
+            }
+            {
+                let input = input.clone();
+                lua.create_function(move |_, seconds: f32| {
+                    input.lock().unwrap().gestures.long_press_time = seconds.max(0.0);
+                    Ok(())
+                })
+                .and_then(|f| input_table.set("set_long_press_time", f))
+                .unwrap();
+            }
+            lua.globals().set("input", input_table).unwrap();
+        }
+        Self {
+            handlers,
+            action_handlers,
+            shortcuts,
+            camera,
+            input,
+            error_log,
+        }
+    }
+
+    /// Call the Lua handlers for all events recorded since the last dispatch
+    pub fn dispatch(&mut self) {
+        let triggered: Vec<LuaFunction> = self
+            .shortcuts
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(shortcut, _)| shortcut.is_pressed())
+            .map(|(_, handler)| handler.clone())
+            .collect();
+        for handler in triggered {
+            if let Err(e) = handler.call::<()>(()) {
+                self.error_log.report("Shortcut handler", e);
+            }
+        }
+
+        let actions = self.input.lock().unwrap().actions().to_vec();
+        for action in actions {
+            let handlers = match self.action_handlers.lock().unwrap().get(&action) {
+                Some(handlers) => handlers.clone(),
+                None => continue,
+            };
+            for handler in handlers {
+                if let Err(e) = handler.call::<()>(()) {
+                    self.error_log
+                        .report(&format!("'{}' action handler", action), e);
+                }
+            }
+        }
+
+        let events = self.input.lock().unwrap().take_events();
+        if events.is_empty() {
+            return;
+        }
+
+        let calls: Vec<(&str, EventArgs)> = {
+            let camera = self.camera.lock().unwrap();
+            events
+                .into_iter()
+                .map(|event| match event {
+                    InputEvent::KeyPressed(key) => ("key_pressed", EventArgs::Key(key_name(key))),
+                    InputEvent::KeyReleased(key) => ("key_released", EventArgs::Key(key_name(key))),
+                    InputEvent::Clicked { button, position } => {
+                        let tile = TilePosition::from_world_pos(camera.screen_to_world(position));
+                        (
+                            "tile_clicked",
+                            EventArgs::Tile(tile.x, tile.y, mouse_button_name(button)),
+                        )
+                    }
+                    InputEvent::DoubleClicked { position } => {
+                        let tile = TilePosition::from_world_pos(camera.screen_to_world(position));
+                        (
+                            "tile_double_clicked",
+                            EventArgs::Tile(tile.x, tile.y, "left"),
+                        )
+                    }
+                    InputEvent::LongPressed { position } => {
+                        let tile = TilePosition::from_world_pos(camera.screen_to_world(position));
+                        ("tile_long_pressed", EventArgs::Tile(tile.x, tile.y, "left"))
+                    }
+                })
+                .collect()
+        };
+
+        for (event, args) in calls {
+            // Clone the handlers so that they can register new ones while being called
+            let handlers = match self.handlers.lock().unwrap().get(event) {
+                Some(handlers) => handlers.clone(),
+                None => continue,
+            };
+            for handler in handlers {
+                let result = match &args {
+                    EventArgs::Key(key) => handler.call::<()>(key.as_str()),
+                    EventArgs::Tile(x, y, button) => handler.call::<()>((*x, *y, *button)),
+                };
+                if let Err(e) = result {
+                    self.error_log
+                        .report(&format!("'{}' input handler", event), e);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+
+const KEYWORDS: [&str; 22] = [
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+// Keywords opening a block closed by `end` (or `until` for `repeat`).
+// `while` and `for` are covered by their `do`.
+const BLOCK_OPENERS: [&str; 4] = ["function", "if", "do", "repeat"];
+const BLOCK_CLOSERS: [&str; 2] = ["end", "until"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Keyword,
+    Identifier,
+    Number,
+    String,
+    Comment,
+    Operator,
+    OpenBracket,
+    CloseBracket,
+    Whitespace,
+}
+
+/// Token with its byte range in the source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Result of lexing a chunk of Lua code good enough for highlighting and completeness checks
+pub struct Analysis {
+    pub tokens: Vec<Token>,
+    /// Matching opener/closer token indices, stored in both directions
+    pub pairs: HashMap<usize, usize>,
+    /// Openers and closers without a partner
+    pub unmatched: Vec<usize>,
+    /// A string or long comment runs until the end of the source
+    pub unterminated: bool,
+    // Openers still waiting for their closer at the end of the source
+    open: Vec<usize>,
+}
+
+// Level of a long bracket (`[[`, `[==[`) starting at `pos`
+fn long_bracket_level(bytes: &[u8], pos: usize) -> Option<usize> {
+    if bytes.get(pos) != Some(&b'[') {
+        return None;
+    }
+    let mut level = 0;
+    while bytes.get(pos + 1 + level) == Some(&b'=') {
+        level += 1;
+    }
+    (bytes.get(pos + 1 + level) == Some(&b'[')).then_some(level)
+}
+
+// End of a long bracket body starting at `pos` (after the opening bracket), None when unterminated
+fn long_bracket_end(src: &str, pos: usize, level: usize) -> Option<usize> {
+    let closing = format!("]{}]", "=".repeat(level));
+    src[pos..].find(&closing).map(|i| pos + i + closing.len())
+}
+
+// Role of a token in block and bracket nesting
+enum Nesting {
+    Open,
+    Close,
+    None,
+}
+
+fn nesting(src: &str, token: &Token) -> Nesting {
+    let text = &src[token.start..token.end];
+    match token.kind {
+        TokenKind::Keyword if BLOCK_OPENERS.contains(&text) => Nesting::Open,
+        TokenKind::Keyword if BLOCK_CLOSERS.contains(&text) => Nesting::Close,
+        TokenKind::OpenBracket => Nesting::Open,
+        TokenKind::CloseBracket => Nesting::Close,
+        _ => Nesting::None,
+    }
+}
+
+fn closes(src: &str, opener: &Token, closer: &Token) -> bool {
+    let open = &src[opener.start..opener.end];
+    let close = &src[closer.start..closer.end];
+    match open {
+        "(" => close == ")",
+        "[" => close == "]",
+        "{" => close == "}",
+        "repeat" => close == "until",
+        _ => close == "end",
+    }
+}
+
+pub fn tokenize(src: &str) -> (Vec<Token>, bool) {
+    let bytes = src.as_bytes();
+    let mut tokens = Vec::new();
+    let mut unterminated = false;
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let start = pos;
+        let c = bytes[pos];
+        let kind = if c.is_ascii_whitespace() {
+            while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            TokenKind::Whitespace
+        } else if src[pos..].starts_with("--") {
+            match long_bracket_level(bytes, pos + 2) {
+                Some(level) => match long_bracket_end(src, pos + 4 + level, level) {
+                    Some(end) => pos = end,
+                    None => {
+                        pos = bytes.len();
+                        unterminated = true;
+                    }
+                },
+                None => pos = src[pos..].find('\n').map_or(bytes.len(), |i| pos + i),
+            }
+            TokenKind::Comment
+        } else if c == b'"' || c == b'\'' {
+            pos += 1;
+            loop {
+                match bytes.get(pos) {
+                    Some(b'\\') => pos += 2,
+                    Some(&q) if q == c => {
+                        pos += 1;
+                        break;
+                    }
+                    // A line break ends a short string (Lua reports the error), the end of input leaves it open
+                    Some(b'\n') => break,
+                    None => {
+                        unterminated = true;
+                        break;
+                    }
+                    Some(_) => pos += 1,
+                }
+            }
+            pos = pos.min(bytes.len());
+            TokenKind::String
+        } else if let Some(level) = long_bracket_level(bytes, pos) {
+            match long_bracket_end(src, pos + 2 + level, level) {
+                Some(end) => pos = end,
+                None => {
+                    pos = bytes.len();
+                    unterminated = true;
+                }
+            }
+            TokenKind::String
+        } else if c.is_ascii_digit()
+            || (c == b'.' && bytes.get(pos + 1).is_some_and(u8::is_ascii_digit))
+        {
+            while pos < bytes.len() {
+                let b = bytes[pos];
+                let exponent_sign =
+                    (b == b'+' || b == b'-') && matches!(bytes[pos - 1], b'e' | b'E' | b'p' | b'P');
+                if b.is_ascii_alphanumeric() || b == b'.' || b == b'_' || exponent_sign {
+                    pos += 1;
+                } else {
+                    break;
+                }
+            }
+            TokenKind::Number
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            while pos < bytes.len() && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_') {
+                pos += 1;
+            }
+            if KEYWORDS.contains(&&src[start..pos]) {
+                TokenKind::Keyword
+            } else {
+                TokenKind::Identifier
+            }
+        } else if matches!(c, b'(' | b'[' | b'{') {
+            pos += 1;
+            TokenKind::OpenBracket
+        } else if matches!(c, b')' | b']' | b'}') {
+            pos += 1;
+            TokenKind::CloseBracket
+        } else {
+            // Whole character, the source may contain non-ASCII text
+            pos += src[pos..].chars().next().map_or(1, char::len_utf8);
+            TokenKind::Operator
+        };
+        tokens.push(Token {
+            kind,
+            start,
+            end: pos,
+        });
+    }
+    (tokens, unterminated)
+}
+
+pub fn analyze(src: &str) -> Analysis {
+    let (tokens, unterminated) = tokenize(src);
+    let mut pairs = HashMap::new();
+    let mut unmatched = Vec::new();
+    let mut open: Vec<usize> = Vec::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        match nesting(src, token) {
+            Nesting::Open => open.push(i),
+            Nesting::Close => match open.last() {
+                Some(&opener) if closes(src, &tokens[opener], token) => {
+                    open.pop();
+                    pairs.insert(opener, i);
+                    pairs.insert(i, opener);
+                }
+                _ => unmatched.push(i),
+            },
+            Nesting::None => {}
+        }
+    }
+    unmatched.extend(open.iter().copied());
+
+    Analysis {
+        tokens,
+        pairs,
+        unmatched,
+        unterminated,
+        open,
+    }
+}
+
+impl Analysis {
+    /// Whether the chunk can be run, i.e. nothing is left open (stray closers are left for Lua to report)
+    pub fn is_complete(&self) -> bool {
+        !self.unterminated && self.open.is_empty()
+    }
+
+    /// What the chunk is waiting for when it's incomplete
+    pub fn expected_closer(&self, src: &str) -> Option<&'static str> {
+        if self.unterminated {
+            return Some("closing quote or bracket");
+        }
+        let opener = self.tokens[*self.open.last()?];
+        Some(match &src[opener.start..opener.end] {
+            "(" => ")",
+            "[" => "]",
+            "{" => "}",
+            "repeat" => "until",
+            _ => "end",
+        })
+    }
+
+    /// Index of the token covering the byte position (or ending right at it)
+    pub fn token_at(&self, pos: usize) -> Option<usize> {
+        self.tokens
+            .iter()
+            .position(|token| token.start <= pos && pos < token.end)
+            .filter(|&i| self.tokens[i].kind != TokenKind::Whitespace)
+            .or_else(|| self.tokens.iter().position(|token| token.end == pos))
+    }
+}
+
+/// Number of blocks and brackets still open before the byte position
+pub fn open_depth(src: &str, pos: usize) -> usize {
+    analyze(&src[..pos]).open.len()
+}
+
+/// Whether the line starts with a keyword or bracket that closes (or continues) a block
+pub fn starts_with_closer(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    let word: String = trimmed
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect();
+    matches!(word.as_str(), "end" | "until" | "else" | "elseif")
+        || trimmed.starts_with([')', ']', '}'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(src: &str) -> Vec<TokenKind> {
+        tokenize(src)
+            .0
+            .into_iter()
+            .filter(|token| token.kind != TokenKind::Whitespace)
+            .map(|token| token.kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize_kinds() {
+        assert_eq!(
+            kinds("local x = 1.5e+3 -- note"),
+            vec![
+                TokenKind::Keyword,
+                TokenKind::Identifier,
+                TokenKind::Operator,
+                TokenKind::Number,
+                TokenKind::Comment,
+            ]
+        );
+        assert_eq!(
+            kinds("print('a\\'b', [[long]])"),
+            vec![
+                TokenKind::Identifier,
+                TokenKind::OpenBracket,
+                TokenKind::String,
+                TokenKind::Operator,
+                TokenKind::String,
+                TokenKind::CloseBracket,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_complete_chunks() {
+        assert!(analyze("print(1)").is_complete());
+        assert!(analyze("for i = 1, 3 do print(i) end").is_complete());
+        assert!(analyze("local f = function() return {1, 2} end").is_complete());
+        assert!(analyze("repeat x = x + 1 until x > 3").is_complete());
+        assert!(analyze("if a then b() elseif c then d() else e() end").is_complete());
+    }
+
+    #[test]
+    fn test_incomplete_chunks() {
+        let src = "function f()\n  if x then";
+        let analysis = analyze(src);
+        assert!(!analysis.is_complete());
+        assert_eq!(analysis.expected_closer(src), Some("end"));
+
+        let src = "print(1,";
+        assert_eq!(analyze(src).expected_closer(src), Some(")"));
+        assert!(!analyze("x = [[ unterminated").is_complete());
+        assert!(!analyze("--[[ comment").is_complete());
+    }
+
+    #[test]
+    fn test_stray_closer_is_unmatched() {
+        let analysis = analyze("print(1))");
+        assert!(analysis.is_complete());
+        assert_eq!(analysis.unmatched.len(), 1);
+    }
+
+    #[test]
+    fn test_pairs_and_depth() {
+        let src = "f(function() end)";
+        let analysis = analyze(src);
+        let paren = analysis.token_at(1).unwrap();
+        let closing = analysis.pairs[&paren];
+        assert_eq!(
+            &src[analysis.tokens[closing].start..analysis.tokens[closing].end],
+            ")"
+        );
+
+        let src = "if x then\n  f(";
+        assert_eq!(open_depth(src, src.len()), 2);
+        assert!(starts_with_closer("  end"));
+        assert!(starts_with_closer("elseif y then"));
+        assert!(!starts_with_closer("ending = 1"));
+    }
+}
@@ -1,10 +1,20 @@
+use crate::brush::{Brush, BrushShape};
 use crate::camera::CameraController;
+use crate::charts::{draw_bar_chart, draw_line_chart, draw_sparkline};
+use crate::config::{
+    BRUSH_MAX_SIZE, BUTTON_ACTIVE_COLOR, BUTTON_COLOR, BUTTON_HEIGHT, BUTTON_PADDING,
+    COMPONENT_TIMING_SMOOTHING, TEXT_BACKGROUND_COLOR, TEXT_FONT_SIZE,
+};
+use crate::indicators::OffscreenIndicators;
 use crate::input::InputManager;
+use crate::people::PersonId;
 use crate::utils::draw_text_with_background;
 use crate::{TileMap, TilePosition};
+use lua_engine::color::Rgba;
+use lua_engine::error_log::ErrorLog;
 use lua_engine::lua_engine::LuaEngine;
-use lua_engine::LuaFunction;
-use macroquad::prelude::get_fps;
+use lua_engine::{LuaError, LuaFunction, Metrics, Value};
+use macroquad::prelude::*;
 use std::sync::{Arc, Mutex};
 
 enum UIComponent {
@@ -13,36 +23,528 @@ enum UIComponent {
         y: f32,
         handler: LuaFunction,
     },
-    // Placeholder for other components we're not implementing yet
-    // These would be converted similarly to Label when needed
+    Button {
+        x: f32,
+        y: f32,
+        label: String,
+        on_click: LuaFunction,
+        is_active: Option<LuaFunction>,
+    },
+    TilePreview {
+        x: f32,
+        y: f32,
+        size: f32,
+        handler: LuaFunction,
+    },
+    Sparkline {
+        rect: Rect,
+        handler: LuaFunction,
+    },
+    BarChart {
+        rect: Rect,
+        handler: LuaFunction,
+    },
+    LineChart {
+        rect: Rect,
+        title: String,
+        handler: LuaFunction,
+    },
+    // A metric of `api.metrics` over the ticks, like `metric_plot` of the egui frontend
+    MetricChart {
+        rect: Rect,
+        metric: String,
+        metrics: Metrics,
+    },
+    // Drags between min and max, the handler returns the value when called without arguments
+    // and gets the new one while dragging
+    Slider {
+        rect: Rect,
+        label: String,
+        min: f64,
+        max: f64,
+        handler: LuaFunction,
+    },
+    // A titled panel drawn behind the components its build function added, which are placed in
+    // screen coordinates like any other
     Window {
+        rect: Rect,
         label: String,
         children: Vec<UIComponent>,
     },
 }
 
+// The components in the order they were added. While the build function of a window runs, what
+// it adds goes into that window instead.
+#[derive(Default)]
+struct Components {
+    roots: Vec<UIComponent>,
+    open: Vec<Vec<UIComponent>>,
+}
+
+impl Components {
+    fn push(&mut self, component: UIComponent) {
+        match self.open.last_mut() {
+            Some(children) => children.push(component),
+            None => self.roots.push(component),
+        }
+    }
+
+    fn open_window(&mut self) {
+        self.open.push(Vec::new());
+    }
+
+    fn close_window(&mut self, rect: Rect, label: String) {
+        let children = self.open.pop().unwrap_or_default();
+        self.push(UIComponent::Window {
+            rect,
+            label,
+            children,
+        });
+    }
+}
+
+// A slider being dragged, it keeps following the mouse until the button is released
+struct SliderDrag {
+    rect: Rect,
+    min: f64,
+    max: f64,
+    handler: LuaFunction,
+    // The value the handler got last
+    value: Option<f64>,
+}
+
+impl SliderDrag {
+    fn value_at(&self, mouse_x: f32) -> f64 {
+        let fraction = ((mouse_x - self.rect.x) / self.rect.w).clamp(0.0, 1.0) as f64;
+        self.min + (self.max - self.min) * fraction
+    }
+}
+
+/// Time the Lua handlers of a UI component took, so a slow one stands out in the debug window
+#[derive(Debug, Clone)]
+pub struct ComponentTiming {
+    pub name: String,
+    /// Milliseconds spent in its handlers during the last frame
+    pub last_ms: f64,
+    /// Rolling average of the milliseconds per frame
+    pub average_ms: f64,
+}
+
+impl ComponentTiming {
+    fn new(name: String, ms: f64) -> Self {
+        Self {
+            name,
+            last_ms: ms,
+            average_ms: ms,
+        }
+    }
+
+    fn record(&mut self, ms: f64) {
+        self.last_ms = ms;
+        self.average_ms += (ms - self.average_ms) * COMPONENT_TIMING_SMOOTHING;
+    }
+}
+
+// Call a Lua handler, adding the milliseconds it took to `lua_ms`
+fn timed<T>(lua_ms: &mut f64, call: impl FnOnce() -> T) -> T {
+    let started = get_time();
+    let result = call();
+    *lua_ms += (get_time() - started) * 1000.0;
+    result
+}
+
+// What the Lua handlers of a component returned last, drawn again while they don't run
+enum ComponentValue {
+    Label(Option<String>),
+    /// Whether the button is active
+    Button(bool),
+    TilePreview(Option<usize>),
+    Sparkline(Vec<f64>),
+    BarChart(Vec<(String, f64)>),
+    /// Points of a line chart as (x, y)
+    LineChart(Vec<(f64, f64)>),
+    Slider(Option<f64>),
+    Window(Vec<ComponentValue>),
+}
+
+// A bar is a number, `{ label, value }` or `{ label = ..., value = ... }`
+fn parse_bar(value: Value) -> Result<(String, f64), LuaError> {
+    match value {
+        Value::Integer(n) => Ok((String::new(), n as f64)),
+        Value::Number(n) => Ok((String::new(), n)),
+        Value::Table(bar) => {
+            let label = match bar.get::<Option<String>>("label")? {
+                Some(label) => label,
+                None => bar.get::<Option<String>>(1)?.unwrap_or_default(),
+            };
+            let value = match bar.get::<Option<f64>>("value")? {
+                Some(value) => value,
+                None => bar.get::<f64>(2)?,
+            };
+            Ok((label, value))
+        }
+        value => Err(LuaError::RuntimeError(format!(
+            "A bar is a number or a {{ label, value }} table, got a {}",
+            value.type_name()
+        ))),
+    }
+}
+
 impl UIComponent {
-    pub fn draw(&self) {
+    // How the debug window refers to the component
+    fn name(&self) -> String {
+        match self {
+            UIComponent::Label { x, y, .. } => format!("label at {:.0}, {:.0}", x, y),
+            UIComponent::Button { label, .. } => format!("button '{}'", label),
+            UIComponent::TilePreview { x, y, .. } => {
+                format!("tile_preview at {:.0}, {:.0}", x, y)
+            }
+            UIComponent::Sparkline { rect, .. } => {
+                format!("sparkline at {:.0}, {:.0}", rect.x, rect.y)
+            }
+            UIComponent::BarChart { rect, .. } => {
+                format!("bar_chart at {:.0}, {:.0}", rect.x, rect.y)
+            }
+            UIComponent::LineChart { title, .. } => format!("line_chart '{}'", title),
+            UIComponent::MetricChart { metric, .. } => format!("metric_chart '{}'", metric),
+            UIComponent::Slider { label, .. } => format!("slider '{}'", label),
+            UIComponent::Window { label, .. } => format!("window '{}'", label),
+        }
+    }
+
+    fn button_rect(x: f32, y: f32, label: &str) -> Rect {
+        let text_dimensions = measure_text(label, None, TEXT_FONT_SIZE as u16, 1.0);
+        Rect::new(
+            x,
+            y,
+            text_dimensions.width + BUTTON_PADDING * 2.0,
+            BUTTON_HEIGHT,
+        )
+    }
+
+    // Whether the component covers the screen position and takes mouse input there
+    fn captures_mouse(&self, mouse_pos: Vec2) -> bool {
+        match self {
+            UIComponent::Button { x, y, label, .. } => {
+                Self::button_rect(*x, *y, label).contains(mouse_pos)
+            }
+            UIComponent::TilePreview { x, y, size, .. } => {
+                Rect::new(*x, *y, *size, *size).contains(mouse_pos)
+            }
+            UIComponent::Sparkline { rect, .. }
+            | UIComponent::BarChart { rect, .. }
+            | UIComponent::LineChart { rect, .. }
+            | UIComponent::MetricChart { rect, .. }
+            | UIComponent::Slider { rect, .. } => rect.contains(mouse_pos),
+            UIComponent::Window { rect, .. } => rect.contains(mouse_pos),
+            UIComponent::Label { .. } => false,
+        }
+    }
+
+    // Returns the click handler if the component was clicked this frame
+    fn clicked_handler(&self, mouse_pos: Vec2) -> Option<LuaFunction> {
+        match self {
+            UIComponent::Button {
+                x,
+                y,
+                label,
+                on_click,
+                ..
+            } if Self::button_rect(*x, *y, label).contains(mouse_pos) => Some(on_click.clone()),
+            UIComponent::Window { children, .. } => children
+                .iter()
+                .find_map(|child| child.clicked_handler(mouse_pos)),
+            _ => None,
+        }
+    }
+
+    // Returns the slider under the mouse, to drag it from now on
+    fn slider_at(&self, mouse_pos: Vec2) -> Option<SliderDrag> {
+        match self {
+            UIComponent::Slider {
+                rect,
+                min,
+                max,
+                handler,
+                ..
+            } if rect.contains(mouse_pos) => Some(SliderDrag {
+                rect: *rect,
+                min: *min,
+                max: *max,
+                handler: handler.clone(),
+                value: None,
+            }),
+            UIComponent::Window { children, .. } => {
+                children.iter().find_map(|child| child.slider_at(mouse_pos))
+            }
+            _ => None,
+        }
+    }
+
+    // Ask the Lua handlers for what to show, adding the milliseconds they took to `lua_ms`
+    fn refresh(&self, error_log: &ErrorLog, lua_ms: &mut f64) -> ComponentValue {
         match self {
-            UIComponent::Label { x, y, handler } => {
-                // Call the Lua function to draw the label
-                match handler.call::<String>(()) {
-                    Ok(value) => draw_text_with_background(&value, *x, *y, macroquad::color::WHITE),
-                    Err(e) => eprintln!("Error fetching Label value from Lua: {}", e),
+            UIComponent::Label { handler, .. } => {
+                match timed(lua_ms, || handler.call::<String>(())) {
+                    Ok(value) => ComponentValue::Label(Some(value)),
+                    Err(e) => {
+                        error_log.report("Label handler", e);
+                        ComponentValue::Label(None)
+                    }
+                }
+            }
+            UIComponent::Button { is_active, .. } => ComponentValue::Button(match is_active {
+                Some(handler) => timed(lua_ms, || handler.call::<bool>(())).unwrap_or_else(|e| {
+                    error_log.report("Button state handler", e);
+                    false
+                }),
+                None => false,
+            }),
+            UIComponent::TilePreview { handler, .. } => {
+                // Asked before drawing, the handler may need the map lock itself
+                match timed(lua_ms, || handler.call::<Option<usize>>(())) {
+                    Ok(tile_id) => ComponentValue::TilePreview(tile_id),
+                    Err(e) => {
+                        error_log.report("TilePreview handler", e);
+                        ComponentValue::TilePreview(None)
+                    }
+                }
+            }
+            UIComponent::Sparkline { handler, .. } => ComponentValue::Sparkline(
+                timed(lua_ms, || handler.call::<Vec<f64>>(())).unwrap_or_else(|e| {
+                    error_log.report("Sparkline handler", e);
+                    Vec::new()
+                }),
+            ),
+            UIComponent::BarChart { handler, .. } => ComponentValue::BarChart(
+                timed(lua_ms, || {
+                    handler
+                        .call::<Vec<Value>>(())?
+                        .into_iter()
+                        .map(parse_bar)
+                        .collect::<Result<_, _>>()
+                })
+                .unwrap_or_else(|e| {
+                    error_log.report("BarChart handler", e);
+                    Vec::new()
+                }),
+            ),
+            // The values are plotted over their index, like the plots of the egui frontend
+            UIComponent::LineChart { handler, .. } => ComponentValue::LineChart(
+                timed(lua_ms, || handler.call::<Vec<f64>>(()))
+                    .map(|values| {
+                        values
+                            .into_iter()
+                            .enumerate()
+                            .map(|(i, value)| (i as f64, value))
+                            .collect()
+                    })
+                    .unwrap_or_else(|e| {
+                        error_log.report("LineChart handler", e);
+                        Vec::new()
+                    }),
+            ),
+            UIComponent::MetricChart {
+                metric, metrics, ..
+            } => ComponentValue::LineChart(metrics.samples(metric)),
+            UIComponent::Slider { handler, .. } => {
+                match timed(lua_ms, || handler.call::<f64>(())) {
+                    Ok(value) => ComponentValue::Slider(Some(value)),
+                    Err(e) => {
+                        error_log.report("Slider handler", e);
+                        ComponentValue::Slider(None)
+                    }
+                }
+            }
+            UIComponent::Window { children, .. } => ComponentValue::Window(
+                children
+                    .iter()
+                    .map(|child| child.refresh(error_log, lua_ms))
+                    .collect(),
+            ),
+        }
+    }
+
+    // What the component shows, for `ui.describe()`
+    fn describe(&self, value: &ComponentValue) -> String {
+        match (self, value) {
+            (UIComponent::Label { .. }, ComponentValue::Label(Some(text))) => {
+                format!("{}: {}", self.name(), text)
+            }
+            (UIComponent::Button { .. }, ComponentValue::Button(true)) => {
+                format!("{} (active)", self.name())
+            }
+            (UIComponent::TilePreview { .. }, ComponentValue::TilePreview(Some(tile_id))) => {
+                format!("{}: tile {}", self.name(), tile_id)
+            }
+            (UIComponent::TilePreview { .. }, ComponentValue::TilePreview(None)) => {
+                format!("{}: empty", self.name())
+            }
+            (UIComponent::Sparkline { .. }, ComponentValue::Sparkline(values)) => {
+                match values.last() {
+                    Some(last) => {
+                        format!("{}: {} values, last {}", self.name(), values.len(), last)
+                    }
+                    None => format!("{}: empty", self.name()),
+                }
+            }
+            (UIComponent::BarChart { .. }, ComponentValue::BarChart(bars)) => {
+                let bars: Vec<String> = bars
+                    .iter()
+                    .map(|(label, value)| format!("{} {}", label, value).trim().to_string())
+                    .collect();
+                format!("{}: {}", self.name(), bars.join(", "))
+            }
+            (UIComponent::LineChart { .. }, ComponentValue::LineChart(points))
+            | (UIComponent::MetricChart { .. }, ComponentValue::LineChart(points)) => {
+                match points.last() {
+                    Some((_, last)) => {
+                        format!("{}: {} points, last {}", self.name(), points.len(), last)
+                    }
+                    None => format!("{}: empty", self.name()),
+                }
+            }
+            (UIComponent::Slider { .. }, ComponentValue::Slider(Some(value))) => {
+                format!("{}: {}", self.name(), value)
+            }
+            (UIComponent::Window { children, .. }, ComponentValue::Window(values)) => {
+                let mut description = self.name();
+                for (child, value) in children.iter().zip(values) {
+                    description.push_str("\n  ");
+                    description.push_str(&child.describe(value));
+                }
+                description
+            }
+            _ => self.name(),
+        }
+    }
+
+    pub fn draw(&self, value: &ComponentValue, map: &Arc<Mutex<TileMap>>) {
+        match (self, value) {
+            (UIComponent::Label { x, y, .. }, ComponentValue::Label(Some(text))) => {
+                draw_text_with_background(text, *x, *y, macroquad::color::WHITE);
+            }
+            (UIComponent::Button { x, y, label, .. }, ComponentValue::Button(active)) => {
+                let rect = Self::button_rect(*x, *y, label);
+                let color = if *active {
+                    BUTTON_ACTIVE_COLOR
+                } else {
+                    BUTTON_COLOR
+                };
+                draw_rectangle(rect.x, rect.y, rect.w, rect.h, color);
+                draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 1.0, GRAY);
+                draw_text(
+                    label,
+                    rect.x + BUTTON_PADDING,
+                    rect.y + (BUTTON_HEIGHT + TEXT_FONT_SIZE) / 2.0 - 4.0,
+                    TEXT_FONT_SIZE,
+                    WHITE,
+                );
+            }
+            (UIComponent::TilePreview { x, y, size, .. }, ComponentValue::TilePreview(tile_id)) => {
+                draw_rectangle(*x, *y, *size, *size, BUTTON_COLOR);
+                if let Some(tile_id) = tile_id {
+                    let map = map.lock().unwrap();
+                    draw_texture_ex(
+                        &map.tileset,
+                        *x,
+                        *y,
+                        WHITE,
+                        DrawTextureParams {
+                            source: Some(map.atlas.source(*tile_id)),
+                            dest_size: Some(Vec2::new(*size, *size)),
+                            ..Default::default()
+                        },
+                    );
+                }
+                draw_rectangle_lines(*x, *y, *size, *size, 1.0, GRAY);
+            }
+            (UIComponent::Sparkline { rect, .. }, ComponentValue::Sparkline(values)) => {
+                draw_sparkline(*rect, values);
+            }
+            (UIComponent::BarChart { rect, .. }, ComponentValue::BarChart(bars)) => {
+                draw_bar_chart(*rect, bars);
+            }
+            (UIComponent::LineChart { rect, title, .. }, ComponentValue::LineChart(points)) => {
+                draw_line_chart(*rect, title, points);
+            }
+            (UIComponent::MetricChart { rect, metric, .. }, ComponentValue::LineChart(points)) => {
+                draw_line_chart(*rect, metric, points);
+            }
+            (
+                UIComponent::Slider {
+                    rect,
+                    label,
+                    min,
+                    max,
+                    ..
+                },
+                ComponentValue::Slider(value),
+            ) => {
+                draw_rectangle(rect.x, rect.y, rect.w, rect.h, BUTTON_COLOR);
+                if let Some(value) = value {
+                    let fraction = if max > min {
+                        ((value - min) / (max - min)).clamp(0.0, 1.0) as f32
+                    } else {
+                        0.0
+                    };
+                    draw_rectangle(
+                        rect.x,
+                        rect.y,
+                        rect.w * fraction,
+                        rect.h,
+                        BUTTON_ACTIVE_COLOR,
+                    );
                 }
+                draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 1.0, GRAY);
+                let text = match value {
+                    Some(value) => format!("{}: {:.2}", label, value),
+                    None => label.clone(),
+                };
+                draw_text(
+                    &text,
+                    rect.x + BUTTON_PADDING,
+                    rect.y + (rect.h + TEXT_FONT_SIZE) / 2.0 - 4.0,
+                    TEXT_FONT_SIZE,
+                    WHITE,
+                );
             }
-            UIComponent::Window { label, children } => {
-                // Draw the children
-                children.iter().for_each(|child| {
-                    child.draw();
-                });
+            (
+                UIComponent::Window {
+                    rect,
+                    label,
+                    children,
+                },
+                ComponentValue::Window(values),
+            ) => {
+                draw_rectangle(rect.x, rect.y, rect.w, rect.h, TEXT_BACKGROUND_COLOR);
+                draw_rectangle(rect.x, rect.y, rect.w, BUTTON_HEIGHT, BUTTON_COLOR);
+                draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 1.0, GRAY);
+                draw_text(
+                    label,
+                    rect.x + BUTTON_PADDING,
+                    rect.y + (BUTTON_HEIGHT + TEXT_FONT_SIZE) / 2.0 - 4.0,
+                    TEXT_FONT_SIZE,
+                    WHITE,
+                );
+                for (child, value) in children.iter().zip(values) {
+                    child.draw(value, map);
+                }
             }
+            // Values come from refreshing the same component, they always match it
+            _ => {}
         }
     }
 }
 
 pub struct LuaUIBindings {
-    components: Arc<Mutex<Vec<UIComponent>>>,
+    components: Arc<Mutex<Components>>,
+    // By the index of their component, components are only ever added
+    values: Vec<ComponentValue>,
+    timings: Vec<ComponentTiming>,
+    dragging: Option<SliderDrag>,
+    map: Arc<Mutex<TileMap>>,
+    error_log: ErrorLog,
 }
 
 impl LuaUIBindings {
@@ -51,13 +553,18 @@ impl LuaUIBindings {
         camera: Arc<Mutex<CameraController>>,
         input: Arc<Mutex<InputManager>>,
         map: Arc<Mutex<TileMap>>,
+        brush: Arc<Mutex<Brush>>,
+        indicators: Arc<Mutex<OffscreenIndicators>>,
     ) -> Self {
-        let components = Arc::new(Mutex::new(Vec::new()));
+        let components = Arc::new(Mutex::new(Components::default()));
         {
             let lua = &lua_engine.lock().unwrap().lua;
             let globals = lua.globals();
             let ui = lua.create_table().unwrap();
             let tile = lua.create_table().unwrap();
+            let brush_table = lua.create_table().unwrap();
+            let camera_table = lua.create_table().unwrap();
+            let indicators_table = lua.create_table().unwrap();
             {
                 let components = components.clone();
                 lua.create_function(move |_, (x, y, handler): (f32, f32, LuaFunction)| {
@@ -70,6 +577,172 @@ impl LuaUIBindings {
                 .and_then(|f| ui.set("label", f))
                 .unwrap();
             }
+            {
+                let components = components.clone();
+                lua.create_function(
+                    move |_,
+                          (x, y, label, on_click, is_active): (
+                        f32,
+                        f32,
+                        String,
+                        LuaFunction,
+                        Option<LuaFunction>,
+                    )| {
+                        components.lock().unwrap().push(UIComponent::Button {
+                            x,
+                            y,
+                            label,
+                            on_click,
+                            is_active,
+                        });
+                        Ok(())
+                    },
+                )
+                .and_then(|f| ui.set("button", f))
+                .unwrap();
+            }
+            {
+                let components = components.clone();
+                lua.create_function(
+                    move |_, (x, y, size, handler): (f32, f32, f32, LuaFunction)| {
+                        components.lock().unwrap().push(UIComponent::TilePreview {
+                            x,
+                            y,
+                            size,
+                            handler,
+                        });
+                        Ok(())
+                    },
+                )
+                .and_then(|f| ui.set("tile_preview", f))
+                .unwrap();
+            }
+            {
+                let components = components.clone();
+                lua.create_function(
+                    move |_, (x, y, w, h, handler): (f32, f32, f32, f32, LuaFunction)| {
+                        components.lock().unwrap().push(UIComponent::Sparkline {
+                            rect: Rect::new(x, y, w, h),
+                            handler,
+                        });
+                        Ok(())
+                    },
+                )
+                .and_then(|f| ui.set("sparkline", f))
+                .unwrap();
+            }
+            {
+                let components = components.clone();
+                lua.create_function(
+                    move |_, (x, y, w, h, handler): (f32, f32, f32, f32, LuaFunction)| {
+                        components.lock().unwrap().push(UIComponent::BarChart {
+                            rect: Rect::new(x, y, w, h),
+                            handler,
+                        });
+                        Ok(())
+                    },
+                )
+                .and_then(|f| ui.set("bar_chart", f))
+                .unwrap();
+            }
+            {
+                let components = components.clone();
+                lua.create_function(
+                    move |_,
+                          (x, y, w, h, title, handler): (
+                        f32,
+                        f32,
+                        f32,
+                        f32,
+                        String,
+                        LuaFunction,
+                    )| {
+                        components.lock().unwrap().push(UIComponent::LineChart {
+                            rect: Rect::new(x, y, w, h),
+                            title,
+                            handler,
+                        });
+                        Ok(())
+                    },
+                )
+                .and_then(|f| ui.set("line_chart", f))
+                .unwrap();
+            }
+            {
+                let components = components.clone();
+                let metrics = lua_engine.lock().unwrap().metrics.clone();
+                lua.create_function(
+                    move |_, (x, y, w, h, metric): (f32, f32, f32, f32, String)| {
+                        components.lock().unwrap().push(UIComponent::MetricChart {
+                            rect: Rect::new(x, y, w, h),
+                            metric,
+                            metrics: metrics.clone(),
+                        });
+                        Ok(())
+                    },
+                )
+                .and_then(|f| ui.set("metric_chart", f))
+                .unwrap();
+            }
+            {
+                let components = components.clone();
+                // What `build` adds becomes part of the window, windows can be nested
+                lua
+                    .create_function(
+                        move |_,
+                              (x, y, w, h, label, build): (
+                            f32,
+                            f32,
+                            f32,
+                            f32,
+                            String,
+                            LuaFunction,
+                        )| {
+                            components.lock().unwrap().open_window();
+                            // Called without holding the lock, the components it adds need it
+                            let built = build.call::<()>(());
+                            components
+                                .lock()
+                                .unwrap()
+                                .close_window(Rect::new(x, y, w, h), label);
+                            built
+                        },
+                    )
+                    .and_then(|f| ui.set("window", f))
+                    .unwrap();
+            }
+            {
+                let components = components.clone();
+                lua.create_function(
+                    move |_,
+                          (x, y, w, label, min, max, handler): (
+                        f32,
+                        f32,
+                        f32,
+                        String,
+                        f64,
+                        f64,
+                        LuaFunction,
+                    )| {
+                        if min > max {
+                            return Err(LuaError::RuntimeError(format!(
+                                "Slider '{}' can't go from {} down to {}",
+                                label, min, max
+                            )));
+                        }
+                        components.lock().unwrap().push(UIComponent::Slider {
+                            rect: Rect::new(x, y, w, BUTTON_HEIGHT),
+                            label,
+                            min,
+                            max,
+                            handler,
+                        });
+                        Ok(())
+                    },
+                )
+                .and_then(|f| ui.set("slider", f))
+                .unwrap();
+            }
             lua.create_function(move |_, ()| Ok(get_fps()))
                 .and_then(|f| ui.set("fps", f))
                 .unwrap();
@@ -100,21 +773,373 @@ impl LuaUIBindings {
                 .and_then(|f| tile.set("at", f))
                 .unwrap();
             }
+            {
+                let map = map.clone();
+                lua.create_function(move |lua_ctx, id: usize| {
+                    let binding = map.lock().unwrap();
+                    let props = binding.manifest.properties(id);
+                    let props_table = lua_ctx.create_table()?;
+                    props_table.set("walkable", props.walkable)?;
+                    props_table.set("buildable", props.buildable)?;
+                    props_table.set("movement_cost", props.movement_cost)?;
+                    props_table.set("tags", props.tags.clone())?;
+                    Ok(props_table)
+                })
+                .and_then(|f| tile.set("props", f))
+                .unwrap();
+            }
+            {
+                let map = map.clone();
+                // Corners of the area the map's tiles cover: min_x, min_y, max_x, max_y
+                lua.create_function(move |_, ()| {
+                    let bounds = map.lock().unwrap().bounds;
+                    Ok((bounds.min_x, bounds.min_y, bounds.max_x, bounds.max_y))
+                })
+                .and_then(|f| tile.set("bounds", f))
+                .unwrap();
+            }
+            {
+                let map = map.clone();
+                // A color, palette name or hex string tints the tile, nil takes the tint off
+                lua.create_function(move |_, (x, y, color): (i32, i32, Option<Rgba>)| {
+                    let tints = &mut map.lock().unwrap().tints;
+                    match color {
+                        Some(color) => {
+                            tints.insert((x, y), Color::new(color.r, color.g, color.b, color.a))
+                        }
+                        None => tints.remove(&(x, y)),
+                    };
+                    Ok(())
+                })
+                .and_then(|f| tile.set("tint", f))
+                .unwrap();
+            }
+            {
+                let map = map.clone();
+                lua.create_function(move |_, ()| {
+                    map.lock().unwrap().tints.clear();
+                    Ok(())
+                })
+                .and_then(|f| tile.set("clear_tints", f))
+                .unwrap();
+            }
+            {
+                let brush = brush.clone();
+                lua.create_function(move |lua_ctx, ()| {
+                    let brush = brush.lock().unwrap();
+                    let brush_info = lua_ctx.create_table()?;
+                    brush_info.set("tile", brush.tile_id)?;
+                    brush_info.set("size", brush.size)?;
+                    brush_info.set("shape", brush.shape.name())?;
+                    Ok(brush_info)
+                })
+                .and_then(|f| brush_table.set("get", f))
+                .unwrap();
+            }
+            {
+                let brush = brush.clone();
+                lua.create_function(move |_, tile_id: Option<usize>| {
+                    brush.lock().unwrap().tile_id = tile_id;
+                    Ok(())
+                })
+                .and_then(|f| brush_table.set("set_tile", f))
+                .unwrap();
+            }
+            {
+                let brush = brush.clone();
+                lua.create_function(move |_, size: i32| {
+                    brush.lock().unwrap().size = size.clamp(1, BRUSH_MAX_SIZE);
+                    Ok(())
+                })
+                .and_then(|f| brush_table.set("set_size", f))
+                .unwrap();
+            }
+            {
+                let brush = brush.clone();
+                lua.create_function(move |_, name: String| {
+                    let shape = match name.as_str() {
+                        "square" => BrushShape::Square,
+                        "circle" => BrushShape::Circle,
+                        _ => {
+                            return Err(LuaError::RuntimeError(format!(
+                                "Unknown brush shape '{}'",
+                                name
+                            )))
+                        }
+                    };
+                    brush.lock().unwrap().shape = shape;
+                    Ok(())
+                })
+                .and_then(|f| brush_table.set("set_shape", f))
+                .unwrap();
+            }
+            {
+                let camera = camera.clone();
+                let map = map.clone();
+                lua.create_function(move |_, ()| {
+                    let bounds = map.lock().unwrap().bounds;
+                    camera.lock().unwrap().fit(&bounds);
+                    Ok(())
+                })
+                .and_then(|f| camera_table.set("fit", f))
+                .unwrap();
+            }
+            {
+                let camera = camera.clone();
+                lua.create_function(move |_, ()| Ok(camera.lock().unwrap().zoom))
+                    .and_then(|f| camera_table.set("zoom", f))
+                    .unwrap();
+            }
+            {
+                let camera = camera.clone();
+                lua.create_function(move |_, zoom: f32| {
+                    camera.lock().unwrap().set_zoom(zoom);
+                    Ok(())
+                })
+                .and_then(|f| camera_table.set("set_zoom", f))
+                .unwrap();
+            }
+            {
+                let camera = camera.clone();
+                lua.create_function(move |_, (duration, amplitude): (f32, f32)| {
+                    camera.lock().unwrap().shake(duration, amplitude);
+                    Ok(())
+                })
+                .and_then(|f| camera_table.set("shake", f))
+                .unwrap();
+            }
+            {
+                let camera = camera.clone();
+                lua.create_function(move |_, (amount, duration): (f32, f32)| {
+                    camera.lock().unwrap().zoom_punch(amount, duration);
+                    Ok(())
+                })
+                .and_then(|f| camera_table.set("punch", f))
+                .unwrap();
+            }
+            {
+                let camera = camera.clone();
+                lua.create_function(move |_, (x, y, duration): (i32, i32, Option<f32>)| {
+                    let target = TilePosition::new(x, y).center_world_pos();
+                    camera
+                        .lock()
+                        .unwrap()
+                        .pan_to(target, duration.unwrap_or(0.5));
+                    Ok(())
+                })
+                .and_then(|f| camera_table.set("pan_to", f))
+                .unwrap();
+            }
+            {
+                let indicators = indicators.clone();
+                lua.create_function(move |_, enabled: bool| {
+                    indicators.lock().unwrap().enabled = enabled;
+                    Ok(())
+                })
+                .and_then(|f| indicators_table.set("set_enabled", f))
+                .unwrap();
+            }
+            {
+                let indicators = indicators.clone();
+                lua.create_function(move |_, person: u32| {
+                    indicators.lock().unwrap().tracked.insert(PersonId(person));
+                    Ok(())
+                })
+                .and_then(|f| indicators_table.set("track", f))
+                .unwrap();
+            }
+            {
+                let indicators = indicators.clone();
+                lua.create_function(move |_, person: u32| {
+                    indicators.lock().unwrap().tracked.remove(&PersonId(person));
+                    Ok(())
+                })
+                .and_then(|f| indicators_table.set("untrack", f))
+                .unwrap();
+            }
+            {
+                let indicators = indicators.clone();
+                lua.create_function(move |_, ()| {
+                    let mut tracked: Vec<u32> = indicators
+                        .lock()
+                        .unwrap()
+                        .tracked
+                        .iter()
+                        .map(|id| id.0)
+                        .collect();
+                    tracked.sort_unstable();
+                    Ok(tracked)
+                })
+                .and_then(|f| indicators_table.set("tracked", f))
+                .unwrap();
+            }
+            {
+                let indicators = indicators.clone();
+                lua.create_function(move |_, (r, g, b): (f32, f32, f32)| {
+                    indicators.lock().unwrap().color = Color::new(r, g, b, 1.0);
+                    Ok(())
+                })
+                .and_then(|f| indicators_table.set("set_color", f))
+                .unwrap();
+            }
             ui.set("tile", tile).unwrap();
+            ui.set("brush", brush_table).unwrap();
+            ui.set("indicators", indicators_table).unwrap();
             globals.set("ui", ui).unwrap();
+            globals.set("camera", camera_table).unwrap();
+        }
+        let error_log = lua_engine.lock().unwrap().error_log.clone();
+        Self {
+            components,
+            values: Vec::new(),
+            timings: Vec::new(),
+            dragging: None,
+            map,
+            error_log,
+        }
+    }
+
+    /// Whether any component is under the mouse or a slider is dragged, world tools ignore the
+    /// mouse then
+    pub fn captures_mouse(&self, mouse_pos: Vec2) -> bool {
+        self.dragging.is_some()
+            || self
+                .components
+                .lock()
+                .unwrap()
+                .roots
+                .iter()
+                .any(|component| component.captures_mouse(mouse_pos))
+    }
+
+    pub fn update(&mut self) {
+        let mouse_pos = Vec2::from(mouse_position());
+        self.drag_slider(mouse_pos);
+        if !is_mouse_button_pressed(MouseButton::Left) {
+            return;
+        }
+
+        let clicked = self
+            .components
+            .lock()
+            .unwrap()
+            .roots
+            .iter()
+            .find_map(|component| component.clicked_handler(mouse_pos));
+
+        // Call the handler without holding the lock, it may register new components
+        if let Some(handler) = clicked
+            && let Err(e) = handler.call::<()>(())
+        {
+            self.error_log.report("Button handler", e);
+        }
+        self.dragging = self
+            .components
+            .lock()
+            .unwrap()
+            .roots
+            .iter()
+            .find_map(|component| component.slider_at(mouse_pos));
+        self.drag_slider(mouse_pos);
+    }
+
+    // Hand the value under the mouse to the dragged slider when it changed
+    fn drag_slider(&mut self, mouse_pos: Vec2) {
+        if !is_mouse_button_down(MouseButton::Left) {
+            self.dragging = None;
+        }
+        let Some(drag) = &mut self.dragging else {
+            return;
+        };
+        let value = drag.value_at(mouse_pos.x);
+        if drag.value == Some(value) {
+            return;
+        }
+        drag.value = Some(value);
+        if let Err(e) = drag.handler.call::<()>(value) {
+            self.error_log.report("Slider handler", e);
         }
-        Self { components }
+    }
+    // Returns the number of components drawn. Without `refresh` the Lua handlers don't run and
+    // what they returned last is drawn again, except for components added since.
+    pub fn draw(&mut self, refresh: bool) -> usize {
+        let components = &self.components.lock().unwrap().roots;
+        for (index, component) in components.iter().enumerate() {
+            if refresh || index >= self.values.len() {
+                let mut lua_ms = 0.0;
+                let value = component.refresh(&self.error_log, &mut lua_ms);
+                if index < self.values.len() {
+                    self.values[index] = value;
+                    self.timings[index].record(lua_ms);
+                } else {
+                    self.values.push(value);
+                    self.timings
+                        .push(ComponentTiming::new(component.name(), lua_ms));
+                }
+            }
+            component.draw(&self.values[index], &self.map);
+        }
+        components.len()
     }
 
-    pub fn update(&mut self) {}
-    pub fn draw(&self) {
-        // Draw the UI
+    /// One line per component as of the last frame drawn, in the order they were added
+    pub fn describe(&self) -> Vec<String> {
         self.components
             .lock()
             .unwrap()
+            .roots
             .iter()
-            .for_each(|component| {
-                component.draw();
-            })
+            .zip(&self.values)
+            .map(|(component, value)| component.describe(value))
+            .collect()
+    }
+
+    /// How many components the scripts added, children count with their parent
+    #[cfg(feature = "frame-test")]
+    pub fn component_count(&self) -> usize {
+        self.components.lock().unwrap().roots.len()
+    }
+
+    /// Lua time of the components in the order they were added, as of the last frame drawn
+    pub fn timings(&self) -> &[ComponentTiming] {
+        &self.timings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_windows_take_the_components_added_while_they_are_built() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let engine = LuaEngine::new(command_rx);
+        let label = || UIComponent::Label {
+            x: 0.0,
+            y: 0.0,
+            handler: engine.lua.create_function(|_, ()| Ok("")).unwrap(),
+        };
+
+        let mut components = Components::default();
+        components.push(label());
+        components.open_window();
+        components.push(label());
+        components.open_window();
+        components.push(label());
+        components.close_window(Rect::new(0.0, 0.0, 50.0, 50.0), "inner".to_string());
+        components.close_window(Rect::new(0.0, 0.0, 100.0, 100.0), "outer".to_string());
+        components.push(label());
+
+        let names: Vec<String> = components.roots.iter().map(UIComponent::name).collect();
+        assert_eq!(
+            names,
+            vec!["label at 0, 0", "window 'outer'", "label at 0, 0"]
+        );
+        let UIComponent::Window { children, .. } = &components.roots[1] else {
+            panic!("expected a window");
+        };
+        let names: Vec<String> = children.iter().map(UIComponent::name).collect();
+        assert_eq!(names, vec!["label at 0, 0", "window 'inner'"]);
     }
 }
@@ -0,0 +1,171 @@
+//! Recording the edits made with the tools as the Lua calls doing the same
+
+use crate::config::MACROS_DIR;
+use lua_engine::lua_engine::LuaEngine;
+use lua_engine::LuaError;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct RecorderState {
+    recording: bool,
+    // One Lua call per line
+    lines: Vec<String>,
+}
+
+/// The calls recorded from the tools, shared with Lua as `macro`. Ctrl+M starts recording and
+/// stops it again, saving the script to `MACROS_DIR`:
+///
+/// ```lua
+/// macro.start()
+/// local script = macro.stop()   -- "map.place(3, 4, 12)\nmap.erase(5, 4)\n..."
+/// macro.play("macros/island.lua")
+/// ```
+#[derive(Clone, Default)]
+pub struct MacroRecorder {
+    state: Arc<Mutex<RecorderState>>,
+}
+
+impl MacroRecorder {
+    pub(crate) fn new(lua_engine: &Arc<Mutex<LuaEngine>>) -> Self {
+        let recorder = Self::default();
+
+        let lua = &lua_engine.lock().unwrap().lua;
+        let table = lua.create_table().unwrap();
+        {
+            let recorder = recorder.clone();
+            lua.create_function(move |_, ()| {
+                recorder.start();
+                Ok(())
+            })
+            .and_then(|f| table.set("start", f))
+            .unwrap();
+        }
+        {
+            let recorder = recorder.clone();
+            // Returns the script recorded
+            lua.create_function(move |_, ()| Ok(recorder.stop()))
+                .and_then(|f| table.set("stop", f))
+                .unwrap();
+        }
+        {
+            let recorder = recorder.clone();
+            lua.create_function(move |_, ()| Ok(recorder.is_recording()))
+                .and_then(|f| table.set("recording", f))
+                .unwrap();
+        }
+        {
+            let recorder = recorder.clone();
+            lua.create_function(move |_, ()| Ok(recorder.script()))
+                .and_then(|f| table.set("script", f))
+                .unwrap();
+        }
+        {
+            let recorder = recorder.clone();
+            lua.create_function(move |_, path: String| {
+                recorder.save(&path).map_err(LuaError::RuntimeError)
+            })
+            .and_then(|f| table.set("save", f))
+            .unwrap();
+        }
+        {
+            let recorder = recorder.clone();
+            // Runs a saved macro, or the one recorded last without a path
+            lua.create_function(move |lua, path: Option<String>| {
+                let (script, name) = match path {
+                    Some(path) => {
+                        let script = fs::read_to_string(&path).map_err(|e| {
+                            LuaError::RuntimeError(format!("Failed to read {}: {}", path, e))
+                        })?;
+                        (script, format!("@{}", path))
+                    }
+                    None => (recorder.script(), "=macro".to_string()),
+                };
+                lua.load(script).set_name(name).exec()
+            })
+            .and_then(|f| table.set("play", f))
+            .unwrap();
+        }
+        lua.globals().set("macro", table).unwrap();
+
+        recorder
+    }
+
+    /// Start recording a new macro, the one recorded before is dropped
+    pub(crate) fn start(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.recording = true;
+        state.lines.clear();
+    }
+
+    /// Stop recording, returns the script recorded
+    pub(crate) fn stop(&self) -> String {
+        self.state.lock().unwrap().recording = false;
+        self.script()
+    }
+
+    pub(crate) fn is_recording(&self) -> bool {
+        self.state.lock().unwrap().recording
+    }
+
+    /// Number of calls recorded so far
+    pub(crate) fn len(&self) -> usize {
+        self.state.lock().unwrap().lines.len()
+    }
+
+    /// Append a Lua call while recording
+    pub(crate) fn record(&self, call: String) {
+        let mut state = self.state.lock().unwrap();
+        if state.recording {
+            state.lines.push(call);
+        }
+    }
+
+    /// The calls recorded, one per line
+    pub(crate) fn script(&self) -> String {
+        let state = self.state.lock().unwrap();
+        state
+            .lines
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+
+    pub(crate) fn save(&self, path: &str) -> Result<(), String> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+        }
+        fs::write(path, self.script()).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+
+    /// Stop recording and save the macro as the first free `macro_<n>.lua` of `MACROS_DIR`,
+    /// returns where it went
+    pub(crate) fn stop_and_save(&self) -> Result<String, String> {
+        self.stop();
+        let path = (1..)
+            .map(|n| format!("{}/macro_{}.lua", MACROS_DIR, n))
+            .find(|path| !Path::new(path).exists())
+            .unwrap();
+        self.save(&path).map(|()| path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_calls_while_recording_are_kept() {
+        let recorder = MacroRecorder::default();
+        recorder.record("map.place(0, 0, 1)".to_string());
+        recorder.start();
+        recorder.record("map.place(1, 0, 1)".to_string());
+        recorder.record("map.erase(2, 0)".to_string());
+        assert_eq!(recorder.len(), 2);
+        assert_eq!(recorder.stop(), "map.place(1, 0, 1)\nmap.erase(2, 0)\n");
+        recorder.record("map.erase(3, 0)".to_string());
+        assert_eq!(recorder.len(), 2);
+        assert!(!recorder.is_recording());
+    }
+}
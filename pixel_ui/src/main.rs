@@ -1,11 +1,65 @@
+mod accessibility;
+mod animation;
+mod batch;
+mod brush;
+mod budget;
 mod camera;
+mod charts;
+mod code_editor;
+mod command_palette;
 mod console;
+mod console_config;
 mod debug;
+mod debugger_panel;
+mod describe;
+mod docs_browser;
+mod effects;
+mod error_overlay;
+mod fixed_clock;
+mod fog;
+#[cfg(feature = "frame-test")]
+mod frame_test;
+mod goals_panel;
+mod hud;
+mod indicators;
 mod input;
+mod layers;
+mod load_report_dialog;
+mod lua_input;
+mod lua_syntax;
 mod lua_ui_integration;
+mod macro_recorder;
+mod map_editing;
+mod map_file;
+mod mod_settings_panel;
+mod notifications_panel;
+mod overlay_layer;
+mod pathing;
+mod people;
+mod pool;
+mod portals;
+mod profiler;
+mod replay_viewer;
+mod routes;
+mod scenario_overlay;
+mod script_menu;
+mod selection;
+mod session;
+mod speech;
+mod splash;
+mod streaming;
+mod tile_inspector;
+mod tileset;
+mod tools;
+mod tutorial_overlay;
+mod vehicles;
+mod view_settings;
+mod viewport;
+mod watch;
+mod zones;
 
 use macroquad::prelude::*;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::path::{Path, PathBuf};
 use std::sync::mpsc::Sender;
 use std::sync::{mpsc, Arc, Mutex};
@@ -17,33 +71,281 @@ mod config {
 
     pub const TILE_SIZE: f32 = 32.0;
     pub const SOURCE_TILE_SIZE: f32 = 16.0;
-    pub const ZOOM_SPEED: f32 = 1.3;
+    /// Tiles along each side of a chunk, the map is drawn one prepared mesh per chunk and layer
+    pub const TILE_CHUNK_SIZE: i32 = 16;
+    /// Factor one mouse wheel notch or zoom key press zooms by, unless the view settings say
+    /// otherwise
+    pub const ZOOM_STEP: f32 = 1.3;
+    pub const ZOOM_STEP_MAX: f32 = 4.0;
+    pub const VIEW_CONFIG_PATH: &str = "view.json";
     pub const ZOOM_MIN: f32 = 0.02;
     pub const ZOOM_MAX: f32 = 5.0;
+    pub const FIT_MARGIN: f32 = 0.95;
     pub const DRAG_THRESHOLD: f32 = 5.0;
+    pub const DOUBLE_CLICK_TIME: f32 = 0.3;
+    pub const DOUBLE_CLICK_DISTANCE: f32 = 5.0;
+    pub const LONG_PRESS_TIME: f32 = 0.6;
+    pub const CONSOLE_HEIGHT_RATIO: f32 = 0.4;
+    pub const CONSOLE_INPUT_HEIGHT: f32 = 180.0;
+    pub const CONSOLE_LINE_HEIGHT: f32 = 20.0;
+    pub const CONSOLE_CONFIG_PATH: &str = "console.json";
+    pub const CONSOLE_HISTORY_SIZE: usize = 500;
+    /// Scripts the quick-run menu of the console remembers having run
+    pub const CONSOLE_RECENT_SCRIPTS: usize = 10;
+    pub const SCRIPT_MENU_WIDTH: f32 = 700.0;
+    pub const COMMAND_PALETTE_WIDTH: f32 = 500.0;
+    /// Matches the command palette shows below the query
+    pub const COMMAND_PALETTE_ROWS: usize = 12;
+    pub const DOCS_BROWSER_WIDTH: f32 = 900.0;
+    pub const DOCS_BROWSER_HEIGHT: f32 = 560.0;
+    /// Width of the list of modules and methods on the left of the docs browser
+    pub const DOCS_BROWSER_TREE_WIDTH: f32 = 260.0;
+    /// Where the quick-run menu of the console looks for scripts
+    pub const SCRIPTS_DIR: &str = "scripts";
     pub const SELECTED_TILE_ZOOM: f32 = 8.0;
     pub const FPS_HISTORY_SIZE: usize = 60;
+    /// Weight of the last frame in the rolling average of the Lua time of a UI component
+    pub const COMPONENT_TIMING_SMOOTHING: f64 = 0.05;
+    /// UI components listed in the debug window, the slowest on average
+    pub const COMPONENT_TIMINGS_SHOWN: usize = 5;
+    /// Height of the Lua time of the mods per frame in the debug window
+    pub const MOD_BUDGET_CHART_HEIGHT: f32 = 60.0;
+    /// Colors of the mods in the debug window, the slowest on average first
+    pub const MOD_BUDGET_COLORS: [Color; 6] = [
+        Color::new(0.95, 0.35, 0.3, 1.0),
+        Color::new(0.95, 0.7, 0.25, 1.0),
+        Color::new(0.4, 0.8, 0.4, 1.0),
+        Color::new(0.3, 0.75, 1.0, 1.0),
+        Color::new(0.7, 0.5, 0.95, 1.0),
+        Color::new(0.6, 0.6, 0.6, 1.0),
+    ];
+    /// Milliseconds a frame may take before the frame budget starts leaving work out
+    pub const FRAME_BUDGET_MS: f64 = 25.0;
+    /// Frames in a row over the budget before the next piece of work is left out
+    pub const FRAME_BUDGET_OVER_FRAMES: u32 = 10;
+    /// Frames in a row well within the budget before the last piece of work left out comes back
+    pub const FRAME_BUDGET_RECOVER_FRAMES: u32 = 120;
+    /// Share of the budget a frame may take to count as well within it
+    pub const FRAME_BUDGET_HEADROOM: f64 = 0.75;
     pub const BENCHMARK_MAP_SIZE: usize = 1;
-    pub const CAMERA_SPEED: f32 = 5.0;
+    /// World pixels per second the camera pans at zoom 1 as a direction key goes down
+    pub const CAMERA_PAN_SPEED: f32 = 300.0;
+    /// Pan speed reached once a direction key was held for `CAMERA_PAN_RAMP_TIME` seconds
+    pub const CAMERA_PAN_MAX_SPEED: f32 = 1200.0;
+    pub const CAMERA_PAN_RAMP_TIME: f32 = 1.5;
+    /// How quickly the zoom follows the mouse wheel, the remaining gap shrinks by a factor of e
+    /// every `1 / ZOOM_SMOOTHING` seconds
+    pub const ZOOM_SMOOTHING: f32 = 15.0;
     pub const TILE_BUFFER: i32 = 2;
     pub const TEXT_BACKGROUND_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.7);
+    /// Opacity of the preview of the tiles the brush would place
+    pub const GHOST_ALPHA: f32 = 0.5;
     pub const TEXT_FONT_SIZE: f32 = 20.0;
     pub const TEXT_PADDING: f32 = 15.0;
+    /// Bounds of the HUD text scale of the accessibility settings
+    pub const TEXT_SCALE_MIN: f32 = 0.5;
+    pub const TEXT_SCALE_MAX: f32 = 3.0;
+    pub const ACCESSIBILITY_CONFIG_PATH: &str = "accessibility.json";
     pub const PERSON_SOURCE_TILE_SIZE: f32 = 32.0;
     pub const PERSON_TILE_SIZE: f32 = 32.0;
-    pub const PEOPLE_BENCHMARK_SIZE: usize = 100;
-    pub const PEOPLE_BENCHMARK_DISPERSION: i32 = 1;
+    pub const CROWD_BENCHMARK_SIZE: usize = 10_000;
+    pub const CROWD_BENCHMARK_REPORT_INTERVAL: f64 = 5.0;
+    /// Seconds a frame of the crowd benchmark may take, the crowd has to run at 60 FPS
+    pub const CROWD_BENCHMARK_FRAME_BUDGET: f64 = 1.0 / 60.0;
+    pub const PERSON_MEAN_IDLE_TIME: f32 = 0.83;
+    pub const OFFSCREEN_UPDATE_INTERVAL: usize = 4;
+    /// Tiles the path search looks at before it gives up on reaching a destination
+    pub const PATH_SEARCH_LIMIT: usize = 20_000;
+    /// Seconds between the departures of the members of a group sent somewhere, so they arrive
+    /// one after another instead of in a clump
+    pub const GROUP_DEPARTURE_INTERVAL: f32 = 0.15;
+    /// Seconds of a step of the people simulation, walking, idling and animating run in these
+    /// steps so they don't depend on the frame rate
+    pub const SIMULATION_STEP: f32 = 1.0 / 120.0;
+    /// Steps a frame may take, the simulation slows down on frames longer than that
+    pub const MAX_SIMULATION_STEPS: u32 = 12;
+    pub const BRUSH_MAX_SIZE: i32 = 9;
+    pub const BUTTON_HEIGHT: f32 = 30.0;
+    pub const BUTTON_PADDING: f32 = 10.0;
+    pub const BUTTON_COLOR: Color = Color::new(0.15, 0.15, 0.15, 0.85);
+    pub const BUTTON_ACTIVE_COLOR: Color = Color::new(0.2, 0.45, 0.2, 0.9);
+    pub const LAYERS_PANEL_WIDTH: f32 = 260.0;
+    pub const LAYERS_PANEL_ROW_HEIGHT: f32 = 95.0;
+    pub const GOALS_PANEL_WIDTH: f32 = 320.0;
+    pub const GOALS_PANEL_ROW_HEIGHT: f32 = 40.0;
+    pub const NOTIFICATIONS_PANEL_WIDTH: f32 = 450.0;
+    pub const TOAST_WIDTH: f32 = 400.0;
+    pub const TUTORIAL_PANEL_WIDTH: f32 = 420.0;
+    /// Outline of what a tutorial step points at, and the title of the step
+    pub const TUTORIAL_HIGHLIGHT_COLOR: Color = Color::new(1.0, 0.85, 0.2, 1.0);
+    pub const MOD_SETTINGS_PANEL_WIDTH: f32 = 400.0;
+    pub const MOD_SETTINGS_ROW_HEIGHT: f32 = 26.0;
+    pub const TILE_INSPECTOR_WIDTH: f32 = 280.0;
+    pub const TILE_INSPECTOR_ROW_HEIGHT: f32 = 24.0;
+    /// Pixels between the HUD elements and to the edges of the window, at a UI scale of 1
+    pub const HUD_MARGIN: f32 = 10.0;
+    /// Window height the HUD is drawn at its plain size for, it's scaled along with the height
+    /// between `HUD_AUTO_SCALE_MIN` and `HUD_AUTO_SCALE_MAX` times that
+    pub const HUD_REFERENCE_HEIGHT: f32 = 1080.0;
+    pub const HUD_AUTO_SCALE_MIN: f32 = 0.75;
+    pub const HUD_AUTO_SCALE_MAX: f32 = 3.0;
+    /// Bounds of the UI scale of the view settings
+    pub const UI_SCALE_MIN: f32 = 0.5;
+    pub const UI_SCALE_MAX: f32 = 3.0;
+    pub const FOG_UNEXPLORED_COLOR: Color = Color::new(0.0, 0.0, 0.0, 1.0);
+    pub const FOG_EXPLORED_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.5);
+    pub const ZONE_FILL_ALPHA: f32 = 0.2;
+    pub const ZONE_OUTLINE_WIDTH: f32 = 2.0;
+    pub const CHART_BACKGROUND_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.7);
+    pub const CHART_AXIS_COLOR: Color = Color::new(0.5, 0.5, 0.5, 1.0);
+    pub const CHART_LINE_COLOR: Color = Color::new(0.3, 0.75, 1.0, 1.0);
+    pub const CHART_FONT_SIZE: f32 = 14.0;
+    /// Ticks along each axis of a line chart, roughly, they are rounded to even steps
+    pub const CHART_TICKS: usize = 5;
+    pub const OVERLAY_LEGEND_WIDTH: f32 = 220.0;
+    pub const OVERLAY_LEGEND_BAR_HEIGHT: f32 = 14.0;
+    /// Slices of the ramp the legend bar is drawn from
+    pub const OVERLAY_LEGEND_STEPS: usize = 44;
+    /// Share of a tile a vehicle covers
+    pub const VEHICLE_SIZE: f32 = 0.7;
+    pub const VEHICLE_COLOR: Color = Color::new(0.85, 0.55, 0.1, 1.0);
+    pub const VEHICLE_OUTLINE_COLOR: Color = Color::new(0.1, 0.1, 0.1, 1.0);
+    /// Share of a tile the ring at a portal end covers
+    pub const PORTAL_SIZE: f32 = 0.9;
+    pub const PORTAL_COLOR: Color = Color::new(0.6, 0.3, 1.0, 1.0);
+    pub const ROUTE_COLOR: Color = Color::new(1.0, 0.8, 0.2, 0.6);
+    /// Color of the route the route tool adds waypoints to
+    pub const ROUTE_ACTIVE_COLOR: Color = Color::new(1.0, 0.55, 0.1, 1.0);
+    pub const ROUTE_LINE_WIDTH: f32 = 3.0;
+    /// Share of a tile the square at a waypoint covers
+    pub const ROUTE_WAYPOINT_SIZE: f32 = 0.3;
+    pub const MAP_FILE_PATH: &str = "maps/map.json";
+    /// Most tiles a single `map.fill` of the scripts may cover
+    pub const MAP_FILL_LIMIT: usize = 1 << 20;
+    /// Functions of the globals of this frontend that change the map or the people, denied in
+    /// spectator mode like the ones of `MUTATING_API`
+    pub const MUTATING_GLOBALS: &[(&str, &[&str])] = &[
+        ("map", &["place", "erase", "fill", "flood_fill"]),
+        (
+            "people",
+            &["spawn", "clear", "walk_to", "move_group", "set_movement"],
+        ),
+        ("routes", &["set", "remove"]),
+    ];
+    /// Where the macros recorded with Ctrl+M are saved, as `macro_<n>.lua`
+    pub const MACROS_DIR: &str = "macros";
+    /// Where the maps other than the main one are saved, as `<name>.json`, and the levels other
+    /// than the ground level of every map, as `<name>_<level>.json`
+    pub const MAPS_DIR: &str = "maps";
+    pub const PIP_WIDTH: f32 = 320.0;
+    pub const PIP_HEIGHT: f32 = 240.0;
+    pub const PIP_ZOOM: f32 = 2.0;
+    pub const INDICATOR_SIZE: f32 = 14.0;
+    pub const INDICATOR_MARGIN: f32 = 40.0;
+    pub const WATCH_INTERVAL: f64 = 1.0;
+    pub const WATCH_PANEL_WIDTH: f32 = 450.0;
+    pub const DEBUGGER_PANEL_WIDTH: f32 = 420.0;
+    pub const DEBUGGER_PANEL_HEIGHT: f32 = 320.0;
+    pub const ERROR_PANEL_WIDTH: f32 = 600.0;
+    pub const LUA_TICK_INTERVAL: f32 = 1.0 / 60.0;
+    pub const DEV_SCRIPT_PATH: &str = "scripts/scratch.lua";
+    pub const DEV_SCRIPT_POLL_INTERVAL: f64 = 0.5;
+    /// Seconds between two passes of `--stream` over the chunks around the cameras
+    pub const STREAM_INTERVAL: f32 = 1.0;
+    /// Chunks around the ones the cameras show that `--stream` keeps loaded, so panning doesn't
+    /// wait for the disk
+    pub const STREAM_MARGIN: i32 = 1;
+    pub const COMMAND_SOCKET_PATH: &str = "sb5s.sock";
+    /// Window, camera and open panels of the last session, restored on startup
+    pub const SESSION_PATH: &str = "session.json";
+    pub const SESSION_MIN_WINDOW_WIDTH: u32 = 320;
+    pub const SESSION_MIN_WINDOW_HEIGHT: u32 = 240;
+    /// Where `--keep-events` moves the older events unless `--event-archive` names a file, with
+    /// the extension of the codec. Lines of a JSON archive start with the checksum of the event,
+    /// `cut -d' ' -f2-` leaves the plain JSON lines.
+    pub const EVENT_ARCHIVE_NAME: &str = "events_archive";
+    /// Events a second `--replay` plays a run back at to start with, minus and plus halve and
+    /// double it
+    pub const REPLAY_EVENTS_PER_SECOND: f32 = 60.0;
+    pub const REPLAY_SPEED_MAX: f32 = 100_000.0;
+    pub const REPLAY_TIMELINE_HEIGHT: f32 = 100.0;
+    pub const REPLAY_DENSITY_COLOR: Color = Color::new(0.3, 0.75, 1.0, 0.8);
+    pub const REPLAY_CURSOR_COLOR: Color = Color::new(1.0, 0.85, 0.2, 1.0);
+    pub const REPLAY_PERSON_COLOR: Color = Color::new(0.4, 0.8, 0.4, 1.0);
+    /// Characters of the last event replayed shown under the timeline
+    pub const REPLAY_EVENT_TEXT_LENGTH: usize = 140;
+    pub const PLUGIN_DIR: &str = "plugins";
+    pub const MAX_MAP_EFFECTS: usize = 500;
+    pub const SPARKLE_DURATION: f32 = 0.6;
+    pub const FLOAT_TEXT_DURATION: f32 = 1.2;
+    /// World units a floating text rises over its lifetime
+    pub const FLOAT_TEXT_RISE: f32 = 24.0;
+    /// Bubbles of `people.say` are wrapped to lines at most this wide, in pixels
+    pub const SPEECH_BUBBLE_MAX_WIDTH: f32 = 220.0;
+    pub const SPEECH_FONT_SIZE: f32 = 16.0;
+    /// Seconds a bubble shows unless the script says otherwise
+    pub const SPEECH_DEFAULT_DURATION: f32 = 3.0;
+    /// Seconds between two bubbles of the same person
+    pub const SPEECH_GAP: f32 = 0.3;
+    /// Bubbles a person may have waiting, further ones are dropped
+    pub const SPEECH_QUEUE_LENGTH: usize = 3;
 }
 
 mod utils {
     use super::config::*;
     use macroquad::math::f32;
     use macroquad::prelude::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    // Bits of the f32 scales, 1.0 to begin with
+    static TEXT_SCALE: AtomicU32 = AtomicU32::new(0x3f80_0000);
+    static UI_SCALE: AtomicU32 = AtomicU32::new(0x3f80_0000);
+
+    /// Scale of the texts relative to the rest of the HUD, from the accessibility settings
+    pub fn set_text_scale(scale: f32) {
+        TEXT_SCALE.store(scale.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Scale of the whole HUD, from the view settings and the size of the window
+    pub fn set_ui_scale(scale: f32) {
+        UI_SCALE.store(scale.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn ui_scale() -> f32 {
+        f32::from_bits(UI_SCALE.load(Ordering::Relaxed))
+    }
+
+    /// Scale of the texts drawn by the helpers below, 1.0 is the default size
+    pub fn text_scale() -> f32 {
+        f32::from_bits(TEXT_SCALE.load(Ordering::Relaxed)) * ui_scale()
+    }
+
+    /// Size `draw_text_box` takes for the text, background included
+    pub fn text_box_size(text: &str) -> Vec2 {
+        let dimensions = measure_text(text, None, (TEXT_FONT_SIZE * text_scale()) as u16, 1.0);
+        let padding = TEXT_PADDING * text_scale();
+        Vec2::new(
+            dimensions.width + padding * 2.0,
+            dimensions.height + padding * 2.0,
+        )
+    }
+
+    /// `draw_text_with_background` with the top left corner of the background at `pos`
+    pub fn draw_text_box(text: &str, pos: Vec2, color: Color) {
+        let dimensions = measure_text(text, None, (TEXT_FONT_SIZE * text_scale()) as u16, 1.0);
+        let padding = TEXT_PADDING * text_scale();
+        draw_text_with_background(
+            text,
+            pos.x + padding,
+            pos.y + padding + dimensions.offset_y,
+            color,
+        );
+    }
 
     pub fn draw_text_with_background(text: &str, x: f32, y: f32, color: Color) {
-        let font_size = TEXT_FONT_SIZE;
+        let font_size = TEXT_FONT_SIZE * text_scale();
         let text_dimensions = measure_text(text, None, font_size as u16, 1.0);
-        let padding = TEXT_PADDING;
+        let padding = TEXT_PADDING * text_scale();
 
         // Draw background rectangle with padding
         draw_rectangle(
@@ -57,9 +359,32 @@ mod utils {
         // Draw text
         draw_text(text, x, y, font_size, color);
     }
+    pub fn yes_no(value: bool) -> &'static str {
+        if value {
+            "yes"
+        } else {
+            "no"
+        }
+    }
+
+    /// Size `draw_text_list` takes for the texts, background included
+    pub fn text_list_size(texts: &[(String, Color)]) -> Vec2 {
+        let font_size = TEXT_FONT_SIZE * text_scale();
+        let padding = TEXT_PADDING * text_scale();
+        let width = texts
+            .iter()
+            .map(|(text, _)| measure_text(text, None, font_size as u16, 1.0).width)
+            .fold(0.0, f32::max);
+        Vec2::new(
+            width + padding * 2.0,
+            texts.len() as f32 * (font_size + 4.0) + padding,
+        )
+    }
+
+    /// Lines under each other on one background, whose top left corner is at (`x` - padding, `y`)
     pub fn draw_text_list(texts: Vec<(String, Color)>, x: f32, y: f32) -> f32 {
-        let font_size = TEXT_FONT_SIZE;
-        let padding = TEXT_PADDING;
+        let font_size = TEXT_FONT_SIZE * text_scale();
+        let padding = TEXT_PADDING * text_scale();
 
         // Use a consistent line height based on font size rather than measuring each string
         let line_height = font_size + 4.0; // Consistent line height
@@ -94,22 +419,81 @@ mod utils {
     }
 }
 
+use crate::accessibility::Accessibility;
+use crate::animation::{AnimationController, AnimationState, CharacterManifest, CharacterSprites};
+use crate::batch::{QuadBatch, TileBatches};
+use crate::brush::Brush;
+use crate::budget::{Degradation, FrameBudget};
 use crate::camera::CameraController;
+use crate::command_palette::{CommandPalette, PaletteCommand};
 use crate::console::Console;
-use crate::debug::DebugWindow;
+use crate::debug::{DebugFrame, DebugWindow};
+use crate::debugger_panel::DebuggerPanel;
+use crate::describe::UiDescription;
+use crate::docs_browser::DocsBrowser;
+use crate::effects::MapEffects;
+use crate::error_overlay::ErrorOverlay;
+use crate::fixed_clock::FixedClock;
+use crate::fog::FogLayer;
+use crate::goals_panel::GoalsPanel;
+use crate::hud::{Anchor, HudLayout};
+use crate::indicators::OffscreenIndicators;
 use crate::input::InputManager;
+use crate::layers::{LayersPanel, TileLayer};
+use crate::load_report_dialog::LoadReportDialog;
+use crate::lua_input::LuaInputBindings;
 use crate::lua_ui_integration::LuaUIBindings;
+use crate::macro_recorder::MacroRecorder;
+use crate::map_file::{content_hash, LayerFile, LoadReport, MapFile, RouteFile, SaveVersion};
+use crate::mod_settings_panel::ModSettingsPanel;
+use crate::notifications_panel::NotificationsPanel;
+use crate::overlay_layer::OverlayLayer;
+use crate::pathing::{find_path, line_tiles, neighbors, smooth, Movement};
+use crate::people::{CrowdBenchmark, People, PersonId};
+use crate::portals::PortalLayer;
+use crate::profiler::FrameProfiler;
+use crate::replay_viewer::ReplayViewer;
+use crate::routes::{draw_routes, RouteEditor, Routes};
+use crate::scenario_overlay::ScenarioOverlay;
+use crate::selection::Selection;
+use crate::session::{CameraPlacement, Session, WindowGeometry};
+use crate::speech::{wrap, SpeechBubbles};
+use crate::streaming::ChunkStreamer;
+use crate::tile_inspector::{InspectorAction, TileInspector};
+use crate::tileset::{TileAtlas, TileProperties, TilesetManifest};
+use crate::tools::{StampTool, ToolInput, ToolRegistry, ToolWorld};
+use crate::tutorial_overlay::TutorialOverlay;
 use crate::utils::*;
+use crate::vehicles::VehicleLayer;
+use crate::view_settings::ViewSettings;
+use crate::viewport::Viewport;
+use crate::zones::ZoneLayer;
 use config::*;
-use lua_engine::lua_client::LuaClient;
+use lua_engine::actions::Action;
+use lua_engine::clock::{Clock, TimeMode};
+#[cfg(unix)]
+use lua_engine::command_socket::CommandSocket;
+use lua_engine::dev_script::DevScript;
+use lua_engine::lifecycle::{Hook, LifecycleHooks};
+use lua_engine::lua_client::{FrameTicker, LuaClient};
 use lua_engine::lua_engine::{LuaCommand, LuaEngine};
+use lua_engine::mod_budget::ModBudget;
+use lua_engine::mod_settings::MOD_SETTINGS_PATH;
+use lua_engine::permissions::Permissions;
+use lua_engine::script_args;
+use lua_engine::script_error::ScriptError;
+use lua_engine::tutorial::TUTORIAL_PROGRESS_PATH;
+use lua_engine::IntoLuaMulti;
+use lua_engine::{
+    Chunk, Codec, FsyncPolicy, MapEdits, Replay, RetentionPolicy, TileChange, World, MAIN_MAP,
+};
 
 #[derive(Clone)]
 struct Tile {
     id: usize,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct TilePosition {
     x: i32,
     y: i32,
@@ -132,8 +516,20 @@ impl TilePosition {
     fn to_world_pos(&self) -> Vec2 {
         Vec2::new(self.x as f32 * TILE_SIZE, self.y as f32 * TILE_SIZE)
     }
+
+    fn center_world_pos(&self) -> Vec2 {
+        self.to_world_pos() + Vec2::splat(TILE_SIZE / 2.0)
+    }
+
+    // Straight line distance in tiles
+    fn distance_to(&self, other: &TilePosition) -> f32 {
+        let dx = (other.x - self.x) as f32;
+        let dy = (other.y - self.y) as f32;
+        (dx * dx + dy * dy).sqrt()
+    }
 }
 
+#[derive(Clone, Copy)]
 struct MapBounds {
     min_x: i32,
     min_y: i32,
@@ -158,17 +554,57 @@ impl MapBounds {
         self.max_y = self.max_y.max(pos.y);
     }
 
+    fn contains(&self, pos: &TilePosition) -> bool {
+        (self.min_x..=self.max_x).contains(&pos.x) && (self.min_y..=self.max_y).contains(&pos.y)
+    }
+
     fn as_tuple(&self) -> (i32, i32, i32, i32) {
         (self.min_x, self.min_y, self.max_x, self.max_y)
     }
 }
 
 struct TileMap {
-    tiles: HashMap<(i32, i32), Tile>,
+    layers: Vec<TileLayer>,
+    active_layer: usize,
     tileset: Texture2D,
     visible_tiles_count: usize,
     bounds: MapBounds,
-    tiles_per_row: f32,
+    /// Where the tiles are in the tileset
+    atlas: TileAtlas,
+    /// Number of tiles in the tileset, valid tile ids are below it
+    tile_ids: usize,
+    manifest: TilesetManifest,
+    /// Whether the map came from the saved map file rather than being generated
+    loaded_from_file: bool,
+    /// Counts the edits, so what's derived from the tiles knows when to update
+    revision: u64,
+    /// Name of the map shown, the active one of the logic
+    name: String,
+    /// Level of the map shown, 0 is the ground level
+    level: i32,
+    /// The other maps and levels as they were left when switching away from them
+    stashed: HashMap<(String, i32), MapFile>,
+    /// Meshes the layers are drawn with, updated along with the tiles
+    batches: TileBatches,
+    /// Tints the scripts put on tiles, for heatmaps and zones, see `ui.tile.tint`
+    tints: HashMap<(i32, i32), Color>,
+    /// Patrol routes drawn on the map, saved along with it
+    routes: Routes,
+    /// State of the mods saved along with the map, see `LifecycleHooks::save_state`
+    mod_state: BTreeMap<String, serde_json::Value>,
+    /// What the map shown was saved with
+    saved_version: SaveVersion,
+    /// Content hash of the tileset manifest, saved to tell when tile ids may have changed
+    tileset_hash: String,
+    /// What loading the saved map at startup ran into, until it's shown
+    load_report: Option<LoadReport>,
+    /// Where the edits are published, so the scripts see them and they're replayed
+    edits: Option<MapEdits>,
+    /// Edits of the maps and levels not shown, applied once they are
+    offscreen_edits: HashMap<(String, i32), Vec<TileChange>>,
+    /// Chunks of the map shown that `--stream` unloaded, by column and row, with the edits
+    /// waiting for them to be loaded again
+    streamed_out: HashMap<(i32, i32), Vec<TileChange>>,
 }
 
 impl TileMap {
@@ -176,14 +612,78 @@ impl TileMap {
         let tileset = load_texture("assets/tileset.png").await.unwrap();
         tileset.set_filter(FilterMode::Nearest);
 
-        let tiles_per_row = (tileset.width() / SOURCE_TILE_SIZE).floor();
+        let manifest = TilesetManifest::load("assets/tileset.json");
+        let tileset_hash = std::fs::read("assets/tileset.json")
+            .map(|bytes| content_hash(&bytes))
+            .unwrap_or_default();
+        let atlas = TileAtlas::new(tileset.width(), tileset.height(), manifest.padding);
+        let mut map = Self::empty(tileset, atlas, manifest);
+        map.tileset_hash = tileset_hash;
+
+        // Use the saved map when there is one, otherwise generate the benchmark map
+        match MapFile::load(MAP_FILE_PATH) {
+            Ok((file, report)) if !file.layers.is_empty() => {
+                map.apply_map_file(file);
+                map.loaded_from_file = true;
+                map.load_report = Some(report);
+            }
+            Ok(_) => println!(
+                "Map file {} has no layers, generating a new map",
+                MAP_FILE_PATH
+            ),
+            // A map that exists but can't be read is reported rather than silently replaced
+            Err(e) if Path::new(MAP_FILE_PATH).exists() => {
+                println!("{}, generating a new map", e);
+                map.load_report = Some(LoadReport::failed(
+                    MAP_FILE_PATH,
+                    format!("{}. Saving will overwrite it with a new map.", e),
+                ));
+            }
+            Err(e) => println!("{}, generating a new map", e),
+        }
+        if map.layers.is_empty() {
+            map.generate();
+        }
+        map
+    }
+
+    /// A map without tiles drawn with the tileset
+    fn empty(tileset: Texture2D, atlas: TileAtlas, manifest: TilesetManifest) -> Self {
+        Self {
+            layers: Vec::new(),
+            active_layer: 0,
+            tileset,
+            visible_tiles_count: 0,
+            bounds: MapBounds::new(0, 0, 0, 0),
+            atlas,
+            tile_ids: atlas.tile_ids(),
+            manifest,
+            loaded_from_file: false,
+            revision: 0,
+            name: MAIN_MAP.to_string(),
+            level: 0,
+            stashed: HashMap::new(),
+            batches: TileBatches::default(),
+            tints: HashMap::new(),
+            routes: Routes::new(),
+            mod_state: BTreeMap::new(),
+            saved_version: SaveVersion::default(),
+            tileset_hash: String::new(),
+            load_report: None,
+            edits: None,
+            offscreen_edits: HashMap::new(),
+            streamed_out: HashMap::new(),
+        }
+    }
+
+    fn generate(&mut self) {
         let width = 16;
         let height = 16;
-        let mut tiles = HashMap::new();
+        let mut ground = TileLayer::new("ground");
 
         for y in 0..height * BENCHMARK_MAP_SIZE {
             for x in 0..width * BENCHMARK_MAP_SIZE {
-                tiles.insert(
+                ground.tiles.insert(
                     (x as i32, y as i32),
                     Tile {
                         id: (x + y * height) % 256,
@@ -192,25 +692,233 @@ impl TileMap {
             }
         }
 
-        let bounds = MapBounds::new(
+        self.layers = vec![ground, TileLayer::new("objects")];
+        self.active_layer = 0;
+        self.revision += 1;
+        self.batches.invalidate_all();
+        self.bounds = MapBounds::new(
             0,
             0,
             (width * BENCHMARK_MAP_SIZE - 1) as i32,
             (height * BENCHMARK_MAP_SIZE - 1) as i32,
         );
+    }
 
-        Self {
-            tiles,
-            tileset,
-            visible_tiles_count: 0,
-            bounds,
-            tiles_per_row,
+    fn apply_map_file(&mut self, file: MapFile) {
+        self.layers.clear();
+        let mut bounds: Option<MapBounds> = None;
+        for layer_file in file.layers {
+            let mut layer = TileLayer::new(&layer_file.name);
+            layer.visible = layer_file.visible;
+            layer.opacity = layer_file.opacity.clamp(0.0, 1.0);
+            for (x, y, id) in layer_file.tiles {
+                let pos = TilePosition::new(x, y);
+                match bounds.as_mut() {
+                    Some(bounds) => bounds.expand_to_include(&pos),
+                    None => bounds = Some(MapBounds::new(x, y, x, y)),
+                }
+                layer.tiles.insert((x, y), Tile { id });
+            }
+            self.layers.push(layer);
+        }
+        self.routes = file
+            .routes
+            .into_iter()
+            .map(|route| {
+                let waypoints = route
+                    .waypoints
+                    .into_iter()
+                    .map(|(x, y)| TilePosition::new(x, y))
+                    .collect();
+                (route.name, waypoints)
+            })
+            .collect();
+        self.mod_state = file.mod_state;
+        self.saved_version = file.version;
+        self.active_layer = file.active_layer.min(self.layers.len().saturating_sub(1));
+        self.bounds = bounds.unwrap_or(MapBounds::new(0, 0, 0, 0));
+        self.revision += 1;
+        self.batches.invalidate_all();
+    }
+
+    fn to_map_file(&self) -> MapFile {
+        let layers = self
+            .layers
+            .iter()
+            .map(|layer| {
+                let mut tiles: Vec<(i32, i32, usize)> = layer
+                    .tiles
+                    .iter()
+                    .map(|(&(x, y), tile)| (x, y, tile.id))
+                    .collect();
+                // Keep the file stable between saves
+                tiles.sort_by_key(|&(x, y, _)| (y, x));
+                LayerFile {
+                    name: layer.name.clone(),
+                    visible: layer.visible,
+                    opacity: layer.opacity,
+                    tiles,
+                }
+            })
+            .collect();
+        let routes = self
+            .routes
+            .iter()
+            .map(|(name, waypoints)| RouteFile {
+                name: name.clone(),
+                waypoints: waypoints.iter().map(|pos| (pos.x, pos.y)).collect(),
+            })
+            .collect();
+        MapFile {
+            version: self.saved_version.clone(),
+            active_layer: self.active_layer,
+            layers,
+            routes,
+            mod_state: self.mod_state.clone(),
+        }
+    }
+
+    // Show another map or level, the one shown so far is kept to return to. One not shown
+    // before comes from its file, or is generated when there is none.
+    fn switch_to(&mut self, name: &str, level: i32) {
+        let file = self.to_map_file();
+        let left = (
+            std::mem::replace(&mut self.name, name.to_string()),
+            std::mem::replace(&mut self.level, level),
+        );
+        self.stashed.insert(left, file);
+        self.layers.clear();
+        self.tints.clear();
+        self.routes.clear();
+        self.loaded_from_file = false;
+        match self.stashed.remove(&(name.to_string(), level)) {
+            Some(file) => self.apply_map_file(file),
+            None => match MapFile::load(&self.file_path()) {
+                Ok((file, report)) if !file.layers.is_empty() => {
+                    for line in report.lines() {
+                        println!("{}: {}", report.path, line);
+                    }
+                    self.apply_map_file(file);
+                    self.loaded_from_file = true;
+                }
+                Err(e) => {
+                    println!("{}, generating a new map", e);
+                    self.generate();
+                }
+                _ => self.generate(),
+            },
+        }
+        for change in self
+            .offscreen_edits
+            .remove(&(name.to_string(), level))
+            .unwrap_or_default()
+        {
+            self.apply_change(&change);
+        }
+    }
+
+    // Apply the edits published since the last call, the ones made here again too. Edits of
+    // the maps and levels not shown, or of unloaded chunks, wait until they are.
+    fn sync(&mut self) {
+        let Some(edits) = &self.edits else {
+            return;
+        };
+        for change in edits.take_changes() {
+            let location = &change.location;
+            if location.map.as_str() == self.name && location.z == self.level {
+                let chunk = Chunk::of(location);
+                match self.streamed_out.get_mut(&(chunk.x, chunk.y)) {
+                    Some(waiting) => waiting.push(change),
+                    None => self.apply_change(&change),
+                }
+            } else {
+                self.offscreen_edits
+                    .entry((location.map.to_string(), location.z))
+                    .or_default()
+                    .push(change);
+            }
+        }
+    }
+
+    // Set the cell of the layer named, adding the layer when a tile goes on one the map lacks
+    fn apply_change(&mut self, change: &TileChange) {
+        let pos = TilePosition::new(change.location.x, change.location.y);
+        let index = match self
+            .layers
+            .iter()
+            .position(|layer| layer.name == change.layer)
+        {
+            Some(index) => index,
+            None if change.tile.is_none() => return,
+            None => {
+                self.layers.push(TileLayer::new(&change.layer));
+                self.batches.invalidate_all();
+                self.layers.len() - 1
+            }
+        };
+        let tiles = &mut self.layers[index].tiles;
+        let changed = match change.tile {
+            Some(id) => {
+                let id = id as usize;
+                self.bounds.expand_to_include(&pos);
+                tiles
+                    .insert((pos.x, pos.y), Tile { id })
+                    .map(|tile| tile.id)
+                    != Some(id)
+            }
+            None => tiles.remove(&(pos.x, pos.y)).is_some(),
+        };
+        if changed {
+            self.revision += 1;
+            self.batches.invalidate(pos.x, pos.y);
+        }
+    }
+
+    // Publish edits of the active layer of the map shown before making them, the reason when
+    // they are rejected
+    fn publish(&self, positions: &[TilePosition], tile_id: Option<usize>) -> Result<(), String> {
+        let Some(edits) = &self.edits else {
+            return Ok(());
+        };
+        let layer = &self.layers[self.active_layer].name;
+        match tile_id {
+            Some(id) => {
+                let cells: Vec<_> = positions.iter().map(|pos| (pos.x, pos.y)).collect();
+                edits.place_at(&self.name, self.level, layer, &cells, id as u32)
+            }
+            None => positions
+                .iter()
+                .try_for_each(|pos| edits.remove_at(&self.name, self.level, layer, pos.x, pos.y)),
+        }
+    }
+
+    // The ground level of the main map keeps the file it always had
+    fn file_path(&self) -> String {
+        match (self.name.as_str(), self.level) {
+            (MAIN_MAP, 0) => MAP_FILE_PATH.to_string(),
+            (name, 0) => format!("{}/{}.json", MAPS_DIR, name),
+            (name, level) => format!("{}/{}_{}.json", MAPS_DIR, name, level),
+        }
+    }
+
+    // Saved with the version of this build and the mods registered
+    fn save(&mut self, mods: Vec<String>) {
+        let path = self.file_path();
+        self.saved_version = SaveVersion::current(&self.tileset_hash, mods);
+        match self.to_map_file().save(&path) {
+            Ok(()) => println!("Map saved to {}", path),
+            Err(e) => println!("Failed to save map: {}", e),
         }
     }
 
+    fn tile_count(&self) -> usize {
+        self.layers.iter().map(|layer| layer.tiles.len()).sum()
+    }
+
     fn get_visible_range(&self, camera: &CameraController) -> (i32, i32, i32, i32) {
-        let visible_world_width = screen_width() / camera.zoom;
-        let visible_world_height = screen_height() / camera.zoom;
+        let view_size = camera.view_size();
+        let visible_world_width = view_size.x / camera.zoom;
+        let visible_world_height = view_size.y / camera.zoom;
 
         let min_tile_x =
             ((camera.position.x - visible_world_width / 2.0) / TILE_SIZE).floor() as i32;
@@ -230,116 +938,224 @@ impl TileMap {
         (min_x, min_y, max_x, max_y)
     }
 
-    fn draw(&mut self, camera: &CameraController, selected_pos: Option<&TilePosition>) {
+    // Returns the number of tiles drawn, the tinted tiles and the selected ones, with
+    // `selected_color`, are drawn again in their tint on top
+    fn draw(
+        &mut self,
+        camera: &CameraController,
+        selected_tiles: &[TilePosition],
+        selected_color: Color,
+    ) -> usize {
         let (min_x, min_y, max_x, max_y) = self.get_visible_range(camera);
 
         // Skip drawing if nothing is visible
         if max_x < min_x || max_y < min_y {
-            self.visible_tiles_count = 0;
-            return;
+            return 0;
         }
 
-        // Collect visible tiles
-        let mut tiles_to_draw = Vec::new();
-        for x in (min_x - TILE_BUFFER).max(self.bounds.min_x)
-            ..=(max_x + TILE_BUFFER).min(self.bounds.max_x)
-        {
-            for y in (min_y - TILE_BUFFER).max(self.bounds.min_y)
-                ..=(max_y + TILE_BUFFER).min(self.bounds.max_y)
-            {
-                if let Some(tile) = self.tiles.get(&(x, y)) {
-                    tiles_to_draw.push((TilePosition::new(x, y), tile));
-                }
+        let range = (
+            (min_x - TILE_BUFFER).max(self.bounds.min_x),
+            (min_y - TILE_BUFFER).max(self.bounds.min_y),
+            (max_x + TILE_BUFFER).min(self.bounds.max_x),
+            (max_y + TILE_BUFFER).min(self.bounds.max_y),
+        );
+        let mut visible_tiles_count = 0;
+        for (index, layer) in self.layers.iter().enumerate() {
+            if layer.visible {
+                visible_tiles_count +=
+                    self.batches
+                        .draw_layer(index, layer, &self.tileset, self.atlas, range);
             }
         }
 
-        // Sort by ID for better rendering efficiency
-        tiles_to_draw.sort_by_key(|(_, tile)| tile.id);
-        self.visible_tiles_count = tiles_to_draw.len();
-
-        // Draw tiles
-        for (pos, tile) in tiles_to_draw {
-            let src_x = (tile.id as f32 % self.tiles_per_row) * SOURCE_TILE_SIZE;
-            let src_y = (tile.id as f32 / self.tiles_per_row).floor() * SOURCE_TILE_SIZE;
+        // Tinted and selected tiles are drawn on top of the prepared meshes
+        let (from_x, from_y, to_x, to_y) = range;
+        let tinted = self
+            .tints
+            .iter()
+            .filter(|((x, y), _)| (from_x..=to_x).contains(x) && (from_y..=to_y).contains(y))
+            .map(|(&(x, y), &color)| (TilePosition::new(x, y), color));
+        let selected = selected_tiles.iter().map(|pos| (*pos, selected_color));
+        let mut batch = QuadBatch::new();
+        for (pos, color) in tinted.chain(selected) {
+            if let Some(tile) = self.get_visible_tile(&pos) {
+                batch.push(
+                    &self.tileset,
+                    Rect::new(
+                        pos.x as f32 * TILE_SIZE,
+                        pos.y as f32 * TILE_SIZE,
+                        TILE_SIZE,
+                        TILE_SIZE,
+                    ),
+                    self.atlas.source(tile.id),
+                    color,
+                );
+            }
+        }
+        batch.flush();
+        visible_tiles_count
+    }
 
-            let is_selected =
-                selected_pos.map_or(false, |sel_pos| pos.x == sel_pos.x && pos.y == sel_pos.y);
-            let color = if is_selected { MAGENTA } else { WHITE };
+    // Tiles above the ground layer need buildable ground under them, the ground takes anything
+    fn can_place(&self, pos: &TilePosition) -> bool {
+        self.active_layer == 0
+            || self.layers[..self.active_layer]
+                .iter()
+                .rev()
+                .find_map(|layer| layer.tiles.get(&(pos.x, pos.y)))
+                .is_some_and(|tile| self.manifest.properties(tile.id).buildable)
+    }
 
-            draw_texture_ex(
+    // Translucent preview of placing the tile at the positions, tinted where it can't go
+    fn draw_ghost(&self, positions: &[TilePosition], tile_id: usize, invalid: Color) -> usize {
+        let mut batch = QuadBatch::new();
+        for pos in positions {
+            let color = if self.can_place(pos) { WHITE } else { invalid };
+            batch.push(
                 &self.tileset,
-                pos.x as f32 * TILE_SIZE,
-                pos.y as f32 * TILE_SIZE,
-                color,
-                DrawTextureParams {
-                    source: Some(Rect::new(src_x, src_y, SOURCE_TILE_SIZE, SOURCE_TILE_SIZE)),
-                    dest_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
-                    ..Default::default()
+                Rect::new(
+                    pos.x as f32 * TILE_SIZE,
+                    pos.y as f32 * TILE_SIZE,
+                    TILE_SIZE,
+                    TILE_SIZE,
+                ),
+                self.atlas.source(tile_id),
+                Color {
+                    a: GHOST_ALPHA,
+                    ..color
                 },
             );
         }
+        batch.flush();
+        positions.len()
     }
 
+    // Topmost tile at the position across all layers, this is what gameplay sees
     fn get_tile(&self, pos: &TilePosition) -> Option<&Tile> {
-        self.tiles.get(&(pos.x, pos.y))
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|layer| layer.tiles.get(&(pos.x, pos.y)))
     }
 
-    fn place_tile(&mut self, pos: &TilePosition, tile_id: usize) {
-        self.tiles.insert((pos.x, pos.y), Tile { id: tile_id });
-        self.bounds.expand_to_include(pos);
+    // Topmost tile the player can actually see, used for picking
+    fn get_visible_tile(&self, pos: &TilePosition) -> Option<&Tile> {
+        self.layers
+            .iter()
+            .rev()
+            .filter(|layer| layer.visible)
+            .find_map(|layer| layer.tiles.get(&(pos.x, pos.y)))
     }
 
-    fn get_initial_center(&self) -> Vec2 {
-        Vec2::new(
-            (self.bounds.max_x as f32 + self.bounds.min_x as f32) * TILE_SIZE / 2.0,
-            (self.bounds.max_y as f32 + self.bounds.min_y as f32) * TILE_SIZE / 2.0,
-        )
+    fn active_layer_mut(&mut self) -> &mut TileLayer {
+        &mut self.layers[self.active_layer]
     }
-}
-struct Animation {
-    frames: Vec<usize>,   // Tile IDs for each frame
-    current_frame: usize, // Current frame index
-    frame_time: f32,      // Time per frame in seconds
-    timer: f32,           // Current timer
-}
 
-impl Animation {
-    // Constructor now takes total_anim_time instead of frame_time
-    fn new(frames: Vec<usize>, total_anim_time: f32) -> Self {
-        // Calculate frame time to maintain consistent total animation time
-        let frame_time = if frames.is_empty() {
-            total_anim_time // Avoid division by zero
-        } else {
-            total_anim_time / frames.len() as f32
-        };
+    fn get_tile_properties(&self, pos: &TilePosition) -> Option<&TileProperties> {
+        self.get_tile(pos)
+            .map(|tile| self.manifest.properties(tile.id))
+    }
 
-        Self {
-            frames,
-            current_frame: 0,
-            frame_time,
-            timer: 0.0,
-        }
+    // Cost of entering a tile, None when it can't be walked on (empty map cells included)
+    fn movement_cost(&self, pos: &TilePosition) -> Option<f32> {
+        self.get_tile_properties(pos)
+            .filter(|props| props.walkable)
+            .map(|props| props.movement_cost)
     }
 
-    fn update(&mut self, dt: f32) {
-        self.timer += dt;
+    // Editing always targets the active layer. Edits fail with the reason where another owner
+    // locked the tiles, leaving them as they were.
+    fn place_tile(&mut self, pos: &TilePosition, tile_id: usize) -> Result<(), String> {
+        self.publish(&[*pos], Some(tile_id))?;
+        self.active_layer_mut()
+            .tiles
+            .insert((pos.x, pos.y), Tile { id: tile_id });
+        self.bounds.expand_to_include(pos);
+        self.revision += 1;
+        self.batches.invalidate(pos.x, pos.y);
+        Ok(())
+    }
+
+    // Replace the contiguous area of identical tiles (or empty cells) of the active layer around `start` within the map bounds
+    fn flood_fill(&mut self, start: &TilePosition, tile_id: usize) -> Result<usize, String> {
+        let bounds = self.bounds;
+        let tiles = &self.layers[self.active_layer].tiles;
+        let target = tiles.get(&(start.x, start.y)).map(|tile| tile.id);
+        if target == Some(tile_id) {
+            return Ok(0);
+        }
+
+        // Find the whole area first, it's filled at once or not at all
+        let mut filled = Vec::new();
+        let mut seen = HashSet::new();
+        let mut stack = vec![*start];
+        while let Some(pos) = stack.pop() {
+            if !bounds.contains(&pos)
+                || tiles.get(&(pos.x, pos.y)).map(|tile| tile.id) != target
+                || !seen.insert(pos)
+            {
+                continue;
+            }
+            filled.push(pos);
 
-        // Advance frame if timer exceeds frame_time
-        if self.timer >= self.frame_time && !self.frames.is_empty() {
-            self.timer -= self.frame_time;
-            self.current_frame = (self.current_frame + 1) % self.frames.len();
+            stack.push(TilePosition::new(pos.x + 1, pos.y));
+            stack.push(TilePosition::new(pos.x - 1, pos.y));
+            stack.push(TilePosition::new(pos.x, pos.y + 1));
+            stack.push(TilePosition::new(pos.x, pos.y - 1));
+        }
+        self.publish(&filled, Some(tile_id))?;
+        let tiles = &mut self.active_layer_mut().tiles;
+        for pos in &filled {
+            tiles.insert((pos.x, pos.y), Tile { id: tile_id });
         }
+        self.revision += 1;
+        self.batches.invalidate_all();
+        Ok(filled.len())
     }
 
-    fn get_current_frame(&self) -> usize {
-        if self.frames.is_empty() {
-            0
-        } else {
-            self.frames[self.current_frame]
+    // Every tile between the corners of the active layer, returns the number of tiles placed
+    fn fill_rect(
+        &mut self,
+        min: TilePosition,
+        max: TilePosition,
+        tile_id: usize,
+    ) -> Result<usize, String> {
+        let filled: Vec<_> = (min.y..=max.y)
+            .flat_map(|y| (min.x..=max.x).map(move |x| TilePosition::new(x, y)))
+            .collect();
+        self.publish(&filled, Some(tile_id))?;
+        let tiles = &mut self.layers[self.active_layer].tiles;
+        for pos in &filled {
+            tiles.insert((pos.x, pos.y), Tile { id: tile_id });
         }
+        self.bounds.expand_to_include(&min);
+        self.bounds.expand_to_include(&max);
+        self.revision += 1;
+        self.batches.invalidate_all();
+        Ok(filled.len())
+    }
+
+    // Bounds are kept as they are, the cell simply becomes empty on the active layer
+    fn remove_tile(&mut self, pos: &TilePosition) -> Result<Option<Tile>, String> {
+        if !self.layers[self.active_layer]
+            .tiles
+            .contains_key(&(pos.x, pos.y))
+        {
+            return Ok(None);
+        }
+        self.publish(&[*pos], None)?;
+        self.revision += 1;
+        self.batches.invalidate(pos.x, pos.y);
+        Ok(self.active_layer_mut().tiles.remove(&(pos.x, pos.y)))
     }
-}
 
+    fn get_initial_center(&self) -> Vec2 {
+        Vec2::new(
+            (self.bounds.max_x as f32 + self.bounds.min_x as f32) * TILE_SIZE / 2.0,
+            (self.bounds.max_y as f32 + self.bounds.min_y as f32) * TILE_SIZE / 2.0,
+        )
+    }
+}
 // Direction enum for people
 enum Direction {
     Up,
@@ -349,19 +1165,14 @@ enum Direction {
 }
 
 impl Direction {
-    fn get_animation_frames(&self, tiles_per_row: i32) -> Vec<usize> {
-        // Calculate the starting index and frame count based on direction
-        let (start_frame, frame_count) = match self {
-            Direction::Right => (0, tiles_per_row.min(8)), // First row, up to 8 frames
-            Direction::Left => (tiles_per_row, tiles_per_row.min(8)), // Second row
-            Direction::Down => (tiles_per_row * 2, tiles_per_row.min(8)), // Third row
-            Direction::Up => (tiles_per_row * 3, tiles_per_row.min(8)), // Fourth row
-        };
-
-        // Generate frames sequence with explicit type conversion from i32 to usize
-        (start_frame..start_frame + frame_count)
-            .map(|i| i as usize)
-            .collect()
+    // Row of the character sheets showing this direction
+    fn row(&self) -> usize {
+        match self {
+            Direction::Right => 0,
+            Direction::Left => 1,
+            Direction::Down => 2,
+            Direction::Up => 3,
+        }
     }
 
     // Get direction based on movement vector
@@ -387,58 +1198,80 @@ enum PersonState {
     Moving,
 }
 
+// The world position lives in `People`, stored next to the other positions for the hot loops
 struct Person {
-    position: Vec2,                    // Current world position
-    texture: Texture2D,                // Person texture
-    tile_pos: TilePosition,            // Current tile position
-    start_pos: Vec2,                   // Starting position for movement
-    target_pos: Option<Vec2>,          // Target world position for movement
+    sheet: usize,             // Index of the character, people are drawn grouped by it
+    tile_pos: TilePosition,   // Current tile position
+    start_pos: Vec2,          // Starting position for movement
+    target_pos: Option<Vec2>, // Target world position for movement
     target_tile: Option<TilePosition>, // Target tile position
-    animation: Animation,              // Current animation
-    direction: Direction,              // Facing direction
-    state: PersonState,                // Current state
-    move_timer: f32,                   // Timer for movement (0.0 to 1.0)
-    move_duration: f32,                // How long it takes to move one tile (seconds)
-    tiles_per_row: i32,                // Calculated per texture
+    animation: AnimationController, // Animation state, set by the behavior below or Lua
+    direction: Direction,     // Facing direction
+    state: PersonState,       // Current state
+    idle_timer: f32,          // Time left standing before moving on
+    move_timer: f32,          // Timer for movement (0.0 to 1.0)
+    move_duration: f32,       // How long it takes to move one tile (seconds)
+    destination: Option<TilePosition>, // Where to walk to once the current step is done
+    departure_delay: f32,     // Time to stand before heading for the destination
+    path: VecDeque<TilePosition>, // Corners of the path to walk after the current target
 }
 
 impl Person {
-    fn new(tile_x: i32, tile_y: i32, direction: Direction, texture: Texture2D) -> Self {
+    fn new(
+        tile_x: i32,
+        tile_y: i32,
+        direction: Direction,
+        sheet: usize,
+        sprites: Arc<CharacterSprites>,
+    ) -> Self {
         let tile_pos = TilePosition::new(tile_x, tile_y);
-        let position = tile_pos.to_world_pos() + Vec2::new(TILE_SIZE / 2.0, TILE_SIZE / 2.0);
-
-        // Calculate tiles_per_row based on texture width
-        let tiles_per_row = (texture.width() / PERSON_SOURCE_TILE_SIZE) as i32;
-
-        // Get animation frames for the initial direction
-        let frames = direction.get_animation_frames(tiles_per_row);
-
-        // Create animation with consistent total time
-        let animation = Animation::new(frames, 0.6); // 0.6s for a complete walk cycle
 
         Self {
-            position,
             tile_pos,
-            texture,
-            start_pos: position,
+            sheet,
+            start_pos: tile_pos.center_world_pos(),
             target_pos: None,
             target_tile: None,
-            animation,
+            animation: AnimationController::new(sprites),
             direction,
             state: PersonState::Idle,
+            idle_timer: random_idle_time(),
             move_timer: 0.0,
             move_duration: 1.0,
-            tiles_per_row,
+            destination: None,
+            departure_delay: 0.0,
+            path: VecDeque::new(),
         }
     }
 
-    fn update(&mut self, dt: f32) {
+    // `animate` is false for people nobody sees, their animation frame doesn't matter
+    fn update(
+        &mut self,
+        position: &mut Vec2,
+        dt: f32,
+        map: &TileMap,
+        movement: Movement,
+        animate: bool,
+    ) {
         match self.state {
             PersonState::Idle => {
-                // Pick a random direction to move
-                if rand::gen_range(0.0, 1.0) < 0.02 {
-                    // 2% chance to start moving each frame
-                    self.pick_random_direction();
+                // Working and sleeping people stay where they are until set back to idle
+                let busy = matches!(
+                    self.animation.state(),
+                    AnimationState::Work | AnimationState::Sleep
+                );
+                self.idle_timer -= dt;
+                if self.destination.is_some() {
+                    self.departure_delay -= dt;
+                    if let Some(destination) =
+                        self.destination.take_if(|_| self.departure_delay <= 0.0)
+                    {
+                        self.plan_path(destination, map, movement);
+                        self.walk_path(*position, map);
+                    }
+                } else if self.idle_timer <= 0.0 && !busy {
+                    self.idle_timer = random_idle_time();
+                    self.pick_random_direction(*position, map, movement);
                 }
             }
             PersonState::Moving => {
@@ -448,7 +1281,7 @@ impl Person {
                 if self.move_timer >= 1.0 {
                     // Movement complete - snap to final position
                     if let Some(target) = self.target_pos {
-                        self.position = target;
+                        *position = target;
                     }
                     if let Some(target_tile) = self.target_tile {
                         self.tile_pos = target_tile;
@@ -459,42 +1292,130 @@ impl Person {
                     self.target_tile = None;
                     self.state = PersonState::Idle;
                     self.move_timer = 0.0;
+                    // Walk on along the path, or towards a new destination right away
+                    if let Some(destination) =
+                        self.destination.take_if(|_| self.departure_delay <= 0.0)
+                    {
+                        self.plan_path(destination, map, movement);
+                    }
+                    if !self.walk_path(*position, map)
+                        && self.animation.state() == AnimationState::Walk
+                    {
+                        // Unless Lua switched the animation meanwhile
+                        self.animation.set_state(AnimationState::Idle);
+                    }
                 } else {
                     // Interpolate position using the stored start_pos
                     if let Some(target) = self.target_pos {
-                        self.position = self.start_pos.lerp(target, self.move_timer);
+                        *position = self.start_pos.lerp(target, self.move_timer);
                     }
                 }
             }
         }
-        // Always update animation
-        self.animation.update(dt);
+        if animate {
+            self.animation.update(dt);
+        }
     }
 
-    fn set_direction(&mut self, direction: Direction) {
-        let frames = direction.get_animation_frames(self.tiles_per_row);
-        self.direction = direction;
-        self.animation = Animation::new(frames, 0.6); // Same 0.6s total animation time
+    /// Head for `destination` after standing for `delay` seconds, once done with the current step
+    fn set_destination(&mut self, destination: TilePosition, delay: f32) {
+        self.destination = Some(destination);
+        self.departure_delay = delay;
     }
 
-    fn pick_random_direction(&mut self) {
-        // 1. Select a random adjacent tile
-        let directions = [
-            Direction::Up,
-            Direction::Down,
-            Direction::Left,
-            Direction::Right,
-        ];
-        let rand_dir = &directions[rand::gen_range(0, directions.len())];
-
-        // Calculate the new target tile
-        let mut new_tile = self.tile_pos;
-        match rand_dir {
-            Direction::Up => new_tile.y -= 1,
-            Direction::Down => new_tile.y += 1,
-            Direction::Left => new_tile.x -= 1,
-            Direction::Right => new_tile.x += 1,
+    /// Find the way to `destination`, walked from the next time the person stands on a tile.
+    /// Without a way there the person stays where they are.
+    fn plan_path(&mut self, destination: TilePosition, map: &TileMap, movement: Movement) {
+        let cost = |x, y| map.movement_cost(&TilePosition::new(x, y));
+        let from = (self.tile_pos.x, self.tile_pos.y);
+        let to = (destination.x, destination.y);
+        let Some(mut path) = find_path(from, to, movement.diagonal, PATH_SEARCH_LIMIT, &cost)
+        else {
+            self.path.clear();
+            return;
+        };
+        if movement.smoothing {
+            path = smooth(&path, &cost);
         }
+        // The first tile is where the person stands already
+        self.path = path
+            .into_iter()
+            .skip(1)
+            .map(|(x, y)| TilePosition::new(x, y))
+            .collect();
+    }
+
+    // Set off towards the next corner of the path, returns false at the end of it
+    fn walk_path(&mut self, position: Vec2, map: &TileMap) -> bool {
+        let Some(next) = self.path.pop_front() else {
+            return false;
+        };
+        // Crossing several tiles on a smoothed path costs what they cost on average
+        let costs: Option<Vec<f32>> =
+            line_tiles((self.tile_pos.x, self.tile_pos.y), (next.x, next.y))
+                .into_iter()
+                .skip(1)
+                .map(|(x, y)| map.movement_cost(&TilePosition::new(x, y)))
+                .collect();
+        let Some(costs) = costs.filter(|costs| !costs.is_empty()) else {
+            // The map changed under the path
+            self.path.clear();
+            return false;
+        };
+        let average = costs.iter().sum::<f32>() / costs.len() as f32;
+        let duration = self.tile_pos.distance_to(&next) * average;
+        self.start_moving(position, next, next.center_world_pos(), duration);
+        true
+    }
+
+    fn start_moving(
+        &mut self,
+        position: Vec2,
+        tile: TilePosition,
+        target_pos: Vec2,
+        duration: f32,
+    ) {
+        // Face where the person is going
+        let movement_vector = target_pos - position;
+        self.direction = Direction::from_movement(movement_vector.x, movement_vector.y);
+        self.animation.set_state(AnimationState::Walk);
+
+        self.start_pos = position;
+        self.target_pos = Some(target_pos);
+        self.target_tile = Some(tile);
+        self.state = PersonState::Moving;
+        self.move_timer = 0.0;
+        self.move_duration = duration;
+    }
+
+    fn pick_random_direction(&mut self, position: Vec2, map: &TileMap, movement: Movement) {
+        // 1. Select a random adjacent tile, diagonal ones too if people may step diagonally
+        let directions: &[(i32, i32)] = if movement.diagonal {
+            &[
+                (0, -1),
+                (0, 1),
+                (-1, 0),
+                (1, 0),
+                (-1, -1),
+                (1, -1),
+                (-1, 1),
+                (1, 1),
+            ]
+        } else {
+            &[(0, -1), (0, 1), (-1, 0), (1, 0)]
+        };
+        let (dx, dy) = directions[rand::gen_range(0, directions.len())];
+        let new_tile = TilePosition::new(self.tile_pos.x + dx, self.tile_pos.y + dy);
+
+        // Stay idle if the chosen tile can't be stepped on from here
+        let cost = |x, y| map.movement_cost(&TilePosition::new(x, y));
+        let Some((_, movement_cost)) =
+            neighbors((self.tile_pos.x, self.tile_pos.y), movement.diagonal, &cost)
+                .into_iter()
+                .find(|(tile, _)| *tile == (new_tile.x, new_tile.y))
+        else {
+            return;
+        };
 
         // 2. Calculate a random point within the inner 3/4 rectangle of the target tile
         let tile_world_pos = new_tile.to_world_pos();
@@ -506,49 +1427,25 @@ impl Person {
         let random_y = tile_world_pos.y + offset + rand::gen_range(0.0, inner_size);
         let target_pos = Vec2::new(random_x, random_y);
 
-        // 3. Calculate movement vector for direction determination
-        let movement_vector = target_pos - self.position;
-
-        // 4. Set direction based on movement vector rather than randomly
-        let movement_direction = Direction::from_movement(movement_vector.x, movement_vector.y);
-        self.set_direction(movement_direction);
-
-        // 5. Store current position and start moving
-        self.start_pos = self.position;
-        self.target_pos = Some(target_pos);
-        self.target_tile = Some(new_tile);
-        self.state = PersonState::Moving;
-        self.move_timer = 0.0;
+        // 3. Face the way of the movement rather than a random one and start moving
+        self.start_moving(position, new_tile, target_pos, movement_cost);
     }
 
-    fn draw(&self) {
-        // Get current frame tile ID
-        let tile_id = self.animation.get_current_frame();
-
-        // Calculate source rectangle using the texture's actual tiles_per_row
-        let src_x = (tile_id as f32 % self.tiles_per_row as f32) * PERSON_SOURCE_TILE_SIZE;
-        let src_y = (tile_id as f32 / self.tiles_per_row as f32).floor() * PERSON_SOURCE_TILE_SIZE;
+    fn draw_below(&self, batch: &mut QuadBatch, position: Vec2) {
+        self.animation.draw_below(batch, position, &self.direction);
+    }
 
-        // Draw person
-        draw_texture_ex(
-            &self.texture,
-            self.position.x - PERSON_TILE_SIZE / 2.0,
-            self.position.y - PERSON_TILE_SIZE / 2.0,
-            WHITE,
-            DrawTextureParams {
-                source: Some(Rect::new(
-                    src_x,
-                    src_y,
-                    PERSON_SOURCE_TILE_SIZE,
-                    PERSON_SOURCE_TILE_SIZE,
-                )),
-                dest_size: Some(Vec2::new(PERSON_TILE_SIZE, PERSON_TILE_SIZE)),
-                ..Default::default()
-            },
-        );
+    fn draw(&self, batch: &mut QuadBatch, position: Vec2) {
+        self.animation.draw(batch, position, &self.direction);
     }
 }
 
+// Time a person stands still, exponentially distributed so people don't start walking in lockstep.
+// Drawn once per stop instead of rolling a chance every frame, which was most of the RNG work.
+fn random_idle_time() -> f32 {
+    -(1.0 - rand::gen_range(0.0f32, 1.0)).ln() * PERSON_MEAN_IDLE_TIME
+}
+
 struct UI {}
 
 impl UI {
@@ -556,68 +1453,157 @@ impl UI {
         Self {}
     }
 
-    fn draw_selected_tile_preview(&self, selected_pos: Option<&TilePosition>, map: &TileMap) {
-        if let Some(pos) = selected_pos {
-            if let Some(tile) = map.get_tile(pos) {
-                let preview_size = TILE_SIZE * SELECTED_TILE_ZOOM;
-                let pos_x = screen_width() - preview_size - 20.0;
-                let pos_y = 20.0;
-
-                // Background
-                draw_rectangle(
-                    pos_x - 10.0,
-                    pos_y - 10.0,
-                    preview_size + 20.0,
-                    preview_size + 20.0,
-                    Color::new(0.0, 0.0, 0.0, 0.7),
-                );
+    // The tile inspector lists what's on the tile next to it
+    fn draw_selected_tile_preview(
+        &self,
+        layout: &mut HudLayout,
+        anchor: Anchor,
+        selected_pos: Option<&TilePosition>,
+        map: &TileMap,
+    ) {
+        let Some(tile) = selected_pos.and_then(|pos| map.get_visible_tile(pos)) else {
+            return;
+        };
+        let preview_size = TILE_SIZE * SELECTED_TILE_ZOOM * ui_scale();
+        let rect = layout.place(anchor, Vec2::new(preview_size + 20.0, preview_size + 20.0));
+        draw_rectangle(
+            rect.x,
+            rect.y,
+            rect.w,
+            rect.h,
+            Color::new(0.0, 0.0, 0.0, 0.7),
+        );
 
-                // Tile image
-                let src_x = (tile.id as f32 % map.tiles_per_row) * SOURCE_TILE_SIZE;
-                let src_y = (tile.id as f32 / map.tiles_per_row).floor() * SOURCE_TILE_SIZE;
-
-                draw_texture_ex(
-                    &map.tileset,
-                    pos_x,
-                    pos_y,
-                    WHITE,
-                    DrawTextureParams {
-                        source: Some(Rect::new(src_x, src_y, SOURCE_TILE_SIZE, SOURCE_TILE_SIZE)),
-                        dest_size: Some(Vec2::new(preview_size, preview_size)),
-                        ..Default::default()
-                    },
-                );
+        // Tile image
+        draw_texture_ex(
+            &map.tileset,
+            rect.x + 10.0,
+            rect.y + 10.0,
+            WHITE,
+            DrawTextureParams {
+                source: Some(map.atlas.source(tile.id)),
+                dest_size: Some(Vec2::new(preview_size, preview_size)),
+                ..Default::default()
+            },
+        );
+
+        // Border
+        draw_rectangle_lines(
+            rect.x + 10.0,
+            rect.y + 10.0,
+            preview_size,
+            preview_size,
+            2.0,
+            RED,
+        );
+    }
 
-                // Border
-                draw_rectangle_lines(pos_x, pos_y, preview_size, preview_size, 2.0, RED);
+    fn draw_coordinates(&self, layout: &mut HudLayout, hover_pos: &TilePosition) {
+        draw_line_box(
+            layout,
+            &format!("Tile: ({}, {})", hover_pos.x, hover_pos.y),
+            WHITE,
+        );
+    }
 
-                // Tile info
-                draw_text(
-                    &format!("Tile ID: {}", tile.id),
-                    pos_x,
-                    pos_y + preview_size + 20.0,
-                    20.0,
-                    WHITE,
-                );
-            }
+    // Only when another map than the main one or another level than the ground level is shown
+    fn draw_map_name(&self, layout: &mut HudLayout, name: &str, level: i32) {
+        if name == MAIN_MAP && level == 0 {
+            return;
         }
+        draw_line_box(layout, &format!("Map: {}, level {}", name, level), SKYBLUE);
     }
 
-    fn draw_instructions(&self) {
-        draw_text_with_background(
-            "WASD/Arrows: move, Mouse wheel: zoom, Left-click drag: pan, Left-click: select, Right-click/drag: place tiles",
-            10.0,
-            screen_height() - 30.0,
-            WHITE,
+    // Only in turn mode, where nothing moves until the turn is ended
+    fn draw_turn(&self, layout: &mut HudLayout, clock: &Clock) {
+        if clock.mode() != TimeMode::Turns {
+            return;
+        }
+        draw_line_box(
+            layout,
+            &format!("Turn {} - Enter: end turn", clock.turn()),
+            YELLOW,
         );
     }
+
+    // Wrapped to the width of the window, at the very bottom
+    fn draw_instructions(&self, layout: &mut HudLayout) {
+        let font_size = TEXT_FONT_SIZE * text_scale();
+        let lines: Vec<(String, Color)> = wrap(
+            "WASD/Arrows: move, Mouse wheel: zoom, Left-click drag: pan, Left-click: select, Right-click/drag: place tiles, I: eyedropper, X: eraser, F: fill, M: measure, R: routes, L: layers, P: follow view, T: track person, 1/2/3: zoom 1x/2x/0.5x, Home: fit map, PgUp/PgDn: level, Ctrl+S: save map",
+            layout.max_width() - TEXT_PADDING * text_scale() * 2.0,
+            |text| measure_text(text, None, font_size as u16, 1.0).width,
+        )
+        .into_iter()
+        .map(|line| (line, WHITE))
+        .collect();
+        let rect = layout.place(Anchor::BottomLeft, text_list_size(&lines));
+        draw_text_list(lines, rect.x + TEXT_PADDING * text_scale(), rect.y);
+    }
+}
+
+// One line of text on its background, stacked with the other lines at the bottom left
+fn draw_line_box(layout: &mut HudLayout, text: &str, color: Color) {
+    let rect = layout.place(Anchor::BottomLeft, text_box_size(text));
+    draw_text_box(text, rect.point(), color);
 }
 
 // Define a UI state enum to track the current mode
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum UIState {
+    TileSelection,
     TileCreation,
+    TileFilling,
+    TileErasing,
     PeopleCreation,
+    Measuring,
+    RouteEditing,
+    /// A tool added by a plugin, the index in the `ToolRegistry`
+    Plugin(usize),
+}
+
+impl UIState {
+    const ALL: [UIState; 7] = [
+        UIState::TileSelection,
+        UIState::TileCreation,
+        UIState::TileFilling,
+        UIState::TileErasing,
+        UIState::PeopleCreation,
+        UIState::Measuring,
+        UIState::RouteEditing,
+    ];
+
+    // Tool name used by the toolbar and Lua scripts
+    fn name(&self) -> &'static str {
+        match self {
+            UIState::TileSelection => "select",
+            UIState::TileCreation => "paint",
+            UIState::TileFilling => "fill",
+            UIState::TileErasing => "erase",
+            UIState::PeopleCreation => "people",
+            UIState::Measuring => "measure",
+            UIState::RouteEditing => "route",
+            UIState::Plugin(_) => "plugin",
+        }
+    }
+
+    // Text of the toolbar button
+    fn label(&self) -> &'static str {
+        match self {
+            UIState::TileSelection => "Select",
+            UIState::TileCreation => "Paint",
+            UIState::TileFilling => "Fill",
+            UIState::TileErasing => "Erase",
+            UIState::PeopleCreation => "People",
+            UIState::Measuring => "Measure",
+            UIState::RouteEditing => "Route",
+            UIState::Plugin(_) => "Plugin",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|state| state.name() == name)
+    }
 }
 
 struct GameState {
@@ -626,71 +1612,159 @@ struct GameState {
     input: Arc<Mutex<InputManager>>,
     ui: UI,
     debug: DebugWindow,
-    selected_pos: Option<TilePosition>,
-    people: Vec<Person>,
+    accessibility: Accessibility,
+    description: UiDescription,
+    budget: FrameBudget,
+    /// Lua time of the mods per frame, for the debug window
+    mod_budget: ModBudget,
+    layers_panel: LayersPanel,
+    tile_inspector: TileInspector,
+    goals_panel: GoalsPanel,
+    notifications_panel: NotificationsPanel,
+    mod_settings_panel: ModSettingsPanel,
+    debugger_panel: DebuggerPanel,
+    error_overlay: ErrorOverlay,
+    scenario_overlay: ScenarioOverlay,
+    tutorial_overlay: TutorialOverlay,
+    command_palette: CommandPalette,
+    docs_browser: DocsBrowser,
+    /// Migrations and mismatches of the map loaded at startup
+    load_report_dialog: LoadReportDialog,
+    selection: Selection,
+    /// Edits done with the tools, as Lua calls, while recording
+    macro_recorder: MacroRecorder,
+    /// Spectator mode, editing is off for the tools and the scripts
+    permissions: Permissions,
+    measure_anchor: Option<TilePosition>,
+    route_editor: RouteEditor,
+    brush: Arc<Mutex<Brush>>,
+    people: Arc<Mutex<People>>,
+    viewports: Vec<Viewport>,
+    indicators: Arc<Mutex<OffscreenIndicators>>,
+    effects: MapEffects,
+    speech: SpeechBubbles,
+    fog: FogLayer,
+    zones: ZoneLayer,
+    overlay: OverlayLayer,
+    vehicles: VehicleLayer,
+    portals: PortalLayer,
+    world: World,
+    clock: Clock,
+    profiler: FrameProfiler,
     last_frame_time: f64,
-    ui_state: UIState,
-    character_textures: Vec<Texture2D>,
+    // Fixed steps the people are simulated in
+    simulation_clock: FixedClock,
+    ui_state: Arc<Mutex<UIState>>,
+    /// Tools of the plugins, see `UIState::Plugin`
+    tools: ToolRegistry,
+    tool_world: ToolWorld,
+    // The tool of the last update, to tell plugin tools when they are picked or left
+    active_tool: UIState,
     last_person_pos: Option<Vec2>,
     console: Console,
     lua_client: Arc<LuaClient>,
     lua_ui: LuaUIBindings,
+    lua_input: LuaInputBindings,
+    hooks: LifecycleHooks,
+    ticker: FrameTicker,
+    // Only set in dev mode, started with `--dev [script]`
+    dev_script: Option<DevScript>,
+    // Only set when started with `--crowd-benchmark`
+    crowd_benchmark: Option<CrowdBenchmark>,
+    // Only set when started with `--stream`
+    streamer: Option<ChunkStreamer>,
 }
 
 impl GameState {
     async fn new(command_tx: Sender<LuaCommand>, lua_engine: Arc<Mutex<LuaEngine>>) -> Self {
         // Create the client that the game state will use
         let lua_client = Arc::new(LuaClient::new(command_tx.clone()));
-        let map = Arc::new(Mutex::new(TileMap::new().await));
+        let mut tile_map = TileMap::new().await;
+        tile_map.edits = Some(lua_engine.lock().unwrap().map_edits.clone());
+        let map = Arc::new(Mutex::new(tile_map));
         let initial_center = { map.lock().unwrap().get_initial_center() };
-        let camera = Arc::new(Mutex::new(CameraController::new(initial_center)));
+        let mut camera = CameraController::new(initial_center);
+        camera.view = ViewSettings::load(VIEW_CONFIG_PATH);
+        let camera = Arc::new(Mutex::new(camera));
         let input = Arc::new(Mutex::new(InputManager::new()));
+        let ui_state = Arc::new(Mutex::new(UIState::TileCreation)); // Default state
+        let brush = Arc::new(Mutex::new(Brush::new()));
+        let indicators = Arc::new(Mutex::new(OffscreenIndicators::new()));
+        let tools = ToolRegistry::default();
+        tools.register(Box::new(StampTool::default())).unwrap();
         let lua_ui = LuaUIBindings::new(
             lua_engine.clone(),
             camera.clone(),
             input.clone(),
             map.clone(),
+            brush.clone(),
+            indicators.clone(),
         );
-
-        // Load character textures
-        let character_paths = find_character_textures("assets");
-        let mut character_textures = Vec::new();
-
-        for path in &character_paths {
-            if let Some(path_str) = path.to_str() {
-                match load_texture(path_str).await {
-                    Ok(texture) => {
-                        texture.set_filter(FilterMode::Nearest);
-                        character_textures.push(texture);
-                    }
-                    Err(e) => println!("Failed to load texture {}: {:?}", path_str, e),
-                }
+        tools::install(&lua_engine, tools.clone(), ui_state.clone());
+        let description = UiDescription::new(&lua_engine);
+        let lua_input = LuaInputBindings::new(lua_engine.clone(), camera.clone(), input.clone());
+        let debugger_panel = DebuggerPanel::new(lua_engine.lock().unwrap().debugger.clone());
+        let error_overlay = ErrorOverlay::new(lua_engine.lock().unwrap().error_log.clone());
+        let scenario_overlay = ScenarioOverlay::new(lua_engine.lock().unwrap().scenario.clone());
+        let tutorial_overlay = TutorialOverlay::new(lua_engine.lock().unwrap().tutorials.clone());
+        let mod_budget = lua_engine.lock().unwrap().budget.clone();
+        let command_palette = CommandPalette::new(lua_engine.lock().unwrap().commands.clone());
+        let docs_browser = DocsBrowser::new(lua_engine.lock().unwrap().lua.clone());
+        let goals_panel = GoalsPanel::new(lua_engine.lock().unwrap().goals.clone());
+        let notifications_panel =
+            NotificationsPanel::new(lua_engine.lock().unwrap().notifications.clone());
+        let mod_settings_panel = ModSettingsPanel::new(lua_engine.lock().unwrap().settings.clone());
+        let hooks = lua_engine.lock().unwrap().hooks.clone();
+        let effects = MapEffects::new(&lua_engine);
+        let fog = FogLayer::new(lua_engine.lock().unwrap().fog.clone());
+        let zones = ZoneLayer::new(lua_engine.lock().unwrap().factions.clone());
+        let overlay = OverlayLayer::new(lua_engine.lock().unwrap().overlay.clone());
+        let vehicles = VehicleLayer::new(lua_engine.lock().unwrap().vehicles.clone());
+        let portals = PortalLayer::new(lua_engine.lock().unwrap().portals.clone());
+        let world = lua_engine.lock().unwrap().world.clone();
+        let clock = lua_engine.lock().unwrap().clock.clone();
+        let profiler = FrameProfiler::new(&lua_engine);
+        let selection = Selection::new(&lua_engine);
+        let macro_recorder = MacroRecorder::new(&lua_engine);
+        let tool_world = ToolWorld {
+            map: map.clone(),
+            recorder: macro_recorder.clone(),
+            notifications: lua_engine.lock().unwrap().notifications.clone(),
+        };
+        let accessibility = Accessibility::new(&lua_engine);
+
+        // Load the sheets of every character found by its walk sheet
+        let character_manifest = CharacterManifest::load("assets/characters.json");
+        let mut characters = Vec::new();
+        for path in &find_character_textures("assets") {
+            if let Some(sprites) = CharacterSprites::load(path, &character_manifest).await {
+                characters.push(Arc::new(sprites));
             }
         }
 
-        // Create initial people
-        let mut people = Vec::new();
-
-        for _ in 0..PEOPLE_BENCHMARK_SIZE {
-            let tile_x = 1 + rand::gen_range(0, PEOPLE_BENCHMARK_DISPERSION);
-            let tile_y = 1 + rand::gen_range(0, PEOPLE_BENCHMARK_DISPERSION);
-
-            if !character_textures.is_empty() {
-                // Select random texture
-                let texture_index = rand::gen_range(0, character_textures.len());
-                let texture = character_textures[texture_index].clone();
-
-                // Random direction
-                let direction = match rand::gen_range(0, 4) {
-                    0 => Direction::Up,
-                    1 => Direction::Down,
-                    2 => Direction::Left,
-                    _ => Direction::Right,
-                };
-
-                people.push(Person::new(tile_x, tile_y, direction, texture));
+        // People are spawned by the scripts, see scripts/benchmark.lua
+        let people = People::shared(&lua_engine, characters, map.clone());
+        let speech = SpeechBubbles::new(&lua_engine, people.clone());
+        routes::install(&lua_engine, map.clone());
+        map_editing::install(&lua_engine, map.clone());
+        let permissions = lua_engine.lock().unwrap().permissions.clone();
+        {
+            let engine = lua_engine.lock().unwrap();
+            for (path, names) in MUTATING_GLOBALS {
+                permissions.guard(&engine.lua, path, names).unwrap();
             }
         }
+        let dev_script = std::env::args().any(|arg| arg == "--dev").then(|| {
+            let path = arg_value("--dev").unwrap_or_else(|| DEV_SCRIPT_PATH.to_string());
+            let error_log = lua_engine.lock().unwrap().error_log.clone();
+            DevScript::new(&path, DEV_SCRIPT_POLL_INTERVAL, error_log)
+        });
+        let crowd_benchmark = std::env::args()
+            .any(|arg| arg == "--crowd-benchmark")
+            .then(CrowdBenchmark::default);
+        let streamer = std::env::args()
+            .any(|arg| arg == "--stream")
+            .then(|| ChunkStreamer::new(lua_engine.lock().unwrap().chunks.clone()));
 
         Self {
             map,
@@ -698,15 +1772,64 @@ impl GameState {
             input: input.clone(),
             ui: UI::new(),
             debug: DebugWindow::new(),
-            selected_pos: None,
+            accessibility,
+            description,
+            budget: FrameBudget::new(),
+            mod_budget,
+            layers_panel: LayersPanel::new(),
+            tile_inspector: TileInspector::new(lua_engine.lock().unwrap().snapshots.clone()),
+            goals_panel,
+            notifications_panel,
+            mod_settings_panel,
+            debugger_panel,
+            error_overlay,
+            scenario_overlay,
+            tutorial_overlay,
+            command_palette,
+            docs_browser,
+            load_report_dialog: LoadReportDialog::default(),
+            selection,
+            macro_recorder,
+            permissions,
+            measure_anchor: None,
+            route_editor: RouteEditor::default(),
+            brush,
             people,
+            viewports: Vec::new(),
+            indicators,
+            effects,
+            speech,
+            fog,
+            zones,
+            overlay,
+            vehicles,
+            portals,
+            world,
+            clock,
+            profiler,
             last_frame_time: get_time(),
-            ui_state: UIState::TileCreation, // Default state
-            character_textures,
+            simulation_clock: FixedClock::new(SIMULATION_STEP, MAX_SIMULATION_STEPS),
+            ui_state,
+            tools,
+            tool_world,
+            active_tool: UIState::TileCreation,
             last_person_pos: None,
-            console: Console::new(lua_client.clone()),
+            console: Console::new(lua_client.clone(), lua_engine.clone()),
             lua_client,
             lua_ui,
+            lua_input,
+            hooks,
+            ticker: FrameTicker::new(LUA_TICK_INTERVAL),
+            dev_script,
+            crowd_benchmark,
+            streamer,
+        }
+    }
+
+    // Hooks would block while a script is paused in the debugger, so they are skipped then
+    fn call_hook(&self, hook: Hook, args: impl IntoLuaMulti + Clone) {
+        if !self.debugger_panel.is_paused() {
+            self.hooks.call(hook, args);
         }
     }
 
@@ -714,42 +1837,301 @@ impl GameState {
         let current_time = get_time();
         let dt = (current_time - self.last_frame_time) as f32;
         self.last_frame_time = current_time;
-        if is_key_pressed(KeyCode::GraveAccent) {
-            self.console.toggle();
+
+        self.budget.record(dt as f64 * 1000.0);
+        self.effects
+            .set_particles(!self.budget.is_active(Degradation::Particles));
+
+        self.camera.lock().unwrap().update_effects(dt);
+        self.effects.update(dt);
+        self.speech.update(dt);
+        {
+            // Follow the logic onto the map and level it switched to
+            let mut map = self.map.lock().unwrap();
+            let (active, level) = (self.world.active(), self.world.level());
+            if map.name != active || map.level != level {
+                if let Some(streamer) = &mut self.streamer {
+                    streamer.load_all(&mut map, &mut self.people.lock().unwrap());
+                }
+                map.switch_to(&active, level);
+            }
+            map.sync();
+            self.vehicles.sync_roads(&map);
+        }
+
+        // Update people, the ones no camera shows are updated less often
+        {
+            let started = get_time();
+            let visible: Vec<Rect> =
+                std::iter::once(self.camera.lock().unwrap().visible_world_rect())
+                    .chain(
+                        self.viewports
+                            .iter()
+                            .map(|viewport| viewport.camera.visible_world_rect()),
+                    )
+                    .collect();
+            let mut map = self.map.lock().unwrap();
+            let mut people = self.people.lock().unwrap();
+            // Chunks far from every camera go to disk with the people on them
+            if let Some(streamer) = &mut self.streamer {
+                streamer.update(dt, &mut map, &mut people, &visible);
+            }
+            let offscreen = !self.budget.is_active(Degradation::OffscreenPeople);
+            let steps = self.simulation_clock.advance(dt);
+            people.update(steps, &map, &visible, offscreen);
+            if let Some(benchmark) = &mut self.crowd_benchmark {
+                benchmark.record(
+                    dt as f64,
+                    get_time() - started,
+                    people.len(),
+                    self.profiler.draw_calls(),
+                    people.is_grouped_draw(),
+                );
+            }
         }
 
-        // Update people
-        for person in &mut self.people {
-            person.update(dt);
+        for viewport in &mut self.viewports {
+            let follow_position = viewport
+                .follow
+                .and_then(|id| self.people.lock().unwrap().position(id));
+            viewport.update(follow_position);
         }
 
-        // Update input
+        // Update input, UI elements under the cursor take the mouse away from the world
         {
+            let mouse_over_ui = self.mouse_over_ui(Vec2::from(mouse_position()));
             let mut input = self.input.lock().unwrap();
-            input.update();
+            input.update(mouse_over_ui);
+        }
+        // The actions the bindings and scripts pressed this frame
+        let actions = self.input.lock().unwrap().actions().to_vec();
+        let pressed = |action| actions.contains(&action);
+        if pressed(Action::ToggleConsole) {
+            self.console.toggle();
         }
 
+        self.debugger_panel.update();
+
         // Update and draw the console
         if self.console.visible {
             self.console.update();
             return;
         }
-        // Update camera with input
+        if pressed(Action::CommandPalette) {
+            let input = self.input.lock().unwrap();
+            self.command_palette.open(input.bindings());
+        }
+        // The palette takes the keys while open, what it picks runs like the console's commands
+        if self.command_palette.is_open() {
+            match self.command_palette.update() {
+                Some(PaletteCommand::Action(action)) => self.input.lock().unwrap().trigger(action),
+                Some(PaletteCommand::Script(name)) if !self.debugger_panel.is_paused() => {
+                    self.command_palette.run(&name)
+                }
+                Some(PaletteCommand::Script(name)) => {
+                    println!("Can't run '{}' while a script is paused", name)
+                }
+                None => {}
+            }
+            return;
+        }
+        if pressed(Action::ApiDocs) {
+            self.docs_browser.toggle();
+        }
+        // The docs take the keys while open, the example picked goes into the console
+        if self.docs_browser.is_open() {
+            if let Some(example) = self.docs_browser.update() {
+                self.docs_browser.close();
+                self.console.insert(&example);
+            }
+            return;
+        }
+        // Update camera with input
         {
             let mut camera = self.camera.lock().unwrap();
             let input = self.input.lock().unwrap();
-            camera.update(&input);
+            camera.update(&input, dt);
         }
 
         self.debug.update();
 
         // Toggle debug mode
-        if is_key_down(KeyCode::LeftShift) && is_key_pressed(KeyCode::D) {
+        if pressed(Action::ToggleDebug) {
             self.debug.toggle();
         }
+        if pressed(Action::TogglePeopleBatching) {
+            self.people.lock().unwrap().toggle_grouped_draw();
+        }
+        if pressed(Action::ToggleMacroRecording) {
+            if self.macro_recorder.is_recording() {
+                match self.macro_recorder.stop_and_save() {
+                    Ok(path) => println!(
+                        "Saved {} calls to {}, run it with macro.play(\"{}\")",
+                        self.macro_recorder.len(),
+                        path,
+                        path
+                    ),
+                    Err(e) => eprintln!("Failed to save macro: {}", e),
+                }
+            } else {
+                self.macro_recorder.start();
+            }
+        }
+
+        // Script handlers would block while a script is paused in the debugger
+        if !self.debugger_panel.is_paused() {
+            self.lua_ui.update();
+            self.lua_input.dispatch();
+        }
+        // on_frame hooks and timers run on the engine thread
+        self.ticker.update(&self.lua_client, dt);
+        if let Some(dev_script) = &mut self.dev_script {
+            dev_script.update(&self.lua_client, current_time);
+        }
+        self.error_overlay.update();
+        self.scenario_overlay.update();
+        self.tutorial_overlay.update(&actions);
+        self.load_report_dialog.update();
+
+        if pressed(Action::ToggleSpectator) {
+            let spectating = !self.permissions.spectating();
+            self.permissions.set_spectating(spectating);
+            println!("Spectator mode {}", if spectating { "on" } else { "off" });
+        }
+        let spectating = self.permissions.spectating();
+
+        if pressed(Action::PeopleTool) {
+            *self.ui_state.lock().unwrap() = UIState::PeopleCreation;
+        }
+
+        if pressed(Action::EraseTool) {
+            *self.ui_state.lock().unwrap() = UIState::TileErasing;
+        }
+
+        if pressed(Action::FillTool) {
+            *self.ui_state.lock().unwrap() = UIState::TileFilling;
+        }
+
+        if pressed(Action::MeasureTool) {
+            *self.ui_state.lock().unwrap() = UIState::Measuring;
+        }
+
+        if pressed(Action::RouteTool) {
+            let mut ui_state = self.ui_state.lock().unwrap();
+            if *ui_state == UIState::RouteEditing {
+                self.route_editor.start_new_route();
+            }
+            *ui_state = UIState::RouteEditing;
+        }
+
+        // Spectators keep to selecting and measuring, whatever asked for another tool
+        {
+            let mut ui_state = self.ui_state.lock().unwrap();
+            if spectating && !matches!(*ui_state, UIState::TileSelection | UIState::Measuring) {
+                *ui_state = UIState::TileSelection;
+            }
+        }
+
+        if pressed(Action::FitMap) {
+            let map = self.map.lock().unwrap();
+            self.camera.lock().unwrap().fit(&map.bounds);
+        }
+
+        // Picture-in-picture following the person closest to the cursor
+        if pressed(Action::PictureInPicture) {
+            if self.viewports.is_empty() {
+                let mouse_world_pos = {
+                    let camera = self.camera.lock().unwrap();
+                    camera.screen_to_world(self.input.lock().unwrap().get_mouse_position())
+                };
+                if let Some(id) = self.closest_person(mouse_world_pos)
+                    && let Some(position) = self.person_position(id)
+                {
+                    let view = self.camera.lock().unwrap().view;
+                    self.viewports
+                        .push(Viewport::picture_in_picture(id, position, view));
+                }
+            } else {
+                self.viewports.clear();
+            }
+        }
+
+        // Track the person closest to the cursor with an off-screen indicator
+        if pressed(Action::TrackPerson) {
+            let mouse_world_pos = {
+                let camera = self.camera.lock().unwrap();
+                camera.screen_to_world(self.input.lock().unwrap().get_mouse_position())
+            };
+            if let Some(id) = self.closest_person(mouse_world_pos) {
+                self.indicators.lock().unwrap().toggle_tracked(id);
+            }
+        }
+
+        // Clicking an indicator jumps the camera to the person
+        if is_mouse_button_pressed(MouseButton::Left)
+            && let Some(person) = self.indicator_at(Vec2::from(mouse_position()))
+            && let Some(position) = self.person_position(person)
+        {
+            self.camera.lock().unwrap().pan_to(position, 0.4);
+        }
 
-        if is_key_pressed(KeyCode::E) {
-            self.ui_state = UIState::PeopleCreation;
+        if pressed(Action::ToggleLayers) {
+            self.layers_panel.toggle();
+        }
+
+        if pressed(Action::ToggleGoals) {
+            self.goals_panel.toggle();
+        }
+
+        if pressed(Action::ToggleNotifications) {
+            self.notifications_panel.toggle();
+        }
+
+        if pressed(Action::ToggleModSettings) {
+            self.mod_settings_panel.toggle();
+        }
+
+        // The world waits for the player in turn mode
+        if !spectating && pressed(Action::EndTurn) {
+            self.clock.end_turn();
+        }
+
+        // Up and down the levels of the map, the view follows on the next update
+        if pressed(Action::LevelUp) {
+            self.world.switch_level(self.world.level() + 1);
+        }
+        if pressed(Action::LevelDown) {
+            self.world.switch_level(self.world.level() - 1);
+        }
+
+        if !spectating && pressed(Action::SaveMap) {
+            let path = self.map.lock().unwrap().file_path();
+            // Scripts paused in the debugger can't be asked, the state they saved before stays
+            if !self.debugger_panel.is_paused() {
+                let mod_state = self.hooks.save_state(&path);
+                self.map.lock().unwrap().mod_state = mod_state;
+            }
+            let mods = self.hooks.mod_names();
+            let mut map = self.map.lock().unwrap();
+            // The chunks on disk are saved along with the rest
+            if let Some(streamer) = &mut self.streamer {
+                streamer.load_all(&mut map, &mut self.people.lock().unwrap());
+            }
+            map.save(mods);
+        }
+
+        // Brush size and shape
+        {
+            let mut brush = self.brush.lock().unwrap();
+            if pressed(Action::BrushGrow) {
+                brush.grow();
+            }
+            if pressed(Action::BrushShrink) {
+                brush.shrink();
+            }
+            if pressed(Action::BrushShape) {
+                brush.toggle_shape();
+            }
         }
 
         // Convert mouse position to world coordinates
@@ -762,58 +2144,155 @@ impl GameState {
         }
         hover_pos = TilePosition::from_world_pos(mouse_world_pos);
 
-        // Handle tile selection
-        let should_select;
-        {
-            let input = self.input.lock().unwrap();
-            should_select = input.should_select_tile();
+        // Eyedropper: pick the hovered tile as the brush without touching selection or mode
+        if pressed(Action::Eyedropper) {
+            let map = self.map.lock().unwrap();
+            if let Some(tile) = map.get_visible_tile(&hover_pos) {
+                self.brush.lock().unwrap().tile_id = Some(tile.id);
+            }
+        }
+
+        // Double-click centers the camera on the tile
+        if self.input.lock().unwrap().is_double_clicked() {
+            self.camera
+                .lock()
+                .unwrap()
+                .pan_to(hover_pos.center_world_pos(), 0.3);
         }
 
-        if should_select {
+        // Handle tile selection
+        if pressed(Action::Select) {
             // Check if tile exists with lock
-            let tile_exists = {
+            let tile_id = {
                 let map = self.map.lock().unwrap();
-                map.get_tile(&hover_pos).is_some()
+                map.get_visible_tile(&hover_pos).map(|tile| tile.id)
             };
 
-            if tile_exists {
-                self.selected_pos = Some(hover_pos);
-                self.ui_state = UIState::TileCreation;
+            // Clicking a person selects them along with the tile they stand on
+            let person = self.closest_person(mouse_world_pos).filter(|&id| {
+                self.person_position(id).is_some_and(|position| {
+                    position.distance(mouse_world_pos) < PERSON_TILE_SIZE / 2.0
+                })
+            });
+            self.selection.select_person(person);
+
+            if let Some(tile_id) = tile_id {
+                self.selection.select_tile(hover_pos);
+                self.brush.lock().unwrap().tile_id = Some(tile_id);
+
+                // Selecting a tile leaves the eraser and people modes
+                let mut ui_state = self.ui_state.lock().unwrap();
+                if matches!(*ui_state, UIState::TileErasing | UIState::PeopleCreation) {
+                    *ui_state = UIState::TileCreation;
+                }
             }
         }
 
         // Handle actions based on UI state
-        match self.ui_state {
-            UIState::TileCreation => {
-                // Check conditions for tile placement
-                let should_place_tile;
-                let can_place;
+        let ui_state = *self.ui_state.lock().unwrap();
+        if ui_state != self.active_tool {
+            if let UIState::Plugin(index) = self.active_tool
+                && let Some(tool) = self.tools.get(index)
+            {
+                tool.lock().unwrap().deactivate(&self.tool_world);
+            }
+            if let UIState::Plugin(index) = ui_state
+                && let Some(tool) = self.tools.get(index)
+            {
+                tool.lock().unwrap().activate(&self.tool_world);
+            }
+            self.active_tool = ui_state;
+        }
+        match ui_state {
+            UIState::TileSelection => {
+                // Right click sends the selected people there as a group
+                let people = self.selection.people();
+                if !people.is_empty()
+                    && !spectating
+                    && self
+                        .input
+                        .lock()
+                        .unwrap()
+                        .world_button_pressed(MouseButton::Right)
                 {
-                    let mut input = self.input.lock().unwrap();
-                    should_place_tile = input.should_place_tile(self.selected_pos.as_ref());
-                    can_place = input.can_place_at(hover_pos);
+                    let map = self.map.lock().unwrap();
+                    self.people
+                        .lock()
+                        .unwrap()
+                        .move_group(&people, hover_pos, &map);
                 }
+            }
+            UIState::TileCreation => {
+                let mut input = self.input.lock().unwrap();
+                let brush = self.brush.lock().unwrap();
 
-                // Handle tile placement
-                if should_place_tile && can_place {
-                    if let Some(selected_pos) = &self.selected_pos {
-                        // Get the tile ID from the selected position
-                        let selected_tile_id = {
-                            let map = self.map.lock().unwrap();
-                            map.get_tile(selected_pos).map(|tile| tile.id)
-                        };
-
-                        // Place the tile if we found a valid ID
-                        if let Some(tile_id) = selected_tile_id {
-                            let mut map = self.map.lock().unwrap();
-                            map.place_tile(&hover_pos, tile_id);
+                // Place the brush tile on every position not yet painted in this stroke
+                if let Some(tile_id) = brush.tile_id
+                    && input.should_paint()
+                {
+                    let mut map = self.map.lock().unwrap();
+                    for pos in brush.footprint(hover_pos) {
+                        if input.can_place_at(pos) {
+                            match map.place_tile(&pos, tile_id) {
+                                Ok(()) => self.macro_recorder.record(format!(
+                                    "map.place({}, {}, {})",
+                                    pos.x, pos.y, tile_id
+                                )),
+                                Err(reason) => self.tool_world.reject(&reason),
+                            }
+                        }
+                    }
+                }
+            }
+            UIState::TileFilling => {
+                let brush = self.brush.lock().unwrap();
+
+                if let Some(tile_id) = brush.tile_id
+                    && self
+                        .input
+                        .lock()
+                        .unwrap()
+                        .world_button_pressed(MouseButton::Right)
+                {
+                    let mut map = self.map.lock().unwrap();
+                    match map.flood_fill(&hover_pos, tile_id) {
+                        Ok(0) => {}
+                        Ok(_) => self.macro_recorder.record(format!(
+                            "map.flood_fill({}, {}, {})",
+                            hover_pos.x, hover_pos.y, tile_id
+                        )),
+                        Err(reason) => self.tool_world.reject(&reason),
+                    }
+                }
+            }
+            UIState::TileErasing => {
+                let mut input = self.input.lock().unwrap();
+                let brush = self.brush.lock().unwrap();
+
+                if input.should_paint() {
+                    let mut map = self.map.lock().unwrap();
+                    for pos in brush.footprint(hover_pos) {
+                        if !input.can_place_at(pos) {
+                            continue;
+                        }
+                        match map.remove_tile(&pos) {
+                            Ok(Some(_)) => self
+                                .macro_recorder
+                                .record(format!("map.erase({}, {})", pos.x, pos.y)),
+                            Ok(None) => {}
+                            Err(reason) => self.tool_world.reject(&reason),
                         }
                     }
                 }
             }
             UIState::PeopleCreation => {
                 // Handle person creation with dragging - now purely distance-based
-                if is_mouse_button_down(MouseButton::Right) {
+                let world_button_down = self
+                    .input
+                    .lock()
+                    .unwrap()
+                    .world_button_down(MouseButton::Right);
+                if world_button_down {
                     // Check if we've moved enough since last person creation
                     let should_create = match self.last_person_pos {
                         Some(last_pos) => {
@@ -829,39 +2308,227 @@ impl GameState {
                         self.add_person_at_position(hover_pos, mouse_world_pos);
                         self.last_person_pos = Some(mouse_world_pos);
                     }
-                } else if is_mouse_button_released(MouseButton::Right) {
+                } else {
                     // Reset when mouse button is released
                     self.last_person_pos = None;
                 }
             }
+            UIState::Measuring => {
+                if self
+                    .input
+                    .lock()
+                    .unwrap()
+                    .world_button_pressed(MouseButton::Right)
+                {
+                    self.measure_anchor = Some(hover_pos);
+                }
+            }
+            // Scripts paused in the debugger can't run their tools
+            UIState::Plugin(index) => {
+                if let Some(tool) = self.tools.get(index)
+                    && !self.debugger_panel.is_paused()
+                {
+                    let input = self.tool_input(hover_pos);
+                    tool.lock().unwrap().handle_input(&input, &self.tool_world);
+                }
+            }
+            UIState::RouteEditing => {
+                let (pressed, down) = {
+                    let input = self.input.lock().unwrap();
+                    (
+                        input.world_button_pressed(MouseButton::Right),
+                        input.world_button_down(MouseButton::Right),
+                    )
+                };
+                let mut map = self.map.lock().unwrap();
+                self.route_editor
+                    .update(&mut map.routes, hover_pos, pressed, down);
+            }
         }
     }
 
-    fn add_person_at_position(&mut self, tile_pos: TilePosition, world_pos: Vec2) {
-        if !self.character_textures.is_empty() {
-            let texture_index = rand::gen_range(0, self.character_textures.len());
-            let texture = self.character_textures[texture_index].clone();
-
-            // Random direction
-            let random_dir = match rand::gen_range(0, 4) {
-                0 => Direction::Up,
-                1 => Direction::Down,
-                2 => Direction::Left,
-                _ => Direction::Right,
-            };
+    // Person whose off-screen indicator is under the given screen position
+    fn indicator_at(&self, screen_pos: Vec2) -> Option<PersonId> {
+        let camera = self.camera.lock().unwrap();
+        self.indicators
+            .lock()
+            .unwrap()
+            .collect(
+                &camera,
+                &self.people.lock().unwrap(),
+                self.followed_people(),
+            )
+            .into_iter()
+            .find(|indicator| indicator.contains(screen_pos))
+            .map(|indicator| indicator.person)
+    }
 
-            // Create person and set position directly to mouse position
-            let mut person = Person::new(tile_pos.x, tile_pos.y, random_dir, texture);
-            person.position = world_pos;
+    // UI elements take precedence over world tools (the console blocks everything while open)
+    fn mouse_over_ui(&self, screen_pos: Vec2) -> bool {
+        self.command_palette.is_open()
+            || self.docs_browser.captures_mouse(screen_pos)
+            || self.lua_ui.captures_mouse(screen_pos)
+            || self.debugger_panel.captures_mouse(screen_pos)
+            || self.error_overlay.captures_mouse(screen_pos)
+            || self.scenario_overlay.captures_mouse(screen_pos)
+            || self.tutorial_overlay.captures_mouse(screen_pos)
+            || self.load_report_dialog.captures_mouse(screen_pos)
+            || self.layers_panel.captures_mouse(screen_pos)
+            || self.tile_inspector.captures_mouse(screen_pos)
+            || self.goals_panel.captures_mouse(screen_pos)
+            || self.notifications_panel.captures_mouse(screen_pos)
+            || self.mod_settings_panel.captures_mouse(screen_pos)
+            || self.indicator_at(screen_pos).is_some()
+    }
+
+    // People followed by the additional viewports
+    fn followed_people(&self) -> Vec<PersonId> {
+        self.viewports
+            .iter()
+            .filter_map(|viewport| viewport.follow)
+            .collect()
+    }
 
-            // Add to people list
-            self.people.push(person);
+    fn closest_person(&self, world_pos: Vec2) -> Option<PersonId> {
+        self.people.lock().unwrap().closest(world_pos)
+    }
+
+    fn person_position(&self, id: PersonId) -> Option<Vec2> {
+        self.people.lock().unwrap().position(id)
+    }
+
+    fn tool_input(&self, hover: TilePosition) -> ToolInput {
+        let input = self.input.lock().unwrap();
+        let brush = self.brush.lock().unwrap();
+        ToolInput {
+            hover,
+            pressed: input.world_button_pressed(MouseButton::Right),
+            held: input.world_button_down(MouseButton::Right),
+            tile_id: brush.tile_id,
+            footprint: brush.footprint(hover),
+        }
+    }
+
+    // What the mode of the editor does, shown above the instructions
+    fn mode_text(&self, ui_state: UIState, hover_pos: &TilePosition) -> Option<(String, Color)> {
+        let brush = self.brush.lock().unwrap();
+        let text = match ui_state {
+            UIState::PeopleCreation => (
+                "PEOPLE CREATION MODE (select a tile to exit, Right-click to add person)"
+                    .to_string(),
+                YELLOW,
+            ),
+            UIState::TileSelection if self.permissions.spectating() => (
+                "SPECTATOR MODE (Left-click to inspect, M to measure, Shift+V to edit again)"
+                    .to_string(),
+                SKYBLUE,
+            ),
+            UIState::TileSelection => (
+                "SELECT MODE (Left-click to select a tile, right-click to move the selected people)"
+                    .to_string(),
+                SKYBLUE,
+            ),
+            UIState::TileCreation => (
+                format!(
+                    "TILE CREATION MODE (Press `e` to switch to people mode) Brush: tile {} {}x{} {} ([ ] to resize, B to change shape)",
+                    brush.tile_id?,
+                    brush.size,
+                    brush.size,
+                    brush.shape.name()
+                ),
+                GREEN,
+            ),
+            UIState::TileFilling => (
+                match brush.tile_id {
+                    Some(tile_id) => format!(
+                        "FILL MODE (Right-click to flood fill with tile {})",
+                        tile_id
+                    ),
+                    None => "FILL MODE (select or pick a tile to fill with)".to_string(),
+                },
+                PURPLE,
+            ),
+            UIState::Measuring => (
+                match self.measure_anchor {
+                    Some(anchor) => {
+                        let distance = anchor.distance_to(hover_pos);
+                        format!(
+                            "MEASURE MODE (Right-click to move anchor) From ({}, {}): {:.2} tiles, {:.1} world units (dx {}, dy {})",
+                            anchor.x,
+                            anchor.y,
+                            distance,
+                            distance * TILE_SIZE,
+                            hover_pos.x - anchor.x,
+                            hover_pos.y - anchor.y
+                        )
+                    }
+                    None => "MEASURE MODE (Right-click to set the anchor)".to_string(),
+                },
+                LIME,
+            ),
+            UIState::TileErasing => (
+                format!(
+                    "ERASER MODE (select a tile to exit, Right-click/drag to erase) Brush: {}x{} {}",
+                    brush.size,
+                    brush.size,
+                    brush.shape.name()
+                ),
+                ORANGE,
+            ),
+            UIState::RouteEditing => (
+                match self.route_editor.active() {
+                    Some(route) => format!(
+                        "ROUTE MODE (Right-click to add a waypoint to {}, drag one to move it, click it to remove it, R for a new route)",
+                        route
+                    ),
+                    None => "ROUTE MODE (Right-click to start a new route, or on a waypoint to edit its route)".to_string(),
+                },
+                GOLD,
+            ),
+            UIState::Plugin(_) => (
+                format!(
+                    "{} TOOL (added by a plugin)",
+                    self.tools.state_name(ui_state).to_uppercase()
+                ),
+                WHITE,
+            ),
+        };
+        Some(text)
+    }
+
+    fn macro_recording_text(&self) -> String {
+        format!(
+            "RECORDING MACRO: {} calls (Ctrl+M to stop and save it)",
+            self.macro_recorder.len()
+        )
+    }
+
+    fn add_person_at_position(&mut self, tile_pos: TilePosition, world_pos: Vec2) {
+        // Create person at the mouse position, not snapped to the tile center
+        let mut people = self.people.lock().unwrap();
+        if let Some(id) = people.spawn(tile_pos, world_pos, None)
+            && let Some(sheet) = people.sheet_of(id)
+        {
+            // Replayed at the tile center, `people.spawn` places there
+            self.macro_recorder.record(format!(
+                "people.spawn({}, {}, {:?})",
+                tile_pos.x, tile_pos.y, sheet
+            ));
         }
     }
 
     fn draw(&mut self) {
+        self.profiler.begin_frame();
         clear_background(BLACK);
 
+        let hover_pos = {
+            let camera = self.camera.lock().unwrap();
+            let input = self.input.lock().unwrap();
+            TilePosition::from_world_pos(camera.screen_to_world(input.get_mouse_position()))
+        };
+        let ui_state = *self.ui_state.lock().unwrap();
+        let palette = self.accessibility.palette();
+
         // Draw world
         {
             let camera = self.camera.lock().unwrap();
@@ -869,57 +2536,217 @@ impl GameState {
 
             // Draw map with locked access
             {
+                let started = get_time();
                 let mut map = self.map.lock().unwrap();
-                map.draw(&camera, self.selected_pos.as_ref());
+                map.visible_tiles_count =
+                    map.draw(&camera, &self.selection.tiles(), palette.selected());
+                self.profiler
+                    .record("map", started, map.visible_tiles_count);
             }
 
-            for person in &self.people {
-                person.draw(); // Using the updated draw method without tiles_per_row
+            // Zones of the factions on the ground, under the people
+            let started = get_time();
+            let drawn = self.zones.draw(camera.visible_world_rect());
+            self.profiler.record("zones", started, drawn);
+
+            // Data overlay of the scripts, above the tiles and zones
+            let started = get_time();
+            let drawn = self.overlay.draw(camera.visible_world_rect());
+            self.profiler.record("overlay", started, drawn);
+
+            let started = get_time();
+            let drawn = self.vehicles.draw(camera.visible_world_rect());
+            self.profiler.record("vehicles", started, drawn);
+
+            let started = get_time();
+            let drawn = self.portals.draw(camera.visible_world_rect());
+            self.profiler.record("portals", started, drawn);
+
+            let started = get_time();
+            let drawn = draw_routes(
+                &self.map.lock().unwrap().routes,
+                self.route_editor.active(),
+                camera.visible_world_rect(),
+            );
+            self.profiler.record("routes", started, drawn);
+
+            let started = get_time();
+            let people = self.people.lock().unwrap();
+            for pos in self
+                .selection
+                .people()
+                .into_iter()
+                .filter_map(|id| people.position(id))
+            {
+                draw_circle_lines(
+                    pos.x,
+                    pos.y,
+                    PERSON_TILE_SIZE * 0.6,
+                    2.0,
+                    palette.selected(),
+                );
             }
+            let drawn = people.draw(camera.visible_world_rect(), camera.pixel_snap());
+            self.profiler.record("people", started, drawn);
+
+            let started = get_time();
+            let drawn = self.effects.draw(&camera);
+            self.profiler.record("effects", started, drawn);
+
+            // Fog over everything in the world, people out of sight included
+            let started = get_time();
+            let drawn = self.fog.draw(
+                camera.visible_world_rect(),
+                &self.map.lock().unwrap().bounds,
+            );
+            self.profiler.record("fog", started, drawn);
 
             // Highlight hovered tile if not dragging (only in debug mode)
             {
                 let input = self.input.lock().unwrap();
                 if input.get_drag_delta().is_none() {
-                    let mouse_pos = input.get_mouse_position();
-                    let hover_pos = TilePosition::from_world_pos(camera.screen_to_world(mouse_pos));
-                    self.debug.draw_tile_highlight(&hover_pos);
+                    self.debug
+                        .draw_tile_highlight(&hover_pos, palette.hovered());
+                }
+            }
+
+            // What painting would place, before the click commits it
+            if ui_state == UIState::TileCreation {
+                let input = self.input.lock().unwrap();
+                let brush = self.brush.lock().unwrap();
+                if let Some(tile_id) = brush.tile_id
+                    && input.world_has_mouse()
+                    && input.get_drag_delta().is_none()
+                {
+                    let started = get_time();
+                    let drawn = self.map.lock().unwrap().draw_ghost(
+                        &brush.footprint(hover_pos),
+                        tile_id,
+                        palette.invalid(),
+                    );
+                    self.profiler.record("ghost", started, drawn);
                 }
             }
+
+            // Measure line between tile centers
+            if ui_state == UIState::Measuring
+                && let Some(anchor) = self.measure_anchor
+            {
+                let world_pos = anchor.to_world_pos();
+                draw_rectangle_lines(world_pos.x, world_pos.y, TILE_SIZE, TILE_SIZE, 2.0, LIME);
+                let from = anchor.center_world_pos();
+                let to = hover_pos.center_world_pos();
+                draw_line(from.x, from.y, to.x, to.y, 2.0, LIME);
+            }
+
+            if let UIState::Plugin(index) = ui_state
+                && let Some(tool) = self.tools.get(index)
+                && !self.debugger_panel.is_paused()
+            {
+                let input = self.tool_input(hover_pos);
+                tool.lock().unwrap().draw_preview(&input);
+            }
+        }
+
+        // Composite the additional viewports over the main view
+        for viewport in &self.viewports {
+            set_default_camera();
+            viewport.draw_frame();
+            viewport.camera.apply();
+            let started = get_time();
+            let drawn = self.map.lock().unwrap().draw(
+                &viewport.camera,
+                &self.selection.tiles(),
+                palette.selected(),
+            );
+            self.profiler.record("map", started, drawn);
+
+            let started = get_time();
+            let drawn = self.zones.draw(viewport.camera.visible_world_rect());
+            self.profiler.record("zones", started, drawn);
+
+            let started = get_time();
+            let drawn = self.overlay.draw(viewport.camera.visible_world_rect());
+            self.profiler.record("overlay", started, drawn);
+
+            let started = get_time();
+            let drawn = self.vehicles.draw(viewport.camera.visible_world_rect());
+            self.profiler.record("vehicles", started, drawn);
+
+            let started = get_time();
+            let drawn = self.portals.draw(viewport.camera.visible_world_rect());
+            self.profiler.record("portals", started, drawn);
+
+            let started = get_time();
+            let drawn = self.people.lock().unwrap().draw(
+                viewport.camera.visible_world_rect(),
+                viewport.camera.pixel_snap(),
+            );
+            self.profiler.record("people", started, drawn);
+
+            let started = get_time();
+            let drawn = self.fog.draw(
+                viewport.camera.visible_world_rect(),
+                &self.map.lock().unwrap().bounds,
+            );
+            self.profiler.record("fog", started, drawn);
         }
 
         // Draw UI (always visible)
         set_default_camera();
-        self.ui.draw_instructions();
-
-        // Draw tile preview with locked map
+        let view = self.camera.lock().unwrap().view;
+        set_ui_scale(view.hud_scale(screen_height()));
+        let mut layout = HudLayout::new(
+            Rect::new(0.0, 0.0, screen_width(), screen_height()),
+            HUD_MARGIN * ui_scale(),
+        );
+        let started = get_time();
+        let drawn = self.effects.draw_texts(&self.camera.lock().unwrap());
+        self.profiler.record("effects", started, drawn);
+
+        let started = get_time();
+        let drawn = self
+            .speech
+            .draw(&self.camera.lock().unwrap(), &self.people.lock().unwrap());
+        self.profiler.record("speech", started, drawn);
+
+        let started = get_time();
+        let indicator_count = {
+            let camera = self.camera.lock().unwrap();
+            let indicators = self.indicators.lock().unwrap();
+            let visible = indicators.collect(
+                &camera,
+                &self.people.lock().unwrap(),
+                self.followed_people(),
+            );
+            indicators.draw(&visible);
+            visible.len()
+        };
+        // The bottom left stack, from the bottom up
+        self.ui.draw_instructions(&mut layout);
+        if let Some((text, color)) = self.mode_text(ui_state, &hover_pos) {
+            draw_line_box(&mut layout, &text, color);
+        }
+        if self.macro_recorder.is_recording() {
+            draw_line_box(&mut layout, &self.macro_recording_text(), RED);
+        }
+        self.ui.draw_coordinates(&mut layout, &hover_pos);
+        self.ui.draw_turn(&mut layout, &self.clock);
         {
             let map = self.map.lock().unwrap();
-            self.ui
-                .draw_selected_tile_preview(self.selected_pos.as_ref(), &map);
+            self.ui.draw_map_name(&mut layout, &map.name, map.level);
         }
+        self.overlay.draw_legend(&mut layout);
 
-        // Display mode-specific message
-        match self.ui_state {
-            UIState::PeopleCreation => {
-                draw_text_with_background(
-                    "PEOPLE CREATION MODE (select a tile to exit, Right-click to add person)",
-                    10.0,
-                    screen_height() - 60.0,
-                    YELLOW,
-                );
-            }
-            UIState::TileCreation => {
-                // Optional: Show tile creation mode text
-                if self.selected_pos.is_some() {
-                    draw_text_with_background(
-                        "TILE CREATION MODE (Press `e` to switch to people mode)",
-                        10.0,
-                        screen_height() - 60.0,
-                        GREEN,
-                    );
-                }
-            }
+        // Draw tile preview with locked map
+        {
+            let map = self.map.lock().unwrap();
+            self.ui.draw_selected_tile_preview(
+                &mut layout,
+                view.tile_anchor,
+                self.selection.tile().as_ref(),
+                &map,
+            );
         }
 
         // Draw debug window if enabled
@@ -927,15 +2754,253 @@ impl GameState {
             let camera = self.camera.lock().unwrap();
             let input = self.input.lock().unwrap();
             let map = self.map.lock().unwrap();
-            self.debug
-                .draw(&map, &camera, self.selected_pos.as_ref(), &input);
+            let selected = self.selection.tile();
+            let pools = self.effects.pool_stats();
+            let profile = self.profiler.last_frame();
+            let frame = DebugFrame {
+                map: &map,
+                camera: &camera,
+                selected_pos: selected.as_ref(),
+                input: &input,
+                pools: &pools,
+                profile: &profile,
+                components: self.lua_ui.timings(),
+                mod_budget: &self.mod_budget,
+                degradations: self.budget.active(),
+                people_grouped: self.people.lock().unwrap().is_grouped_draw(),
+            };
+            self.debug.draw(&mut layout, view.debug_anchor, &frame);
+        }
+
+        self.profiler.record("ui", started, indicator_count);
+
+        // Timed on their own, the components of the UI scripts call into Lua every frame
+        if !self.debugger_panel.is_paused() {
+            let started = get_time();
+            let drawn = self.lua_ui.draw(self.budget.refresh_lua_ui());
+            self.profiler.record("lua_ui", started, drawn);
+        }
+
+        let started = get_time();
+        {
+            let mut map = self.map.lock().unwrap();
+            self.layers_panel.draw(&mut map);
+        }
+        let inspected = {
+            let map = self.map.lock().unwrap();
+            self.tile_inspector.draw(
+                &mut layout,
+                view.tile_anchor,
+                self.selection.tile().as_ref(),
+                &map,
+                !self.permissions.spectating(),
+            )
+        };
+        if let Some(InspectorAction::CopyAsBrush(tile_id)) = inspected {
+            self.brush.lock().unwrap().tile_id = Some(tile_id);
         }
+        if let Some(InspectorAction::Clear(pos)) = inspected {
+            let removed = self.map.lock().unwrap().remove_tile(&pos);
+            match removed {
+                Ok(Some(_)) => self
+                    .macro_recorder
+                    .record(format!("map.erase({}, {})", pos.x, pos.y)),
+                Ok(None) => {}
+                Err(reason) => self.tool_world.reject(&reason),
+            }
+        }
+        self.goals_panel.draw();
+        self.notifications_panel.draw();
+        self.mod_settings_panel.draw();
+        self.error_overlay.draw();
+        self.scenario_overlay.draw();
+        self.tutorial_overlay
+            .draw(&self.camera.lock().unwrap(), |name| self.ui_rect(name));
+        self.load_report_dialog.draw();
+        self.command_palette.draw();
+        self.docs_browser.draw();
+        self.profiler.record("ui", started, 0);
 
-        self.lua_ui.draw();
         // Draw console
+        let started = get_time();
+        self.console.high_contrast = self.accessibility.settings().high_contrast_console;
         self.console.draw();
+        self.debugger_panel.draw();
+        self.profiler
+            .record("console", started, usize::from(self.console.visible));
+
+        self.description.set(self.describe(hover_pos));
+    }
+
+    // The HUD, the panels shown and the components of the UI scripts as text, see `ui.describe()`
+    fn describe(&self, hover_pos: TilePosition) -> Vec<String> {
+        let mut lines = Vec::new();
+        {
+            let brush = self.brush.lock().unwrap();
+            let tile = brush
+                .tile_id
+                .map_or("no tile".to_string(), |id| format!("tile {}", id));
+            lines.push(format!(
+                "Tool: {}, brush: {}, {}x{} {}",
+                self.tools.state_name(*self.ui_state.lock().unwrap()),
+                tile,
+                brush.size,
+                brush.size,
+                brush.shape.name()
+            ));
+        }
+        {
+            let map = self.map.lock().unwrap();
+            lines.push(format!("Map: {}, level {}", map.name, map.level));
+            let hovered = map
+                .get_visible_tile(&hover_pos)
+                .map_or("empty".to_string(), |tile| format!("tile {}", tile.id));
+            lines.push(format!(
+                "Hovered: ({}, {}) {}",
+                hover_pos.x, hover_pos.y, hovered
+            ));
+        }
+        if let Some(tile) = self.selection.tile() {
+            lines.push(format!("Selected tile: ({}, {})", tile.x, tile.y));
+        }
+        let people = self.selection.people();
+        if !people.is_empty() {
+            let ids: Vec<String> = people.iter().map(|id| id.0.to_string()).collect();
+            lines.push(format!("Selected people: {}", ids.join(", ")));
+        }
+        {
+            let camera = self.camera.lock().unwrap();
+            lines.push(format!(
+                "Camera: ({:.0}, {:.0}), zoom {:.2}",
+                camera.position.x, camera.position.y, camera.zoom
+            ));
+        }
+        lines.push(format!("People: {}", self.people.lock().unwrap().len()));
+        if self.permissions.spectating() {
+            lines.push("Spectating, editing is off".to_string());
+        }
+        if self.clock.mode() == TimeMode::Turns {
+            lines.push(format!("Turn: {}", self.clock.turn()));
+        }
+
+        lines.push(format!("Panels: {}", self.open_panels().join(", ")));
+        lines.extend(self.error_overlay.describe());
+        lines.extend(self.scenario_overlay.describe());
+        lines.extend(self.tutorial_overlay.describe());
+        lines.extend(self.command_palette.describe());
+        lines.extend(self.docs_browser.describe());
+        lines.extend(self.load_report_dialog.describe());
+        if self.macro_recorder.is_recording() {
+            lines.push(self.macro_recording_text());
+        }
+
+        for component in self.lua_ui.describe() {
+            lines.push(format!("Component: {}", component));
+        }
+        lines
+    }
+
+    // Where the part of the UI a tutorial step names is shown, None while it isn't
+    fn ui_rect(&self, name: &str) -> Option<Rect> {
+        match name {
+            "console" => self.console.visible.then(|| {
+                Rect::new(
+                    0.0,
+                    0.0,
+                    screen_width(),
+                    screen_height() * CONSOLE_HEIGHT_RATIO,
+                )
+            }),
+            "goals" => self.goals_panel.rect(),
+            "notifications" => self.notifications_panel.rect(),
+            _ => None,
+        }
+    }
+
+    fn open_panels(&self) -> Vec<&'static str> {
+        [
+            (self.console.visible, "console"),
+            (self.debug.is_enabled(), "debug"),
+            (self.layers_panel.visible, "layers"),
+            (self.tile_inspector.is_shown(), "tile inspector"),
+            (self.goals_panel.visible, "goals"),
+            (self.notifications_panel.visible, "notifications"),
+            (self.mod_settings_panel.visible, "mod settings"),
+            (!self.viewports.is_empty(), "picture-in-picture"),
+            (self.debugger_panel.is_paused(), "debugger"),
+        ]
+        .into_iter()
+        .filter(|(open, _)| *open)
+        .map(|(_, name)| name)
+        .collect()
+    }
+
+    /// The window, the camera and the open panels to pick up from next time
+    fn session(&self) -> Session {
+        let camera = self.camera.lock().unwrap();
+        Session {
+            window: Some(WindowGeometry {
+                width: screen_width() as u32,
+                height: screen_height() as u32,
+                // miniquad knows where the window is on Windows only
+                #[cfg(target_os = "windows")]
+                position: Some(macroquad::miniquad::window::get_window_position()),
+                #[cfg(not(target_os = "windows"))]
+                position: None,
+            }),
+            camera: Some(CameraPlacement {
+                map: self.map.lock().unwrap().file_path(),
+                x: camera.position.x,
+                y: camera.position.y,
+                zoom: camera.zoom,
+            }),
+            panels: Some(self.open_panels().into_iter().map(String::from).collect()),
+        }
+    }
+
+    // The window size was restored by `window_conf` already
+    fn restore_session(&mut self, session: &Session) {
+        if let Some((x, y)) = session.window.and_then(|window| window.position) {
+            macroquad::miniquad::window::set_window_position(x, y);
+        }
+        if let Some(placement) = &session.camera
+            && placement.map == self.map.lock().unwrap().file_path()
+        {
+            let mut camera = self.camera.lock().unwrap();
+            camera.position = Vec2::new(placement.x, placement.y);
+            camera.set_zoom(placement.zoom);
+        }
+        self.console.visible = session.panel_open("console", self.console.visible);
+        if session.panel_open("debug", self.debug.is_enabled()) != self.debug.is_enabled() {
+            self.debug.toggle();
+        }
+        self.layers_panel.visible = session.panel_open("layers", self.layers_panel.visible);
+        self.goals_panel.visible = session.panel_open("goals", self.goals_panel.visible);
+        self.notifications_panel.visible =
+            session.panel_open("notifications", self.notifications_panel.visible);
+        self.mod_settings_panel.visible =
+            session.panel_open("mod settings", self.mod_settings_panel.visible);
     }
 }
+
+// The window opens at the size it had when the last session ended
+fn window_conf() -> Conf {
+    let window = Session::load(SESSION_PATH).window;
+    Conf {
+        window_title: "Tilemap Example".to_string(),
+        window_width: window.map_or(800, |window| window.width as i32),
+        window_height: window.map_or(600, |window| window.height as i32),
+        ..Default::default()
+    }
+}
+// Value following the command-line flag, unless it's another flag
+fn arg_value(flag: &str) -> Option<String> {
+    std::env::args()
+        .skip_while(|arg| arg != flag)
+        .nth(1)
+        .filter(|value| !value.starts_with("--"))
+}
+
 // Function to find character textures using standard fs
 fn find_character_textures(dir_path: &str) -> Vec<PathBuf> {
     let mut paths = Vec::new();
@@ -973,27 +3038,195 @@ fn visit_dirs(dir: &Path, paths: &mut Vec<PathBuf>) -> std::io::Result<()> {
     Ok(())
 }
 
-#[macroquad::main("Tilemap Example")]
+#[macroquad::main(window_conf)]
 async fn main() {
+    // `--replay <log>` plays a run recorded with `--keep-events 0` back instead of the game, the
+    // codec is told by the extension unless `--event-codec` says otherwise
+    if let Some(path) = arg_value("--replay") {
+        let codec = arg_value("--event-codec")
+            .and_then(|name| Codec::from_name(&name))
+            .or_else(|| Codec::from_path(Path::new(&path)))
+            .unwrap_or_default();
+        match Replay::load(Path::new(&path), codec) {
+            Ok(replay) => {
+                let mut viewer = ReplayViewer::new(replay).await;
+                loop {
+                    viewer.update();
+                    viewer.draw();
+                    next_frame().await;
+                }
+            }
+            Err(e) => println!("Failed to load the replay {}: {}", path, e),
+        }
+        return;
+    }
     let (command_tx, command_rx) = mpsc::channel();
     let lua_engine = Arc::new(Mutex::new(LuaEngine::new(command_rx)));
-    let mut game = GameState::new(command_tx, lua_engine.clone()).await;
-    if let Err(e) = lua_engine.lock().unwrap().run_script(
-        r#"-- Add scripts directory to Lua's package path
-        package.path = "./scripts/?.lua;" .. package.path
-        require('init')"#,
-    ) {
-        println!("Error during lua initialization: {:?}", e);
+    // The projections rebuild from the stored events on their own threads, the game reads them
+    let projections = lua_engine.lock().unwrap().projections.clone();
+    splash::show_rebuild_progress(&projections).await;
+    let mut game = GameState::new(command_tx.clone(), lua_engine.clone()).await;
+    game.restore_session(&Session::load(SESSION_PATH));
+    {
+        let mut engine = lua_engine.lock().unwrap();
+        // `--script-arg key=value` pairs end up in the read-only `env` table of the scripts
+        match script_args::from_command_line(std::env::args()) {
+            Ok(args) => engine.set_script_args(&args).unwrap(),
+            Err(e) => println!("{}", e),
+        }
+        // `--keep-events N` for very long runs, the older events go to `--event-archive [path]`
+        // written with `--event-codec json|binary` and synced to disk as `--event-fsync
+        // never|write|N` says, after every write by default
+        if let Some(keep) = arg_value("--keep-events") {
+            let retention = keep
+                .parse()
+                .map_err(|_| format!("--keep-events expects a number of events, not {}", keep))
+                .and_then(|keep_last| {
+                    let codec = match arg_value("--event-codec") {
+                        Some(name) => Codec::from_name(&name).ok_or_else(|| {
+                            format!("--event-codec expects json or binary, not {}", name)
+                        })?,
+                        None => Codec::default(),
+                    };
+                    let fsync = match arg_value("--event-fsync") {
+                        Some(name) => FsyncPolicy::from_name(&name).ok_or_else(|| {
+                            format!(
+                                "--event-fsync expects never, write or a number of events, not {}",
+                                name
+                            )
+                        })?,
+                        None => FsyncPolicy::default(),
+                    };
+                    let archive = arg_value("--event-archive")
+                        .unwrap_or_else(|| format!("{}.{}", EVENT_ARCHIVE_NAME, codec.extension()));
+                    engine
+                        .set_event_retention(RetentionPolicy {
+                            keep_last: Some(keep_last),
+                            archive: Some(archive.clone().into()),
+                            codec,
+                            fsync,
+                        })
+                        .map(|recovery| (archive.clone(), recovery))
+                        .map_err(|e| format!("Failed to create {}: {}", archive, e))
+                });
+            match retention {
+                Ok((archive, Some(recovery))) => {
+                    println!(
+                        "Archive of the last run kept aside from {}: {}",
+                        archive, recovery
+                    )
+                }
+                Ok((_, None)) => {}
+                Err(e) => println!("{}", e),
+            }
+        }
+        // Before the mods declare their settings, so they start with the saved values
+        engine.settings.persist_to(MOD_SETTINGS_PATH);
+        engine.tutorials.persist_to(TUTORIAL_PROGRESS_PATH);
+        // Plugins first, so init.lua can use the modules they add
+        for error in engine.load_plugins(PLUGIN_DIR) {
+            println!("{}", error);
+            engine
+                .error_log
+                .report("Plugins", ScriptError::new(&error, ""));
+        }
+        if let Err(e) = engine.run_script(
+            r#"-- Add scripts directory to Lua's package path
+            package.path = "./scripts/?.lua;" .. package.path
+            require('init')"#,
+        ) {
+            println!("Error during lua initialization: {}\n{}", e, e.traceback);
+            // Also show it in the error overlay, the console isn't open at startup
+            engine.error_log.report("Lua initialization", e);
+        }
+        engine.hooks.call(Hook::Init, ());
+        {
+            let mut map = game.map.lock().unwrap();
+            if map.loaded_from_file {
+                engine
+                    .hooks
+                    .load_state(&engine.lua, MAP_FILE_PATH, &map.mod_state);
+            }
+            // The mods are known only now that the scripts ran
+            if let Some(mut report) = map.load_report.take() {
+                let current = SaveVersion::current(&map.tileset_hash, engine.hooks.mod_names());
+                report.compare(&map.saved_version, &current);
+                game.load_report_dialog.show(report);
+            }
+        }
+        // The crowd benchmark spreads a big crowd as in the scenario, everyone else gets the
+        // default crowd of scripts/init.lua
+        if game.crowd_benchmark.is_some() {
+            let scenario = arg_value("--crowd-benchmark").unwrap_or_else(|| "uniform".to_string());
+            if let Err(e) = engine.run_script(&format!(
+                "people.clear()\nrequire('benchmark').run({:?}, {})",
+                scenario, CROWD_BENCHMARK_SIZE
+            )) {
+                println!("Error starting the crowd benchmark: {}\n{}", e, e.traceback);
+                engine.error_log.report("Crowd benchmark", e);
+            }
+        }
+        if let Some(path) = arg_value("--eval-file")
+            && let Err(e) = engine.run_file(&path)
+        {
+            println!("Error in {}: {}\n{}", path, e, e.traceback);
+            engine.error_log.report(&path, e);
+        }
     }
+    // `--spectator` for demos and watched runs, after the init scripts set up the world
+    if std::env::args().any(|arg| arg == "--spectator") {
+        game.permissions.set_spectating(true);
+    }
+    // Handles of the engine the frame test needs, taken while the engine is still free to lock
+    #[cfg(feature = "frame-test")]
+    let mut frame_test = {
+        let engine = lua_engine.lock().unwrap();
+        frame_test::FrameTest::from_args(engine.lua.clone(), engine.error_log.clone())
+    };
     // Create game state with client
     // spawn thread to run the lua engine
     thread::spawn(move || {
         lua_engine.lock().unwrap().run();
     });
-
+    // External tools send commands through `--socket [path]`, removed again when the game quits
+    #[cfg(unix)]
+    let _command_socket = std::env::args()
+        .any(|arg| arg == "--socket")
+        .then(|| {
+            let path = arg_value("--socket").unwrap_or_else(|| COMMAND_SOCKET_PATH.to_string());
+            CommandSocket::bind(&path, command_tx)
+                .inspect_err(|e| println!("Failed to open the command socket {}: {}", path, e))
+                .ok()
+        })
+        .flatten();
+
+    // Give mods a chance to run on_shutdown before the window closes
+    prevent_quit();
     loop {
+        if is_quit_requested() {
+            game.call_hook(Hook::Shutdown, ());
+            if let Err(e) = game.session().save(SESSION_PATH) {
+                println!("{}", e);
+            }
+            break;
+        }
+        let started = get_time();
         game.update();
         game.draw();
+        if let Some(benchmark) = &mut game.crowd_benchmark {
+            benchmark.end_frame(get_time() - started);
+        }
+        #[cfg(feature = "frame-test")]
+        if let Some(outcome) = frame_test.as_mut().and_then(|test| test.after_frame(&game)) {
+            match outcome {
+                Ok(summary) => println!("{}", summary),
+                Err(failure) => {
+                    println!("{}", failure);
+                    std::process::exit(1);
+                }
+            }
+            std::process::exit(0);
+        }
         next_frame().await;
     }
 }
@@ -0,0 +1,106 @@
+//! Editing the map shown from the scripts, as the `map` global
+
+use crate::config::MAP_FILL_LIMIT;
+use crate::{TileMap, TilePosition};
+use lua_engine::lua_engine::LuaEngine;
+use lua_engine::{LuaError, LuaResult, Table};
+use std::sync::{Arc, Mutex};
+
+fn check_tile_id(map: &TileMap, id: usize) -> LuaResult<()> {
+    if id >= map.tile_ids {
+        return Err(LuaError::RuntimeError(format!(
+            "There is no tile {}, the tileset has {} tiles",
+            id, map.tile_ids
+        )));
+    }
+    Ok(())
+}
+
+/// Set up the `map` global on the map shown. Like the brush, edits go to the active layer under
+/// the map lock, and fail with the reason in regions the scripts locked with `api.map.lock`:
+///
+/// ```lua
+/// map.fill({ x = 0, y = 0, w = 64, h = 64 }, 12)
+/// for x = 10, 20 do map.place(x, 30, 40) end
+/// map.flood_fill(0, 0, 7)
+/// ```
+pub(crate) fn install(lua_engine: &Arc<Mutex<LuaEngine>>, map: Arc<Mutex<TileMap>>) {
+    let lua = &lua_engine.lock().unwrap().lua;
+    let table = lua.create_table().unwrap();
+    {
+        let map = map.clone();
+        lua.create_function(move |_, (x, y, id): (i32, i32, usize)| {
+            let mut map = map.lock().unwrap();
+            check_tile_id(&map, id)?;
+            map.place_tile(&TilePosition::new(x, y), id)
+                .map_err(LuaError::RuntimeError)
+        })
+        .and_then(|f| table.set("place", f))
+        .unwrap();
+    }
+    {
+        let map = map.clone();
+        // Returns false if there was no tile
+        lua.create_function(move |_, (x, y): (i32, i32)| {
+            let mut map = map.lock().unwrap();
+            map.remove_tile(&TilePosition::new(x, y))
+                .map(|removed| removed.is_some())
+                .map_err(LuaError::RuntimeError)
+        })
+        .and_then(|f| table.set("erase", f))
+        .unwrap();
+    }
+    {
+        let map = map.clone();
+        // The rect is { x, y, w, h } in tiles, returns the number of tiles placed
+        lua.create_function(move |_, (rect, id): (Table, usize)| {
+            let (x, y): (i32, i32) = (rect.get("x")?, rect.get("y")?);
+            let (w, h): (i32, i32) = (rect.get("w")?, rect.get("h")?);
+            if w <= 0 || h <= 0 {
+                return Ok(0);
+            }
+            if w as usize * h as usize > MAP_FILL_LIMIT {
+                return Err(LuaError::RuntimeError(format!(
+                    "Can't fill {}x{} tiles at once, at most {}",
+                    w, h, MAP_FILL_LIMIT
+                )));
+            }
+            let mut map = map.lock().unwrap();
+            check_tile_id(&map, id)?;
+            map.fill_rect(
+                TilePosition::new(x, y),
+                TilePosition::new(x + w - 1, y + h - 1),
+                id,
+            )
+            .map_err(LuaError::RuntimeError)
+        })
+        .and_then(|f| table.set("fill", f))
+        .unwrap();
+    }
+    {
+        let map = map.clone();
+        // Like the fill tool, replaces the area of the same tile around the start within the map
+        // bounds, returns the number of tiles placed
+        lua.create_function(move |_, (x, y, id): (i32, i32, usize)| {
+            let mut map = map.lock().unwrap();
+            check_tile_id(&map, id)?;
+            map.flood_fill(&TilePosition::new(x, y), id)
+                .map_err(LuaError::RuntimeError)
+        })
+        .and_then(|f| table.set("flood_fill", f))
+        .unwrap();
+    }
+    {
+        // The id of the tile on the active layer, nil if it's empty
+        lua.create_function(move |_, (x, y): (i32, i32)| {
+            let map = map.lock().unwrap();
+            Ok(map.layers[map.active_layer]
+                .tiles
+                .get(&(x, y))
+                .map(|tile| tile.id))
+        })
+        .and_then(|f| table.set("get", f))
+        .unwrap();
+    }
+    lua.globals().set("map", table).unwrap();
+}
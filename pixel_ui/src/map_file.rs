@@ -0,0 +1,290 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Layout of the map files written, raised with a migration whenever an older file can't be read
+/// as it is. Files from before the layout was versioned are version 1.
+pub const MAP_SCHEMA_VERSION: u32 = 2;
+
+/// On-disk form of a single map layer
+#[derive(Serialize, Deserialize)]
+pub struct LayerFile {
+    pub name: String,
+    #[serde(default = "default_visible")]
+    pub visible: bool,
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+    /// Tiles stored as (x, y, tile id)
+    #[serde(default)]
+    pub tiles: Vec<(i32, i32, usize)>,
+}
+
+/// On-disk form of a patrol route
+#[derive(Serialize, Deserialize)]
+pub struct RouteFile {
+    pub name: String,
+    /// Waypoints stored as (x, y), in the order they are walked
+    pub waypoints: Vec<(i32, i32)>,
+}
+
+/// What a map file was written with, to tell what changed since when loading it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveVersion {
+    pub schema: u32,
+    /// Version of the game
+    #[serde(default)]
+    pub game: String,
+    /// Version of the Lua API, see `api.version`
+    #[serde(default)]
+    pub api: u32,
+    /// Content hash of the tileset manifest the tile ids refer to
+    #[serde(default)]
+    pub tileset: String,
+    /// Mods registered when saving
+    #[serde(default)]
+    pub mods: Vec<String>,
+}
+
+impl Default for SaveVersion {
+    fn default() -> Self {
+        Self {
+            schema: 1,
+            game: String::new(),
+            api: 0,
+            tileset: String::new(),
+            mods: Vec::new(),
+        }
+    }
+}
+
+impl SaveVersion {
+    /// The version of this build with the tileset and mods in use
+    pub fn current(tileset: &str, mods: Vec<String>) -> Self {
+        Self {
+            schema: MAP_SCHEMA_VERSION,
+            game: env!("CARGO_PKG_VERSION").to_string(),
+            api: lua_engine::lua_engine::API_VERSION,
+            tileset: tileset.to_string(),
+            mods,
+        }
+    }
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+fn default_opacity() -> f32 {
+    1.0
+}
+
+/// On-disk form of the whole map, layers ordered bottom to top
+#[derive(Serialize, Deserialize)]
+pub struct MapFile {
+    #[serde(default)]
+    pub version: SaveVersion,
+    #[serde(default)]
+    pub active_layer: usize,
+    pub layers: Vec<LayerFile>,
+    #[serde(default)]
+    pub routes: Vec<RouteFile>,
+    /// What the mods returned from `on_save`, by mod name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub mod_state: BTreeMap<String, serde_json::Value>,
+}
+
+// Brings a file of the schema `from` to the next one
+struct Migration {
+    from: u32,
+    description: &'static str,
+    apply: fn(&mut Json) -> Result<(), String>,
+}
+
+// Every schema below the current one needs its migration here
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 1,
+    description: "Named the layers that had no name",
+    apply: name_layers,
+}];
+
+// Hand-written files of the first schema may leave out the layer names
+fn name_layers(file: &mut Json) -> Result<(), String> {
+    let Some(layers) = file.get_mut("layers").and_then(Json::as_array_mut) else {
+        return Ok(());
+    };
+    for (i, layer) in layers.iter_mut().enumerate() {
+        let layer = layer
+            .as_object_mut()
+            .ok_or_else(|| format!("Layer {} is not an object", i))?;
+        layer
+            .entry("name")
+            .or_insert_with(|| Json::from(format!("layer {}", i + 1)));
+    }
+    Ok(())
+}
+
+/// What loading a map file ran into: the migrations it took and whatever differs from what it
+/// was saved with. Shown to the player instead of failing on the first mismatch.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadReport {
+    pub path: String,
+    pub migrations: Vec<String>,
+    pub warnings: Vec<String>,
+    /// Why the file couldn't be loaded at all
+    pub error: Option<String>,
+}
+
+impl LoadReport {
+    pub fn failed(path: &str, error: String) -> Self {
+        Self {
+            path: path.to_string(),
+            error: Some(error),
+            ..Default::default()
+        }
+    }
+
+    /// Note what differs between the version the file was saved with and the current one
+    pub fn compare(&mut self, saved: &SaveVersion, current: &SaveVersion) {
+        if saved.schema == 1 {
+            // Unversioned files tell nothing else
+            return;
+        }
+        if saved.game != current.game {
+            self.warnings.push(format!(
+                "Saved with game version {}, this is {}",
+                saved.game, current.game
+            ));
+        }
+        if saved.api != current.api {
+            self.warnings.push(format!(
+                "Saved with Lua API version {}, this is {}",
+                saved.api, current.api
+            ));
+        }
+        if saved.tileset != current.tileset {
+            self.warnings
+                .push("The tileset changed since, tiles may look different".to_string());
+        }
+        for name in &saved.mods {
+            if !current.mods.contains(name) {
+                self.warnings.push(format!(
+                    "Mod '{}' is missing, its saved state is kept",
+                    name
+                ));
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.migrations.is_empty() && self.warnings.is_empty() && self.error.is_none()
+    }
+
+    /// The report as text, one line per entry
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        lines.extend(self.error.iter().map(|e| format!("Error: {}", e)));
+        lines.extend(self.migrations.iter().map(|m| format!("Migrated: {}", m)));
+        lines.extend(self.warnings.iter().map(|w| format!("Warning: {}", w)));
+        lines
+    }
+}
+
+/// Content hash of a file, FNV-1a so it stays the same between builds
+pub fn content_hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+impl MapFile {
+    /// Read a map file, migrated to the current schema. Files of a newer schema than this build
+    /// knows are refused.
+    pub fn load(path: &str) -> Result<(Self, LoadReport), String> {
+        let content =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let json: Json = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse {}: {}", path, e))?;
+        Self::migrate(path, json)
+    }
+
+    fn migrate(path: &str, mut json: Json) -> Result<(Self, LoadReport), String> {
+        let mut report = LoadReport {
+            path: path.to_string(),
+            ..Default::default()
+        };
+        let schema = json
+            .pointer("/version/schema")
+            .and_then(Json::as_u64)
+            .unwrap_or(1) as u32;
+        if schema > MAP_SCHEMA_VERSION {
+            return Err(format!(
+                "{} was saved by a newer game (map schema {}, this game reads up to {})",
+                path, schema, MAP_SCHEMA_VERSION
+            ));
+        }
+        for from in schema..MAP_SCHEMA_VERSION {
+            let migration = MIGRATIONS
+                .iter()
+                .find(|migration| migration.from == from)
+                .ok_or_else(|| format!("No migration from map schema {} for {}", from, path))?;
+            (migration.apply)(&mut json)
+                .map_err(|e| format!("Failed to migrate {}: {}", path, e))?;
+            report.migrations.push(format!(
+                "{} (schema {} to {})",
+                migration.description,
+                from,
+                from + 1
+            ));
+        }
+        let file =
+            serde_json::from_value(json).map_err(|e| format!("Failed to parse {}: {}", path, e))?;
+        Ok((file, report))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+        }
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize map: {}", e))?;
+        fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_old_files_are_migrated_and_newer_refused() {
+        let old = serde_json::json!({
+            "layers": [{ "tiles": [[0, 0, 3]] }, { "name": "objects" }]
+        });
+        let (file, report) = MapFile::migrate("old.json", old).unwrap();
+        assert_eq!(file.layers[0].name, "layer 1");
+        assert_eq!(file.layers[1].name, "objects");
+        assert_eq!(file.version.schema, 1);
+        assert_eq!(report.migrations.len(), 1);
+
+        let newer =
+            serde_json::json!({ "version": { "schema": MAP_SCHEMA_VERSION + 1 }, "layers": [] });
+        assert!(MapFile::migrate("new.json", newer).is_err());
+    }
+
+    #[test]
+    fn test_report_lists_mismatches() {
+        let saved = SaveVersion::current("aaaa", vec!["quests".to_string(), "weather".to_string()]);
+        let mut current = saved.clone();
+        current.tileset = "bbbb".to_string();
+        current.mods = vec!["weather".to_string()];
+        let mut report = LoadReport::default();
+        report.compare(&saved, &current);
+        assert_eq!(report.warnings.len(), 2);
+        assert!(report.warnings[1].contains("'quests'"));
+        assert_eq!(content_hash(b"a"), content_hash(b"a"));
+        assert_ne!(content_hash(b"a"), content_hash(b"b"));
+    }
+}
@@ -0,0 +1,123 @@
+use crate::config::{MOD_SETTINGS_PANEL_WIDTH, MOD_SETTINGS_ROW_HEIGHT};
+use lua_engine::mod_settings::{ModSettings, SettingKind, SettingValue};
+use macroquad::hash;
+use macroquad::prelude::*;
+use macroquad::ui::{root_ui, widgets};
+
+/// Panel with a control for every setting the mods declared, generated from their declarations
+pub struct ModSettingsPanel {
+    settings: ModSettings,
+    pub(crate) visible: bool,
+}
+
+impl ModSettingsPanel {
+    pub(crate) fn new(settings: ModSettings) -> Self {
+        Self {
+            settings,
+            visible: false,
+        }
+    }
+
+    pub(crate) fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub(crate) fn captures_mouse(&self, screen_pos: Vec2) -> bool {
+        self.visible && root_ui().is_mouse_over(screen_pos)
+    }
+
+    pub(crate) fn draw(&self) {
+        if !self.visible {
+            return;
+        }
+        let mods = self.settings.mods();
+        let rows: usize = mods.iter().map(|(_, settings)| settings.len() + 2).sum();
+        let height = 40.0 + rows.max(1) as f32 * MOD_SETTINGS_ROW_HEIGHT;
+        let mut changes = Vec::new();
+        let mut resets = Vec::new();
+
+        widgets::Window::new(
+            hash!(),
+            Vec2::new((screen_width() - MOD_SETTINGS_PANEL_WIDTH) / 2.0, 80.0),
+            Vec2::new(MOD_SETTINGS_PANEL_WIDTH, height),
+        )
+        .label("Mod settings (O to hide)")
+        .ui(&mut root_ui(), |ui| {
+            if mods.is_empty() {
+                ui.label(None, "No mod declares settings");
+            }
+            for (m, (mod_name, settings)) in mods.iter().enumerate() {
+                ui.label(None, mod_name);
+                for (s, setting) in settings.iter().enumerate() {
+                    let id = hash!("mod_setting", m, s);
+                    let value = match (&setting.kind, &setting.value) {
+                        (SettingKind::Boolean, SettingValue::Boolean(on)) => {
+                            let mut on = *on;
+                            ui.checkbox(id, &setting.name, &mut on);
+                            SettingValue::Boolean(on)
+                        }
+                        // Without a range there is nothing to slide along
+                        (SettingKind::Number { min, max, .. }, SettingValue::Number(current))
+                            if !(*min as f32).is_finite() || !(*max as f32).is_finite() =>
+                        {
+                            let mut text = current.to_string();
+                            ui.input_text(id, &setting.name, &mut text);
+                            text.trim()
+                                .parse()
+                                .map_or_else(|_| setting.value.clone(), SettingValue::Number)
+                        }
+                        (SettingKind::Number { min, max, step }, SettingValue::Number(current)) => {
+                            let mut n = *current as f32;
+                            ui.slider(id, &setting.name, *min as f32..*max as f32, &mut n);
+                            let n = match step {
+                                Some(step) if *step > 0.0 => {
+                                    min + ((n as f64 - min) / step).round() * step
+                                }
+                                _ => n as f64,
+                            };
+                            // The slider works in f32, only take what was actually moved
+                            if (n - current).abs() > 1e-6 * current.abs().max(1.0) {
+                                SettingValue::Number(n)
+                            } else {
+                                setting.value.clone()
+                            }
+                        }
+                        (SettingKind::Choice(options), SettingValue::Text(text)) => {
+                            let variants: Vec<&str> = options.iter().map(String::as_str).collect();
+                            let mut selected = options
+                                .iter()
+                                .position(|option| option == text)
+                                .unwrap_or(0);
+                            ui.combo_box(id, &setting.name, &variants, &mut selected);
+                            SettingValue::Text(options[selected].clone())
+                        }
+                        (_, SettingValue::Text(text)) => {
+                            let mut text = text.clone();
+                            ui.input_text(id, &setting.name, &mut text);
+                            SettingValue::Text(text)
+                        }
+                        (_, value) => value.clone(),
+                    };
+                    if value != setting.value {
+                        changes.push((mod_name.clone(), setting.key.clone(), value));
+                    }
+                }
+                if ui.button(None, "Reset to defaults") {
+                    resets.push(mod_name.clone());
+                }
+                ui.separator();
+            }
+        });
+
+        for (mod_name, key, value) in changes {
+            if let Err(e) = self.settings.set(&mod_name, &key, value) {
+                println!("{}", e);
+            }
+        }
+        for mod_name in resets {
+            if let Err(e) = self.settings.reset(&mod_name) {
+                println!("{}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::config::{
+    CONSOLE_LINE_HEIGHT, NOTIFICATIONS_PANEL_WIDTH, TEXT_BACKGROUND_COLOR, TEXT_FONT_SIZE,
+    TOAST_WIDTH,
+};
+use lua_engine::notifications::Notifications;
+use macroquad::hash;
+use macroquad::prelude::*;
+use macroquad::ui::{root_ui, widgets};
+
+// Seconds a toast takes to fade out at the end
+const TOAST_FADE: f64 = 0.5;
+// Newest entries of the event log listed in the panel
+const LOG_LINES: usize = 20;
+
+/// Toasts of the notifications in the bottom right corner, and the event log with the mute
+/// toggles of the categories
+pub struct NotificationsPanel {
+    notifications: Notifications,
+    pub(crate) visible: bool,
+}
+
+impl NotificationsPanel {
+    pub(crate) fn new(notifications: Notifications) -> Self {
+        Self {
+            notifications,
+            visible: false,
+        }
+    }
+
+    pub(crate) fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub(crate) fn captures_mouse(&self, screen_pos: Vec2) -> bool {
+        self.visible && root_ui().is_mouse_over(screen_pos)
+    }
+
+    /// Where the event log is drawn unless it was dragged away, None while it's hidden
+    pub(crate) fn rect(&self) -> Option<Rect> {
+        self.visible.then(|| {
+            let lines = self.notifications.log().len().min(LOG_LINES);
+            Self::log_rect(self.notifications.categories().len(), lines)
+        })
+    }
+
+    fn log_rect(categories: usize, lines: usize) -> Rect {
+        let height = 60.0 + (categories + lines) as f32 * CONSOLE_LINE_HEIGHT * 1.5;
+        Rect::new(
+            screen_width() - NOTIFICATIONS_PANEL_WIDTH - 20.0,
+            80.0,
+            NOTIFICATIONS_PANEL_WIDTH,
+            height,
+        )
+    }
+
+    // Newest toast at the bottom, fading out before it disappears
+    fn draw_toasts(&self) {
+        let toasts = self.notifications.toasts();
+        let height = CONSOLE_LINE_HEIGHT + 10.0;
+        for (i, toast) in toasts.iter().rev().enumerate() {
+            let alpha = (toast.remaining / TOAST_FADE).min(1.0) as f32;
+            let x = screen_width() - TOAST_WIDTH - 20.0;
+            let y = screen_height() - 20.0 - (i + 1) as f32 * (height + 5.0);
+            let background = Color {
+                a: TEXT_BACKGROUND_COLOR.a * alpha,
+                ..TEXT_BACKGROUND_COLOR
+            };
+            draw_rectangle(x, y, TOAST_WIDTH, height, background);
+            draw_text(
+                &format!(
+                    "[{}] {}",
+                    toast.notification.category, toast.notification.text
+                ),
+                x + 10.0,
+                y + height - 10.0,
+                TEXT_FONT_SIZE,
+                Color { a: alpha, ..WHITE },
+            );
+        }
+    }
+
+    fn draw_log(&self) {
+        let categories = self.notifications.categories();
+        let log = self.notifications.log();
+        let rect = Self::log_rect(categories.len(), log.len().min(LOG_LINES));
+        let mut toggled = Vec::new();
+
+        widgets::Window::new(hash!(), rect.point(), rect.size())
+            .label("Event log (N to hide)")
+            .ui(&mut root_ui(), |ui| {
+                for (i, (category, muted)) in categories.iter().enumerate() {
+                    let mut shown = !muted;
+                    ui.checkbox(hash!("notification_category", i), category, &mut shown);
+                    if shown == *muted {
+                        toggled.push((category.clone(), !shown));
+                    }
+                }
+                ui.separator();
+                // Newest first
+                for notification in log.iter().rev().take(LOG_LINES) {
+                    ui.label(
+                        None,
+                        &format!(
+                            "{} [{}] {}",
+                            notification.tick, notification.category, notification.text
+                        ),
+                    );
+                }
+            });
+
+        for (category, muted) in toggled {
+            self.notifications.set_muted(&category, muted);
+        }
+    }
+
+    pub(crate) fn draw(&self) {
+        self.draw_toasts();
+        if self.visible {
+            self.draw_log();
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::config::{
+    OVERLAY_LEGEND_BAR_HEIGHT, OVERLAY_LEGEND_STEPS, OVERLAY_LEGEND_WIDTH, TILE_SIZE,
+};
+use crate::hud::{Anchor, HudLayout};
+use crate::utils::{draw_text_box, text_box_size, text_scale};
+use lua_engine::color::Rgba;
+use lua_engine::overlay::{sample, Overlay};
+use lua_engine::text_format::format_number;
+use macroquad::prelude::*;
+
+fn to_color(color: Rgba) -> Color {
+    Color::new(color.r, color.g, color.b, color.a)
+}
+
+/// The values of the `overlay` global as translucent tiles above the map, with their legend
+pub struct OverlayLayer {
+    overlay: Overlay,
+}
+
+impl OverlayLayer {
+    pub(crate) fn new(overlay: Overlay) -> Self {
+        Self { overlay }
+    }
+
+    // Returns the number of tiles drawn
+    pub(crate) fn draw(&self, visible: Rect) -> usize {
+        let mut drawn = 0;
+        for ((x, y), color) in self.overlay.tiles() {
+            let rect = Rect::new(
+                x as f32 * TILE_SIZE,
+                y as f32 * TILE_SIZE,
+                TILE_SIZE,
+                TILE_SIZE,
+            );
+            if !rect.overlaps(&visible) {
+                continue;
+            }
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, to_color(color));
+            drawn += 1;
+        }
+        drawn
+    }
+
+    /// The title with a bar of the ramp between the low and the high end of the range, in screen
+    /// space at the bottom left
+    pub(crate) fn draw_legend(&self, layout: &mut HudLayout) {
+        let Some(legend) = self.overlay.legend() else {
+            return;
+        };
+        let font_size = 16.0 * text_scale();
+        let title = if legend.title.is_empty() {
+            Vec2::ZERO
+        } else {
+            text_box_size(&legend.title)
+        };
+        let rect = layout.place(
+            Anchor::BottomLeft,
+            Vec2::new(
+                title.x.max(OVERLAY_LEGEND_WIDTH),
+                title.y + OVERLAY_LEGEND_BAR_HEIGHT + font_size,
+            ),
+        );
+        if !legend.title.is_empty() {
+            draw_text_box(&legend.title, rect.point(), WHITE);
+        }
+        let x = rect.x;
+        let y = rect.y + title.y;
+
+        let step_width = OVERLAY_LEGEND_WIDTH / OVERLAY_LEGEND_STEPS as f32;
+        for step in 0..OVERLAY_LEGEND_STEPS {
+            let t = step as f64 / (OVERLAY_LEGEND_STEPS - 1) as f64;
+            draw_rectangle(
+                x + step as f32 * step_width,
+                y,
+                step_width.ceil(),
+                OVERLAY_LEGEND_BAR_HEIGHT,
+                to_color(sample(&legend.ramp, t)),
+            );
+        }
+        draw_rectangle_lines(
+            x,
+            y,
+            OVERLAY_LEGEND_WIDTH,
+            OVERLAY_LEGEND_BAR_HEIGHT,
+            1.0,
+            WHITE,
+        );
+
+        // Whole numbers unless the range is too narrow for them to tell the ends apart
+        let decimals = if legend.max - legend.min >= 10.0 {
+            0
+        } else {
+            2
+        };
+        let label_y = y + OVERLAY_LEGEND_BAR_HEIGHT + font_size;
+        let max = format_number(legend.max, decimals);
+        let max_width = measure_text(&max, None, font_size as u16, 1.0).width;
+        draw_text(
+            &format_number(legend.min, decimals),
+            x,
+            label_y,
+            font_size,
+            WHITE,
+        );
+        draw_text(
+            &max,
+            x + OVERLAY_LEGEND_WIDTH - max_width,
+            label_y,
+            font_size,
+            WHITE,
+        );
+    }
+}
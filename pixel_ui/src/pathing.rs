@@ -0,0 +1,302 @@
+//! Paths for the people across the walkable tiles, and the smoothing that straightens them out
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+const ORTHOGONAL: [(i32, i32); 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+const DIAGONAL: [(i32, i32); 4] = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+
+/// How the people move from tile to tile
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Movement {
+    /// Step diagonally as well, never across the corner of a tile that can't be walked on
+    pub diagonal: bool,
+    /// Walk straight lines across the walkable tiles instead of hopping from tile to tile
+    pub smoothing: bool,
+}
+
+/// The tiles next to a tile that can be stepped on, with the cost of the step. `cost` tells the
+/// movement cost of a tile as `(x, y)`, none if it can't be walked on.
+pub fn neighbors(
+    (x, y): (i32, i32),
+    diagonal: bool,
+    cost: &impl Fn(i32, i32) -> Option<f32>,
+) -> Vec<((i32, i32), f32)> {
+    let mut result: Vec<_> = ORTHOGONAL
+        .iter()
+        .filter_map(|(dx, dy)| cost(x + dx, y + dy).map(|cost| ((x + dx, y + dy), cost)))
+        .collect();
+    if diagonal {
+        // Cutting a corner would clip a tile that can't be walked on
+        result.extend(DIAGONAL.iter().filter_map(|(dx, dy)| {
+            cost(x + dx, y)?;
+            cost(x, y + dy)?;
+            cost(x + dx, y + dy).map(|cost| ((x + dx, y + dy), cost * std::f32::consts::SQRT_2))
+        }));
+    }
+    result
+}
+
+#[derive(PartialEq)]
+struct Open {
+    estimate: f32,
+    tile: (i32, i32),
+}
+
+impl Eq for Open {}
+
+// Reversed, so the heap pops the lowest estimate first
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Distance when every step costs 1, diagonal steps sqrt 2
+fn heuristic((x1, y1): (i32, i32), (x2, y2): (i32, i32), diagonal: bool) -> f32 {
+    let (dx, dy) = ((x2 - x1).abs() as f32, (y2 - y1).abs() as f32);
+    if diagonal {
+        dx.max(dy) + (std::f32::consts::SQRT_2 - 1.0) * dx.min(dy)
+    } else {
+        dx + dy
+    }
+}
+
+/// The cheapest tiles from `from` to `to`, both included, with A*. Gives up after looking at
+/// `limit` tiles, as an unreachable target would otherwise have it search the whole map.
+pub fn find_path(
+    from: (i32, i32),
+    to: (i32, i32),
+    diagonal: bool,
+    limit: usize,
+    cost: &impl Fn(i32, i32) -> Option<f32>,
+) -> Option<Vec<(i32, i32)>> {
+    cost(to.0, to.1)?;
+    let mut open = BinaryHeap::from([Open {
+        estimate: heuristic(from, to, diagonal),
+        tile: from,
+    }]);
+    let mut costs = HashMap::from([(from, 0.0f32)]);
+    let mut came_from = HashMap::new();
+
+    while let Some(Open { tile, .. }) = open.pop() {
+        if tile == to {
+            let mut path = vec![to];
+            while let Some(previous) = came_from.get(path.last().unwrap()) {
+                path.push(*previous);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if costs.len() > limit {
+            return None;
+        }
+        let cost_so_far = costs[&tile];
+        for (next, step_cost) in neighbors(tile, diagonal, cost) {
+            let next_cost = cost_so_far + step_cost;
+            if costs.get(&next).is_none_or(|known| next_cost < *known) {
+                costs.insert(next, next_cost);
+                came_from.insert(next, tile);
+                open.push(Open {
+                    estimate: next_cost + heuristic(next, to, diagonal),
+                    tile: next,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// The tiles a straight line between the centers of two tiles passes, both ends included. Passing
+/// exactly through a corner counts the tiles on both sides of it.
+pub fn line_tiles(from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let (nx, ny) = (dx.abs(), dy.abs());
+    let (sx, sy) = (dx.signum(), dy.signum());
+    let (mut x, mut y) = from;
+    let mut tiles = vec![from];
+    let (mut ix, mut iy) = (0, 0);
+    while ix < nx || iy < ny {
+        // Compare where the line crosses the next vertical and horizontal tile edges
+        let decision = (1 + 2 * ix) * ny - (1 + 2 * iy) * nx;
+        if decision == 0 {
+            tiles.push((x + sx, y));
+            tiles.push((x, y + sy));
+            x += sx;
+            y += sy;
+            ix += 1;
+            iy += 1;
+        } else if decision < 0 {
+            x += sx;
+            ix += 1;
+        } else {
+            y += sy;
+            iy += 1;
+        }
+        tiles.push((x, y));
+    }
+    tiles
+}
+
+/// Straighten a path by string pulling: from every corner kept, walk straight to the farthest
+/// tile of the path the line to which crosses only walkable tiles, none of them costlier than the
+/// path tiles it replaces. The ends are always kept.
+pub fn smooth(path: &[(i32, i32)], cost: &impl Fn(i32, i32) -> Option<f32>) -> Vec<(i32, i32)> {
+    let Some(&first) = path.first() else {
+        return Vec::new();
+    };
+    let mut result = vec![first];
+    let mut anchor = 0;
+    while anchor < path.len() - 1 {
+        let mut next = anchor + 1;
+        for candidate in (anchor + 2..path.len()).rev() {
+            let replaced = path[anchor..=candidate]
+                .iter()
+                .filter_map(|(x, y)| cost(*x, *y))
+                .fold(0.0, f32::max);
+            let clear = line_tiles(path[anchor], path[candidate])
+                .into_iter()
+                .all(|(x, y)| cost(x, y).is_some_and(|cost| cost <= replaced));
+            if clear {
+                next = candidate;
+                break;
+            }
+        }
+        result.push(path[next]);
+        anchor = next;
+    }
+    result
+}
+
+/// Tiles for a group of `count` to spread out on around `target`, the closest to it first. They
+/// are the walkable tiles connected to the target, so everyone gets a tile of their own unless
+/// there are fewer than `count` within `limit` tiles, then some share. Empty if the target can't
+/// be walked on.
+pub fn formation(
+    target: (i32, i32),
+    count: usize,
+    limit: usize,
+    cost: &impl Fn(i32, i32) -> Option<f32>,
+) -> Vec<(i32, i32)> {
+    if count == 0 || cost(target.0, target.1).is_none() {
+        return Vec::new();
+    }
+    // Breadth first in 8 directions, so the group fills a square ring by ring
+    let mut tiles = Vec::new();
+    let mut seen = HashSet::from([target]);
+    let mut queue = VecDeque::from([target]);
+    while let Some(tile) = queue.pop_front() {
+        tiles.push(tile);
+        if tiles.len() == count || seen.len() > limit {
+            break;
+        }
+        for (next, _) in neighbors(tile, true, cost) {
+            if seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    // Closest to the target first, breadth first alone puts the diagonal ones too early
+    tiles.sort_by_key(|(x, y)| (x - target.0).pow(2) + (y - target.1).pow(2));
+    let found = tiles.len();
+    (0..count).map(|i| tiles[i % found]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Walkable tiles of a map drawn as text, '#' being a wall and '~' costing 3
+    fn map(rows: &[&str]) -> impl Fn(i32, i32) -> Option<f32> {
+        let rows: Vec<Vec<char>> = rows.iter().map(|row| row.chars().collect()).collect();
+        move |x, y| {
+            let tile = rows
+                .get(usize::try_from(y).ok()?)?
+                .get(usize::try_from(x).ok()?)?;
+            match tile {
+                '#' => None,
+                '~' => Some(3.0),
+                _ => Some(1.0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_diagonal_steps_dont_cut_corners() {
+        let cost = map(&["..#", "...", "#.."]);
+        let from_center: Vec<_> = neighbors((1, 1), true, &cost)
+            .into_iter()
+            .map(|(tile, _)| tile)
+            .collect();
+        assert!(from_center.contains(&(0, 0)));
+        assert!(from_center.contains(&(2, 2)));
+        assert!(!from_center.contains(&(2, 0)) && !from_center.contains(&(0, 2)));
+
+        // Around the wall's corner, not across it
+        let cost = map(&[".#", ".."]);
+        assert_eq!(
+            find_path((0, 0), (1, 1), true, 100, &cost),
+            Some(vec![(0, 0), (0, 1), (1, 1)])
+        );
+        assert_eq!(
+            find_path((0, 0), (2, 2), true, 100, &map(&["...", "...", "..."]))
+                .map(|path| path.len()),
+            Some(3)
+        );
+        assert_eq!(find_path((0, 0), (1, 0), false, 100, &cost), None);
+    }
+
+    #[test]
+    fn test_formations_spread_out_around_the_target() {
+        let cost = map(&["#....", "#....", "#....", "#...."]);
+        let slots = formation((1, 1), 5, 100, &cost);
+        assert_eq!(slots[0], (1, 1));
+        assert_eq!(slots.iter().collect::<HashSet<_>>().len(), 5);
+        assert!(slots.iter().all(|(x, y)| cost(*x, *y).is_some()));
+        assert!(slots[1..]
+            .iter()
+            .all(|(x, y)| (x - 1).abs() <= 1 && (y - 1).abs() <= 1));
+
+        // More people than room, they share
+        let cost = map(&["#..#"]);
+        assert_eq!(
+            formation((1, 0), 3, 100, &cost),
+            vec![(1, 0), (2, 0), (1, 0)]
+        );
+        assert!(formation((0, 0), 3, 100, &cost).is_empty());
+    }
+
+    #[test]
+    fn test_paths_are_smoothed_around_walls() {
+        let cost = map(&[
+            ".......", //
+            ".......", "...#...", ".......",
+        ]);
+        let path = find_path((0, 0), (6, 3), false, 100, &cost).unwrap();
+        assert_eq!(path.len(), 10);
+        let smoothed = smooth(&path, &cost);
+        assert_eq!(smoothed.first(), Some(&(0, 0)));
+        assert_eq!(smoothed.last(), Some(&(6, 3)));
+        assert!(smoothed.len() < 4, "{:?}", smoothed);
+        for pair in smoothed.windows(2) {
+            assert!(line_tiles(pair[0], pair[1])
+                .iter()
+                .all(|(x, y)| cost(*x, *y).is_some()));
+        }
+
+        // Costly tiles the path went around stay avoided
+        let cost = map(&["....", ".~~.", "...."]);
+        let path = vec![(0, 1), (0, 2), (1, 2), (2, 2), (3, 2), (3, 1)];
+        assert_eq!(smooth(&path, &cost), vec![(0, 1), (0, 2), (3, 2), (3, 1)]);
+        assert_eq!(
+            line_tiles((0, 0), (2, 2)),
+            vec![(0, 0), (1, 0), (0, 1), (1, 1), (2, 1), (1, 2), (2, 2)]
+        );
+    }
+}
@@ -0,0 +1,764 @@
+use crate::animation::{AnimationState, CharacterSprites};
+use crate::batch::QuadBatch;
+use crate::camera::snap_to_pixel;
+use crate::config::{
+    CROWD_BENCHMARK_FRAME_BUDGET, CROWD_BENCHMARK_REPORT_INTERVAL, CROWD_BENCHMARK_SIZE,
+    GROUP_DEPARTURE_INTERVAL, MAX_SIMULATION_STEPS, OFFSCREEN_UPDATE_INTERVAL, PATH_SEARCH_LIMIT,
+    PERSON_TILE_SIZE, SIMULATION_STEP,
+};
+use crate::effects::lua_color;
+use crate::pathing::{formation, Movement};
+use crate::{Direction, Person, TileMap, TilePosition};
+use lua_engine::lua_engine::LuaEngine;
+use lua_engine::{LuaError, LuaValue, Table};
+use macroquad::prelude::{rand, Rect, Vec2, WHITE};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// Steps an off-screen person catches up on at most, those missed beyond them are dropped so the
+// catching up fits in a frame
+const MAX_PENDING_STEPS: u32 = MAX_SIMULATION_STEPS * OFFSCREEN_UPDATE_INTERVAL as u32;
+
+/// Stable ID of a person, unlike the index it stays the same when other people come and go.
+/// Assigned locally for now, people coming from the logic will use their domain ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PersonId(pub u32);
+
+/// Someone taken off the map along with the chunk they stood on, see `People::take_within`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct StoredPerson {
+    pub id: u32,
+    pub x: i32,
+    pub y: i32,
+    /// Name of the character sheet they are drawn with
+    pub sheet: String,
+}
+
+/// People on the map, looked up by ID or by tile, shared with Lua as `people`.
+/// The data the per-frame loops go through is kept in separate arrays, indexed like `persons`.
+pub struct People {
+    persons: Vec<Person>,
+    ids: Vec<PersonId>,
+    positions: Vec<Vec2>,
+    // Steps not simulated yet for people updated less often while off-screen
+    pending_steps: Vec<u32>,
+    indices: HashMap<PersonId, usize>,
+    next_id: u32,
+    // Sheets new people pick their look from
+    characters: Vec<Arc<CharacterSprites>>,
+    // Steps simulated so far, picks which off-screen people are updated
+    step: usize,
+    movement: Movement,
+    // Experimental drawing grouping all sprites by texture, see `QuadBatch::grouped`
+    grouped_draw: bool,
+}
+
+impl People {
+    pub(crate) fn shared(
+        lua_engine: &Arc<Mutex<LuaEngine>>,
+        characters: Vec<Arc<CharacterSprites>>,
+        map: Arc<Mutex<TileMap>>,
+    ) -> Arc<Mutex<Self>> {
+        let people = Arc::new(Mutex::new(Self {
+            persons: Vec::new(),
+            ids: Vec::new(),
+            positions: Vec::new(),
+            pending_steps: Vec::new(),
+            indices: HashMap::new(),
+            next_id: 1,
+            characters,
+            step: 0,
+            movement: Movement::default(),
+            grouped_draw: false,
+        }));
+
+        let lua = &lua_engine.lock().unwrap().lua;
+        let table = lua.create_table().unwrap();
+        {
+            let people = people.clone();
+            lua.create_function(move |lua, id: u32| {
+                let people = people.lock().unwrap();
+                let Some(&index) = people.indices.get(&PersonId(id)) else {
+                    return Ok(None);
+                };
+                let person = &people.persons[index];
+                let position = people.positions[index];
+                let result = lua.create_table()?;
+                result.set("id", id)?;
+                result.set("x", person.tile_pos.x)?;
+                result.set("y", person.tile_pos.y)?;
+                result.set("world_x", position.x)?;
+                result.set("world_y", position.y)?;
+                result.set("animation", person.animation.state().name())?;
+                result.set("sheet", people.characters[person.sheet].name.as_str())?;
+                Ok(Some(result))
+            })
+            .and_then(|f| table.set("get", f))
+            .unwrap();
+        }
+        {
+            let people = people.clone();
+            // Returns false when there is no such person
+            lua.create_function(move |_, (id, state): (u32, String)| {
+                let state = AnimationState::from_name(&state).ok_or_else(|| {
+                    LuaError::RuntimeError(format!(
+                        "Unknown animation state '{}', expected one of: {}",
+                        state,
+                        AnimationState::ALL.map(AnimationState::name).join(", ")
+                    ))
+                })?;
+                let mut people = people.lock().unwrap();
+                let Some(&index) = people.indices.get(&PersonId(id)) else {
+                    return Ok(false);
+                };
+                people.persons[index].animation.set_state(state);
+                Ok(true)
+            })
+            .and_then(|f| table.set("set_animation", f))
+            .unwrap();
+        }
+        {
+            let people = people.clone();
+            // Returns false when there is no such person or their character has no such layer
+            lua.create_function(move |_, (id, layer, visible): (u32, String, bool)| {
+                let mut people = people.lock().unwrap();
+                let Some(&index) = people.indices.get(&PersonId(id)) else {
+                    return Ok(false);
+                };
+                Ok(people.persons[index]
+                    .animation
+                    .set_layer_visible(&layer, visible))
+            })
+            .and_then(|f| table.set("set_layer", f))
+            .unwrap();
+        }
+        {
+            let people = people.clone();
+            // set_tint(id, color) tints the body, set_tint(id, color, layer) one of its layers.
+            // A nil color removes the tint, returns false like set_layer.
+            lua.create_function(
+                move |_, (id, color, layer): (u32, LuaValue, Option<String>)| {
+                    let tint = lua_color(color, WHITE)?;
+                    let mut people = people.lock().unwrap();
+                    let Some(&index) = people.indices.get(&PersonId(id)) else {
+                        return Ok(false);
+                    };
+                    Ok(people.persons[index]
+                        .animation
+                        .set_tint(layer.as_deref(), tint))
+                },
+            )
+            .and_then(|f| table.set("set_tint", f))
+            .unwrap();
+        }
+        {
+            let people = people.clone();
+            lua.create_function(move |_, (x, y): (i32, i32)| {
+                let people = people.lock().unwrap();
+                let ids: Vec<u32> = people
+                    .at_tile(TilePosition::new(x, y))
+                    .into_iter()
+                    .map(|id| id.0)
+                    .collect();
+                Ok(ids)
+            })
+            .and_then(|f| table.set("at", f))
+            .unwrap();
+        }
+        {
+            let people = people.clone();
+            lua.create_function(move |_, ()| {
+                let ids: Vec<u32> = people.lock().unwrap().ids.iter().map(|id| id.0).collect();
+                Ok(ids)
+            })
+            .and_then(|f| table.set("ids", f))
+            .unwrap();
+        }
+        {
+            let people = people.clone();
+            lua.create_function(move |_, ()| Ok(people.lock().unwrap().len()))
+                .and_then(|f| table.set("count", f))
+                .unwrap();
+        }
+        {
+            let people = people.clone();
+            // Returns the ID of the new person standing in the middle of the tile, nil when there
+            // are no character sheets to draw them with. Without a sheet, see `people.sheets()`,
+            // they get a random one.
+            lua.create_function(move |_, (x, y, sheet): (i32, i32, Option<String>)| {
+                let tile = TilePosition::new(x, y);
+                let mut people = people.lock().unwrap();
+                let sheet = match sheet {
+                    Some(name) => Some(people.sheet_index(&name).ok_or_else(|| {
+                        LuaError::RuntimeError(format!(
+                            "Unknown character sheet '{}', see people.sheets()",
+                            name
+                        ))
+                    })?),
+                    None => None,
+                };
+                let id = people.spawn(tile, tile.center_world_pos(), sheet);
+                Ok(id.map(|id| id.0))
+            })
+            .and_then(|f| table.set("spawn", f))
+            .unwrap();
+        }
+        {
+            let people = people.clone();
+            lua.create_function(move |_, ()| {
+                let people = people.lock().unwrap();
+                let names: Vec<String> = people
+                    .characters
+                    .iter()
+                    .map(|sprites| sprites.name.clone())
+                    .collect();
+                Ok(names)
+            })
+            .and_then(|f| table.set("sheets", f))
+            .unwrap();
+        }
+        {
+            let people = people.clone();
+            lua.create_function(move |_, ()| {
+                people.lock().unwrap().clear();
+                Ok(())
+            })
+            .and_then(|f| table.set("clear", f))
+            .unwrap();
+        }
+        {
+            let people = people.clone();
+            // Returns false if there is no such person, a destination that can't be reached
+            // leaves them standing where they are
+            lua.create_function(move |_, (id, x, y): (u32, i32, i32)| {
+                Ok(people
+                    .lock()
+                    .unwrap()
+                    .walk_to(PersonId(id), TilePosition::new(x, y)))
+            })
+            .and_then(|f| table.set("walk_to", f))
+            .unwrap();
+        }
+        {
+            let people = people.clone();
+            // Returns the number of people sent, unknown IDs are skipped
+            lua.create_function(move |_, (ids, x, y): (Vec<u32>, i32, i32)| {
+                // The map first, like the frame update does
+                let map = map.lock().unwrap();
+                let ids: Vec<PersonId> = ids.into_iter().map(PersonId).collect();
+                Ok(people
+                    .lock()
+                    .unwrap()
+                    .move_group(&ids, TilePosition::new(x, y), &map))
+            })
+            .and_then(|f| table.set("move_group", f))
+            .unwrap();
+        }
+        {
+            let people = people.clone();
+            // Options not given keep their value
+            lua.create_function(move |_, options: Table| {
+                let mut people = people.lock().unwrap();
+                if let Some(diagonal) = options.get::<Option<bool>>("diagonal")? {
+                    people.movement.diagonal = diagonal;
+                }
+                if let Some(smoothing) = options.get::<Option<bool>>("smoothing")? {
+                    people.movement.smoothing = smoothing;
+                }
+                Ok(())
+            })
+            .and_then(|f| table.set("set_movement", f))
+            .unwrap();
+        }
+        {
+            let people = people.clone();
+            lua.create_function(move |lua, ()| {
+                let movement = people.lock().unwrap().movement;
+                let result = lua.create_table()?;
+                result.set("diagonal", movement.diagonal)?;
+                result.set("smoothing", movement.smoothing)?;
+                Ok(result)
+            })
+            .and_then(|f| table.set("movement", f))
+            .unwrap();
+        }
+        lua.globals().set("people", table).unwrap();
+
+        people
+    }
+
+    pub(crate) fn add(&mut self, person: Person, position: Vec2) -> PersonId {
+        let id = PersonId(self.next_id);
+        self.next_id += 1;
+        self.indices.insert(id, self.persons.len());
+        self.persons.push(person);
+        self.ids.push(id);
+        self.positions.push(position);
+        self.pending_steps.push(0);
+        id
+    }
+
+    /// Index of the character sheet with the name, the first one if several share it
+    pub(crate) fn sheet_index(&self, name: &str) -> Option<usize> {
+        self.characters
+            .iter()
+            .position(|sprites| sprites.name == name)
+    }
+
+    /// Add someone with the look of the `sheet`, a random one without it, and a random facing on
+    /// `tile`, standing at `position`
+    pub(crate) fn spawn(
+        &mut self,
+        tile: TilePosition,
+        position: Vec2,
+        sheet: Option<usize>,
+    ) -> Option<PersonId> {
+        if self.characters.is_empty() {
+            return None;
+        }
+        let sheet = sheet.unwrap_or_else(|| rand::gen_range(0, self.characters.len()));
+        let sprites = self.characters[sheet].clone();
+        let direction = match rand::gen_range(0, 4) {
+            0 => Direction::Up,
+            1 => Direction::Down,
+            2 => Direction::Left,
+            _ => Direction::Right,
+        };
+        let person = Person::new(tile.x, tile.y, direction, sheet, sprites);
+        Some(self.add(person, position))
+    }
+
+    /// Take everyone standing on a tile between the corners out, to be written to disk with the
+    /// chunk of the map they are on
+    pub(crate) fn take_within(
+        &mut self,
+        min: TilePosition,
+        max: TilePosition,
+    ) -> Vec<StoredPerson> {
+        let mut taken = Vec::new();
+        let mut index = 0;
+        while index < self.persons.len() {
+            let tile = self.persons[index].tile_pos;
+            if !((min.x..=max.x).contains(&tile.x) && (min.y..=max.y).contains(&tile.y)) {
+                index += 1;
+                continue;
+            }
+            let person = self.persons.swap_remove(index);
+            let id = self.ids.swap_remove(index);
+            self.positions.swap_remove(index);
+            self.pending_steps.swap_remove(index);
+            self.indices.remove(&id);
+            if let Some(moved) = self.ids.get(index) {
+                self.indices.insert(*moved, index);
+            }
+            taken.push(StoredPerson {
+                id: id.0,
+                x: tile.x,
+                y: tile.y,
+                sheet: self.characters[person.sheet].name.clone(),
+            });
+        }
+        taken
+    }
+
+    /// Put people taken out back under their IDs, standing on their tiles. Those whose sheet is
+    /// gone get the first one.
+    pub(crate) fn restore(&mut self, stored: Vec<StoredPerson>) {
+        if self.characters.is_empty() {
+            return;
+        }
+        for person in stored {
+            let sheet = self.sheet_index(&person.sheet).unwrap_or(0);
+            let tile = TilePosition::new(person.x, person.y);
+            let sprites = self.characters[sheet].clone();
+            let id = PersonId(person.id);
+            self.indices.insert(id, self.persons.len());
+            self.persons
+                .push(Person::new(tile.x, tile.y, Direction::Down, sheet, sprites));
+            self.ids.push(id);
+            self.positions.push(tile.center_world_pos());
+            self.pending_steps.push(0);
+            self.next_id = self.next_id.max(person.id + 1);
+        }
+    }
+
+    /// Remove everyone, IDs aren't reused so stale ones from before stay unknown
+    pub(crate) fn clear(&mut self) {
+        self.persons.clear();
+        self.ids.clear();
+        self.positions.clear();
+        self.pending_steps.clear();
+        self.indices.clear();
+    }
+
+    /// Send someone walking to `tile` along a path, once they are done with their current step.
+    /// Returns false if there is no such person.
+    pub(crate) fn walk_to(&mut self, id: PersonId, tile: TilePosition) -> bool {
+        let Some(&index) = self.indices.get(&id) else {
+            return false;
+        };
+        self.persons[index].set_destination(tile, 0.0);
+        true
+    }
+
+    /// Send a group to spread out around `target`, each to a tile of their own where there is
+    /// room. The one closest to the target takes the middle and sets off first, the others follow
+    /// `GROUP_DEPARTURE_INTERVAL` apart. Returns the number of people sent.
+    pub(crate) fn move_group(
+        &mut self,
+        ids: &[PersonId],
+        target: TilePosition,
+        map: &TileMap,
+    ) -> usize {
+        let mut members: Vec<usize> = ids
+            .iter()
+            .filter_map(|id| self.indices.get(id).copied())
+            .collect();
+        members.sort_unstable();
+        members.dedup();
+        let target_pos = target.center_world_pos();
+        members.sort_by(|a, b| {
+            self.positions[*a]
+                .distance_squared(target_pos)
+                .total_cmp(&self.positions[*b].distance_squared(target_pos))
+        });
+
+        let cost = |x, y| map.movement_cost(&TilePosition::new(x, y));
+        let slots = formation(
+            (target.x, target.y),
+            members.len(),
+            PATH_SEARCH_LIMIT,
+            &cost,
+        );
+        for (order, (index, (x, y))) in members.iter().zip(&slots).enumerate() {
+            self.persons[*index].set_destination(
+                TilePosition::new(*x, *y),
+                order as f32 * GROUP_DEPARTURE_INTERVAL,
+            );
+        }
+        slots.len()
+    }
+
+    pub(crate) fn position(&self, id: PersonId) -> Option<Vec2> {
+        self.indices.get(&id).map(|&index| self.positions[index])
+    }
+
+    /// Name of the character sheet the person is drawn with
+    pub(crate) fn sheet_of(&self, id: PersonId) -> Option<&str> {
+        let &index = self.indices.get(&id)?;
+        Some(self.characters[self.persons[index].sheet].name.as_str())
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.persons.len()
+    }
+
+    /// Simulate everyone for a number of `SIMULATION_STEP`s, `visible` are the world areas shown
+    /// by the cameras. People outside of them are only updated every `OFFSCREEN_UPDATE_INTERVAL`
+    /// steps (catching up on the steps they missed) and aren't animated, spreading the cost of
+    /// large crowds over several frames. Without `offscreen` they aren't updated at all and catch
+    /// up once it's given again, on `MAX_PENDING_STEPS` at most.
+    pub(crate) fn update(&mut self, steps: u32, map: &TileMap, visible: &[Rect], offscreen: bool) {
+        if steps == 0 {
+            return;
+        }
+        // The buckets of the steps of this frame, all of them once there are enough steps
+        let first_bucket = (self.step + 1) % OFFSCREEN_UPDATE_INTERVAL;
+        let buckets = (steps as usize).min(OFFSCREEN_UPDATE_INTERVAL);
+        self.step = self.step.wrapping_add(steps as usize);
+        let visible = expanded(visible);
+
+        for (index, (person, position)) in self
+            .persons
+            .iter_mut()
+            .zip(self.positions.iter_mut())
+            .enumerate()
+        {
+            let pending = &mut self.pending_steps[index];
+            *pending = (*pending + steps).min(MAX_PENDING_STEPS);
+            let on_screen = visible.iter().any(|rect| rect.contains(*position));
+            let bucket =
+                (index + OFFSCREEN_UPDATE_INTERVAL - first_bucket) % OFFSCREEN_UPDATE_INTERVAL;
+            if on_screen || (offscreen && bucket < buckets) {
+                for _ in 0..*pending {
+                    person.update(position, SIMULATION_STEP, map, self.movement, on_screen);
+                }
+                *pending = 0;
+            }
+        }
+    }
+
+    /// Switch between drawing sorted by sheet and grouping every sprite by its texture, returns
+    /// whether they are grouped now
+    pub(crate) fn toggle_grouped_draw(&mut self) -> bool {
+        self.grouped_draw = !self.grouped_draw;
+        self.grouped_draw
+    }
+
+    pub(crate) fn is_grouped_draw(&self) -> bool {
+        self.grouped_draw
+    }
+
+    /// Draw the people inside `visible`, grouped by sheet so that consecutive sprites share a
+    /// texture and go out in few meshes. Grouped drawing instead collects the sprites of all
+    /// textures, layers included, and submits one texture after the other, at the cost of layers
+    /// of overlapping people mixing. With `pixel` they are snapped to whole screen pixels, see
+    /// `snap_to_pixel`. Returns how many were drawn.
+    pub(crate) fn draw(&self, visible: Rect, pixel: Option<f32>) -> usize {
+        let visible = expanded(&[visible])[0];
+        let mut drawn: Vec<usize> = (0..self.persons.len())
+            .filter(|&index| visible.contains(self.positions[index]))
+            .collect();
+        let mut batch = if self.grouped_draw {
+            QuadBatch::grouped()
+        } else {
+            // Stable, so people with the same sheet keep their order between frames
+            drawn.sort_by_key(|&index| {
+                let person = &self.persons[index];
+                (person.sheet, person.animation.state())
+            });
+            QuadBatch::new()
+        };
+        // Shadows and other layers below go first, so they never cover someone else's body
+        for &index in &drawn {
+            let position = snap_to_pixel(self.positions[index], pixel);
+            self.persons[index].draw_below(&mut batch, position);
+        }
+        batch.flush();
+        for &index in &drawn {
+            let position = snap_to_pixel(self.positions[index], pixel);
+            self.persons[index].draw(&mut batch, position);
+        }
+        batch.flush();
+        drawn.len()
+    }
+
+    /// People currently on the tile, moving people count for the tile they left until they arrive
+    pub(crate) fn at_tile(&self, tile: TilePosition) -> Vec<PersonId> {
+        self.ids
+            .iter()
+            .zip(&self.persons)
+            .filter(|(_, person)| person.tile_pos == tile)
+            .map(|(&id, _)| id)
+            .collect()
+    }
+
+    pub(crate) fn closest(&self, world_pos: Vec2) -> Option<PersonId> {
+        self.ids
+            .iter()
+            .zip(&self.positions)
+            .min_by(|(_, a), (_, b)| {
+                a.distance_squared(world_pos)
+                    .total_cmp(&b.distance_squared(world_pos))
+            })
+            .map(|(&id, _)| id)
+    }
+}
+
+// Grow the areas by a sprite so people partly inside are still handled
+fn expanded(areas: &[Rect]) -> Vec<Rect> {
+    areas
+        .iter()
+        .map(|rect| {
+            Rect::new(
+                rect.x - PERSON_TILE_SIZE,
+                rect.y - PERSON_TILE_SIZE,
+                rect.w + PERSON_TILE_SIZE * 2.0,
+                rect.h + PERSON_TILE_SIZE * 2.0,
+            )
+        })
+        .collect()
+}
+
+/// Frame statistics printed every few seconds while running with `--crowd-benchmark [scenario]`,
+/// checked against `CROWD_BENCHMARK_SIZE` people in `CROWD_BENCHMARK_FRAME_BUDGET` per frame.
+/// The check goes by the 99th percentile of the work of the frames, the time from the start of
+/// the update to the end of the draw without waiting for the display. The presented FPS follow
+/// the refresh rate with vsync on and are only reported. Run it in a release build, the
+/// scenarios are the ones of scripts/benchmark.lua:
+///
+/// ```sh
+/// cargo run --release -p sb5s-pixel -- --crowd-benchmark uniform
+/// ```
+#[derive(Default)]
+pub struct CrowdBenchmark {
+    frames: u32,
+    elapsed: f64,
+    // Seconds of work of every frame since the last report
+    work: Vec<f64>,
+    update_time: f64,
+    draw_calls: usize,
+    people: usize,
+    grouped: bool,
+}
+
+impl CrowdBenchmark {
+    /// Record the update of a frame that came `dt` seconds after the last one, of which
+    /// `update_time` went into updating people, and the draw calls of the frame before, drawn
+    /// `grouped` by texture or not
+    pub(crate) fn record(
+        &mut self,
+        dt: f64,
+        update_time: f64,
+        people: usize,
+        draw_calls: usize,
+        grouped: bool,
+    ) {
+        self.frames += 1;
+        self.elapsed += dt;
+        self.update_time += update_time;
+        self.draw_calls += draw_calls;
+        self.people = people;
+        self.grouped = grouped;
+    }
+
+    /// Record the seconds the frame took from the start of its update to the end of its draw,
+    /// printing the report every `CROWD_BENCHMARK_REPORT_INTERVAL`
+    pub(crate) fn end_frame(&mut self, work: f64) {
+        self.work.push(work);
+        if self.elapsed >= CROWD_BENCHMARK_REPORT_INTERVAL {
+            println!("{}", self.report());
+            *self = Self::default();
+        }
+    }
+
+    // Seconds of work the share `p` of the frames stayed within
+    fn percentile(&self, p: f64) -> f64 {
+        let mut work = self.work.clone();
+        work.sort_by(f64::total_cmp);
+        let rank = (p * work.len() as f64).ceil() as usize;
+        work.get(rank.saturating_sub(1))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Whether the benchmark crowd ran with 99% of the frames within the budget
+    fn passed(&self) -> bool {
+        self.people >= CROWD_BENCHMARK_SIZE
+            && !self.work.is_empty()
+            && self.percentile(0.99) <= CROWD_BENCHMARK_FRAME_BUDGET
+    }
+
+    fn report(&self) -> String {
+        let frames = self.frames.max(1) as f64;
+        format!(
+            "Crowd benchmark: {} people, {:.1} FPS presented, frame work p50 {:.2} ms, p95 {:.2} ms, p99 {:.2} ms (budget {:.2} ms for {} people), people update {:.2} ms per frame, {:.0} draw calls per frame ({}): {}",
+            self.people,
+            frames / self.elapsed,
+            self.percentile(0.5) * 1000.0,
+            self.percentile(0.95) * 1000.0,
+            self.percentile(0.99) * 1000.0,
+            CROWD_BENCHMARK_FRAME_BUDGET * 1000.0,
+            CROWD_BENCHMARK_SIZE,
+            self.update_time * 1000.0 / frames,
+            self.draw_calls as f64 / frames,
+            if self.grouped { "grouped by texture" } else { "sorted by sheet" },
+            if self.passed() { "PASS" } else { "FAIL" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixed_clock::FixedClock;
+    use crate::layers::TileLayer;
+    use crate::tileset::{TileAtlas, TilesetManifest};
+    use crate::{Direction, Tile};
+    use macroquad::miniquad::{RawId, TextureId};
+    use macroquad::prelude::Texture2D;
+
+    fn people() -> People {
+        People {
+            persons: Vec::new(),
+            ids: Vec::new(),
+            positions: Vec::new(),
+            pending_steps: Vec::new(),
+            indices: HashMap::new(),
+            next_id: 1,
+            characters: vec![Arc::new(CharacterSprites::blank("Blank"))],
+            step: 0,
+            movement: Movement::default(),
+            grouped_draw: false,
+        }
+    }
+
+    fn walkable_map(width: i32, height: i32) -> TileMap {
+        let texture = Texture2D::from_miniquad_texture(TextureId::from_raw_id(RawId::OpenGl(0)));
+        let atlas = TileAtlas::new(256.0, 256.0, 0.0);
+        let mut map = TileMap::empty(texture, atlas, TilesetManifest::default());
+        let mut ground = TileLayer::new("ground");
+        for y in 0..height {
+            for x in 0..width {
+                ground.tiles.insert((x, y), Tile { id: 0 });
+            }
+        }
+        map.layers = vec![ground];
+        map
+    }
+
+    // People sent across the map after `seconds` of frames at `fps`, updated like `People::update`
+    // is told to
+    fn walk(fps: u32, seconds: u32, visible: &[Rect], offscreen: bool) -> People {
+        let map = walkable_map(40, 3);
+        let mut people = people();
+        for y in 0..3 {
+            let tile = TilePosition::new(0, y);
+            let sprites = people.characters[0].clone();
+            let mut person = Person::new(tile.x, tile.y, Direction::Right, 0, sprites);
+            person.set_destination(TilePosition::new(35, 2 - y), y as f32 * 0.3);
+            people.add(person, tile.center_world_pos());
+        }
+        let mut clock = FixedClock::new(SIMULATION_STEP, MAX_SIMULATION_STEPS);
+        for _ in 0..fps * seconds {
+            let steps = clock.advance(1.0 / fps as f32);
+            people.update(steps, &map, visible, offscreen);
+        }
+        people
+    }
+
+    #[test]
+    fn test_people_walk_the_same_at_any_frame_rate() {
+        let everywhere = [Rect::new(-1000.0, -1000.0, 10_000.0, 10_000.0)];
+        let positions = walk(60, 5, &everywhere, true).positions;
+        assert_ne!(positions[0], TilePosition::new(0, 0).center_world_pos());
+        for fps in [30, 144, 240] {
+            let other = walk(fps, 5, &everywhere, true).positions;
+            assert_eq!(other, positions, "at {} FPS", fps);
+        }
+    }
+
+    #[test]
+    fn test_steps_missed_off_screen_are_capped() {
+        // Off-screen people left out for a while, as the frame budget does under load
+        let people = walk(60, 10, &[], false);
+        assert!(people
+            .pending_steps
+            .iter()
+            .all(|pending| *pending == MAX_PENDING_STEPS));
+    }
+
+    #[test]
+    fn test_crowd_benchmark_checks_the_slow_frames() {
+        let mut benchmark = CrowdBenchmark::default();
+        let frame = |benchmark: &mut CrowdBenchmark, work| {
+            benchmark.record(1.0 / 60.0, 0.002, CROWD_BENCHMARK_SIZE, 10, true);
+            benchmark.end_frame(work);
+        };
+        for _ in 0..99 {
+            frame(&mut benchmark, 0.005);
+        }
+        frame(&mut benchmark, 0.5);
+        assert!(benchmark.passed());
+        assert_eq!(benchmark.percentile(0.99), 0.005);
+
+        // One more slow frame puts the 99th percentile over the budget, the average stays low
+        frame(&mut benchmark, 0.5);
+        assert!(!benchmark.passed());
+        assert!(benchmark.report().ends_with("FAIL"));
+        benchmark.people = CROWD_BENCHMARK_SIZE / 2;
+        benchmark.work = vec![0.005];
+        assert!(!benchmark.passed());
+    }
+}
@@ -0,0 +1,116 @@
+/// Something a `Pool` can hand out again after clearing it, keeping its allocation
+pub trait Reusable: Default {
+    fn reset(&mut self);
+}
+
+impl<T> Reusable for Vec<T> {
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+impl Reusable for String {
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+/// How often a pool could serve a request from released objects, shown in the debug window
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PoolStats {
+    pub created: usize,
+    pub reused: usize,
+    pub free: usize,
+}
+
+impl PoolStats {
+    pub fn reuse_ratio(&self) -> f32 {
+        let total = self.created + self.reused;
+        if total == 0 {
+            return 0.0;
+        }
+        self.reused as f32 / total as f32
+    }
+}
+
+/// Free list of released objects, so short-lived things like particles don't allocate every time
+/// they are spawned. Keeps at most `capacity` objects around, the rest is dropped.
+pub struct Pool<T: Reusable> {
+    free: Vec<T>,
+    capacity: usize,
+    created: usize,
+    reused: usize,
+}
+
+impl<T: Reusable> Pool<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            free: Vec::new(),
+            capacity,
+            created: 0,
+            reused: 0,
+        }
+    }
+
+    /// A released object if there is one, cleared, otherwise a new one
+    pub fn acquire(&mut self) -> T {
+        match self.free.pop() {
+            Some(item) => {
+                self.reused += 1;
+                item
+            }
+            None => {
+                self.created += 1;
+                T::default()
+            }
+        }
+    }
+
+    pub fn release(&mut self, mut item: T) {
+        if self.free.len() < self.capacity {
+            item.reset();
+            self.free.push(item);
+        }
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            created: self.created,
+            reused: self.reused,
+            free: self.free.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_released_objects_are_reused_cleared() {
+        let mut pool: Pool<Vec<u32>> = Pool::new(4);
+        let mut item = pool.acquire();
+        item.extend([1, 2, 3]);
+        pool.release(item);
+
+        let item = pool.acquire();
+        assert!(item.is_empty());
+        assert!(item.capacity() >= 3);
+        assert_eq!(
+            pool.stats(),
+            PoolStats {
+                created: 1,
+                reused: 1,
+                free: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_pool_keeps_at_most_its_capacity() {
+        let mut pool: Pool<String> = Pool::new(1);
+        pool.release(String::from("a"));
+        pool.release(String::from("b"));
+        assert_eq!(pool.stats().free, 1);
+    }
+}
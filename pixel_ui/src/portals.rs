@@ -0,0 +1,54 @@
+use crate::config::{PORTAL_COLOR, PORTAL_SIZE, TILE_SIZE};
+use lua_engine::Portals;
+use macroquad::prelude::*;
+
+/// The ends of the portals on the map shown, drawn as rings labeled with the id of their portal
+/// so the ends that belong together can be told apart
+pub struct PortalLayer {
+    portals: Portals,
+}
+
+impl PortalLayer {
+    pub(crate) fn new(portals: Portals) -> Self {
+        Self { portals }
+    }
+
+    // Returns the number of portal ends drawn
+    pub(crate) fn draw(&self, visible: Rect) -> usize {
+        let radius = TILE_SIZE * PORTAL_SIZE / 2.0;
+        let mut drawn = 0;
+        for (id, (x, y)) in self.portals.ends() {
+            let center = vec2((x as f32 + 0.5) * TILE_SIZE, (y as f32 + 0.5) * TILE_SIZE);
+            let bounds = Rect::new(
+                center.x - radius,
+                center.y - radius,
+                radius * 2.0,
+                radius * 2.0,
+            );
+            if !bounds.overlaps(&visible) {
+                continue;
+            }
+            draw_circle(
+                center.x,
+                center.y,
+                radius,
+                Color {
+                    a: 0.3,
+                    ..PORTAL_COLOR
+                },
+            );
+            draw_circle_lines(center.x, center.y, radius, 3.0, PORTAL_COLOR);
+            let text = id.to_string();
+            let dimensions = measure_text(&text, None, 16, 1.0);
+            draw_text(
+                &text,
+                center.x - dimensions.width / 2.0,
+                center.y + dimensions.height / 2.0,
+                16.0,
+                WHITE,
+            );
+            drawn += 1;
+        }
+        drawn
+    }
+}
@@ -0,0 +1,114 @@
+use crate::batch::take_draw_calls;
+use lua_engine::lua_engine::LuaEngine;
+use macroquad::prelude::get_time;
+use std::sync::{Arc, Mutex};
+
+/// CPU time, number of drawn items and meshes submitted of one draw phase in a frame
+#[derive(Debug, Clone, Copy)]
+pub struct PhaseTiming {
+    pub phase: &'static str,
+    pub ms: f64,
+    pub draws: usize,
+    pub draw_calls: usize,
+}
+
+#[derive(Default)]
+struct ProfileState {
+    current: Vec<PhaseTiming>,
+    // The last complete frame, what the debug window and Lua see
+    last: Vec<PhaseTiming>,
+}
+
+/// Times the draw phases (map, people, UI, ...) of every frame, shown in the debug window and
+/// available to scripts as `api.debug.frame_profile()`. Measures the CPU side only, macroquad
+/// submits the batched draw calls to the GPU at the end of the frame.
+#[derive(Clone, Default)]
+pub struct FrameProfiler {
+    state: Arc<Mutex<ProfileState>>,
+}
+
+impl FrameProfiler {
+    pub(crate) fn new(lua_engine: &Arc<Mutex<LuaEngine>>) -> Self {
+        let profiler = Self::default();
+
+        {
+            let profiler = profiler.clone();
+            lua_engine
+                .lock()
+                .unwrap()
+                .register_module("debug", move |lua, table| {
+                    // { total_ms = ..., map = { ms = ..., draws = ... }, people = { ... }, ... }
+                    let frame_profile = lua.create_function(move |lua, ()| {
+                        let phases = profiler.last_frame();
+                        let result = lua.create_table()?;
+                        for timing in &phases {
+                            let phase = lua.create_table()?;
+                            phase.set("ms", timing.ms)?;
+                            phase.set("draws", timing.draws)?;
+                            phase.set("draw_calls", timing.draw_calls)?;
+                            result.set(timing.phase, phase)?;
+                        }
+                        result.set(
+                            "total_ms",
+                            phases.iter().map(|timing| timing.ms).sum::<f64>(),
+                        )?;
+                        result.set(
+                            "draw_calls",
+                            phases.iter().map(|timing| timing.draw_calls).sum::<usize>(),
+                        )?;
+                        Ok(result)
+                    })?;
+                    table.set("frame_profile", frame_profile)
+                })
+                .unwrap();
+        }
+
+        profiler
+    }
+
+    /// Start a new frame, the phases recorded so far become the last frame
+    pub(crate) fn begin_frame(&self) {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        std::mem::swap(&mut state.current, &mut state.last);
+        state.current.clear();
+        // Submitted outside of the phases
+        take_draw_calls();
+    }
+
+    /// Meshes submitted in the last complete frame, see `batch::take_draw_calls`
+    pub(crate) fn draw_calls(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state.last.iter().map(|timing| timing.draw_calls).sum()
+    }
+
+    /// Record a phase that started at `started` (from `get_time`) and drew `draws` items, the
+    /// meshes submitted since the previous phase count for it.
+    /// Recording a phase again in the same frame adds to it, e.g. the map of every viewport.
+    pub(crate) fn record(&self, phase: &'static str, started: f64, draws: usize) {
+        let ms = (get_time() - started) * 1000.0;
+        let draw_calls = take_draw_calls();
+        let mut state = self.state.lock().unwrap();
+        match state
+            .current
+            .iter_mut()
+            .find(|timing| timing.phase == phase)
+        {
+            Some(timing) => {
+                timing.ms += ms;
+                timing.draws += draws;
+                timing.draw_calls += draw_calls;
+            }
+            None => state.current.push(PhaseTiming {
+                phase,
+                ms,
+                draws,
+                draw_calls,
+            }),
+        }
+    }
+
+    pub(crate) fn last_frame(&self) -> Vec<PhaseTiming> {
+        self.state.lock().unwrap().last.clone()
+    }
+}
@@ -0,0 +1,383 @@
+use crate::batch::QuadBatch;
+use crate::camera::CameraController;
+use crate::config::{
+    BUTTON_ACTIVE_COLOR, BUTTON_COLOR, BUTTON_HEIGHT, CHART_BACKGROUND_COLOR, REPLAY_CURSOR_COLOR,
+    REPLAY_DENSITY_COLOR, REPLAY_EVENTS_PER_SECOND, REPLAY_EVENT_TEXT_LENGTH, REPLAY_PERSON_COLOR,
+    REPLAY_SPEED_MAX, REPLAY_TIMELINE_HEIGHT, TEXT_BACKGROUND_COLOR, TEXT_FONT_SIZE, TILE_SIZE,
+    VEHICLE_COLOR, VEHICLE_OUTLINE_COLOR, VEHICLE_SIZE,
+};
+use crate::input::InputManager;
+use crate::tileset::{TileAtlas, TilesetManifest};
+use lua_engine::{MapId, Replay, ReplayState};
+use macroquad::prelude::*;
+
+// Seeking further ahead than this starts from the closest state the replay keeps instead
+const FORWARD_LIMIT: usize = 1000;
+// Buttons left of the scrubber, each this wide
+const BUTTON_WIDTH: f32 = 60.0;
+const BUTTONS: [Control; 5] = [
+    Control::Start,
+    Control::StepBack,
+    Control::PlayPause,
+    Control::Step,
+    Control::End,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Control {
+    Start,
+    StepBack,
+    PlayPause,
+    Step,
+    End,
+}
+
+/// A recorded run played back, shown by `--replay <log>` instead of the game. The world is
+/// drawn as it was after the events up to the cursor of the timeline at the bottom, which shows
+/// how many events happened along the run. Space plays and pauses, comma and period step,
+/// Home and End jump to the ends, minus and plus change the speed and dragging along the
+/// timeline seeks.
+pub struct ReplayViewer {
+    replay: Replay,
+    // The world after the first `position` events
+    state: ReplayState,
+    position: usize,
+    playing: bool,
+    // Events a second while playing
+    speed: f32,
+    // Part of the next event played already
+    pending: f32,
+    // Events per column of the timeline, for the width it was counted for
+    density: (usize, Vec<usize>),
+    scrubbing: bool,
+    camera: CameraController,
+    input: InputManager,
+    tileset: Texture2D,
+    atlas: TileAtlas,
+}
+
+impl ReplayViewer {
+    pub(crate) async fn new(replay: Replay) -> Self {
+        let tileset = load_texture("assets/tileset.png").await.unwrap();
+        tileset.set_filter(FilterMode::Nearest);
+        let manifest = TilesetManifest::load("assets/tileset.json");
+        let atlas = TileAtlas::new(tileset.width(), tileset.height(), manifest.padding);
+        Self {
+            replay,
+            state: ReplayState::default(),
+            position: 0,
+            playing: false,
+            speed: REPLAY_EVENTS_PER_SECOND,
+            pending: 0.0,
+            density: (0, Vec::new()),
+            scrubbing: false,
+            camera: CameraController::new(Vec2::ZERO),
+            input: InputManager::new(),
+            tileset,
+            atlas,
+        }
+    }
+
+    /// Replay up to the position, stepping forward applies the events passed instead of
+    /// seeking
+    fn seek(&mut self, position: usize) {
+        let position = position.min(self.replay.len());
+        if position >= self.position && position - self.position <= FORWARD_LIMIT {
+            for index in self.position..position {
+                if let Some(event) = self.replay.event(index) {
+                    self.state.apply(event);
+                }
+            }
+        } else {
+            self.state = self.replay.state_at(position);
+        }
+        self.position = position;
+    }
+
+    fn timeline_rect() -> Rect {
+        Rect::new(
+            0.0,
+            screen_height() - REPLAY_TIMELINE_HEIGHT,
+            screen_width(),
+            REPLAY_TIMELINE_HEIGHT,
+        )
+    }
+
+    fn button_rect(index: usize) -> Rect {
+        let timeline = Self::timeline_rect();
+        Rect::new(
+            timeline.x + 10.0 + index as f32 * (BUTTON_WIDTH + 5.0),
+            timeline.bottom() - BUTTON_HEIGHT - 10.0,
+            BUTTON_WIDTH,
+            BUTTON_HEIGHT,
+        )
+    }
+
+    // Right of the buttons, as tall as them
+    fn scrubber_rect() -> Rect {
+        let last = Self::button_rect(BUTTONS.len() - 1);
+        let x = last.right() + 15.0;
+        Rect::new(x, last.y, screen_width() - x - 10.0, BUTTON_HEIGHT)
+    }
+
+    fn press(&mut self, control: Control) {
+        match control {
+            Control::Start => self.seek(0),
+            Control::StepBack => self.seek(self.position.saturating_sub(1)),
+            Control::PlayPause => {
+                // Playing at the end starts over
+                if !self.playing && self.position == self.replay.len() {
+                    self.seek(0);
+                }
+                self.playing = !self.playing;
+                self.pending = 0.0;
+            }
+            Control::Step => self.seek(self.position + 1),
+            Control::End => self.seek(self.replay.len()),
+        }
+        if control != Control::PlayPause {
+            self.playing = false;
+        }
+    }
+
+    pub(crate) fn update(&mut self) {
+        let dt = get_frame_time();
+        let mouse = Vec2::from(mouse_position());
+        let over_timeline = Self::timeline_rect().contains(mouse);
+        self.input.update(over_timeline || self.scrubbing);
+        if !over_timeline && !self.scrubbing {
+            self.camera.update(&self.input, dt);
+        }
+
+        for (key, control) in [
+            (KeyCode::Space, Control::PlayPause),
+            (KeyCode::Comma, Control::StepBack),
+            (KeyCode::Period, Control::Step),
+            (KeyCode::Home, Control::Start),
+            (KeyCode::End, Control::End),
+        ] {
+            if is_key_pressed(key) {
+                self.press(control);
+            }
+        }
+        if is_key_pressed(KeyCode::Minus) {
+            self.speed = (self.speed / 2.0).max(1.0);
+        }
+        if is_key_pressed(KeyCode::Equal) {
+            self.speed = (self.speed * 2.0).min(REPLAY_SPEED_MAX);
+        }
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            if let Some(index) = (0..BUTTONS.len()).find(|i| Self::button_rect(*i).contains(mouse))
+            {
+                self.press(BUTTONS[index]);
+            } else if Self::scrubber_rect().contains(mouse) {
+                self.scrubbing = true;
+                self.playing = false;
+            }
+        }
+        if !is_mouse_button_down(MouseButton::Left) {
+            self.scrubbing = false;
+        }
+        if self.scrubbing {
+            let scrubber = Self::scrubber_rect();
+            self.seek(position_at(
+                (mouse.x - scrubber.x) / scrubber.w,
+                self.replay.len(),
+            ));
+        }
+
+        if self.playing {
+            self.pending += dt * self.speed;
+            let steps = self.pending as usize;
+            self.pending -= steps as f32;
+            self.seek(self.position + steps);
+            if self.position == self.replay.len() {
+                self.playing = false;
+            }
+        }
+    }
+
+    pub(crate) fn draw(&mut self) {
+        clear_background(BLACK);
+        self.camera.apply();
+        let visible = self.camera.visible_world_rect();
+        let mut batch = QuadBatch::new();
+        for (_, tiles) in self.state.layers(self.state.map(), self.state.level()) {
+            for (&(x, y), &tile) in tiles {
+                let dest = Rect::new(
+                    x as f32 * TILE_SIZE,
+                    y as f32 * TILE_SIZE,
+                    TILE_SIZE,
+                    TILE_SIZE,
+                );
+                if dest.overlaps(&visible) {
+                    batch.push(&self.tileset, dest, self.atlas.source(tile as usize), WHITE);
+                }
+            }
+        }
+        batch.flush();
+
+        let shown =
+            |map: &MapId, level: i32| *map == *self.state.map() && level == self.state.level();
+        let size = TILE_SIZE * VEHICLE_SIZE;
+        for (_, location) in self
+            .state
+            .vehicles()
+            .filter(|(_, location)| shown(&location.map, location.z))
+        {
+            let x = (location.x as f32 + 0.5) * TILE_SIZE - size / 2.0;
+            let y = (location.y as f32 + 0.5) * TILE_SIZE - size / 2.0;
+            draw_rectangle(x, y, size, size, VEHICLE_COLOR);
+            draw_rectangle_lines(x, y, size, size, 2.0, VEHICLE_OUTLINE_COLOR);
+        }
+        for person in self
+            .state
+            .persons()
+            .filter(|person| shown(&person.location.map, person.location.z))
+        {
+            let center = Vec2::new(
+                (person.location.x as f32 + 0.5) * TILE_SIZE,
+                (person.location.y as f32 + 0.5) * TILE_SIZE,
+            );
+            if !visible.contains(center) {
+                continue;
+            }
+            draw_circle(center.x, center.y, TILE_SIZE * 0.3, REPLAY_PERSON_COLOR);
+            draw_text(
+                &person.name,
+                center.x - TILE_SIZE / 2.0,
+                center.y - TILE_SIZE / 2.0,
+                14.0,
+                WHITE,
+            );
+        }
+
+        set_default_camera();
+        self.draw_timeline();
+    }
+
+    fn draw_timeline(&mut self) {
+        let timeline = Self::timeline_rect();
+        draw_rectangle(
+            timeline.x,
+            timeline.y,
+            timeline.w,
+            timeline.h,
+            TEXT_BACKGROUND_COLOR,
+        );
+        let event = match self
+            .position
+            .checked_sub(1)
+            .and_then(|i| self.replay.event(i))
+        {
+            Some(event) => format!("{:?}", event)
+                .chars()
+                .take(REPLAY_EVENT_TEXT_LENGTH)
+                .collect(),
+            None => "Start of the run".to_string(),
+        };
+        let torn = if self.replay.is_torn() {
+            ", torn at the end"
+        } else {
+            ""
+        };
+        draw_text(
+            &format!(
+                "Event {}/{}{} at {}/s on {} level {}",
+                self.position,
+                self.replay.len(),
+                torn,
+                self.speed,
+                self.state.map(),
+                self.state.level()
+            ),
+            timeline.x + 10.0,
+            timeline.y + 20.0,
+            TEXT_FONT_SIZE,
+            WHITE,
+        );
+        draw_text(&event, timeline.x + 10.0, timeline.y + 40.0, 16.0, GRAY);
+
+        for (i, control) in BUTTONS.iter().enumerate() {
+            let rect = Self::button_rect(i);
+            let (label, color) = match control {
+                Control::Start => ("|<", BUTTON_COLOR),
+                Control::StepBack => ("<", BUTTON_COLOR),
+                Control::PlayPause if self.playing => ("Pause", BUTTON_ACTIVE_COLOR),
+                Control::PlayPause => ("Play", BUTTON_COLOR),
+                Control::Step => (">", BUTTON_COLOR),
+                Control::End => (">|", BUTTON_COLOR),
+            };
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, color);
+            draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 1.0, GRAY);
+            let width = measure_text(label, None, TEXT_FONT_SIZE as u16, 1.0).width;
+            draw_text(
+                label,
+                rect.center().x - width / 2.0,
+                rect.y + (BUTTON_HEIGHT + TEXT_FONT_SIZE) / 2.0 - 4.0,
+                TEXT_FONT_SIZE,
+                WHITE,
+            );
+        }
+
+        // Event density, a column every two pixels scaled to the busiest one
+        let scrubber = Self::scrubber_rect();
+        draw_rectangle(
+            scrubber.x,
+            scrubber.y,
+            scrubber.w,
+            scrubber.h,
+            CHART_BACKGROUND_COLOR,
+        );
+        let columns = (scrubber.w / 2.0).max(1.0) as usize;
+        if self.density.0 != columns {
+            self.density = (columns, self.replay.density(columns));
+        }
+        let busiest = self.density.1.iter().copied().max().unwrap_or(0).max(1);
+        let column_width = scrubber.w / columns as f32;
+        for (i, &count) in self.density.1.iter().enumerate() {
+            let height = scrubber.h * count as f32 / busiest as f32;
+            draw_rectangle(
+                scrubber.x + i as f32 * column_width,
+                scrubber.bottom() - height,
+                column_width,
+                height,
+                REPLAY_DENSITY_COLOR,
+            );
+        }
+        let fraction = if self.replay.is_empty() {
+            0.0
+        } else {
+            self.position as f32 / self.replay.len() as f32
+        };
+        let x = scrubber.x + fraction * scrubber.w;
+        draw_line(
+            x,
+            scrubber.y - 4.0,
+            x,
+            scrubber.bottom() + 4.0,
+            2.0,
+            REPLAY_CURSOR_COLOR,
+        );
+    }
+}
+
+// Events replayed at a fraction of the way along the timeline, rounded to the closest
+fn position_at(fraction: f32, len: usize) -> usize {
+    (fraction.clamp(0.0, 1.0) * len as f32).round() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_the_timeline_covers_the_whole_run() {
+        assert_eq!(position_at(-0.5, 200), 0);
+        assert_eq!(position_at(0.5, 200), 100);
+        assert_eq!(position_at(0.999, 200), 200);
+        assert_eq!(position_at(3.0, 200), 200);
+        assert_eq!(position_at(0.5, 0), 0);
+    }
+}
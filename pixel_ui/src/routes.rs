@@ -0,0 +1,289 @@
+//! Named patrol routes on the map, edited with the route tool and saved with the map
+
+use crate::config::{
+    ROUTE_ACTIVE_COLOR, ROUTE_COLOR, ROUTE_LINE_WIDTH, ROUTE_WAYPOINT_SIZE, TILE_SIZE,
+};
+use crate::{TileMap, TilePosition};
+use lua_engine::lua_engine::LuaEngine;
+use lua_engine::{LuaResult, Table};
+use macroquad::prelude::*;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Waypoints of every route by its name, a patrol walks them in order and back to the first
+pub type Routes = BTreeMap<String, Vec<TilePosition>>;
+
+// A waypoint held with the mouse, a click that doesn't move it removes it
+struct Drag {
+    route: String,
+    index: usize,
+    moved: bool,
+}
+
+/// State of the route tool: the route new waypoints go to and the waypoint being dragged
+#[derive(Default)]
+pub struct RouteEditor {
+    active: Option<String>,
+    drag: Option<Drag>,
+}
+
+impl RouteEditor {
+    /// Name of the route the next waypoint goes to, none until one is clicked or started
+    pub(crate) fn active(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    /// Have the next waypoint start a route of its own
+    pub(crate) fn start_new_route(&mut self) {
+        self.active = None;
+        self.drag = None;
+    }
+
+    /// Pressing on a waypoint grabs it, its route becomes the active one, elsewhere a waypoint is
+    /// added to the active route. A grabbed waypoint follows the mouse while it's down and is
+    /// removed if it was let go of without moving.
+    pub(crate) fn update(
+        &mut self,
+        routes: &mut Routes,
+        hover: TilePosition,
+        pressed: bool,
+        down: bool,
+    ) {
+        // A route removed by a script can't stay active
+        if self
+            .active
+            .as_ref()
+            .is_some_and(|name| !routes.contains_key(name))
+        {
+            self.start_new_route();
+        }
+
+        if pressed {
+            // Waypoints of the active route are on top of those of other routes
+            let grabbed = routes
+                .iter()
+                .filter(|(name, _)| Some(name.as_str()) == self.active())
+                .chain(routes.iter())
+                .find_map(|(name, waypoints)| {
+                    let index = waypoints.iter().position(|pos| *pos == hover)?;
+                    Some((name.clone(), index))
+                });
+            match grabbed {
+                Some((route, index)) => {
+                    self.active = Some(route.clone());
+                    self.drag = Some(Drag {
+                        route,
+                        index,
+                        moved: false,
+                    });
+                }
+                None => {
+                    let route = self.active.clone().unwrap_or_else(|| unused_name(routes));
+                    routes.entry(route.clone()).or_default().push(hover);
+                    self.active = Some(route);
+                }
+            }
+            return;
+        }
+
+        let Some(drag) = &mut self.drag else {
+            return;
+        };
+        let Some(waypoints) = routes.get_mut(&drag.route) else {
+            self.drag = None;
+            return;
+        };
+        if down {
+            if let Some(waypoint) = waypoints.get_mut(drag.index)
+                && *waypoint != hover
+            {
+                *waypoint = hover;
+                drag.moved = true;
+            }
+            return;
+        }
+        if !drag.moved && drag.index < waypoints.len() {
+            waypoints.remove(drag.index);
+            if waypoints.is_empty() {
+                routes.remove(&drag.route);
+                self.active = None;
+            }
+        }
+        self.drag = None;
+    }
+}
+
+// "route 1", "route 2" and so on, the first one not taken
+fn unused_name(routes: &Routes) -> String {
+    (1..)
+        .map(|i| format!("route {}", i))
+        .find(|name| !routes.contains_key(name))
+        .unwrap()
+}
+
+/// The routes as closed lines through the centers of their waypoints, the active one highlighted
+/// and labeled at its first waypoint. Returns the number of routes drawn.
+pub(crate) fn draw_routes(routes: &Routes, active: Option<&str>, visible: Rect) -> usize {
+    let mut drawn = 0;
+    for (name, waypoints) in routes {
+        let centers: Vec<Vec2> = waypoints.iter().map(|pos| pos.center_world_pos()).collect();
+        let Some(first) = centers.first() else {
+            continue;
+        };
+        let (min, max) = centers.iter().fold((*first, *first), |(min, max), center| {
+            (min.min(*center), max.max(*center))
+        });
+        let bounds = Rect::new(
+            min.x - TILE_SIZE,
+            min.y - TILE_SIZE,
+            max.x - min.x + TILE_SIZE * 2.0,
+            max.y - min.y + TILE_SIZE * 2.0,
+        );
+        if !bounds.overlaps(&visible) {
+            continue;
+        }
+
+        let is_active = Some(name.as_str()) == active;
+        let color = if is_active {
+            ROUTE_ACTIVE_COLOR
+        } else {
+            ROUTE_COLOR
+        };
+        for (i, from) in centers.iter().enumerate() {
+            let to = centers[(i + 1) % centers.len()];
+            // The way back to the first waypoint is drawn thinner
+            let width = if i + 1 == centers.len() {
+                ROUTE_LINE_WIDTH / 2.0
+            } else {
+                ROUTE_LINE_WIDTH
+            };
+            draw_line(from.x, from.y, to.x, to.y, width, color);
+        }
+        let size = TILE_SIZE * ROUTE_WAYPOINT_SIZE;
+        for center in &centers {
+            draw_rectangle(
+                center.x - size / 2.0,
+                center.y - size / 2.0,
+                size,
+                size,
+                color,
+            );
+        }
+        if is_active {
+            draw_text(name, centers[0].x + size, centers[0].y - size, 16.0, WHITE);
+        }
+        drawn += 1;
+    }
+    drawn
+}
+
+/// Set up the `routes` global on the routes of the map shown, a route is handed to the task
+/// system as it is:
+///
+/// ```lua
+/// api.task.patrol(guard.id, routes.get("route 1"))
+/// ```
+pub(crate) fn install(lua_engine: &Arc<Mutex<LuaEngine>>, map: Arc<Mutex<TileMap>>) {
+    let lua = &lua_engine.lock().unwrap().lua;
+    let table = lua.create_table().unwrap();
+    {
+        let map = map.clone();
+        lua.create_function(move |_, ()| {
+            Ok(map
+                .lock()
+                .unwrap()
+                .routes
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>())
+        })
+        .and_then(|f| table.set("names", f))
+        .unwrap();
+    }
+    {
+        let map = map.clone();
+        // The waypoints as a list of { x = ..., y = ... }, nil if there is no such route
+        lua.create_function(move |lua, name: String| {
+            let map = map.lock().unwrap();
+            let Some(waypoints) = map.routes.get(&name) else {
+                return Ok(None);
+            };
+            let result = lua.create_table()?;
+            for pos in waypoints {
+                let waypoint = lua.create_table()?;
+                waypoint.set("x", pos.x)?;
+                waypoint.set("y", pos.y)?;
+                result.push(waypoint)?;
+            }
+            Ok(Some(result))
+        })
+        .and_then(|f| table.set("get", f))
+        .unwrap();
+    }
+    {
+        let map = map.clone();
+        // Replaces the waypoints of a route or adds it, no waypoints remove it
+        lua.create_function(move |_, (name, waypoints): (String, Vec<Table>)| {
+            let waypoints = waypoints
+                .iter()
+                .map(|waypoint| Ok(TilePosition::new(waypoint.get("x")?, waypoint.get("y")?)))
+                .collect::<LuaResult<Vec<_>>>()?;
+            let mut map = map.lock().unwrap();
+            if waypoints.is_empty() {
+                map.routes.remove(&name);
+            } else {
+                map.routes.insert(name, waypoints);
+            }
+            Ok(())
+        })
+        .and_then(|f| table.set("set", f))
+        .unwrap();
+    }
+    {
+        // Returns false if there is no such route
+        lua.create_function(move |_, name: String| {
+            Ok(map.lock().unwrap().routes.remove(&name).is_some())
+        })
+        .and_then(|f| table.set("remove", f))
+        .unwrap();
+    }
+    lua.globals().set("routes", table).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waypoints_are_added_moved_and_removed() {
+        let mut routes = Routes::new();
+        let mut editor = RouteEditor::default();
+        let at = TilePosition::new;
+        let mut click = |routes: &mut Routes, pos| {
+            editor.update(routes, pos, true, true);
+            editor.update(routes, pos, false, false);
+        };
+        click(&mut routes, at(0, 0));
+        click(&mut routes, at(3, 0));
+        assert_eq!(routes["route 1"], vec![at(0, 0), at(3, 0)]);
+
+        // Dragging a waypoint moves it, clicking it removes it
+        editor.update(&mut routes, at(3, 0), true, true);
+        editor.update(&mut routes, at(3, 2), false, true);
+        editor.update(&mut routes, at(3, 2), false, false);
+        assert_eq!(routes["route 1"], vec![at(0, 0), at(3, 2)]);
+        editor.update(&mut routes, at(0, 0), true, true);
+        editor.update(&mut routes, at(0, 0), false, false);
+        assert_eq!(routes["route 1"], vec![at(3, 2)]);
+
+        // A new route takes the next free name, the last waypoint removed takes the route along
+        editor.start_new_route();
+        editor.update(&mut routes, at(5, 5), true, true);
+        editor.update(&mut routes, at(5, 5), false, false);
+        assert_eq!(editor.active(), Some("route 2"));
+        editor.update(&mut routes, at(3, 2), true, true);
+        editor.update(&mut routes, at(3, 2), false, false);
+        assert_eq!(routes.keys().collect::<Vec<_>>(), vec!["route 2"]);
+        assert_eq!(editor.active(), None);
+    }
+}
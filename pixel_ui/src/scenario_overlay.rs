@@ -0,0 +1,134 @@
+use crate::config::{
+    BUTTON_COLOR, BUTTON_HEIGHT, BUTTON_PADDING, CONSOLE_LINE_HEIGHT, TEXT_BACKGROUND_COLOR,
+    TEXT_FONT_SIZE,
+};
+use lua_engine::scenario::Scenario;
+use macroquad::prelude::*;
+
+const PANEL_WIDTH: f32 = 420.0;
+
+/// Result of the scenario in the middle of the screen once it ended, until it's closed
+pub struct ScenarioOverlay {
+    scenario: Scenario,
+    closed: bool,
+}
+
+impl ScenarioOverlay {
+    pub(crate) fn new(scenario: Scenario) -> Self {
+        Self {
+            scenario,
+            closed: false,
+        }
+    }
+
+    // Title colored by the outcome, then the summary and the metrics
+    fn lines(&self) -> Vec<(String, Color)> {
+        let Some(result) = self.scenario.result() else {
+            return Vec::new();
+        };
+        let color = match result.outcome.as_str() {
+            "won" => GREEN,
+            "lost" => RED,
+            _ => WHITE,
+        };
+        let mut lines = vec![
+            (format!("Scenario {}", result.outcome), color),
+            (result.reason.clone(), WHITE),
+            (
+                format!("{} ticks, {:.1}s", result.ticks, result.duration),
+                GRAY,
+            ),
+            (
+                format!(
+                    "{} events, {} persons on {} locations",
+                    result.events, result.persons, result.occupied_locations
+                ),
+                GRAY,
+            ),
+        ];
+        for (metric, value) in &result.metrics {
+            lines.push((format!("{} = {}", metric, value), GRAY));
+        }
+        lines
+    }
+
+    fn panel_rect(&self, line_count: usize) -> Rect {
+        let height = line_count as f32 * CONSOLE_LINE_HEIGHT + BUTTON_HEIGHT + 30.0;
+        Rect::new(
+            (screen_width() - PANEL_WIDTH) / 2.0,
+            (screen_height() - height) / 2.0,
+            PANEL_WIDTH,
+            height,
+        )
+    }
+
+    fn close_button_rect(&self, panel: Rect) -> Rect {
+        Rect::new(
+            panel.right() - 80.0,
+            panel.bottom() - BUTTON_HEIGHT - 10.0,
+            70.0,
+            BUTTON_HEIGHT,
+        )
+    }
+
+    fn is_shown(&self) -> bool {
+        !self.closed && self.scenario.result().is_some()
+    }
+
+    /// Title and reason of the result while it is shown
+    pub(crate) fn describe(&self) -> Option<String> {
+        if !self.is_shown() {
+            return None;
+        }
+        let lines = self.lines();
+        Some(format!("{}: {}", lines[0].0, lines[1].0))
+    }
+
+    pub(crate) fn captures_mouse(&self, screen_pos: Vec2) -> bool {
+        self.is_shown() && self.panel_rect(self.lines().len()).contains(screen_pos)
+    }
+
+    pub(crate) fn update(&mut self) {
+        if !self.is_shown() || !is_mouse_button_pressed(MouseButton::Left) {
+            return;
+        }
+        let panel = self.panel_rect(self.lines().len());
+        if self
+            .close_button_rect(panel)
+            .contains(Vec2::from(mouse_position()))
+        {
+            self.closed = true;
+        }
+    }
+
+    pub(crate) fn draw(&self) {
+        if !self.is_shown() {
+            return;
+        }
+
+        let lines = self.lines();
+        let panel = self.panel_rect(lines.len());
+        draw_rectangle(panel.x, panel.y, panel.w, panel.h, TEXT_BACKGROUND_COLOR);
+        draw_rectangle_lines(panel.x, panel.y, panel.w, panel.h, 1.0, GRAY);
+        for (i, (line, color)) in lines.iter().enumerate() {
+            draw_text(
+                line,
+                panel.x + 10.0,
+                panel.y + 10.0 + (i + 1) as f32 * CONSOLE_LINE_HEIGHT,
+                TEXT_FONT_SIZE,
+                *color,
+            );
+        }
+
+        let close = self.close_button_rect(panel);
+        draw_rectangle(close.x, close.y, close.w, close.h, BUTTON_COLOR);
+        draw_rectangle_lines(close.x, close.y, close.w, close.h, 1.0, GRAY);
+        draw_text(
+            "Close",
+            close.x + BUTTON_PADDING,
+            close.y + (BUTTON_HEIGHT + TEXT_FONT_SIZE) / 2.0 - 4.0,
+            TEXT_FONT_SIZE,
+            WHITE,
+        );
+    }
+}
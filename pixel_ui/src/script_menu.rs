@@ -0,0 +1,256 @@
+use crate::config::{CONSOLE_LINE_HEIGHT, SCRIPT_MENU_WIDTH};
+use macroquad::prelude::*;
+use std::fs;
+use std::path::Path;
+
+const DIGIT_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+struct ScriptEntry {
+    path: String,
+    recent: bool,
+}
+
+/// Quick-run menu of the console, opened with Ctrl+O. Lists the scripts run recently and the
+/// ones under the scripts directory. A click or a number key runs one, with Ctrl held it's
+/// marked instead and Enter runs the marked ones in the order they were marked.
+#[derive(Default)]
+pub struct ScriptMenu {
+    open: bool,
+    entries: Vec<ScriptEntry>,
+    // Indices of the marked entries, in the order they were marked
+    marked: Vec<usize>,
+}
+
+impl ScriptMenu {
+    pub(crate) fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub(crate) fn open(&mut self, dir: &str, recent: &[String]) {
+        self.entries = list_scripts(Path::new(dir), recent);
+        self.marked.clear();
+        self.open = true;
+    }
+
+    pub(crate) fn close(&mut self) {
+        self.open = false;
+    }
+
+    // Mark an entry or take the mark away again
+    fn toggle_mark(&mut self, index: usize) {
+        if index >= self.entries.len() {
+            return;
+        }
+        match self.marked.iter().position(|marked| *marked == index) {
+            Some(position) => {
+                self.marked.remove(position);
+            }
+            None => self.marked.push(index),
+        }
+    }
+
+    // Paths of the marked entries, or of the entry picked when nothing is marked
+    fn chosen(&self, picked: Option<usize>) -> Vec<String> {
+        let indices = if self.marked.is_empty() {
+            picked.into_iter().collect()
+        } else {
+            self.marked.clone()
+        };
+        indices
+            .into_iter()
+            .filter_map(|index| self.entries.get(index))
+            .map(|entry| entry.path.clone())
+            .collect()
+    }
+
+    fn rect(&self, bottom: f32) -> Rect {
+        let height = self.entries.len().max(1) as f32 * CONSOLE_LINE_HEIGHT + CONSOLE_LINE_HEIGHT;
+        Rect::new(10.0, bottom - height, SCRIPT_MENU_WIDTH, height)
+    }
+
+    // Entry under the screen position, the first row is the title
+    fn entry_at(&self, bottom: f32, pos: Vec2) -> Option<usize> {
+        let rect = self.rect(bottom);
+        if !rect.contains(pos) {
+            return None;
+        }
+        let row = ((pos.y - rect.y) / CONSOLE_LINE_HEIGHT) as usize;
+        (1..=self.entries.len()).contains(&row).then(|| row - 1)
+    }
+
+    /// Handle the keys and clicks while open, with the menu ending at `bottom` on the screen.
+    /// Returns the scripts to run, the menu closes once it returns any.
+    pub(crate) fn update(&mut self, bottom: f32) -> Vec<String> {
+        // The keys pressed in the menu aren't typed into the editor later on
+        while get_char_pressed().is_some() {}
+        if is_key_pressed(KeyCode::Escape) {
+            self.close();
+            return Vec::new();
+        }
+        let ctrl_down = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        let mut picked = DIGIT_KEYS.iter().position(|key| is_key_pressed(*key));
+        if is_mouse_button_pressed(MouseButton::Left) {
+            let pos = Vec2::from(mouse_position());
+            match self.entry_at(bottom, pos) {
+                Some(index) => picked = Some(index),
+                None if !self.rect(bottom).contains(pos) => self.close(),
+                None => {}
+            }
+        }
+        let scripts = match picked {
+            Some(index) if ctrl_down => {
+                self.toggle_mark(index);
+                Vec::new()
+            }
+            Some(index) if index < self.entries.len() => self.chosen(Some(index)),
+            _ if is_key_pressed(KeyCode::Enter) => self.chosen(None),
+            _ => Vec::new(),
+        };
+        if !scripts.is_empty() {
+            self.close();
+        }
+        scripts
+    }
+
+    pub(crate) fn draw(&self, bottom: f32) {
+        if !self.open {
+            return;
+        }
+        let rect = self.rect(bottom);
+        draw_rectangle(
+            rect.x,
+            rect.y,
+            rect.w,
+            rect.h,
+            Color::new(0.0, 0.0, 0.0, 0.9),
+        );
+        draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 1.0, GRAY);
+        let title = if self.entries.is_empty() {
+            "No scripts found"
+        } else {
+            "Run a script: click or 1-9, Ctrl to mark several, Enter runs the marked"
+        };
+        draw_text(
+            title,
+            rect.x + 8.0,
+            rect.y + CONSOLE_LINE_HEIGHT - 5.0,
+            18.0,
+            GRAY,
+        );
+        let hovered = self.entry_at(bottom, Vec2::from(mouse_position()));
+        for (i, entry) in self.entries.iter().enumerate() {
+            let y = rect.y + (i as f32 + 2.0) * CONSOLE_LINE_HEIGHT - 5.0;
+            let number = if i < DIGIT_KEYS.len() {
+                format!("{}", i + 1)
+            } else {
+                " ".to_string()
+            };
+            let mark = match self.marked.iter().position(|marked| *marked == i) {
+                Some(order) => format!("[{}]", order + 1),
+                None => "[ ]".to_string(),
+            };
+            let line = format!(
+                "{} {} {}{}",
+                number,
+                mark,
+                entry.path,
+                if entry.recent { "  (recent)" } else { "" }
+            );
+            let color = if hovered == Some(i) { YELLOW } else { WHITE };
+            draw_text(&line, rect.x + 8.0, y, 20.0, color);
+        }
+    }
+}
+
+/// The recent scripts still around, most recent first, then the other `.lua` files under `dir`
+/// sorted by path. Type definitions (`.d.lua`) aren't meant to be run and are left out.
+fn list_scripts(dir: &Path, recent: &[String]) -> Vec<ScriptEntry> {
+    let mut found = Vec::new();
+    collect_scripts(dir, &mut found);
+    found.sort();
+    recent
+        .iter()
+        .filter(|path| Path::new(path).is_file())
+        .map(|path| ScriptEntry {
+            path: path.clone(),
+            recent: true,
+        })
+        .chain(
+            found
+                .into_iter()
+                .filter(|path| !recent.contains(path))
+                .map(|path| ScriptEntry {
+                    path,
+                    recent: false,
+                }),
+        )
+        .collect()
+}
+
+fn collect_scripts(dir: &Path, found: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if path.is_dir() {
+            collect_scripts(&path, found);
+        } else if name.ends_with(".lua") && !name.ends_with(".d.lua") {
+            found.push(path.to_string_lossy().replace('\\', "/"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_scripts_come_first_and_marks_keep_their_order() {
+        let dir = std::env::temp_dir().join(format!("sb5s_script_menu_{}", std::process::id()));
+        fs::create_dir_all(dir.join("ui")).unwrap();
+        for name in ["b.lua", "a.lua", "api.d.lua", "notes.txt", "ui/hud.lua"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let path = |name: &str| dir.join(name).to_string_lossy().replace('\\', "/");
+        let recent = vec![path("b.lua"), path("gone.lua")];
+
+        let mut menu = ScriptMenu::default();
+        menu.open(dir.to_str().unwrap(), &recent);
+        fs::remove_dir_all(&dir).unwrap();
+        let listed: Vec<(String, bool)> = menu
+            .entries
+            .iter()
+            .map(|entry| (entry.path.clone(), entry.recent))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                (path("b.lua"), true),
+                (path("a.lua"), false),
+                (path("ui/hud.lua"), false),
+            ]
+        );
+
+        assert_eq!(menu.chosen(Some(1)), vec![path("a.lua")]);
+        menu.toggle_mark(2);
+        menu.toggle_mark(0);
+        menu.toggle_mark(1);
+        menu.toggle_mark(1);
+        assert_eq!(
+            menu.chosen(Some(1)),
+            vec![path("ui/hud.lua"), path("b.lua")]
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use crate::people::PersonId;
+use crate::TilePosition;
+use lua_engine::lua_engine::LuaEngine;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct SelectionState {
+    tiles: Vec<TilePosition>,
+    people: Vec<PersonId>,
+}
+
+/// Selected tiles and people, the one place tools, inspector panels and Lua (`selection`) look at
+#[derive(Clone, Default)]
+pub struct Selection {
+    state: Arc<Mutex<SelectionState>>,
+}
+
+impl Selection {
+    pub(crate) fn new(lua_engine: &Arc<Mutex<LuaEngine>>) -> Self {
+        let selection = Self::default();
+
+        let lua = &lua_engine.lock().unwrap().lua;
+        let table = lua.create_table().unwrap();
+        {
+            let selection = selection.clone();
+            lua.create_function(move |lua, ()| {
+                let state = selection.state.lock().unwrap();
+                let tiles = lua.create_table()?;
+                for tile in &state.tiles {
+                    let position = lua.create_table()?;
+                    position.set("x", tile.x)?;
+                    position.set("y", tile.y)?;
+                    tiles.push(position)?;
+                }
+                let result = lua.create_table()?;
+                result.set("tiles", tiles)?;
+                let people: Vec<u32> = state.people.iter().map(|id| id.0).collect();
+                result.set("people", people)?;
+                Ok(result)
+            })
+            .and_then(|f| table.set("get", f))
+            .unwrap();
+        }
+        {
+            let selection = selection.clone();
+            // set_tile() without a position deselects the tiles
+            lua.create_function(move |_, (x, y): (Option<i32>, Option<i32>)| {
+                match x.zip(y) {
+                    Some((x, y)) => selection.select_tile(TilePosition::new(x, y)),
+                    None => selection.state.lock().unwrap().tiles.clear(),
+                }
+                Ok(())
+            })
+            .and_then(|f| table.set("set_tile", f))
+            .unwrap();
+        }
+        {
+            let selection = selection.clone();
+            lua.create_function(move |_, (x, y): (i32, i32)| {
+                selection.add_tile(TilePosition::new(x, y));
+                Ok(())
+            })
+            .and_then(|f| table.set("add_tile", f))
+            .unwrap();
+        }
+        {
+            let selection = selection.clone();
+            lua.create_function(move |_, person: Option<u32>| {
+                selection.select_person(person.map(PersonId));
+                Ok(())
+            })
+            .and_then(|f| table.set("set_person", f))
+            .unwrap();
+        }
+        {
+            let selection = selection.clone();
+            lua.create_function(move |_, person: u32| {
+                let person = PersonId(person);
+                let mut state = selection.state.lock().unwrap();
+                if !state.people.contains(&person) {
+                    state.people.push(person);
+                }
+                Ok(())
+            })
+            .and_then(|f| table.set("add_person", f))
+            .unwrap();
+        }
+        {
+            let selection = selection.clone();
+            lua.create_function(move |_, ()| {
+                selection.clear();
+                Ok(())
+            })
+            .and_then(|f| table.set("clear", f))
+            .unwrap();
+        }
+        lua.globals().set("selection", table).unwrap();
+
+        selection
+    }
+
+    /// The first selected tile, the one tools like the tile preview work on
+    pub(crate) fn tile(&self) -> Option<TilePosition> {
+        self.state.lock().unwrap().tiles.first().copied()
+    }
+
+    pub(crate) fn tiles(&self) -> Vec<TilePosition> {
+        self.state.lock().unwrap().tiles.clone()
+    }
+
+    /// Replace the selected tiles with `tile`
+    pub(crate) fn select_tile(&self, tile: TilePosition) {
+        self.state.lock().unwrap().tiles = vec![tile];
+    }
+
+    pub(crate) fn add_tile(&self, tile: TilePosition) {
+        let mut state = self.state.lock().unwrap();
+        if !state.tiles.contains(&tile) {
+            state.tiles.push(tile);
+        }
+    }
+
+    pub(crate) fn people(&self) -> Vec<PersonId> {
+        self.state.lock().unwrap().people.clone()
+    }
+
+    /// Replace the selected people with `person`, or deselect them all
+    pub(crate) fn select_person(&self, person: Option<PersonId>) {
+        self.state.lock().unwrap().people = person.into_iter().collect();
+    }
+
+    pub(crate) fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.tiles.clear();
+        state.people.clear();
+    }
+}
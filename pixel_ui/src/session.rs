@@ -0,0 +1,113 @@
+use crate::config::{SESSION_MIN_WINDOW_HEIGHT, SESSION_MIN_WINDOW_WIDTH, ZOOM_MAX, ZOOM_MIN};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Size of the window, and where it was on the screen where miniquad can tell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub width: u32,
+    pub height: u32,
+    pub position: Option<(u32, u32)>,
+}
+
+/// Where the camera looked at, only restored on the map it was saved for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraPlacement {
+    /// File of the map, tells the maps and their levels apart
+    pub map: String,
+    pub x: f32,
+    pub y: f32,
+    pub zoom: f32,
+}
+
+/// What the last session left on screen, saved on exit and restored on startup
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub window: Option<WindowGeometry>,
+    pub camera: Option<CameraPlacement>,
+    /// Names of the open panels, None keeps the panels as they start out
+    pub panels: Option<Vec<String>>,
+}
+
+impl Session {
+    /// Missing or broken files give an empty session
+    pub fn load(path: &str) -> Self {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        let mut session: Self = serde_json::from_str(&content).unwrap_or_else(|e| {
+            println!("Failed to parse {}: {}", path, e);
+            Self::default()
+        });
+        if let Some(window) = &mut session.window {
+            window.width = window.width.max(SESSION_MIN_WINDOW_WIDTH);
+            window.height = window.height.max(SESSION_MIN_WINDOW_HEIGHT);
+        }
+        // A camera somewhere no float can reach is no place to return to
+        session.camera = session
+            .camera
+            .filter(|camera| camera.x.is_finite() && camera.y.is_finite())
+            .map(|camera| CameraPlacement {
+                zoom: if camera.zoom.is_finite() {
+                    camera.zoom.clamp(ZOOM_MIN, ZOOM_MAX)
+                } else {
+                    1.0
+                },
+                ..camera
+            });
+        session
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize the session: {}", e))?;
+        fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+
+    /// Whether the panel was open, `default` if the session didn't record the panels
+    pub fn panel_open(&self, name: &str, default: bool) -> bool {
+        match &self.panels {
+            Some(panels) => panels.iter().any(|panel| panel == name),
+            None => default,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_is_restored_within_bounds() {
+        let path = std::env::temp_dir().join(format!("sb5s_session_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let session = Session {
+            window: Some(WindowGeometry {
+                width: 10,
+                height: 900,
+                position: None,
+            }),
+            camera: Some(CameraPlacement {
+                map: "maps/map.json".to_string(),
+                x: 120.0,
+                y: -40.0,
+                zoom: 100.0,
+            }),
+            panels: Some(vec!["layers".to_string()]),
+        };
+        session.save(path).unwrap();
+        let restored = Session::load(path);
+        std::fs::remove_file(path).unwrap();
+
+        let window = restored.window.unwrap();
+        assert_eq!(
+            (window.width, window.height),
+            (SESSION_MIN_WINDOW_WIDTH, 900)
+        );
+        assert_eq!(restored.camera.as_ref().unwrap().zoom, ZOOM_MAX);
+        assert!(restored.panel_open("layers", false));
+        assert!(!restored.panel_open("debug", true));
+        assert!(Session::default().panel_open("debug", true));
+    }
+}
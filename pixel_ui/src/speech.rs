@@ -0,0 +1,224 @@
+use crate::camera::CameraController;
+use crate::config::{
+    PERSON_TILE_SIZE, SPEECH_BUBBLE_MAX_WIDTH, SPEECH_DEFAULT_DURATION, SPEECH_FONT_SIZE,
+    SPEECH_GAP, SPEECH_QUEUE_LENGTH,
+};
+use crate::people::{People, PersonId};
+use lua_engine::lua_engine::LuaEngine;
+use lua_engine::{LuaError, Table};
+use macroquad::prelude::*;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+// Space between the text and the outline of a bubble
+const BUBBLE_PADDING: f32 = 6.0;
+// Height of the tail pointing down at the speaker
+const TAIL_HEIGHT: f32 = 8.0;
+
+struct Bubble {
+    text: String,
+    age: f32,
+    duration: f32,
+}
+
+// What one person says and is going to say
+#[derive(Default)]
+struct Speaker {
+    current: Option<Bubble>,
+    queued: VecDeque<Bubble>,
+    // Seconds until the next bubble may show, counted down once the current one is gone
+    pause: f32,
+}
+
+/// Speech bubbles following people around, filled by scripts through `people.say`. A person
+/// says one thing at a time, what they are told to say meanwhile waits its turn.
+#[derive(Clone, Default)]
+pub struct SpeechBubbles {
+    speakers: Arc<Mutex<HashMap<PersonId, Speaker>>>,
+}
+
+impl SpeechBubbles {
+    pub(crate) fn new(lua_engine: &Arc<Mutex<LuaEngine>>, people: Arc<Mutex<People>>) -> Self {
+        let speech = Self::default();
+
+        let lua = &lua_engine.lock().unwrap().lua;
+        let table: Table = lua.globals().get("people").unwrap();
+        {
+            let speech = speech.clone();
+            // Returns false when the person has too much to say already
+            lua.create_function(move |_, (id, text, duration): (u32, String, Option<f32>)| {
+                if people.lock().unwrap().position(PersonId(id)).is_none() {
+                    return Err(LuaError::RuntimeError(format!("There is no person {}", id)));
+                }
+                Ok(speech.say(
+                    PersonId(id),
+                    text,
+                    duration.unwrap_or(SPEECH_DEFAULT_DURATION),
+                ))
+            })
+            .and_then(|f| table.set("say", f))
+            .unwrap();
+        }
+        {
+            let speech = speech.clone();
+            lua.create_function(move |_, id: u32| {
+                speech.speakers.lock().unwrap().remove(&PersonId(id));
+                Ok(())
+            })
+            .and_then(|f| table.set("hush", f))
+            .unwrap();
+        }
+
+        speech
+    }
+
+    /// Queue a bubble, shown for `duration` seconds once the ones before it are gone. Returns
+    /// false if `SPEECH_QUEUE_LENGTH` bubbles wait already.
+    pub(crate) fn say(&self, id: PersonId, text: String, duration: f32) -> bool {
+        let mut speakers = self.speakers.lock().unwrap();
+        let speaker = speakers.entry(id).or_default();
+        if speaker.queued.len() >= SPEECH_QUEUE_LENGTH {
+            return false;
+        }
+        speaker.queued.push_back(Bubble {
+            text,
+            age: 0.0,
+            duration: duration.max(0.0),
+        });
+        true
+    }
+
+    pub(crate) fn update(&self, dt: f32) {
+        let mut speakers = self.speakers.lock().unwrap();
+        for speaker in speakers.values_mut() {
+            if let Some(bubble) = &mut speaker.current {
+                bubble.age += dt;
+                if bubble.age >= bubble.duration {
+                    speaker.current = None;
+                    speaker.pause = SPEECH_GAP;
+                }
+            } else {
+                speaker.pause -= dt;
+            }
+            if speaker.current.is_none() && speaker.pause <= 0.0 {
+                speaker.current = speaker.queued.pop_front();
+            }
+        }
+        speakers.retain(|_, speaker| speaker.current.is_some() || !speaker.queued.is_empty());
+    }
+
+    /// Draw the bubbles in screen space above the people in view, returns how many it drew
+    pub(crate) fn draw(&self, camera: &CameraController, people: &People) -> usize {
+        let screen = Rect::new(0.0, 0.0, screen_width(), screen_height());
+        let mut drawn = 0;
+        for (id, speaker) in self.speakers.lock().unwrap().iter() {
+            let (Some(bubble), Some(position)) = (&speaker.current, people.position(*id)) else {
+                continue;
+            };
+            let head = camera.world_to_screen(position - Vec2::new(0.0, PERSON_TILE_SIZE / 2.0));
+            let lines = wrap(&bubble.text, SPEECH_BUBBLE_MAX_WIDTH, |text| {
+                measure_text(text, None, SPEECH_FONT_SIZE as u16, 1.0).width
+            });
+            let width = lines
+                .iter()
+                .map(|line| measure_text(line, None, SPEECH_FONT_SIZE as u16, 1.0).width)
+                .fold(0.0, f32::max)
+                + BUBBLE_PADDING * 2.0;
+            let height = lines.len() as f32 * SPEECH_FONT_SIZE + BUBBLE_PADDING * 2.0;
+            let rect = Rect::new(
+                head.x - width / 2.0,
+                head.y - TAIL_HEIGHT - height,
+                width,
+                height,
+            );
+            if !screen.overlaps(&rect) {
+                continue;
+            }
+            let tail = [
+                Vec2::new(head.x - TAIL_HEIGHT / 2.0, rect.bottom()),
+                Vec2::new(head.x + TAIL_HEIGHT / 2.0, rect.bottom()),
+                head,
+            ];
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, WHITE);
+            draw_triangle(tail[0], tail[1], tail[2], WHITE);
+            draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 2.0, BLACK);
+            draw_line(tail[0].x, tail[0].y, tail[2].x, tail[2].y, 2.0, BLACK);
+            draw_line(tail[1].x, tail[1].y, tail[2].x, tail[2].y, 2.0, BLACK);
+            for (i, line) in lines.iter().enumerate() {
+                draw_text(
+                    line,
+                    rect.x + BUBBLE_PADDING,
+                    rect.y + BUBBLE_PADDING + (i as f32 + 0.8) * SPEECH_FONT_SIZE,
+                    SPEECH_FONT_SIZE,
+                    BLACK,
+                );
+            }
+            drawn += 1;
+        }
+        drawn
+    }
+}
+
+/// Break the text into lines at most `max_width` wide by `measure`, between words. A word wider
+/// than that gets a line of its own.
+pub(crate) fn wrap(text: &str, max_width: f32, measure: impl Fn(&str) -> f32) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && measure(&format!("{} {}", line, word)) > max_width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_is_wrapped_between_words() {
+        let measure = |text: &str| text.len() as f32;
+        assert_eq!(
+            wrap("I need to find some food soon", 12.0, measure),
+            vec!["I need to", "find some", "food soon"]
+        );
+        assert_eq!(
+            wrap("Unbelievable!\nReally", 5.0, measure),
+            vec!["Unbelievable!", "Really"]
+        );
+    }
+
+    #[test]
+    fn test_bubbles_wait_their_turn() {
+        let speech = SpeechBubbles::default();
+        let ann = PersonId(1);
+        assert!(speech.say(ann, "Hello".to_string(), 1.0));
+        for _ in 1..SPEECH_QUEUE_LENGTH {
+            assert!(speech.say(ann, "Again".to_string(), 1.0));
+        }
+        assert!(!speech.say(ann, "Too much".to_string(), 1.0));
+
+        let current = |speech: &SpeechBubbles| {
+            speech
+                .speakers
+                .lock()
+                .unwrap()
+                .get(&ann)
+                .and_then(|speaker| speaker.current.as_ref().map(|bubble| bubble.text.clone()))
+        };
+        speech.update(0.1);
+        assert_eq!(current(&speech).as_deref(), Some("Hello"));
+        speech.update(1.0);
+        assert_eq!(current(&speech), None);
+        speech.update(SPEECH_GAP);
+        assert_eq!(current(&speech).as_deref(), Some("Again"));
+    }
+}
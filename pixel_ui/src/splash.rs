@@ -0,0 +1,82 @@
+use crate::config::{BUTTON_ACTIVE_COLOR, BUTTON_COLOR};
+use lua_engine::{Projections, RebuildProgress};
+use macroquad::prelude::*;
+
+const BAR_WIDTH: f32 = 400.0;
+const BAR_HEIGHT: f32 = 16.0;
+const LINE_HEIGHT: f32 = 40.0;
+
+/// Show how far the projections got rebuilding from the stored events until all of them are
+/// ready. Returns right away unless the event log is large enough to take a while.
+pub(crate) async fn show_rebuild_progress(projections: &Projections) {
+    while !projections.ready() {
+        clear_background(BLACK);
+        let progress = projections.progress();
+        let top = (screen_height() - progress.len() as f32 * LINE_HEIGHT) / 2.0;
+        let left = (screen_width() - BAR_WIDTH) / 2.0;
+        draw_text(
+            "Loading the world",
+            left,
+            top - LINE_HEIGHT / 2.0,
+            30.0,
+            WHITE,
+        );
+        for (i, projection) in progress.iter().enumerate() {
+            let y = top + i as f32 * LINE_HEIGHT;
+            draw_text(&label(projection), left, y, 20.0, LIGHTGRAY);
+            draw_rectangle(left, y + 6.0, BAR_WIDTH, BAR_HEIGHT, BUTTON_COLOR);
+            draw_rectangle(
+                left,
+                y + 6.0,
+                BAR_WIDTH * fraction(projection),
+                BAR_HEIGHT,
+                BUTTON_ACTIVE_COLOR,
+            );
+        }
+        next_frame().await;
+    }
+}
+
+fn label(projection: &RebuildProgress) -> String {
+    if projection.ready {
+        format!("{}: ready", projection.name)
+    } else {
+        format!(
+            "Rebuilding {}: {} / {} events",
+            projection.name, projection.applied, projection.total
+        )
+    }
+}
+
+// Share of the stored events applied, live events published meanwhile don't count past full
+fn fraction(projection: &RebuildProgress) -> f32 {
+    if projection.ready || projection.total == 0 {
+        return 1.0;
+    }
+    (projection.applied as f32 / projection.total as f32).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_is_labelled_until_ready() {
+        let mut projection = RebuildProgress {
+            name: "StatsProjection".to_string(),
+            applied: 250,
+            total: 1000,
+            ready: false,
+        };
+        assert_eq!(
+            label(&projection),
+            "Rebuilding StatsProjection: 250 / 1000 events"
+        );
+        assert_eq!(fraction(&projection), 0.25);
+
+        projection.applied = 1200;
+        projection.ready = true;
+        assert_eq!(label(&projection), "StatsProjection: ready");
+        assert_eq!(fraction(&projection), 1.0);
+    }
+}
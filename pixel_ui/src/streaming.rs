@@ -0,0 +1,219 @@
+//! Streaming of the map with `--stream`, chunks far from every camera are kept on disk
+
+use crate::config::{STREAM_INTERVAL, STREAM_MARGIN, TILE_SIZE};
+use crate::layers::TileLayer;
+use crate::people::{People, StoredPerson};
+use crate::{Tile, TileMap, TilePosition};
+use lua_engine::{Chunk, Chunks, MapId, CHUNK_SIZE};
+use macroquad::prelude::Rect;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+// Tiles of a layer in a chunk file by column, row and id
+type StoredTiles = Vec<(i32, i32, usize)>;
+
+/// What a chunk left on disk: the tiles of each layer and the people standing on them
+#[derive(Default, Serialize, Deserialize)]
+struct ChunkFile {
+    layers: Vec<(String, StoredTiles)>,
+    people: Vec<StoredPerson>,
+}
+
+/// Unloads the chunks of the map shown that no camera is near and loads them back when one is.
+/// The logic unloads the persons of the chunk along with them, see `api.stream`.
+pub(crate) struct ChunkStreamer {
+    chunks: Chunks,
+    dir: PathBuf,
+    // Seconds since the last pass over the chunks
+    elapsed: f32,
+}
+
+impl ChunkStreamer {
+    pub(crate) fn new(chunks: Chunks) -> Self {
+        Self {
+            chunks,
+            dir: std::env::temp_dir().join(format!("sb5s_stream_{}", std::process::id())),
+            elapsed: 0.0,
+        }
+    }
+
+    /// Load the chunks around the areas shown and unload the others every `STREAM_INTERVAL`,
+    /// returns how many chunks were loaded or unloaded
+    pub(crate) fn update(
+        &mut self,
+        dt: f32,
+        map: &mut TileMap,
+        people: &mut People,
+        areas: &[Rect],
+    ) -> usize {
+        self.elapsed += dt;
+        if self.elapsed < STREAM_INTERVAL {
+            return 0;
+        }
+        self.elapsed = 0.0;
+
+        let wanted = wanted_chunks(areas);
+        let mut streamed = 0;
+        let returning: Vec<(i32, i32)> = map
+            .streamed_out
+            .keys()
+            .filter(|chunk| wanted.contains(chunk))
+            .copied()
+            .collect();
+        for chunk in returning {
+            self.load(chunk, map, people);
+            streamed += 1;
+        }
+
+        // Chunks the scripts unloaded come back with their persons too, the tiles never left
+        for chunk in self.chunks.unloaded_on(&map.name, map.level) {
+            let key = (chunk.x, chunk.y);
+            if wanted.contains(&key) && !map.streamed_out.contains_key(&key) {
+                if let Err(e) = self.chunks.load(&chunk) {
+                    println!("Failed to load chunk {},{}: {}", chunk.x, chunk.y, e);
+                }
+                streamed += 1;
+            }
+        }
+
+        let unwanted: BTreeSet<(i32, i32)> = map
+            .layers
+            .iter()
+            .flat_map(|layer| layer.tiles.keys())
+            .map(|&(x, y)| (x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE)))
+            .filter(|chunk| !wanted.contains(chunk))
+            .collect();
+        for chunk in unwanted {
+            if self.unload(chunk, map, people) {
+                streamed += 1;
+            }
+        }
+        streamed
+    }
+
+    /// Load every chunk back, before the map is saved or switched away from
+    pub(crate) fn load_all(&mut self, map: &mut TileMap, people: &mut People) {
+        let chunks: Vec<(i32, i32)> = map.streamed_out.keys().copied().collect();
+        for chunk in chunks {
+            self.load(chunk, map, people);
+        }
+    }
+
+    // Write the tiles and people of the chunk to its file and take them off the map, returns
+    // false when they stay because the file couldn't be written
+    fn unload(&mut self, (x, y): (i32, i32), map: &mut TileMap, people: &mut People) -> bool {
+        let (min_x, min_y) = (x * CHUNK_SIZE, y * CHUNK_SIZE);
+        let (max_x, max_y) = (min_x + CHUNK_SIZE - 1, min_y + CHUNK_SIZE - 1);
+        let inside =
+            |&(tx, ty): &(i32, i32)| (min_x..=max_x).contains(&tx) && (min_y..=max_y).contains(&ty);
+        let mut file = ChunkFile::default();
+        for layer in &mut map.layers {
+            let positions: Vec<(i32, i32)> = layer.tiles.keys().copied().filter(inside).collect();
+            let tiles = positions
+                .into_iter()
+                .filter_map(|pos| layer.tiles.remove(&pos).map(|tile| (pos.0, pos.1, tile.id)))
+                .collect();
+            file.layers.push((layer.name.clone(), tiles));
+        }
+        file.people = people.take_within(
+            TilePosition::new(min_x, min_y),
+            TilePosition::new(max_x, max_y),
+        );
+
+        let written = std::fs::create_dir_all(&self.dir)
+            .map_err(|e| e.to_string())
+            .and_then(|_| serde_json::to_string(&file).map_err(|e| e.to_string()))
+            .and_then(|json| std::fs::write(self.path((x, y)), json).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            println!("Failed to write chunk {},{}: {}", x, y, e);
+            put_back(file, map, people);
+            return false;
+        }
+
+        map.streamed_out.insert((x, y), Vec::new());
+        map.revision += 1;
+        map.batches.invalidate_all();
+        let chunk = self.chunk(map, (x, y));
+        if !self.chunks.is_unloaded(&chunk)
+            && let Err(e) = self.chunks.unload(&chunk)
+        {
+            println!("Failed to unload chunk {},{}: {}", x, y, e);
+        }
+        true
+    }
+
+    // Read the chunk back from its file, along with the edits made while it was away
+    fn load(&mut self, (x, y): (i32, i32), map: &mut TileMap, people: &mut People) {
+        let waiting = map.streamed_out.remove(&(x, y)).unwrap_or_default();
+        let path = self.path((x, y));
+        let read = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str::<ChunkFile>(&json).map_err(|e| e.to_string()));
+        match read {
+            Ok(file) => {
+                let _ = std::fs::remove_file(&path);
+                put_back(file, map, people);
+            }
+            Err(e) => println!("Failed to read chunk {},{}: {}", x, y, e),
+        }
+        for change in waiting {
+            map.apply_change(&change);
+        }
+
+        let chunk = self.chunk(map, (x, y));
+        if self.chunks.is_unloaded(&chunk)
+            && let Err(e) = self.chunks.load(&chunk)
+        {
+            println!("Failed to load chunk {},{}: {}", x, y, e);
+        }
+    }
+
+    // Chunk of the logic at the column and row on the map shown
+    fn chunk(&self, map: &TileMap, (x, y): (i32, i32)) -> Chunk {
+        Chunk::new(&MapId::new(&map.name), map.level, x, y)
+    }
+
+    // File of the chunk, only chunks of the map shown are ever out so its column and row do
+    fn path(&self, (x, y): (i32, i32)) -> PathBuf {
+        self.dir.join(format!("{}_{}.json", x, y))
+    }
+}
+
+// Put the tiles and people of a chunk file back on the map, adding the layers it lacks
+fn put_back(file: ChunkFile, map: &mut TileMap, people: &mut People) {
+    for (name, tiles) in file.layers {
+        let index = match map.layers.iter().position(|layer| layer.name == name) {
+            Some(index) => index,
+            None if tiles.is_empty() => continue,
+            None => {
+                map.layers.push(TileLayer::new(&name));
+                map.layers.len() - 1
+            }
+        };
+        for (x, y, id) in tiles {
+            map.layers[index].tiles.insert((x, y), Tile { id });
+        }
+    }
+    people.restore(file.people);
+    map.revision += 1;
+    map.batches.invalidate_all();
+}
+
+// Columns and rows of the chunks the areas cover, with `STREAM_MARGIN` chunks around them
+fn wanted_chunks(areas: &[Rect]) -> BTreeSet<(i32, i32)> {
+    let chunk_size = CHUNK_SIZE as f32 * TILE_SIZE;
+    let mut wanted = BTreeSet::new();
+    for area in areas {
+        let min_x = (area.x / chunk_size).floor() as i32 - STREAM_MARGIN;
+        let min_y = (area.y / chunk_size).floor() as i32 - STREAM_MARGIN;
+        let max_x = (area.right() / chunk_size).floor() as i32 + STREAM_MARGIN;
+        let max_y = (area.bottom() / chunk_size).floor() as i32 + STREAM_MARGIN;
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                wanted.insert((x, y));
+            }
+        }
+    }
+    wanted
+}
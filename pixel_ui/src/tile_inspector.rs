@@ -0,0 +1,194 @@
+use crate::config::{TILE_INSPECTOR_ROW_HEIGHT, TILE_INSPECTOR_WIDTH};
+use crate::hud::{Anchor, HudLayout};
+use crate::tileset::TileProperties;
+use crate::utils::yes_no;
+use crate::{TileMap, TilePosition};
+use lua_engine::Snapshots;
+use macroquad::hash;
+use macroquad::prelude::*;
+use macroquad::ui::{root_ui, widgets};
+
+// Names of the people on the tile listed before the rest is only counted
+const OCCUPANTS_LISTED: usize = 3;
+
+/// What a button of the inspector was clicked for, done by the game state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum InspectorAction {
+    /// Paint with the tile
+    CopyAsBrush(usize),
+    /// Erase the tile from the active layer
+    Clear(TilePosition),
+}
+
+// A tile of the selected position on one of the layers
+struct StackedTile {
+    layer: String,
+    tile_id: usize,
+    visible: bool,
+    active: bool,
+}
+
+/// Window next to the preview of the selected tile with what's there: the tiles of every
+/// layer, the properties of the tile on top and the people standing on it
+pub struct TileInspector {
+    snapshots: Snapshots,
+    // Whether the window was shown last frame
+    shown: bool,
+}
+
+impl TileInspector {
+    pub(crate) fn new(snapshots: Snapshots) -> Self {
+        Self {
+            snapshots,
+            shown: false,
+        }
+    }
+
+    pub(crate) fn is_shown(&self) -> bool {
+        self.shown
+    }
+
+    pub(crate) fn captures_mouse(&self, screen_pos: Vec2) -> bool {
+        self.shown && root_ui().is_mouse_over(screen_pos)
+    }
+
+    /// Inspect the tile at `pos` if there is one, next to the preview of the tile at `anchor`,
+    /// `editable` offers clearing it. Returns what a button was clicked for.
+    pub(crate) fn draw(
+        &mut self,
+        layout: &mut HudLayout,
+        anchor: Anchor,
+        pos: Option<&TilePosition>,
+        map: &TileMap,
+        editable: bool,
+    ) -> Option<InspectorAction> {
+        let pos = pos.filter(|pos| map.get_visible_tile(pos).is_some());
+        self.shown = pos.is_some();
+        let pos = *pos?;
+        let top = map.get_visible_tile(&pos)?.id;
+
+        let stack: Vec<StackedTile> = map
+            .layers
+            .iter()
+            .enumerate()
+            .rev()
+            .filter_map(|(i, layer)| {
+                layer.tiles.get(&(pos.x, pos.y)).map(|tile| StackedTile {
+                    layer: layer.name.clone(),
+                    tile_id: tile.id,
+                    visible: layer.visible,
+                    active: i == map.active_layer,
+                })
+            })
+            .collect();
+        let snapshot = self.snapshots.latest();
+        let occupants: Vec<String> = snapshot
+            .people_at(pos.x, pos.y)
+            .iter()
+            .filter_map(|&id| snapshot.person(id).map(|person| person.name.clone()))
+            .collect();
+        let lines = describe_tile(pos, top, map.manifest.properties(top), &stack, &occupants);
+        let can_clear = editable && stack.iter().any(|tile| tile.active);
+
+        let buttons = if can_clear { 2 } else { 1 };
+        let height = 40.0 + (lines.len() + buttons) as f32 * TILE_INSPECTOR_ROW_HEIGHT;
+        let rect = layout.place(anchor, Vec2::new(TILE_INSPECTOR_WIDTH, height));
+        let mut action = None;
+        widgets::Window::new(hash!(), rect.point(), rect.size())
+            .label("Selected tile")
+            .ui(&mut root_ui(), |ui| {
+                for line in &lines {
+                    ui.label(None, line);
+                }
+                if ui.button(None, "Copy as brush") {
+                    action = Some(InspectorAction::CopyAsBrush(top));
+                }
+                if can_clear && ui.button(None, "Clear from the active layer") {
+                    action = Some(InspectorAction::Clear(pos));
+                }
+            });
+        action
+    }
+}
+
+// The lines of the inspector, the layers listed from the top one down
+fn describe_tile(
+    pos: TilePosition,
+    top: usize,
+    properties: &TileProperties,
+    stack: &[StackedTile],
+    occupants: &[String],
+) -> Vec<String> {
+    let mut lines = vec![
+        format!("Tile ({}, {}): ID {}", pos.x, pos.y, top),
+        format!(
+            "Walkable: {}  Buildable: {}",
+            yes_no(properties.walkable),
+            yes_no(properties.buildable)
+        ),
+        format!("Movement cost: {:.1}", properties.movement_cost),
+        format!("Tags: {}", properties.tags.join(", ")),
+        "Layers:".to_string(),
+    ];
+    for tile in stack {
+        lines.push(format!(
+            "  {}: {}{}{}",
+            tile.layer,
+            tile.tile_id,
+            if tile.visible { "" } else { " (hidden)" },
+            if tile.active { " (editing)" } else { "" }
+        ));
+    }
+    lines.push(match occupants.len() {
+        0 => "Occupants: none".to_string(),
+        n if n <= OCCUPANTS_LISTED => format!("Occupants: {}", occupants.join(", ")),
+        n => format!(
+            "Occupants: {} and {} more",
+            occupants[..OCCUPANTS_LISTED].join(", "),
+            n - OCCUPANTS_LISTED
+        ),
+    });
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tile_is_described_with_its_layers_and_occupants() {
+        let properties = TileProperties {
+            walkable: false,
+            tags: vec!["tree".to_string()],
+            ..Default::default()
+        };
+        let stack = [
+            StackedTile {
+                layer: "Objects".to_string(),
+                tile_id: 7,
+                visible: true,
+                active: false,
+            },
+            StackedTile {
+                layer: "Ground".to_string(),
+                tile_id: 1,
+                visible: false,
+                active: true,
+            },
+        ];
+        let occupants = ["Ann", "Bob", "Cid", "Dee", "Eve"].map(String::from);
+        assert_eq!(
+            describe_tile(TilePosition::new(3, -2), 7, &properties, &stack, &occupants),
+            vec![
+                "Tile (3, -2): ID 7",
+                "Walkable: no  Buildable: yes",
+                "Movement cost: 1.0",
+                "Tags: tree",
+                "Layers:",
+                "  Objects: 7",
+                "  Ground: 1 (hidden) (editing)",
+                "Occupants: Ann, Bob, Cid and 2 more",
+            ]
+        );
+    }
+}
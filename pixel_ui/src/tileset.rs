@@ -0,0 +1,163 @@
+use crate::config::SOURCE_TILE_SIZE;
+use macroquad::math::Rect;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+
+/// Gameplay properties attached to a tile ID
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TileProperties {
+    pub walkable: bool,
+    pub buildable: bool,
+    pub movement_cost: f32,
+    pub tags: Vec<String>,
+}
+
+impl Default for TileProperties {
+    fn default() -> Self {
+        Self {
+            walkable: true,
+            buildable: true,
+            movement_cost: 1.0,
+            tags: Vec::new(),
+        }
+    }
+}
+
+// A group of tile IDs sharing the same properties
+#[derive(Deserialize)]
+struct TileGroup {
+    ids: Vec<usize>,
+    #[serde(flatten)]
+    properties: TileProperties,
+}
+
+#[derive(Deserialize, Default)]
+struct ManifestFile {
+    #[serde(default)]
+    tiles: Vec<TileGroup>,
+    /// Pixels around every tile in the texture, empty or with the edge pixels of the tile
+    /// repeated (extruded) so that sampling next to the edge doesn't pick up the neighbours
+    #[serde(default)]
+    padding: f32,
+}
+
+/// Tileset manifest holding the properties table for every tile ID
+pub struct TilesetManifest {
+    properties: HashMap<usize, TileProperties>,
+    default_properties: TileProperties,
+    /// Pixels around every tile in the texture
+    pub padding: f32,
+}
+
+impl Default for TilesetManifest {
+    // Every tile walkable and buildable, without padding
+    fn default() -> Self {
+        Self {
+            properties: HashMap::new(),
+            default_properties: TileProperties::default(),
+            padding: 0.0,
+        }
+    }
+}
+
+impl TilesetManifest {
+    /// Load the manifest from a JSON file, falling back to defaults when it is missing or invalid
+    pub fn load(path: &str) -> Self {
+        let file: ManifestFile = match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str::<ManifestFile>(&content).unwrap_or_else(|e| {
+                println!("Failed to parse tileset manifest {}: {}", path, e);
+                ManifestFile::default()
+            }),
+            Err(e) => {
+                println!("Failed to read tileset manifest {}: {}", path, e);
+                ManifestFile::default()
+            }
+        };
+
+        let mut properties = HashMap::new();
+        for group in file.tiles {
+            for id in group.ids {
+                properties.insert(id, group.properties.clone());
+            }
+        }
+
+        Self {
+            properties,
+            default_properties: TileProperties::default(),
+            padding: file.padding.max(0.0),
+        }
+    }
+
+    /// Properties of the given tile ID (defaults when the manifest does not mention it)
+    pub fn properties(&self, tile_id: usize) -> &TileProperties {
+        self.properties
+            .get(&tile_id)
+            .unwrap_or(&self.default_properties)
+    }
+}
+
+/// Where the tiles are in the tileset texture, row by row from the top left
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileAtlas {
+    columns: usize,
+    rows: usize,
+    padding: f32,
+}
+
+impl TileAtlas {
+    /// Layout of a texture of the given size with `padding` pixels around every tile
+    pub fn new(width: f32, height: f32, padding: f32) -> Self {
+        let cell = SOURCE_TILE_SIZE + 2.0 * padding;
+        Self {
+            columns: ((width / cell).floor() as usize).max(1),
+            rows: (height / cell).floor() as usize,
+            padding,
+        }
+    }
+
+    /// Number of tiles in the texture, valid tile ids are below it
+    pub fn tile_ids(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    /// Part of the texture showing the tile, without the padding around it
+    pub fn source(&self, tile_id: usize) -> Rect {
+        let cell = SOURCE_TILE_SIZE + 2.0 * self.padding;
+        Rect::new(
+            (tile_id % self.columns) as f32 * cell + self.padding,
+            (tile_id / self.columns) as f32 * cell + self.padding,
+            SOURCE_TILE_SIZE,
+            SOURCE_TILE_SIZE,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sources_skip_the_padding() {
+        let plain = TileAtlas::new(SOURCE_TILE_SIZE * 4.0, SOURCE_TILE_SIZE * 2.0, 0.0);
+        assert_eq!(plain.tile_ids(), 8);
+        assert_eq!(
+            plain.source(5),
+            Rect::new(
+                SOURCE_TILE_SIZE,
+                SOURCE_TILE_SIZE,
+                SOURCE_TILE_SIZE,
+                SOURCE_TILE_SIZE
+            )
+        );
+
+        let cell = SOURCE_TILE_SIZE + 2.0;
+        let padded = TileAtlas::new(cell * 4.0, cell * 2.0, 1.0);
+        assert_eq!(padded.tile_ids(), 8);
+        assert_eq!(
+            padded.source(5),
+            Rect::new(cell + 1.0, cell + 1.0, SOURCE_TILE_SIZE, SOURCE_TILE_SIZE)
+        );
+    }
+}
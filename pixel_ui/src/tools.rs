@@ -0,0 +1,419 @@
+//! Editor tools added by plugins next to the built-in ones of `UIState`
+
+use crate::macro_recorder::MacroRecorder;
+use crate::{TileMap, TilePosition, UIState, TILE_SIZE};
+use lua_engine::error_log::ErrorLog;
+use lua_engine::lua_engine::LuaEngine;
+use lua_engine::notifications::Notifications;
+use lua_engine::{LuaError, LuaFunction, LuaResult, Table};
+use macroquad::prelude::*;
+use std::sync::{Arc, Mutex};
+
+/// Category of the notifications about rejected edits
+const EDIT_REJECTED_CATEGORY: &str = "map";
+
+/// Where the mouse is over the world for a tool
+pub(crate) struct ToolInput {
+    pub hover: TilePosition,
+    /// The right button went down this frame
+    pub pressed: bool,
+    /// The right button is held
+    pub held: bool,
+    /// Tile of the brush
+    pub tile_id: Option<usize>,
+    /// Tiles the brush covers around the hovered one
+    pub footprint: Vec<TilePosition>,
+}
+
+/// What the tools work on
+#[derive(Clone)]
+pub(crate) struct ToolWorld {
+    pub map: Arc<Mutex<TileMap>>,
+    /// Tools placing tiles record them for the macro like the built-in ones
+    pub recorder: MacroRecorder,
+    /// Where the tools tell why edits were rejected
+    pub notifications: Notifications,
+}
+
+impl ToolWorld {
+    /// Show why an edit was rejected as a toast, unless it's shown already, like for every tile
+    /// of a stroke through a locked region
+    pub fn reject(&self, reason: &str) {
+        let shown = self
+            .notifications
+            .toasts()
+            .iter()
+            .any(|toast| toast.notification.text == reason);
+        if !shown {
+            self.notifications.post(EDIT_REJECTED_CATEGORY, reason);
+        }
+    }
+}
+
+/// An editor tool, active while picked in the toolbar
+pub(crate) trait Tool: Send {
+    /// Name for `ui.tool.set`, unique among the tools
+    fn name(&self) -> &str;
+    /// Text of the toolbar button
+    fn label(&self) -> &str;
+    fn activate(&mut self, _world: &ToolWorld) {}
+    /// Called every frame the tool is active and the mouse is over the world
+    fn handle_input(&mut self, input: &ToolInput, world: &ToolWorld);
+    /// Show what using the tool at the hovered tile would do, drawn in world space
+    fn draw_preview(&self, _input: &ToolInput) {}
+    fn deactivate(&mut self, _world: &ToolWorld) {}
+}
+
+type SharedTool = Arc<Mutex<Box<dyn Tool>>>;
+
+/// The tools added by plugins, `UIState::Plugin` is an index into them
+#[derive(Clone, Default)]
+pub(crate) struct ToolRegistry {
+    tools: Arc<Mutex<Vec<SharedTool>>>,
+}
+
+impl ToolRegistry {
+    /// Add a tool, replacing the one of the same name so reloaded scripts don't pile up. Names of
+    /// the built-in tools are taken.
+    pub(crate) fn register(&self, tool: Box<dyn Tool>) -> Result<usize, String> {
+        let name = tool.name().to_string();
+        if UIState::from_name(&name).is_some() {
+            return Err(format!("'{}' is a built-in tool", name));
+        }
+        let mut tools = self.tools.lock().unwrap();
+        let tool = Arc::new(Mutex::new(tool));
+        match tools.iter().position(|t| t.lock().unwrap().name() == name) {
+            Some(index) => {
+                tools[index] = tool;
+                Ok(index)
+            }
+            None => {
+                tools.push(tool);
+                Ok(tools.len() - 1)
+            }
+        }
+    }
+
+    /// The tool, outside of the lock so it may register others while it runs
+    pub(crate) fn get(&self, index: usize) -> Option<SharedTool> {
+        self.tools.lock().unwrap().get(index).cloned()
+    }
+
+    /// Name and label of every tool, in the order they were added
+    pub(crate) fn list(&self) -> Vec<(String, String)> {
+        self.tools
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|tool| {
+                let tool = tool.lock().unwrap();
+                (tool.name().to_string(), tool.label().to_string())
+            })
+            .collect()
+    }
+
+    /// The built-in or added tool of the name
+    pub(crate) fn state_of(&self, name: &str) -> Option<UIState> {
+        UIState::from_name(name).or_else(|| {
+            self.tools
+                .lock()
+                .unwrap()
+                .iter()
+                .position(|tool| tool.lock().unwrap().name() == name)
+                .map(UIState::Plugin)
+        })
+    }
+
+    /// Name of the tool of the state, as `ui.tool.get` returns it
+    pub(crate) fn state_name(&self, state: UIState) -> String {
+        match state {
+            UIState::Plugin(index) => self
+                .get(index)
+                .map(|tool| tool.lock().unwrap().name().to_string())
+                .unwrap_or_default(),
+            state => state.name().to_string(),
+        }
+    }
+}
+
+/// Set up `ui.tool` for picking the tool and adding others, call once the `ui` global is there.
+/// `on_input` runs while the right button is held, `on_preview` returns the tiles to outline:
+///
+/// ```lua
+/// ui.tool.register({
+///     name = "road",
+///     label = "Road",
+///     on_input = function(x, y, pressed, tile) map.place(x, y, 7) end,
+///     on_preview = function(x, y) return { { x = x, y = y } } end,
+/// })
+/// ui.tool.set("road")
+/// ```
+pub(crate) fn install(
+    lua_engine: &Arc<Mutex<LuaEngine>>,
+    tools: ToolRegistry,
+    ui_state: Arc<Mutex<UIState>>,
+) {
+    let engine = lua_engine.lock().unwrap();
+    let lua = &engine.lua;
+    let table = lua.create_table().unwrap();
+    {
+        let tools = tools.clone();
+        let ui_state = ui_state.clone();
+        lua.create_function(move |_, ()| {
+            let state = *ui_state.lock().unwrap();
+            Ok(tools.state_name(state))
+        })
+        .and_then(|f| table.set("get", f))
+        .unwrap();
+    }
+    {
+        let tools = tools.clone();
+        lua.create_function(move |_, name: String| match tools.state_of(&name) {
+            Some(state) => {
+                *ui_state.lock().unwrap() = state;
+                Ok(())
+            }
+            None => Err(LuaError::RuntimeError(format!("Unknown tool '{}'", name))),
+        })
+        .and_then(|f| table.set("set", f))
+        .unwrap();
+    }
+    {
+        let tools = tools.clone();
+        let error_log = engine.error_log.clone();
+        // Replaces the tool of the same name
+        lua.create_function(move |_, definition: Table| {
+            let tool = LuaTool::from_table(&definition, error_log.clone())?;
+            tools
+                .register(Box::new(tool))
+                .map(|_| ())
+                .map_err(LuaError::RuntimeError)
+        })
+        .and_then(|f| table.set("register", f))
+        .unwrap();
+    }
+    {
+        // Every tool as { name, label }, the built-in ones first
+        lua.create_function(move |lua, ()| {
+            let list = lua.create_table()?;
+            let built_in = UIState::ALL
+                .iter()
+                .map(|state| (state.name().to_string(), state.label().to_string()));
+            for (name, label) in built_in.chain(tools.list()) {
+                let entry = lua.create_table()?;
+                entry.set("name", name)?;
+                entry.set("label", label)?;
+                list.push(entry)?;
+            }
+            Ok(list)
+        })
+        .and_then(|f| table.set("list", f))
+        .unwrap();
+    }
+    lua.globals()
+        .get::<Table>("ui")
+        .and_then(|ui| ui.set("tool", table))
+        .unwrap();
+}
+
+/// A tool of the scripts, see `ui.tool.register`
+pub(crate) struct LuaTool {
+    name: String,
+    label: String,
+    on_activate: Option<LuaFunction>,
+    on_input: Option<LuaFunction>,
+    on_preview: Option<LuaFunction>,
+    on_deactivate: Option<LuaFunction>,
+    error_log: ErrorLog,
+}
+
+impl LuaTool {
+    pub(crate) fn from_table(table: &Table, error_log: ErrorLog) -> LuaResult<Self> {
+        let name: String = table.get("name")?;
+        Ok(Self {
+            label: table
+                .get::<Option<String>>("label")?
+                .unwrap_or(name.clone()),
+            name,
+            on_activate: table.get("on_activate")?,
+            on_input: table.get("on_input")?,
+            on_preview: table.get("on_preview")?,
+            on_deactivate: table.get("on_deactivate")?,
+            error_log,
+        })
+    }
+
+    fn call(&self, handler: &Option<LuaFunction>) {
+        if let Some(handler) = handler
+            && let Err(e) = handler.call::<()>(())
+        {
+            self.error_log.report(&format!("Tool {}", self.name), e);
+        }
+    }
+}
+
+impl Tool for LuaTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn label(&self) -> &str {
+        &self.label
+    }
+
+    fn activate(&mut self, _world: &ToolWorld) {
+        self.call(&self.on_activate);
+    }
+
+    // Only while the right button is down, scripts aren't called every frame for nothing
+    fn handle_input(&mut self, input: &ToolInput, _world: &ToolWorld) {
+        let Some(on_input) = &self.on_input else {
+            return;
+        };
+        if !input.pressed && !input.held {
+            return;
+        }
+        let args = (input.hover.x, input.hover.y, input.pressed, input.tile_id);
+        if let Err(e) = on_input.call::<()>(args) {
+            self.error_log.report(&format!("Tool {}", self.name), e);
+        }
+    }
+
+    fn draw_preview(&self, input: &ToolInput) {
+        let Some(on_preview) = &self.on_preview else {
+            return;
+        };
+        match on_preview.call::<Option<Vec<Table>>>((input.hover.x, input.hover.y)) {
+            Ok(tiles) => {
+                for tile in tiles.unwrap_or_default() {
+                    if let (Ok(x), Ok(y)) = (tile.get("x"), tile.get("y")) {
+                        outline(TilePosition::new(x, y), GOLD);
+                    }
+                }
+            }
+            Err(e) => self.error_log.report(&format!("Tool {}", self.name), e),
+        }
+    }
+
+    fn deactivate(&mut self, _world: &ToolWorld) {
+        self.call(&self.on_deactivate);
+    }
+}
+
+fn outline(tile: TilePosition, color: Color) {
+    let position = tile.to_world_pos();
+    draw_rectangle_lines(position.x, position.y, TILE_SIZE, TILE_SIZE, 2.0, color);
+}
+
+/// Copies the tiles under the brush with the first right-click and places the copy with every
+/// one after, picking the tool again takes a new copy
+#[derive(Default)]
+pub(crate) struct StampTool {
+    // Offsets from the hovered tile and the tile ids
+    stamp: Option<Vec<(i32, i32, usize)>>,
+}
+
+impl Tool for StampTool {
+    fn name(&self) -> &str {
+        "stamp"
+    }
+
+    fn label(&self) -> &str {
+        "Stamp"
+    }
+
+    fn handle_input(&mut self, input: &ToolInput, world: &ToolWorld) {
+        if !input.pressed {
+            return;
+        }
+        let mut map = world.map.lock().unwrap();
+        let Some(stamp) = &self.stamp else {
+            let tiles = &map.layers[map.active_layer].tiles;
+            let stamp = input
+                .footprint
+                .iter()
+                .filter_map(|pos| {
+                    let tile = tiles.get(&(pos.x, pos.y))?;
+                    Some((pos.x - input.hover.x, pos.y - input.hover.y, tile.id))
+                })
+                .collect();
+            self.stamp = Some(stamp);
+            return;
+        };
+        for (dx, dy, id) in stamp {
+            let pos = TilePosition::new(input.hover.x + dx, input.hover.y + dy);
+            match map.place_tile(&pos, *id) {
+                Ok(()) => world
+                    .recorder
+                    .record(format!("map.place({}, {}, {})", pos.x, pos.y, id)),
+                Err(reason) => world.reject(&reason),
+            }
+        }
+    }
+
+    fn draw_preview(&self, input: &ToolInput) {
+        match &self.stamp {
+            Some(stamp) => {
+                for (dx, dy, _) in stamp {
+                    outline(
+                        TilePosition::new(input.hover.x + dx, input.hover.y + dy),
+                        GOLD,
+                    );
+                }
+            }
+            None => {
+                for pos in &input.footprint {
+                    outline(*pos, SKYBLUE);
+                }
+            }
+        }
+    }
+
+    fn deactivate(&mut self, _world: &ToolWorld) {
+        self.stamp = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NamedTool(&'static str, &'static str);
+
+    impl Tool for NamedTool {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn label(&self) -> &str {
+            self.1
+        }
+
+        fn handle_input(&mut self, _input: &ToolInput, _world: &ToolWorld) {}
+    }
+
+    #[test]
+    fn test_tools_are_found_by_name_and_replaced() {
+        let registry = ToolRegistry::default();
+        assert!(registry
+            .register(Box::new(NamedTool("paint", "Paint")))
+            .is_err());
+        assert_eq!(
+            registry.register(Box::new(NamedTool("road", "Road"))),
+            Ok(0)
+        );
+        assert_eq!(
+            registry.register(Box::new(NamedTool("zone", "Zone"))),
+            Ok(1)
+        );
+        assert_eq!(
+            registry.register(Box::new(NamedTool("road", "Roads"))),
+            Ok(0)
+        );
+
+        assert_eq!(registry.state_of("zone"), Some(UIState::Plugin(1)));
+        assert_eq!(registry.state_of("fill"), Some(UIState::TileFilling));
+        assert_eq!(registry.state_name(UIState::Plugin(0)), "road");
+        assert_eq!(registry.list()[0].1, "Roads");
+    }
+}
@@ -0,0 +1,187 @@
+use crate::camera::CameraController;
+use crate::config::{
+    BUTTON_COLOR, BUTTON_HEIGHT, BUTTON_PADDING, CONSOLE_LINE_HEIGHT, TEXT_BACKGROUND_COLOR,
+    TEXT_FONT_SIZE, TILE_SIZE, TUTORIAL_HIGHLIGHT_COLOR, TUTORIAL_PANEL_WIDTH,
+};
+use crate::speech::wrap;
+use lua_engine::actions::Action;
+use lua_engine::tutorial::{Highlight, TutorialStep, Tutorials};
+use macroquad::prelude::*;
+
+// Width of the buttons below the text
+const BUTTON_WIDTH: f32 = 70.0;
+
+/// The step of the running tutorial at the top of the screen with buttons to go on and to skip,
+/// and an outline around what it points at
+pub struct TutorialOverlay {
+    tutorials: Tutorials,
+}
+
+impl TutorialOverlay {
+    pub(crate) fn new(tutorials: Tutorials) -> Self {
+        Self { tutorials }
+    }
+
+    // Title with the step, then the text wrapped to the panel
+    fn lines(step: &TutorialStep) -> Vec<String> {
+        let mut lines = vec![format!(
+            "Tutorial {} ({}/{})",
+            step.tutorial,
+            step.index + 1,
+            step.count
+        )];
+        lines.extend(wrap(&step.text, TUTORIAL_PANEL_WIDTH - 20.0, |text| {
+            measure_text(text, None, TEXT_FONT_SIZE as u16, 1.0).width
+        }));
+        lines
+    }
+
+    fn panel_rect(line_count: usize) -> Rect {
+        let height = line_count as f32 * CONSOLE_LINE_HEIGHT + BUTTON_HEIGHT + 30.0;
+        Rect::new(
+            (screen_width() - TUTORIAL_PANEL_WIDTH) / 2.0,
+            20.0,
+            TUTORIAL_PANEL_WIDTH,
+            height,
+        )
+    }
+
+    // Skip on the right, Next left of it for steps going on when told to
+    fn button_rects(panel: Rect, step: &TutorialStep) -> (Option<Rect>, Rect) {
+        let y = panel.bottom() - BUTTON_HEIGHT - 10.0;
+        let skip = Rect::new(
+            panel.right() - BUTTON_WIDTH - 10.0,
+            y,
+            BUTTON_WIDTH,
+            BUTTON_HEIGHT,
+        );
+        let next = (!step.waits)
+            .then(|| Rect::new(skip.x - BUTTON_WIDTH - 10.0, y, BUTTON_WIDTH, BUTTON_HEIGHT));
+        (next, skip)
+    }
+
+    /// The step shown, if a tutorial is running
+    pub(crate) fn describe(&self) -> Option<String> {
+        let step = self.tutorials.current()?;
+        Some(format!(
+            "Tutorial {}, step {}/{}: {}",
+            step.tutorial,
+            step.index + 1,
+            step.count,
+            step.text
+        ))
+    }
+
+    pub(crate) fn captures_mouse(&self, screen_pos: Vec2) -> bool {
+        self.tutorials
+            .current()
+            .is_some_and(|step| Self::panel_rect(Self::lines(&step).len()).contains(screen_pos))
+    }
+
+    /// Tell the running tutorial the actions of the frame and handle the clicks on its buttons
+    pub(crate) fn update(&self, actions: &[Action]) {
+        for action in actions {
+            self.tutorials.action(*action);
+        }
+        let Some(step) = self.tutorials.current() else {
+            return;
+        };
+        if !is_mouse_button_pressed(MouseButton::Left) {
+            return;
+        }
+        let mouse = Vec2::from(mouse_position());
+        let (next, skip) = Self::button_rects(Self::panel_rect(Self::lines(&step).len()), &step);
+        if skip.contains(mouse) {
+            self.tutorials.skip();
+        } else if next.is_some_and(|next| next.contains(mouse)) {
+            self.tutorials.next();
+        }
+    }
+
+    /// Draw the step, `ui_rect` tells where the parts of the UI named by the steps are
+    pub(crate) fn draw(&self, camera: &CameraController, ui_rect: impl Fn(&str) -> Option<Rect>) {
+        let Some(step) = self.tutorials.current() else {
+            return;
+        };
+
+        let highlighted = match &step.highlight {
+            Some(Highlight::Ui(name)) => ui_rect(name),
+            Some(Highlight::Rect(x, y, w, h)) => Some(Rect::new(*x, *y, *w, *h)),
+            Some(Highlight::Region {
+                min_x,
+                min_y,
+                max_x,
+                max_y,
+            }) => {
+                let min = camera.world_to_screen(Vec2::new(
+                    *min_x as f32 * TILE_SIZE,
+                    *min_y as f32 * TILE_SIZE,
+                ));
+                let max = camera.world_to_screen(Vec2::new(
+                    (*max_x + 1) as f32 * TILE_SIZE,
+                    (*max_y + 1) as f32 * TILE_SIZE,
+                ));
+                Some(Rect::new(min.x, min.y, max.x - min.x, max.y - min.y))
+            }
+            None => None,
+        };
+        if let Some(rect) = highlighted {
+            // Pulsing, so it catches the eye
+            let alpha = 0.6 + 0.4 * (get_time() as f32 * 4.0).sin();
+            let color = Color {
+                a: alpha,
+                ..TUTORIAL_HIGHLIGHT_COLOR
+            };
+            draw_rectangle_lines(
+                rect.x - 4.0,
+                rect.y - 4.0,
+                rect.w + 8.0,
+                rect.h + 8.0,
+                3.0,
+                color,
+            );
+        }
+
+        let lines = Self::lines(&step);
+        let panel = Self::panel_rect(lines.len());
+        draw_rectangle(panel.x, panel.y, panel.w, panel.h, TEXT_BACKGROUND_COLOR);
+        draw_rectangle_lines(
+            panel.x,
+            panel.y,
+            panel.w,
+            panel.h,
+            1.0,
+            TUTORIAL_HIGHLIGHT_COLOR,
+        );
+        for (i, line) in lines.iter().enumerate() {
+            draw_text(
+                line,
+                panel.x + 10.0,
+                panel.y + 10.0 + (i + 1) as f32 * CONSOLE_LINE_HEIGHT,
+                TEXT_FONT_SIZE,
+                if i == 0 {
+                    TUTORIAL_HIGHLIGHT_COLOR
+                } else {
+                    WHITE
+                },
+            );
+        }
+
+        let (next, skip) = Self::button_rects(panel, &step);
+        for (rect, label) in next
+            .map(|next| (next, "Next"))
+            .into_iter()
+            .chain([(skip, "Skip")])
+        {
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, BUTTON_COLOR);
+            draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 1.0, GRAY);
+            draw_text(
+                label,
+                rect.x + BUTTON_PADDING,
+                rect.y + (BUTTON_HEIGHT + TEXT_FONT_SIZE) / 2.0 - 4.0,
+                TEXT_FONT_SIZE,
+                WHITE,
+            );
+        }
+    }
+}
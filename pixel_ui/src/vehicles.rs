@@ -0,0 +1,94 @@
+use crate::config::{TILE_SIZE, VEHICLE_COLOR, VEHICLE_OUTLINE_COLOR, VEHICLE_SIZE};
+use crate::{TileMap, TilePosition};
+use lua_engine::Vehicles;
+use macroquad::prelude::*;
+use std::collections::HashMap;
+
+/// Tiles tagged like this are roads, crossing one costs its movement cost
+const ROAD_TAG: &str = "road";
+
+/// Vehicles driving along the roads of the map. The logic only knows the roads it's told about,
+/// so the road tiles are handed over again whenever the map was edited or another one is shown.
+pub struct VehicleLayer {
+    vehicles: Vehicles,
+    // The roads told about by the name and level of their map
+    roads: HashMap<(String, i32), HashMap<(i32, i32), f32>>,
+    // Revision of the map the roads were taken from, None before the first sync
+    revision: Option<u64>,
+}
+
+impl VehicleLayer {
+    pub(crate) fn new(vehicles: Vehicles) -> Self {
+        Self {
+            vehicles,
+            roads: HashMap::new(),
+            revision: None,
+        }
+    }
+
+    // Tell the logic about the roads added, changed or removed since the last sync
+    pub(crate) fn sync_roads(&mut self, map: &TileMap) {
+        if self.revision == Some(map.revision) {
+            return;
+        }
+        self.revision = Some(map.revision);
+
+        let (min_x, min_y, max_x, max_y) = map.bounds.as_tuple();
+        let mut roads = HashMap::new();
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                if let Some(props) = map.get_tile_properties(&TilePosition::new(x, y))
+                    && props.tags.iter().any(|tag| tag == ROAD_TAG)
+                    && props.movement_cost > 0.0
+                {
+                    roads.insert((x, y), props.movement_cost);
+                }
+            }
+        }
+        let known = self.roads.entry((map.name.clone(), map.level)).or_default();
+        for &(x, y) in known.keys().filter(|tile| !roads.contains_key(tile)) {
+            self.vehicles.clear_road(&map.name, map.level, x, y);
+        }
+        for (&(x, y), &cost) in &roads {
+            if known.get(&(x, y)) != Some(&cost) {
+                // Costs are positive and finite, so the logic takes every one of them
+                let _ = self
+                    .vehicles
+                    .set_road(&map.name, map.level, x, y, cost as f64);
+            }
+        }
+        *known = roads;
+    }
+
+    // Returns the number of vehicles drawn
+    pub(crate) fn draw(&self, visible: Rect) -> usize {
+        let size = TILE_SIZE * VEHICLE_SIZE;
+        let mut drawn = 0;
+        for ((x, y), load) in self.vehicles.positions() {
+            let rect = Rect::new(
+                (x + 0.5) * TILE_SIZE - size / 2.0,
+                (y + 0.5) * TILE_SIZE - size / 2.0,
+                size,
+                size,
+            );
+            if !rect.overlaps(&visible) {
+                continue;
+            }
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, VEHICLE_COLOR);
+            draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 2.0, VEHICLE_OUTLINE_COLOR);
+            if load > 0 {
+                let text = load.to_string();
+                let dimensions = measure_text(&text, None, 16, 1.0);
+                draw_text(
+                    &text,
+                    rect.center().x - dimensions.width / 2.0,
+                    rect.center().y + dimensions.height / 2.0,
+                    16.0,
+                    VEHICLE_OUTLINE_COLOR,
+                );
+            }
+            drawn += 1;
+        }
+        drawn
+    }
+}
@@ -0,0 +1,166 @@
+use crate::config::{
+    HUD_AUTO_SCALE_MAX, HUD_AUTO_SCALE_MIN, HUD_REFERENCE_HEIGHT, SOURCE_TILE_SIZE, TILE_SIZE,
+    UI_SCALE_MAX, UI_SCALE_MIN, ZOOM_MAX, ZOOM_MIN, ZOOM_STEP, ZOOM_STEP_MAX,
+};
+use crate::hud::Anchor;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+// Zoom levels count as the same within this ratio, so stepping doesn't stall on rounding
+const LEVEL_TOLERANCE: f32 = 1.001;
+
+/// How the view zooms, read from the view settings file at startup
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewSettings {
+    /// Factor one mouse wheel notch or zoom key press zooms by
+    pub zoom_step: f32,
+    /// Only zoom to levels drawing every tileset pixel as a whole number of screen pixels, or a
+    /// screen pixel as a whole power of two of tileset pixels, so tiles don't shimmer
+    pub pixel_zoom_levels: bool,
+    /// Snap the camera and the moving sprites to whole screen pixels, against tile seams and
+    /// sprites wobbling at zooms between the pixel zoom levels
+    pub pixel_perfect: bool,
+    /// Size of the HUD on top of the scaling with the window height
+    pub ui_scale: f32,
+    /// Where the debug window goes
+    pub debug_anchor: Anchor,
+    /// Where the preview of the selected tile goes, with the tile inspector next to it
+    pub tile_anchor: Anchor,
+}
+
+impl Default for ViewSettings {
+    fn default() -> Self {
+        Self {
+            zoom_step: ZOOM_STEP,
+            pixel_zoom_levels: false,
+            pixel_perfect: false,
+            ui_scale: 1.0,
+            debug_anchor: Anchor::TopLeft,
+            tile_anchor: Anchor::TopRight,
+        }
+    }
+}
+
+impl ViewSettings {
+    /// Missing or broken files give the defaults
+    pub fn load(path: &str) -> Self {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        let mut settings: Self = serde_json::from_str(&content).unwrap_or_else(|e| {
+            println!("Failed to parse {}: {}", path, e);
+            Self::default()
+        });
+        settings.zoom_step = settings.zoom_step.clamp(LEVEL_TOLERANCE, ZOOM_STEP_MAX);
+        settings.ui_scale = settings.ui_scale.clamp(UI_SCALE_MIN, UI_SCALE_MAX);
+        settings
+    }
+
+    /// Scale of the HUD in a window `screen_height` pixels high, growing with the window so it
+    /// reads the same at 720p and 4K
+    pub fn hud_scale(&self, screen_height: f32) -> f32 {
+        let auto =
+            (screen_height / HUD_REFERENCE_HEIGHT).clamp(HUD_AUTO_SCALE_MIN, HUD_AUTO_SCALE_MAX);
+        self.ui_scale * auto
+    }
+
+    /// The zoom one step closer in from `zoom`
+    pub fn zoom_in(&self, zoom: f32) -> f32 {
+        if !self.pixel_zoom_levels {
+            return (zoom * self.zoom_step).min(ZOOM_MAX);
+        }
+        let levels = pixel_zoom_levels();
+        levels
+            .iter()
+            .copied()
+            .find(|&level| level > zoom * LEVEL_TOLERANCE)
+            .unwrap_or(levels[levels.len() - 1])
+    }
+
+    /// The zoom one step further out from `zoom`
+    pub fn zoom_out(&self, zoom: f32) -> f32 {
+        if !self.pixel_zoom_levels {
+            return (zoom / self.zoom_step).max(ZOOM_MIN);
+        }
+        let levels = pixel_zoom_levels();
+        levels
+            .iter()
+            .copied()
+            .rfind(|&level| level * LEVEL_TOLERANCE < zoom)
+            .unwrap_or(levels[0])
+    }
+
+    /// The zoom to use for showing at least as much as `zoom` does, the closest level below it
+    /// with pixel zoom levels
+    pub fn fitting_zoom(&self, zoom: f32) -> f32 {
+        if !self.pixel_zoom_levels {
+            return zoom;
+        }
+        let levels = pixel_zoom_levels();
+        levels
+            .iter()
+            .copied()
+            .rfind(|&level| level <= zoom * LEVEL_TOLERANCE)
+            .unwrap_or(levels[0])
+    }
+}
+
+// Zooms between ZOOM_MIN and ZOOM_MAX at which a tileset pixel covers a whole number of screen
+// pixels, or a screen pixel a power of two of tileset pixels, smallest first
+fn pixel_zoom_levels() -> Vec<f32> {
+    let screen_pixels_per_texel = TILE_SIZE / SOURCE_TILE_SIZE;
+    let mut levels = Vec::new();
+    let mut level = 0.5 / screen_pixels_per_texel;
+    while level >= ZOOM_MIN {
+        levels.insert(0, level);
+        level /= 2.0;
+    }
+    levels.extend(
+        (1..)
+            .map(|pixels| pixels as f32 / screen_pixels_per_texel)
+            .take_while(|&level| level <= ZOOM_MAX),
+    );
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zoom_steps_by_factor_or_to_the_next_pixel_level() {
+        let free = ViewSettings {
+            zoom_step: 2.0,
+            pixel_zoom_levels: false,
+            pixel_perfect: false,
+            ..Default::default()
+        };
+        assert_eq!(free.zoom_in(0.7), 1.4);
+        assert_eq!(free.zoom_out(0.7), 0.35);
+        assert_eq!(free.zoom_in(ZOOM_MAX), ZOOM_MAX);
+
+        let pixel = ViewSettings {
+            pixel_zoom_levels: true,
+            ..free
+        };
+        let texel = TILE_SIZE / SOURCE_TILE_SIZE;
+        assert_eq!(pixel.zoom_in(1.2 / texel), 2.0 / texel);
+        assert_eq!(pixel.zoom_in(1.0 / texel), 2.0 / texel);
+        assert_eq!(pixel.zoom_out(1.0 / texel), 0.5 / texel);
+        assert_eq!(pixel.zoom_out(0.7 / texel), 0.5 / texel);
+        assert_eq!(pixel.fitting_zoom(2.9 / texel), 2.0 / texel);
+        assert!(pixel.zoom_out(ZOOM_MIN) >= ZOOM_MIN);
+    }
+
+    #[test]
+    fn test_hud_grows_with_the_window() {
+        let settings = ViewSettings {
+            ui_scale: 1.5,
+            ..Default::default()
+        };
+        assert_eq!(settings.hud_scale(HUD_REFERENCE_HEIGHT), 1.5);
+        assert_eq!(settings.hud_scale(HUD_REFERENCE_HEIGHT * 2.0), 3.0);
+        assert_eq!(settings.hud_scale(10.0), 1.5 * HUD_AUTO_SCALE_MIN);
+    }
+}
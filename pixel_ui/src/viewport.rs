@@ -0,0 +1,54 @@
+use crate::camera::CameraController;
+use crate::config::{PIP_HEIGHT, PIP_WIDTH, PIP_ZOOM};
+use crate::people::PersonId;
+use crate::view_settings::ViewSettings;
+use macroquad::prelude::*;
+
+/// Additional view of the world rendered into a part of the screen, composited over the main view
+pub struct Viewport {
+    pub(crate) camera: CameraController,
+    /// Person the view is following
+    pub(crate) follow: Option<PersonId>,
+}
+
+impl Viewport {
+    /// Picture-in-picture view in the bottom right corner following a person, zooming and
+    /// snapping to pixels as the main view does
+    pub(crate) fn picture_in_picture(follow: PersonId, position: Vec2, view: ViewSettings) -> Self {
+        let mut camera = CameraController::new(position);
+        camera.view = view;
+        camera.set_zoom(PIP_ZOOM);
+        Self {
+            camera,
+            follow: Some(follow),
+        }
+    }
+
+    /// Recompute the screen area and move the camera to the followed position
+    pub(crate) fn update(&mut self, follow_position: Option<Vec2>) {
+        self.camera.viewport = Some(Rect::new(
+            screen_width() - PIP_WIDTH - 20.0,
+            screen_height() - PIP_HEIGHT - 110.0,
+            PIP_WIDTH,
+            PIP_HEIGHT,
+        ));
+        if let Some(position) = follow_position {
+            self.camera.position = position;
+        }
+    }
+
+    /// Background and frame, drawn in screen space before rendering the world into the viewport
+    pub(crate) fn draw_frame(&self) {
+        if let Some(rect) = self.camera.viewport {
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, BLACK);
+            draw_rectangle_lines(
+                rect.x - 2.0,
+                rect.y - 2.0,
+                rect.w + 4.0,
+                rect.h + 4.0,
+                2.0,
+                GRAY,
+            );
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::config::{CONSOLE_LINE_HEIGHT, TEXT_FONT_SIZE, WATCH_PANEL_WIDTH};
+use lua_engine::lua_client::LuaClient;
+use lua_engine::script_error::ScriptError;
+use macroquad::prelude::*;
+use std::sync::mpsc;
+
+/// Expression re-evaluated periodically, keeping its last result
+struct Watch {
+    expression: String,
+    value: Option<Result<String, ScriptError>>,
+    pending: Option<mpsc::Receiver<Result<String, ScriptError>>>,
+}
+
+/// Watch expressions shown next to the console history, managed through the Lua `console` global
+pub struct WatchList {
+    watches: Vec<Watch>,
+    pub(crate) interval: f64,
+    last_refresh: f64,
+}
+
+impl WatchList {
+    pub(crate) fn new(interval: f64) -> Self {
+        Self {
+            watches: Vec::new(),
+            interval,
+            last_refresh: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Returns false when the expression is already watched
+    pub(crate) fn add(&mut self, expression: &str) -> bool {
+        if self.watches.iter().any(|w| w.expression == expression) {
+            return false;
+        }
+        self.watches.push(Watch {
+            expression: expression.to_string(),
+            value: None,
+            pending: None,
+        });
+        // Evaluate the new expression right away
+        self.last_refresh = f64::NEG_INFINITY;
+        true
+    }
+
+    /// Returns false when the expression wasn't watched
+    pub(crate) fn remove(&mut self, expression: &str) -> bool {
+        let count = self.watches.len();
+        self.watches.retain(|w| w.expression != expression);
+        self.watches.len() != count
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.watches.clear();
+    }
+
+    pub(crate) fn expressions(&self) -> Vec<String> {
+        self.watches.iter().map(|w| w.expression.clone()).collect()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.watches.is_empty()
+    }
+
+    /// Collect finished evaluations and start new ones once the interval has passed
+    pub(crate) fn update(&mut self, lua_client: &LuaClient) {
+        for watch in &mut self.watches {
+            if let Some(receiver) = &watch.pending {
+                match receiver.try_recv() {
+                    Ok(result) => {
+                        watch.value = Some(result);
+                        watch.pending = None;
+                    }
+                    Err(mpsc::TryRecvError::Empty) => {}
+                    Err(mpsc::TryRecvError::Disconnected) => watch.pending = None,
+                }
+            }
+        }
+
+        let now = get_time();
+        if now - self.last_refresh < self.interval {
+            return;
+        }
+        self.last_refresh = now;
+        // Skip watches still waiting for their previous result so a slow expression doesn't pile up
+        for watch in self.watches.iter_mut().filter(|w| w.pending.is_none()) {
+            watch.pending = Some(lua_client.execute_non_blocking(&watch.expression));
+        }
+    }
+
+    /// Panel on the right side of the given area, one line per watch
+    pub(crate) fn draw(&self, right: f32, top: f32, height: f32) {
+        if self.watches.is_empty() {
+            return;
+        }
+
+        let x = right - WATCH_PANEL_WIDTH;
+        draw_rectangle(
+            x,
+            top,
+            WATCH_PANEL_WIDTH,
+            height,
+            Color::new(0.0, 0.0, 0.0, 0.8),
+        );
+        draw_text(
+            &format!("Watches (every {:.1}s)", self.interval),
+            x + 10.0,
+            top + CONSOLE_LINE_HEIGHT,
+            TEXT_FONT_SIZE,
+            GRAY,
+        );
+
+        let visible_lines = (height / CONSOLE_LINE_HEIGHT) as usize;
+        for (i, watch) in self
+            .watches
+            .iter()
+            .take(visible_lines.saturating_sub(1))
+            .enumerate()
+        {
+            let (value, color) = match &watch.value {
+                Some(Ok(value)) => (value.clone(), WHITE),
+                Some(Err(err)) => (err.message.clone(), RED),
+                None => ("...".to_string(), GRAY),
+            };
+            draw_text(
+                &format!("{} = {}", watch.expression, value),
+                x + 10.0,
+                top + (i + 2) as f32 * CONSOLE_LINE_HEIGHT,
+                TEXT_FONT_SIZE,
+                color,
+            );
+        }
+    }
+}
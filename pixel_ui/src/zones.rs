@@ -0,0 +1,40 @@
+use crate::config::{TILE_SIZE, ZONE_FILL_ALPHA, ZONE_OUTLINE_WIDTH};
+use lua_engine::Factions;
+use macroquad::prelude::*;
+
+/// Zones owned by a faction, tinted and outlined in the faction's color. Zones nobody owns
+/// aren't drawn.
+pub struct ZoneLayer {
+    factions: Factions,
+}
+
+impl ZoneLayer {
+    pub(crate) fn new(factions: Factions) -> Self {
+        Self { factions }
+    }
+
+    // Returns the number of zones drawn
+    pub(crate) fn draw(&self, visible: Rect) -> usize {
+        let mut drawn = 0;
+        for ((x1, y1, x2, y2), [r, g, b]) in self.factions.owned_zones() {
+            let rect = Rect::new(
+                x1 as f32 * TILE_SIZE,
+                y1 as f32 * TILE_SIZE,
+                (x2 - x1 + 1) as f32 * TILE_SIZE,
+                (y2 - y1 + 1) as f32 * TILE_SIZE,
+            );
+            if !rect.overlaps(&visible) {
+                continue;
+            }
+            let color = Color::from_rgba(r, g, b, 255);
+            let fill = Color {
+                a: ZONE_FILL_ALPHA,
+                ..color
+            };
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, fill);
+            draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, ZONE_OUTLINE_WIDTH, color);
+            drawn += 1;
+        }
+        drawn
+    }
+}
@@ -0,0 +1,59 @@
+-- Spawn scenarios for stress testing, each puts a crowd on the map in a different shape.
+-- From the console: benchmark.run("column", 5000), people.clear() first to start over.
+-- Mods can add their own with benchmark.scenarios.name = function(count, bounds) ... end,
+-- where bounds has min_x, min_y, max_x and max_y of the map and the function spawns with
+-- people.spawn(x, y).
+local benchmark = {
+    -- How many tiles a cluster reaches out from its center
+    dispersion = 1,
+    -- Tiles across a marching column
+    column_width = 3,
+}
+
+local function center(bounds)
+    return (bounds.min_x + bounds.max_x) // 2, (bounds.min_y + bounds.max_y) // 2
+end
+
+benchmark.scenarios = {
+    -- Everyone packed around the middle of the map, most people on screen at once
+    clustered = function(count, bounds)
+        local x, y = center(bounds)
+        local d = benchmark.dispersion
+        for _ = 1, count do
+            people.spawn(math.random(x - d, x + d), math.random(y - d, y + d))
+        end
+    end,
+    -- Spread evenly over the whole map, most people off-screen
+    uniform = function(count, bounds)
+        for _ = 1, count do
+            people.spawn(math.random(bounds.min_x, bounds.max_x), math.random(bounds.min_y, bounds.max_y))
+        end
+    end,
+    -- Rank after rank running down the map through its middle, the screen edges cut through it
+    column = function(count, bounds)
+        local x = center(bounds) - benchmark.column_width // 2
+        local height = bounds.max_y - bounds.min_y + 1
+        for i = 0, count - 1 do
+            local rank = i // benchmark.column_width
+            people.spawn(x + i % benchmark.column_width, bounds.min_y + rank % height)
+        end
+    end,
+}
+
+-- Spawn count people as in the scenario, returns how many there are now
+function benchmark.run(name, count)
+    local scenario = benchmark.scenarios[name]
+    if not scenario then
+        local names = {}
+        for known in pairs(benchmark.scenarios) do
+            table.insert(names, known)
+        end
+        table.sort(names)
+        error("Unknown scenario '" .. tostring(name) .. "', expected one of: " .. table.concat(names, ", "))
+    end
+    local min_x, min_y, max_x, max_y = ui.tile.bounds()
+    scenario(count, { min_x = min_x, min_y = min_y, max_x = max_x, max_y = max_y })
+    return people.count()
+end
+
+return benchmark
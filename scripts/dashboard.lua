@@ -0,0 +1,118 @@
+-- Economy dashboard, an example of what the Lua UI and the api can do together: a window with
+-- plots of the population and a price series, a table of persons and buttons issuing domain
+-- commands. Open it from the console with examples.dashboard().
+-- Components can't be taken off the screen yet, so the dashboard is opened once and stays.
+local dashboard = {
+    x = 480,
+    y = 40,
+    width = 420,
+    height = 470,
+    -- Persons listed in the table, the rest is summed up in its last row
+    rows = 8,
+    -- Seconds between two samples of the price
+    price_interval = 1,
+    -- Steps of work a person is sent to do
+    work_steps = 20,
+    opened = false,
+}
+
+-- Whether a person has tasks queued, running ones included
+local function is_busy(person)
+    return #api.task.queue_of(person.id) > 0
+end
+
+-- A made up price of food: it rises with every mouth to feed and falls with every working hand
+function dashboard.price()
+    local persons = api.person.get_all()
+    local working = 0
+    for _, person in ipairs(persons) do
+        if is_busy(person) then
+            working = working + 1
+        end
+    end
+    return 5 * (#persons + 1) / (working + 1)
+end
+
+-- The first person with nothing to do, nil if everyone is busy
+local function idle_person()
+    for _, person in ipairs(api.person.get_all()) do
+        if not is_busy(person) then
+            return person
+        end
+    end
+end
+
+local function center()
+    local min_x, min_y, max_x, max_y = ui.tile.bounds()
+    return (min_x + max_x) // 2, (min_y + max_y) // 2
+end
+
+local function row(index)
+    local persons = api.person.get_all()
+    local person = persons[index]
+    if person then
+        local state = is_busy(person) and "busy" or "idle"
+        return string.format("%3d %-12s %4d,%-4d %s", person.id, person.name,
+            person.location.x, person.location.y, state)
+    elseif index == dashboard.rows + 1 and #persons > dashboard.rows then
+        return string.format("    ... and %d more", #persons - dashboard.rows)
+    end
+    return ""
+end
+
+function dashboard.build()
+    local x, y = dashboard.x + 10, dashboard.y + 30
+    local width = dashboard.width - 20
+
+    -- Plots: a metric the engine records and one this script records itself
+    ui.metric_chart(x, y, width / 2 - 5, 100, "persons")
+    ui.metric_chart(x + width / 2 + 5, y, width / 2 - 5, 100, "price")
+    y = y + 115
+
+    -- Person table
+    ui.label(x, y + 15, function()
+        return " id name         position  state"
+    end)
+    for index = 1, dashboard.rows + 1 do
+        ui.label(x, y + 15 + index * 20, function()
+            return row(index)
+        end)
+    end
+    y = y + (dashboard.rows + 2) * 20 + 10
+
+    -- Domain commands, they fail in spectator mode and end up in the error overlay
+    ui.button(x, y, "Hire", function()
+        local cx, cy = center()
+        local count = #api.person.get_all()
+        api.person.create("Worker " .. (count + 1), cx + count % 5, cy + count // 5)
+    end)
+    ui.button(x + 70, y, "Send to work", function()
+        local person = idle_person()
+        if person then
+            local cx, cy = center()
+            api.task.work_at(person.id, cx, cy, dashboard.work_steps)
+        end
+    end, function()
+        return idle_person() ~= nil
+    end)
+    ui.button(x + 200, y, "Sample price", function()
+        api.metrics.record("price", dashboard.price())
+    end)
+    ui.label(x, y + 50, function()
+        return string.format("Food price: %.2f", dashboard.price())
+    end)
+end
+
+function dashboard.open()
+    if dashboard.opened then
+        return
+    end
+    dashboard.opened = true
+    ui.window(dashboard.x, dashboard.y, dashboard.width, dashboard.height, "Economy dashboard",
+        dashboard.build)
+    timer.every(dashboard.price_interval, function()
+        api.metrics.record("price", dashboard.price())
+    end)
+end
+
+return dashboard
@@ -0,0 +1,20 @@
+-- Map effects shown for domain events, keyed by event kind.
+-- Each handler gets the event with its kind and the tile it happened at (x, y), batched moves
+-- come as PersonsMoved with a list of moves shaped like PersonMoved events.
+-- Effects: fx.sparkle(x, y, color) and fx.float_text(x, y, text, color), where color is a name
+-- like "gold" or {r, g, b, a} with components from 0 to 1.
+-- Mods can replace or add handlers, e.g. event_effects.PersonMoved = nil to turn one off.
+event_effects = {
+    PersonCreated = function(event)
+        fx.sparkle(event.x, event.y, "gold")
+        fx.float_text(event.x, event.y, event.name, "white")
+    end,
+    PersonMoved = function(event)
+        fx.sparkle(event.x, event.y, { 0.6, 0.8, 1.0, 0.8 })
+    end,
+    PersonsMoved = function(event)
+        for _, person_move in ipairs(event.moves) do
+            event_effects.PersonMoved(person_move)
+        end
+    end,
+}
@@ -0,0 +1,8 @@
+-- Example scripts bundled with the game, opened from the console, e.g. examples.dashboard().
+-- They are meant to be read: each one shows how a part of the Lua UI and the api is used.
+examples = {}
+
+-- Plots, a person table and buttons issuing commands in a window, see scripts/dashboard.lua
+function examples.dashboard()
+    require("dashboard").open()
+end
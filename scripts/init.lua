@@ -1,2 +1,12 @@
 require("ui.init")
-require("ui.debug"):draw()
\ No newline at end of file
+require("ui.debug"):draw()
+require("effects")
+require("notifications")
+require("examples")
+require("spawner")
+-- After the scripts adding tools, so they get a button
+require("ui.toolbar"):draw()
+
+-- The crowd everyone starts with, --crowd-benchmark [scenario] replaces it with a big one
+benchmark = require("benchmark")
+benchmark.run("clustered", 100)
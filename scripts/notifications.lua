@@ -0,0 +1,10 @@
+-- Notifications shown as toasts and kept in the event log, routed from domain events.
+-- Templates fill {field} with the event's fields, functions may return nil to skip an event.
+-- Mods can add their own routes with notify.route(kind, { category = ..., template = ... }),
+-- post directly with notify.post(category, text) and silence a category with notify.mute.
+notify.route("PersonCreated", { category = "people", template = "{name} arrived at {x},{y}" })
+notify.route("GoalCompleted", { category = "goals", template = function(event)
+    local goal = api.goals.get(event.goal)
+    return "Goal reached: " .. (goal and goal.name or event.goal)
+end })
+notify.route("ScenarioEnded", { category = "scenario", template = "Scenario {outcome}: {reason}" })
@@ -0,0 +1,26 @@
+-- Adds people around the middle of the map at the spawn_rate parameter of api.params, in
+-- persons per simulated minute. 0, the default, spawns nobody.
+local spawner = {
+    -- How many tiles from the middle a person may appear
+    dispersion = 5,
+    -- Fraction of a person owed from earlier frames
+    owed = 0,
+}
+
+mods.register("spawner", {
+    on_frame = function(dt)
+        spawner.owed = spawner.owed + api.params.get("spawn_rate") * dt / 60
+        if spawner.owed < 1 then
+            return
+        end
+        local min_x, min_y, max_x, max_y = ui.tile.bounds()
+        local x, y = (min_x + max_x) // 2, (min_y + max_y) // 2
+        local d = spawner.dispersion
+        while spawner.owed >= 1 do
+            spawner.owed = spawner.owed - 1
+            people.spawn(math.random(x - d, x + d), math.random(y - d, y + d))
+        end
+    end,
+})
+
+return spawner
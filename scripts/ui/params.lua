@@ -0,0 +1,35 @@
+-- Sliders for the simulation parameters of api.params: the speed, the day length, the spawn
+-- rate and whatever the mods defined until the panel was opened.
+-- Sliders call api.params.set every frame while dragged, the change handlers still run once
+-- per tick.
+local params_panel = {
+    x = 20,
+    y = 370,
+    width = 300,
+    slider_spacing = 35,
+    opened = false,
+}
+
+function params_panel.open()
+    if params_panel.opened then
+        return
+    end
+    params_panel.opened = true
+    local list = api.params.list()
+    local height = 40 + #list * params_panel.slider_spacing
+    ui.window(params_panel.x, params_panel.y, params_panel.width, height, "Simulation", function()
+        local y = params_panel.y + 35
+        for _, param in ipairs(list) do
+            ui.slider(params_panel.x + 10, y, params_panel.width - 20, param.name, param.min, param.max,
+                function(value)
+                    if value == nil then
+                        return api.params.get(param.key)
+                    end
+                    api.params.set(param.key, value)
+                end)
+            y = y + params_panel.slider_spacing
+        end
+    end)
+end
+
+return params_panel
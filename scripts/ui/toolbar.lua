@@ -0,0 +1,47 @@
+local toolbar = {
+    x = 20,
+    y = 290,
+    button_spacing = 90,
+}
+
+function toolbar.draw()
+    local x = toolbar.x
+    -- The built-in tools and the ones added until now, see ui.tool.register
+    for _, tool in ipairs(ui.tool.list()) do
+        ui.button(x, toolbar.y, tool.label, function()
+            ui.tool.set(tool.name)
+        end, function()
+            return ui.tool.get() == tool.name
+        end)
+        x = x + toolbar.button_spacing
+    end
+
+    -- Active brush: tile preview, size controls and shape toggle
+    ui.tile_preview(x, toolbar.y, 30, function()
+        return ui.brush.get().tile
+    end)
+    x = x + 40
+    ui.button(x, toolbar.y, "-", function()
+        ui.brush.set_size(ui.brush.get().size - 1)
+    end)
+    x = x + 35
+    ui.button(x, toolbar.y, "+", function()
+        ui.brush.set_size(ui.brush.get().size + 1)
+    end)
+    x = x + 35
+    ui.button(x, toolbar.y, "Shape", function()
+        local shape = ui.brush.get().shape == "square" and "circle" or "square"
+        ui.brush.set_shape(shape)
+    end)
+    ui.label(x + 100, toolbar.y + 20, function()
+        local brush = ui.brush.get()
+        return string.format("Brush: %dx%d %s", brush.size, brush.size, brush.shape)
+    end)
+
+    -- Sliders for the simulation speed, day length and spawn rate, see scripts/ui/params.lua
+    ui.button(toolbar.x, toolbar.y + 40, "Tuning", function()
+        require("ui.params").open()
+    end)
+end
+
+return toolbar
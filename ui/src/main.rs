@@ -1,19 +1,47 @@
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
 mod ui;
 
+use lua_engine::lifecycle::Hook;
+use lua_engine::lua_client::LuaClient;
 use lua_engine::lua_engine::LuaEngine;
+use lua_engine::mod_settings::MOD_SETTINGS_PATH;
+use lua_engine::script_args;
 use ui::MyApp;
 
 fn main() -> eframe::Result<()> {
     // Create the Lua Engine, exposing the logic API to Lua
-    let lua_engine = Arc::new(RwLock::new(LuaEngine::new()));
+    let (command_tx, command_rx) = mpsc::channel();
+    let lua_engine = Arc::new(Mutex::new(LuaEngine::new(command_rx)));
 
     // Run the UI
     let options = eframe::NativeOptions::default();
-    let app = MyApp::new(lua_engine.clone());
-    if let Err(err) = lua_engine.write().unwrap().run_script("require('init')") {
-        eprintln!("Unable to load init.lua due to lua error: {}", err);
+    let app = MyApp::new(lua_engine.clone(), LuaClient::new(command_tx));
+    {
+        let mut engine = lua_engine.lock().unwrap();
+        // `--script-arg key=value` pairs end up in the read-only `env` table of the scripts
+        match script_args::from_command_line(std::env::args()) {
+            Ok(args) => engine.set_script_args(&args).unwrap(),
+            Err(e) => eprintln!("{}", e),
+        }
+        // Before the mods declare their settings, so they start with the saved values
+        engine.settings.persist_to(MOD_SETTINGS_PATH);
+        // Plugins first, so init.lua can use the modules they add
+        for error in engine.load_plugins("plugins") {
+            eprintln!("{}", error);
+        }
+        if let Err(err) = engine.run_script("require('init')") {
+            eprintln!(
+                "Unable to load init.lua due to lua error: {}\n{}",
+                err, err.traceback
+            );
+        }
+        engine.hooks.call(Hook::Init, ());
     }
+    // Scripts from the console run on their own thread, so the debugger can pause them
+    thread::spawn(move || {
+        lua_engine.lock().unwrap().run();
+    });
     eframe::run_native(
         "Space Business 5",
         options,
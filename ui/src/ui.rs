@@ -1,8 +1,24 @@
 use egui::Window;
 use egui_plot::{Line, Plot, PlotPoints};
+use lua_engine::actions::{Action, KeyBindings, KEY_BINDINGS_PATH};
+use lua_engine::api_reference::{ApiReference, MethodPage};
+use lua_engine::clock::{Clock, TimeMode};
+use lua_engine::debugger::{Debugger, PausedFrame};
+use lua_engine::error_log::ErrorLog;
+use lua_engine::goals::Goals;
+use lua_engine::lifecycle::{Hook, LifecycleHooks};
+use lua_engine::lua_client::{FrameTicker, LuaClient};
 use lua_engine::lua_engine::LuaEngine;
+use lua_engine::mod_settings::{ModSettings, SettingKind, SettingValue};
+use lua_engine::notifications::Notifications;
+use lua_engine::scenario::Scenario;
+use lua_engine::script_error::ScriptError;
+use lua_engine::timers::Timers;
+use lua_engine::{Metrics, Snapshots};
 use mlua::prelude::LuaFunction;
-use std::sync::{Arc, RwLock, RwLockWriteGuard};
+use mlua::Lua;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::sync::{Arc, Mutex, RwLock};
 
 enum UIComponent {
     Button {
@@ -24,6 +40,11 @@ enum UIComponent {
         label: String,
         handler: LuaFunction,
     },
+    MetricPlot {
+        label: String,
+        metric: String,
+        metrics: Metrics,
+    },
     Window {
         label: String,
         children: Vec<UIComponent>,
@@ -32,19 +53,88 @@ enum UIComponent {
         script: String,
     },
 }
+
+// Series of the world stats the engine samples on its own, plotted in the stats window as
+// (title, metric)
+const STATS_PLOTS: [(&str, &str); 4] = [
+    ("Population", "persons"),
+    ("Occupied tiles", "occupied"),
+    ("Moves per sample", "moves"),
+    ("Events per tick", "event_rate"),
+];
+
+// A metric of `api.metrics` plotted over the ticks
+fn metric_plot(ui: &mut egui::Ui, label: &str, metrics: &Metrics, metric: &str) {
+    let points: Vec<[f64; 2]> = metrics
+        .samples(metric)
+        .into_iter()
+        .map(|(tick, value)| [tick, value])
+        .collect();
+    Plot::new(label).view_aspect(2.0).show(ui, |plot_ui| {
+        plot_ui.line(Line::new(PlotPoints::new(points)));
+    });
+}
+
+// Script of the first Lua console of the components, looking into the windows too
+fn console_script(components: &mut [UIComponent]) -> Option<&mut String> {
+    components.iter_mut().find_map(|component| match component {
+        UIComponent::LuaConsole { script } => Some(script),
+        UIComponent::Window { children, .. } => console_script(children),
+        _ => None,
+    })
+}
 pub struct MyApp {
-    lua_engine: Arc<RwLock<LuaEngine>>,
+    lua_client: LuaClient,
+    debugger: Debugger,
+    error_log: ErrorLog,
+    hooks: LifecycleHooks,
+    timers: Timers,
+    snapshots: Snapshots,
+    metrics: Metrics,
+    scenario: Scenario,
+    goals: Goals,
+    notifications: Notifications,
+    settings: ModSettings,
+    clock: Clock,
+    ticker: FrameTicker,
+    bindings: KeyBindings,
+    lua: Lua,
+    // Read when the docs window opens, mods may document more in the meantime
+    docs: Option<ApiReference>,
+    docs_query: String,
+    // Module and name of the method shown in the docs window
+    docs_selected: Option<(String, String)>,
+    show_errors: bool,
+    show_result: bool,
+    show_log: bool,
+    show_mod_settings: bool,
+    show_stats: bool,
+    pending_scripts: Vec<Receiver<Result<String, ScriptError>>>,
     script_input: String,
     components: Arc<RwLock<Vec<UIComponent>>>,
     new_components: Arc<RwLock<Vec<UIComponent>>>,
 }
 
 impl MyApp {
-    pub fn new(lua_engine: Arc<RwLock<LuaEngine>>) -> Self {
+    pub fn new(lua_engine: Arc<Mutex<LuaEngine>>, lua_client: LuaClient) -> Self {
         let components: Arc<RwLock<Vec<UIComponent>>> = Arc::new(RwLock::new(Vec::new()));
         let old_components = Arc::new(RwLock::new(Vec::new()));
+        let debugger = lua_engine.lock().unwrap().debugger.clone();
+        let error_log = lua_engine.lock().unwrap().error_log.clone();
+        let hooks = lua_engine.lock().unwrap().hooks.clone();
+        let timers = lua_engine.lock().unwrap().timers.clone();
+        let snapshots = lua_engine.lock().unwrap().snapshots.clone();
+        let metrics = lua_engine.lock().unwrap().metrics.clone();
+        let scenario = lua_engine.lock().unwrap().scenario.clone();
+        let goals = lua_engine.lock().unwrap().goals.clone();
+        let notifications = lua_engine.lock().unwrap().notifications.clone();
+        let settings = lua_engine.lock().unwrap().settings.clone();
+        let clock = lua_engine.lock().unwrap().clock.clone();
+        let lua = lua_engine.lock().unwrap().lua.clone();
+        // Handlers run on the UI thread, pausing them would freeze the debugger window
+        debugger.set_ui_thread();
         {
-            let lua = &lua_engine.write().unwrap().lua;
+            let lua = &lua_engine.lock().unwrap().lua;
 
             // Register UI components (buttons, labels, etc.) in Lua
             let globals = lua.globals();
@@ -101,6 +191,21 @@ impl MyApp {
                 })
                 .unwrap();
             globals.set("plot", add_plot).unwrap();
+            // Register metric_plot in Lua, plotting a metric of api.metrics over the ticks
+            let components_clone = Arc::clone(&components);
+            let metrics = metrics.clone();
+            let add_metric_plot = lua
+                .create_function(move |_, (label, metric): (String, String)| {
+                    let mut plots = components_clone.write().unwrap();
+                    plots.push(UIComponent::MetricPlot {
+                        label,
+                        metric,
+                        metrics: metrics.clone(),
+                    });
+                    Ok(())
+                })
+                .unwrap();
+            globals.set("metric_plot", add_metric_plot).unwrap();
             let components_clone = Arc::clone(&components);
             let add_window = lua
                 .create_function(move |_, (label, child_func): (String, LuaFunction)| {
@@ -144,7 +249,30 @@ impl MyApp {
             globals.set("reset", reset_components).unwrap();
         }
         Self {
-            lua_engine,
+            lua_client,
+            debugger,
+            error_log,
+            hooks,
+            timers,
+            snapshots,
+            metrics,
+            scenario,
+            goals,
+            notifications,
+            settings,
+            clock,
+            ticker: FrameTicker::new(1.0 / 60.0),
+            bindings: KeyBindings::load(KEY_BINDINGS_PATH),
+            lua,
+            docs: None,
+            docs_query: String::new(),
+            docs_selected: None,
+            show_errors: false,
+            show_result: true,
+            show_log: false,
+            show_mod_settings: false,
+            show_stats: false,
+            pending_scripts: Vec::new(),
             script_input: String::new(),
             components: old_components,
             new_components: components,
@@ -152,7 +280,9 @@ impl MyApp {
     }
 
     fn render_component(
-        lua_engine: &RwLockWriteGuard<LuaEngine>,
+        error_log: &ErrorLog,
+        lua_client: &LuaClient,
+        pending_scripts: &mut Vec<Receiver<Result<String, ScriptError>>>,
         ctx: &egui::Context,
         ui: &mut egui::Ui,
         component: &mut UIComponent,
@@ -161,7 +291,7 @@ impl MyApp {
             UIComponent::Button { label, handler } => {
                 if ui.button(label.clone()).clicked() {
                     if let Err(err) = handler.call::<()>(()) {
-                        eprintln!("Error calling Lua handler: {}", err);
+                        error_log.report("Button handler", err);
                     }
                 }
             }
@@ -171,24 +301,23 @@ impl MyApp {
                 if response.changed() {
                     // Send the new value back to Lua
                     if let Err(err) = handler.call::<String>(value) {
-                        eprintln!("Error updating TextEdit value: {}", err);
+                        error_log.report("TextEdit handler", err);
                     }
                 }
             }
-            UIComponent::Label { handler } => {
-                if let Ok(value) = handler.call::<String>(()) {
+            UIComponent::Label { handler } => match handler.call::<String>(()) {
+                Ok(value) => {
                     ui.label(&value);
-                } else {
-                    eprintln!("Error fetching Label value.");
                 }
-            }
+                Err(err) => error_log.report("Label handler", err),
+            },
             UIComponent::Slider { label, handler } => {
                 let mut value = handler.call::<f64>(()).unwrap_or_default();
                 let response = ui.add(egui::Slider::new(&mut value, 0.0..=100.0));
                 if response.changed() {
                     // Send the new value back to Lua
                     if let Err(err) = handler.call::<String>(value) {
-                        eprintln!("Error updating Slider value: {}", err);
+                        error_log.report("Slider handler", err);
                     }
                 }
             }
@@ -199,10 +328,22 @@ impl MyApp {
                     }
                 });
             }
+            UIComponent::MetricPlot {
+                label,
+                metric,
+                metrics,
+            } => metric_plot(ui, label, metrics, metric),
             UIComponent::Window { label, children } => {
                 Window::new(label.clone()).show(ctx, |ui| {
                     for child in children {
-                        Self::render_component(lua_engine, ctx, ui, child);
+                        Self::render_component(
+                            error_log,
+                            lua_client,
+                            pending_scripts,
+                            ctx,
+                            ui,
+                            child,
+                        );
                     }
                 });
             }
@@ -220,13 +361,396 @@ impl MyApp {
                     && ctx.input(|i| i.key_pressed(egui::Key::Enter) && i.modifiers.ctrl))
                     || ui.button("Run (Ctrl + Enter)").clicked()
                 {
-                    if let Err(e) = lua_engine.run_script(&script) {
-                        eprintln!("Error running Lua script: {}", e);
+                    pending_scripts.push(lua_client.execute_non_blocking(script));
+                }
+            }
+        }
+    }
+
+    // Whether a binding of the action was pressed, while no text field takes the keys
+    fn action_pressed(&self, ctx: &egui::Context, action: Action) -> bool {
+        if ctx.wants_keyboard_input() {
+            return false;
+        }
+        self.bindings.chords(action).iter().any(|chord| {
+            egui_key(&chord.key).is_some_and(|key| {
+                ctx.input(|i| {
+                    i.key_pressed(key)
+                        && i.modifiers.ctrl == chord.ctrl
+                        && i.modifiers.alt == chord.alt
+                        && i.modifiers.shift == chord.shift
+                })
+            })
+        })
+    }
+
+    // Badge with the number of background script errors, expanding into the list of errors
+    fn render_errors(&mut self, ctx: &egui::Context) {
+        if self.error_log.is_empty() {
+            self.show_errors = false;
+            return;
+        }
+
+        egui::Area::new(egui::Id::new("script_error_badge"))
+            .anchor(egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+            .show(ctx, |ui| {
+                let text = egui::RichText::new(format!("{} script errors", self.error_log.len()))
+                    .color(egui::Color32::WHITE);
+                let badge = egui::Button::new(text).fill(egui::Color32::DARK_RED);
+                if ui.add(badge).clicked() {
+                    self.show_errors = !self.show_errors;
+                }
+            });
+
+        let mut open = self.show_errors;
+        Window::new("Script errors")
+            .open(&mut open)
+            .anchor(egui::Align2::RIGHT_TOP, [-10.0, 40.0])
+            .show(ctx, |ui| {
+                if ui.button("Clear").clicked() {
+                    self.error_log.clear();
+                }
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    // Newest first
+                    for logged in self.error_log.errors().iter().rev() {
+                        ui.separator();
+                        let repeated = if logged.count > 1 {
+                            format!(" (x{})", logged.count)
+                        } else {
+                            String::new()
+                        };
+                        ui.colored_label(
+                            egui::Color32::ORANGE,
+                            format!("{}{}", logged.context, repeated),
+                        );
+                        if let Some(location) = logged.error.location() {
+                            ui.weak(format!("at {}", location));
+                        }
+                        ui.label(&logged.error.message);
+                    }
+                });
+            });
+        self.show_errors = open;
+    }
+
+    // Result of the scenario once it ended, until the window is closed
+    fn render_result(&mut self, ctx: &egui::Context) {
+        let Some(result) = self.scenario.result() else {
+            return;
+        };
+        let mut open = self.show_result;
+        Window::new(format!("Scenario {}", result.outcome))
+            .open(&mut open)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.heading(&result.reason);
+                ui.label(format!("{} ticks, {:.1}s", result.ticks, result.duration));
+                ui.label(format!(
+                    "{} events, {} persons on {} locations",
+                    result.events, result.persons, result.occupied_locations
+                ));
+                ui.separator();
+                for (metric, value) in &result.metrics {
+                    ui.monospace(format!("{} = {}", metric, value));
+                }
+            });
+        self.show_result = open;
+    }
+
+    // Progress of the goals, once the mods defined some
+    fn render_goals(&self, ctx: &egui::Context) {
+        let goals = self.goals.all();
+        if goals.is_empty() {
+            return;
+        }
+        Window::new("Goals")
+            .anchor(egui::Align2::LEFT_TOP, [10.0, 10.0])
+            .show(ctx, |ui| {
+                for goal in &goals {
+                    let fraction = if goal.target > 0.0 {
+                        (goal.progress / goal.target).clamp(0.0, 1.0)
+                    } else {
+                        1.0
+                    };
+                    let name = if goal.completed {
+                        format!("{} (done)", goal.name)
+                    } else {
+                        goal.name.clone()
+                    };
+                    ui.label(name);
+                    ui.add(
+                        egui::ProgressBar::new(fraction as f32)
+                            .text(format!("{}/{}", goal.progress, goal.target)),
+                    );
+                }
+            });
+    }
+
+    // Toasts in the bottom right corner, newest at the bottom, and the event log with the mute
+    // toggles of the categories
+    fn render_notifications(&mut self, ctx: &egui::Context) {
+        let toasts = self.notifications.toasts();
+        if !toasts.is_empty() {
+            egui::Area::new(egui::Id::new("notification_toasts"))
+                .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -40.0])
+                .show(ctx, |ui| {
+                    for toast in &toasts {
+                        egui::Frame::popup(ui.style()).show(ui, |ui| {
+                            ui.weak(&toast.notification.category);
+                            ui.label(&toast.notification.text);
+                        });
+                    }
+                });
+            // Keep repainting until they are gone
+            ctx.request_repaint();
+        }
+
+        let mut open = self.show_log;
+        Window::new("Event log").open(&mut open).show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for (category, muted) in self.notifications.categories() {
+                    let mut shown = !muted;
+                    if ui.checkbox(&mut shown, &category).changed() {
+                        self.notifications.set_muted(&category, !shown);
+                    }
+                }
+            });
+            ui.separator();
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                // Newest first
+                for notification in self.notifications.log().iter().rev() {
+                    ui.label(format!(
+                        "{} [{}] {}",
+                        notification.tick, notification.category, notification.text
+                    ));
+                }
+            });
+        });
+        self.show_log = open;
+    }
+
+    // Plots of the world stats, read straight from the metrics without going through Lua
+    fn render_stats(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_stats;
+        Window::new("World stats").open(&mut open).show(ctx, |ui| {
+            for (title, metric) in STATS_PLOTS {
+                ui.label(title);
+                metric_plot(ui, title, &self.metrics, metric);
+            }
+        });
+        self.show_stats = open;
+    }
+
+    // The modules of the scripting API with their methods, or the methods matching the search,
+    // and the page of the one picked with a button putting a call of it into the Lua console
+    fn render_docs(&mut self, ctx: &egui::Context) {
+        let Some(docs) = &self.docs else {
+            return;
+        };
+        let mut open = true;
+        let mut example = None;
+        Window::new("API docs")
+            .open(&mut open)
+            .default_size([800.0, 500.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Search");
+                    ui.text_edit_singleline(&mut self.docs_query);
+                });
+                ui.separator();
+                ui.columns(2, |columns| {
+                    egui::ScrollArea::vertical()
+                        .id_salt("docs_tree")
+                        .show(&mut columns[0], |ui| {
+                            let mut pick = |ui: &mut egui::Ui, method: &MethodPage, label: String| {
+                                let key = (method.module.clone(), method.name.clone());
+                                let selected = self.docs_selected.as_ref() == Some(&key);
+                                if ui.selectable_label(selected, label).clicked() {
+                                    self.docs_selected = Some(key);
+                                }
+                            };
+                            if self.docs_query.is_empty() {
+                                for module in &docs.modules {
+                                    egui::CollapsingHeader::new(&module.name).show(ui, |ui| {
+                                        for method in &module.methods {
+                                            pick(ui, method, method.name.clone());
+                                        }
+                                    });
+                                }
+                            } else {
+                                let found = docs.search(&self.docs_query);
+                                if found.is_empty() {
+                                    ui.label("Nothing matches");
+                                }
+                                for method in found {
+                                    pick(ui, method, format!("{}.{}", method.module, method.name));
+                                }
+                            }
+                        });
+                    let page = self.docs_selected.as_ref().and_then(|(module, name)| {
+                        docs.modules
+                            .iter()
+                            .find(|page| &page.name == module)
+                            .and_then(|page| page.methods.iter().find(|method| &method.name == name))
+                    });
+                    let ui = &mut columns[1];
+                    let Some(page) = page else {
+                        ui.label("Pick a method to see its page");
+                        return;
+                    };
+                    ui.monospace(page.signature());
+                    ui.separator();
+                    ui.label(page.description.replace('\n', " "));
+                    if !page.params.is_empty() {
+                        ui.strong("Parameters");
+                        for param in &page.params {
+                            ui.monospace(format!("{}: {}", param.name, param.type_name));
+                        }
+                    }
+                    if !page.returns.is_empty() {
+                        ui.strong("Returns");
+                        ui.monospace(&page.returns);
+                    }
+                    if let Some(call) = &page.example {
+                        ui.strong("Example");
+                        ui.code(call);
+                        if ui.button("Insert into console").clicked() {
+                            example = Some(call.clone());
+                        }
+                    }
+                });
+            });
+        if !open {
+            self.docs = None;
+        }
+        if let Some(example) = example {
+            let mut components = self.components.write().unwrap();
+            match console_script(&mut components) {
+                Some(script) => {
+                    if !script.is_empty() && !script.ends_with('\n') {
+                        script.push('\n');
                     }
+                    script.push_str(&example);
                 }
+                None => components.push(UIComponent::LuaConsole { script: example }),
             }
         }
     }
+
+    // Read the docs when the window opens, drop them when it closes
+    fn toggle_docs(&mut self) {
+        if self.docs.is_some() {
+            self.docs = None;
+            return;
+        }
+        match ApiReference::read(&self.lua) {
+            Ok(docs) => self.docs = Some(docs),
+            Err(e) => self.error_log.report("API docs", e),
+        }
+    }
+
+    // A control for every setting the mods declared, generated from their declarations
+    fn render_mod_settings(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_mod_settings;
+        Window::new("Mod settings").open(&mut open).show(ctx, |ui| {
+            let mods = self.settings.mods();
+            if mods.is_empty() {
+                ui.label("No mod declares settings");
+            }
+            for (mod_name, settings) in mods {
+                egui::CollapsingHeader::new(&mod_name)
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        for setting in settings {
+                            let mut value = setting.value.clone();
+                            let changed = match (&setting.kind, &mut value) {
+                                (SettingKind::Boolean, SettingValue::Boolean(on)) => {
+                                    ui.checkbox(on, &setting.name).changed()
+                                }
+                                (
+                                    SettingKind::Number { min, max, step },
+                                    SettingValue::Number(n),
+                                ) => {
+                                    ui.horizontal(|ui| {
+                                        let response = if min.is_finite() && max.is_finite() {
+                                            let mut slider = egui::Slider::new(n, *min..=*max);
+                                            if let Some(step) = step {
+                                                slider = slider.step_by(*step);
+                                            }
+                                            ui.add(slider)
+                                        } else {
+                                            ui.add(egui::DragValue::new(n).range(*min..=*max))
+                                        };
+                                        ui.label(&setting.name);
+                                        response.changed()
+                                    })
+                                    .inner
+                                }
+                                (SettingKind::Choice(options), SettingValue::Text(selected)) => {
+                                    let before = selected.clone();
+                                    egui::ComboBox::from_label(&setting.name)
+                                        .selected_text(selected.as_str())
+                                        .show_ui(ui, |ui| {
+                                            for option in options {
+                                                ui.selectable_value(
+                                                    selected,
+                                                    option.clone(),
+                                                    option,
+                                                );
+                                            }
+                                        });
+                                    *selected != before
+                                }
+                                (_, SettingValue::Text(text)) => {
+                                    ui.horizontal(|ui| {
+                                        let changed = ui.text_edit_singleline(text).changed();
+                                        ui.label(&setting.name);
+                                        changed
+                                    })
+                                    .inner
+                                }
+                                _ => false,
+                            };
+                            if changed
+                                && let Err(e) = self.settings.set(&mod_name, &setting.key, value)
+                            {
+                                eprintln!("{}", e);
+                            }
+                        }
+                        if ui.button("Reset to defaults").clicked()
+                            && let Err(e) = self.settings.reset(&mod_name)
+                        {
+                            eprintln!("{}", e);
+                        }
+                    });
+            }
+        });
+        self.show_mod_settings = open;
+    }
+
+    fn render_debugger(debugger: &Debugger, frame: &PausedFrame, ctx: &egui::Context) {
+        Window::new(format!("Paused at {}:{}", frame.file, frame.line)).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Step").clicked() {
+                    debugger.step();
+                }
+                if ui.button("Continue").clicked() {
+                    debugger.resume();
+                }
+            });
+            ui.separator();
+            ui.label("Locals");
+            for (name, value) in &frame.locals {
+                ui.monospace(format!("{} = {}", name, value));
+            }
+            ui.label("Upvalues");
+            for (name, value) in &frame.upvalues {
+                ui.monospace(format!("{} = {}", name, value));
+            }
+        });
+        // Keep polling until the script continues
+        ctx.request_repaint();
+    }
 }
 
 impl eframe::App for MyApp {
@@ -238,12 +762,107 @@ impl eframe::App for MyApp {
             components.append(&mut new_components);
             new_components.clear()
         }
+        self.pending_scripts
+            .retain(|receiver| match receiver.try_recv() {
+                Ok(Err(e)) => {
+                    self.error_log.report("Lua console", e);
+                    false
+                }
+                Ok(Ok(_)) | Err(TryRecvError::Disconnected) => false,
+                Err(TryRecvError::Empty) => true,
+            });
+
+        self.render_errors(ctx);
+        self.render_result(ctx);
+        self.render_goals(ctx);
+        self.render_notifications(ctx);
+        self.render_mod_settings(ctx);
+        self.render_stats(ctx);
+
+        // Handlers would block on the Lua state while a script is paused
+        if let Some(frame) = self.debugger.paused() {
+            Self::render_debugger(&self.debugger, &frame, ctx);
+            return;
+        }
+        self.render_docs(ctx);
+        self.ticker
+            .update(&self.lua_client, ctx.input(|i| i.stable_dt));
+        // egui only repaints on input, keep the frames coming while scripts wait for them
+        if self.hooks.has(Hook::Frame) || !self.timers.is_empty() {
+            ctx.request_repaint();
+        }
+        if self.action_pressed(ctx, Action::ToggleNotifications) {
+            self.show_log = !self.show_log;
+        }
+        if self.action_pressed(ctx, Action::ToggleModSettings) {
+            self.show_mod_settings = !self.show_mod_settings;
+        }
+        if self.action_pressed(ctx, Action::ApiDocs) {
+            self.toggle_docs();
+        }
+        if self.clock.mode() == TimeMode::Turns && self.action_pressed(ctx, Action::EndTurn) {
+            self.clock.end_turn();
+        }
+        egui::TopBottomPanel::bottom("world").show(ctx, |ui| {
+            let snapshot = self.snapshots.latest();
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "Persons: {}, tick {}",
+                    snapshot.persons().len(),
+                    snapshot.tick()
+                ));
+                ui.toggle_value(&mut self.show_log, "Event log");
+                ui.toggle_value(&mut self.show_mod_settings, "Mod settings");
+                ui.toggle_value(&mut self.show_stats, "World stats");
+                if ui.selectable_label(self.docs.is_some(), "API docs").clicked() {
+                    self.toggle_docs();
+                }
+                if self.clock.mode() == TimeMode::Turns {
+                    ui.separator();
+                    ui.label(format!("Turn {}", self.clock.turn()));
+                    if ui.button("End turn").clicked() {
+                        self.clock.end_turn();
+                    }
+                }
+            });
+        });
         egui::CentralPanel::default().show(ctx, |ui| {
             let mut components = self.components.write().unwrap();
-            let lua_engine = self.lua_engine.write().unwrap();
             for component in components.iter_mut() {
-                Self::render_component(&lua_engine, ctx, ui, component);
+                Self::render_component(
+                    &self.error_log,
+                    &self.lua_client,
+                    &mut self.pending_scripts,
+                    ctx,
+                    ui,
+                    component,
+                );
             }
         });
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // A script paused in the debugger would block the hooks forever
+        if !self.debugger.is_paused() {
+            self.hooks.call(Hook::Shutdown, ());
+        }
+    }
+}
+
+// Key of a binding, bindings name keys like the pixel UI does and egui names a few differently
+fn egui_key(name: &str) -> Option<egui::Key> {
+    let name = match name {
+        "graveaccent" => "backtick",
+        "leftbracket" => "openbracket",
+        "rightbracket" => "closebracket",
+        "equal" => "equals",
+        name => name
+            .strip_prefix("key")
+            .filter(|digit| digit.len() == 1)
+            .unwrap_or(name),
+    };
+    egui::Key::ALL
+        .iter()
+        .copied()
+        .find(|key| key.name().eq_ignore_ascii_case(name))
 }